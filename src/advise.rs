@@ -0,0 +1,125 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+/// An expected pattern of access to a file, passed to [`DirView::advise`]
+/// and [`ReadOnlyFile::advise`].
+///
+/// [`ReadOnlyFile::advise`]: crate::ReadOnlyFile::advise
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Advice {
+    /// No particular pattern, which is the default.
+    Normal,
+
+    /// The data will be read in order, so read ahead more aggressively.
+    Sequential,
+
+    /// The data will be read in no particular order, so don't read ahead.
+    Random,
+
+    /// The data will be read soon, so start reading it into the page cache.
+    WillNeed,
+
+    /// The data won't be read again soon, so drop it from the page cache.
+    DontNeed,
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Declares how the `len` bytes of the file at `path` starting at
+    /// `offset` will be accessed, or the rest of the file if `len` is 0.
+    ///
+    /// This corresponds to `posix_fadvise`, and is permitted for all kinds
+    /// of views, as it only affects caching. [`Advice::WillNeed`] and
+    /// [`Advice::DontNeed`] apply to the file's data wherever it's read
+    /// from, so that, for example, a large scan can drop what it read from
+    /// the page cache. [`Advice::Sequential`] and [`Advice::Random`] only
+    /// apply to reads through the same handle, so for them use
+    /// [`ReadOnlyFile::advise`] on an open file instead. Advice is ignored
+    /// on platforms without `posix_fadvise`.
+    ///
+    /// [`ReadOnlyFile::advise`]: crate::ReadOnlyFile::advise
+    pub fn advise<P: AsRef<Path>>(
+        &self,
+        path: P,
+        offset: u64,
+        len: u64,
+        advice: Advice,
+    ) -> io::Result<()> {
+        self.audited(Operation::Advise, path, |path| {
+            self.check_resolve(Operation::Advise, path)?;
+            advise(&self.dir.borrow().open(path)?, offset, len, advice)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Declares how the `len` bytes of the file at `path` starting at
+    /// `offset` will be accessed, or the rest of the file if `len` is 0.
+    ///
+    /// See [`DirView::advise`].
+    pub fn advise<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        offset: u64,
+        len: u64,
+        advice: Advice,
+    ) -> io::Result<()> {
+        self.audited(Operation::Advise, path, |path| {
+            self.check_resolve(Operation::Advise, path)?;
+            let file = self.dir.borrow().as_cap_std().open(path.as_std_path())?;
+            advise(&file, offset, len, advice)
+        })
+    }
+}
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+        target_os = "dragonfly",
+        target_os = "haiku",
+        target_os = "redox",
+        target_os = "espidf",
+        target_os = "vita",
+    ))
+))]
+pub(crate) fn advise(file: &File, offset: u64, len: u64, advice: Advice) -> io::Result<()> {
+    use rustix::fs::fadvise;
+
+    let advice = match advice {
+        Advice::Normal => rustix::fs::Advice::Normal,
+        Advice::Sequential => rustix::fs::Advice::Sequential,
+        Advice::Random => rustix::fs::Advice::Random,
+        Advice::WillNeed => rustix::fs::Advice::WillNeed,
+        Advice::DontNeed => rustix::fs::Advice::DontNeed,
+    };
+    Ok(fadvise(file, offset, len, advice)?)
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+        target_os = "dragonfly",
+        target_os = "haiku",
+        target_os = "redox",
+        target_os = "espidf",
+        target_os = "vita",
+    ))
+)))]
+pub(crate) fn advise(_file: &File, _offset: u64, _len: u64, _advice: Advice) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,142 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Allocates space on disk for the `len` bytes of the file at `path`
+    /// starting at `offset`, extending the file if they're past its end, so
+    /// that later writes to them don't fail for lack of space.
+    ///
+    /// This fails with [`io::ErrorKind::StorageFull`] if the filesystem
+    /// doesn't have room, which lets downloaders fail before fetching
+    /// anything. On Unix this corresponds to `posix_fallocate`, on macOS to
+    /// `F_PREALLOCATE`, and on Windows to setting the file's allocation
+    /// size. It fails with [`io::ErrorKind::Unsupported`] on platforms and
+    /// filesystems which can't allocate space.
+    pub fn allocate<P: AsRef<Path>>(&self, path: P, offset: u64, len: u64) -> io::Result<()> {
+        self.audited(Operation::Allocate, path, |path| {
+            self.check_resolve(Operation::Allocate, path)?;
+            if self.dry_run(Operation::Allocate, path, None)? {
+                return Ok(());
+            }
+            let file = self
+                .dir
+                .borrow()
+                .open_with(path, OpenOptions::new().write(true))?;
+            allocate(&file, offset, len)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Allocates space on disk for the `len` bytes of the file at `path`
+    /// starting at `offset`, extending the file if they're past its end.
+    ///
+    /// See [`DirView::allocate`].
+    pub fn allocate<P: AsRef<Utf8Path>>(&self, path: P, offset: u64, len: u64) -> io::Result<()> {
+        self.audited(Operation::Allocate, path, |path| {
+            self.check_resolve(Operation::Allocate, path)?;
+            if self.dry_run(Operation::Allocate, path, None)? {
+                return Ok(());
+            }
+            let file = self
+                .dir
+                .borrow()
+                .as_cap_std()
+                .open_with(path.as_std_path(), OpenOptions::new().write(true))?;
+            allocate(&file, offset, len)
+        })
+    }
+}
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "redox",
+        target_os = "nto",
+        target_os = "espidf",
+        target_os = "vita",
+    ))
+))]
+fn allocate(file: &File, offset: u64, len: u64) -> io::Result<()> {
+    use rustix::fs::{fallocate, FallocateFlags};
+    use rustix::io::Errno;
+
+    match fallocate(file, FallocateFlags::empty(), offset, len) {
+        Ok(()) => Ok(()),
+        Err(Errno::OPNOTSUPP) => Err(unsupported()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(windows)]
+fn allocate(file: &File, offset: u64, len: u64) -> io::Result<()> {
+    use std::mem::size_of;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FileAllocationInfo, SetFileInformationByHandle, FILE_ALLOCATION_INFO,
+    };
+
+    let end = offset
+        .checked_add(len)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "allocation too large"))?;
+    let info = FILE_ALLOCATION_INFO {
+        AllocationSize: i64::try_from(end)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "allocation too large"))?,
+    };
+    // SAFETY: `info` is a `FILE_ALLOCATION_INFO` of the given size.
+    let ok = unsafe {
+        SetFileInformationByHandle(
+            file.as_raw_handle(),
+            FileAllocationInfo,
+            (&info as *const FILE_ALLOCATION_INFO).cast(),
+            size_of::<FILE_ALLOCATION_INFO>() as u32,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Setting the allocation size doesn't extend the file, which
+    // `posix_fallocate` does.
+    if file.metadata()?.len() < end {
+        file.set_len(end)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(
+    windows,
+    all(
+        unix,
+        not(any(
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "dragonfly",
+            target_os = "redox",
+            target_os = "nto",
+            target_os = "espidf",
+            target_os = "vita",
+        ))
+    )
+)))]
+fn allocate(_file: &File, _offset: u64, _len: u64) -> io::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(windows))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "allocating space isn't supported on this platform or filesystem",
+    )
+}
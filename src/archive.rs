@@ -0,0 +1,926 @@
+use crate::read_range::read_at;
+use crate::zip_view::InflateEntry;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, FileTypeSummary, Operation, ViewError};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, vec};
+
+/// The size of a tar block.
+const BLOCK: u64 = 512;
+
+/// The most symbolic links followed in resolving a path, as on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// The largest PAX extended header or GNU long name accepted.
+const MAX_EXTENDED: u64 = 1 << 20;
+
+/// A readonly view of the contents of an archive, presented as a directory.
+///
+/// This has the read side of the [`DirView`] method surface, so code which
+/// reads trees through a view can consume packaged ones the same way. The
+/// archive is indexed once, when the view is created, and files are read
+/// from it as they're opened, without extracting anything.
+///
+/// Paths are resolved as in a view: symbolic links within the archive are
+/// followed, but paths which would lead outside of it, through `..` or an
+/// absolute link, are denied. Entries whose names lead outside of it are
+/// left out.
+///
+/// This is constructed from a tar archive by [`Self::from_file`] or
+/// [`DirView::open_tar`], and from a zip archive by [`ZipDirView`].
+///
+/// [`ZipDirView`]: crate::ZipDirView
+#[derive(Clone)]
+pub struct ArchiveDirView {
+    index: Arc<Index>,
+
+    /// The path of this directory within the archive.
+    root: PathBuf,
+}
+
+struct Index {
+    file: File,
+    entries: BTreeMap<PathBuf, Entry>,
+
+    /// The entry for the root of the archive, which needn't be in it.
+    root: Entry,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    pub(crate) kind: FileTypeSummary,
+
+    /// The offset of the entry's data in the archive.
+    pub(crate) offset: u64,
+
+    /// The size of the entry's contents, once decompressed.
+    pub(crate) len: u64,
+    pub(crate) mode: u32,
+    pub(crate) mtime: u64,
+    pub(crate) link: PathBuf,
+    pub(crate) compression: Compression,
+}
+
+/// How the data of an entry is stored in an archive.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Compression {
+    Stored,
+
+    /// Deflated, as in zip archives, into `compressed` bytes, from contents
+    /// with the CRC-32 `crc`.
+    Deflated {
+        compressed: u64,
+        crc: u32,
+    },
+
+    /// Compressed or encrypted in some way which isn't supported.
+    Unsupported,
+}
+
+impl Entry {
+    /// Returns a directory entry for a directory which is implied by the
+    /// names of other entries, but not in the archive itself.
+    pub(crate) fn implied_dir() -> Self {
+        Self {
+            kind: FileTypeSummary::Dir,
+            offset: 0,
+            len: 0,
+            mode: 0o755,
+            mtime: 0,
+            link: PathBuf::new(),
+            compression: Compression::Stored,
+        }
+    }
+
+    fn metadata(&self) -> ArchiveMetadata {
+        ArchiveMetadata {
+            kind: self.kind,
+            len: self.len,
+            mode: self.mode,
+            mtime: self.mtime,
+        }
+    }
+}
+
+impl ArchiveDirView {
+    /// Constructs a view of the tar archive in `file`.
+    ///
+    /// This reads every header in the archive, failing if any is malformed.
+    /// Archives may be in the ustar, PAX, or GNU formats, but not compressed.
+    pub fn from_file(file: File) -> io::Result<Self> {
+        let entries = read_index(&file)?;
+        Ok(Self::from_entries(file, entries))
+    }
+
+    /// Constructs a view of the archive in `file`, given its entries by
+    /// path.
+    pub(crate) fn from_entries(file: File, entries: BTreeMap<PathBuf, Entry>) -> Self {
+        Self {
+            index: Arc::new(Index {
+                file,
+                entries,
+                root: Entry::implied_dir(),
+            }),
+            root: PathBuf::new(),
+        }
+    }
+
+    /// Attempts to open a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveFile> {
+        let path = path.as_ref();
+        let (_, entry) = self.lookup(Operation::Open, path, true)?;
+        match entry.kind {
+            FileTypeSummary::File => Ok(ArchiveFile {
+                index: Arc::clone(&self.index),
+                contents: match entry.compression {
+                    Compression::Stored => Contents::Archive(entry.offset),
+                    Compression::Deflated { compressed, crc } => {
+                        if entry.offset.saturating_add(compressed)
+                            > self.index.file.metadata()?.len()
+                        {
+                            return Err(truncated());
+                        }
+                        let deflated = Deflated {
+                            offset: entry.offset,
+                            compressed,
+                            crc,
+                        };
+                        let inflate = deflated.inflate(&self.index, entry.len);
+                        Contents::Deflated(deflated, Box::new(inflate))
+                    }
+                    Compression::Unsupported => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Unsupported,
+                            "archive entry is compressed or encrypted in an unsupported way",
+                        ))
+                    }
+                },
+                len: entry.len,
+                pos: 0,
+                metadata: entry.metadata(),
+            }),
+            FileTypeSummary::Dir => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "attempted to open a directory in an archive as a file",
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "attempted to open a special file in an archive",
+            )),
+        }
+    }
+
+    /// Attempts to open a directory, as a view of the same archive.
+    pub fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Self> {
+        let (root, _) = self.lookup_dir(Operation::OpenDir, path.as_ref())?;
+        Ok(Self {
+            index: Arc::clone(&self.index),
+            root,
+        })
+    }
+
+    /// Read the entire contents of a file into a bytes vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let mut file = self.open(path)?;
+        let mut contents = Vec::with_capacity(file.len as usize);
+        file.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Read the entire contents of a file into a string.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let (_, entry) = self.lookup(Operation::ReadLink, path.as_ref(), false)?;
+        if entry.kind != FileTypeSummary::Symlink {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a symbolic link",
+            ));
+        }
+        Ok(entry.link.clone())
+    }
+
+    /// Given a path, query the archive to get information about a file,
+    /// directory, etc.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveMetadata> {
+        let (_, entry) = self.lookup(Operation::Metadata, path.as_ref(), true)?;
+        Ok(entry.metadata())
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    pub fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveMetadata> {
+        let (_, entry) = self.lookup(Operation::Metadata, path.as_ref(), false)?;
+        Ok(entry.metadata())
+    }
+
+    /// Queries metadata about this directory.
+    pub fn dir_metadata(&self) -> io::Result<ArchiveMetadata> {
+        self.metadata(".")
+    }
+
+    /// Returns an iterator over the entries within `self`.
+    pub fn entries(&self) -> io::Result<ArchiveReadDir> {
+        self.read_dir(".")
+    }
+
+    /// Returns an iterator over the entries within a directory, in order of
+    /// their names.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveReadDir> {
+        let (dir, _) = self.lookup_dir(Operation::ReadDir, path.as_ref())?;
+        let entries = self
+            .index
+            .entries
+            .range(dir.clone()..)
+            .skip_while(|(path, _)| **path == dir)
+            .take_while(|(path, _)| path.starts_with(&dir))
+            .filter(|(path, _)| path.parent() == Some(&dir))
+            .map(|(path, entry)| ArchiveDirEntry {
+                name: path.file_name().unwrap().to_os_string(),
+                metadata: entry.metadata(),
+            })
+            .collect::<Vec<_>>();
+        Ok(ArchiveReadDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Returns `true` if the path points at an existing entity, or an error
+    /// if its existence can't be determined.
+    pub fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        match self.metadata(path) {
+            Ok(_) => Ok(true),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns `true` if the path points at a regular file.
+    #[inline]
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_file())
+    }
+
+    /// Checks if `path` is a directory.
+    #[inline]
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    fn lookup_dir(&self, operation: Operation, path: &Path) -> io::Result<(PathBuf, &Entry)> {
+        let (path, entry) = self.lookup(operation, path, true)?;
+        if entry.kind != FileTypeSummary::Dir {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "not a directory",
+            ));
+        }
+        Ok((path, entry))
+    }
+
+    /// Resolves `path` to the path of an entry in the archive, and the entry,
+    /// following symbolic links in every component but the last, and in the
+    /// last if `follow`.
+    fn lookup(
+        &self,
+        operation: Operation,
+        path: &Path,
+        follow: bool,
+    ) -> io::Result<(PathBuf, &Entry)> {
+        let escape = || -> io::Error {
+            ViewError::denied(
+                io::ErrorKind::PermissionDenied,
+                "a path led outside of the archive",
+                operation,
+                path,
+                false,
+            )
+            .into()
+        };
+        let get = |resolved: &Path| {
+            if resolved.as_os_str().is_empty() {
+                Some(&self.index.root)
+            } else {
+                self.index.entries.get(resolved)
+            }
+        };
+
+        let mut resolved = self.root.clone();
+        let mut entry = get(&resolved);
+        // Components still to be resolved, in reverse order.
+        let mut pending = Vec::new();
+        push_components(&mut pending, path).map_err(|()| escape())?;
+        let mut symlinks = 0;
+        while let Some(component) = pending.pop() {
+            if component.as_os_str() == ".." {
+                if resolved == self.root {
+                    return Err(escape());
+                }
+                resolved.pop();
+                entry = get(&resolved);
+                continue;
+            }
+            match entry {
+                Some(entry) if entry.kind == FileTypeSummary::Dir => {}
+                Some(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotADirectory,
+                        "not a directory",
+                    ))
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no such entry in the archive",
+                    ))
+                }
+            }
+            resolved.push(&component);
+            entry = self.index.entries.get(&resolved);
+            if let Some(link) = entry.filter(|entry| entry.kind == FileTypeSummary::Symlink) {
+                if !pending.is_empty() || follow {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "too many levels of symbolic links",
+                        ));
+                    }
+                    resolved.pop();
+                    entry = get(&resolved);
+                    push_components(&mut pending, &link.link).map_err(|()| escape())?;
+                }
+            }
+        }
+        match entry {
+            Some(entry) => Ok((resolved, entry)),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no such entry in the archive",
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for ArchiveDirView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveDirView")
+            .field("file", &self.index.file)
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+/// Pushes the components of `path` onto `pending`, in reverse order, or
+/// fails if it's absolute.
+fn push_components(pending: &mut Vec<PathBuf>, path: &Path) -> Result<(), ()> {
+    for component in path.components().rev() {
+        match component {
+            Component::Normal(name) => pending.push(PathBuf::from(name)),
+            Component::ParentDir => pending.push(PathBuf::from("..")),
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return Err(()),
+        }
+    }
+    Ok(())
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Opens the tar archive at `path` as an [`ArchiveDirView`], a readonly
+    /// view of its contents.
+    ///
+    /// The archive is read through this view, but entries within it aren't
+    /// subject to this view's policy.
+    pub fn open_tar<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveDirView> {
+        ArchiveDirView::from_file(self.open(path)?)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Opens the tar archive at `path` as an [`ArchiveDirView`], a readonly
+    /// view of its contents.
+    ///
+    /// See [`DirView::open_tar`].
+    pub fn open_tar<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<ArchiveDirView> {
+        ArchiveDirView::from_file(File::from_std(self.open(path)?.into_std()))
+    }
+}
+
+/// Metadata about an entry in an [`ArchiveDirView`].
+#[derive(Clone, Debug)]
+pub struct ArchiveMetadata {
+    kind: FileTypeSummary,
+    len: u64,
+    mode: u32,
+    mtime: u64,
+}
+
+impl ArchiveMetadata {
+    /// Returns the type of the entry.
+    #[inline]
+    pub fn file_type(&self) -> FileTypeSummary {
+        self.kind
+    }
+
+    /// Returns `true` if this is a directory.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileTypeSummary::Dir
+    }
+
+    /// Returns `true` if this is a regular file.
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        self.kind == FileTypeSummary::File
+    }
+
+    /// Returns `true` if this is a symbolic link.
+    #[inline]
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileTypeSummary::Symlink
+    }
+
+    /// Returns the size of the file in bytes.
+    #[allow(clippy::len_without_is_empty)]
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the permission bits of the entry's mode, as recorded in the
+    /// archive.
+    #[inline]
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Returns the last modification time recorded in the archive.
+    #[inline]
+    pub fn modified(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.mtime)
+    }
+}
+
+/// A file opened from an [`ArchiveDirView`].
+///
+/// Files are read from the archive as they're read, and compressed files are
+/// decompressed as they're read, and checked once they're read to the end.
+/// Either can be read from any position, though seeking backwards in a
+/// compressed file decompresses it again from the start.
+pub struct ArchiveFile {
+    index: Arc<Index>,
+    contents: Contents,
+    len: u64,
+    pos: u64,
+    metadata: ArchiveMetadata,
+}
+
+enum Contents {
+    /// The contents are in the archive, at this offset.
+    Archive(u64),
+
+    /// The contents are deflated in the archive, and are being decompressed.
+    Deflated(Deflated, Box<InflateEntry<ArchiveInput>>),
+}
+
+/// Where deflated contents are in the archive.
+struct Deflated {
+    offset: u64,
+    compressed: u64,
+    crc: u32,
+}
+
+impl Deflated {
+    /// Starts decompressing the contents, which are `len` bytes long, from
+    /// the start.
+    fn inflate(&self, index: &Arc<Index>, len: u64) -> InflateEntry<ArchiveInput> {
+        let input = ArchiveInput {
+            index: Arc::clone(index),
+            offset: self.offset,
+            left: self.compressed,
+        };
+        InflateEntry::new(input, len, self.crc)
+    }
+}
+
+/// A reader of a range of the archive.
+struct ArchiveInput {
+    index: Arc<Index>,
+    offset: u64,
+    left: u64,
+}
+
+impl Read for ArchiveInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let read = read_at(&self.index.file, &mut buf[..len], self.offset)?;
+        if read == 0 {
+            return Err(truncated());
+        }
+        self.offset += read as u64;
+        self.left -= read as u64;
+        Ok(read)
+    }
+}
+
+impl ArchiveFile {
+    /// Queries metadata about the file.
+    #[inline]
+    pub fn metadata(&self) -> ArchiveMetadata {
+        self.metadata.clone()
+    }
+}
+
+impl Read for ArchiveFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let read = match &mut self.contents {
+            Contents::Archive(offset) => {
+                let read = read_at(&self.index.file, &mut buf[..len], *offset + self.pos)?;
+                if read == 0 {
+                    return Err(truncated());
+                }
+                read
+            }
+            Contents::Deflated(deflated, inflate) => {
+                if self.pos < inflate.position() {
+                    **inflate = deflated.inflate(&self.index, self.len);
+                }
+                // Decompress and discard the contents up to the position.
+                let skip = self.pos - inflate.position();
+                io::copy(&mut (&mut **inflate).take(skip), &mut io::sink())?;
+                inflate.read(&mut buf[..len])?
+            }
+        };
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for ArchiveFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+impl fmt::Debug for ArchiveFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveFile")
+            .field("len", &self.len)
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a directory in an [`ArchiveDirView`].
+#[derive(Debug)]
+pub struct ArchiveReadDir {
+    entries: vec::IntoIter<ArchiveDirEntry>,
+}
+
+impl Iterator for ArchiveReadDir {
+    type Item = io::Result<ArchiveDirEntry>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Ok)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// An entry of a directory in an [`ArchiveDirView`].
+#[derive(Clone, Debug)]
+pub struct ArchiveDirEntry {
+    name: OsString,
+    metadata: ArchiveMetadata,
+}
+
+impl ArchiveDirEntry {
+    /// Returns the bare file name of this entry, without any other leading
+    /// path components.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the type of this entry, without following a symbolic link.
+    #[inline]
+    pub fn file_type(&self) -> io::Result<FileTypeSummary> {
+        Ok(self.metadata.kind)
+    }
+
+    /// Returns the metadata for this entry, without following a symbolic
+    /// link.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<ArchiveMetadata> {
+        Ok(self.metadata.clone())
+    }
+}
+
+pub(crate) fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "archive is truncated")
+}
+
+pub(crate) fn malformed(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub(crate) fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        match read_at(file, buf, offset) {
+            Ok(0) => return Err(truncated()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Reads the headers of the tar archive in `file`, returning its entries by
+/// path.
+fn read_index(file: &File) -> io::Result<BTreeMap<PathBuf, Entry>> {
+    let file_len = file.metadata()?.len();
+    let mut entries = BTreeMap::new();
+    let mut offset = 0;
+    // Extended names and values which apply to the next entry.
+    let mut long_name = None;
+    let mut long_link = None;
+    let mut pax_size = None;
+    let mut pax_mtime = None;
+    // An archive may end without the usual two blocks of zeros.
+    while offset < file_len {
+        let mut header = [0; BLOCK as usize];
+        read_exact_at(file, &mut header, offset)?;
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let sum = header[..148].iter().chain(&[b' '; 8]).chain(&header[156..]);
+        if number(&header[148..156])? != sum.map(|b| u64::from(*b)).sum::<u64>() {
+            return Err(malformed("invalid tar header checksum"));
+        }
+        let len = match pax_size.take() {
+            Some(len) => len,
+            None => number(&header[124..136])?,
+        };
+        let data = offset + BLOCK;
+        offset = len
+            .div_ceil(BLOCK)
+            .checked_mul(BLOCK)
+            .and_then(|len| data.checked_add(len))
+            .ok_or_else(truncated)?;
+        match header[156] {
+            b'x' | b'L' | b'K' => {
+                if len > MAX_EXTENDED {
+                    return Err(malformed("tar extended header is too large"));
+                }
+                let mut extended = vec![0; len as usize];
+                read_exact_at(file, &mut extended, data)?;
+                match header[156] {
+                    b'L' => long_name = Some(until_nul(&extended).to_vec()),
+                    b'K' => long_link = Some(until_nul(&extended).to_vec()),
+                    _ => {
+                        for (key, value) in pax_records(&extended)? {
+                            match key {
+                                b"path" => long_name = Some(value.to_vec()),
+                                b"linkpath" => long_link = Some(value.to_vec()),
+                                b"size" => pax_size = Some(decimal(value)?),
+                                b"mtime" => pax_mtime = Some(decimal(value)?),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+            // PAX global headers apply to the whole archive, but none of
+            // their keys affect what's presented.
+            b'g' => {}
+            kind => {
+                let name = long_name.take().unwrap_or_else(|| ustar_name(&header));
+                let link = long_link
+                    .take()
+                    .unwrap_or_else(|| until_nul(&header[157..257]).to_vec());
+                let mtime = match pax_mtime.take() {
+                    Some(mtime) => mtime,
+                    None => number(&header[136..148])?,
+                };
+                let Some(path) = normalize(&name) else {
+                    continue;
+                };
+                let mut entry = Entry {
+                    kind: FileTypeSummary::Other,
+                    offset: data,
+                    len: 0,
+                    mode: (number(&header[100..108])? & 0o7777) as u32,
+                    mtime,
+                    link: PathBuf::new(),
+                    compression: Compression::Stored,
+                };
+                match kind {
+                    b'0' | b'\0' | b'7' => {
+                        entry.kind = FileTypeSummary::File;
+                        entry.len = len;
+                    }
+                    b'1' => {
+                        let target = normalize(&link)
+                            .and_then(|target| entries.get(&target))
+                            .filter(|target: &&Entry| target.kind == FileTypeSummary::File)
+                            .ok_or_else(|| malformed("tar hard link to a missing file"))?;
+                        entry.kind = FileTypeSummary::File;
+                        entry.offset = target.offset;
+                        entry.len = target.len;
+                    }
+                    b'2' => {
+                        entry.kind = FileTypeSummary::Symlink;
+                        entry.len = link.len() as u64;
+                        entry.link = os_path(&link);
+                    }
+                    b'5' => entry.kind = FileTypeSummary::Dir,
+                    _ => {}
+                }
+                if entry.kind == FileTypeSummary::File && entry.offset + entry.len > file_len {
+                    return Err(truncated());
+                }
+                insert_entry(&mut entries, path, entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Inserts `entry` at `path`, along with any parent directories which
+/// aren't in the archive themselves.
+pub(crate) fn insert_entry(entries: &mut BTreeMap<PathBuf, Entry>, path: PathBuf, entry: Entry) {
+    // The root itself, and entries whose parents were replaced by something
+    // other than a directory, are left out.
+    let parents = || {
+        path.ancestors()
+            .skip(1)
+            .filter(|parent| !parent.as_os_str().is_empty())
+    };
+    if path.as_os_str().is_empty()
+        || parents().any(|parent| {
+            entries
+                .get(parent)
+                .is_some_and(|parent| parent.kind != FileTypeSummary::Dir)
+        })
+    {
+        return;
+    }
+    for parent in parents() {
+        entries
+            .entry(parent.to_path_buf())
+            .or_insert_with(Entry::implied_dir);
+    }
+    entries.insert(path, entry);
+}
+
+/// Returns the name in a ustar header, joining its `prefix` and `name`
+/// fields.
+fn ustar_name(header: &[u8; BLOCK as usize]) -> Vec<u8> {
+    let name = until_nul(&header[..100]);
+    let prefix = until_nul(&header[345..500]);
+    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+        [prefix, b"/", name].concat()
+    } else {
+        name.to_vec()
+    }
+}
+
+/// Returns the path of an entry named `name` within the archive, or `None`
+/// if it would lead outside of it.
+pub(crate) fn normalize(name: &[u8]) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.split(|b| *b == b'/') {
+        match component {
+            b"" | b"." => {}
+            b".." => return None,
+            component => path.push(os_path(component)),
+        }
+    }
+    Some(path)
+}
+
+#[cfg(unix)]
+pub(crate) fn os_path(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_path(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn until_nul(field: &[u8]) -> &[u8] {
+    &field[..field.iter().position(|b| *b == 0).unwrap_or(field.len())]
+}
+
+/// Parses a numeric header field, in octal or GNU base-256.
+fn number(field: &[u8]) -> io::Result<u64> {
+    if field[0] & 0x80 != 0 {
+        let mut value = u64::from(field[0] & 0x7f);
+        for byte in &field[1..] {
+            value = value
+                .checked_mul(256)
+                .map(|value| value | u64::from(*byte))
+                .ok_or_else(|| malformed("tar header number is too large"))?;
+        }
+        return Ok(value);
+    }
+    let digits = until_nul(field);
+    let digits = std::str::from_utf8(digits)
+        .map_err(|_| malformed("invalid tar header number"))?
+        .trim_matches(' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| malformed("invalid tar header number"))
+}
+
+/// Parses a decimal PAX value, ignoring any fractional part.
+fn decimal(value: &[u8]) -> io::Result<u64> {
+    let value = value.split(|b| *b == b'.').next().unwrap();
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| malformed("invalid tar extended header value"))
+}
+
+/// Parses PAX extended header records, `"<len> <key>=<value>\n"`.
+fn pax_records(mut pax: &[u8]) -> io::Result<Vec<(&[u8], &[u8])>> {
+    let mut records = Vec::new();
+    while !pax.is_empty() {
+        let space = pax
+            .iter()
+            .position(|b| *b == b' ')
+            .ok_or_else(|| malformed("invalid tar extended header"))?;
+        let len = decimal(&pax[..space])? as usize;
+        if len <= space || len > pax.len() || pax[len - 1] != b'\n' {
+            return Err(malformed("invalid tar extended header"));
+        }
+        let record = &pax[space + 1..len - 1];
+        let equals = record
+            .iter()
+            .position(|b| *b == b'=')
+            .ok_or_else(|| malformed("invalid tar extended header"))?;
+        records.push((&record[..equals], &record[equals + 1..]));
+        pax = &pax[len..];
+    }
+    Ok(records)
+}
@@ -0,0 +1,334 @@
+use crate::error::no_file_name;
+use crate::fsync::sync_dir;
+use crate::policy::Policy;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::{fmt, mem};
+
+/// A file which atomically replaces its target when committed, returned by
+/// [`DirView::open_atomic`].
+///
+/// Data is written to a temporary file in the target's directory, which
+/// [`AtomicFile::commit`] syncs and renames over the target. If an
+/// `AtomicFile` is dropped without being committed, the temporary file is
+/// removed and the target is left unchanged.
+pub struct AtomicFile {
+    file: File,
+    dir: Dir,
+    name: OsString,
+    temp_name: OsString,
+    policy: Arc<Policy>,
+    committed: bool,
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Writes a slice as the entire contents of a file, atomically
+    /// replacing any existing file at `path`.
+    ///
+    /// This is like [`Self::write`], but writes to a temporary file next to
+    /// `path`, syncs it, and renames it over `path`, so that even after a
+    /// crash, `path` holds either its old contents or the new ones.
+    pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            let mut file = AtomicFile::create(self.dir.borrow(), path, &self.policy)?;
+            file.write_all(contents.as_ref())?;
+            file.commit()
+        })
+    }
+
+    /// Writes a slice as the entire contents of a file, like
+    /// [`Self::write_atomic`], keeping up to `keep` previous versions of it
+    /// as backups.
+    ///
+    /// An existing file at `path` named `name` is hard-linked as
+    /// `name.~1~`, after renaming the backup `name.~1~` to `name.~2~`, and
+    /// so on, so that lower numbers are newer, and `path` is never missing.
+    /// Backups numbered beyond `keep` are removed. With a `keep` of 0, no
+    /// backups are kept.
+    pub fn write_versioned<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: C,
+        keep: usize,
+    ) -> io::Result<()> {
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            let mut file = AtomicFile::create(self.dir.borrow(), path, &self.policy)?;
+            file.write_all(contents.as_ref())?;
+            file.rotate_backups(keep)?;
+            file.commit()
+        })
+    }
+
+    /// Opens a file which atomically replaces any existing file at `path`
+    /// once it's written and committed with [`AtomicFile::commit`].
+    ///
+    /// This obtains a writable handle, so it fails for read-only and dry-run
+    /// views.
+    pub fn open_atomic<P: AsRef<Path>>(&self, path: P) -> io::Result<AtomicFile> {
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            self.check_mutation(Operation::Write, path)?;
+            self.policy.check_unbudgeted(Operation::Write, path)?;
+            AtomicFile::create(self.dir.borrow(), path, &self.policy)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Writes a slice as the entire contents of a file, atomically
+    /// replacing any existing file at `path`.
+    ///
+    /// This is like [`Self::write`], but writes to a temporary file next to
+    /// `path`, syncs it, and renames it over `path`, so that even after a
+    /// crash, `path` holds either its old contents or the new ones.
+    pub fn write_atomic<P: AsRef<Utf8Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            let mut file = AtomicFile::create(
+                self.dir.borrow().as_cap_std(),
+                path.as_std_path(),
+                &self.policy,
+            )?;
+            file.write_all(contents.as_ref())?;
+            file.commit()
+        })
+    }
+
+    /// Writes a slice as the entire contents of a file, like
+    /// [`Self::write_atomic`], keeping up to `keep` previous versions of it
+    /// as backups.
+    ///
+    /// See [`DirView::write_versioned`].
+    pub fn write_versioned<P: AsRef<Utf8Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: C,
+        keep: usize,
+    ) -> io::Result<()> {
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            let mut file = AtomicFile::create(
+                self.dir.borrow().as_cap_std(),
+                path.as_std_path(),
+                &self.policy,
+            )?;
+            file.write_all(contents.as_ref())?;
+            file.rotate_backups(keep)?;
+            file.commit()
+        })
+    }
+
+    /// Opens a file which atomically replaces any existing file at `path`
+    /// once it's written and committed with [`AtomicFile::commit`].
+    ///
+    /// This obtains a writable handle, so it fails for read-only and dry-run
+    /// views.
+    pub fn open_atomic<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<AtomicFile> {
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            self.check_mutation(Operation::Write, path)?;
+            self.policy
+                .check_unbudgeted(Operation::Write, path.as_std_path())?;
+            AtomicFile::create(
+                self.dir.borrow().as_cap_std(),
+                path.as_std_path(),
+                &self.policy,
+            )
+        })
+    }
+}
+
+impl AtomicFile {
+    fn create(dir: &Dir, path: &Path, policy: &Arc<Policy>) -> io::Result<Self> {
+        let name = path.file_name().ok_or_else(no_file_name)?;
+        let dir = match path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            Some(parent) => dir.open_dir(parent)?,
+            None => dir.try_clone()?,
+        };
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        loop {
+            let temp_name = temp_name(name);
+            let file = match dir.open_with(&temp_name, &options) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            };
+            let file = Self {
+                file,
+                dir,
+                name: name.to_os_string(),
+                temp_name,
+                policy: Arc::clone(policy),
+                committed: false,
+            };
+            // Keep the permissions of the file being replaced.
+            match file.dir.symlink_metadata(name) {
+                Ok(metadata) if metadata.is_file() => {
+                    file.file.set_permissions(metadata.permissions())?
+                }
+                _ => {}
+            }
+            return Ok(file);
+        }
+    }
+
+    /// Returns the temporary file being written.
+    #[inline]
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns the temporary file being written.
+    #[inline]
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Links an existing target as its first backup, after shifting its
+    /// existing backups along, and removes backups numbered beyond `keep`.
+    ///
+    /// The target stays in place until it's replaced by [`Self::commit`].
+    fn rotate_backups(&self, keep: usize) -> io::Result<()> {
+        for entry in self.dir.entries()? {
+            let entry = entry?;
+            if backup_number(&self.name, &entry.file_name()).is_some_and(|n| n > keep) {
+                self.dir.remove_file(entry.file_name())?;
+            }
+        }
+        match self.dir.symlink_metadata(&self.name) {
+            Ok(metadata) if metadata.is_file() && keep != 0 => {}
+            _ => return Ok(()),
+        }
+        for n in (1..keep).rev() {
+            match self.dir.rename(
+                backup_name(&self.name, n),
+                &self.dir,
+                backup_name(&self.name, n + 1),
+            ) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        // With a `keep` of 1, the first backup is still there to replace.
+        let first = backup_name(&self.name, 1);
+        match self.dir.remove_file(&first) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        self.dir.hard_link(&self.name, &self.dir, first)
+    }
+
+    /// Syncs the written data and renames the temporary file over the
+    /// target, replacing it.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.sync_all()?;
+        self.dir.rename(&self.temp_name, &self.dir, &self.name)?;
+        self.committed = true;
+        // Sync the directory too, so that the rename itself is durable.
+        sync_dir(&self.dir)
+    }
+}
+
+impl Write for AtomicFile {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.file.write(buf)?;
+        self.policy.account_bytes_written(len);
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.dir.remove_file(mem::take(&mut self.temp_name)).ok();
+        }
+    }
+}
+
+impl fmt::Debug for AtomicFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicFile")
+            .field("name", &self.name)
+            .field("temp_name", &self.temp_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Generates a name for a temporary file to replace `name`, which is hidden
+/// and unlikely to collide with existing files.
+pub(crate) fn temp_name(name: &OsStr) -> OsString {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(
+        ".tmp-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    temp_name
+}
+
+/// Returns the name of backup number `n` of the file named `name`.
+fn backup_name(name: &OsStr, n: usize) -> OsString {
+    let mut backup_name = name.to_os_string();
+    backup_name.push(format!(".~{}~", n));
+    backup_name
+}
+
+/// Returns the number of the backup named `backup_name`, if it's a backup of
+/// the file named `name`.
+fn backup_number(name: &OsStr, backup_name: &OsStr) -> Option<usize> {
+    let suffix = backup_name
+        .as_encoded_bytes()
+        .strip_prefix(name.as_encoded_bytes())?
+        .strip_prefix(b".~")?
+        .strip_suffix(b"~")?;
+    if suffix.is_empty() || !suffix.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(suffix).ok()?.parse().ok()
+}
@@ -0,0 +1,131 @@
+#[cfg(feature = "tracing")]
+use crate::ViewError;
+use crate::{Operation, ViewKind};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, io};
+
+/// A record of an operation performed through an audited view.
+///
+/// See [`DirView::with_audit`] and [`DirViewBuilder::audit`].
+///
+/// [`DirView::with_audit`]: crate::DirView::with_audit
+/// [`DirViewBuilder::audit`]: crate::DirViewBuilder::audit
+#[derive(Copy, Clone, Debug)]
+pub struct AuditEvent<'a> {
+    pub(crate) operation: Operation,
+    pub(crate) path: &'a Path,
+    pub(crate) target: Option<&'a Path>,
+    pub(crate) view_kind: ViewKind,
+    pub(crate) outcome: Result<(), &'a io::Error>,
+}
+
+impl<'a> AuditEvent<'a> {
+    /// Returns the operation that was performed.
+    #[inline]
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Returns the path the operation was applied to, relative to the view.
+    ///
+    /// Operations on the view's directory itself, such as
+    /// [`DirView::entries`], report a path of `.`.
+    ///
+    /// [`DirView::entries`]: crate::DirView::entries
+    #[inline]
+    pub fn path(&self) -> &'a Path {
+        self.path
+    }
+
+    /// Returns the second path of operations involving two, such as the
+    /// destination of a rename or the contents of a symbolic link.
+    #[inline]
+    pub fn target(&self) -> Option<&'a Path> {
+        self.target
+    }
+
+    /// Returns the kind of the view the operation was performed through.
+    #[inline]
+    pub fn view_kind(&self) -> ViewKind {
+        self.view_kind
+    }
+
+    /// Returns the outcome of the operation.
+    ///
+    /// Denials by the view are reported as errors too, and can be
+    /// distinguished with [`ViewError`].
+    ///
+    /// [`ViewError`]: crate::ViewError
+    #[inline]
+    pub fn outcome(&self) -> Result<(), &'a io::Error> {
+        self.outcome
+    }
+}
+
+/// A hook called with every operation performed through a view.
+#[derive(Clone)]
+pub(crate) struct AuditHook(Arc<dyn Fn(&AuditEvent<'_>) + Send + Sync>);
+
+impl AuditHook {
+    pub(crate) fn new(hook: impl Fn(&AuditEvent<'_>) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, event: &AuditEvent<'_>) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for AuditHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditHook").finish_non_exhaustive()
+    }
+}
+
+/// Test whether the current `tracing` subscriber is interested in any of the
+/// events [`trace`] emits.
+#[cfg(feature = "tracing")]
+#[inline]
+pub(crate) fn is_traced() -> bool {
+    use tracing::{enabled, Level};
+
+    enabled!(Level::WARN) || enabled!(Level::DEBUG) || enabled!(Level::TRACE)
+}
+
+/// Emits a `tracing` event for an operation.
+///
+/// Successful operations are logged at `TRACE` level, failures at `DEBUG`,
+/// and denials by the view at `WARN`.
+#[cfg(feature = "tracing")]
+pub(crate) fn trace(event: &AuditEvent<'_>) {
+    use tracing::{debug, trace, warn};
+
+    let path = event.path.display();
+    let target = event.target.map(Path::display);
+    match event.outcome {
+        Ok(()) => trace!(
+            operation = %event.operation,
+            path = %path,
+            target = target.map(tracing::field::display),
+            view_kind = ?event.view_kind,
+            "dir-view operation",
+        ),
+        Err(error) if ViewError::is_denial(error) => warn!(
+            operation = %event.operation,
+            path = %path,
+            target = target.map(tracing::field::display),
+            view_kind = ?event.view_kind,
+            %error,
+            "dir-view operation denied",
+        ),
+        Err(error) => debug!(
+            operation = %event.operation,
+            path = %path,
+            target = target.map(tracing::field::display),
+            view_kind = ?event.view_kind,
+            %error,
+            "dir-view operation failed",
+        ),
+    }
+}
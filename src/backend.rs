@@ -0,0 +1,336 @@
+use crate::dry_run;
+use crate::policy::Policy;
+use crate::resolve;
+use crate::{FileTypeSummary, Operation, ViewKind};
+use cap_std::fs::Dir;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The queries a view's restrictions make of the tree it's a view of.
+///
+/// Views check their operations with [`Restrictions`] over their backend,
+/// so that views over different backends, such as [`Dir`] and the trees of
+/// [`MemDirView`], share one set of restriction logic.
+///
+/// [`MemDirView`]: crate::MemDirView
+pub(crate) trait Backend {
+    /// Returns the type of the entry at `path`, without following a symbolic
+    /// link in its last component.
+    fn entry_type(&self, path: &Path) -> io::Result<FileTypeSummary>;
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Returns the names of the entries of the directory at `path`, which is
+    /// the view's directory if it's empty.
+    fn entry_names(&self, path: &Path) -> io::Result<Vec<OsString>>;
+
+    /// Returns the canonical form of `path`, relative to the view's
+    /// directory.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Test whether resolving `path` crosses a mount point.
+    fn crosses_filesystem(&self, path: &Path) -> io::Result<bool>;
+
+    /// Test whether resolving `path` passes through a junction or other
+    /// reparse point.
+    #[cfg(windows)]
+    fn crosses_reparse_point(&self, path: &Path) -> io::Result<bool>;
+}
+
+impl Backend for Dir {
+    fn entry_type(&self, path: &Path) -> io::Result<FileTypeSummary> {
+        Ok(self.symlink_metadata(path)?.file_type().into())
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.read_link_contents(path)
+    }
+
+    fn entry_names(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        let entries = if path.as_os_str().is_empty() {
+            self.entries()?
+        } else {
+            self.read_dir(path)?
+        };
+        Ok(entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name())
+            .collect())
+    }
+
+    #[inline]
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Dir::canonicalize(self, path)
+    }
+
+    #[inline]
+    fn crosses_filesystem(&self, path: &Path) -> io::Result<bool> {
+        resolve::crosses_filesystem(self, path)
+    }
+
+    #[cfg(windows)]
+    #[inline]
+    fn crosses_reparse_point(&self, path: &Path) -> io::Result<bool> {
+        resolve::crosses_reparse_point(self, path)
+    }
+}
+
+/// The type of a view, for error messages.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ViewType {
+    DirView,
+    #[cfg(feature = "fs_utf8")]
+    DirViewUtf8,
+    #[cfg(feature = "testing")]
+    MemDirView,
+}
+
+impl ViewType {
+    fn readonly_message(self) -> &'static str {
+        match self {
+            Self::DirView => "attempt to modify a directory tree through a read-only `DirView`",
+            #[cfg(feature = "fs_utf8")]
+            Self::DirViewUtf8 => {
+                "attempt to modify a directory tree through a read-only `DirViewUtf8`"
+            }
+            #[cfg(feature = "testing")]
+            Self::MemDirView => {
+                "attempt to modify a directory tree through a read-only `MemDirView`"
+            }
+        }
+    }
+
+    fn dry_run_message(self) -> &'static str {
+        match self {
+            Self::DirView => "attempt to obtain a writable handle through a dry-run `DirView`",
+            #[cfg(feature = "fs_utf8")]
+            Self::DirViewUtf8 => {
+                "attempt to obtain a writable handle through a dry-run `DirViewUtf8`"
+            }
+            #[cfg(feature = "testing")]
+            Self::MemDirView => {
+                "attempt to obtain a writable handle through a dry-run `MemDirView`"
+            }
+        }
+    }
+
+    /// Test whether views of this type only use UTF-8 paths.
+    fn is_utf8(self) -> bool {
+        #[cfg(feature = "fs_utf8")]
+        return self == Self::DirViewUtf8;
+        #[cfg(not(feature = "fs_utf8"))]
+        return false;
+    }
+}
+
+/// The restrictions of a view of kind `view_kind` with `policy`, over
+/// `backend`.
+pub(crate) struct Restrictions<'a, B: ?Sized> {
+    pub(crate) backend: &'a B,
+    pub(crate) policy: &'a Policy,
+    pub(crate) view_kind: ViewKind,
+    pub(crate) view_type: ViewType,
+}
+
+impl<B: Backend + ?Sized> Restrictions<'_, B> {
+    /// Test whether the view is a full view which imposes no restrictions
+    /// and observes nothing, so that operations can go straight to the
+    /// backend.
+    #[inline]
+    pub(crate) fn is_passthrough(&self) -> bool {
+        self.view_kind == ViewKind::Full
+            && self.policy.is_unrestricted()
+            && !self.policy.is_observed()
+    }
+
+    /// Performs `f` on `path`, reporting the outcome to the audit hook.
+    pub(crate) fn audited<T>(
+        &self,
+        operation: Operation,
+        path: &Path,
+        f: impl FnOnce(&Path) -> io::Result<T>,
+    ) -> io::Result<T> {
+        if self.is_passthrough() {
+            return f(path);
+        }
+        let generation = self.policy.cache_generation();
+        let checked = self.policy.check_lexical(operation, path);
+        let path = match checked {
+            Ok(()) => self.fold_names(path),
+            Err(_) => Cow::Borrowed(path),
+        };
+        let result = checked
+            .and_then(|()| self.policy.check_portable(operation, &path))
+            .and_then(|()| {
+                self.policy
+                    .check_symlink_limit(self.backend, operation, &path)
+            })
+            .and_then(|()| f(&path));
+        self.policy.revalidate_cache(generation);
+        self.policy
+            .audit(self.view_kind, operation, &path, None, &result);
+        result
+    }
+
+    /// Performs `f` on `path` and `target`, reporting the outcome to the
+    /// audit hook.
+    ///
+    /// Only `path` is in the view, so only it is matched to the entries it
+    /// names. The targets of symbolic links aren't paths in a view, so
+    /// they aren't checked as paths.
+    pub(crate) fn audited_pair<T>(
+        &self,
+        operation: Operation,
+        path: &Path,
+        target: &Path,
+        f: impl FnOnce(&Path) -> io::Result<T>,
+    ) -> io::Result<T> {
+        if self.is_passthrough() {
+            return f(path);
+        }
+        let generation = self.policy.cache_generation();
+        let checked = self.policy.check_lexical(operation, path).and_then(|()| {
+            if operation == Operation::Symlink {
+                return Ok(());
+            }
+            self.policy.check_lexical(operation, target)
+        });
+        let path = match checked {
+            Ok(()) => self.fold_names(path),
+            Err(_) => Cow::Borrowed(path),
+        };
+        let result = checked
+            .and_then(|()| self.policy.check_portable(operation, &path))
+            .and_then(|()| {
+                self.policy
+                    .check_symlink_limit(self.backend, operation, &path)
+            })
+            .and_then(|()| self.policy.check_portable(operation, target))
+            .and_then(|()| f(&path));
+        self.policy.revalidate_cache(generation);
+        self.policy
+            .audit(self.view_kind, operation, &path, Some(target), &result);
+        result
+    }
+
+    /// Returns `path` with its components normalized and matched to the
+    /// entries they name, if the view normalizes names or resolves paths
+    /// case-insensitively.
+    ///
+    /// Views of UTF-8 paths keep `path` as it is if it would match names
+    /// which aren't valid UTF-8.
+    pub(crate) fn fold_names<'p>(&self, path: &'p Path) -> Cow<'p, Path> {
+        if !self.policy.case_insensitive && !self.policy.normalizes() {
+            return Cow::Borrowed(path);
+        }
+        match resolve::fold_names(self.backend, self.policy, path) {
+            Cow::Owned(folded) if self.view_type.is_utf8() && folded.to_str().is_none() => {
+                Cow::Borrowed(path)
+            }
+            folded => folded,
+        }
+    }
+
+    /// Checks whether a symbolic link at `link` may point to `target`.
+    pub(crate) fn check_symlink_target(&self, link: &Path, target: &Path) -> io::Result<()> {
+        if !self.policy.confine_symlink_targets {
+            return Ok(());
+        }
+        let depth = match link.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                resolve::depth(&self.backend.canonicalize(parent)?)
+            }
+            _ => 0,
+        };
+        self.policy.check_symlink_target(link, target, depth)
+    }
+
+    pub(crate) fn check_resolve(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        if self.policy.same_filesystem && self.backend.crosses_filesystem(path)? {
+            return Err(self.policy.denied(
+                "attempt to cross a mount point through a view confined to one filesystem",
+                operation,
+                path,
+            ));
+        }
+        #[cfg(windows)]
+        if self.policy.deny_reparse_points && self.backend.crosses_reparse_point(path)? {
+            return Err(self.policy.denied(
+                "attempt to traverse a junction or other reparse point through a view which denies them",
+                operation,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the kind governing `operation` on `path`, which differs from
+    /// the kind of the view in subtrees with overridden kinds.
+    pub(crate) fn kind_for(&self, operation: Operation, path: &Path) -> ViewKind {
+        self.policy
+            .kind_for(self.view_kind, self.backend, operation, path)
+    }
+
+    /// Checks whether `operation` may obtain a writable handle for `path`.
+    pub(crate) fn check_mutation(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.policy.invalidate_cache();
+        match self.kind_for(operation, path) {
+            ViewKind::Full => Ok(()),
+            ViewKind::Readonly => self.check_change(operation, path),
+            ViewKind::DryRun => {
+                Err(self
+                    .policy
+                    .denied(self.view_type.dry_run_message(), operation, path))
+            }
+        }
+    }
+
+    /// Checks whether `operation` may change `path`, possibly only as a dry
+    /// run.
+    pub(crate) fn check_change(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.policy.invalidate_cache();
+        self.check_change_as(self.kind_for(operation, path), operation, path)
+    }
+
+    fn check_change_as(
+        &self,
+        view_kind: ViewKind,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<()> {
+        match view_kind {
+            ViewKind::Full | ViewKind::DryRun => Ok(()),
+            ViewKind::Readonly => {
+                Err(self
+                    .policy
+                    .denied(self.view_type.readonly_message(), operation, path))
+            }
+        }
+    }
+
+    /// Checks whether `operation` may change `path`. If the view is a
+    /// dry-run view, this also checks that the change could be made,
+    /// records it, and returns `true`, so that the caller returns without
+    /// making it.
+    pub(crate) fn dry_run(
+        &self,
+        operation: Operation,
+        path: &Path,
+        target: Option<&Path>,
+    ) -> io::Result<bool> {
+        self.policy.invalidate_cache();
+        let view_kind = self.kind_for(operation, path);
+        self.check_change_as(view_kind, operation, path)?;
+        if view_kind != ViewKind::DryRun {
+            return Ok(false);
+        }
+        if dry_run::changes_existing(operation) {
+            self.backend.entry_type(path)?;
+        }
+        self.policy.dry_run_log.record(operation, path, target);
+        Ok(true)
+    }
+}
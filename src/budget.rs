@@ -0,0 +1,309 @@
+use crate::policy::Policy;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation, ViewError};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::io::{self, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// A limit on the number of handles open at once through views, set with
+/// [`DirViewBuilder::handle_budget`].
+///
+/// Subdirectory views, enumerations, and files opened with
+/// [`DirView::open_budgeted`] each take one handle from the budget, which
+/// they give back when they're dropped, along with everything derived from
+/// them. Clones share the same budget, so one budget can limit several
+/// views together, such as all of the views of one tenant of a host.
+///
+/// Plain [`File`]s can't hold a handle from a budget, so views with a
+/// budget deny the methods which return them, such as [`DirView::open`]
+/// and [`DirView::create`], in favor of [`DirView::open_budgeted`].
+/// Methods which only use a file while they run, such as [`DirView::read`],
+/// are still permitted.
+///
+/// ```
+/// # use dir_view::{DirViewBuilder, HandleBudget};
+/// # let dir = cap_std::fs::Dir::open_ambient_dir(".", cap_std::ambient_authority())?;
+/// let budget = HandleBudget::new(64);
+/// let view = DirViewBuilder::new().handle_budget(&budget).build(&dir);
+/// let entries = view.entries()?;
+/// assert_eq!(budget.open_handles(), 1);
+/// drop(entries);
+/// assert_eq!(budget.open_handles(), 0);
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// [`DirViewBuilder::handle_budget`]: crate::DirViewBuilder::handle_budget
+#[derive(Clone, Debug)]
+pub struct HandleBudget {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    max: usize,
+    blocking: bool,
+    open: Mutex<usize>,
+    released: Condvar,
+}
+
+impl HandleBudget {
+    /// Constructs a budget of `max` handles, where opening a handle beyond
+    /// it fails.
+    ///
+    /// The failures are denials of kind [`io::ErrorKind::QuotaExceeded`],
+    /// which [`ViewError::is_limit_exceeded`] distinguishes from other
+    /// denials.
+    ///
+    /// [`ViewError::is_limit_exceeded`]: crate::ViewError::is_limit_exceeded
+    #[inline]
+    pub fn new(max: usize) -> Self {
+        Self::with_blocking(max, false)
+    }
+
+    /// Constructs a budget of `max` handles, where opening a handle beyond
+    /// it waits until another handle is dropped.
+    ///
+    /// This can deadlock if the thread which would drop a handle is the one
+    /// waiting.
+    #[inline]
+    pub fn blocking(max: usize) -> Self {
+        Self::with_blocking(max, true)
+    }
+
+    fn with_blocking(max: usize, blocking: bool) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                max,
+                blocking,
+                open: Mutex::new(0),
+                released: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of handles currently open against the budget.
+    #[inline]
+    pub fn open_handles(&self) -> usize {
+        *self.inner.open.lock().unwrap()
+    }
+
+    /// Takes a handle from the budget, or returns `None` if there are none
+    /// left and the budget doesn't block.
+    fn acquire(&self) -> Option<Permit> {
+        let mut open = self.inner.open.lock().unwrap();
+        while *open >= self.inner.max {
+            if !self.inner.blocking {
+                return None;
+            }
+            open = self.inner.released.wait(open).unwrap();
+        }
+        *open += 1;
+        Some(Permit {
+            inner: Arc::clone(&self.inner),
+        })
+    }
+}
+
+/// One handle taken from a [`HandleBudget`], which is given back on drop.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    inner: Arc<Inner>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.inner.open.lock().unwrap() -= 1;
+        self.inner.released.notify_one();
+    }
+}
+
+impl Policy {
+    /// Takes a handle from the view's budget, if it has one, for a handle
+    /// opened by `operation` on `path`.
+    pub(crate) fn acquire_handle(
+        &self,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<Option<Permit>> {
+        let Some(budget) = &self.handle_budget else {
+            return Ok(None);
+        };
+        match budget.acquire() {
+            Some(permit) => Ok(Some(permit)),
+            None => Err(ViewError::limit_exceeded(
+                io::ErrorKind::QuotaExceeded,
+                "attempt to open more handles than the view's budget permits",
+                operation,
+                path,
+                self.denial_context,
+            )
+            .into()),
+        }
+    }
+
+    /// Checks that the view has no budget, for `operation` on `path`, which
+    /// returns a handle that can't hold a place in one.
+    pub(crate) fn check_unbudgeted(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        if self.handle_budget.is_none() {
+            return Ok(());
+        }
+        Err(self.denied(
+            "attempt to obtain an unbudgeted handle through a view with a handle budget",
+            operation,
+            path,
+        ))
+    }
+
+    /// Returns `policy` holding a handle from the view's budget, if it has
+    /// one, for a new directory handle opened by `operation` on `path` which
+    /// will share it.
+    pub(crate) fn with_permit(
+        policy: Arc<Policy>,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<Arc<Self>> {
+        match policy.acquire_handle(operation, path)? {
+            Some(permit) => Ok(Arc::new(Self {
+                _permit: Some(Arc::new(permit)),
+                ..Policy::clone(&policy)
+            })),
+            None => Ok(policy),
+        }
+    }
+}
+
+/// A handle opened through a view with a [`HandleBudget`], which holds one
+/// handle from the budget until it's dropped.
+///
+/// This is returned by [`DirView::open_budgeted`], and dereferences to the
+/// handle.
+#[derive(Debug)]
+pub struct Budgeted<T> {
+    inner: T,
+    _permit: Option<Permit>,
+}
+
+impl<T> Budgeted<T> {
+    /// Returns the handle, giving its place in the budget back.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Deref for Budgeted<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Budgeted<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: Read> Read for Budgeted<T> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+
+    #[inline]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.inner.read_vectored(bufs)
+    }
+}
+
+impl<T: Write> Write for Budgeted<T> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T: Seek> Seek for Budgeted<T> {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Opens a file at `path` with the options specified by `options`, like
+    /// [`Self::open_with`], taking a handle from the view's
+    /// [`HandleBudget`], if it has one, until the file is dropped.
+    ///
+    /// Views with a budget deny [`Self::open`], [`Self::create`], and the
+    /// other methods returning plain [`File`]s, which can't count against
+    /// it, so this is how files are opened through them.
+    #[inline]
+    pub fn open_budgeted<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &OpenOptions,
+    ) -> io::Result<Budgeted<File>> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            let permit = self.policy.acquire_handle(Operation::Open, path)?;
+            let options = self.kind_for(Operation::Open, path).open_options(options);
+            Ok(Budgeted {
+                inner: self.dir.borrow().open_with(path, &options)?,
+                _permit: permit,
+            })
+        })
+    }
+
+    /// Opens a file at `path` for reading by an operation of the view,
+    /// which works through views with a budget.
+    pub(crate) fn open_budgeted_read(&self, path: &Path) -> io::Result<Budgeted<File>> {
+        self.open_budgeted(path, OpenOptions::new().read(true))
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Opens a file at `path` with the options specified by `options`,
+    /// taking a handle from the view's [`HandleBudget`], if it has one,
+    /// until the file is dropped.
+    ///
+    /// See [`DirView::open_budgeted`].
+    #[inline]
+    pub fn open_budgeted<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        options: &cap_std::fs_utf8::OpenOptions,
+    ) -> io::Result<Budgeted<cap_std::fs_utf8::File>> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            let permit = self
+                .policy
+                .acquire_handle(Operation::Open, path.as_std_path())?;
+            let options = self.kind_for(Operation::Open, path).open_options(options);
+            Ok(Budgeted {
+                inner: self.dir.borrow().open_with(path, &options)?,
+                _permit: permit,
+            })
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+use std::borrow::Borrow;
+use std::io::{self, BufRead, BufReader, BufWriter, Lines};
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Opens a file in read-only mode, wrapped in a [`BufReader`].
+    ///
+    /// This is [`Self::open`] followed by [`BufReader::new`].
+    #[inline]
+    pub fn open_buffered<P: AsRef<Path>>(&self, path: P) -> io::Result<BufReader<File>> {
+        Ok(BufReader::new(self.open(path)?))
+    }
+
+    /// Opens a file in write-only mode, as with [`Self::create`], wrapped in
+    /// a [`BufWriter`].
+    ///
+    /// As with any `BufWriter`, call [`BufWriter::flush`] or
+    /// [`BufWriter::into_inner`] to observe errors writing the last of the
+    /// data.
+    ///
+    /// [`BufWriter::flush`]: std::io::Write::flush
+    #[inline]
+    pub fn open_buffered_write<P: AsRef<Path>>(&self, path: P) -> io::Result<BufWriter<File>> {
+        Ok(BufWriter::new(self.create(path)?))
+    }
+
+    /// Returns an iterator over the lines of a file.
+    ///
+    /// This opens the file with [`Self::open_buffered`]. Lines are split as
+    /// by [`BufRead::lines`], and must be valid UTF-8.
+    #[inline]
+    pub fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines<BufReader<File>>> {
+        Ok(self.open_buffered(path)?.lines())
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Opens a file in read-only mode, wrapped in a [`BufReader`].
+    ///
+    /// This is [`Self::open`] followed by [`BufReader::new`].
+    #[inline]
+    pub fn open_buffered<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<BufReader<cap_std::fs_utf8::File>> {
+        Ok(BufReader::new(self.open(path)?))
+    }
+
+    /// Opens a file in write-only mode, as with [`Self::create`], wrapped in
+    /// a [`BufWriter`].
+    ///
+    /// See [`DirView::open_buffered_write`].
+    #[inline]
+    pub fn open_buffered_write<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<BufWriter<cap_std::fs_utf8::File>> {
+        Ok(BufWriter::new(self.create(path)?))
+    }
+
+    /// Returns an iterator over the lines of a file.
+    ///
+    /// See [`DirView::read_lines`].
+    #[inline]
+    pub fn read_lines<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Lines<BufReader<cap_std::fs_utf8::File>>> {
+        Ok(self.open_buffered(path)?.lines())
+    }
+}
@@ -0,0 +1,472 @@
+use crate::audit::{AuditEvent, AuditHook};
+use crate::dry_run::DryRunLog;
+use crate::overrides::KindOverrides;
+use crate::policy::Policy;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+#[cfg(feature = "unicode-normalization")]
+use crate::NormalizationForm;
+use crate::{Coordinator, DirView, HandleBudget, RateLimiter, ViewKind};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A builder used to create views with restrictions beyond those implied by
+/// their [`ViewKind`].
+///
+/// The restrictions are inherited by all views, iterators, and entries
+/// derived from the built view.
+#[derive(Clone, Debug)]
+pub struct DirViewBuilder {
+    view_kind: ViewKind,
+    policy: Policy,
+    overrides: Vec<(PathBuf, ViewKind)>,
+}
+
+impl DirViewBuilder {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// All options are initially set to their least restrictive values, with
+    /// a view kind of [`ViewKind::Full`].
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            view_kind: ViewKind::Full,
+            policy: Policy::default(),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Sets the kind of the view.
+    #[inline]
+    pub fn view_kind(&mut self, view_kind: ViewKind) -> &mut Self {
+        self.view_kind = view_kind;
+        self
+    }
+
+    /// Sets the kind of the view to [`ViewKind::Readonly`].
+    #[inline]
+    pub fn readonly(&mut self) -> &mut Self {
+        self.view_kind(ViewKind::Readonly)
+    }
+
+    /// Sets the kind of the subtree at `path`, relative to the view's
+    /// directory, overriding the kind of the view there.
+    ///
+    /// This allows, for example, a readonly view of a workspace with
+    /// writable scratch directories. Operations are governed by the kind of
+    /// the directory they change, and views and entries of directories in
+    /// the subtree have its kind. Where overrides are nested, the innermost
+    /// applies. Where a symbolic link is followed, the narrower of the kinds
+    /// of the link and its target applies, and where a location can't be
+    /// determined, the narrowest kind in the view applies.
+    ///
+    /// Like the view kind itself, this is enforced by the view, and hard
+    /// links made from outside of it can make files in the subtree
+    /// reachable from elsewhere. Capsicum rights and Landlock rules follow
+    /// the kind of the view itself. Views with overrides can't be sent over
+    /// Unix-domain sockets.
+    ///
+    /// Which subtree a path is in is determined by resolving it before the
+    /// operation is performed, and the two aren't atomic. If others can
+    /// change the tree concurrently, for example by replacing a directory
+    /// with a symbolic link, an operation can act on a location with a
+    /// broader kind than the one it was checked against, so overrides aren't
+    /// a confinement boundary against them.
+    #[inline]
+    pub fn override_view_kind<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        view_kind: ViewKind,
+    ) -> &mut Self {
+        self.overrides.push((path.as_ref().to_owned(), view_kind));
+        self
+    }
+
+    /// Denies creating symbolic links through the view.
+    #[inline]
+    pub fn deny_symlinks(&mut self) -> &mut Self {
+        self.policy.deny_symlinks = true;
+        self
+    }
+
+    /// Omits entries whose names start with `.` when iterating over
+    /// directories through the view.
+    ///
+    /// This only affects iteration; hidden files may still be accessed by
+    /// name.
+    #[inline]
+    pub fn hide_hidden_files(&mut self) -> &mut Self {
+        self.policy.hide_hidden = true;
+        self
+    }
+
+    /// Limits how many directory levels below the built view may be opened
+    /// or iterated through it.
+    ///
+    /// A `max_depth` of 0 permits no subdirectories to be opened at all.
+    ///
+    /// Paths are checked both as they're written and as they resolve, so
+    /// symbolic links within the view can't lead past the limit, and
+    /// directories can't be opened through links found by iterating. The
+    /// check and the open aren't atomic, so the limit isn't a confinement
+    /// boundary against others changing the tree concurrently.
+    #[inline]
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.policy.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Denies paths longer than `max_path_len` bytes, in the platform's
+    /// encoding, before resolving them.
+    ///
+    /// Such denials have the kind [`io::ErrorKind::InvalidFilename`], and
+    /// [`ViewError::is_limit_exceeded`] distinguishes them from other
+    /// denials.
+    ///
+    /// [`ViewError::is_limit_exceeded`]: crate::ViewError::is_limit_exceeded
+    #[inline]
+    pub fn max_path_len(&mut self, max_path_len: usize) -> &mut Self {
+        self.policy.max_path_len = Some(max_path_len);
+        self
+    }
+
+    /// Denies paths with more than `max_path_components` components,
+    /// counting `..` and any leading `.`, before resolving them.
+    ///
+    /// Such denials have the kind [`io::ErrorKind::InvalidFilename`], and
+    /// [`ViewError::is_limit_exceeded`] distinguishes them from other
+    /// denials.
+    ///
+    /// [`ViewError::is_limit_exceeded`]: crate::ViewError::is_limit_exceeded
+    #[inline]
+    pub fn max_path_components(&mut self, max_path_components: usize) -> &mut Self {
+        self.policy.max_path_components = Some(max_path_components);
+        self
+    }
+
+    /// Denies paths whose resolution traverses more than
+    /// `max_symlink_traversals` symbolic links, including one in the last
+    /// component of the path, or in the components of link targets.
+    ///
+    /// The links are counted by resolving the path one component at a time
+    /// before the operation, so this adds a system call for each
+    /// component. Such denials have the kind the platform reports for
+    /// symbolic link loops, `ELOOP` on Unix-family platforms, and
+    /// [`ViewError::is_limit_exceeded`] distinguishes them from other
+    /// denials. A `max_symlink_traversals` of 0 denies paths through any
+    /// symbolic link.
+    ///
+    /// [`ViewError::is_limit_exceeded`]: crate::ViewError::is_limit_exceeded
+    #[inline]
+    pub fn max_symlink_traversals(&mut self, max_symlink_traversals: usize) -> &mut Self {
+        self.policy.max_symlink_traversals = Some(max_symlink_traversals);
+        self
+    }
+
+    /// Denies connecting to, or sending datagrams to, Unix-domain sockets
+    /// through the view.
+    ///
+    /// Binding sockets is controlled by the view kind, as it creates
+    /// filesystem nodes.
+    #[inline]
+    pub fn deny_unix_connect(&mut self) -> &mut Self {
+        self.policy.deny_unix_connect = true;
+        self
+    }
+
+    /// Denies spawning processes in the view's directory with
+    /// [`DirView::spawn_in`].
+    ///
+    /// A spawned process isn't confined to the view, so a readonly view
+    /// handed to code which shouldn't run programs should deny this.
+    #[inline]
+    pub fn deny_exec(&mut self) -> &mut Self {
+        self.policy.deny_exec = true;
+        self
+    }
+
+    /// Denies opening files and directories through the view if resolving
+    /// their paths would cross a mount point, confining the view to the
+    /// filesystem of its directory.
+    ///
+    /// On Linux this checks paths with `openat2` and `RESOLVE_NO_XDEV`, and
+    /// elsewhere by comparing the devices of the directories along them. It
+    /// isn't supported on Windows, where opening paths through such a view
+    /// fails. Paths are checked before they're opened, and the two aren't
+    /// atomic, so this isn't a confinement boundary against others mounting
+    /// filesystems or changing the tree concurrently.
+    ///
+    /// Views already refuse to follow "magic links" such as those in
+    /// `/proc`, and never resolve paths outside their directory.
+    #[inline]
+    pub fn same_filesystem(&mut self) -> &mut Self {
+        self.policy.same_filesystem = true;
+        self
+    }
+
+    /// Denies opening files and directories through the view if resolving
+    /// their paths would pass through a junction or any other reparse
+    /// point, including one at the path itself.
+    ///
+    /// Views never follow junctions or symbolic links to paths outside
+    /// their directory, but other reparse points, such as those of cloud
+    /// storage providers or deduplication, are resolved by the filesystem
+    /// itself. This refuses them all.
+    #[cfg(windows)]
+    #[inline]
+    pub fn deny_reparse_points(&mut self) -> &mut Self {
+        self.policy.deny_reparse_points = true;
+        self
+    }
+
+    /// Denies paths containing names which aren't valid on Windows, so that
+    /// applications behave the same wherever the view's directory is.
+    ///
+    /// This denies names reserved for devices, such as `CON`, `NUL`, `COM1`,
+    /// or `nul.txt`, which on Windows refer to the device rather than to a
+    /// file, names ending in a dot or space, which Windows silently strips,
+    /// names containing characters such as `:`, `\`, or `*`, names which
+    /// aren't valid Unicode, and names and paths longer than Windows
+    /// permits. Paths up to the limit of `\\?\` paths are permitted, as the
+    /// standard library adds that prefix on Windows where needed.
+    ///
+    /// This applies to every path used through the view, including when
+    /// reading, so existing entries with such names can be listed but not
+    /// opened.
+    #[inline]
+    pub fn portable_names(&mut self) -> &mut Self {
+        self.policy.portable_names = true;
+        self
+    }
+
+    /// Denies paths which [`sanitize_path`] rejects, such as paths which are
+    /// absolute or contain `..`, empty components, NUL bytes, or names which
+    /// aren't valid on Windows, before touching the filesystem.
+    ///
+    /// Without this, such paths either fail during resolution or, in the
+    /// case of `..` which stays within the view, succeed. Servers handling
+    /// untrusted request paths can use this to fail early with a clear
+    /// error. The targets of symbolic links aren't checked; see
+    /// [`Self::confine_symlink_targets`] for those.
+    ///
+    /// [`sanitize_path`]: crate::sanitize_path
+    #[inline]
+    pub fn strict_paths(&mut self) -> &mut Self {
+        self.policy.strict_paths = true;
+        self
+    }
+
+    /// Denies creating symbolic links whose targets are absolute, or which
+    /// resolve lexically, from the directory containing the link, to
+    /// somewhere outside of the view.
+    ///
+    /// This lets untrusted input, such as an archive being extracted,
+    /// create symbolic links without creating ways out of the view for
+    /// anything which later follows them without the view's protection.
+    /// The directory containing the link is located by resolving its
+    /// symbolic links, so a link can't be placed behind one to make its
+    /// target appear deeper than it is.
+    #[inline]
+    pub fn confine_symlink_targets(&mut self) -> &mut Self {
+        self.policy.confine_symlink_targets = true;
+        self
+    }
+
+    /// Resolves paths case-insensitively, for serving trees authored on
+    /// case-insensitive filesystems from case-sensitive ones.
+    ///
+    /// Where a path component doesn't name an entry exactly, the entries of
+    /// its directory are listed, and the one whose name matches it
+    /// case-insensitively is used in its place, or the first in sorted
+    /// order if several do. The path with the matched names is the one the
+    /// view's restrictions check and the audit hook sees. Components which
+    /// don't match any entry are kept as they are, so new files are created
+    /// with the case they're given, and the contents of symbolic links
+    /// aren't matched.
+    ///
+    /// Each lookup which misses lists a directory, so this is slow for
+    /// large directories.
+    #[inline]
+    pub fn case_insensitive(&mut self) -> &mut Self {
+        self.policy.case_insensitive = true;
+        self
+    }
+
+    /// Put path components and the names of entries in the Unicode
+    /// normalization form `form`, so that trees whose names are stored in
+    /// one form, such as those written on macOS, can be used by clients
+    /// which produce the other.
+    ///
+    /// Path components are normalized before they're resolved, and where
+    /// a normalized component doesn't name an entry exactly, the entries of
+    /// its directory are listed, and the one whose normalized name is the
+    /// same is used in its place, as with [`Self::case_insensitive`]. New
+    /// files are created with normalized names. Names returned by directory
+    /// iteration are normalized too. Names which aren't valid UTF-8, and
+    /// the contents of symbolic links, are left as they are.
+    #[cfg(feature = "unicode-normalization")]
+    #[inline]
+    pub fn normalize_names(&mut self, form: NormalizationForm) -> &mut Self {
+        self.policy.normalization = Some(form);
+        self
+    }
+
+    /// Sets the kind of the errors returned when the view denies an
+    /// operation.
+    ///
+    /// The default is [`io::ErrorKind::PermissionDenied`]. Some applications
+    /// may prefer [`io::ErrorKind::ReadOnlyFilesystem`] for readonly views,
+    /// for example.
+    #[inline]
+    pub fn denial_error_kind(&mut self, kind: io::ErrorKind) -> &mut Self {
+        self.policy.denial_kind = Some(kind);
+        self
+    }
+
+    /// Includes the denied operation and the path it was applied to in the
+    /// messages of errors returned when the view denies an operation.
+    ///
+    /// This is off by default, so that error messages don't reveal paths
+    /// where that's undesirable.
+    #[inline]
+    pub fn denial_context(&mut self) -> &mut Self {
+        self.policy.denial_context = true;
+        self
+    }
+
+    /// Sets the size of the buffer used to read batches of entries when
+    /// iterating over directories through the view.
+    ///
+    /// Listing a large directory takes a system call per batch, so a larger
+    /// buffer, such as 1 MiB, can make listing directories with very many
+    /// entries much faster, at the cost of memory for each open iterator.
+    ///
+    /// This is currently only used on Linux, where it's the buffer passed to
+    /// `getdents64`, and where sizes smaller than 1 KiB are rounded up.
+    /// Elsewhere, the platform's default batching is used.
+    #[inline]
+    pub fn read_dir_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.policy.read_dir_buffer_size = Some(size);
+        self
+    }
+
+    /// Uses io_uring for the bulk operations of the view, such as
+    /// [`DirView::read_many`], submitting the system calls for many files at
+    /// a time.
+    ///
+    /// Where io_uring isn't available, such as on older kernels or where
+    /// it's disabled, the view falls back to making the calls one at a time.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    #[inline]
+    pub fn io_uring(&mut self) -> &mut Self {
+        self.policy.io_uring = true;
+        self
+    }
+
+    /// Calls `audit` with every operation performed through the view.
+    ///
+    /// See [`DirView::with_audit`].
+    #[inline]
+    pub fn audit(&mut self, audit: impl Fn(&AuditEvent<'_>) + Send + Sync + 'static) -> &mut Self {
+        self.policy.audit = Some(AuditHook::new(audit));
+        self
+    }
+
+    /// Counts the operations performed through the view, for
+    /// [`DirView::stats`].
+    ///
+    /// Each built view has its own counts, which are shared with the views
+    /// derived from it.
+    #[inline]
+    pub fn collect_stats(&mut self) -> &mut Self {
+        self.policy.stats = Some(Arc::default());
+        self
+    }
+
+    /// Limits the rate of reads and writes through the view with `limiter`,
+    /// which may be shared with other views.
+    ///
+    /// This applies to whole-file reads and writes such as [`DirView::read`]
+    /// and [`DirView::write`], to copies such as [`DirView::copy`], where
+    /// both the source's and the destination's limits apply, and to handles
+    /// wrapped with [`DirView::throttle`]. Reads and writes through other
+    /// handles opened through the view aren't limited.
+    #[inline]
+    pub fn rate_limit(&mut self, limiter: &RateLimiter) -> &mut Self {
+        self.policy.rate_limit = Some(limiter.clone());
+        self
+    }
+
+    /// Limits the number of handles open at once through the view and the
+    /// views derived from it with `budget`, which may be shared with other
+    /// views.
+    ///
+    /// Subdirectory views, including ones from [`DirView::try_clone`] and
+    /// [`DirView::tempdir_in`], enumerations, and files opened with
+    /// [`DirView::open_budgeted`] count against the budget until they, and
+    /// the views, iterators, and entries derived from them, are dropped.
+    /// The built view itself doesn't. Methods returning plain files, such
+    /// as [`DirView::open`], are denied; see [`HandleBudget`].
+    #[inline]
+    pub fn handle_budget(&mut self, budget: &HandleBudget) -> &mut Self {
+        self.policy.handle_budget = Some(budget.clone());
+        self
+    }
+
+    /// Coordinates the view's intent locks, taken with
+    /// [`DirView::read_guard`] and [`DirView::write_guard`], with those of
+    /// the other views sharing `coordinator`.
+    #[inline]
+    pub fn coordinate(&mut self, coordinator: &Coordinator) -> &mut Self {
+        self.policy.coordinator = Some(coordinator.clone());
+        self
+    }
+
+    /// Constructs a new [`DirView`] from the given [`Dir`], or a reference to
+    /// one, with the options configured in this builder.
+    #[inline]
+    pub fn build<D: Borrow<Dir>>(&self, dir: D) -> DirView<D> {
+        DirView {
+            dir,
+            view_kind: self.view_kind,
+            policy: self.policy(),
+            depth: 0,
+        }
+    }
+
+    /// Constructs a new [`DirViewUtf8`] from the given
+    /// [`cap_std::fs_utf8::Dir`], or a reference to one, with the options
+    /// configured in this builder.
+    #[cfg(feature = "fs_utf8")]
+    #[inline]
+    pub fn build_utf8<D: Borrow<cap_std::fs_utf8::Dir>>(&self, dir: D) -> DirViewUtf8<D> {
+        DirViewUtf8 {
+            dir,
+            view_kind: self.view_kind,
+            policy: self.policy(),
+            depth: 0,
+        }
+    }
+}
+
+impl DirViewBuilder {
+    fn policy(&self) -> Arc<Policy> {
+        let mut policy = self.policy.clone();
+        if policy.stats.is_some() {
+            policy.stats = Some(Arc::default());
+        }
+        policy.dry_run_log = DryRunLog::default();
+        policy.overrides = KindOverrides::new(self.view_kind, &self.overrides);
+        Arc::new(policy)
+    }
+}
+
+impl Default for DirViewBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
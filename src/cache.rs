@@ -0,0 +1,278 @@
+use crate::policy::Policy;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Options for [`DirView::with_cache`].
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+    capacity: usize,
+    ttl: Duration,
+    negative_ttl: Option<Duration>,
+}
+
+impl CacheConfig {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// The cache initially holds up to 1024 entries, each for one second.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            capacity: 1024,
+            ttl: Duration::from_secs(1),
+            negative_ttl: None,
+        }
+    }
+
+    /// Sets the maximum number of entries in the cache. When it's full, the
+    /// oldest entries are evicted first.
+    #[inline]
+    pub fn capacity(&mut self, capacity: usize) -> &mut Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets how long entries are used for before they're looked up again.
+    #[inline]
+    pub fn ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets how long paths which weren't found are remembered as missing
+    /// before they're looked up again, instead of [`Self::ttl`].
+    ///
+    /// Resolvers which probe long chains of fallback paths, most of which
+    /// don't exist, can remember the missing ones for longer than the
+    /// metadata of the files they find, as creating them through the view
+    /// or its clones discards the cache once they're created. A zero
+    /// duration disables caching of missing paths.
+    #[inline]
+    pub fn negative_ttl(&mut self, negative_ttl: Duration) -> &mut Self {
+        self.negative_ttl = Some(negative_ttl);
+        self
+    }
+}
+
+impl Default for CacheConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Memoized metadata lookups, shared by a view and its clones.
+#[derive(Debug)]
+pub(crate) struct MetadataCache {
+    config: CacheConfig,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// The metadata of each path, or `None` if it wasn't found, keyed by the
+    /// path and whether symbolic links were followed.
+    entries: HashMap<(PathBuf, bool), (Instant, Option<Metadata>)>,
+
+    /// The keys of `entries`, oldest first.
+    order: VecDeque<(PathBuf, bool)>,
+
+    /// The number of times the cache has been cleared, so that lookups
+    /// which overlap changes to the tree aren't cached.
+    generation: u64,
+}
+
+impl MetadataCache {
+    fn new(config: &CacheConfig) -> Self {
+        Self {
+            config: config.clone(),
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Returns the cached metadata of `path`, or looks it up with `lookup`
+    /// and caches it. Failures other than `NotFound` aren't cached.
+    pub(crate) fn metadata(
+        &self,
+        path: &Path,
+        follow: bool,
+        lookup: impl FnOnce() -> io::Result<Metadata>,
+    ) -> io::Result<Metadata> {
+        let key = (path.to_path_buf(), follow);
+        let generation = {
+            let inner = self.inner.lock().unwrap();
+            if let Some((time, metadata)) = inner.entries.get(&key) {
+                if time.elapsed() < self.ttl(metadata.is_some()) {
+                    return metadata
+                        .clone()
+                        .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound));
+                }
+            }
+            inner.generation
+        };
+        let result = lookup();
+        let metadata = match &result {
+            Ok(metadata) => Some(metadata.clone()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(_) => return result,
+        };
+        if self.config.capacity > 0 && !self.ttl(metadata.is_some()).is_zero() {
+            let mut inner = self.inner.lock().unwrap();
+            let inner = &mut *inner;
+            // If the tree changed during the lookup, the result may already
+            // be stale.
+            if inner.generation == generation
+                && inner
+                    .entries
+                    .insert(key.clone(), (Instant::now(), metadata))
+                    .is_none()
+            {
+                inner.order.push_back(key);
+                if inner.order.len() > self.config.capacity {
+                    let oldest = inner.order.pop_front().unwrap();
+                    inner.entries.remove(&oldest);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns how long entries for paths which were `found`, or weren't,
+    /// are used for.
+    fn ttl(&self, found: bool) -> Duration {
+        match self.config.negative_ttl {
+            Some(negative_ttl) if !found => negative_ttl,
+            _ => self.config.ttl,
+        }
+    }
+
+    /// Discards all cached metadata.
+    pub(crate) fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.order.clear();
+        inner.generation += 1;
+    }
+
+    fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+}
+
+impl Policy {
+    /// Returns `policy` with a new cache configured by `config`.
+    pub(crate) fn with_cache(policy: &Policy, config: &CacheConfig) -> Arc<Self> {
+        Arc::new(Self {
+            cache: Some(Arc::new(MetadataCache::new(config))),
+            ..policy.clone()
+        })
+    }
+
+    /// Returns the policy for views of other directories opened through a
+    /// view with `policy`, which don't share its cache, as paths in them are
+    /// relative to a different directory.
+    pub(crate) fn for_subdir(policy: &Arc<Policy>) -> Arc<Self> {
+        if policy.cache.is_none() {
+            return Arc::clone(policy);
+        }
+        Arc::new(Self {
+            cache: None,
+            ..Policy::clone(policy)
+        })
+    }
+
+    /// Discards any cached metadata, before changes are made through a
+    /// view.
+    pub(crate) fn invalidate_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Returns the current generation of the cache, if there is one, to
+    /// pass to [`Self::revalidate_cache`] once an operation completes.
+    pub(crate) fn cache_generation(&self) -> Option<u64> {
+        self.cache.as_ref().map(|cache| cache.generation())
+    }
+
+    /// Discards any cached metadata again, after changes were made through
+    /// a view since `generation`, as lookups made by its clones while the
+    /// changes were being made may have cached what they replaced.
+    pub(crate) fn revalidate_cache(&self, generation: Option<u64>) {
+        if let (Some(cache), Some(generation)) = (&self.cache, generation) {
+            if cache.generation() != generation {
+                cache.clear();
+            }
+        }
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns a view of the same directory which memoizes the results of
+    /// [`Self::metadata`], [`Self::symlink_metadata`], and the methods based
+    /// on them, such as [`Self::exists`] and [`Self::is_dir`], including
+    /// which paths weren't found.
+    ///
+    /// The cache is shared with clones of the returned view, but not with
+    /// views of other directories opened through it. Changes made through
+    /// the view or its clones discard the whole cache; changes made any
+    /// other way are seen once the affected entries expire. Operations are
+    /// still reported to the view's audit hook when they're answered from
+    /// the cache.
+    pub fn with_cache(self, config: &CacheConfig) -> Self {
+        Self {
+            policy: Policy::with_cache(&self.policy, config),
+            ..self
+        }
+    }
+
+    /// Looks up the metadata of `path` with `lookup`, through the cache if
+    /// there is one.
+    pub(crate) fn cached_metadata(
+        &self,
+        path: &Path,
+        follow: bool,
+        lookup: impl FnOnce() -> io::Result<Metadata>,
+    ) -> io::Result<Metadata> {
+        match &self.policy.cache {
+            Some(cache) => cache.metadata(path, follow, lookup),
+            None => lookup(),
+        }
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns a view of the same directory which memoizes the results of
+    /// [`Self::metadata`], [`Self::symlink_metadata`], and the methods based
+    /// on them, such as [`Self::exists`] and [`Self::is_dir`].
+    ///
+    /// See [`DirView::with_cache`].
+    pub fn with_cache(self, config: &CacheConfig) -> Self {
+        Self {
+            policy: Policy::with_cache(&self.policy, config),
+            ..self
+        }
+    }
+
+    /// Looks up the metadata of `path` with `lookup`, through the cache if
+    /// there is one.
+    pub(crate) fn cached_metadata(
+        &self,
+        path: &Path,
+        follow: bool,
+        lookup: impl FnOnce() -> io::Result<Metadata>,
+    ) -> io::Result<Metadata> {
+        match &self.policy.cache {
+            Some(cache) => cache.metadata(path, follow, lookup),
+            None => lookup(),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::policy::Policy;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::ViewKind;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+
+impl DirView {
+    /// Limits the Capsicum rights of the view's directory descriptor to
+    /// those its view kind allows.
+    ///
+    /// For readonly views, this drops rights such as `CAP_WRITE`,
+    /// `CAP_CREATE`, and `CAP_UNLINKAT`, so the kernel enforces the view's
+    /// restrictions on the descriptor exposed through `AsFd`, and on
+    /// descriptors opened through it. Full views are left unchanged.
+    ///
+    /// Rights can only be dropped, never regained.
+    pub fn limit_rights(&self) -> io::Result<()> {
+        limit_rights(self.as_fd(), self.view_kind, &self.policy)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl DirViewUtf8 {
+    /// Limits the Capsicum rights of the view's directory descriptor to
+    /// those its view kind allows.
+    ///
+    /// For readonly views, this drops rights such as `CAP_WRITE`,
+    /// `CAP_CREATE`, and `CAP_UNLINKAT`, so the kernel enforces the view's
+    /// restrictions on the descriptor exposed through `AsFd`, and on
+    /// descriptors opened through it. Full views are left unchanged.
+    ///
+    /// Rights can only be dropped, never regained.
+    pub fn limit_rights(&self) -> io::Result<()> {
+        limit_rights(self.as_fd(), self.view_kind, &self.policy)
+    }
+}
+
+fn limit_rights(dir: BorrowedFd<'_>, view_kind: ViewKind, policy: &Policy) -> io::Result<()> {
+    match view_kind {
+        ViewKind::Full => return Ok(()),
+        ViewKind::Readonly | ViewKind::DryRun => {}
+    }
+
+    let connect = if policy.deny_unix_connect {
+        0
+    } else {
+        libc::CAP_CONNECTAT
+    };
+
+    // SAFETY: `cap_rights_t` is plain data, and `__cap_rights_init`
+    // initializes it from a zero-terminated list of rights.
+    let rights = unsafe {
+        let mut rights = std::mem::zeroed::<libc::cap_rights_t>();
+        libc::__cap_rights_init(
+            libc::CAP_RIGHTS_VERSION,
+            &mut rights,
+            libc::CAP_READ,
+            libc::CAP_SEEK,
+            libc::CAP_MMAP_R,
+            libc::CAP_LOOKUP,
+            libc::CAP_FSTAT,
+            libc::CAP_FSTATAT,
+            libc::CAP_FSTATFS,
+            libc::CAP_FCHDIR,
+            libc::CAP_FCNTL,
+            libc::CAP_FLOCK,
+            libc::CAP_FPATHCONF,
+            libc::CAP_EVENT,
+            connect,
+            0_u64,
+        );
+        rights
+    };
+
+    // SAFETY: `dir` is a valid descriptor and `rights` is initialized.
+    if unsafe { libc::cap_rights_limit(dir.as_raw_fd(), &rights) } < 0 {
+        let err = io::Error::last_os_error();
+        return Err(match err.raw_os_error() {
+            Some(libc::ENOSYS) => io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Capsicum is not supported by this kernel",
+            ),
+            _ => err,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use crate::atomic_file::temp_name;
+use crate::fsync::sync_dir;
+use crate::policy::Policy;
+use crate::rename_noreplace::rename_noreplace;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation, Sha256Hasher};
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, str};
+
+/// The SHA-256 hash of a blob in a content-addressable store, returned by
+/// [`DirView::cas_write`].
+///
+/// This displays as 64 lowercase hexadecimal digits.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    /// Returns the hash of `contents`.
+    pub fn of(contents: &[u8]) -> Self {
+        let mut hasher = Sha256Hasher::new();
+        hasher.update(contents);
+        Self(hasher.finish())
+    }
+
+    /// Constructs a hash from its bytes.
+    #[inline]
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Parses a hash from 64 hexadecimal digits, returning `None` if `hex`
+    /// isn't a hash.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        for (byte, digits) in bytes.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            *byte = u8::from_str_radix(str::from_utf8(digits).ok()?, 16).ok()?;
+        }
+        Some(Self(bytes))
+    }
+
+    /// Returns the bytes of the hash.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the path the blob is stored at, which is fanned out into
+    /// subdirectories named after the first byte of the hash, so that no
+    /// directory gets too large.
+    fn path(&self) -> PathBuf {
+        let hex = self.to_string();
+        Path::new(&hex[..2]).join(&hex[2..])
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ContentHash({})", self)
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Stores `contents` in a content-addressable store in the view,
+    /// returning their hash, which [`Self::cas_open`] opens them by.
+    ///
+    /// Blobs are stored at paths like `ab/cdef…`, where `abcdef…` is the
+    /// hexadecimal SHA-256 hash of their contents. Storing contents which
+    /// are already stored does nothing. Blobs are written to a temporary
+    /// file, synced, and renamed into place without replacing anything, so
+    /// concurrent writers of the same contents all succeed, and readers
+    /// never see a partial blob.
+    ///
+    /// This is denied on readonly views.
+    pub fn cas_write<C: AsRef<[u8]>>(&self, contents: C) -> io::Result<ContentHash> {
+        let contents = contents.as_ref();
+        let hash = ContentHash::of(contents);
+        self.audited(Operation::Write, hash.path(), |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            cas_write(self.dir.borrow(), path, contents, &self.policy)
+        })?;
+        Ok(hash)
+    }
+
+    /// Opens the blob with `hash` in the view's content-addressable store
+    /// for reading.
+    ///
+    /// See [`Self::cas_write`].
+    #[inline]
+    pub fn cas_open(&self, hash: &ContentHash) -> io::Result<File> {
+        self.open(hash.path())
+    }
+
+    /// Returns `true` if the blob with `hash` is in the view's
+    /// content-addressable store.
+    ///
+    /// See [`Self::cas_write`].
+    #[inline]
+    pub fn cas_contains(&self, hash: &ContentHash) -> bool {
+        self.is_file(hash.path())
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Stores `contents` in a content-addressable store in the view,
+    /// returning their hash, which [`Self::cas_open`] opens them by.
+    ///
+    /// See [`DirView::cas_write`].
+    pub fn cas_write<C: AsRef<[u8]>>(&self, contents: C) -> io::Result<ContentHash> {
+        let contents = contents.as_ref();
+        let hash = ContentHash::of(contents);
+        self.audited(Operation::Write, utf8_path(&hash), |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            cas_write(
+                self.dir.borrow().as_cap_std(),
+                path.as_std_path(),
+                contents,
+                &self.policy,
+            )
+        })?;
+        Ok(hash)
+    }
+
+    /// Opens the blob with `hash` in the view's content-addressable store
+    /// for reading.
+    ///
+    /// See [`DirView::cas_write`].
+    #[inline]
+    pub fn cas_open(&self, hash: &ContentHash) -> io::Result<cap_std::fs_utf8::File> {
+        self.open(utf8_path(hash))
+    }
+
+    /// Returns `true` if the blob with `hash` is in the view's
+    /// content-addressable store.
+    ///
+    /// See [`DirView::cas_write`].
+    #[inline]
+    pub fn cas_contains(&self, hash: &ContentHash) -> bool {
+        self.is_file(utf8_path(hash))
+    }
+}
+
+/// Stores `contents` at `path` in `dir`, unless something's already there.
+fn cas_write(dir: &Dir, path: &Path, contents: &[u8], policy: &Policy) -> io::Result<()> {
+    let (Some(fan), Some(name)) = (path.parent(), path.file_name()) else {
+        unreachable!("content paths have two components")
+    };
+    match dir.create_dir(fan) {
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists => return Err(err),
+        _ => {}
+    }
+    let dir = dir.open_dir(fan)?;
+    if dir.exists(name) {
+        return Ok(());
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    let (mut file, temp_name) = loop {
+        let temp_name = temp_name(name);
+        match dir.open_with(&temp_name, &options) {
+            Ok(file) => break (file, temp_name),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    };
+    let result = file
+        .write_all(contents)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| {
+            policy.account_bytes_written(contents.len());
+            rename_noreplace(&dir, Path::new(&temp_name), &dir, Path::new(name))
+        });
+    match result {
+        Ok(()) => sync_dir(&dir),
+        // Another writer stored the same contents first.
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            dir.remove_file(&temp_name)?;
+            Ok(())
+        }
+        Err(err) => {
+            dir.remove_file(&temp_name).ok();
+            Err(err)
+        }
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+fn utf8_path(hash: &ContentHash) -> String {
+    let hex = hash.to_string();
+    format!("{}/{}", &hex[..2], &hex[2..])
+}
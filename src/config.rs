@@ -0,0 +1,218 @@
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::borrow::Borrow;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// The largest file [`DirView::read_json`] and [`DirView::read_toml`] read,
+/// which is far larger than any reasonable configuration file, but keeps a
+/// stray large file from being read into memory.
+const CONFIG_LIMIT: u64 = 16 * 1024 * 1024;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Reads a JSON file and deserializes it as a `T`.
+    ///
+    /// Files larger than 16 MiB fail with [`io::ErrorKind::FileTooLarge`];
+    /// use [`Self::read_json_limit`] to read larger or smaller ones. Files
+    /// which don't parse fail with [`io::ErrorKind::InvalidData`], with a
+    /// message including `path`.
+    #[cfg(feature = "json")]
+    #[inline]
+    pub fn read_json<T: DeserializeOwned, P: AsRef<Path>>(&self, path: P) -> io::Result<T> {
+        self.read_json_limit(path, CONFIG_LIMIT)
+    }
+
+    /// Reads a JSON file and deserializes it as a `T`, failing with
+    /// [`io::ErrorKind::FileTooLarge`] if it's larger than `max` bytes.
+    ///
+    /// See [`Self::read_json`].
+    #[cfg(feature = "json")]
+    pub fn read_json_limit<T: DeserializeOwned, P: AsRef<Path>>(
+        &self,
+        path: P,
+        max: u64,
+    ) -> io::Result<T> {
+        let path = path.as_ref();
+        let contents = self.read_to_string_limit(path, max)?;
+        serde_json::from_str(&contents).map_err(|err| invalid(path.display(), "JSON", err))
+    }
+
+    /// Serializes `value` as pretty-printed JSON, and writes it as the
+    /// entire contents of a file, atomically replacing any existing file at
+    /// `path`, as with [`Self::write_atomic`].
+    ///
+    /// Values which can't be serialized as JSON fail with
+    /// [`io::ErrorKind::InvalidInput`], before anything is written.
+    #[cfg(feature = "json")]
+    pub fn write_json<T: Serialize + ?Sized, P: AsRef<Path>>(
+        &self,
+        path: P,
+        value: &T,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        self.write_atomic(path, to_json(path.display(), value)?)
+    }
+
+    /// Reads a TOML file and deserializes it as a `T`.
+    ///
+    /// Files larger than 16 MiB fail with [`io::ErrorKind::FileTooLarge`];
+    /// use [`Self::read_toml_limit`] to read larger or smaller ones. Files
+    /// which don't parse fail with [`io::ErrorKind::InvalidData`], with a
+    /// message including `path`.
+    #[cfg(feature = "toml")]
+    #[inline]
+    pub fn read_toml<T: DeserializeOwned, P: AsRef<Path>>(&self, path: P) -> io::Result<T> {
+        self.read_toml_limit(path, CONFIG_LIMIT)
+    }
+
+    /// Reads a TOML file and deserializes it as a `T`, failing with
+    /// [`io::ErrorKind::FileTooLarge`] if it's larger than `max` bytes.
+    ///
+    /// See [`Self::read_toml`].
+    #[cfg(feature = "toml")]
+    pub fn read_toml_limit<T: DeserializeOwned, P: AsRef<Path>>(
+        &self,
+        path: P,
+        max: u64,
+    ) -> io::Result<T> {
+        let path = path.as_ref();
+        let contents = self.read_to_string_limit(path, max)?;
+        toml::from_str(&contents).map_err(|err| invalid(path.display(), "TOML", err))
+    }
+
+    /// Serializes `value` as TOML, and writes it as the entire contents of a
+    /// file, atomically replacing any existing file at `path`, as with
+    /// [`Self::write_atomic`].
+    ///
+    /// Values which can't be serialized as TOML, such as those which aren't
+    /// structs or maps, fail with [`io::ErrorKind::InvalidInput`], before
+    /// anything is written.
+    #[cfg(feature = "toml")]
+    pub fn write_toml<T: Serialize + ?Sized, P: AsRef<Path>>(
+        &self,
+        path: P,
+        value: &T,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        self.write_atomic(path, to_toml(path.display(), value)?)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Reads a JSON file and deserializes it as a `T`.
+    ///
+    /// See [`DirView::read_json`].
+    #[cfg(feature = "json")]
+    #[inline]
+    pub fn read_json<T: DeserializeOwned, P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<T> {
+        self.read_json_limit(path, CONFIG_LIMIT)
+    }
+
+    /// Reads a JSON file and deserializes it as a `T`, failing with
+    /// [`io::ErrorKind::FileTooLarge`] if it's larger than `max` bytes.
+    ///
+    /// See [`DirView::read_json`].
+    #[cfg(feature = "json")]
+    pub fn read_json_limit<T: DeserializeOwned, P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        max: u64,
+    ) -> io::Result<T> {
+        let path = path.as_ref();
+        let contents = self.read_to_string_limit(path, max)?;
+        serde_json::from_str(&contents).map_err(|err| invalid(path, "JSON", err))
+    }
+
+    /// Serializes `value` as pretty-printed JSON, and writes it as the
+    /// entire contents of a file, atomically replacing any existing file at
+    /// `path`.
+    ///
+    /// See [`DirView::write_json`].
+    #[cfg(feature = "json")]
+    pub fn write_json<T: Serialize + ?Sized, P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        value: &T,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        self.write_atomic(path, to_json(path, value)?)
+    }
+
+    /// Reads a TOML file and deserializes it as a `T`.
+    ///
+    /// See [`DirView::read_toml`].
+    #[cfg(feature = "toml")]
+    #[inline]
+    pub fn read_toml<T: DeserializeOwned, P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<T> {
+        self.read_toml_limit(path, CONFIG_LIMIT)
+    }
+
+    /// Reads a TOML file and deserializes it as a `T`, failing with
+    /// [`io::ErrorKind::FileTooLarge`] if it's larger than `max` bytes.
+    ///
+    /// See [`DirView::read_toml`].
+    #[cfg(feature = "toml")]
+    pub fn read_toml_limit<T: DeserializeOwned, P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        max: u64,
+    ) -> io::Result<T> {
+        let path = path.as_ref();
+        let contents = self.read_to_string_limit(path, max)?;
+        toml::from_str(&contents).map_err(|err| invalid(path, "TOML", err))
+    }
+
+    /// Serializes `value` as TOML, and writes it as the entire contents of a
+    /// file, atomically replacing any existing file at `path`.
+    ///
+    /// See [`DirView::write_toml`].
+    #[cfg(feature = "toml")]
+    pub fn write_toml<T: Serialize + ?Sized, P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        value: &T,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        self.write_atomic(path, to_toml(path, value)?)
+    }
+}
+
+/// Returns an error for the file at `path` which isn't valid `format`.
+fn invalid(path: impl fmt::Display, format: &str, err: impl fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("`{path}` isn't valid {format}: {err}"),
+    )
+}
+
+/// Serializes `value`, to be written to `path`, as pretty-printed JSON.
+#[cfg(feature = "json")]
+fn to_json<T: Serialize + ?Sized>(path: impl fmt::Display, value: &T) -> io::Result<String> {
+    let mut json =
+        serde_json::to_string_pretty(value).map_err(|err| unserializable(path, "JSON", err))?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Serializes `value`, to be written to `path`, as TOML.
+#[cfg(feature = "toml")]
+fn to_toml<T: Serialize + ?Sized>(path: impl fmt::Display, value: &T) -> io::Result<String> {
+    toml::to_string(value).map_err(|err| unserializable(path, "TOML", err))
+}
+
+/// Returns an error for a value to be written to `path` which can't be
+/// serialized as `format`.
+fn unserializable(path: impl fmt::Display, format: &str, err: impl fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("can't write `{path}` as {format}: {err}"),
+    )
+}
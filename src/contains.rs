@@ -0,0 +1,297 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8PathBuf;
+use cap_std::fs::Dir;
+use cap_std::io_lifetimes::AsFilelike;
+use std::borrow::Borrow;
+use std::io;
+use std::path::PathBuf;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns `true` if the open file or directory `file` is within the
+    /// view's directory, or is the directory itself.
+    ///
+    /// This lets servers which receive handles from clients, such as over
+    /// Unix-domain sockets, check that a handle is within a tree they serve
+    /// before acting on it.
+    ///
+    /// On Unix, a directory is checked by following `..` links from it up to
+    /// the root of the filesystem, comparing each directory with the view's,
+    /// by device and inode numbers. Other files are first located with the
+    /// platform's facility for getting a handle's path, which on Linux is
+    /// `/proc/self/fd` and on Apple platforms is `F_GETPATH`, and which
+    /// reports just one of the names of a file with several hard links.
+    /// Files which have been removed, and handles without paths, such as
+    /// pipes and sockets, aren't within any directory. On other Unix
+    /// platforms, checking files other than directories isn't supported. On
+    /// Windows, the final paths of the handles are compared.
+    ///
+    /// A file may be moved after it's checked, so this is only meaningful
+    /// for trees which aren't being changed by others.
+    pub fn contains<Filelike: AsFilelike>(&self, file: &Filelike) -> io::Result<bool> {
+        self.audited(Operation::Metadata, ".", |_| {
+            contains(self.dir.borrow(), file)
+        })
+    }
+
+    /// Returns the path of the open file or directory `file`, relative to
+    /// the view's directory, failing with [`io::ErrorKind::NotFound`] if it
+    /// isn't within it.
+    ///
+    /// This is for logging and auditing handles, such as those received from
+    /// clients. The handle's path is found with the platform's facility for
+    /// getting a handle's path, which on Linux is `/proc/self/fd`, on Apple
+    /// platforms is `F_GETPATH`, and on Windows is
+    /// `GetFinalPathNameByHandle`, and which reports just one of the names of
+    /// a file with several hard links. On Unix, the path is then checked by
+    /// comparing what's there in the view with `file`. Elsewhere, this
+    /// isn't supported.
+    ///
+    /// The directory itself is at `.`.
+    pub fn path_of<Filelike: AsFilelike>(&self, file: &Filelike) -> io::Result<PathBuf> {
+        self.audited(Operation::Metadata, ".", |_| {
+            path_of(self.dir.borrow(), file)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns `true` if the open file or directory `file` is within the
+    /// view's directory, or is the directory itself.
+    ///
+    /// See [`DirView::contains`].
+    pub fn contains<Filelike: AsFilelike>(&self, file: &Filelike) -> io::Result<bool> {
+        self.audited(Operation::Metadata, ".", |_| {
+            contains(self.dir.borrow().as_cap_std(), file)
+        })
+    }
+
+    /// Returns the path of the open file or directory `file`, relative to
+    /// the view's directory, failing with [`io::ErrorKind::NotFound`] if it
+    /// isn't within it.
+    ///
+    /// Paths which aren't valid UTF-8 fail with
+    /// [`io::ErrorKind::InvalidData`].
+    ///
+    /// See [`DirView::path_of`].
+    pub fn path_of<Filelike: AsFilelike>(&self, file: &Filelike) -> io::Result<Utf8PathBuf> {
+        self.audited(Operation::Metadata, ".", |_| {
+            let path = path_of(self.dir.borrow().as_cap_std(), file)?;
+            Utf8PathBuf::from_path_buf(path).map_err(|path| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("`{}` isn't valid UTF-8", path.display()),
+                )
+            })
+        })
+    }
+}
+
+#[cfg(unix)]
+fn contains<Filelike: AsFilelike>(root: &Dir, file: &Filelike) -> io::Result<bool> {
+    use rustix::fs::{fstat, openat, Mode, OFlags};
+
+    let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+    let root = fstat(root)?;
+    let root = (root.st_dev, root.st_ino);
+    let file = file.as_filelike();
+    let mut dir = if file
+        .as_filelike_view::<std::fs::File>()
+        .metadata()?
+        .is_dir()
+    {
+        // Reopen the directory, which may be an `O_PATH` descriptor.
+        openat(file, ".", flags, Mode::empty())?
+    } else {
+        match parent_of(file)? {
+            Some(dir) => dir,
+            None => return Ok(false),
+        }
+    };
+    loop {
+        let stat = fstat(&dir)?;
+        let id = (stat.st_dev, stat.st_ino);
+        if id == root {
+            return Ok(true);
+        }
+        let parent = openat(&dir, "..", flags, Mode::empty())?;
+        let stat = fstat(&parent)?;
+        // The root of the filesystem is its own parent.
+        if (stat.st_dev, stat.st_ino) == id {
+            return Ok(false);
+        }
+        dir = parent;
+    }
+}
+
+/// Opens the directory containing `file`, or returns `None` if it isn't in
+/// one, such as if it's been removed.
+#[cfg(unix)]
+fn parent_of(file: std::os::fd::BorrowedFd<'_>) -> io::Result<Option<std::os::fd::OwnedFd>> {
+    use rustix::fs::{fstat, open, statat, AtFlags, Mode, OFlags};
+
+    let path = handle_path(file)?;
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(None);
+    };
+    // Handles without paths have pseudo-paths, such as `pipe:[1234]`.
+    if !path.is_absolute() {
+        return Ok(None);
+    }
+    // This resolves `parent` in the whole filesystem, but only to find the
+    // directory already containing `file`, which is checked by comparing
+    // what's at `name` in it with `file`.
+    let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+    let dir = match open(parent, flags, Mode::empty()) {
+        Ok(dir) => dir,
+        Err(rustix::io::Errno::NOENT) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let stat = fstat(file)?;
+    match statat(&dir, name, AtFlags::SYMLINK_NOFOLLOW) {
+        Ok(found) if (found.st_dev, found.st_ino) == (stat.st_dev, stat.st_ino) => Ok(Some(dir)),
+        Ok(_) | Err(rustix::io::Errno::NOENT) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(unix)]
+fn path_of<Filelike: AsFilelike>(root: &Dir, file: &Filelike) -> io::Result<PathBuf> {
+    use cap_std::fs::MetadataExt;
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::MetadataExt as _;
+
+    let file = file.as_filelike();
+    let root_path = handle_path(root.as_fd())?;
+    let path = relative(&handle_path(file)?, &root_path)?;
+    // Check that the path still leads to `file`.
+    let metadata = file.as_filelike_view::<std::fs::File>().metadata()?;
+    match root.symlink_metadata(&path) {
+        Ok(found) if (found.dev(), found.ino()) == (metadata.dev(), metadata.ino()) => Ok(path),
+        Ok(_) => Err(not_within()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Err(not_within()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(windows)]
+fn path_of<Filelike: AsFilelike>(root: &Dir, file: &Filelike) -> io::Result<PathBuf> {
+    use std::os::windows::io::AsRawHandle;
+
+    let root = final_path(root.as_raw_handle())?;
+    relative(&final_path(file.as_filelike().as_raw_handle())?, &root)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn path_of<Filelike: AsFilelike>(_root: &Dir, _file: &Filelike) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "getting the path of a handle is not supported on this platform",
+    ))
+}
+
+/// Returns `path` relative to `root`, which is `.` for `root` itself.
+#[cfg(any(unix, windows))]
+fn relative(path: &std::path::Path, root: &std::path::Path) -> io::Result<PathBuf> {
+    match path.strip_prefix(root) {
+        Ok(path) if path.as_os_str().is_empty() => Ok(PathBuf::from(".")),
+        Ok(path) => Ok(path.to_owned()),
+        Err(_) => Err(not_within()),
+    }
+}
+
+#[cfg(any(unix, windows))]
+fn not_within() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "the handle isn't within the view")
+}
+
+/// Returns the path of the file open as `file`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn handle_path(file: std::os::fd::BorrowedFd<'_>) -> io::Result<std::path::PathBuf> {
+    use std::os::fd::AsRawFd;
+
+    std::fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd()))
+}
+
+/// Returns the path of the file open as `file`.
+#[cfg(target_vendor = "apple")]
+fn handle_path(file: std::os::fd::BorrowedFd<'_>) -> io::Result<std::path::PathBuf> {
+    use std::ffi::OsString;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStringExt;
+
+    let mut buf = vec![0_u8; libc::PATH_MAX as usize];
+    // SAFETY: `F_GETPATH` writes a NUL-terminated path of at most
+    // `PATH_MAX` bytes to `buf`.
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETPATH, buf.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = buf.iter().position(|byte| *byte == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    Ok(OsString::from_vec(buf).into())
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "linux", target_os = "android", target_vendor = "apple"))
+))]
+fn handle_path(_file: std::os::fd::BorrowedFd<'_>) -> io::Result<std::path::PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "getting the path of a handle is not supported on this platform",
+    ))
+}
+
+#[cfg(windows)]
+fn contains<Filelike: AsFilelike>(root: &Dir, file: &Filelike) -> io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+
+    let root = final_path(root.as_raw_handle())?;
+    let file = final_path(file.as_filelike().as_raw_handle())?;
+    Ok(file.starts_with(root))
+}
+
+/// Returns the normalized final path of the file open as `handle`.
+#[cfg(windows)]
+pub(crate) fn final_path(
+    handle: std::os::windows::io::RawHandle,
+) -> io::Result<std::path::PathBuf> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFinalPathNameByHandleW, FILE_NAME_NORMALIZED,
+    };
+
+    let mut buf = vec![0_u16; 260];
+    loop {
+        // SAFETY: `buf` is a buffer of the given length.
+        let len = unsafe {
+            GetFinalPathNameByHandleW(
+                handle,
+                buf.as_mut_ptr(),
+                buf.len() as u32,
+                FILE_NAME_NORMALIZED,
+            )
+        } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // If the buffer is too small, the length returned includes the NUL.
+        if len < buf.len() {
+            buf.truncate(len);
+            return Ok(OsString::from_wide(&buf).into());
+        }
+        buf.resize(len, 0);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn contains<Filelike: AsFilelike>(_root: &Dir, _file: &Filelike) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "checking whether a file is within a view is not supported on this platform",
+    ))
+}
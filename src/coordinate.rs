@@ -0,0 +1,339 @@
+use crate::same_dir::dir_id;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::{fmt, io};
+
+/// A registry of the files being read and written through views in this
+/// process, set with [`DirViewBuilder::coordinate`].
+///
+/// Views sharing a coordinator can take shared and exclusive intent locks on
+/// files with [`DirView::read_guard`] and [`DirView::write_guard`], which
+/// exclude each other even when the views are of different directories or
+/// reach the file through different paths, as files are identified by the
+/// device and inode of the directory containing them. Unlike
+/// [`DirView::lock_exclusive`], this doesn't open the file, and works on
+/// filesystems without advisory locks, but only coordinates with views
+/// sharing the same coordinator.
+///
+/// ```
+/// # use dir_view::{Coordinator, DirViewBuilder};
+/// # let dir = cap_std::fs::Dir::open_ambient_dir(".", cap_std::ambient_authority())?;
+/// let coordinator = Coordinator::new();
+/// let plugin_a = DirViewBuilder::new().coordinate(&coordinator).build(&dir);
+/// let plugin_b = DirViewBuilder::new().coordinate(&coordinator).build(&dir);
+/// let guard = plugin_a.write_guard("config.toml")?;
+/// assert!(plugin_b.try_write_guard("config.toml")?.is_none());
+/// drop(guard);
+/// assert!(plugin_b.try_write_guard("config.toml")?.is_some());
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// [`DirViewBuilder::coordinate`]: crate::DirViewBuilder::coordinate
+#[derive(Clone, Default)]
+pub struct Coordinator {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    held: Mutex<HashMap<Key, Holders>>,
+    released: Condvar,
+}
+
+/// A file, identified by the directory containing it and its name within
+/// it, or a directory with no name, identified by itself.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+struct Key {
+    dev: u64,
+    ino: u128,
+    name: Option<OsString>,
+}
+
+#[derive(Default)]
+struct Holders {
+    readers: usize,
+    writer: bool,
+}
+
+impl Coordinator {
+    /// Constructs a new coordinator, with no files guarded.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of files currently guarded.
+    pub fn guarded_files(&self) -> usize {
+        self.inner.held.lock().unwrap().len()
+    }
+
+    /// Takes a guard on `key`, or returns `None` if it's held in a
+    /// conflicting way and `wait` is false.
+    fn acquire(&self, key: Key, exclusive: bool, wait: bool) -> Option<IntentGuard> {
+        let mut held = self.inner.held.lock().unwrap();
+        loop {
+            let holders = held.entry(key.clone()).or_default();
+            let available = if exclusive {
+                !holders.writer && holders.readers == 0
+            } else {
+                !holders.writer
+            };
+            if available {
+                if exclusive {
+                    holders.writer = true;
+                } else {
+                    holders.readers += 1;
+                }
+                break;
+            }
+            if !wait {
+                return None;
+            }
+            held = self.inner.released.wait(held).unwrap();
+        }
+        Some(IntentGuard {
+            inner: Some((Arc::clone(&self.inner), key)),
+            exclusive,
+        })
+    }
+}
+
+impl fmt::Debug for Coordinator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coordinator")
+            .field("guarded_files", &self.guarded_files())
+            .finish()
+    }
+}
+
+/// A shared or exclusive intent lock on a file, which is released when
+/// dropped.
+///
+/// This is returned by [`DirView::read_guard`], [`DirView::write_guard`],
+/// and their `try_` variants.
+pub struct IntentGuard {
+    /// The coordinator and the file, or `None` if the view had no
+    /// coordinator.
+    inner: Option<(Arc<Inner>, Key)>,
+    exclusive: bool,
+}
+
+impl IntentGuard {
+    /// Returns `true` if the guard is exclusive.
+    #[inline]
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+}
+
+impl Drop for IntentGuard {
+    fn drop(&mut self) {
+        let Some((inner, key)) = self.inner.take() else {
+            return;
+        };
+        let mut held = inner.held.lock().unwrap();
+        if let Some(holders) = held.get_mut(&key) {
+            if self.exclusive {
+                holders.writer = false;
+            } else {
+                holders.readers -= 1;
+            }
+            if !holders.writer && holders.readers == 0 {
+                held.remove(&key);
+            }
+        }
+        inner.released.notify_all();
+    }
+}
+
+impl fmt::Debug for IntentGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntentGuard")
+            .field("exclusive", &self.exclusive)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Takes an exclusive intent lock on the file at `path` in the view's
+    /// [`Coordinator`], blocking until no other guard on it is held.
+    ///
+    /// The guard doesn't prevent anything by itself; it only excludes other
+    /// guards. Views without a coordinator grant guards immediately, so
+    /// code can take them unconditionally. The file doesn't need to exist,
+    /// but the directory containing it does.
+    ///
+    /// This is denied on readonly views.
+    pub fn write_guard<P: AsRef<Path>>(&self, path: P) -> io::Result<IntentGuard> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            guard(
+                self.dir.borrow(),
+                &self.policy.coordinator,
+                path,
+                true,
+                true,
+            )
+            .map(Option::unwrap)
+        })
+    }
+
+    /// Takes a shared intent lock on the file at `path` in the view's
+    /// [`Coordinator`], blocking until no exclusive guard on it is held.
+    ///
+    /// See [`Self::write_guard`].
+    pub fn read_guard<P: AsRef<Path>>(&self, path: P) -> io::Result<IntentGuard> {
+        self.audited(Operation::Lock, path, |path| {
+            guard(
+                self.dir.borrow(),
+                &self.policy.coordinator,
+                path,
+                false,
+                true,
+            )
+            .map(Option::unwrap)
+        })
+    }
+
+    /// Like [`Self::write_guard`], but returns `None` instead of blocking if
+    /// another guard on the file is held.
+    pub fn try_write_guard<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<IntentGuard>> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            guard(
+                self.dir.borrow(),
+                &self.policy.coordinator,
+                path,
+                true,
+                false,
+            )
+        })
+    }
+
+    /// Like [`Self::read_guard`], but returns `None` instead of blocking if
+    /// an exclusive guard on the file is held.
+    pub fn try_read_guard<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<IntentGuard>> {
+        self.audited(Operation::Lock, path, |path| {
+            guard(
+                self.dir.borrow(),
+                &self.policy.coordinator,
+                path,
+                false,
+                false,
+            )
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Takes an exclusive intent lock on the file at `path` in the view's
+    /// [`Coordinator`], blocking until no other guard on it is held.
+    ///
+    /// See [`DirView::write_guard`].
+    pub fn write_guard<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<IntentGuard> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            guard(
+                self.dir.borrow().as_cap_std(),
+                &self.policy.coordinator,
+                path.as_std_path(),
+                true,
+                true,
+            )
+            .map(Option::unwrap)
+        })
+    }
+
+    /// Takes a shared intent lock on the file at `path` in the view's
+    /// [`Coordinator`], blocking until no exclusive guard on it is held.
+    ///
+    /// See [`DirView::read_guard`].
+    pub fn read_guard<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<IntentGuard> {
+        self.audited(Operation::Lock, path, |path| {
+            guard(
+                self.dir.borrow().as_cap_std(),
+                &self.policy.coordinator,
+                path.as_std_path(),
+                false,
+                true,
+            )
+            .map(Option::unwrap)
+        })
+    }
+
+    /// Like [`Self::write_guard`], but returns `None` instead of blocking if
+    /// another guard on the file is held.
+    pub fn try_write_guard<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<IntentGuard>> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            guard(
+                self.dir.borrow().as_cap_std(),
+                &self.policy.coordinator,
+                path.as_std_path(),
+                true,
+                false,
+            )
+        })
+    }
+
+    /// Like [`Self::read_guard`], but returns `None` instead of blocking if
+    /// an exclusive guard on the file is held.
+    pub fn try_read_guard<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<IntentGuard>> {
+        self.audited(Operation::Lock, path, |path| {
+            guard(
+                self.dir.borrow().as_cap_std(),
+                &self.policy.coordinator,
+                path.as_std_path(),
+                false,
+                false,
+            )
+        })
+    }
+}
+
+/// Takes a guard on `path` in `dir` from `coordinator`, if there is one,
+/// returning `None` if it's held in a conflicting way and `wait` is false.
+fn guard(
+    dir: &Dir,
+    coordinator: &Option<Coordinator>,
+    path: &Path,
+    exclusive: bool,
+    wait: bool,
+) -> io::Result<Option<IntentGuard>> {
+    let Some(coordinator) = coordinator else {
+        return Ok(Some(IntentGuard {
+            inner: None,
+            exclusive,
+        }));
+    };
+    let key = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let (dev, ino) = dir_id(dir, parent)?;
+            Key {
+                dev,
+                ino,
+                name: Some(name.to_owned()),
+            }
+        }
+        _ => {
+            let (dev, ino) = dir_id(dir, path)?;
+            Key {
+                dev,
+                ino,
+                name: None,
+            }
+        }
+    };
+    Ok(coordinator.acquire(key, exclusive, wait))
+}
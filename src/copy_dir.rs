@@ -0,0 +1,654 @@
+use crate::progress::{Monitor, Tracker};
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "cap-fs-ext")]
+use crate::ViewKind;
+use crate::{CancellationToken, DirView, Progress};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+#[cfg(feature = "cap-fs-ext")]
+use cap_fs_ext::SystemTimeSpec;
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs::{Dir, FileType, Metadata};
+use std::borrow::Borrow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::{io, thread};
+
+/// Options for [`DirView::copy_with`].
+#[derive(Clone, Debug, Default)]
+pub struct CopyOptions {
+    pub(crate) monitor: Monitor,
+}
+
+impl CopyOptions {
+    /// Creates a blank new set of options ready for configuration.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a callback to call with the progress of the copy, as each chunk
+    /// of the file is copied.
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.monitor.progress(progress);
+        self
+    }
+
+    /// Sets a token which can cancel the copy, which then fails with a
+    /// [`Cancelled`] error.
+    ///
+    /// [`Cancelled`]: crate::Cancelled
+    #[inline]
+    pub fn cancel(&mut self, token: CancellationToken) -> &mut Self {
+        self.monitor.cancel(token);
+        self
+    }
+}
+
+/// Options for [`DirView::copy_dir_all_with`].
+#[derive(Clone, Debug)]
+pub struct CopyDirOptions {
+    symlinks: CopySymlinks,
+    concurrency: usize,
+    monitor: Monitor,
+}
+
+/// How [`DirView::copy_dir_all_with`] copies symbolic links.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CopySymlinks {
+    /// Create symbolic links with the same contents in the copy.
+    Preserve,
+
+    /// Copy the files and directories that symbolic links point to, resolved
+    /// within the source view.
+    Follow,
+
+    /// Leave symbolic links out of the copy.
+    Skip,
+}
+
+impl CopyDirOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Symbolic links are initially preserved, and files are copied one at
+    /// a time.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            symlinks: CopySymlinks::Preserve,
+            concurrency: 1,
+            monitor: Monitor::default(),
+        }
+    }
+
+    /// Sets how symbolic links are copied.
+    #[inline]
+    pub fn symlinks(&mut self, symlinks: CopySymlinks) -> &mut Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Sets the number of files to copy at once, on separate threads.
+    ///
+    /// With more than one, the directory tree is created first, then the
+    /// files are copied into it, and then the directories' permissions and
+    /// timestamps are set. Progress is reported for the files copied by all
+    /// of the threads together.
+    #[inline]
+    pub fn concurrency(&mut self, concurrency: usize) -> &mut Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets a callback to call with the progress of the copy, as each file,
+    /// or chunk of a large file, is copied.
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.monitor.progress(progress);
+        self
+    }
+
+    /// Sets a token which can cancel the copy, which then fails with a
+    /// [`Cancelled`] error, leaving the entries copied so far in place.
+    ///
+    /// [`Cancelled`]: crate::Cancelled
+    #[inline]
+    pub fn cancel(&mut self, token: CancellationToken) -> &mut Self {
+        self.monitor.cancel(token);
+        self
+    }
+}
+
+impl Default for CopyDirOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Recursively copies the directory at `from`, and its contents, to a new
+    /// directory at `to` in `to_dir`.
+    ///
+    /// Permissions are copied, and with the `cap-fs-ext` feature, so are
+    /// timestamps. Symbolic links are preserved. This fails before copying
+    /// anything if `to` already exists or if `to_dir` doesn't permit changes.
+    /// If `to` is within `from`, it's left out of the copy.
+    ///
+    /// The copy is made through the views, so their restrictions apply to
+    /// every file copied, and each step is reported to their audit hooks.
+    #[inline]
+    pub fn copy_dir_all<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &DirView<E>,
+        to: Q,
+    ) -> io::Result<()> {
+        self.copy_dir_all_with(from, to_dir, to, &CopyDirOptions::new())
+    }
+
+    /// Recursively copies the directory at `from`, and its contents, to a new
+    /// directory at `to` in `to_dir`, with the given options.
+    ///
+    /// See [`Self::copy_dir_all`] for details.
+    pub fn copy_dir_all_with<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &DirView<E>,
+        to: Q,
+        options: &CopyDirOptions,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::CopyDir, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            let (from, to) = (from, to.as_ref());
+            to_dir.check_change(Operation::CopyDir, to)?;
+            if self.dry_run_pair(to_dir, Operation::CopyDir, from, to)? {
+                return Ok(());
+            }
+            let mut tracker = Tracker::new(&options.monitor);
+            let mut deferred = (options.concurrency > 1).then(Deferred::default);
+            self.copy_tree(
+                from,
+                to_dir,
+                to,
+                options,
+                &mut tracker,
+                &mut Descent::default(),
+                deferred.as_mut(),
+            )?;
+            match deferred {
+                Some(deferred) => {
+                    deferred.finish(&self.try_clone()?, &to_dir.try_clone()?, options)
+                }
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Copies the whole tree of the view to a new directory at `to` in
+    /// `to_dir`, as a point-in-time snapshot.
+    ///
+    /// Where the filesystem supports it, files are cloned rather than
+    /// copied, sharing their data with the originals until either is
+    /// changed, so that snapshots are nearly free. This uses `FICLONE` on
+    /// Linux and `clonefile` on macOS, and falls back to copying files where
+    /// cloning isn't supported, such as between filesystems. Otherwise, this
+    /// is like [`Self::copy_dir_all`], so `to` may be within the view, such
+    /// as in a directory of snapshots, and is left out of the snapshot.
+    #[inline]
+    pub fn snapshot_to<E: Borrow<Dir>, P: AsRef<Path>>(
+        &self,
+        to_dir: &DirView<E>,
+        to: P,
+    ) -> io::Result<()> {
+        self.copy_dir_all(".", to_dir, to)
+    }
+
+    /// Copies the directory at `from`. With `deferred`, the files in it
+    /// are recorded there to copy later, instead of being copied.
+    #[allow(clippy::too_many_arguments)]
+    fn copy_tree<E: Borrow<Dir>>(
+        &self,
+        from: &Path,
+        to_dir: &DirView<E>,
+        to: &Path,
+        options: &CopyDirOptions,
+        tracker: &mut Tracker<'_>,
+        descent: &mut Descent,
+        mut deferred: Option<&mut Deferred>,
+    ) -> io::Result<()> {
+        let metadata = self.metadata(from)?;
+        if descent.is_dest(&metadata) {
+            return Ok(());
+        }
+        enter(&mut descent.ancestors, &metadata)?;
+        to_dir.create_dir(to)?;
+        if descent.ancestors.len() == 1 {
+            descent.dest = dir_id(&to_dir.dir.borrow().metadata(to)?);
+        }
+        for entry in self.read_dir(from)? {
+            tracker.check()?;
+            let entry = entry?;
+            let name = entry.file_name();
+            let (from, to) = (from.join(&name), to.join(&name));
+            let mut file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                match options.symlinks {
+                    CopySymlinks::Preserve => {
+                        self.copy_symlink(&from, to_dir, &to)?;
+                        continue;
+                    }
+                    CopySymlinks::Follow => file_type = self.metadata(&from)?.file_type(),
+                    CopySymlinks::Skip => continue,
+                }
+            }
+            if file_type.is_dir() {
+                self.copy_tree(
+                    &from,
+                    to_dir,
+                    &to,
+                    options,
+                    tracker,
+                    descent,
+                    deferred.as_deref_mut(),
+                )?;
+            } else {
+                check_copyable(file_type, &from)?;
+                if let Some(deferred) = deferred.as_deref_mut() {
+                    deferred.files.push((from, to));
+                    continue;
+                }
+                self.copy_tracked(&from, to_dir, &to, tracker)?;
+                #[cfg(feature = "cap-fs-ext")]
+                to_dir.copy_times(&to, &self.metadata(&from)?)?;
+            }
+        }
+        descent.ancestors.pop();
+        if let Some(deferred) = deferred {
+            deferred.dirs.push((to.to_path_buf(), metadata));
+            return Ok(());
+        }
+        #[cfg(not(target_os = "wasi"))]
+        to_dir.set_permissions(to, metadata.permissions())?;
+        #[cfg(feature = "cap-fs-ext")]
+        to_dir.copy_times(to, &metadata)?;
+        Ok(())
+    }
+
+    /// Sets the timestamps of `path` to those in `metadata`.
+    ///
+    /// This accompanies other changes, so dry-run views don't record it.
+    #[cfg(feature = "cap-fs-ext")]
+    pub(crate) fn copy_times(&self, path: &Path, metadata: &Metadata) -> io::Result<()> {
+        self.audited(Operation::SetTimes, path, |path| {
+            self.check_change(Operation::SetTimes, path)?;
+            if self.kind_for(Operation::SetTimes, path) == ViewKind::DryRun {
+                return Ok(());
+            }
+            let (atime, mtime) = times(metadata);
+            cap_fs_ext::DirExt::set_times(self.dir.borrow(), path, atime, mtime)
+        })
+    }
+
+    #[cfg(not(windows))]
+    pub(crate) fn copy_symlink<E: Borrow<Dir>>(
+        &self,
+        from: &Path,
+        to_dir: &DirView<E>,
+        to: &Path,
+    ) -> io::Result<()> {
+        to_dir.symlink_contents(self.read_link_contents(from)?, to)
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn copy_symlink<E: Borrow<Dir>>(
+        &self,
+        from: &Path,
+        to_dir: &DirView<E>,
+        to: &Path,
+    ) -> io::Result<()> {
+        let original = self.read_link(from)?;
+        if self.is_dir(from) {
+            to_dir.symlink_dir(original, to)
+        } else {
+            to_dir.symlink_file(original, to)
+        }
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Recursively copies the directory at `from`, and its contents, to a new
+    /// directory at `to` in `to_dir`.
+    ///
+    /// Permissions are copied, and with the `cap-fs-ext` feature, so are
+    /// timestamps. Symbolic links are preserved. This fails before copying
+    /// anything if `to` already exists or if `to_dir` doesn't permit changes.
+    /// If `to` is within `from`, it's left out of the copy.
+    ///
+    /// The copy is made through the views, so their restrictions apply to
+    /// every file copied, and each step is reported to their audit hooks.
+    #[inline]
+    pub fn copy_dir_all<
+        E: Borrow<cap_std::fs_utf8::Dir>,
+        P: AsRef<Utf8Path>,
+        Q: AsRef<Utf8Path>,
+    >(
+        &self,
+        from: P,
+        to_dir: &DirViewUtf8<E>,
+        to: Q,
+    ) -> io::Result<()> {
+        self.copy_dir_all_with(from, to_dir, to, &CopyDirOptions::new())
+    }
+
+    /// Recursively copies the directory at `from`, and its contents, to a new
+    /// directory at `to` in `to_dir`, with the given options.
+    ///
+    /// See [`Self::copy_dir_all`] for details.
+    pub fn copy_dir_all_with<
+        E: Borrow<cap_std::fs_utf8::Dir>,
+        P: AsRef<Utf8Path>,
+        Q: AsRef<Utf8Path>,
+    >(
+        &self,
+        from: P,
+        to_dir: &DirViewUtf8<E>,
+        to: Q,
+        options: &CopyDirOptions,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::CopyDir, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            let (from, to) = (from, to.as_ref());
+            to_dir.check_change(Operation::CopyDir, to)?;
+            if self.dry_run_pair(to_dir, Operation::CopyDir, from, to)? {
+                return Ok(());
+            }
+            let mut tracker = Tracker::new(&options.monitor);
+            let mut deferred = (options.concurrency > 1).then(Deferred::default);
+            self.copy_tree(
+                from,
+                to_dir,
+                to,
+                options,
+                &mut tracker,
+                &mut Descent::default(),
+                deferred.as_mut(),
+            )?;
+            match deferred {
+                Some(deferred) => deferred.finish(
+                    &self.try_clone()?.into_cap_std_view(),
+                    &to_dir.try_clone()?.into_cap_std_view(),
+                    options,
+                ),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Copies the whole tree of the view to a new directory at `to` in
+    /// `to_dir`, as a point-in-time snapshot.
+    ///
+    /// See [`DirView::snapshot_to`].
+    #[inline]
+    pub fn snapshot_to<E: Borrow<cap_std::fs_utf8::Dir>, P: AsRef<Utf8Path>>(
+        &self,
+        to_dir: &DirViewUtf8<E>,
+        to: P,
+    ) -> io::Result<()> {
+        self.copy_dir_all(".", to_dir, to)
+    }
+
+    /// Copies the directory at `from`. With `deferred`, the files in it
+    /// are recorded there to copy later, instead of being copied.
+    #[allow(clippy::too_many_arguments)]
+    fn copy_tree<E: Borrow<cap_std::fs_utf8::Dir>>(
+        &self,
+        from: &Utf8Path,
+        to_dir: &DirViewUtf8<E>,
+        to: &Utf8Path,
+        options: &CopyDirOptions,
+        tracker: &mut Tracker<'_>,
+        descent: &mut Descent,
+        mut deferred: Option<&mut Deferred>,
+    ) -> io::Result<()> {
+        let metadata = self.metadata(from)?;
+        if descent.is_dest(&metadata) {
+            return Ok(());
+        }
+        enter(&mut descent.ancestors, &metadata)?;
+        to_dir.create_dir(to)?;
+        if descent.ancestors.len() == 1 {
+            descent.dest = dir_id(&to_dir.dir.borrow().metadata(to)?);
+        }
+        for entry in self.read_dir(from)? {
+            tracker.check()?;
+            let entry = entry?;
+            let name = entry.file_name()?;
+            let (from, to) = (from.join(&name), to.join(&name));
+            let mut file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                match options.symlinks {
+                    CopySymlinks::Preserve => {
+                        self.copy_symlink(&from, to_dir, &to)?;
+                        continue;
+                    }
+                    CopySymlinks::Follow => file_type = self.metadata(&from)?.file_type(),
+                    CopySymlinks::Skip => continue,
+                }
+            }
+            if file_type.is_dir() {
+                self.copy_tree(
+                    &from,
+                    to_dir,
+                    &to,
+                    options,
+                    tracker,
+                    descent,
+                    deferred.as_deref_mut(),
+                )?;
+            } else {
+                check_copyable(file_type, from.as_std_path())?;
+                if let Some(deferred) = deferred.as_deref_mut() {
+                    deferred.files.push((from.into(), to.into()));
+                    continue;
+                }
+                self.copy_tracked(&from, to_dir, &to, tracker)?;
+                #[cfg(feature = "cap-fs-ext")]
+                to_dir.audited(Operation::SetTimes, &to, |to| {
+                    let (atime, mtime) = times(&self.metadata(&from)?);
+                    cap_fs_ext::DirExtUtf8::set_times(to_dir.dir.borrow(), to, atime, mtime)
+                })?;
+            }
+        }
+        descent.ancestors.pop();
+        if let Some(deferred) = deferred {
+            deferred.dirs.push((to.into(), metadata));
+            return Ok(());
+        }
+        #[cfg(not(target_os = "wasi"))]
+        to_dir.set_permissions(to, metadata.permissions())?;
+        #[cfg(feature = "cap-fs-ext")]
+        to_dir.audited(Operation::SetTimes, to, |to| {
+            let (atime, mtime) = times(&metadata);
+            cap_fs_ext::DirExtUtf8::set_times(to_dir.dir.borrow(), to, atime, mtime)
+        })?;
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    fn copy_symlink<E: Borrow<cap_std::fs_utf8::Dir>>(
+        &self,
+        from: &Utf8Path,
+        to_dir: &DirViewUtf8<E>,
+        to: &Utf8Path,
+    ) -> io::Result<()> {
+        to_dir.symlink_contents(self.read_link_contents(from)?, to)
+    }
+
+    #[cfg(windows)]
+    fn copy_symlink<E: Borrow<cap_std::fs_utf8::Dir>>(
+        &self,
+        from: &Utf8Path,
+        to_dir: &DirViewUtf8<E>,
+        to: &Utf8Path,
+    ) -> io::Result<()> {
+        let original = self.read_link(from)?;
+        if self.is_dir(from) {
+            to_dir.symlink_dir(original, to)
+        } else {
+            to_dir.symlink_file(original, to)
+        }
+    }
+}
+
+/// The directories a recursive copy is within.
+#[derive(Default)]
+struct Descent {
+    /// The source directories being copied, to detect loops through symbolic
+    /// links.
+    ancestors: Vec<(u64, u64)>,
+
+    /// The directory being copied into, which is left out of the copy if
+    /// it's within the source.
+    dest: Option<(u64, u64)>,
+}
+
+impl Descent {
+    /// Tests whether `metadata` is that of the directory being copied into.
+    fn is_dest(&self, metadata: &Metadata) -> bool {
+        self.dest.is_some() && self.dest == dir_id(metadata)
+    }
+}
+
+/// The parts of a recursive copy left until the directory tree exists, so
+/// that files can be copied concurrently.
+#[derive(Default)]
+struct Deferred {
+    /// The files to copy, from the source view to the destination view.
+    files: Vec<(PathBuf, PathBuf)>,
+
+    /// The directories whose permissions and timestamps are set once the
+    /// files are copied, each before its parent.
+    dirs: Vec<(PathBuf, Metadata)>,
+}
+
+impl Deferred {
+    fn finish(self, from: &DirView, to: &DirView, options: &CopyDirOptions) -> io::Result<()> {
+        copy_files(from, to, self.files, options.concurrency, &options.monitor)?;
+        for (path, metadata) in self.dirs {
+            #[cfg(not(target_os = "wasi"))]
+            to.set_permissions(&path, metadata.permissions())?;
+            #[cfg(feature = "cap-fs-ext")]
+            to.copy_times(&path, &metadata)?;
+            #[cfg(all(target_os = "wasi", not(feature = "cap-fs-ext")))]
+            let _ = (path, metadata);
+        }
+        Ok(())
+    }
+}
+
+/// Copies each pair of paths in `files` from `from` to `to`, along with
+/// their timestamps, copying up to `concurrency` at once.
+///
+/// After a failure, no more files are started, and the first error is
+/// returned.
+pub(crate) fn copy_files(
+    from: &DirView,
+    to: &DirView,
+    files: Vec<(PathBuf, PathBuf)>,
+    concurrency: usize,
+    monitor: &Monitor,
+) -> io::Result<()> {
+    let threads = concurrency.clamp(1, files.len().max(1));
+    let files = Mutex::new(files.into_iter());
+    let progress = Mutex::default();
+    let failed = AtomicBool::new(false);
+    let error = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let mut tracker = Tracker::shared(monitor, &progress);
+                while !failed.load(Ordering::Relaxed) {
+                    let Some((from_path, to_path)) = files.lock().unwrap().next() else {
+                        break;
+                    };
+                    let result = tracker.check().and_then(|()| {
+                        from.copy_tracked(&from_path, to, &to_path, &mut tracker)?;
+                        #[cfg(feature = "cap-fs-ext")]
+                        to.copy_times(&to_path, &from.metadata(&from_path)?)?;
+                        Ok(())
+                    });
+                    if let Err(err) = result {
+                        failed.store(true, Ordering::Relaxed);
+                        error.lock().unwrap().get_or_insert(err);
+                    }
+                }
+            });
+        }
+    });
+    match error.into_inner().unwrap() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Records entering the directory with `metadata`, failing if it's already
+/// being visited, which can happen when following symbolic links.
+#[cfg(unix)]
+pub(crate) fn enter(ancestors: &mut Vec<(u64, u64)>, metadata: &Metadata) -> io::Result<()> {
+    let id = (metadata.dev(), metadata.ino());
+    if ancestors.contains(&id) {
+        return Err(io::Error::other(
+            "symbolic link to a directory containing it",
+        ));
+    }
+    ancestors.push(id);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn enter(_ancestors: &mut Vec<(u64, u64)>, _metadata: &Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+/// Returns the device and inode of `metadata`, where they're available.
+#[cfg(unix)]
+fn dir_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Fails if `file_type` is something other than a regular file, which
+/// [`std::fs::copy`] would copy the contents of, or block on in the case of
+/// a FIFO.
+pub(crate) fn check_copyable(file_type: FileType, path: &Path) -> io::Result<()> {
+    if file_type.is_file() {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("can't copy special file `{}`", path.display()),
+    ))
+}
+
+#[cfg(feature = "cap-fs-ext")]
+fn times(metadata: &Metadata) -> (Option<SystemTimeSpec>, Option<SystemTimeSpec>) {
+    (
+        metadata.accessed().ok().map(SystemTimeSpec::Absolute),
+        metadata.modified().ok().map(SystemTimeSpec::Absolute),
+    )
+}
@@ -0,0 +1,478 @@
+use crate::archive::malformed;
+use crate::inflate::Inflate;
+use crate::policy::Policy;
+use crate::zip::Crc32;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+#[cfg(feature = "xz")]
+use lzma_rust2::XzReader;
+#[cfg(feature = "zstd")]
+use ruzstd::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
+#[cfg(feature = "zstd")]
+use ruzstd::decoding::{BlockDecodingStrategy, FrameDecoder};
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fmt, mem};
+
+/// The magic number at the start of gzip streams.
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// The magic number at the start of Zstandard frames.
+const ZSTD_MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+
+/// The magic number at the start of xz streams.
+const XZ_MAGIC: &[u8] = b"\xfd7zXZ\x00";
+
+/// A reader of a file opened with [`DirView::open_decompressed`], which
+/// yields its decompressed contents.
+pub struct Decompressed {
+    inner: Inner,
+}
+
+enum Inner {
+    Plain(File),
+    Gzip(Box<Gunzip<BufReader<Accounted>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(Box<Unzstd<BufReader<Accounted>>>),
+    #[cfg(feature = "xz")]
+    Xz(Box<Unxz<BufReader<Accounted>>>),
+}
+
+impl Decompressed {
+    /// Returns `true` if the file was compressed, and is being decompressed.
+    #[inline]
+    pub fn is_decompressed(&self) -> bool {
+        !matches!(self.inner, Inner::Plain(_))
+    }
+}
+
+impl Read for Decompressed {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Plain(file) => file.read(buf),
+            Inner::Gzip(gunzip) => gunzip.read(buf),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(unzstd) => unzstd.read(buf),
+            #[cfg(feature = "xz")]
+            Inner::Xz(unxz) => unxz.read(buf),
+        }
+    }
+}
+
+impl fmt::Debug for Decompressed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match &self.inner {
+            Inner::Plain(file) => return f.debug_tuple("Decompressed").field(file).finish(),
+            Inner::Gzip(gunzip) => &gunzip.path,
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(unzstd) => &unzstd.path,
+            #[cfg(feature = "xz")]
+            Inner::Xz(unxz) => &unxz.path,
+        };
+        f.debug_struct("Decompressed")
+            .field("path", path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Opens a file for reading, decompressing it if it's compressed.
+    ///
+    /// Compressed files are detected by their magic numbers, or by their
+    /// extensions for files which have the extension of a compressed format
+    /// but not its magic number, which fail as corrupt. Files which aren't
+    /// compressed are read as they are.
+    ///
+    /// Gzip files (`.gz`), and with the `zstd` and `xz` features, Zstandard
+    /// (`.zst`) and xz (`.xz`) files, are decompressed as they're read.
+    /// They fail with [`io::ErrorKind::InvalidData`] if they're corrupt,
+    /// including when a checksum doesn't match once the data it covers has
+    /// been read. Zstandard and xz files fail to open with
+    /// [`io::ErrorKind::Unsupported`] without their features.
+    ///
+    /// This only reads, so it's permitted on readonly views.
+    #[inline]
+    pub fn open_decompressed<P: AsRef<Path>>(&self, path: P) -> io::Result<Decompressed> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            decompressed(self.dir.borrow().open(path)?, path, &self.policy)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Opens a file for reading, decompressing it if it's compressed.
+    ///
+    /// See [`DirView::open_decompressed`].
+    #[inline]
+    pub fn open_decompressed<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Decompressed> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            let file = self.dir.borrow().as_cap_std().open(path)?;
+            decompressed(file, path.as_std_path(), &self.policy)
+        })
+    }
+}
+
+/// Returns a reader of the decompressed contents of `file`, at `path`.
+fn decompressed(mut file: File, path: &Path, policy: &Arc<Policy>) -> io::Result<Decompressed> {
+    let mut magic = [0; 6];
+    let magic_len = read_up_to(&mut file, &mut magic)?;
+    let magic = &magic[..magic_len];
+    let extension = path.extension().and_then(OsStr::to_str);
+    file.rewind()?;
+
+    let gzip = magic.starts_with(GZIP_MAGIC) || extension == Some("gz");
+    let zstd = magic.starts_with(ZSTD_MAGIC) || extension == Some("zst");
+    let xz = magic.starts_with(XZ_MAGIC) || extension == Some("xz");
+    if !(gzip || zstd || xz) {
+        return Ok(Decompressed {
+            inner: Inner::Plain(file),
+        });
+    }
+    let input = BufReader::new(Accounted {
+        file,
+        policy: Arc::clone(policy),
+    });
+    let inner = if gzip {
+        Inner::Gzip(Box::new(Gunzip::new(input, path)?))
+    } else if zstd {
+        #[cfg(feature = "zstd")]
+        {
+            Inner::Zstd(Box::new(Unzstd::new(input, path)?))
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err(unsupported(path, "Zstandard", "zstd"));
+    } else {
+        if !magic.starts_with(XZ_MAGIC) {
+            return Err(invalid(path, "xz", malformed("bad magic number")));
+        }
+        #[cfg(feature = "xz")]
+        {
+            Inner::Xz(Box::new(Unxz {
+                reader: XzReader::new(input, true),
+                path: path.to_path_buf(),
+            }))
+        }
+        #[cfg(not(feature = "xz"))]
+        return Err(unsupported(path, "xz", "xz"));
+    };
+    Ok(Decompressed { inner })
+}
+
+/// Returns the error for a file compressed in a format which is only
+/// supported with `feature`.
+#[cfg(any(not(feature = "zstd"), not(feature = "xz")))]
+fn unsupported(path: &Path, format: &str, feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "`{}` is {format}-compressed, which requires the `{feature}` feature",
+            path.display()
+        ),
+    )
+}
+
+/// Reads into `buf` until it's full or the file ends, returning the number
+/// of bytes read.
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
+
+/// A file whose reads are counted against its view's stats and rate limit.
+struct Accounted {
+    file: File,
+    policy: Arc<Policy>,
+}
+
+impl Read for Accounted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.file.read(buf)?;
+        self.policy.account_bytes_read(len);
+        Ok(len)
+    }
+}
+
+/// A reader of the decompressed contents of a gzip stream, which may
+/// consist of several members, as produced by concatenating gzip files.
+struct Gunzip<R> {
+    inflate: Inflate<R>,
+
+    /// The path of the file, for error messages.
+    path: PathBuf,
+
+    /// The checksum and length, modulo 2^32, of the current member's
+    /// contents so far.
+    checksum: Crc32,
+    len: u32,
+
+    /// Whether the last member has been read.
+    done: bool,
+}
+
+impl<R: BufRead> Gunzip<R> {
+    /// Reads the header of the first member of `input`.
+    fn new(mut input: R, path: &Path) -> io::Result<Self> {
+        let path = path.to_path_buf();
+        if let Err(err) = header(&mut input) {
+            return Err(invalid(&path, "gzip", err));
+        }
+        Ok(Self {
+            inflate: Inflate::new(input),
+            path,
+            checksum: Crc32::new(),
+            len: 0,
+            done: false,
+        })
+    }
+
+    /// Checks the trailer of the member which just ended, and starts the
+    /// next one, if there is one.
+    fn next_member(&mut self) -> io::Result<()> {
+        let input = self.inflate.get_mut();
+        let mut trailer = [0; 8];
+        input.read_exact(&mut trailer).map_err(eof_as_truncated)?;
+        let checksum = mem::replace(&mut self.checksum, Crc32::new()).finish();
+        if checksum != u32::from_le_bytes(trailer[..4].try_into().unwrap())
+            || mem::take(&mut self.len) != u32::from_le_bytes(trailer[4..].try_into().unwrap())
+        {
+            return Err(malformed("checksum mismatch"));
+        }
+        if input.fill_buf()?.is_empty() {
+            self.done = true;
+            return Ok(());
+        }
+        header(input)?;
+        self.inflate.reset();
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Gunzip<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.done && !buf.is_empty() {
+            let len = match self.inflate.read(buf) {
+                Ok(0) => match self.next_member() {
+                    Ok(()) => continue,
+                    Err(err) => return Err(invalid(&self.path, "gzip", err)),
+                },
+                Ok(len) => len,
+                Err(err) => return Err(invalid(&self.path, "gzip", err)),
+            };
+            self.checksum.update(&buf[..len]);
+            self.len = self.len.wrapping_add(len as u32);
+            return Ok(len);
+        }
+        Ok(0)
+    }
+}
+
+/// Reads and checks the header of a gzip member.
+fn header(input: &mut impl BufRead) -> io::Result<()> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let mut header = [0; 10];
+    input.read_exact(&mut header).map_err(eof_as_truncated)?;
+    if !header.starts_with(GZIP_MAGIC) {
+        return Err(malformed("bad magic number"));
+    }
+    if header[2] != 8 {
+        return Err(malformed("unknown compression method"));
+    }
+    let flags = header[3];
+    if flags & FEXTRA != 0 {
+        let mut len = [0; 2];
+        input.read_exact(&mut len).map_err(eof_as_truncated)?;
+        let len = u64::from(u16::from_le_bytes(len));
+        if io::copy(&mut input.take(len), &mut io::sink())? != len {
+            return Err(truncated());
+        }
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let mut field = Vec::new();
+            input.read_until(0, &mut field)?;
+            if field.last() != Some(&0) {
+                return Err(truncated());
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        input.read_exact(&mut [0; 2]).map_err(eof_as_truncated)?;
+    }
+    Ok(())
+}
+
+fn truncated() -> io::Error {
+    malformed("truncated")
+}
+
+/// Reports an unexpected end of the input as a truncated stream.
+fn eof_as_truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => truncated(),
+        _ => err,
+    }
+}
+
+/// Adds the path of a compressed file to errors describing what's wrong
+/// with it.
+fn invalid(path: &Path, format: &str, err: io::Error) -> io::Error {
+    if err.kind() != io::ErrorKind::InvalidData {
+        return err;
+    }
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("`{}` isn't valid {format}: {err}", path.display()),
+    )
+}
+
+/// A reader of the decompressed contents of a Zstandard stream, which may
+/// consist of several frames, as produced by concatenating Zstandard files.
+#[cfg(feature = "zstd")]
+struct Unzstd<R> {
+    input: R,
+    decoder: FrameDecoder,
+
+    /// The path of the file, for error messages.
+    path: PathBuf,
+
+    /// Whether the current frame has been read to its end.
+    frame_done: bool,
+}
+
+#[cfg(feature = "zstd")]
+impl<R: BufRead> Unzstd<R> {
+    /// Reads the header of the first frame of `input`.
+    fn new(input: R, path: &Path) -> io::Result<Self> {
+        let mut unzstd = Self {
+            input,
+            decoder: FrameDecoder::new(),
+            path: path.to_path_buf(),
+            frame_done: true,
+        };
+        match unzstd.next_frame() {
+            Ok(true) => Ok(unzstd),
+            Ok(false) => Err(invalid(path, "Zstandard", truncated())),
+            Err(err) => Err(invalid(path, "Zstandard", err)),
+        }
+    }
+
+    /// Reads the header of the next frame, skipping skippable frames, or
+    /// returns `false` at the end of the input.
+    fn next_frame(&mut self) -> io::Result<bool> {
+        loop {
+            if self.input.fill_buf()?.is_empty() {
+                return Ok(false);
+            }
+            match self.decoder.reset(&mut self.input) {
+                Ok(()) => {
+                    self.frame_done = false;
+                    return Ok(true);
+                }
+                Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame {
+                    length,
+                    ..
+                })) => {
+                    let length = u64::from(length);
+                    if io::copy(&mut (&mut self.input).take(length), &mut io::sink())? != length {
+                        return Err(truncated());
+                    }
+                }
+                Err(err) => return Err(zstd_error(err)),
+            }
+        }
+    }
+
+    fn read_frames(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !buf.is_empty() {
+            if self.frame_done && !self.next_frame()? {
+                break;
+            }
+            while self.decoder.can_collect() < buf.len() && !self.decoder.is_finished() {
+                let wanted = buf.len() - self.decoder.can_collect();
+                self.decoder
+                    .decode_blocks(&mut self.input, BlockDecodingStrategy::UptoBytes(wanted))
+                    .map_err(zstd_error)?;
+            }
+            let len = self.decoder.read(buf)?;
+            if len != 0 {
+                return Ok(len);
+            }
+            // The frame has been read to its end, so its checksum, if it
+            // has one, covers all of it.
+            if let Some(checksum) = self.decoder.get_checksum_from_data() {
+                if self.decoder.get_calculated_checksum() != Some(checksum) {
+                    return Err(malformed("checksum mismatch"));
+                }
+            }
+            self.frame_done = true;
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<R: BufRead> Read for Unzstd<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_frames(buf)
+            .map_err(|err| invalid(&self.path, "Zstandard", err))
+    }
+}
+
+/// Converts an error from the Zstandard decoder, passing on errors reading
+/// the file rather than reporting them as corrupt data.
+#[cfg(feature = "zstd")]
+fn zstd_error(err: FrameDecoderError) -> io::Error {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+    while let Some(error) = source {
+        if let Some(io_error) = error.downcast_ref::<io::Error>() {
+            return match io_error.kind() {
+                io::ErrorKind::UnexpectedEof => truncated(),
+                kind => io::Error::new(kind, io_error.to_string()),
+            };
+        }
+        source = error.source();
+    }
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// A reader of the decompressed contents of an xz file, which may consist
+/// of several streams, as produced by concatenating xz files.
+#[cfg(feature = "xz")]
+struct Unxz<R: Read> {
+    reader: XzReader<R>,
+
+    /// The path of the file, for error messages.
+    path: PathBuf,
+}
+
+#[cfg(feature = "xz")]
+impl<R: Read> Read for Unxz<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader
+            .read(buf)
+            .map_err(|err| invalid(&self.path, "xz", eof_as_truncated(err)))
+    }
+}
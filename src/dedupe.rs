@@ -0,0 +1,198 @@
+use crate::atomic_file::temp_name;
+use crate::sync::same_contents;
+use crate::{DirView, Operation, Sha256Hasher, ViewKind};
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Options for [`DirView::dedupe`].
+#[derive(Clone, Debug)]
+pub struct DedupeOptions {
+    min_size: u64,
+    match_permissions: bool,
+}
+
+impl DedupeOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Files of at least one byte are initially considered, and only files
+    /// with the same permissions, and on Unix the same owner, are linked.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            min_size: 1,
+            match_permissions: true,
+        }
+    }
+
+    /// Sets the minimum size of files to consider.
+    #[inline]
+    pub fn min_size(&mut self, min_size: u64) -> &mut Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Sets whether files must have the same permissions and owner to be
+    /// linked. Linked files share their metadata, so without this, some
+    /// files may end up with the permissions of others.
+    #[inline]
+    pub fn match_permissions(&mut self, match_permissions: bool) -> &mut Self {
+        self.match_permissions = match_permissions;
+        self
+    }
+}
+
+impl Default for DedupeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A summary of the changes made by [`DirView::dedupe`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DedupeReport {
+    linked: Vec<(PathBuf, PathBuf)>,
+    bytes_saved: u64,
+}
+
+impl DedupeReport {
+    /// Returns the files replaced with hard links, each with the file it's
+    /// now linked to. Paths are relative to the deduplicated tree.
+    #[inline]
+    pub fn linked(&self) -> &[(PathBuf, PathBuf)] {
+        &self.linked
+    }
+
+    /// Returns the total size of the replaced files.
+    #[inline]
+    pub fn bytes_saved(&self) -> u64 {
+        self.bytes_saved
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Finds identical files in the tree at `path` and replaces duplicates
+    /// with hard links to a single copy.
+    ///
+    /// Files are compared by size, then by hash, then byte by byte, so only
+    /// identical files are linked. Files on different filesystems aren't
+    /// linked, and each duplicate is replaced atomically, by linking it to a
+    /// temporary name and renaming that over it. If `self` is a dry-run
+    /// view, the returned report describes what would be linked.
+    ///
+    /// The tree is read through the view, so entries it hides aren't
+    /// considered. Symbolic links aren't followed.
+    pub fn dedupe<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &DedupeOptions,
+    ) -> io::Result<DedupeReport> {
+        let path = path.as_ref();
+        self.check_change(Operation::HardLink, path)?;
+        let root = self.open_dir(path)?;
+
+        // Group files which could be identical, by size and by the metadata
+        // they'd have to share.
+        let mut candidates = BTreeMap::<_, Vec<_>>::new();
+        for entry in root.walk()?.sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let metadata = root.symlink_metadata(entry.path())?;
+            if metadata.len() < options.min_size {
+                continue;
+            }
+            candidates
+                .entry(group_key(&metadata, options))
+                .or_default()
+                .push((entry.into_path(), file_id(&metadata)));
+        }
+
+        let mut report = DedupeReport::default();
+        for ((len, ..), files) in candidates {
+            // Files which are already linked together only need one path.
+            let mut ids = HashSet::new();
+            let files = files
+                .into_iter()
+                .filter(|(_, id)| id.is_none() || ids.insert(*id))
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            if files.len() < 2 {
+                continue;
+            }
+
+            let mut by_hash = BTreeMap::<_, Vec<_>>::new();
+            for path in files {
+                by_hash.entry(hash(&root, &path)?).or_default().push(path);
+            }
+            for files in by_hash.into_values() {
+                let (original, duplicates) = files.split_first().unwrap();
+                for duplicate in duplicates {
+                    if !same_contents(
+                        root.open_budgeted_read(original)?,
+                        root.open_budgeted_read(duplicate)?,
+                    )? {
+                        continue;
+                    }
+                    if !root.dry_run(Operation::HardLink, original, Some(duplicate))? {
+                        replace_with_link(&root, original, duplicate)?;
+                    }
+                    report.linked.push((duplicate.clone(), original.clone()));
+                    report.bytes_saved += len;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Replaces `duplicate` with a hard link to `original`, atomically.
+fn replace_with_link(root: &DirView, original: &Path, duplicate: &Path) -> io::Result<()> {
+    debug_assert_ne!(root.view_kind, ViewKind::DryRun);
+    let name = duplicate.file_name().unwrap_or_default();
+    let temp = duplicate.with_file_name(temp_name(name));
+    root.hard_link(original, root, &temp)?;
+    root.rename(&temp, root, duplicate).inspect_err(|_| {
+        root.remove_file(&temp).ok();
+    })
+}
+
+/// Returns the key of the group of files which could be deduplicated with
+/// the file with `metadata`.
+#[cfg(unix)]
+fn group_key(metadata: &Metadata, options: &DedupeOptions) -> (u64, u64, u32, u32, u32) {
+    let (mode, uid, gid) = if options.match_permissions {
+        (metadata.mode(), metadata.uid(), metadata.gid())
+    } else {
+        (0, 0, 0)
+    };
+    (metadata.len(), metadata.dev(), mode, uid, gid)
+}
+
+#[cfg(not(unix))]
+fn group_key(metadata: &Metadata, options: &DedupeOptions) -> (u64, bool) {
+    let readonly = options.match_permissions && metadata.permissions().readonly();
+    (metadata.len(), readonly)
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<u64> {
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+fn hash(root: &DirView, path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256Hasher::new();
+    io::copy(&mut root.open_budgeted_read(path)?, &mut hasher)?;
+    Ok(hasher.finish())
+}
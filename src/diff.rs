@@ -0,0 +1,169 @@
+use crate::sync::same_contents;
+use crate::DirView;
+use cap_std::fs::{Dir, FileType};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Options for [`diff`].
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    compare_contents: bool,
+}
+
+impl DiffOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Files are initially compared by size and modification time.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether files of the same size are compared by their contents,
+    /// rather than by their modification times.
+    #[inline]
+    pub fn compare_contents(&mut self, compare_contents: bool) -> &mut Self {
+        self.compare_contents = compare_contents;
+        self
+    }
+}
+
+/// The differences between two views, returned by [`diff`].
+///
+/// Paths are relative to the views, and sorted. The contents of added and
+/// removed directories aren't listed separately.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DiffReport {
+    added: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+    modified: Vec<PathBuf>,
+    type_changed: Vec<PathBuf>,
+}
+
+impl DiffReport {
+    /// Returns the entries which are in the second view but not the first.
+    #[inline]
+    pub fn added(&self) -> &[PathBuf] {
+        &self.added
+    }
+
+    /// Returns the entries which are in the first view but not the second.
+    #[inline]
+    pub fn removed(&self) -> &[PathBuf] {
+        &self.removed
+    }
+
+    /// Returns the files and symbolic links which differ between the views.
+    #[inline]
+    pub fn modified(&self) -> &[PathBuf] {
+        &self.modified
+    }
+
+    /// Returns the entries which are of different types in the views, such
+    /// as a file in one and a directory in the other.
+    #[inline]
+    pub fn type_changed(&self) -> &[PathBuf] {
+        &self.type_changed
+    }
+
+    /// Tests whether the views are the same.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.type_changed.is_empty()
+    }
+}
+
+/// Compares the trees in views `a` and `b`.
+///
+/// Symbolic links are compared by their contents, rather than followed. The
+/// comparison is made through the views, so entries they hide aren't
+/// compared, and subdirectories beyond their maximum depths fail to open.
+pub fn diff<D: Borrow<Dir>, E: Borrow<Dir>>(
+    a: &DirView<D>,
+    b: &DirView<E>,
+    options: &DiffOptions,
+) -> io::Result<DiffReport> {
+    let mut report = DiffReport::default();
+    diff_dir(a, b, Path::new(""), options, &mut report)?;
+    report.added.sort();
+    report.removed.sort();
+    report.modified.sort();
+    report.type_changed.sort();
+    Ok(report)
+}
+
+fn diff_dir<D: Borrow<Dir>, E: Borrow<Dir>>(
+    a: &DirView<D>,
+    b: &DirView<E>,
+    path: &Path,
+    options: &DiffOptions,
+    report: &mut DiffReport,
+) -> io::Result<()> {
+    let a_entries = file_types(a, path)?;
+    let mut b_entries = file_types(b, path)?;
+    for (name, a_type) in a_entries {
+        let path = path.join(&name);
+        let Some(b_type) = b_entries.remove(&name) else {
+            report.removed.push(path);
+            continue;
+        };
+        if a_type.is_dir() != b_type.is_dir()
+            || a_type.is_file() != b_type.is_file()
+            || a_type.is_symlink() != b_type.is_symlink()
+        {
+            report.type_changed.push(path);
+        } else if a_type.is_dir() {
+            diff_dir(a, b, &path, options, report)?;
+        } else if a_type.is_symlink() {
+            if a.read_link_contents(&path)? != b.read_link_contents(&path)? {
+                report.modified.push(path);
+            }
+        } else if a_type.is_file() && files_differ(a, b, &path, options)? {
+            report.modified.push(path);
+        }
+    }
+    report
+        .added
+        .extend(b_entries.into_keys().map(|name| path.join(name)));
+    Ok(())
+}
+
+fn file_types<D: Borrow<Dir>>(
+    view: &DirView<D>,
+    path: &Path,
+) -> io::Result<BTreeMap<OsString, FileType>> {
+    let mut file_types = BTreeMap::new();
+    for entry in view.read_dir_or_entries(path)? {
+        let entry = entry?;
+        file_types.insert(entry.file_name(), entry.file_type()?);
+    }
+    Ok(file_types)
+}
+
+fn files_differ<D: Borrow<Dir>, E: Borrow<Dir>>(
+    a: &DirView<D>,
+    b: &DirView<E>,
+    path: &Path,
+    options: &DiffOptions,
+) -> io::Result<bool> {
+    let (a_metadata, b_metadata) = (a.metadata(path)?, b.metadata(path)?);
+    if a_metadata.len() != b_metadata.len() {
+        return Ok(true);
+    }
+    if options.compare_contents {
+        return Ok(!same_contents(
+            a.open_budgeted_read(path)?,
+            b.open_budgeted_read(path)?,
+        )?);
+    }
+    match (a_metadata.modified(), b_metadata.modified()) {
+        (Ok(a_modified), Ok(b_modified)) => Ok(a_modified != b_modified),
+        _ => Ok(true),
+    }
+}
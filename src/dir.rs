@@ -1,6 +1,17 @@
-use crate::{ReadDirView, ViewKind};
+use crate::audit::{AuditEvent, AuditHook};
+use crate::backend::{Restrictions, ViewType};
+use crate::policy::Policy;
+use crate::progress::Tracker;
+use crate::reflink;
+use crate::throttle::CopyThrottle;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+use crate::{CopyOptions, DirEntryView, ReadDirView, ReadDirWithMetadata, ViewKind, Walk};
 #[cfg(feature = "cap-fs-ext")]
 use cap_fs_ext::{AccessType, SystemTimeSpec};
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
 use cap_std::fs::{Dir, DirBuilder, File, Metadata, OpenOptions, Permissions};
 use cap_std::io_lifetimes::AsFilelike;
 #[cfg(unix)]
@@ -8,24 +19,134 @@ use cap_std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
 use cap_std::AmbientAuthority;
 #[cfg(target_os = "wasi")]
 use rustix::fs::OpenOptionsExt;
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
+#[cfg(not(windows))]
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, BorrowedHandle, OwnedHandle};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::{fmt, io};
 
 /// A view of a [`Dir`].
 ///
 /// This provides the same API as `Dir`, but imposes restrictions according
 /// to the view kind.
-pub struct DirView {
-    pub(crate) dir: Dir,
+///
+/// The OS doesn't restrict what can be done relative to a directory handle,
+/// so code given the handle of the underlying `Dir` isn't bound by the
+/// restrictions of the view. Views don't implement `AsFd` or `AsHandle`,
+/// and only expose their handles through methods such as `as_fd`, which
+/// return `None` for restricted views, and by sending them to other
+/// processes with `DirView::send_to_unix_socket`.
+///
+/// The view is generic over its backend `D`, which may be any type that can
+/// lend a `Dir`: the `Dir` itself, a reference as in [`DirViewRef`], an
+/// `Arc<Dir>`, or a wrapper carrying additional state. Methods which open
+/// new directories always return views owning their `Dir`.
+pub struct DirView<D = Dir> {
+    pub(crate) dir: D,
     pub(crate) view_kind: ViewKind,
+    pub(crate) policy: Arc<Policy>,
+    pub(crate) depth: usize,
 }
 
-impl DirView {
-    /// Constructs a new instance of `Self` from the given [`Dir`] and
-    /// [`ViewKind`].
+/// A view of a borrowed [`Dir`].
+///
+/// This allows lending a restricted view of a `Dir` owned elsewhere without
+/// duplicating its handle.
+pub type DirViewRef<'a> = DirView<&'a Dir>;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Constructs a new instance of `Self` from the given [`Dir`], or a
+    /// reference to one, and [`ViewKind`].
+    #[inline]
+    pub fn from_dir(dir: D, view_kind: ViewKind) -> Self {
+        Self {
+            dir,
+            view_kind,
+            policy: Arc::default(),
+            depth: 0,
+        }
+    }
+
+    /// Constructs a new readonly view of the given [`Dir`].
+    ///
+    /// This is equivalent to `Self::from_dir(dir, ViewKind::Readonly)`.
+    #[inline]
+    pub fn readonly(dir: D) -> Self {
+        Self::from_dir(dir, ViewKind::Readonly)
+    }
+
+    /// Constructs a new full view of the given [`Dir`].
+    ///
+    /// This is equivalent to `Self::from_dir(dir, ViewKind::Full)`.
     #[inline]
-    pub fn from_dir(dir: Dir, view_kind: ViewKind) -> Self {
-        Self { dir, view_kind }
+    pub fn full(dir: D) -> Self {
+        Self::from_dir(dir, ViewKind::Full)
+    }
+
+    /// Constructs a new view of the given [`Dir`], or a reference to one,
+    /// which calls `audit` with every operation performed through it.
+    ///
+    /// Views, iterators, and entries derived from the view call `audit` too.
+    /// Operations involving two views, such as [`Self::rename`], are
+    /// reported by the view the method is called on.
+    #[inline]
+    pub fn with_audit(
+        dir: D,
+        view_kind: ViewKind,
+        audit: impl Fn(&AuditEvent<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            dir,
+            view_kind,
+            policy: Arc::new(Policy {
+                audit: Some(AuditHook::new(audit)),
+                ..Policy::default()
+            }),
+            depth: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying [`Dir`], if `self` is a full
+    /// view which imposes no other restrictions.
+    ///
+    /// This is useful for interoperating with code that expects a `Dir`
+    /// without giving up ownership of the view. Restricted, audited,
+    /// coordinated, or cached views never expose their `Dir`, as changes made
+    /// through it would bypass their restrictions, auditing, intent locks,
+    /// or cache.
+    #[inline]
+    pub fn as_dir(&self) -> Option<&Dir> {
+        match self.view_kind {
+            ViewKind::Full if self.policy.is_unrestricted() => Some(self.dir.borrow()),
+            _ => None,
+        }
+    }
+
+    /// Returns the file descriptor of the underlying directory, if `self` is
+    /// a full view which imposes no other restrictions, as with
+    /// [`Self::as_dir`].
+    ///
+    /// The OS doesn't restrict what can be done relative to a directory's
+    /// file descriptor, so restricted views never expose theirs.
+    #[cfg(not(windows))]
+    #[inline]
+    pub fn as_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.as_dir().map(AsFd::as_fd)
+    }
+
+    /// Returns the handle of the underlying directory, if `self` is a full
+    /// view which imposes no other restrictions, as with [`Self::as_dir`].
+    ///
+    /// The OS doesn't restrict what can be done relative to a directory's
+    /// handle, so restricted views never expose theirs.
+    #[cfg(windows)]
+    #[inline]
+    pub fn as_handle(&self) -> Option<BorrowedHandle<'_>> {
+        self.as_dir().map(AsHandle::as_handle)
     }
 
     /// Attempts to open a file in read-only mode.
@@ -34,7 +155,14 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
-        self.dir.open(path)
+        if self.restrictions().is_passthrough() {
+            return self.dir.borrow().open(path);
+        }
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            self.policy.check_unbudgeted(Operation::Open, path)?;
+            self.dir.borrow().open(path)
+        })
     }
 
     /// Opens a file at `path` with the options specified by `options`.
@@ -43,29 +171,70 @@ impl DirView {
     ///
     /// Instead of being a method on `OpenOptions`, this is a method on `Dir`,
     /// and it only accesses paths relative to `self`.
+    ///
+    /// On readonly views, any options that would allow writing are cleared,
+    /// along with platform-specific options such as custom flags.
+    /// On WASI, the rights of the returned descriptor are derived from its
+    /// access mode, so the runtime also denies writes to it.
     #[inline]
     pub fn open_with<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> io::Result<File> {
-        let mut options = options.clone();
-        match self.view_kind {
-            ViewKind::Full => {}
-            ViewKind::Readonly => {
-                // Override any flag that allows writing.
-                options.append(false);
-                options.truncate(false);
-                options.write(false);
-                options.create(false);
-                options.create_new(false);
-            }
+        if self.restrictions().is_passthrough() {
+            return self.dir.borrow().open_with(path, options);
         }
-        self.dir.open_with(path, &options)
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            self.policy.check_unbudgeted(Operation::Open, path)?;
+            let options = self.kind_for(Operation::Open, path).open_options(options);
+            self.dir.borrow().open_with(path, &options)
+        })
     }
 
     /// Attempts to open a directory.
     #[inline]
-    pub fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Self> {
-        Ok(Self {
-            dir: self.dir.open_dir(path)?,
-            view_kind: self.view_kind,
+    pub fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<DirView> {
+        self.audited(Operation::OpenDir, path, |path| self.open_child_dir(path))
+    }
+
+    /// Attempts to open a directory, as a view of the given kind.
+    ///
+    /// This fails if `view_kind` is broader than the kind of the directory
+    /// in `self`, so views can be narrowed, but never widened, when
+    /// descending.
+    #[inline]
+    pub fn open_dir_view<P: AsRef<Path>>(
+        &self,
+        path: P,
+        view_kind: ViewKind,
+    ) -> io::Result<DirView> {
+        self.audited(Operation::OpenDir, path, |path| {
+            if self.policy.overrides.is_none() {
+                self.policy.narrow(self.view_kind, view_kind, path)?;
+            }
+            let mut view = self.open_child_dir(path)?;
+            view.view_kind = self.policy.narrow(view.view_kind, view_kind, path)?;
+            view.policy = Policy::narrowed(view.policy, view_kind);
+            Ok(view)
+        })
+    }
+
+    /// Attempts to open a directory, without following a symbolic link in
+    /// the last component of `path`.
+    ///
+    /// This fails if `path` names a symbolic link, even one that refers to a
+    /// directory.
+    #[inline]
+    pub fn open_dir_nofollow<P: AsRef<Path>>(&self, path: P) -> io::Result<DirView> {
+        self.audited(Operation::OpenDir, path, |path| {
+            self.check_resolve(Operation::OpenDir, path)?;
+            let depth =
+                self.policy
+                    .descend(self.dir.borrow(), self.depth, Operation::OpenDir, path)?;
+            let dir = cap_primitives::fs::open_dir_nofollow(
+                &self.dir.borrow().as_filelike_view::<std::fs::File>(),
+                path,
+            )?;
+            let location = self.policy.locate(self.dir.borrow(), path);
+            self.child(path, Dir::from_std_file(dir), depth, location.as_deref())
         })
     }
 
@@ -75,8 +244,12 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.create_dir(path)
+        self.audited(Operation::CreateDir, path, |path| {
+            if self.dry_run(Operation::CreateDir, path, None)? {
+                return Ok(());
+            }
+            self.dir.borrow().create_dir(path)
+        })
     }
 
     /// Recursively create a directory and all of its parent components if they
@@ -86,8 +259,12 @@ impl DirView {
     /// paths relative to `self`.
     #[inline]
     pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.create_dir_all(path)
+        self.audited(Operation::CreateDir, path, |path| {
+            if self.dry_run(Operation::CreateDir, path, None)? {
+                return Ok(());
+            }
+            self.dir.borrow().create_dir_all(path)
+        })
     }
 
     /// Creates the specified directory with the options configured in this
@@ -101,8 +278,12 @@ impl DirView {
         path: P,
         dir_builder: &DirBuilder,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.create_dir_with(path, dir_builder)
+        self.audited(Operation::CreateDir, path, |path| {
+            if self.dry_run(Operation::CreateDir, path, None)? {
+                return Ok(());
+            }
+            self.dir.borrow().create_dir_with(path, dir_builder)
+        })
     }
 
     /// Opens a file in write-only mode.
@@ -111,8 +292,44 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
-        self.check_mutation()?;
-        self.dir.create(path)
+        self.audited(Operation::Create, path, |path| {
+            self.check_mutation(Operation::Create, path)?;
+            self.check_resolve(Operation::Create, path)?;
+            self.policy.check_unbudgeted(Operation::Create, path)?;
+            self.dir.borrow().create(path)
+        })
+    }
+
+    /// Creates a new file in write-only mode, failing if it already exists.
+    ///
+    /// This corresponds to [`std::fs::File::create_new`], but only accesses
+    /// paths relative to `self`.
+    #[inline]
+    pub fn create_new<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        self.audited(Operation::Create, path, |path| {
+            self.check_mutation(Operation::Create, path)?;
+            self.check_resolve(Operation::Create, path)?;
+            self.policy.check_unbudgeted(Operation::Create, path)?;
+            self.dir
+                .borrow()
+                .open_with(path, OpenOptions::new().write(true).create_new(true))
+        })
+    }
+
+    /// Opens a file in append mode, creating it if it doesn't exist.
+    ///
+    /// This corresponds to opening with [`std::fs::OpenOptions`] with
+    /// `append` and `create` set, but only accesses paths relative to `self`.
+    #[inline]
+    pub fn open_append<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_mutation(Operation::Open, path)?;
+            self.check_resolve(Operation::Open, path)?;
+            self.policy.check_unbudgeted(Operation::Open, path)?;
+            self.dir
+                .borrow()
+                .open_with(path, OpenOptions::new().append(true).create(true))
+        })
     }
 
     /// Returns the canonical form of a path with all intermediate components
@@ -123,7 +340,9 @@ impl DirView {
     /// represented by `self`.
     #[inline]
     pub fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
-        self.dir.canonicalize(path)
+        self.audited(Operation::Canonicalize, path, |path| {
+            self.dir.borrow().canonicalize(path)
+        })
     }
 
     /// Copies the contents of one file to another. This function will also
@@ -132,15 +351,60 @@ impl DirView {
     ///
     /// This corresponds to [`std::fs::copy`], but only accesses paths
     /// relative to `self`.
+    ///
+    /// On Linux, this first tries to share the data between the files, on
+    /// filesystems which support reflinks, and otherwise copies it within
+    /// the kernel with `copy_file_range` where possible. On macOS, it clones
+    /// the file where possible.
     #[inline]
-    pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+    pub fn copy<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
         &self,
         from: P,
-        to_dir: &Self,
+        to_dir: &DirView<E>,
         to: Q,
     ) -> io::Result<u64> {
-        to_dir.check_mutation()?;
-        self.dir.copy(from, &to_dir.dir, to)
+        self.copy_tracked(from, to_dir, to, &mut Tracker::default())
+    }
+
+    /// Copies the contents of one file to another, like [`Self::copy`],
+    /// with a progress callback and cancellation token from `options`.
+    #[inline]
+    pub fn copy_with<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &DirView<E>,
+        to: Q,
+        options: &CopyOptions,
+    ) -> io::Result<u64> {
+        self.copy_tracked(from, to_dir, to, &mut Tracker::new(&options.monitor))
+    }
+
+    /// Copies a file, reporting the progress of the copy to `tracker`.
+    pub(crate) fn copy_tracked<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &DirView<E>,
+        to: Q,
+        tracker: &mut Tracker<'_>,
+    ) -> io::Result<u64> {
+        self.audited_pair(Operation::Copy, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            to_dir.check_change(Operation::Copy, to.as_ref())?;
+            if self.dry_run_pair(to_dir, Operation::Copy, from, to.as_ref())? {
+                return Ok(self.dir.borrow().metadata(from)?.len());
+            }
+            reflink::copy(
+                self.dir.borrow(),
+                from,
+                to_dir.dir.borrow(),
+                to.as_ref(),
+                tracker,
+                CopyThrottle {
+                    read: self.policy.rate_limit.as_ref(),
+                    write: to_dir.policy.rate_limit.as_ref(),
+                },
+            )
+        })
     }
 
     /// Creates a new hard link on a filesystem.
@@ -148,15 +412,21 @@ impl DirView {
     /// This corresponds to [`std::fs::hard_link`], but only accesses paths
     /// relative to `self`.
     #[inline]
-    pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(
+    pub fn hard_link<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
         &self,
         src: P,
-        dst_dir: &Self,
+        dst_dir: &DirView<E>,
         dst: Q,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        dst_dir.check_mutation()?;
-        self.dir.hard_link(src, &dst_dir.dir, dst)
+        self.audited_pair(Operation::HardLink, src, dst, |src, dst| {
+            let dst = dst_dir.fold_names(dst.as_ref());
+            self.check_change(Operation::HardLink, src)?;
+            dst_dir.check_change(Operation::HardLink, dst.as_ref())?;
+            if self.dry_run_pair(dst_dir, Operation::HardLink, src, dst.as_ref())? {
+                return Ok(());
+            }
+            self.dir.borrow().hard_link(src, dst_dir.dir.borrow(), dst)
+        })
     }
 
     /// Given a path, query the file system to get information about a file,
@@ -166,7 +436,9 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<cap_std::fs::Metadata> {
-        self.dir.metadata(path)
+        self.audited(Operation::Metadata, path, |path| {
+            self.cached_metadata(path, true, || self.dir.borrow().metadata(path))
+        })
     }
 
     /// Queries metadata about the underlying directory.
@@ -175,37 +447,123 @@ impl DirView {
     /// than for `File`.
     #[inline]
     pub fn dir_metadata(&self) -> io::Result<Metadata> {
-        self.dir.dir_metadata()
+        self.audited(Operation::Metadata, ".", |_| {
+            self.dir.borrow().dir_metadata()
+        })
     }
 
     /// Returns an iterator over the entries within `self`.
     #[inline]
     pub fn entries(&self) -> io::Result<ReadDirView> {
-        Ok(ReadDirView {
-            read_dir: self.dir.entries()?,
-            view_kind: self.view_kind,
+        self.audited(Operation::ReadDir, ".", |_| {
+            ReadDirView::new(
+                self.dir.borrow().try_clone()?,
+                self.view_kind,
+                Arc::clone(&self.policy),
+                self.depth,
+                Path::new(""),
+            )
         })
     }
 
+    /// Returns an iterator over the entries within `self` together with
+    /// their metadata.
+    ///
+    /// This reads each entry's metadata through the entry itself, which is
+    /// cheaper than calling [`Self::metadata`] with its name.
+    #[inline]
+    pub fn entries_with_metadata(&self) -> io::Result<ReadDirWithMetadata> {
+        Ok(self.entries()?.with_metadata())
+    }
+
+    /// Returns a recursive iterator over the entries within `self` and its
+    /// subdirectories.
+    ///
+    /// The returned [`Walk`] can be configured with a maximum depth, to
+    /// follow symbolic links, to sort entries, and to skip subtrees.
+    #[inline]
+    pub fn walk(&self) -> io::Result<Walk> {
+        Ok(Walk::new(self.try_clone()?))
+    }
+
     /// Returns an iterator over the entries within a directory.
     ///
     /// This corresponds to [`std::fs::read_dir`], but only accesses paths
     /// relative to `self`.
     #[inline]
     pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ReadDirView> {
-        Ok(ReadDirView {
-            read_dir: self.dir.read_dir(path)?,
-            view_kind: self.view_kind,
+        self.audited(Operation::ReadDir, path, |path| {
+            self.check_resolve(Operation::ReadDir, path)?;
+            let depth =
+                self.policy
+                    .descend(self.dir.borrow(), self.depth, Operation::ReadDir, path)?;
+            let location = self.policy.locate(self.dir.borrow(), path);
+            ReadDirView::new(
+                self.dir.borrow().open_dir(path)?,
+                self.policy.kind_at(self.view_kind, location.as_deref()),
+                Policy::for_location(&self.policy, location.as_deref()),
+                depth,
+                path,
+            )
         })
     }
 
+    /// Returns the entries within `self`, sorted by file name.
+    ///
+    /// This reads all of the entries before returning them, so their order
+    /// doesn't depend on the filesystem.
+    pub fn entries_sorted(&self) -> io::Result<Vec<DirEntryView>> {
+        let mut entries = self.entries()?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_cached_key(DirEntryView::file_name);
+        Ok(entries)
+    }
+
+    /// Returns the entries within `self`, sorted with `compare`.
+    pub fn entries_sorted_by<F>(&self, compare: F) -> io::Result<Vec<DirEntryView>>
+    where
+        F: FnMut(&DirEntryView, &DirEntryView) -> Ordering,
+    {
+        let mut entries = self.entries()?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by(compare);
+        Ok(entries)
+    }
+
+    /// Returns the entries within a directory, sorted by file name.
+    ///
+    /// This is like [`Self::read_dir`], but reads all of the entries before
+    /// returning them, so their order doesn't depend on the filesystem.
+    pub fn read_dir_sorted<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntryView>> {
+        let mut entries = self.read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_cached_key(DirEntryView::file_name);
+        Ok(entries)
+    }
+
+    /// Returns the entries within a directory, sorted with `compare`.
+    pub fn read_dir_sorted_by<P: AsRef<Path>, F>(
+        &self,
+        path: P,
+        compare: F,
+    ) -> io::Result<Vec<DirEntryView>>
+    where
+        F: FnMut(&DirEntryView, &DirEntryView) -> Ordering,
+    {
+        let mut entries = self.read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by(compare);
+        Ok(entries)
+    }
+
     /// Read the entire contents of a file into a bytes vector.
     ///
     /// This corresponds to [`std::fs::read`], but only accesses paths
     /// relative to `self`.
     #[inline]
     pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
-        self.dir.read(path)
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            let contents = self.dir.borrow().read(path)?;
+            self.policy.account_bytes_read(contents.len());
+            Ok(contents)
+        })
     }
 
     /// Reads a symbolic link, returning the file that the link points to.
@@ -214,16 +572,38 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
-        self.dir.read_link(path)
+        self.audited(Operation::ReadLink, path, |path| {
+            self.dir.borrow().read_link(path)
+        })
+    }
+
+    /// Reads the contents of a symbolic link, returning them verbatim.
+    ///
+    /// Unlike [`Self::read_link`], this doesn't fail if the contents are an
+    /// absolute path or would otherwise escape `self`. The contents aren't
+    /// resolved, so returning them grants no access outside `self`.
+    #[inline]
+    pub fn read_link_contents<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.audited(Operation::ReadLink, path, |path| {
+            self.dir.borrow().read_link_contents(path)
+        })
     }
 
     /// Read the entire contents of a file into a string.
     ///
     /// This corresponds to [`std::fs::read_to_string`], but only accesses
     /// paths relative to `self`.
+    ///
+    /// This reads files of any size. To bound how much is read, use
+    /// [`Self::read_to_string_limit`].
     #[inline]
     pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
-        self.dir.read_to_string(path)
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            let contents = self.dir.borrow().read_to_string(path)?;
+            self.policy.account_bytes_read(contents.len());
+            Ok(contents)
+        })
     }
 
     /// Removes an empty directory.
@@ -232,8 +612,12 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.remove_dir(path)
+        self.audited(Operation::RemoveDir, path, |path| {
+            if self.dry_run(Operation::RemoveDir, path, None)? {
+                return Ok(());
+            }
+            self.dir.borrow().remove_dir(path)
+        })
     }
 
     /// Removes a directory at this path, after removing all its contents. Use
@@ -241,43 +625,56 @@ impl DirView {
     ///
     /// This corresponds to [`std::fs::remove_dir_all`], but only accesses
     /// paths relative to `self`.
-    #[inline]
-    pub fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.remove_dir_all(path)
-    }
-
-    /// Remove the directory referenced by `self` and consume `self`.
     ///
-    /// Even though this implementation works in terms of handles as much as
-    /// possible, removal is not guaranteed to be atomic with respect to a
-    /// concurrent rename of the directory.
+    /// If `self` has a trash, created with [`Self::with_trash`], the
+    /// directory is moved into it instead.
     #[inline]
-    pub fn remove_open_dir(self) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.remove_open_dir()
+    pub fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.audited(Operation::RemoveDirAll, path, |path| {
+            if self.dry_run(Operation::RemoveDirAll, path, None)?
+                || self.put_in_trash(path, true)?
+            {
+                return Ok(());
+            }
+            self.dir.borrow().remove_dir_all(path)
+        })
     }
 
-    /// Removes the directory referenced by `self`, after removing all its
-    /// contents, and consume `self`. Use carefully!
+    /// Removes all the contents of the directory at this path, leaving the
+    /// directory itself in place. Use carefully!
     ///
-    /// Even though this implementation works in terms of handles as much as
-    /// possible, removal is not guaranteed to be atomic with respect to a
-    /// concurrent rename of the directory.
+    /// Unlike removing the directory and creating it again, this preserves
+    /// the directory's identity, so that handles to it remain valid. This
+    /// fails if `path` names a symbolic link.
     #[inline]
-    pub fn remove_open_dir_all(self) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.remove_open_dir_all()
+    pub fn remove_dir_contents<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.audited(Operation::RemoveDirContents, path, |path| {
+            if self.dry_run(Operation::RemoveDirContents, path, None)? {
+                return Ok(());
+            }
+            let dir = cap_primitives::fs::open_dir_nofollow(
+                &self.dir.borrow().as_filelike_view::<std::fs::File>(),
+                path,
+            )?;
+            remove_contents(&cap_std::fs::Dir::from_std_file(dir))
+        })
     }
 
     /// Removes a file from a filesystem.
     ///
     /// This corresponds to [`std::fs::remove_file`], but only accesses paths
     /// relative to `self`.
+    ///
+    /// If `self` has a trash, created with [`Self::with_trash`], the file is
+    /// moved into it instead.
     #[inline]
     pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.remove_file(path)
+        self.audited(Operation::RemoveFile, path, |path| {
+            if self.dry_run(Operation::RemoveFile, path, None)? || self.put_in_trash(path, false)? {
+                return Ok(());
+            }
+            self.dir.borrow().remove_file(path)
+        })
     }
 
     /// Rename a file or directory to a new name, replacing the original file
@@ -286,15 +683,21 @@ impl DirView {
     /// This corresponds to [`std::fs::rename`], but only accesses paths
     /// relative to `self`.
     #[inline]
-    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+    pub fn rename<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
         &self,
         from: P,
-        to_dir: &Self,
+        to_dir: &DirView<E>,
         to: Q,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        to_dir.check_mutation()?;
-        self.dir.rename(from, &to_dir.dir, to)
+        self.audited_pair(Operation::Rename, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            self.check_change(Operation::Rename, from)?;
+            to_dir.check_change(Operation::Rename, to.as_ref())?;
+            if self.dry_run_pair(to_dir, Operation::Rename, from, to.as_ref())? {
+                return Ok(());
+            }
+            self.dir.borrow().rename(from, to_dir.dir.borrow(), to)
+        })
     }
 
     /// Changes the permissions found on a file or a directory.
@@ -306,8 +709,12 @@ impl DirView {
     #[cfg(not(target_os = "wasi"))]
     #[inline]
     pub fn set_permissions<P: AsRef<Path>>(&self, path: P, perm: Permissions) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.set_permissions(path, perm)
+        self.audited(Operation::SetPermissions, path, |path| {
+            if self.dry_run(Operation::SetPermissions, path, None)? {
+                return Ok(());
+            }
+            self.dir.borrow().set_permissions(path, perm)
+        })
     }
 
     /// Query the metadata about a file without following symlinks.
@@ -316,7 +723,44 @@ impl DirView {
     /// paths relative to `self`.
     #[inline]
     pub fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
-        self.dir.symlink_metadata(path)
+        self.audited(Operation::Metadata, path, |path| {
+            self.cached_metadata(path, false, || self.dir.borrow().symlink_metadata(path))
+        })
+    }
+
+    /// Returns the user ID and group ID of the owner of the file at `path`.
+    ///
+    /// This follows symbolic links, like [`Self::metadata`].
+    #[cfg(unix)]
+    #[inline]
+    pub fn owner<P: AsRef<Path>>(&self, path: P) -> io::Result<(u32, u32)> {
+        self.audited(Operation::Metadata, path, |path| {
+            let metadata = self.dir.borrow().metadata(path)?;
+            Ok((metadata.uid(), metadata.gid()))
+        })
+    }
+
+    /// Returns the mode of the file at `path`, including the file type and
+    /// permission bits.
+    ///
+    /// This follows symbolic links, like [`Self::metadata`].
+    #[cfg(unix)]
+    #[inline]
+    pub fn mode<P: AsRef<Path>>(&self, path: P) -> io::Result<u32> {
+        self.audited(Operation::Metadata, path, |path| {
+            Ok(self.dir.borrow().metadata(path)?.mode())
+        })
+    }
+
+    /// Returns the number of hard links to the file at `path`.
+    ///
+    /// This follows symbolic links, like [`Self::metadata`].
+    #[cfg(unix)]
+    #[inline]
+    pub fn nlink<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        self.audited(Operation::Metadata, path, |path| {
+            Ok(self.dir.borrow().metadata(path)?.nlink())
+        })
     }
 
     /// Write a slice as the entire contents of a file.
@@ -325,8 +769,16 @@ impl DirView {
     /// relative to `self`.
     #[inline]
     pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.write(path, contents)
+        self.audited(Operation::Write, path, |path| {
+            self.check_resolve(Operation::Write, path)?;
+            if self.dry_run(Operation::Write, path, None)? {
+                return Ok(());
+            }
+            let contents = contents.as_ref();
+            self.dir.borrow().write(path, contents)?;
+            self.policy.account_bytes_written(contents.len());
+            Ok(())
+        })
     }
 
     /// Creates a new symbolic link on a filesystem.
@@ -348,8 +800,41 @@ impl DirView {
     #[cfg(not(windows))]
     #[inline]
     pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, original: P, link: Q) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.symlink(original, link)
+        self.audited_pair(Operation::Symlink, link, original, |link, original| {
+            self.policy.check_symlink(link)?;
+            self.check_symlink_target(link, original.as_ref())?;
+            if self.dry_run(Operation::Symlink, link, Some(original.as_ref()))? {
+                return Ok(());
+            }
+            self.dir.borrow().symlink(original, link)
+        })
+    }
+
+    /// Creates a new symbolic link with the given contents verbatim.
+    ///
+    /// Unlike [`Self::symlink`], this doesn't fail if `original` is an
+    /// absolute path or would otherwise escape `self`. This is useful for
+    /// copying symlinks exactly. `link` is still resolved relative to `self`.
+    ///
+    /// This corresponds to [`std::os::unix::fs::symlink`], but only accesses
+    /// paths relative to `self`.
+    ///
+    /// [`std::os::unix::fs::symlink`]: https://doc.rust-lang.org/std/os/unix/fs/fn.symlink.html
+    #[cfg(not(windows))]
+    #[inline]
+    pub fn symlink_contents<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        original: P,
+        link: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Symlink, link, original, |link, original| {
+            self.policy.check_symlink(link)?;
+            self.check_symlink_target(link, original.as_ref())?;
+            if self.dry_run(Operation::Symlink, link, Some(original.as_ref()))? {
+                return Ok(());
+            }
+            self.dir.borrow().symlink_contents(original, link)
+        })
     }
 
     /// Creates a new file symbolic link on a filesystem.
@@ -375,8 +860,14 @@ impl DirView {
         original: P,
         link: Q,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.symlink_file(original, link)
+        self.audited_pair(Operation::Symlink, link, original, |link, original| {
+            self.policy.check_symlink(link)?;
+            self.check_symlink_target(link, original.as_ref())?;
+            if self.dry_run(Operation::Symlink, link, Some(original.as_ref()))? {
+                return Ok(());
+            }
+            self.dir.borrow().symlink_file(original, link)
+        })
     }
 
     /// Creates a new directory symlink on a filesystem.
@@ -402,8 +893,14 @@ impl DirView {
         original: P,
         link: Q,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        self.dir.symlink_dir(original, link)
+        self.audited_pair(Operation::Symlink, link, original, |link, original| {
+            self.policy.check_symlink(link)?;
+            self.check_symlink_target(link, original.as_ref())?;
+            if self.dry_run(Operation::Symlink, link, Some(original.as_ref()))? {
+                return Ok(());
+            }
+            self.dir.borrow().symlink_dir(original, link)
+        })
     }
 
     /// Creates a new `UnixListener` bound to the specified socket.
@@ -417,7 +914,10 @@ impl DirView {
     #[cfg(unix)]
     #[inline]
     pub fn bind_unix_listener<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixListener> {
-        self.dir.bind_unix_listener(path)
+        self.audited(Operation::BindUnixSocket, path, |path| {
+            self.check_mutation(Operation::BindUnixSocket, path)?;
+            self.dir.borrow().bind_unix_listener(path)
+        })
     }
 
     /// Connects to the socket named by path.
@@ -431,7 +931,10 @@ impl DirView {
     #[cfg(unix)]
     #[inline]
     pub fn connect_unix_stream<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixStream> {
-        self.dir.connect_unix_stream(path)
+        self.audited(Operation::ConnectUnixSocket, path, |path| {
+            self.policy.check_unix_connect(path)?;
+            self.dir.borrow().connect_unix_stream(path)
+        })
     }
 
     /// Creates a Unix datagram socket bound to the given path.
@@ -445,7 +948,10 @@ impl DirView {
     #[cfg(unix)]
     #[inline]
     pub fn bind_unix_datagram<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixDatagram> {
-        self.dir.bind_unix_datagram(path)
+        self.audited(Operation::BindUnixSocket, path, |path| {
+            self.check_mutation(Operation::BindUnixSocket, path)?;
+            self.dir.borrow().bind_unix_datagram(path)
+        })
     }
 
     /// Connects the socket to the specified address.
@@ -463,7 +969,10 @@ impl DirView {
         unix_datagram: &UnixDatagram,
         path: P,
     ) -> io::Result<()> {
-        self.dir.connect_unix_datagram(unix_datagram, path)
+        self.audited(Operation::ConnectUnixSocket, path, |path| {
+            self.policy.check_unix_connect(path)?;
+            self.dir.borrow().connect_unix_datagram(unix_datagram, path)
+        })
     }
 
     /// Sends data on the socket to the specified address.
@@ -482,27 +991,55 @@ impl DirView {
         buf: &[u8],
         path: P,
     ) -> io::Result<usize> {
-        self.dir
-            .send_to_unix_datagram_addr(unix_datagram, buf, path)
+        self.audited(Operation::ConnectUnixSocket, path, |path| {
+            self.policy.check_unix_connect(path)?;
+            self.dir
+                .borrow()
+                .send_to_unix_datagram_addr(unix_datagram, buf, path)
+        })
     }
 
     /// Creates a new `Dir` instance that shares the same underlying file
     /// handle as the existing `Dir` instance.
     #[inline]
-    pub fn try_clone(&self) -> io::Result<Self> {
-        Ok(Self {
-            dir: self.dir.try_clone()?,
+    pub fn try_clone(&self) -> io::Result<DirView> {
+        Ok(DirView {
+            dir: self.dir.borrow().try_clone()?,
             view_kind: self.view_kind,
+            policy: Policy::with_permit(
+                Arc::clone(&self.policy),
+                Operation::OpenDir,
+                Path::new("."),
+            )?,
+            depth: self.depth,
         })
     }
 
+    /// Creates a new view of the same directory as `self`, of the given
+    /// kind.
+    ///
+    /// This fails if `view_kind` is broader than the kind of `self`, so
+    /// views can be narrowed, but never widened, when cloning, such as to
+    /// hand a readonly view to a part of a program which shouldn't change
+    /// anything, while keeping a full view.
+    #[inline]
+    pub fn try_clone_with(&self, view_kind: ViewKind) -> io::Result<DirView> {
+        let view_kind = self
+            .policy
+            .narrow(self.view_kind, view_kind, Path::new("."))?;
+        let mut view = self.try_clone()?;
+        view.view_kind = view_kind;
+        view.policy = Policy::narrowed(view.policy, view_kind);
+        Ok(view)
+    }
+
     /// Returns `true` if the path points at an existing entity.
     ///
     /// This corresponds to [`std::path::Path::exists`], but only
     /// accesses paths relative to `self`.
     #[inline]
     pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.dir.exists(path)
+        self.metadata(path).is_ok()
     }
 
     /// Returns `true` if the path points at an existing entity.
@@ -516,7 +1053,9 @@ impl DirView {
     /// information, see the [tracker issue](https://github.com/rust-lang/rust/issues/83186).
     #[inline]
     pub fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
-        self.dir.try_exists(path)
+        self.audited(Operation::Metadata, path, |path| {
+            self.dir.borrow().try_exists(path)
+        })
     }
 
     /// Returns `true` if the path exists on disk and is pointing at a regular
@@ -526,7 +1065,7 @@ impl DirView {
     /// accesses paths relative to `self`.
     #[inline]
     pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.dir.is_file(path)
+        self.metadata(path).is_ok_and(|metadata| metadata.is_file())
     }
 
     /// Checks if `path` is a directory.
@@ -537,7 +1076,386 @@ impl DirView {
     /// file. In case of broken symbolic links, this will return `false`.
     #[inline]
     pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.dir.is_dir(path)
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    /// Checks if `path` is a symbolic link.
+    ///
+    /// Unlike [`Self::is_file`] and [`Self::is_dir`], this doesn't follow
+    /// symbolic links, so it returns `true` for broken symbolic links.
+    #[inline]
+    pub fn is_symlink<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.symlink_metadata(path)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+    }
+
+    /// Checks if `path` is a directory with no entries.
+    ///
+    /// This follows symbolic links, and fails if `path` doesn't exist or isn't
+    /// a directory. Entries are counted even if the view hides them from
+    /// iteration, so this agrees with whether [`Self::remove_dir`] could
+    /// succeed.
+    #[inline]
+    pub fn is_dir_empty<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        self.audited(Operation::ReadDir, path, |path| {
+            self.policy
+                .descend(self.dir.borrow(), self.depth, Operation::ReadDir, path)?;
+            Ok(self.dir.borrow().read_dir(path)?.next().is_none())
+        })
+    }
+
+    /// Tests whether the object at `path` is accessible in the given way,
+    /// taking the view's restrictions into account.
+    ///
+    /// A readonly view reports write access as denied, even if the
+    /// underlying object is writable.
+    #[cfg(feature = "cap-fs-ext")]
+    #[inline]
+    pub fn access<P: AsRef<Path>>(&self, path: P, type_: AccessType) -> io::Result<()> {
+        self.audited(Operation::Access, path, |path| {
+            self.check_access(type_, path)?;
+            cap_fs_ext::DirExt::access(self.dir.borrow(), path, type_)
+        })
+    }
+
+    /// Tests whether the object at `path` is accessible in the given way,
+    /// without following symlinks, taking the view's restrictions into
+    /// account.
+    ///
+    /// A readonly view reports write access as denied, even if the
+    /// underlying object is writable.
+    #[cfg(feature = "cap-fs-ext")]
+    #[inline]
+    pub fn access_symlink<P: AsRef<Path>>(&self, path: P, type_: AccessType) -> io::Result<()> {
+        self.audited(Operation::Access, path, |path| {
+            self.check_access(type_, path)?;
+            cap_fs_ext::DirExt::access_symlink(self.dir.borrow(), path, type_)
+        })
+    }
+
+    /// Constructs a new instance of `Self` by opening the parent directory
+    /// (aka "..") of `self`, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function accesses a directory outside of the `self` subtree.
+    #[inline]
+    pub fn open_parent_dir(
+        &self,
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<DirView> {
+        self.audited(Operation::OpenDir, "..", |_| {
+            Ok(DirView {
+                dir: self.dir.borrow().open_parent_dir(ambient_authority)?,
+                view_kind,
+                policy: Policy::with_permit(
+                    Policy::for_location(&self.policy, None),
+                    Operation::OpenDir,
+                    Path::new(".."),
+                )?,
+                depth: self.depth.saturating_sub(1),
+            })
+        })
+    }
+
+    /// Constructs a new readonly view by opening the parent directory
+    /// (aka "..") of `self`, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function accesses a directory outside of the `self` subtree.
+    #[inline]
+    pub fn open_parent_dir_readonly(
+        &self,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<DirView> {
+        self.open_parent_dir(ViewKind::Readonly, ambient_authority)
+    }
+
+    /// Returns a view of `dir`, which is at `location` within `self` and
+    /// was opened from `path`.
+    fn child(
+        &self,
+        path: &Path,
+        dir: Dir,
+        depth: usize,
+        location: Option<&Path>,
+    ) -> io::Result<DirView> {
+        Ok(DirView {
+            dir,
+            view_kind: self.policy.kind_at(self.view_kind, location),
+            policy: Policy::with_permit(
+                Policy::for_location(&self.policy, location),
+                Operation::OpenDir,
+                path,
+            )?,
+            depth,
+        })
+    }
+
+    pub(crate) fn open_child_dir(&self, path: &Path) -> io::Result<DirView> {
+        self.check_resolve(Operation::OpenDir, path)?;
+        let depth = self
+            .policy
+            .descend(self.dir.borrow(), self.depth, Operation::OpenDir, path)?;
+        let dir = self.dir.borrow().open_dir(path)?;
+        let location = self.policy.locate(self.dir.borrow(), path);
+        self.child(path, dir, depth, location.as_deref())
+    }
+
+    /// Returns the restrictions of `self`, over its directory.
+    pub(crate) fn restrictions(&self) -> Restrictions<'_, Dir> {
+        Restrictions {
+            backend: self.dir.borrow(),
+            policy: &self.policy,
+            view_kind: self.view_kind,
+            view_type: ViewType::DirView,
+        }
+    }
+
+    /// Performs `f` on `path`, reporting the outcome to the audit hook.
+    pub(crate) fn audited<P: AsRef<Path>, T>(
+        &self,
+        operation: Operation,
+        path: P,
+        f: impl FnOnce(&Path) -> io::Result<T>,
+    ) -> io::Result<T> {
+        self.restrictions().audited(operation, path.as_ref(), f)
+    }
+
+    /// Performs `f` on `path` and `target`, reporting the outcome to the
+    /// audit hook.
+    pub(crate) fn audited_pair<P: AsRef<Path>, Q: AsRef<Path>, T>(
+        &self,
+        operation: Operation,
+        path: P,
+        target: Q,
+        f: impl FnOnce(&Path, &Q) -> io::Result<T>,
+    ) -> io::Result<T> {
+        self.restrictions()
+            .audited_pair(operation, path.as_ref(), target.as_ref(), |path| {
+                f(path, &target)
+            })
+    }
+
+    /// Returns `path` with its components normalized and matched to the
+    /// entries they name, if `self` normalizes names or resolves paths
+    /// case-insensitively.
+    pub(crate) fn fold_names<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        self.restrictions().fold_names(path)
+    }
+
+    /// Checks whether a symbolic link at `link` may point to `target`.
+    pub(crate) fn check_symlink_target(&self, link: &Path, target: &Path) -> io::Result<()> {
+        self.restrictions().check_symlink_target(link, target)
+    }
+
+    pub(crate) fn check_resolve(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.restrictions().check_resolve(operation, path)
+    }
+
+    /// Returns the kind governing `operation` on `path`, which differs from
+    /// the kind of `self` in subtrees with overridden kinds.
+    pub(crate) fn kind_for(&self, operation: Operation, path: &Path) -> ViewKind {
+        self.restrictions().kind_for(operation, path)
+    }
+
+    /// Checks whether `operation` may obtain a writable handle for `path`.
+    pub(crate) fn check_mutation(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.restrictions().check_mutation(operation, path)
+    }
+
+    /// Checks whether `operation` may change `path`, possibly only as a dry
+    /// run.
+    pub(crate) fn check_change(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.restrictions().check_change(operation, path)
+    }
+
+    /// Checks whether `operation` may change `path`. If `self` is a dry-run
+    /// view, this also checks that the change could be made, records it, and
+    /// returns `true`, so that the caller returns without making it.
+    pub(crate) fn dry_run(
+        &self,
+        operation: Operation,
+        path: &Path,
+        target: Option<&Path>,
+    ) -> io::Result<bool> {
+        self.restrictions().dry_run(operation, path, target)
+    }
+
+    /// Like [`Self::dry_run`], for operations on `path` in `self` and
+    /// `target` in `other`, which are dry runs if either view is. This
+    /// doesn't check whether the operation may change either path.
+    pub(crate) fn dry_run_pair<E: Borrow<Dir>>(
+        &self,
+        other: &DirView<E>,
+        operation: Operation,
+        path: &Path,
+        target: &Path,
+    ) -> io::Result<bool> {
+        let policy = match (
+            self.kind_for(operation, path),
+            other.kind_for(operation, target),
+        ) {
+            (ViewKind::DryRun, _) => &self.policy,
+            (_, ViewKind::DryRun) => &other.policy,
+            _ => return Ok(false),
+        };
+        self.dir.borrow().symlink_metadata(path)?;
+        policy.dry_run_log.record(operation, path, Some(target));
+        Ok(true)
+    }
+
+    #[cfg(feature = "cap-fs-ext")]
+    fn check_access(&self, type_: AccessType, path: &Path) -> io::Result<()> {
+        match type_ {
+            AccessType::Access(modes) if modes.writable => {
+                self.check_change(Operation::Access, path)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DirView {
+    /// Converts `self` into the file descriptor of the underlying directory,
+    /// if it's a full view which imposes no other restrictions, or returns
+    /// `self` otherwise.
+    ///
+    /// See [`Self::as_fd`].
+    #[cfg(not(windows))]
+    #[inline]
+    pub fn into_fd(self) -> Result<OwnedFd, Self> {
+        match self.as_dir() {
+            Some(_) => Ok(self.dir.into()),
+            None => Err(self),
+        }
+    }
+
+    /// Converts `self` into the handle of the underlying directory, if it's
+    /// a full view which imposes no other restrictions, or returns `self`
+    /// otherwise.
+    ///
+    /// See [`Self::as_handle`].
+    #[cfg(windows)]
+    #[inline]
+    pub fn into_handle(self) -> Result<OwnedHandle, Self> {
+        match self.as_dir() {
+            Some(_) => Ok(self.dir.into()),
+            None => Err(self),
+        }
+    }
+
+    /// Converts `self` into a [`DirViewUtf8`] with the same [`ViewKind`].
+    ///
+    /// This corresponds to [`cap_std::fs_utf8::Dir::from_cap_std`].
+    #[cfg(feature = "fs_utf8")]
+    #[inline]
+    pub fn into_utf8(self) -> DirViewUtf8 {
+        DirViewUtf8 {
+            dir: cap_std::fs_utf8::Dir::from_cap_std(self.dir),
+            view_kind: self.view_kind,
+            policy: self.policy,
+            depth: self.depth,
+        }
+    }
+
+    /// Remove the directory referenced by `self` and consume `self`.
+    ///
+    /// Even though this implementation works in terms of handles as much as
+    /// possible, removal is not guaranteed to be atomic with respect to a
+    /// concurrent rename of the directory.
+    #[inline]
+    pub fn remove_open_dir(self) -> io::Result<()> {
+        let policy = Arc::clone(&self.policy);
+        let view_kind = self.view_kind;
+        let result = match self.dry_run(Operation::RemoveDir, Path::new("."), None) {
+            Ok(false) => self.dir.remove_open_dir(),
+            Ok(true) => Ok(()),
+            Err(err) => Err(err),
+        };
+        policy.audit(
+            view_kind,
+            Operation::RemoveDir,
+            Path::new("."),
+            None,
+            &result,
+        );
+        result
+    }
+
+    /// Removes the directory referenced by `self`, like
+    /// [`Self::remove_open_dir`], but returns `self` together with the error
+    /// if the removal is denied or fails, so that the view remains usable.
+    ///
+    /// The directory is removed through a duplicate of its handle, and
+    /// `self` is only dropped once the removal has succeeded.
+    pub fn try_remove_open_dir(self) -> Result<(), (Self, io::Error)> {
+        let result = match self.dry_run(Operation::RemoveDir, Path::new("."), None) {
+            Ok(false) => self.dir.try_clone().and_then(|dir| dir.remove_open_dir()),
+            Ok(true) => Ok(()),
+            Err(err) => Err(err),
+        };
+        self.policy.audit(
+            self.view_kind,
+            Operation::RemoveDir,
+            Path::new("."),
+            None,
+            &result,
+        );
+        result.map_err(|err| (self, err))
+    }
+
+    /// Removes the directory referenced by `self`, after removing all its
+    /// contents, and consume `self`. Use carefully!
+    ///
+    /// Even though this implementation works in terms of handles as much as
+    /// possible, removal is not guaranteed to be atomic with respect to a
+    /// concurrent rename of the directory.
+    #[inline]
+    pub fn remove_open_dir_all(self) -> io::Result<()> {
+        let policy = Arc::clone(&self.policy);
+        let view_kind = self.view_kind;
+        let result = match self.dry_run(Operation::RemoveDirAll, Path::new("."), None) {
+            Ok(false) => self.dir.remove_open_dir_all(),
+            Ok(true) => Ok(()),
+            Err(err) => Err(err),
+        };
+        policy.audit(
+            view_kind,
+            Operation::RemoveDirAll,
+            Path::new("."),
+            None,
+            &result,
+        );
+        result
+    }
+
+    /// Removes the directory referenced by `self`, after removing all its
+    /// contents, like [`Self::remove_open_dir_all`], but returns `self`
+    /// together with the error if the removal is denied or fails, so that
+    /// the view remains usable. Use carefully!
+    ///
+    /// The directory is removed through a duplicate of its handle, and
+    /// `self` is only dropped once the removal has succeeded.
+    pub fn try_remove_open_dir_all(self) -> Result<(), (Self, io::Error)> {
+        let result = match self.dry_run(Operation::RemoveDirAll, Path::new("."), None) {
+            Ok(false) => self
+                .dir
+                .try_clone()
+                .and_then(|dir| dir.remove_open_dir_all()),
+            Ok(true) => Ok(()),
+            Err(err) => Err(err),
+        };
+        self.policy.audit(
+            self.view_kind,
+            Operation::RemoveDirAll,
+            Path::new("."),
+            None,
+            &result,
+        );
+        result.map_err(|err| (self, err))
     }
 
     /// Constructs a new instance of `Self` by opening the given path as a
@@ -547,34 +1465,51 @@ impl DirView {
     ///
     /// This function is not sandboxed and may access any path that the host
     /// process has access to.
+    ///
+    /// On WASI, the host process' ambient authority is the set of preopened
+    /// directories, so this is how a guest opens a preopen, by the name the
+    /// host gave it, such as `/data` or `.`. `DirView::preopens` opens all of
+    /// them.
     #[inline]
     pub fn open_ambient_dir<P: AsRef<Path>>(
         path: P,
         view_kind: ViewKind,
         ambient_authority: AmbientAuthority,
     ) -> io::Result<Self> {
-        Ok(Self {
-            dir: Dir::open_ambient_dir(path, ambient_authority)?,
+        Ok(Self::from_dir(
+            Dir::open_ambient_dir(path, ambient_authority)?,
             view_kind,
-        })
+        ))
     }
 
-    /// Constructs a new instance of `Self` by opening the parent directory
-    /// (aka "..") of `self`, using the host process' ambient authority.
+    /// Constructs a new readonly view by opening the given path as a
+    /// directory using the host process' ambient authority.
     ///
     /// # Ambient Authority
     ///
-    /// This function accesses a directory outside of the `self` subtree.
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
     #[inline]
-    pub fn open_parent_dir(
-        &self,
-        view_kind: ViewKind,
+    pub fn open_ambient_dir_readonly<P: AsRef<Path>>(
+        path: P,
         ambient_authority: AmbientAuthority,
     ) -> io::Result<Self> {
-        Ok(Self {
-            dir: self.dir.open_parent_dir(ambient_authority)?,
-            view_kind,
-        })
+        Self::open_ambient_dir(path, ViewKind::Readonly, ambient_authority)
+    }
+
+    /// Constructs a new full view by opening the given path as a directory
+    /// using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    #[inline]
+    pub fn open_ambient_dir_full<P: AsRef<Path>>(
+        path: P,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        Self::open_ambient_dir(path, ViewKind::Full, ambient_authority)
     }
 
     /// Construct a new instance of `Self` from existing directory file
@@ -583,36 +1518,47 @@ impl DirView {
     /// This can be useful when interacting with other libraries and or C/C++
     /// code which has invoked `openat(..., O_DIRECTORY)` external to this
     /// crate.
+    ///
+    /// The directory is reopened with `openat(dir, ".")`, so on Linux `dir`
+    /// may also be an `O_PATH` descriptor.
     pub fn reopen_dir<Filelike: AsFilelike>(
         dir: &Filelike,
         view_kind: ViewKind,
     ) -> io::Result<Self> {
-        Ok(Self {
-            dir: Dir::reopen_dir(dir)?,
-            view_kind,
-        })
+        Ok(Self::from_dir(Dir::reopen_dir(dir)?, view_kind))
     }
 
-    fn check_mutation(&self) -> io::Result<()> {
-        match self.view_kind {
-            ViewKind::Full => Ok(()),
-            ViewKind::Readonly => Err(Self::readonly()),
-        }
+    /// Construct a new instance of `Self` from a borrowed directory file
+    /// descriptor, such as an `O_PATH` descriptor received from another
+    /// process.
+    ///
+    /// This is equivalent to [`Self::reopen_dir`]; `fd` is not consumed. On
+    /// WASI, `fd` may be a preopened directory's descriptor, which the libc
+    /// keeps open for resolving paths by name.
+    #[cfg(not(windows))]
+    #[inline]
+    pub fn reopen_dir_fd(fd: BorrowedFd<'_>, view_kind: ViewKind) -> io::Result<Self> {
+        Self::reopen_dir(&fd, view_kind)
     }
+}
 
-    fn readonly() -> io::Error {
-        io::Error::new(
-            io::ErrorKind::PermissionDenied,
-            "attempt to modify a directory tree through a read-only `DirView`",
-        )
+impl<D: Clone> Clone for DirView<D> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            dir: self.dir.clone(),
+            view_kind: self.view_kind,
+            policy: Arc::clone(&self.policy),
+            depth: self.depth,
+        }
     }
 }
 
-impl fmt::Debug for DirView {
+impl<D: Borrow<Dir>> fmt::Debug for DirView<D> {
     // Like libstd's version, but doesn't print the path.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut b = f.debug_struct("DirView");
-        b.field("dir", &self.dir);
+        b.field("dir", self.dir.borrow());
         #[cfg(windows)]
         b.field("view_kind", &self.view_kind);
         b.finish()
@@ -622,13 +1568,21 @@ impl fmt::Debug for DirView {
 #[cfg(feature = "cap-fs-ext")]
 impl cap_fs_ext::DirExt for DirView {
     fn set_atime<P: AsRef<Path>>(&self, path: P, atime: SystemTimeSpec) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::set_atime(&self.dir, path, atime)
+        self.audited(Operation::SetTimes, path, |path| {
+            if self.dry_run(Operation::SetTimes, path, None)? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::set_atime(&self.dir, path, atime)
+        })
     }
 
     fn set_mtime<P: AsRef<Path>>(&self, path: P, mtime: SystemTimeSpec) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::set_mtime(&self.dir, path, mtime)
+        self.audited(Operation::SetTimes, path, |path| {
+            if self.dry_run(Operation::SetTimes, path, None)? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::set_mtime(&self.dir, path, mtime)
+        })
     }
 
     fn set_times<P: AsRef<Path>>(
@@ -637,8 +1591,12 @@ impl cap_fs_ext::DirExt for DirView {
         atime: Option<SystemTimeSpec>,
         mtime: Option<SystemTimeSpec>,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::set_times(&self.dir, path, atime, mtime)
+        self.audited(Operation::SetTimes, path, |path| {
+            if self.dry_run(Operation::SetTimes, path, None)? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::set_times(&self.dir, path, atime, mtime)
+        })
     }
 
     fn set_symlink_times<P: AsRef<Path>>(
@@ -647,46 +1605,69 @@ impl cap_fs_ext::DirExt for DirView {
         atime: Option<SystemTimeSpec>,
         mtime: Option<SystemTimeSpec>,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::set_symlink_times(&self.dir, path, atime, mtime)
+        self.audited(Operation::SetTimes, path, |path| {
+            if self.dry_run(Operation::SetTimes, path, None)? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::set_symlink_times(&self.dir, path, atime, mtime)
+        })
     }
 
     fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::symlink(&self.dir, src, dst)
+        self.audited_pair(Operation::Symlink, dst, src, |dst, src| {
+            self.policy.check_symlink(dst)?;
+            self.check_symlink_target(dst, src.as_ref())?;
+            if self.dry_run(Operation::Symlink, dst, Some(src.as_ref()))? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::symlink(&self.dir, src, dst)
+        })
     }
 
     fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::symlink_file(&self.dir, src, dst)
+        self.audited_pair(Operation::Symlink, dst, src, |dst, src| {
+            self.policy.check_symlink(dst)?;
+            self.check_symlink_target(dst, src.as_ref())?;
+            if self.dry_run(Operation::Symlink, dst, Some(src.as_ref()))? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::symlink_file(&self.dir, src, dst)
+        })
     }
 
     fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::symlink_dir(&self.dir, src, dst)
+        self.audited_pair(Operation::Symlink, dst, src, |dst, src| {
+            self.policy.check_symlink(dst)?;
+            self.check_symlink_target(dst, src.as_ref())?;
+            if self.dry_run(Operation::Symlink, dst, Some(src.as_ref()))? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::symlink_dir(&self.dir, src, dst)
+        })
     }
 
     fn open_dir_nofollow<P: AsRef<Path>>(&self, path: P) -> io::Result<Self>
     where
         Self: Sized,
     {
-        Ok(Self {
-            dir: self.dir.open_dir_nofollow(path)?,
-            view_kind: self.view_kind,
-        })
+        Self::open_dir_nofollow(self, path)
     }
 
     fn remove_file_or_symlink<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::remove_file_or_symlink(&self.dir, path)
+        self.audited(Operation::RemoveFile, path, |path| {
+            if self.dry_run(Operation::RemoveFile, path, None)? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::remove_file_or_symlink(&self.dir, path)
+        })
     }
 
     fn access<P: AsRef<Path>>(&self, path: P, type_: AccessType) -> io::Result<()> {
-        cap_fs_ext::DirExt::access(&self.dir, path, type_)
+        Self::access(self, path, type_)
     }
 
     fn access_symlink<P: AsRef<Path>>(&self, path: P, type_: AccessType) -> io::Result<()> {
-        cap_fs_ext::DirExt::access_symlink(&self.dir, path, type_)
+        Self::access_symlink(self, path, type_)
     }
 
     fn set_symlink_permissions<P: AsRef<Path>>(
@@ -694,7 +1675,24 @@ impl cap_fs_ext::DirExt for DirView {
         path: P,
         perm: Permissions,
     ) -> io::Result<()> {
-        self.check_mutation()?;
-        cap_fs_ext::DirExt::set_symlink_permissions(&self.dir, path, perm)
+        self.audited(Operation::SetPermissions, path, |path| {
+            if self.dry_run(Operation::SetPermissions, path, None)? {
+                return Ok(());
+            }
+            cap_fs_ext::DirExt::set_symlink_permissions(&self.dir, path, perm)
+        })
+    }
+}
+
+/// Removes everything in `dir`, without removing `dir` itself.
+pub(crate) fn remove_contents(dir: &Dir) -> io::Result<()> {
+    for entry in dir.entries()? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dir.remove_dir_all(entry.file_name())?;
+        } else {
+            entry.remove_file()?;
+        }
     }
+    Ok(())
 }
@@ -1,8 +1,17 @@
-use crate::{DirView, ViewKind};
-use cap_std::fs::{DirEntry, File, FileType, Metadata, OpenOptions};
+#[cfg(not(windows))]
+use crate::policy::is_dot_file;
+use crate::policy::Policy;
+use crate::raw_dir::Entry;
+#[cfg(feature = "fs_utf8")]
+use crate::DirEntryViewUtf8;
+use crate::{DirView, Operation, ViewKind};
+use cap_std::fs::{File, FileType, Metadata, OpenOptions};
 #[cfg(not(windows))]
 use rustix::fs::DirEntryExt;
+use std::borrow::Cow;
 use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use std::{fmt, io};
 
 /// Entries returned by the `ReadDir` iterator.
@@ -20,96 +29,361 @@ use std::{fmt, io};
 /// There is no `from_std` method, as `std::fs::DirEntry` doesn't provide a
 /// way to construct a `DirEntry` without opening directories by ambient paths.
 pub struct DirEntryView {
-    pub(crate) entry: DirEntry,
+    pub(crate) entry: Entry,
     pub(crate) view_kind: ViewKind,
+    pub(crate) policy: Arc<Policy>,
+    pub(crate) depth: usize,
+    pub(crate) parent: Arc<Path>,
+    /// The entry's metadata, once it's been read. This is boxed to keep
+    /// entries small when it isn't.
+    pub(crate) metadata: OnceLock<Box<Metadata>>,
 }
 
 impl DirEntryView {
     /// Open the file for reading.
     #[inline]
     pub fn open(&self) -> io::Result<File> {
-        self.entry.open()
+        self.audited(Operation::Open, || {
+            self.policy
+                .check_unbudgeted(Operation::Open, &self.path())?;
+            self.entry.open()
+        })
     }
 
     /// Open the file with the given options.
     #[inline]
     pub fn open_with(&self, options: &OpenOptions) -> io::Result<File> {
-        // Override any flag that allows writing.
-        let mut options = options.clone();
-        match self.view_kind {
-            ViewKind::Full => {}
-            ViewKind::Readonly => {
-                // Override any flag that allows writing.
-                options.append(false);
-                options.truncate(false);
-                options.write(false);
-                options.create(false);
-                options.create_new(false);
-            }
-        }
-        self.entry.open_with(&options)
+        self.audited(Operation::Open, || {
+            self.policy
+                .check_unbudgeted(Operation::Open, &self.path())?;
+            let options = self.kind().open_options(options);
+            self.entry.open_with(&options)
+        })
     }
 
     /// Open the entry as a directory.
     #[inline]
     pub fn open_dir(&self) -> io::Result<DirView> {
-        Ok(DirView {
-            dir: self.entry.open_dir()?,
-            view_kind: self.view_kind,
+        self.audited(Operation::OpenDir, || self.open_child_dir())
+    }
+
+    /// Open the entry as a directory, as a view of the given kind.
+    ///
+    /// This fails if `view_kind` is broader than the kind of the directory
+    /// in the view this entry was obtained from.
+    #[inline]
+    pub fn open_dir_view(&self, view_kind: ViewKind) -> io::Result<DirView> {
+        self.audited(Operation::OpenDir, || {
+            if self.policy.overrides.is_none() {
+                self.policy
+                    .narrow(self.view_kind, view_kind, &self.path())?;
+            }
+            let mut view = self.open_child_dir()?;
+            view.view_kind = self
+                .policy
+                .narrow(view.view_kind, view_kind, &self.path())?;
+            view.policy = Policy::narrowed(view.policy, view_kind);
+            Ok(view)
         })
     }
 
     /// Removes the file from its filesystem.
     #[inline]
     pub fn remove_file(&self) -> io::Result<()> {
-        self.check_mutation()?;
-        self.entry.remove_file()
+        self.audited(Operation::RemoveFile, || {
+            if self.dry_run(Operation::RemoveFile)? {
+                return Ok(());
+            }
+            self.entry.remove_file()
+        })
     }
 
     /// Removes the directory from its filesystem.
     #[inline]
     pub fn remove_dir(&self) -> io::Result<()> {
-        self.check_mutation()?;
-        self.entry.remove_dir()
+        self.audited(Operation::RemoveDir, || {
+            if self.dry_run(Operation::RemoveDir)? {
+                return Ok(());
+            }
+            self.entry.remove_dir()
+        })
+    }
+
+    /// Removes the entry from its filesystem, as a directory if it's a
+    /// directory and as a file otherwise.
+    ///
+    /// Directories must be empty; use [`Self::remove_all`] to remove their
+    /// contents too. Symbolic links are removed, rather than their targets.
+    #[inline]
+    pub fn remove(&self) -> io::Result<()> {
+        if self.file_type()?.is_dir() {
+            self.remove_dir()
+        } else {
+            self.remove_file()
+        }
+    }
+
+    /// Removes the entry from its filesystem, including all the contents
+    /// if it's a directory. Use carefully!
+    ///
+    /// Like [`DirView::remove_open_dir_all`], this works in terms of
+    /// handles as much as possible, but removal is not guaranteed to be
+    /// atomic with respect to a concurrent rename of the directory.
+    pub fn remove_all(&self) -> io::Result<()> {
+        if !self.file_type()?.is_dir() {
+            return self.remove_file();
+        }
+        self.audited(Operation::RemoveDirAll, || {
+            if self.dry_run(Operation::RemoveDirAll)? {
+                return Ok(());
+            }
+            self.entry.open_dir()?.remove_open_dir_all()
+        })
     }
 
     /// Returns the metadata for the file that this entry points at.
     ///
-    /// This corresponds to [`std::fs::DirEntry::metadata`].
+    /// This corresponds to [`std::fs::DirEntry::metadata`]. The metadata is
+    /// read once and then cached on the entry, so it describes the file as
+    /// it was when first read. On Windows it comes from the directory
+    /// enumeration itself, without any further system calls. Use
+    /// [`Self::fresh_metadata`] to read current values.
     #[inline]
     pub fn metadata(&self) -> io::Result<Metadata> {
-        self.entry.metadata()
+        self.audited(Operation::Metadata, || self.cached_metadata())
+    }
+
+    /// Reads the current metadata for the file that this entry points at,
+    /// bypassing the metadata cached on the entry.
+    ///
+    /// On Windows this opens the file, since the directory enumeration's
+    /// metadata may be stale.
+    #[inline]
+    pub fn fresh_metadata(&self) -> io::Result<Metadata> {
+        self.audited(Operation::Metadata, || {
+            #[cfg(windows)]
+            return self.entry.full_metadata();
+            #[cfg(not(windows))]
+            return self.entry.metadata();
+        })
     }
 
     /// Returns the file type for the file that this entry points at.
     ///
-    /// This corresponds to [`std::fs::DirEntry::file_type`].
+    /// This corresponds to [`std::fs::DirEntry::file_type`]. It uses the
+    /// type reported by the directory listing when there is one, and
+    /// otherwise reads the entry's metadata.
     #[inline]
     pub fn file_type(&self) -> io::Result<FileType> {
-        self.entry.file_type()
+        match self.entry.file_type_hint() {
+            Some(file_type) => Ok(file_type),
+            None => Ok(self.cached_metadata()?.file_type()),
+        }
+    }
+
+    /// Returns the file type reported by the directory listing, without
+    /// any further system calls, or `None` if it didn't report one.
+    ///
+    /// Some filesystems don't report types in directory listings, and some
+    /// platforms don't support them at all. And on Linux, when iterating
+    /// with [`DirViewBuilder::read_dir_buffer_size`], only directories and
+    /// regular files are reported. Callers which only need to distinguish
+    /// directories from other entries can use this where it's available,
+    /// and fall back to [`Self::file_type`].
+    ///
+    /// [`DirViewBuilder::read_dir_buffer_size`]: crate::DirViewBuilder::read_dir_buffer_size
+    #[inline]
+    pub fn file_type_hint(&self) -> Option<FileType> {
+        self.entry.file_type_hint()
     }
 
     /// Returns the bare file name of this directory entry without any other
     /// leading path component.
     ///
-    /// This corresponds to [`std::fs::DirEntry::file_name`].
+    /// This corresponds to [`std::fs::DirEntry::file_name`]. For views
+    /// which normalize names with [`DirViewBuilder::normalize_names`], this
+    /// is the normalized name.
+    ///
+    /// [`DirViewBuilder::normalize_names`]: crate::DirViewBuilder::normalize_names
     #[inline]
     pub fn file_name(&self) -> OsString {
-        self.entry.file_name()
+        let name = self.entry.file_name();
+        match self.policy.normalize_name(&name) {
+            Cow::Borrowed(_) => name,
+            Cow::Owned(normalized) => normalized,
+        }
+    }
+
+    /// Returns the path of this entry relative to the view it was read
+    /// through, which is the path passed to `read_dir` joined with the
+    /// entry's name.
+    ///
+    /// Entries read with `entries`, including by iterating over a
+    /// subdirectory opened with [`Self::open_dir`], are relative to the view
+    /// they were read from, so this is just their name.
+    #[inline]
+    pub fn relative_path(&self) -> PathBuf {
+        self.parent.join(self.file_name())
+    }
+
+    /// Returns the bare file name of this directory entry as a `String`.
+    ///
+    /// This fails with [`io::ErrorKind::InvalidData`] if the name isn't
+    /// valid UTF-8, as `DirEntryViewUtf8::file_name` does.
+    #[inline]
+    pub fn file_name_utf8(&self) -> io::Result<String> {
+        self.file_name().into_string().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "filesystem path is not valid UTF-8",
+            )
+        })
+    }
+
+    /// Returns the bare file name of this directory entry for display,
+    /// with any invalid UTF-8 replaced by `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// This corresponds to [`std::ffi::OsStr::to_string_lossy`].
+    #[inline]
+    pub fn file_name_lossy(&self) -> Cow<'_, str> {
+        match self.file_name().into_string() {
+            Ok(name) => Cow::Owned(name),
+            Err(name) => Cow::Owned(name.to_string_lossy().into_owned()),
+        }
     }
 
-    fn check_mutation(&self) -> io::Result<()> {
-        match self.view_kind {
-            ViewKind::Full => Ok(()),
-            ViewKind::Readonly => Err(Self::readonly()),
+    /// Converts `self` into a [`DirEntryViewUtf8`] with the same
+    /// [`ViewKind`], or returns `self` if its name isn't valid UTF-8.
+    #[cfg(feature = "fs_utf8")]
+    pub fn into_utf8(self) -> Result<DirEntryViewUtf8, Self> {
+        let parent = match camino::Utf8Path::from_path(&self.parent) {
+            Some(parent) if self.file_name().to_str().is_some() => parent.into(),
+            _ => return Err(self),
+        };
+        Ok(DirEntryViewUtf8 {
+            entry: self.entry.into_utf8(),
+            view_kind: self.view_kind,
+            policy: self.policy,
+            depth: self.depth,
+            parent,
+            metadata: self.metadata,
+        })
+    }
+
+    /// Returns `true` if this entry is hidden by the platform's convention:
+    /// if its name starts with `.` on Unix-family platforms, or if it has
+    /// the hidden attribute on Windows, which is read from the entry's
+    /// full metadata.
+    ///
+    /// This is the convention [`DirViewBuilder::hide_hidden_files`] uses on
+    /// Unix-family platforms.
+    ///
+    /// [`DirViewBuilder::hide_hidden_files`]: crate::DirViewBuilder::hide_hidden_files
+    #[cfg(not(windows))]
+    #[inline]
+    pub fn is_hidden(&self) -> io::Result<bool> {
+        Ok(is_dot_file(self.file_name().as_encoded_bytes()))
+    }
+
+    /// Returns `true` if this entry is hidden by the platform's convention:
+    /// if its name starts with `.` on Unix-family platforms, or if it has
+    /// the hidden attribute on Windows, which is read from the entry's
+    /// full metadata.
+    ///
+    /// This is the convention [`DirViewBuilder::hide_hidden_files`] uses on
+    /// Unix-family platforms.
+    ///
+    /// [`DirViewBuilder::hide_hidden_files`]: crate::DirViewBuilder::hide_hidden_files
+    #[cfg(windows)]
+    #[inline]
+    pub fn is_hidden(&self) -> io::Result<bool> {
+        use cap_std::fs::MetadataExt;
+        use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_HIDDEN;
+
+        let attributes = self.entry.full_metadata()?.file_attributes();
+        Ok(attributes & FILE_ATTRIBUTE_HIDDEN != 0)
+    }
+
+    fn open_child_dir(&self) -> io::Result<DirView> {
+        let depth = self.depth + 1;
+        let symlink = self.policy.max_depth.is_some() && self.file_type()?.is_symlink();
+        self.policy
+            .check_entry_depth(depth, symlink, Operation::OpenDir, &self.path())?;
+        let location = self.location();
+        let dir = self.entry.open_dir()?;
+        Ok(DirView {
+            dir,
+            view_kind: self.policy.kind_at(self.view_kind, location.as_deref()),
+            policy: Policy::with_permit(
+                Policy::for_location(&self.policy, location.as_deref()),
+                Operation::OpenDir,
+                &self.path(),
+            )?,
+            depth,
+        })
+    }
+
+    /// Returns the location of the entry within the directory it was read
+    /// from, if the view has overridden kinds and the entry isn't a symbolic
+    /// link, which may point anywhere.
+    fn location(&self) -> Option<PathBuf> {
+        self.policy.overrides.as_ref()?;
+        match self.file_type() {
+            Ok(file_type) if !file_type.is_symlink() => Some(PathBuf::from(self.file_name())),
+            _ => None,
+        }
+    }
+
+    /// Returns the kind governing writes to the entry, which, if it's a
+    /// symbolic link, is the narrowest kind in the view.
+    fn kind(&self) -> ViewKind {
+        match self.location() {
+            None if self.policy.overrides.is_some() => self.policy.kind_at(self.view_kind, None),
+            _ => self.view_kind,
+        }
+    }
+
+    /// Returns the entry's metadata, reading it if it hasn't been read yet.
+    fn cached_metadata(&self) -> io::Result<Metadata> {
+        if let Some(metadata) = self.metadata.get() {
+            return Ok(Metadata::clone(metadata));
+        }
+        let metadata = Box::new(self.entry.metadata()?);
+        Ok(Metadata::clone(self.metadata.get_or_init(|| metadata)))
+    }
+
+    /// Performs `f` on the entry, reporting the outcome to the audit hook.
+    fn audited<T>(&self, operation: Operation, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+        let result = f();
+        if self.policy.is_observed() {
+            self.policy
+                .audit(self.view_kind, operation, &self.path(), None, &result);
+        }
+        result
+    }
+
+    /// Checks whether `operation` may change the entry. In dry-run views,
+    /// this records the change and returns `true` instead.
+    fn dry_run(&self, operation: Operation) -> io::Result<bool> {
+        match self.policy.kind_within(self.view_kind, &self.path()) {
+            ViewKind::Full => Ok(false),
+            ViewKind::Readonly => Err(self.policy.denied(
+                "attempt to modify a directory tree through a read-only `DirView`",
+                operation,
+                &self.path(),
+            )),
+            ViewKind::DryRun => {
+                self.policy
+                    .dry_run_log
+                    .record(operation, &self.path(), None);
+                Ok(true)
+            }
         }
     }
 
-    fn readonly() -> io::Error {
-        io::Error::new(
-            io::ErrorKind::PermissionDenied,
-            "attempt to modify a directory tree through a read-only `DirView`",
-        )
+    /// The entry's name, for use in error messages.
+    fn path(&self) -> PathBuf {
+        PathBuf::from(self.entry.file_name())
     }
 }
 
@@ -126,7 +400,7 @@ impl DirEntryExt for DirEntryView {
 impl cap_primitives::fs::_WindowsDirEntryExt for DirEntryView {
     #[inline]
     fn full_metadata(&self) -> io::Result<Metadata> {
-        cap_primitives::fs::_WindowsDirEntryExt::full_metadata(&self.entry)
+        self.entry.full_metadata()
     }
 }
 
@@ -139,6 +413,6 @@ impl fmt::Debug for DirEntryView {
 #[cfg(feature = "cap-fs-ext")]
 impl cap_fs_ext::DirEntryExt for DirEntryView {
     fn full_metadata(&self) -> io::Result<Metadata> {
-        cap_fs_ext::DirEntryExt::full_metadata(&self.entry)
+        self.entry.cap_fs_ext_full_metadata()
     }
 }
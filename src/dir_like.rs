@@ -0,0 +1,654 @@
+use crate::{
+    ArchiveDirEntry, ArchiveDirView, ArchiveFile, ArchiveMetadata, ArchiveReadDir, DirEntryView,
+    DirView, ReadDirView, ZipDirView,
+};
+#[cfg(feature = "fs_utf8")]
+use crate::{DirEntryViewUtf8, DirViewUtf8, ReadDirViewUtf8};
+#[cfg(feature = "testing")]
+use crate::{MemDirEntry, MemDirView, MemFile, MemMetadata, MemReadDir};
+#[cfg(feature = "fs_utf8")]
+use camino::{Utf8Path, Utf8PathBuf};
+use cap_std::fs::{Dir, DirEntry, File, Metadata, ReadDir};
+use std::borrow::Borrow;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The read-side operations common to [`Dir`] and views of it.
+///
+/// This allows code to be generic over whether it's given a raw capability
+/// or a restricted view. It's implemented by [`Dir`], [`DirView`],
+/// [`ArchiveDirView`], and [`ZipDirView`], with the `fs_utf8` feature by
+/// [`cap_std::fs_utf8::Dir`] and [`DirViewUtf8`], and with the `testing`
+/// feature by [`MemDirView`].
+///
+/// [`MemDirView`]: crate::MemDirView
+pub trait DirLike {
+    /// The borrowed path type accepted by this directory type.
+    type Path: ?Sized;
+
+    /// The owned path type returned by this directory type.
+    type PathBuf;
+
+    /// The type of files opened through this directory type.
+    type File;
+
+    /// The type of directories opened through this directory type.
+    type Dir: DirLike<Path = Self::Path>;
+
+    /// The type of entries yielded by [`DirLike::read_dir`].
+    type DirEntry;
+
+    /// The iterator type returned by [`DirLike::read_dir`].
+    type ReadDir: Iterator<Item = io::Result<Self::DirEntry>>;
+
+    /// The type of metadata returned by [`DirLike::metadata`].
+    type Metadata;
+
+    /// Attempts to open a file in read-only mode.
+    fn open<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Self::File>;
+
+    /// Attempts to open a directory.
+    fn open_dir<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Self::Dir>;
+
+    /// Read the entire contents of a file into a bytes vector.
+    fn read<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Vec<u8>>;
+
+    /// Read the entire contents of a file into a string.
+    fn read_to_string<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<String>;
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    fn read_link<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Self::PathBuf>;
+
+    /// Given a path, query the file system to get information about a file,
+    /// directory, etc.
+    fn metadata<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Self::Metadata>;
+
+    /// Query the metadata about a file without following symlinks.
+    fn symlink_metadata<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Self::Metadata>;
+
+    /// Queries metadata about the underlying directory.
+    fn dir_metadata(&self) -> io::Result<Self::Metadata>;
+
+    /// Returns an iterator over the entries within `self`.
+    fn entries(&self) -> io::Result<Self::ReadDir>;
+
+    /// Returns an iterator over the entries within a directory.
+    fn read_dir<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<Self::ReadDir>;
+
+    /// Returns `true` if the path points at an existing entity.
+    fn exists<P: AsRef<Self::Path>>(&self, path: P) -> bool;
+
+    /// Returns `true` if the path points at an existing entity, or an error
+    /// if its existence can't be determined.
+    fn try_exists<P: AsRef<Self::Path>>(&self, path: P) -> io::Result<bool>;
+
+    /// Returns `true` if the path exists on disk and is pointing at a regular
+    /// file.
+    fn is_file<P: AsRef<Self::Path>>(&self, path: P) -> bool;
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Self::Path>>(&self, path: P) -> bool;
+}
+
+impl DirLike for Dir {
+    type Path = Path;
+    type PathBuf = PathBuf;
+    type File = File;
+    type Dir = Dir;
+    type DirEntry = DirEntry;
+    type ReadDir = ReadDir;
+    type Metadata = Metadata;
+
+    #[inline]
+    fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        Dir::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Dir> {
+        Dir::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        Dir::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        Dir::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        Dir::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        Dir::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        Dir::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<Metadata> {
+        Dir::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<ReadDir> {
+        Dir::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ReadDir> {
+        Dir::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        Dir::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        Dir::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        Dir::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        Dir::is_dir(self, path)
+    }
+}
+
+impl<D: Borrow<Dir>> DirLike for DirView<D> {
+    type Path = Path;
+    type PathBuf = PathBuf;
+    type File = File;
+    type Dir = DirView;
+    type DirEntry = DirEntryView;
+    type ReadDir = ReadDirView;
+    type Metadata = Metadata;
+
+    #[inline]
+    fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        DirView::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<DirView> {
+        DirView::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        DirView::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        DirView::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        DirView::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        DirView::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        DirView::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<Metadata> {
+        DirView::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<ReadDirView> {
+        DirView::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ReadDirView> {
+        DirView::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        DirView::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        DirView::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        DirView::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        DirView::is_dir(self, path)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl DirLike for cap_std::fs_utf8::Dir {
+    type Path = Utf8Path;
+    type PathBuf = Utf8PathBuf;
+    type File = cap_std::fs_utf8::File;
+    type Dir = cap_std::fs_utf8::Dir;
+    type DirEntry = cap_std::fs_utf8::DirEntry;
+    type ReadDir = cap_std::fs_utf8::ReadDir;
+    type Metadata = Metadata;
+
+    #[inline]
+    fn open<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<cap_std::fs_utf8::File> {
+        cap_std::fs_utf8::Dir::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<cap_std::fs_utf8::Dir> {
+        cap_std::fs_utf8::Dir::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        cap_std::fs_utf8::Dir::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<String> {
+        cap_std::fs_utf8::Dir::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Utf8PathBuf> {
+        cap_std::fs_utf8::Dir::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Metadata> {
+        cap_std::fs_utf8::Dir::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Metadata> {
+        cap_std::fs_utf8::Dir::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<Metadata> {
+        cap_std::fs_utf8::Dir::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<cap_std::fs_utf8::ReadDir> {
+        cap_std::fs_utf8::Dir::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<cap_std::fs_utf8::ReadDir> {
+        cap_std::fs_utf8::Dir::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        cap_std::fs_utf8::Dir::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<bool> {
+        cap_std::fs_utf8::Dir::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        cap_std::fs_utf8::Dir::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        cap_std::fs_utf8::Dir::is_dir(self, path)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirLike for DirViewUtf8<D> {
+    type Path = Utf8Path;
+    type PathBuf = Utf8PathBuf;
+    type File = cap_std::fs_utf8::File;
+    type Dir = DirViewUtf8;
+    type DirEntry = DirEntryViewUtf8;
+    type ReadDir = ReadDirViewUtf8;
+    type Metadata = Metadata;
+
+    #[inline]
+    fn open<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<cap_std::fs_utf8::File> {
+        DirViewUtf8::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<DirViewUtf8> {
+        DirViewUtf8::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        DirViewUtf8::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<String> {
+        DirViewUtf8::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Utf8PathBuf> {
+        DirViewUtf8::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Metadata> {
+        DirViewUtf8::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Metadata> {
+        DirViewUtf8::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<Metadata> {
+        DirViewUtf8::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<ReadDirViewUtf8> {
+        DirViewUtf8::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<ReadDirViewUtf8> {
+        DirViewUtf8::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        DirViewUtf8::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<bool> {
+        DirViewUtf8::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        DirViewUtf8::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        DirViewUtf8::is_dir(self, path)
+    }
+}
+
+impl DirLike for ArchiveDirView {
+    type Path = Path;
+    type PathBuf = PathBuf;
+    type File = ArchiveFile;
+    type Dir = ArchiveDirView;
+    type DirEntry = ArchiveDirEntry;
+    type ReadDir = ArchiveReadDir;
+    type Metadata = ArchiveMetadata;
+
+    #[inline]
+    fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveFile> {
+        ArchiveDirView::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveDirView> {
+        ArchiveDirView::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        ArchiveDirView::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        ArchiveDirView::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        ArchiveDirView::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveMetadata> {
+        ArchiveDirView::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveMetadata> {
+        ArchiveDirView::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<ArchiveMetadata> {
+        ArchiveDirView::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<ArchiveReadDir> {
+        ArchiveDirView::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveReadDir> {
+        ArchiveDirView::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        ArchiveDirView::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        ArchiveDirView::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        ArchiveDirView::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        ArchiveDirView::is_dir(self, path)
+    }
+}
+
+impl DirLike for ZipDirView {
+    type Path = Path;
+    type PathBuf = PathBuf;
+    type File = ArchiveFile;
+    type Dir = ArchiveDirView;
+    type DirEntry = ArchiveDirEntry;
+    type ReadDir = ArchiveReadDir;
+    type Metadata = ArchiveMetadata;
+
+    #[inline]
+    fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveFile> {
+        ArchiveDirView::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveDirView> {
+        ArchiveDirView::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        ArchiveDirView::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        ArchiveDirView::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        ArchiveDirView::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveMetadata> {
+        ArchiveDirView::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveMetadata> {
+        ArchiveDirView::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<ArchiveMetadata> {
+        ArchiveDirView::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<ArchiveReadDir> {
+        ArchiveDirView::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ArchiveReadDir> {
+        ArchiveDirView::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        ArchiveDirView::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        ArchiveDirView::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        ArchiveDirView::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        ArchiveDirView::is_dir(self, path)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl DirLike for MemDirView {
+    type Path = Path;
+    type PathBuf = PathBuf;
+    type File = MemFile;
+    type Dir = MemDirView;
+    type DirEntry = MemDirEntry;
+    type ReadDir = MemReadDir;
+    type Metadata = MemMetadata;
+
+    #[inline]
+    fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<MemFile> {
+        MemDirView::open(self, path)
+    }
+
+    #[inline]
+    fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<MemDirView> {
+        MemDirView::open_dir(self, path)
+    }
+
+    #[inline]
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        MemDirView::read(self, path)
+    }
+
+    #[inline]
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        MemDirView::read_to_string(self, path)
+    }
+
+    #[inline]
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        MemDirView::read_link(self, path)
+    }
+
+    #[inline]
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<MemMetadata> {
+        MemDirView::metadata(self, path)
+    }
+
+    #[inline]
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<MemMetadata> {
+        MemDirView::symlink_metadata(self, path)
+    }
+
+    #[inline]
+    fn dir_metadata(&self) -> io::Result<MemMetadata> {
+        MemDirView::dir_metadata(self)
+    }
+
+    #[inline]
+    fn entries(&self) -> io::Result<MemReadDir> {
+        MemDirView::entries(self)
+    }
+
+    #[inline]
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<MemReadDir> {
+        MemDirView::read_dir(self, path)
+    }
+
+    #[inline]
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        MemDirView::exists(self, path)
+    }
+
+    #[inline]
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        MemDirView::try_exists(self, path)
+    }
+
+    #[inline]
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        MemDirView::is_file(self, path)
+    }
+
+    #[inline]
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        MemDirView::is_dir(self, path)
+    }
+}
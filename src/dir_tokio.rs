@@ -0,0 +1,408 @@
+use crate::walk_async::Listing;
+use crate::{
+    CopyDirOptions, DirEntryView, DirView, ReadDirView, SyncOptions, SyncReport, ViewKind,
+    WalkEntry, WalkStream,
+};
+use cap_std::fs::{Dir, Metadata, OpenOptions};
+use std::future::{poll_fn, Future};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::fs::File;
+use tokio::task::{self, JoinError, JoinHandle};
+
+/// An asynchronous view of a [`Dir`], for use with tokio.
+///
+/// This provides the same restrictions as [`DirView`], which it wraps: each
+/// operation is performed through the `DirView` with tokio's
+/// [`spawn_blocking`], so the view kind and other restrictions apply exactly
+/// as they do to synchronous calls. Cloning a `TokioDirView` increments a
+/// reference count, as [`SharedDirView`] does.
+///
+/// The methods must be called from within a tokio runtime.
+///
+/// [`spawn_blocking`]: tokio::task::spawn_blocking
+///
+/// [`SharedDirView`]: crate::SharedDirView
+#[derive(Clone, Debug)]
+pub struct TokioDirView(Arc<DirView>);
+
+impl TokioDirView {
+    /// Constructs a new instance of `Self` from the given [`DirView`].
+    #[inline]
+    pub fn new(view: DirView) -> Self {
+        Self(Arc::new(view))
+    }
+
+    /// Constructs a new instance of `Self` from the given [`Dir`] and
+    /// [`ViewKind`].
+    #[inline]
+    pub fn from_dir(dir: Dir, view_kind: ViewKind) -> Self {
+        Self::new(DirView::from_dir(dir, view_kind))
+    }
+
+    /// Returns the underlying [`DirView`], for performing operations
+    /// synchronously.
+    #[inline]
+    pub fn as_view(&self) -> &DirView {
+        &self.0
+    }
+
+    /// Attempts to open a file in read-only mode.
+    ///
+    /// See [`DirView::open`].
+    pub async fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let file = self.run(path, |view, path| view.open(path)).await?;
+        Ok(File::from_std(file.into_std()))
+    }
+
+    /// Opens a file at `path` with the options specified by `options`.
+    ///
+    /// See [`DirView::open_with`].
+    pub async fn open_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &OpenOptions,
+    ) -> io::Result<File> {
+        let options = options.clone();
+        let file = self
+            .run(path, move |view, path| view.open_with(path, &options))
+            .await?;
+        Ok(File::from_std(file.into_std()))
+    }
+
+    /// Opens a file in write-only mode, creating it or truncating it.
+    ///
+    /// See [`DirView::create`].
+    pub async fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let file = self.run(path, |view, path| view.create(path)).await?;
+        Ok(File::from_std(file.into_std()))
+    }
+
+    /// Attempts to open a directory.
+    ///
+    /// See [`DirView::open_dir`].
+    pub async fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Self> {
+        let view = self.run(path, |view, path| view.open_dir(path)).await?;
+        Ok(Self::new(view))
+    }
+
+    /// Creates a new, empty directory at the provided path.
+    ///
+    /// See [`DirView::create_dir`].
+    pub async fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.run(path, |view, path| view.create_dir(path)).await
+    }
+
+    /// Recursively create a directory and all of its parent components if
+    /// they are missing.
+    ///
+    /// See [`DirView::create_dir_all`].
+    pub async fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.run(path, |view, path| view.create_dir_all(path)).await
+    }
+
+    /// Read the entire contents of a file into a bytes vector.
+    ///
+    /// See [`DirView::read`].
+    pub async fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        self.run(path, |view, path| view.read(path)).await
+    }
+
+    /// Read the entire contents of a file into a string.
+    ///
+    /// See [`DirView::read_to_string`].
+    pub async fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        self.run(path, |view, path| view.read_to_string(path)).await
+    }
+
+    /// Write a slice as the entire contents of a file.
+    ///
+    /// See [`DirView::write`].
+    pub async fn write<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        let contents = contents.as_ref().to_vec();
+        self.run(path, move |view, path| view.write(path, contents))
+            .await
+    }
+
+    /// Returns an iterator over the entries within a directory.
+    ///
+    /// See [`DirView::read_dir`]. The entries are synchronous
+    /// [`DirEntryView`]s.
+    pub async fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<TokioReadDirView> {
+        let read_dir = self.run(path, |view, path| view.read_dir(path)).await?;
+        Ok(TokioReadDirView(State::Idle(Some(read_dir))))
+    }
+
+    /// Returns an iterator over the entries within `self`.
+    ///
+    /// See [`DirView::entries`].
+    pub async fn entries(&self) -> io::Result<TokioReadDirView> {
+        let view = Arc::clone(&self.0);
+        let read_dir = join(task::spawn_blocking(move || view.entries()).await)??;
+        Ok(TokioReadDirView(State::Idle(Some(read_dir))))
+    }
+
+    /// Returns a recursive stream over the entries within `self` and its
+    /// subdirectories, reading several directories at once.
+    ///
+    /// See [`WalkStream`].
+    #[inline]
+    pub fn walk(&self) -> WalkStream {
+        WalkStream::new(Arc::clone(&self.0), spawn_listing)
+    }
+
+    /// Removes a file from a filesystem.
+    ///
+    /// See [`DirView::remove_file`].
+    pub async fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.run(path, |view, path| view.remove_file(path)).await
+    }
+
+    /// Removes an empty directory.
+    ///
+    /// See [`DirView::remove_dir`].
+    pub async fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.run(path, |view, path| view.remove_dir(path)).await
+    }
+
+    /// Removes a directory at this path, after removing all its contents.
+    ///
+    /// See [`DirView::remove_dir_all`].
+    pub async fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.run(path, |view, path| view.remove_dir_all(path)).await
+    }
+
+    /// Rename a file or directory to a new name, replacing the original file
+    /// if `to` already exists.
+    ///
+    /// See [`DirView::rename`].
+    pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &Self,
+        to: Q,
+    ) -> io::Result<()> {
+        let to_dir = Arc::clone(&to_dir.0);
+        let to = to.as_ref().to_path_buf();
+        self.run(from, move |view, from| view.rename(from, &to_dir, to))
+            .await
+    }
+
+    /// Copies the contents of one file to another.
+    ///
+    /// See [`DirView::copy`].
+    pub async fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &Self,
+        to: Q,
+    ) -> io::Result<u64> {
+        let to_dir = Arc::clone(&to_dir.0);
+        let to = to.as_ref().to_path_buf();
+        self.run(from, move |view, from| view.copy(from, &to_dir, to))
+            .await
+    }
+
+    /// Recursively copies the directory at `from`, and its contents, to a new
+    /// directory at `to` in `to_dir`.
+    ///
+    /// See [`DirView::copy_dir_all`].
+    pub async fn copy_dir_all<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &Self,
+        to: Q,
+    ) -> io::Result<()> {
+        self.copy_dir_all_with(from, to_dir, to, &CopyDirOptions::new())
+            .await
+    }
+
+    /// Recursively copies the directory at `from`, and its contents, to a new
+    /// directory at `to` in `to_dir`, with the given options.
+    ///
+    /// The copy is made on the blocking thread pool, with additional threads
+    /// if [`CopyDirOptions::concurrency`] is set. See
+    /// [`DirView::copy_dir_all_with`].
+    pub async fn copy_dir_all_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &Self,
+        to: Q,
+        options: &CopyDirOptions,
+    ) -> io::Result<()> {
+        let to_dir = Arc::clone(&to_dir.0);
+        let to = to.as_ref().to_path_buf();
+        let options = options.clone();
+        self.run(from, move |view, from| {
+            view.copy_dir_all_with(from, &to_dir, to, &options)
+        })
+        .await
+    }
+
+    /// Mirrors the contents of `self` into `dest`.
+    ///
+    /// The sync is made on the blocking thread pool, with additional threads
+    /// if [`SyncOptions::concurrency`] is set. See [`DirView::sync_to`].
+    pub async fn sync_to(&self, dest: &Self, options: &SyncOptions) -> io::Result<SyncReport> {
+        let dest = Arc::clone(&dest.0);
+        let options = options.clone();
+        self.run("", move |view, _| view.sync_to(&dest, &options))
+            .await
+    }
+
+    /// Given a path, query the file system to get information about a file,
+    /// directory, etc.
+    ///
+    /// See [`DirView::metadata`].
+    pub async fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.run(path, |view, path| view.metadata(path)).await
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    ///
+    /// See [`DirView::symlink_metadata`].
+    pub async fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.run(path, |view, path| view.symlink_metadata(path))
+            .await
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    ///
+    /// See [`DirView::read_link`].
+    pub async fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.run(path, |view, path| view.read_link(path)).await
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    ///
+    /// See [`DirView::try_exists`].
+    pub async fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        self.run(path, |view, path| view.try_exists(path)).await
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    ///
+    /// See [`DirView::exists`].
+    pub async fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.run(path, |view, path| Ok(view.exists(path)))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Performs `f` with the view and `path` on the blocking thread pool.
+    async fn run<P: AsRef<Path>, T: Send + 'static>(
+        &self,
+        path: P,
+        f: impl FnOnce(&DirView, PathBuf) -> io::Result<T> + Send + 'static,
+    ) -> io::Result<T> {
+        let view = Arc::clone(&self.0);
+        let path = path.as_ref().to_path_buf();
+        join(task::spawn_blocking(move || f(&view, path)).await)?
+    }
+}
+
+impl From<DirView> for TokioDirView {
+    #[inline]
+    fn from(view: DirView) -> Self {
+        Self::new(view)
+    }
+}
+
+/// An iterator over the entries in a directory, returned by
+/// [`TokioDirView::read_dir`].
+///
+/// This corresponds to [`tokio::fs::ReadDir`]. Each entry is read with
+/// [`spawn_blocking`].
+///
+/// [`spawn_blocking`]: tokio::task::spawn_blocking
+#[derive(Debug)]
+pub struct TokioReadDirView(State);
+
+#[derive(Debug)]
+enum State {
+    /// No entry is being read. `None` once the iterator is exhausted.
+    Idle(Option<ReadDirView>),
+
+    /// The next entry is being read.
+    Busy(JoinHandle<(ReadDirView, Option<io::Result<DirEntryView>>)>),
+}
+
+impl TokioReadDirView {
+    /// Returns the next entry in the directory, or `None` if there are no
+    /// more entries.
+    ///
+    /// This is cancel-safe: if the returned future is dropped before it
+    /// completes, the entry it was reading is returned by the next call.
+    pub async fn next_entry(&mut self) -> io::Result<Option<DirEntryView>> {
+        poll_fn(|cx| self.poll_next_entry(cx)).await
+    }
+
+    /// Polls for the next entry in the directory.
+    ///
+    /// See [`Self::next_entry`].
+    pub fn poll_next_entry(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<Option<DirEntryView>>> {
+        loop {
+            match &mut self.0 {
+                State::Idle(read_dir) => {
+                    let Some(mut read_dir) = read_dir.take() else {
+                        return Poll::Ready(Ok(None));
+                    };
+                    self.0 = State::Busy(task::spawn_blocking(move || {
+                        let next = read_dir.next();
+                        (read_dir, next)
+                    }));
+                }
+                State::Busy(handle) => {
+                    let ready = match Pin::new(handle).poll(cx) {
+                        Poll::Ready(ready) => join(ready),
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    let (read_dir, next) = match ready {
+                        Ok(ready) => ready,
+                        Err(err) => {
+                            self.0 = State::Idle(None);
+                            return Poll::Ready(Err(err));
+                        }
+                    };
+                    self.0 = State::Idle(next.is_some().then_some(read_dir));
+                    return Poll::Ready(next.transpose());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for TokioReadDirView {
+    type Item = io::Result<DirEntryView>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_entry(cx).map(Result::transpose)
+    }
+}
+
+fn spawn_listing(read: Box<dyn FnOnce() -> Vec<io::Result<WalkEntry>> + Send>) -> Listing {
+    let handle = task::spawn_blocking(read);
+    Box::pin(async move { join(handle.await) })
+}
+
+/// Returns the result of a blocking task, resuming its panic if it
+/// panicked, or failing if it was cancelled by the runtime shutting down.
+fn join<T>(result: Result<T, JoinError>) -> io::Result<T> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(io::Error::other(err)),
+    }
+}
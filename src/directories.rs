@@ -0,0 +1,110 @@
+use crate::{DirView, ViewKind};
+use cap_std::AmbientAuthority;
+use directories_next::BaseDirs;
+use std::io;
+
+impl DirView {
+    /// Constructs a new instance of `Self` by opening the user's home
+    /// directory, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn user_home_dir(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let base_dirs = base_dirs()?;
+        Self::open_ambient_dir(base_dirs.home_dir(), view_kind, ambient_authority)
+    }
+
+    /// Constructs a new instance of `Self` by opening the user's cache
+    /// directory, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn user_cache_dir(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let base_dirs = base_dirs()?;
+        Self::open_ambient_dir(base_dirs.cache_dir(), view_kind, ambient_authority)
+    }
+
+    /// Constructs a new instance of `Self` by opening the user's config
+    /// directory, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn user_config_dir(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let base_dirs = base_dirs()?;
+        Self::open_ambient_dir(base_dirs.config_dir(), view_kind, ambient_authority)
+    }
+
+    /// Constructs a new instance of `Self` by opening the user's data
+    /// directory, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn user_data_dir(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let base_dirs = base_dirs()?;
+        Self::open_ambient_dir(base_dirs.data_dir(), view_kind, ambient_authority)
+    }
+
+    /// Constructs a new instance of `Self` by opening the user's local data
+    /// directory, using the host process' ambient authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn user_data_local_dir(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let base_dirs = base_dirs()?;
+        Self::open_ambient_dir(base_dirs.data_local_dir(), view_kind, ambient_authority)
+    }
+
+    /// Constructs a new instance of `Self` by opening the user's runtime
+    /// directory, using the host process' ambient authority.
+    ///
+    /// Not all platforms have a runtime directory; on those that don't, this
+    /// fails with [`io::ErrorKind::NotFound`].
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn user_runtime_dir(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let base_dirs = base_dirs()?;
+        let runtime_dir = base_dirs
+            .runtime_dir()
+            .ok_or_else(|| not_found("no runtime directory"))?;
+        Self::open_ambient_dir(runtime_dir, view_kind, ambient_authority)
+    }
+}
+
+fn base_dirs() -> io::Result<BaseDirs> {
+    BaseDirs::new().ok_or_else(|| not_found("no home directory"))
+}
+
+fn not_found(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, msg)
+}
@@ -0,0 +1,213 @@
+use crate::copy_dir::enter;
+use crate::DirView;
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+/// Options for [`DirView::disk_usage_with`].
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsageOptions {
+    follow_symlinks: bool,
+    count_hard_links_once: bool,
+}
+
+impl DiskUsageOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Symbolic links are initially counted rather than followed, and files
+    /// with several hard links are counted once for each.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to count what symbolic links point to, resolved within
+    /// the view, rather than the links themselves.
+    #[inline]
+    pub fn follow_symlinks(&mut self, follow_symlinks: bool) -> &mut Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets whether to count files with several hard links in the subtree
+    /// only once.
+    ///
+    /// This is only supported on Unix, where files are identified by their
+    /// device and inode numbers.
+    #[inline]
+    pub fn count_hard_links_once(&mut self, count_hard_links_once: bool) -> &mut Self {
+        self.count_hard_links_once = count_hard_links_once;
+        self
+    }
+}
+
+/// The space used by a subtree, returned by [`DirView::disk_usage`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DiskUsage {
+    apparent_size: u64,
+    allocated_size: u64,
+    files: u64,
+    dirs: u64,
+    symlinks: u64,
+    others: u64,
+}
+
+impl DiskUsage {
+    /// Returns the total size of the entries, in bytes, as reported by
+    /// their metadata.
+    #[inline]
+    pub fn apparent_size(&self) -> u64 {
+        self.apparent_size
+    }
+
+    /// Returns the total space allocated to the entries, in bytes.
+    ///
+    /// On Unix this counts 512-byte blocks, so it may be smaller than the
+    /// apparent size for sparse files. Elsewhere it's the apparent size.
+    #[inline]
+    pub fn allocated_size(&self) -> u64 {
+        self.allocated_size
+    }
+
+    /// Returns the number of regular files.
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Returns the number of directories, including the one measured.
+    #[inline]
+    pub fn dirs(&self) -> u64 {
+        self.dirs
+    }
+
+    /// Returns the number of symbolic links which weren't followed.
+    #[inline]
+    pub fn symlinks(&self) -> u64 {
+        self.symlinks
+    }
+
+    /// Returns the number of other entries, such as sockets and FIFOs.
+    #[inline]
+    pub fn others(&self) -> u64 {
+        self.others
+    }
+
+    fn add(&mut self, metadata: &Metadata) {
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            self.dirs += 1;
+        } else if file_type.is_file() {
+            self.files += 1;
+        } else if file_type.is_symlink() {
+            self.symlinks += 1;
+        } else {
+            self.others += 1;
+        }
+        self.apparent_size += metadata.len();
+        #[cfg(unix)]
+        {
+            self.allocated_size += metadata.blocks() * 512;
+        }
+        #[cfg(not(unix))]
+        {
+            self.allocated_size += metadata.len();
+        }
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Computes the space used by the file or directory at `path`, and
+    /// everything in it.
+    ///
+    /// Symbolic links are counted rather than followed, and files with
+    /// several hard links are counted once for each. The subtree is read
+    /// through the view, so entries it hides aren't counted, and
+    /// subdirectories beyond its maximum depth fail to open.
+    #[inline]
+    pub fn disk_usage<P: AsRef<Path>>(&self, path: P) -> io::Result<DiskUsage> {
+        self.disk_usage_with(path, &DiskUsageOptions::new())
+    }
+
+    /// Computes the space used by the file or directory at `path`, and
+    /// everything in it, with the given options.
+    ///
+    /// See [`Self::disk_usage`] for details.
+    pub fn disk_usage_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &DiskUsageOptions,
+    ) -> io::Result<DiskUsage> {
+        let path = path.as_ref();
+        let metadata = if options.follow_symlinks {
+            self.metadata(path)?
+        } else {
+            self.symlink_metadata(path)?
+        };
+        let mut walk = DiskUsageWalk {
+            options,
+            usage: DiskUsage::default(),
+            seen: HashSet::new(),
+            ancestors: Vec::new(),
+        };
+        walk.visit(self, path, &metadata)?;
+        Ok(walk.usage)
+    }
+}
+
+struct DiskUsageWalk<'a> {
+    options: &'a DiskUsageOptions,
+    usage: DiskUsage,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    seen: HashSet<(u64, u64)>,
+    ancestors: Vec<(u64, u64)>,
+}
+
+impl DiskUsageWalk<'_> {
+    fn visit<D: Borrow<Dir>>(
+        &mut self,
+        view: &DirView<D>,
+        path: &Path,
+        metadata: &Metadata,
+    ) -> io::Result<()> {
+        if !self.first_link(metadata) {
+            return Ok(());
+        }
+        self.usage.add(metadata);
+        if !metadata.is_dir() {
+            return Ok(());
+        }
+        enter(&mut self.ancestors, metadata)?;
+        for entry in view.read_dir(path)? {
+            let entry = entry?;
+            let path = path.join(entry.file_name());
+            let metadata = if self.options.follow_symlinks && entry.file_type()?.is_symlink() {
+                view.metadata(&path)?
+            } else {
+                entry.metadata()?
+            };
+            self.visit(view, &path, &metadata)?;
+        }
+        self.ancestors.pop();
+        Ok(())
+    }
+
+    /// Tests whether `metadata` is for the first hard link to a file seen,
+    /// or if hard links are counted separately.
+    #[cfg(unix)]
+    fn first_link(&mut self, metadata: &Metadata) -> bool {
+        if !self.options.count_hard_links_once || metadata.is_dir() || metadata.nlink() < 2 {
+            return true;
+        }
+        self.seen.insert((metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn first_link(&mut self, _metadata: &Metadata) -> bool {
+        true
+    }
+}
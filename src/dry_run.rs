@@ -0,0 +1,100 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A change that a [`ViewKind::DryRun`] view recorded instead of making.
+///
+/// [`ViewKind::DryRun`]: crate::ViewKind::DryRun
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DryRunChange {
+    operation: Operation,
+    path: PathBuf,
+    target: Option<PathBuf>,
+}
+
+impl DryRunChange {
+    /// Returns the operation that would have been performed.
+    #[inline]
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Returns the path the operation would have been applied to, relative
+    /// to the view the method was called on.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the second path of operations involving two, such as the
+    /// destination of a rename or the contents of a symbolic link.
+    #[inline]
+    pub fn target(&self) -> Option<&Path> {
+        self.target.as_deref()
+    }
+}
+
+impl fmt::Display for DryRunChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} `{}`", self.operation, self.path.display())?;
+        if let Some(target) = &self.target {
+            write!(f, " `{}`", target.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// The changes recorded by dry-run views, shared between views.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DryRunLog(Arc<Mutex<Vec<DryRunChange>>>);
+
+impl DryRunLog {
+    pub(crate) fn record(&self, operation: Operation, path: &Path, target: Option<&Path>) {
+        self.0.lock().unwrap().push(DryRunChange {
+            operation,
+            path: path.to_path_buf(),
+            target: target.map(Path::to_path_buf),
+        });
+    }
+
+    fn changes(&self) -> Vec<DryRunChange> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Test whether `operation` changes an existing file, so that a dry run of
+/// it should check that the file exists.
+pub(crate) fn changes_existing(operation: Operation) -> bool {
+    !matches!(
+        operation,
+        Operation::CreateDir | Operation::Mknod | Operation::Symlink | Operation::Write
+    )
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the changes recorded by `self` and the views derived from
+    /// it, in order, if `self` is a [`ViewKind::DryRun`] view.
+    ///
+    /// [`ViewKind::DryRun`]: crate::ViewKind::DryRun
+    #[inline]
+    pub fn dry_run_changes(&self) -> Vec<DryRunChange> {
+        self.policy.dry_run_log.changes()
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns the changes recorded by `self` and the views derived from
+    /// it, in order, if `self` is a [`ViewKind::DryRun`] view.
+    ///
+    /// [`ViewKind::DryRun`]: crate::ViewKind::DryRun
+    #[inline]
+    pub fn dry_run_changes(&self) -> Vec<DryRunChange> {
+        self.policy.dry_run_log.changes()
+    }
+}
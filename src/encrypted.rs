@@ -0,0 +1,412 @@
+use crate::DirView;
+use cap_std::fs::{FileType, Metadata};
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+use std::{fmt, io, vec};
+
+/// The version of the associated data files are encrypted with.
+const ASSOCIATED_DATA_VERSION: u8 = 1;
+
+/// An authenticated cipher for [`EncryptedDirView`], holding the caller's
+/// key.
+///
+/// This isn't implemented by this crate, which doesn't depend on any
+/// cryptography crates, and can be implemented with ciphers from other
+/// crates, such as AES-GCM or ChaCha20-Poly1305.
+pub trait Cipher: Send + Sync {
+    /// Encrypts the contents of a file, returning a ciphertext which
+    /// includes whatever [`Self::decrypt`] needs besides the key, such as a
+    /// random nonce and an authentication tag.
+    ///
+    /// `associated_data` identifies the file, and must be authenticated
+    /// along with the contents but needn't be included in the ciphertext,
+    /// as with the associated data of an AEAD.
+    fn encrypt(&self, plaintext: &[u8], associated_data: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Decrypts a ciphertext returned by [`Self::encrypt`], failing with
+    /// [`io::ErrorKind::InvalidData`] if it wasn't produced with the same
+    /// key and associated data or has been modified.
+    fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Encrypts a file name, for views which encrypt names.
+    ///
+    /// Unlike [`Self::encrypt`], this must be deterministic, so that the
+    /// same name always encrypts to the same ciphertext and can be looked
+    /// up, as with AES-SIV. By default, this fails with
+    /// [`io::ErrorKind::Unsupported`].
+    fn encrypt_name(&self, name: &[u8]) -> io::Result<Vec<u8>> {
+        let _ = name;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this cipher doesn't encrypt names",
+        ))
+    }
+
+    /// Decrypts a file name returned by [`Self::encrypt_name`].
+    ///
+    /// By default, this fails with [`io::ErrorKind::Unsupported`].
+    fn decrypt_name(&self, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let _ = ciphertext;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this cipher doesn't encrypt names",
+        ))
+    }
+}
+
+/// A view which encrypts the contents of files, and optionally their names,
+/// with a [`Cipher`].
+///
+/// Files are encrypted as a whole as they're written and decrypted as
+/// they're read, so they're only accessed with whole-file methods like
+/// [`Self::read`] and [`Self::write`]. Everything goes through the
+/// underlying view, so its restrictions apply, though with encrypted names,
+/// the paths in its errors and audit events are the encrypted ones.
+///
+/// Files are encrypted with their paths in the view as associated data, so
+/// an encrypted file moved or copied to another path in the underlying view
+/// fails to decrypt, as does one read through a link at another path.
+///
+/// Encrypted names are encoded in lowercase base32, so they're valid on
+/// case-insensitive filesystems. They're only supported for names which
+/// are valid UTF-8. Metadata isn't encrypted, and reports the sizes of the
+/// encrypted files.
+pub struct EncryptedDirView<C: Cipher> {
+    view: DirView,
+    cipher: C,
+    encrypt_names: bool,
+}
+
+impl<C: Cipher> EncryptedDirView<C> {
+    /// Constructs a view of `view` which encrypts the contents of files with
+    /// `cipher`.
+    #[inline]
+    pub fn new(view: DirView, cipher: C) -> Self {
+        Self {
+            view,
+            cipher,
+            encrypt_names: false,
+        }
+    }
+
+    /// Constructs a view of `view` which encrypts both the contents and the
+    /// names of files with `cipher`, which must implement
+    /// [`Cipher::encrypt_name`] and [`Cipher::decrypt_name`].
+    #[inline]
+    pub fn with_encrypted_names(view: DirView, cipher: C) -> Self {
+        Self {
+            view,
+            cipher,
+            encrypt_names: true,
+        }
+    }
+
+    /// Returns the underlying view, which holds the encrypted files.
+    #[inline]
+    pub fn view(&self) -> &DirView {
+        &self.view
+    }
+
+    /// Reads and decrypts the entire contents of a file into a bytes vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let path = path.as_ref();
+        let ciphertext = self.view.read(self.encrypt_path(path)?)?;
+        self.cipher.decrypt(&ciphertext, &associated_data(path))
+    }
+
+    /// Reads and decrypts the entire contents of a file into a string.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Encrypts a slice and writes it as the entire contents of a file.
+    pub fn write<P: AsRef<Path>, B: AsRef<[u8]>>(&self, path: P, contents: B) -> io::Result<()> {
+        let path = path.as_ref();
+        let ciphertext = self
+            .cipher
+            .encrypt(contents.as_ref(), &associated_data(path))?;
+        self.view.write(self.encrypt_path(path)?, ciphertext)
+    }
+
+    /// Encrypts a slice and writes it as the entire contents of a file,
+    /// atomically replacing any existing file, as with
+    /// [`DirView::write_atomic`].
+    pub fn write_atomic<P: AsRef<Path>, B: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: B,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let ciphertext = self
+            .cipher
+            .encrypt(contents.as_ref(), &associated_data(path))?;
+        self.view.write_atomic(self.encrypt_path(path)?, ciphertext)
+    }
+
+    /// Given a path, query the file system to get information about a file,
+    /// directory, etc.
+    ///
+    /// The length reported is that of the encrypted file.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.view.metadata(self.encrypt_path(path.as_ref())?)
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Returns `true` if the path exists on disk and is pointing at a
+    /// regular file.
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_file())
+    }
+
+    /// Checks if `path` is a directory.
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    /// Returns an iterator over the entries within `self`.
+    pub fn entries(&self) -> io::Result<EncryptedReadDir> {
+        self.read_dir(".")
+    }
+
+    /// Returns an iterator over the entries within a directory, with their
+    /// names decrypted.
+    ///
+    /// With encrypted names, entries whose names aren't encrypted, such as
+    /// the temporary files of [`Self::write_atomic`], are skipped.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<EncryptedReadDir> {
+        let path = self.encrypt_path(path.as_ref())?;
+        let mut entries = Vec::new();
+        for entry in self.view.read_dir(path)? {
+            let entry = entry?;
+            let Some(name) = self.decrypt_name(&entry.file_name())? else {
+                continue;
+            };
+            entries.push(EncryptedDirEntry {
+                name,
+                file_type: entry.file_type()?,
+                metadata: entry.metadata()?,
+            });
+        }
+        Ok(EncryptedReadDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Creates a new, empty directory at the provided path.
+    pub fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.view.create_dir(self.encrypt_path(path.as_ref())?)
+    }
+
+    /// Recursively create a directory and all of its parent components if
+    /// they are missing.
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.view.create_dir_all(self.encrypt_path(path.as_ref())?)
+    }
+
+    /// Removes a file.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.view.remove_file(self.encrypt_path(path.as_ref())?)
+    }
+
+    /// Removes an empty directory.
+    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.view.remove_dir(self.encrypt_path(path.as_ref())?)
+    }
+
+    /// Removes a directory at this path, after removing all its contents.
+    pub fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.view.remove_dir_all(self.encrypt_path(path.as_ref())?)
+    }
+
+    /// Rename a file to a new name, replacing the original file if `to`
+    /// already exists.
+    ///
+    /// Files are encrypted with their paths, so this re-encrypts the file
+    /// for its new path, writing it with [`Self::write_atomic`] and then
+    /// removing the original. Directories can't be renamed, as every file
+    /// in them would need to be re-encrypted.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let encrypted_from = self.encrypt_path(from)?;
+        let file_type = self.view.symlink_metadata(&encrypted_from)?.file_type();
+        if file_type.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "directories in an encrypted view can't be renamed",
+            ));
+        }
+        if file_type.is_symlink() {
+            return self
+                .view
+                .rename(encrypted_from, &self.view, self.encrypt_path(to)?);
+        }
+        self.write_atomic(to, self.read(from)?)?;
+        self.view.remove_file(encrypted_from)
+    }
+
+    /// Returns `path` with each name in it encrypted, if names are
+    /// encrypted.
+    fn encrypt_path(&self, path: &Path) -> io::Result<PathBuf> {
+        if !self.encrypt_names {
+            return Ok(path.to_path_buf());
+        }
+        let mut encrypted = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => {
+                    let name = name.to_str().ok_or_else(non_utf8_name)?;
+                    let ciphertext = self.cipher.encrypt_name(name.as_bytes())?;
+                    encrypted.push(base32::encode(&ciphertext));
+                }
+                component => encrypted.push(component),
+            }
+        }
+        Ok(encrypted)
+    }
+
+    /// Returns the decrypted `name`, if names are encrypted, or `None` if
+    /// it isn't an encrypted name.
+    fn decrypt_name(&self, name: &OsStr) -> io::Result<Option<OsString>> {
+        if !self.encrypt_names {
+            return Ok(Some(name.to_os_string()));
+        }
+        let Some(ciphertext) = name.to_str().and_then(base32::decode) else {
+            return Ok(None);
+        };
+        let name = String::from_utf8(self.cipher.decrypt_name(&ciphertext)?)
+            .map_err(|_| non_utf8_name())?;
+        Ok(Some(name.into()))
+    }
+}
+
+impl<C: Cipher> fmt::Debug for EncryptedDirView<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedDirView")
+            .field("view", &self.view)
+            .field("encrypt_names", &self.encrypt_names)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An iterator over the entries of a directory in an [`EncryptedDirView`].
+#[derive(Debug)]
+pub struct EncryptedReadDir {
+    entries: vec::IntoIter<EncryptedDirEntry>,
+}
+
+impl Iterator for EncryptedReadDir {
+    type Item = io::Result<EncryptedDirEntry>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Ok)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// An entry of a directory in an [`EncryptedDirView`].
+#[derive(Debug)]
+pub struct EncryptedDirEntry {
+    name: OsString,
+    file_type: FileType,
+    metadata: Metadata,
+}
+
+impl EncryptedDirEntry {
+    /// Returns the bare file name of this entry, decrypted, without any
+    /// other leading path components.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the file type for the file that this entry points at.
+    #[inline]
+    pub fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.file_type)
+    }
+
+    /// Returns the metadata for the file that this entry points at, as it
+    /// was when the directory was read.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+}
+
+/// Returns the associated data a file at `path` is encrypted with: a
+/// version, followed by `path` with `.` and `..` components resolved
+/// lexically, and its names separated by `/`.
+fn associated_data(path: &Path) -> Vec<u8> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name),
+            Component::ParentDir => {
+                names.pop();
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    let mut data = vec![ASSOCIATED_DATA_VERSION];
+    for (i, name) in names.iter().enumerate() {
+        if i != 0 {
+            data.push(b'/');
+        }
+        data.extend_from_slice(name.as_encoded_bytes());
+    }
+    data
+}
+
+fn non_utf8_name() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "encrypted names must be valid UTF-8",
+    )
+}
+
+/// Unpadded lowercase base32, from RFC 4648.
+mod base32 {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+    pub(super) fn encode(bytes: &[u8]) -> String {
+        let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+        let (mut buffer, mut bits) = (0u16, 0);
+        for &byte in bytes {
+            buffer = (buffer << 8) | u16::from(byte);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(ALPHABET[usize::from((buffer >> bits) & 31)].into());
+            }
+        }
+        if bits > 0 {
+            encoded.push(ALPHABET[usize::from((buffer << (5 - bits)) & 31)].into());
+        }
+        encoded
+    }
+
+    pub(super) fn decode(encoded: &str) -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+        let (mut buffer, mut bits) = (0u16, 0);
+        for c in encoded.bytes() {
+            let value = ALPHABET.iter().position(|&a| a == c)?;
+            buffer = (buffer << 5) | value as u16;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((buffer >> bits) as u8);
+            }
+        }
+        Some(bytes)
+    }
+}
@@ -0,0 +1,109 @@
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, FileType, Metadata};
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+/// The type of an entry, as returned by [`DirView::entry_kind`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum FileTypeSummary {
+    /// A regular file.
+    File,
+
+    /// A directory.
+    Dir,
+
+    /// A symbolic link.
+    Symlink,
+
+    /// Anything else, such as a socket, FIFO, or device.
+    Other,
+}
+
+impl FileTypeSummary {
+    /// Summarizes `file_type`.
+    pub fn from_file_type(file_type: FileType) -> Self {
+        if file_type.is_file() {
+            Self::File
+        } else if file_type.is_dir() {
+            Self::Dir
+        } else if file_type.is_symlink() {
+            Self::Symlink
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl From<FileType> for FileTypeSummary {
+    #[inline]
+    fn from(file_type: FileType) -> Self {
+        Self::from_file_type(file_type)
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the type of the entry at `path`, or `None` if it doesn't
+    /// exist, without following a symbolic link in its last component.
+    ///
+    /// This answers both whether `path` exists and what it is with a single
+    /// lookup, unlike combining [`Self::exists`], [`Self::is_file`], and
+    /// [`Self::is_dir`], which may each see a different entry if `path` is
+    /// changed concurrently.
+    #[inline]
+    pub fn entry_kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileTypeSummary>> {
+        summarize(self.symlink_metadata(path))
+    }
+
+    /// Returns the type of the entry at `path`, or `None` if it doesn't
+    /// exist, following symbolic links.
+    ///
+    /// This is like [`Self::entry_kind`], but never returns
+    /// [`FileTypeSummary::Symlink`]; broken symbolic links are reported as
+    /// not existing.
+    #[inline]
+    pub fn entry_kind_follow<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Option<FileTypeSummary>> {
+        summarize(self.metadata(path))
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns the type of the entry at `path`, or `None` if it doesn't
+    /// exist, without following a symbolic link in its last component.
+    ///
+    /// See [`DirView::entry_kind`].
+    #[inline]
+    pub fn entry_kind<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<FileTypeSummary>> {
+        summarize(self.symlink_metadata(path))
+    }
+
+    /// Returns the type of the entry at `path`, or `None` if it doesn't
+    /// exist, following symbolic links.
+    ///
+    /// See [`DirView::entry_kind_follow`].
+    #[inline]
+    pub fn entry_kind_follow<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Option<FileTypeSummary>> {
+        summarize(self.metadata(path))
+    }
+}
+
+fn summarize(metadata: io::Result<Metadata>) -> io::Result<Option<FileTypeSummary>> {
+    match metadata {
+        Ok(metadata) => Ok(Some(metadata.file_type().into())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,281 @@
+use crate::Operation;
+use std::path::{Path, PathBuf};
+use std::{error, fmt, io};
+
+/// An error from an operation through a view.
+///
+/// View methods return [`io::Error`]s, for compatibility with `cap_std` and
+/// `std`. Converting one into a `ViewError` distinguishes errors where the
+/// view's policy denied an operation, which might have succeeded through a
+/// [`ViewKind::Full`] view without restrictions, from errors reported by the
+/// underlying filesystem.
+///
+/// ```
+/// # use dir_view::{DirView, ViewError};
+/// # let dir = cap_std::fs::Dir::open_ambient_dir(".", cap_std::ambient_authority())?;
+/// let view = DirView::readonly(&dir);
+/// let error = ViewError::from(view.create_dir("new").unwrap_err());
+/// assert!(error.is_denied());
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// Converting a `ViewError` back into an `io::Error` preserves it, so errors
+/// can pass through code that only knows about `io::Error` and be inspected
+/// afterward.
+///
+/// [`ViewKind::Full`]: crate::ViewKind::Full
+#[derive(Debug)]
+pub struct ViewError {
+    repr: Repr,
+}
+
+#[derive(Debug)]
+enum Repr {
+    Denied(Denial),
+    Io(io::Error),
+}
+
+#[derive(Debug)]
+struct Denial {
+    kind: io::ErrorKind,
+    message: &'static str,
+    operation: Operation,
+    path: PathBuf,
+    context: bool,
+    limit: bool,
+}
+
+impl ViewError {
+    /// Constructs an error reporting that the view denied `operation` on
+    /// `path`. If `context` is set, the operation and path are included in
+    /// the message.
+    pub(crate) fn denied(
+        kind: io::ErrorKind,
+        message: &'static str,
+        operation: Operation,
+        path: &Path,
+        context: bool,
+    ) -> Self {
+        Self {
+            repr: Repr::Denied(Denial {
+                kind,
+                message,
+                operation,
+                path: path.to_path_buf(),
+                context,
+                limit: false,
+            }),
+        }
+    }
+
+    /// Constructs an error reporting that `operation` on `path` exceeded
+    /// one of the view's limits.
+    pub(crate) fn limit_exceeded(
+        kind: io::ErrorKind,
+        message: &'static str,
+        operation: Operation,
+        path: &Path,
+        context: bool,
+    ) -> Self {
+        Self {
+            repr: Repr::Denied(Denial {
+                kind,
+                message,
+                operation,
+                path: path.to_path_buf(),
+                context,
+                limit: true,
+            }),
+        }
+    }
+
+    /// Tests whether `error` reports a denial by a view, without consuming
+    /// it.
+    pub(crate) fn is_denial(error: &io::Error) -> bool {
+        error.get_ref().is_some_and(|inner| inner.is::<Self>())
+    }
+
+    /// Returns `true` if the view's policy denied the operation, rather than
+    /// the underlying filesystem.
+    #[inline]
+    pub fn is_denied(&self) -> bool {
+        matches!(self.repr, Repr::Denied(_))
+    }
+
+    /// Returns `true` if the view denied the operation because its path
+    /// exceeded one of the limits set with
+    /// [`DirViewBuilder::max_path_len`],
+    /// [`DirViewBuilder::max_path_components`], or
+    /// [`DirViewBuilder::max_symlink_traversals`], or because it would have
+    /// opened more handles than its [`HandleBudget`] permits.
+    ///
+    /// [`HandleBudget`]: crate::HandleBudget
+    /// [`DirViewBuilder::max_path_len`]: crate::DirViewBuilder::max_path_len
+    /// [`DirViewBuilder::max_path_components`]: crate::DirViewBuilder::max_path_components
+    /// [`DirViewBuilder::max_symlink_traversals`]: crate::DirViewBuilder::max_symlink_traversals
+    #[inline]
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(&self.repr, Repr::Denied(denial) if denial.limit)
+    }
+
+    /// Returns the kind of this error, as an [`io::ErrorKind`].
+    #[inline]
+    pub fn kind(&self) -> io::ErrorKind {
+        match &self.repr {
+            Repr::Denied(denial) => denial.kind,
+            Repr::Io(error) => error.kind(),
+        }
+    }
+
+    /// Returns the operation that was denied, if this is a denial.
+    #[inline]
+    pub fn operation(&self) -> Option<Operation> {
+        match &self.repr {
+            Repr::Denied(denial) => Some(denial.operation),
+            Repr::Io(_) => None,
+        }
+    }
+
+    /// Returns the path, relative to the view, that the denied operation was
+    /// applied to, if this is a denial.
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        match &self.repr {
+            Repr::Denied(denial) => Some(&denial.path),
+            Repr::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            Repr::Denied(denial) if denial.context => write!(
+                f,
+                "{}: {} `{}`",
+                denial.message,
+                denial.operation,
+                denial.path.display()
+            ),
+            Repr::Denied(denial) => f.write_str(denial.message),
+            Repr::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ViewError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.repr {
+            Repr::Denied(_) => None,
+            // Errors from the filesystem are transparent.
+            Repr::Io(error) => error.source(),
+        }
+    }
+}
+
+impl From<io::Error> for ViewError {
+    fn from(error: io::Error) -> Self {
+        if Self::is_denial(&error) {
+            // The checks above ensure these succeed.
+            return *error.into_inner().unwrap().downcast::<Self>().unwrap();
+        }
+        Self {
+            repr: Repr::Io(error),
+        }
+    }
+}
+
+impl From<ViewError> for io::Error {
+    fn from(error: ViewError) -> Self {
+        match error.repr {
+            Repr::Denied(ref denial) => io::Error::new(denial.kind, error),
+            Repr::Io(error) => error,
+        }
+    }
+}
+
+/// An error from [`sanitize_path`], describing why a path can't be used
+/// through a view.
+///
+/// Converting a `PathError` into an [`io::Error`] gives an error of kind
+/// [`io::ErrorKind::InvalidInput`].
+///
+/// [`sanitize_path`]: crate::sanitize_path
+#[derive(Clone, Debug)]
+pub struct PathError {
+    kind: PathErrorKind,
+    message: &'static str,
+    path: PathBuf,
+}
+
+/// The kinds of [`PathError`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum PathErrorKind {
+    /// The path is absolute, or has a prefix such as a drive letter on
+    /// Windows.
+    Absolute,
+
+    /// The path contains a `..` component.
+    ParentDir,
+
+    /// The path contains an empty component, as in `a//b`.
+    EmptyComponent,
+
+    /// The path contains a NUL byte.
+    Nul,
+
+    /// The path contains a name which isn't valid on Windows, or is longer
+    /// than Windows permits.
+    InvalidName,
+}
+
+impl PathError {
+    pub(crate) fn new(kind: PathErrorKind, message: &'static str, path: &Path) -> Self {
+        Self {
+            kind,
+            message,
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Returns the kind of this error.
+    #[inline]
+    pub fn kind(&self) -> PathErrorKind {
+        self.kind
+    }
+
+    /// Returns the path which was rejected.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a description of the problem, without the path.
+    pub(crate) fn message(&self) -> &'static str {
+        self.message
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: `{}`", self.message, self.path.display())
+    }
+}
+
+impl error::Error for PathError {}
+
+impl From<PathError> for io::Error {
+    fn from(error: PathError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+/// Constructs an error reporting that a path has no final file name, as
+/// needed by operations on its parent directory.
+pub(crate) fn no_file_name() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "path doesn't end in a file name",
+    )
+}
@@ -0,0 +1,107 @@
+use crate::atomic_file::temp_name;
+use crate::error::no_file_name;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Exchanges the entries at `a` in `self` and `b` in `other`, which may
+    /// be files or directories of either type.
+    ///
+    /// On Linux this uses `renameat2` with `RENAME_EXCHANGE`, so the entries
+    /// are swapped atomically, which makes it possible to replace a live
+    /// directory tree with another in one step. Elsewhere, and on
+    /// filesystems which don't support that, the entries are swapped with
+    /// three renames, through a temporary name next to `a`, so that other
+    /// processes may briefly see `a` missing, and a failure part way through
+    /// is undone as far as possible.
+    pub fn exchange<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        a: P,
+        other: &DirView<E>,
+        b: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Rename, a, b, |a, b| {
+            let b = other.fold_names(b.as_ref());
+            self.check_change(Operation::Rename, a)?;
+            other.check_change(Operation::Rename, b.as_ref())?;
+            if self.dry_run_pair(other, Operation::Rename, a, b.as_ref())? {
+                return Ok(());
+            }
+            exchange(self.dir.borrow(), a, other.dir.borrow(), b.as_ref())
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Exchanges the entries at `a` in `self` and `b` in `other`, which may
+    /// be files or directories of either type.
+    ///
+    /// See [`DirView::exchange`].
+    pub fn exchange<E: Borrow<cap_std::fs_utf8::Dir>, P: AsRef<Utf8Path>, Q: AsRef<Utf8Path>>(
+        &self,
+        a: P,
+        other: &DirViewUtf8<E>,
+        b: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Rename, a, b, |a, b| {
+            let b = other.fold_names(b.as_ref());
+            self.check_change(Operation::Rename, a)?;
+            other.check_change(Operation::Rename, b.as_ref())?;
+            if self.dry_run_pair(other, Operation::Rename, a, b.as_ref())? {
+                return Ok(());
+            }
+            exchange(
+                self.dir.borrow().as_cap_std(),
+                a.as_std_path(),
+                other.dir.borrow().as_cap_std(),
+                b.as_ref().as_std_path(),
+            )
+        })
+    }
+}
+
+fn exchange(a_dir: &Dir, a: &Path, b_dir: &Dir, b: &Path) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use crate::rename_noreplace::open_parent;
+        use rustix::fs::{renameat_with, RenameFlags};
+        use rustix::io::Errno;
+
+        let (a_parent, a_name) = open_parent(a_dir, a)?;
+        let (b_parent, b_name) = open_parent(b_dir, b)?;
+        match renameat_with(
+            a_parent.as_ref().unwrap_or(a_dir),
+            a_name,
+            b_parent.as_ref().unwrap_or(b_dir),
+            b_name,
+            RenameFlags::EXCHANGE,
+        ) {
+            // The filesystem or kernel doesn't support `RENAME_EXCHANGE`.
+            Err(Errno::INVAL | Errno::NOSYS) => {}
+            result => return Ok(result?),
+        }
+    }
+
+    // Both entries must exist, as with `RENAME_EXCHANGE`.
+    b_dir.symlink_metadata(b)?;
+    let temp = a.with_file_name(temp_name(a.file_name().ok_or_else(no_file_name)?));
+    a_dir.rename(a, a_dir, &temp)?;
+    if let Err(err) = b_dir.rename(b, a_dir, a) {
+        a_dir.rename(&temp, a_dir, a).ok();
+        return Err(err);
+    }
+    if let Err(err) = a_dir.rename(&temp, b_dir, b) {
+        a_dir.rename(a, b_dir, b).ok();
+        a_dir.rename(&temp, a_dir, a).ok();
+        return Err(err);
+    }
+    Ok(())
+}
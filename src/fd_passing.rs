@@ -0,0 +1,268 @@
+use crate::dry_run::DryRunLog;
+use crate::policy::Policy;
+#[cfg(feature = "unicode-normalization")]
+use crate::NormalizationForm;
+use crate::{DirView, ViewKind};
+use cap_std::fs::Dir;
+use rustix::net::{
+    recvmsg, sendmsg, RecvAncillaryBuffer, RecvAncillaryMessage, RecvFlags, SendAncillaryBuffer,
+    SendAncillaryMessage, SendFlags,
+};
+use std::io::{self, IoSlice, IoSliceMut};
+use std::os::fd::{AsFd, OwnedFd};
+use std::sync::Arc;
+
+/// The version of the encoding of a view's restrictions.
+const VERSION: u8 = 3;
+
+/// The size of the encoding of a view's restrictions.
+const LEN: usize = 45;
+
+const DENY_SYMLINKS: u8 = 0x1;
+const HIDE_HIDDEN: u8 = 0x2;
+const DENY_UNIX_CONNECT: u8 = 0x4;
+const SAME_FILESYSTEM: u8 = 0x8;
+const PORTABLE_NAMES: u8 = 0x10;
+const CONFINE_SYMLINK_TARGETS: u8 = 0x20;
+const CASE_INSENSITIVE: u8 = 0x40;
+const STRICT_PATHS: u8 = 0x80;
+
+// Flags in the second byte of flags.
+const DENY_EXEC: u8 = 0x1;
+
+impl DirView {
+    /// Sends `self` over the given Unix-domain socket.
+    ///
+    /// The directory handle is sent with `SCM_RIGHTS`, along with an encoding
+    /// of the view's kind and restrictions. The receiving end can reconstruct
+    /// the view with [`DirView::recv_from_unix_socket`].
+    ///
+    /// A trash set up with [`DirView::with_trash`] isn't sent, so entries
+    /// removed through the received view are deleted. Nor are the things
+    /// shared in memory with other views, such as a [`RateLimiter`],
+    /// [`HandleBudget`], or [`Coordinator`]. Views with kinds overridden in
+    /// subtrees can't be sent, as the overrides can't be encoded.
+    ///
+    /// The kind and restrictions are advisory for the receiver. They're
+    /// applied by [`DirView::recv_from_unix_socket`], but the handle carries
+    /// no access rights of its own, so a receiver which uses it directly can
+    /// do anything a full view could. Only send restricted views to
+    /// processes trusted to receive them this way.
+    ///
+    /// [`RateLimiter`]: crate::RateLimiter
+    /// [`HandleBudget`]: crate::HandleBudget
+    /// [`Coordinator`]: crate::Coordinator
+    pub fn send_to_unix_socket<Socket: AsFd>(&self, socket: &Socket) -> io::Result<()> {
+        if self.policy.overrides.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "views with overridden kinds can't be sent",
+            ));
+        }
+        let bytes = encode(self);
+        let fds = [self.dir.as_fd()];
+        let mut space = [0; rustix::cmsg_space!(ScmRights(1))];
+        let mut control = SendAncillaryBuffer::new(&mut space);
+        control.push(SendAncillaryMessage::ScmRights(&fds));
+        let n = sendmsg(
+            socket,
+            &[IoSlice::new(&bytes)],
+            &mut control,
+            SendFlags::empty(),
+        )?;
+        if n != bytes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to send a complete view",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Receives a view sent with [`DirView::send_to_unix_socket`] from the
+    /// given Unix-domain socket.
+    pub fn recv_from_unix_socket<Socket: AsFd>(socket: &Socket) -> io::Result<Self> {
+        let mut bytes = [0; LEN];
+        let mut space = [0; rustix::cmsg_space!(ScmRights(1))];
+        let mut control = RecvAncillaryBuffer::new(&mut space);
+        let msg = recvmsg(
+            socket,
+            &mut [IoSliceMut::new(&mut bytes)],
+            &mut control,
+            recv_flags(),
+        )?;
+
+        let mut received = None;
+        for message in control.drain() {
+            if let RecvAncillaryMessage::ScmRights(fds) = message {
+                // Take the first descriptor and close any others.
+                for fd in fds {
+                    received.get_or_insert(fd);
+                }
+            }
+        }
+        let fd = received.ok_or_else(|| invalid("no directory handle received"))?;
+        #[cfg(not(any(target_os = "android", target_os = "freebsd", target_os = "linux")))]
+        rustix::io::fcntl_setfd(&fd, rustix::io::FdFlags::CLOEXEC)?;
+
+        if msg.bytes != LEN {
+            return Err(invalid("truncated view encoding"));
+        }
+        decode(&bytes, fd)
+    }
+
+    /// Constructs a new instance of `Self` from a directory handle received
+    /// from another process, and a [`ViewKind`].
+    ///
+    /// This is for use with handles passed by means other than
+    /// [`DirView::send_to_unix_socket`], where the receiver knows what kind
+    /// of view the handle was meant to provide.
+    #[inline]
+    pub fn from_received(fd: OwnedFd, view_kind: ViewKind) -> Self {
+        Self::from_dir(Dir::from(fd), view_kind)
+    }
+}
+
+fn recv_flags() -> RecvFlags {
+    #[cfg(any(target_os = "android", target_os = "freebsd", target_os = "linux"))]
+    {
+        RecvFlags::CMSG_CLOEXEC
+    }
+    #[cfg(not(any(target_os = "android", target_os = "freebsd", target_os = "linux")))]
+    {
+        RecvFlags::empty()
+    }
+}
+
+fn encode(view: &DirView) -> [u8; LEN] {
+    let mut bytes = [0; LEN];
+    bytes[0] = VERSION;
+    bytes[1] = match view.view_kind {
+        ViewKind::Full => 0,
+        ViewKind::Readonly => 1,
+        ViewKind::DryRun => 2,
+    };
+    if view.policy.deny_symlinks {
+        bytes[2] |= DENY_SYMLINKS;
+    }
+    if view.policy.hide_hidden {
+        bytes[2] |= HIDE_HIDDEN;
+    }
+    if view.policy.deny_unix_connect {
+        bytes[2] |= DENY_UNIX_CONNECT;
+    }
+    if view.policy.same_filesystem {
+        bytes[2] |= SAME_FILESYSTEM;
+    }
+    if view.policy.portable_names {
+        bytes[2] |= PORTABLE_NAMES;
+    }
+    if view.policy.confine_symlink_targets {
+        bytes[2] |= CONFINE_SYMLINK_TARGETS;
+    }
+    if view.policy.case_insensitive {
+        bytes[2] |= CASE_INSENSITIVE;
+    }
+    if view.policy.strict_paths {
+        bytes[2] |= STRICT_PATHS;
+    }
+    bytes[3..11].copy_from_slice(&encode_limit(view.policy.max_depth));
+    bytes[11..19].copy_from_slice(&(view.depth as u64).to_le_bytes());
+    #[cfg(feature = "unicode-normalization")]
+    {
+        bytes[19] = match view.policy.normalization {
+            None => 0,
+            Some(NormalizationForm::Nfc) => 1,
+            Some(NormalizationForm::Nfd) => 2,
+        };
+    }
+    bytes[20..28].copy_from_slice(&encode_limit(view.policy.max_path_len));
+    bytes[28..36].copy_from_slice(&encode_limit(view.policy.max_path_components));
+    bytes[36..44].copy_from_slice(&encode_limit(view.policy.max_symlink_traversals));
+    if view.policy.deny_exec {
+        bytes[44] |= DENY_EXEC;
+    }
+    bytes
+}
+
+/// Encodes an optional limit, with `u64::MAX` meaning no limit.
+fn encode_limit(limit: Option<usize>) -> [u8; 8] {
+    limit.map_or(u64::MAX, |limit| limit as u64).to_le_bytes()
+}
+
+fn decode(bytes: &[u8; LEN], fd: OwnedFd) -> io::Result<DirView> {
+    if bytes[0] != VERSION {
+        return Err(invalid("unsupported view encoding version"));
+    }
+    let view_kind = match bytes[1] {
+        0 => ViewKind::Full,
+        1 => ViewKind::Readonly,
+        2 => ViewKind::DryRun,
+        _ => return Err(invalid("unknown view kind")),
+    };
+    let depth = u64::from_le_bytes(bytes[11..19].try_into().unwrap());
+    #[cfg(feature = "unicode-normalization")]
+    let normalization = match bytes[19] {
+        0 => None,
+        1 => Some(NormalizationForm::Nfc),
+        2 => Some(NormalizationForm::Nfd),
+        _ => return Err(invalid("unknown normalization form")),
+    };
+    #[cfg(not(feature = "unicode-normalization"))]
+    if bytes[19] != 0 {
+        return Err(invalid("unsupported normalization form"));
+    }
+    let policy = Policy {
+        deny_symlinks: bytes[2] & DENY_SYMLINKS != 0,
+        hide_hidden: bytes[2] & HIDE_HIDDEN != 0,
+        max_depth: decode_limit(&bytes[3..11])?,
+        max_path_len: decode_limit(&bytes[20..28])?,
+        max_path_components: decode_limit(&bytes[28..36])?,
+        max_symlink_traversals: decode_limit(&bytes[36..44])?,
+        deny_unix_connect: bytes[2] & DENY_UNIX_CONNECT != 0,
+        deny_exec: bytes[44] & DENY_EXEC != 0,
+        same_filesystem: bytes[2] & SAME_FILESYSTEM != 0,
+        deny_reparse_points: false,
+        portable_names: bytes[2] & PORTABLE_NAMES != 0,
+        strict_paths: bytes[2] & STRICT_PATHS != 0,
+        confine_symlink_targets: bytes[2] & CONFINE_SYMLINK_TARGETS != 0,
+        case_insensitive: bytes[2] & CASE_INSENSITIVE != 0,
+        #[cfg(feature = "unicode-normalization")]
+        normalization,
+        overrides: None,
+        denial_kind: None,
+        denial_context: false,
+        audit: None,
+        stats: None,
+        rate_limit: None,
+        handle_budget: None,
+        _permit: None,
+        coordinator: None,
+        dry_run_log: DryRunLog::default(),
+        read_dir_buffer_size: None,
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        io_uring: false,
+        cache: None,
+        trash: None,
+    };
+    Ok(DirView {
+        dir: Dir::from(fd),
+        view_kind,
+        policy: Arc::new(policy),
+        depth: usize::try_from(depth).map_err(|_| invalid("depth out of range"))?,
+    })
+}
+
+/// Decodes a limit encoded by [`encode_limit`].
+fn decode_limit(bytes: &[u8]) -> io::Result<Option<usize>> {
+    match u64::from_le_bytes(bytes.try_into().unwrap()) {
+        u64::MAX => Ok(None),
+        limit => usize::try_from(limit)
+            .map(Some)
+            .map_err(|_| invalid("limit out of range")),
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
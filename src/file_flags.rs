@@ -0,0 +1,349 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+/// Platform file flags, returned by [`DirView::file_flags`].
+///
+/// Each flag corresponds to a flag of each platform which supports it:
+///
+/// | Flag          | Linux             | macOS and FreeBSD | Windows                   |
+/// |---------------|-------------------|-------------------|---------------------------|
+/// | `immutable`   | `FS_IMMUTABLE_FL` | `UF_IMMUTABLE`    | `FILE_ATTRIBUTE_READONLY` |
+/// | `append_only` | `FS_APPEND_FL`    | `UF_APPEND`       |                           |
+/// | `nodump`      | `FS_NODUMP_FL`    | `UF_NODUMP`       |                           |
+/// | `hidden`      |                   | `UF_HIDDEN`       | `FILE_ATTRIBUTE_HIDDEN`   |
+///
+/// Setting a flag on a platform without it fails with
+/// [`io::ErrorKind::Unsupported`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct FileFlags {
+    immutable: bool,
+    append_only: bool,
+    nodump: bool,
+    hidden: bool,
+}
+
+impl FileFlags {
+    /// Returns `true` if the entry can't be modified, renamed, or removed.
+    #[inline]
+    pub fn immutable(&self) -> bool {
+        self.immutable
+    }
+
+    /// Sets whether the entry can't be modified, renamed, or removed.
+    #[inline]
+    pub fn set_immutable(&mut self, immutable: bool) {
+        self.immutable = immutable;
+    }
+
+    /// Returns `true` if the entry can only be appended to.
+    #[inline]
+    pub fn append_only(&self) -> bool {
+        self.append_only
+    }
+
+    /// Sets whether the entry can only be appended to.
+    #[inline]
+    pub fn set_append_only(&mut self, append_only: bool) {
+        self.append_only = append_only;
+    }
+
+    /// Returns `true` if the entry is excluded from backups by `dump`.
+    #[inline]
+    pub fn nodump(&self) -> bool {
+        self.nodump
+    }
+
+    /// Sets whether the entry is excluded from backups by `dump`.
+    #[inline]
+    pub fn set_nodump(&mut self, nodump: bool) {
+        self.nodump = nodump;
+    }
+
+    /// Returns `true` if the entry is hidden from file browsers.
+    #[inline]
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Sets whether the entry is hidden from file browsers.
+    #[inline]
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the platform file flags of the entry at `path`, following
+    /// symbolic links.
+    ///
+    /// See [`FileFlags`] for the flags of each platform. This fails with
+    /// [`io::ErrorKind::Unsupported`] on other platforms and on filesystems
+    /// without flags.
+    pub fn file_flags<P: AsRef<Path>>(&self, path: P) -> io::Result<FileFlags> {
+        self.audited(Operation::Metadata, path, |path| {
+            sys::get(self.dir.borrow(), path)
+        })
+    }
+
+    /// Sets the platform file flags of the entry at `path`, following
+    /// symbolic links, and leaving any other flags the platform has as they
+    /// are.
+    ///
+    /// This lets archival tools pin files they wrote through a view against
+    /// changes. On Linux, setting or clearing `immutable` or `append_only`
+    /// requires the `CAP_LINUX_IMMUTABLE` capability, and on the BSDs,
+    /// clearing them may be refused at raised securelevels.
+    pub fn set_file_flags<P: AsRef<Path>>(&self, path: P, flags: FileFlags) -> io::Result<()> {
+        self.audited(Operation::SetFlags, path, |path| {
+            if self.dry_run(Operation::SetFlags, path, None)? {
+                return Ok(());
+            }
+            sys::set(self.dir.borrow(), path, flags)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns the platform file flags of the entry at `path`, following
+    /// symbolic links.
+    ///
+    /// See [`DirView::file_flags`].
+    pub fn file_flags<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<FileFlags> {
+        self.audited(Operation::Metadata, path, |path| {
+            sys::get(self.dir.borrow().as_cap_std(), path.as_std_path())
+        })
+    }
+
+    /// Sets the platform file flags of the entry at `path`, following
+    /// symbolic links.
+    ///
+    /// See [`DirView::set_file_flags`].
+    pub fn set_file_flags<P: AsRef<Utf8Path>>(&self, path: P, flags: FileFlags) -> io::Result<()> {
+        self.audited(Operation::SetFlags, path, |path| {
+            if self.dry_run(Operation::SetFlags, path, None)? {
+                return Ok(());
+            }
+            sys::set(self.dir.borrow().as_cap_std(), path.as_std_path(), flags)
+        })
+    }
+}
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "file flags aren't supported on this platform or filesystem",
+    )
+}
+
+/// Opens `path` for querying and changing its flags, without blocking on
+/// FIFOs and devices.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+))]
+fn open(dir: &Dir, path: &Path) -> io::Result<cap_std::fs::File> {
+    use cap_std::fs::{OpenOptions, OpenOptionsExt};
+
+    dir.open_with(
+        path,
+        OpenOptions::new()
+            .read(true)
+            .custom_flags(rustix::fs::OFlags::NONBLOCK.bits() as i32),
+    )
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use super::{open, unsupported, FileFlags};
+    use cap_std::fs::Dir;
+    use rustix::fs::{ioctl_getflags, ioctl_setflags, IFlags};
+    use rustix::io::Errno;
+    use std::io;
+    use std::path::Path;
+
+    pub(super) fn get(dir: &Dir, path: &Path) -> io::Result<FileFlags> {
+        let flags = ioctl_getflags(open(dir, path)?).map_err(map_err)?;
+        Ok(FileFlags {
+            immutable: flags.contains(IFlags::IMMUTABLE),
+            append_only: flags.contains(IFlags::APPEND),
+            nodump: flags.contains(IFlags::NODUMP),
+            hidden: false,
+        })
+    }
+
+    pub(super) fn set(dir: &Dir, path: &Path, flags: FileFlags) -> io::Result<()> {
+        if flags.hidden {
+            return Err(unsupported());
+        }
+        let file = open(dir, path)?;
+        let mut new = ioctl_getflags(&file).map_err(map_err)?;
+        new.set(IFlags::IMMUTABLE, flags.immutable);
+        new.set(IFlags::APPEND, flags.append_only);
+        new.set(IFlags::NODUMP, flags.nodump);
+        ioctl_setflags(&file, new).map_err(map_err)
+    }
+
+    fn map_err(err: Errno) -> io::Error {
+        match err {
+            Errno::NOTTY | Errno::OPNOTSUPP | Errno::INVAL => unsupported(),
+            err => err.into(),
+        }
+    }
+}
+
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+mod sys {
+    use super::{open, FileFlags};
+    use cap_std::fs::Dir;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::path::Path;
+
+    pub(super) fn get(dir: &Dir, path: &Path) -> io::Result<FileFlags> {
+        let flags = st_flags(&open(dir, path)?)?;
+        Ok(FileFlags {
+            immutable: flags & libc::UF_IMMUTABLE as u32 != 0,
+            append_only: flags & libc::UF_APPEND as u32 != 0,
+            nodump: flags & libc::UF_NODUMP as u32 != 0,
+            hidden: flags & libc::UF_HIDDEN as u32 != 0,
+        })
+    }
+
+    pub(super) fn set(dir: &Dir, path: &Path, flags: FileFlags) -> io::Result<()> {
+        let file = open(dir, path)?;
+        let mut new = st_flags(&file)?;
+        for (flag, value) in [
+            (libc::UF_IMMUTABLE as u32, flags.immutable),
+            (libc::UF_APPEND as u32, flags.append_only),
+            (libc::UF_NODUMP as u32, flags.nodump),
+            (libc::UF_HIDDEN as u32, flags.hidden),
+        ] {
+            if value {
+                new |= flag;
+            } else {
+                new &= !flag;
+            }
+        }
+        // SAFETY: `file` is an open file descriptor.
+        if unsafe { libc::fchflags(file.as_raw_fd(), new as _) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn st_flags(file: &cap_std::fs::File) -> io::Result<u32> {
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        // SAFETY: `stat` is written by `fstat` if it succeeds.
+        if unsafe { libc::fstat(file.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fstat` succeeded.
+        Ok(unsafe { stat.assume_init() }.st_flags)
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::{unsupported, FileFlags};
+    use cap_std::fs::{Dir, MetadataExt, OpenOptions, OpenOptionsExt};
+    use std::io;
+    use std::mem::size_of;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FileBasicInfo, SetFileInformationByHandle, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_NORMAL,
+        FILE_ATTRIBUTE_READONLY, FILE_BASIC_INFO, FILE_FLAG_BACKUP_SEMANTICS, FILE_READ_ATTRIBUTES,
+        FILE_WRITE_ATTRIBUTES,
+    };
+
+    pub(super) fn get(dir: &Dir, path: &Path) -> io::Result<FileFlags> {
+        let attributes = dir.metadata(path)?.file_attributes();
+        Ok(FileFlags {
+            immutable: attributes & FILE_ATTRIBUTE_READONLY != 0,
+            append_only: false,
+            nodump: false,
+            hidden: attributes & FILE_ATTRIBUTE_HIDDEN != 0,
+        })
+    }
+
+    pub(super) fn set(dir: &Dir, path: &Path, flags: FileFlags) -> io::Result<()> {
+        if flags.append_only || flags.nodump {
+            return Err(unsupported());
+        }
+        let file = dir.open_with(
+            path,
+            OpenOptions::new()
+                .access_mode(FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES)
+                .custom_flags(FILE_FLAG_BACKUP_SEMANTICS),
+        )?;
+        let mut attributes = file.metadata()?.file_attributes();
+        for (attribute, value) in [
+            (FILE_ATTRIBUTE_READONLY, flags.immutable),
+            (FILE_ATTRIBUTE_HIDDEN, flags.hidden),
+        ] {
+            if value {
+                attributes |= attribute;
+            } else {
+                attributes &= !attribute;
+            }
+        }
+        // Times of 0 are left unchanged, as are attributes of 0, so clear
+        // them all with `FILE_ATTRIBUTE_NORMAL`.
+        let info = FILE_BASIC_INFO {
+            CreationTime: 0,
+            LastAccessTime: 0,
+            LastWriteTime: 0,
+            ChangeTime: 0,
+            FileAttributes: if attributes == 0 {
+                FILE_ATTRIBUTE_NORMAL
+            } else {
+                attributes
+            },
+        };
+        // SAFETY: `info` is a `FILE_BASIC_INFO` of the given size.
+        let ok = unsafe {
+            SetFileInformationByHandle(
+                file.as_raw_handle(),
+                FileBasicInfo,
+                (&info as *const FILE_BASIC_INFO).cast(),
+                size_of::<FILE_BASIC_INFO>() as u32,
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    windows,
+)))]
+mod sys {
+    use super::{unsupported, FileFlags};
+    use cap_std::fs::Dir;
+    use std::io;
+    use std::path::Path;
+
+    pub(super) fn get(_dir: &Dir, _path: &Path) -> io::Result<FileFlags> {
+        Err(unsupported())
+    }
+
+    pub(super) fn set(_dir: &Dir, _path: &Path, _flags: FileFlags) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
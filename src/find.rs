@@ -0,0 +1,112 @@
+use crate::{DirEntryView, DirView, Walk, WalkEntry};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+
+/// Options for [`DirView::find_with`] and [`DirView::find_first_with`].
+#[derive(Clone, Debug)]
+pub struct FindOptions {
+    max_depth: usize,
+    follow_symlinks: bool,
+}
+
+impl FindOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// The search initially has no maximum depth, and doesn't follow
+    /// symbolic links.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            follow_symlinks: false,
+        }
+    }
+
+    /// Sets the maximum depth of entries to search, where entries of the
+    /// searched view itself have a depth of 1.
+    #[inline]
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets whether to search the directories that symbolic links point to.
+    #[inline]
+    pub fn follow_symlinks(&mut self, follow_symlinks: bool) -> &mut Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+}
+
+impl Default for FindOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the entries in `self` and its subdirectories for which
+    /// `predicate` returns `true`.
+    ///
+    /// This is a convenience for filtering [`Self::walk`]. It fails if
+    /// reading any directory fails.
+    #[inline]
+    pub fn find<F: FnMut(&DirEntryView) -> bool>(
+        &self,
+        predicate: F,
+    ) -> io::Result<Vec<WalkEntry>> {
+        self.find_with(&FindOptions::new(), predicate)
+    }
+
+    /// Returns the entries in `self` and its subdirectories for which
+    /// `predicate` returns `true`, with the given options.
+    pub fn find_with<F: FnMut(&DirEntryView) -> bool>(
+        &self,
+        options: &FindOptions,
+        mut predicate: F,
+    ) -> io::Result<Vec<WalkEntry>> {
+        let mut found = Vec::new();
+        for entry in self.find_walk(options)? {
+            let entry = entry?;
+            if predicate(entry.entry()) {
+                found.push(entry);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Returns the first entry in `self` and its subdirectories for which
+    /// `predicate` returns `true`, without searching any further.
+    #[inline]
+    pub fn find_first<F: FnMut(&DirEntryView) -> bool>(
+        &self,
+        predicate: F,
+    ) -> io::Result<Option<WalkEntry>> {
+        self.find_first_with(&FindOptions::new(), predicate)
+    }
+
+    /// Returns the first entry in `self` and its subdirectories for which
+    /// `predicate` returns `true`, with the given options.
+    pub fn find_first_with<F: FnMut(&DirEntryView) -> bool>(
+        &self,
+        options: &FindOptions,
+        mut predicate: F,
+    ) -> io::Result<Option<WalkEntry>> {
+        for entry in self.find_walk(options)? {
+            let entry = entry?;
+            if predicate(entry.entry()) {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    fn find_walk(&self, options: &FindOptions) -> io::Result<Walk> {
+        Ok(self
+            .walk()?
+            .max_depth(options.max_depth)
+            .follow_links(options.follow_symlinks))
+    }
+}
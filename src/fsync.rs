@@ -0,0 +1,92 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Syncs the directory of `self` to storage, so that changes to its
+    /// entries, such as creating, removing, or renaming them, are durable.
+    ///
+    /// This corresponds to calling `fsync` on the directory. On platforms
+    /// other than Unix, directories can't be synced, and this does nothing.
+    #[inline]
+    pub fn sync_dir(&self) -> io::Result<()> {
+        self.audited(Operation::Sync, ".", |_| sync_dir(self.dir.borrow()))
+    }
+
+    /// Syncs the files and directories in the tree at `path` to storage,
+    /// bottom-up, so that each directory is synced after its contents.
+    ///
+    /// The tree is read through the view, so entries it hides aren't
+    /// synced, and subdirectories beyond its maximum depth fail to open.
+    /// Symbolic links and special files are skipped. Directories are only
+    /// synced on Unix, as with [`Self::sync_dir`].
+    pub fn sync_all_tree<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.audited(Operation::Sync, path, |path| {
+            self.check_resolve(Operation::Sync, path)?;
+            sync_tree(&self.open_dir(path)?)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Syncs the directory of `self` to storage, so that changes to its
+    /// entries, such as creating, removing, or renaming them, are durable.
+    ///
+    /// This corresponds to calling `fsync` on the directory. On platforms
+    /// other than Unix, directories can't be synced, and this does nothing.
+    #[inline]
+    pub fn sync_dir(&self) -> io::Result<()> {
+        self.audited(Operation::Sync, ".", |_| {
+            sync_dir(self.dir.borrow().as_cap_std())
+        })
+    }
+
+    /// Syncs the files and directories in the tree at `path` to storage,
+    /// bottom-up, so that each directory is synced after its contents.
+    ///
+    /// The tree is read through the view, so entries it hides aren't
+    /// synced, and subdirectories beyond its maximum depth fail to open.
+    /// Symbolic links and special files are skipped. Directories are only
+    /// synced on Unix, as with [`Self::sync_dir`].
+    pub fn sync_all_tree<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<()> {
+        self.audited(Operation::Sync, path, |path| {
+            self.check_resolve(Operation::Sync, path)?;
+            sync_tree(&self.open_dir(path)?.into_cap_std_view())
+        })
+    }
+}
+
+fn sync_tree(dir: &DirView) -> io::Result<()> {
+    for entry in dir.entries()? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            sync_tree(&entry.open_dir()?)?;
+        } else if file_type.is_file() {
+            entry.open()?.sync_all()?;
+        }
+    }
+    sync_dir(&dir.dir)
+}
+
+/// Syncs `dir` itself to storage.
+pub(crate) fn sync_dir(dir: &Dir) -> io::Result<()> {
+    // A `Dir` may be opened with `O_PATH`, which can't be synced, so open it
+    // again to sync it.
+    #[cfg(unix)]
+    {
+        use rustix::fs::{fsync, openat, Mode, OFlags};
+        let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+        fsync(openat(dir, ".", flags, Mode::empty())?)?;
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
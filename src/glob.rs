@@ -0,0 +1,288 @@
+use crate::{DirView, ReadDirView};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use std::{fmt, io};
+
+/// An iterator over the paths in a view matching a pattern, returned by
+/// [`DirView::glob`].
+///
+/// Paths are relative to the view, and yielded in sorted order within each
+/// directory, with a component of `**` matching in a directory before its
+/// subdirectories. Errors reading directories are yielded in place of the paths
+/// they would have produced, and iteration continues after them.
+pub struct Glob {
+    view: DirView,
+    components: Vec<PatternComponent>,
+    work: Vec<Work>,
+}
+
+pub(crate) enum PatternComponent {
+    Literal(String),
+    Pattern(Vec<Token>),
+    Recursive,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Token {
+    Char(char),
+    AnyChar,
+    AnyChars,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+enum Work {
+    Yield(PathBuf),
+    Visit(PathBuf, usize),
+    Fail(io::Error),
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns an iterator over the paths in `self` matching `pattern`.
+    ///
+    /// Patterns are sequences of `/`-separated components, which may use `?`
+    /// to match any character, `*` to match any sequence of characters, and
+    /// `[...]` to match any character in a set, such as `[a-z]`, or not in
+    /// it, such as `[!a-z]`. A component of `**` matches any number of
+    /// directories, including none.
+    ///
+    /// The pattern is evaluated by reading directories through the view, so
+    /// its restrictions apply, and matches can't escape it. Patterns that
+    /// are absolute or contain `..` components are rejected.
+    pub fn glob(&self, pattern: &str) -> io::Result<Glob> {
+        let components = parse(pattern)?;
+        Ok(Glob {
+            view: self.try_clone()?,
+            components,
+            work: vec![Work::Visit(PathBuf::new(), 0)],
+        })
+    }
+}
+
+impl Glob {
+    /// Matches component `index` of the pattern against the entries of the
+    /// directory at `dir`, queueing the results.
+    fn visit(&mut self, dir: PathBuf, index: usize) -> io::Result<()> {
+        let Some(component) = self.components.get(index) else {
+            if !dir.as_os_str().is_empty() {
+                self.work.push(Work::Yield(dir));
+            }
+            return Ok(());
+        };
+        let last = index + 1 == self.components.len();
+        let mut work = Vec::new();
+        match component {
+            PatternComponent::Literal(name) => {
+                let path = dir.join(name);
+                match self.view.symlink_metadata(&path) {
+                    Ok(_) if last => work.push(Work::Yield(path)),
+                    Ok(_) if self.view.is_dir(&path) => work.push(Work::Visit(path, index + 1)),
+                    Ok(_) => {}
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            PatternComponent::Pattern(tokens) => {
+                for name in self.names(&dir)? {
+                    if !matches(tokens, &name.to_string_lossy()) {
+                        continue;
+                    }
+                    let path = dir.join(&name);
+                    if last {
+                        work.push(Work::Yield(path));
+                    } else if self.view.is_dir(&path) {
+                        work.push(Work::Visit(path, index + 1));
+                    }
+                }
+            }
+            PatternComponent::Recursive => {
+                work.push(Work::Visit(dir.clone(), index + 1));
+                for name in self.names(&dir)? {
+                    let path = dir.join(&name);
+                    match self.view.symlink_metadata(&path) {
+                        Ok(metadata) if metadata.is_dir() => work.push(Work::Visit(path, index)),
+                        Ok(_) => {}
+                        Err(err) => work.push(Work::Fail(err)),
+                    }
+                }
+            }
+        }
+        self.work.extend(work.into_iter().rev());
+        Ok(())
+    }
+
+    /// Returns the sorted names of the entries in the directory at `dir`.
+    fn names(&self, dir: &Path) -> io::Result<Vec<OsString>> {
+        let read_dir: ReadDirView = if dir.as_os_str().is_empty() {
+            self.view.entries()?
+        } else {
+            self.view.read_dir(dir)?
+        };
+        let mut names = Vec::new();
+        for entry in read_dir {
+            names.push(entry?.file_name());
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+impl Iterator for Glob {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.work.pop()? {
+                Work::Yield(path) => return Some(Ok(path)),
+                Work::Visit(dir, index) => {
+                    if let Err(err) = self.visit(dir, index) {
+                        return Some(Err(err));
+                    }
+                }
+                Work::Fail(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Glob").finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn parse(pattern: &str) -> io::Result<Vec<PatternComponent>> {
+    let mut components = Vec::new();
+    for component in Path::new(pattern).components() {
+        let component = match component {
+            Component::Normal(component) => component.to_str().unwrap_or_default(),
+            Component::CurDir => continue,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(invalid_pattern(pattern));
+            }
+        };
+        if component == "**" {
+            if !matches!(components.last(), Some(PatternComponent::Recursive)) {
+                components.push(PatternComponent::Recursive);
+            }
+            continue;
+        }
+        let tokens = tokenize(component).ok_or_else(|| invalid_pattern(pattern))?;
+        if tokens.iter().all(|token| matches!(token, Token::Char(_))) {
+            components.push(PatternComponent::Literal(component.to_owned()));
+        } else {
+            components.push(PatternComponent::Pattern(tokens));
+        }
+    }
+    Ok(components)
+}
+
+fn tokenize(component: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = component.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '?' => Token::AnyChar,
+            '*' => Token::AnyChars,
+            '[' => {
+                let mut negated = false;
+                let mut ranges = Vec::new();
+                loop {
+                    let c = chars.next()?;
+                    if ranges.is_empty() && !negated && (c == '!' || c == '^') {
+                        negated = true;
+                        continue;
+                    }
+                    // A `]` at the start of a set is part of it.
+                    if c == ']' && !ranges.is_empty() {
+                        break;
+                    }
+                    let mut lookahead = chars.clone();
+                    match (lookahead.next(), lookahead.next()) {
+                        (Some('-'), Some(end)) if end != ']' => {
+                            chars = lookahead;
+                            ranges.push((c, end));
+                        }
+                        _ => ranges.push((c, c)),
+                    }
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Char(c),
+        });
+    }
+    Some(tokens)
+}
+
+/// Tests whether the components of `path` match `components`.
+#[cfg(feature = "ignore")]
+pub(crate) fn matches_path(components: &[PatternComponent], path: &[String]) -> bool {
+    let Some((component, rest)) = components.split_first() else {
+        return path.is_empty();
+    };
+    match component {
+        PatternComponent::Recursive => (0..=path.len()).any(|i| matches_path(rest, &path[i..])),
+        PatternComponent::Literal(literal) => {
+            path.first() == Some(literal) && matches_path(rest, &path[1..])
+        }
+        PatternComponent::Pattern(tokens) => {
+            path.first().is_some_and(|name| matches(tokens, name)) && matches_path(rest, &path[1..])
+        }
+    }
+}
+
+/// Tests whether `name` matches `tokens`.
+fn matches(tokens: &[Token], name: &str) -> bool {
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut t, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match tokens.get(t) {
+            Some(Token::AnyChars) => {
+                backtrack = Some((t, n));
+                t += 1;
+                continue;
+            }
+            Some(token) if matches_char(token, name[n]) => {
+                t += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star, start)) => {
+                t = star + 1;
+                n = start + 1;
+                backtrack = Some((star, start + 1));
+            }
+            None => return false,
+        }
+    }
+    tokens[t..].iter().all(|token| *token == Token::AnyChars)
+}
+
+fn matches_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => *expected == c,
+        Token::AnyChar => true,
+        Token::AnyChars => false,
+        Token::Class { negated, ranges } => {
+            ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&c))
+                != *negated
+        }
+    }
+}
+
+fn invalid_pattern(pattern: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid glob pattern `{}`", pattern),
+    )
+}
@@ -0,0 +1,261 @@
+use crate::DirView;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// A streaming hash function for [`DirView::hash_tree`].
+///
+/// This is implemented by [`Sha256`], and can be implemented for hash
+/// functions from other crates.
+///
+/// [`Sha256`]: crate::Sha256
+pub trait TreeHash: Sync {
+    /// The state of a hash in progress, which data is written to.
+    type Hasher: Write;
+
+    /// Creates a new hash with no data.
+    fn hasher(&self) -> Self::Hasher;
+
+    /// Returns the digest of the data written to `hasher`.
+    fn finish(&self, hasher: Self::Hasher) -> Vec<u8>;
+}
+
+/// Options for [`DirView::hash_tree_with`].
+#[derive(Clone, Debug)]
+pub struct HashTreeOptions {
+    threads: usize,
+}
+
+impl HashTreeOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Files are initially hashed one at a time.
+    #[inline]
+    pub fn new() -> Self {
+        Self { threads: 1 }
+    }
+
+    /// Sets the number of threads to hash files on.
+    ///
+    /// A value of 0 uses [`std::thread::available_parallelism`].
+    #[inline]
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads;
+        self
+    }
+}
+
+impl Default for HashTreeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The hashes of the entries in a tree, returned by [`DirView::hash_tree`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TreeManifest {
+    entries: BTreeMap<PathBuf, ManifestEntry>,
+    root_digest: Vec<u8>,
+}
+
+/// An entry in a [`TreeManifest`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ManifestEntry {
+    /// A file, with the digest of its contents.
+    File(Vec<u8>),
+
+    /// A symbolic link, with the digest of its contents.
+    Symlink(Vec<u8>),
+
+    /// A directory.
+    Dir,
+}
+
+impl TreeManifest {
+    /// Constructs a manifest of `entries`, whose paths are relative to the
+    /// root of the tree, computing the digest of the whole tree with
+    /// `algorithm`.
+    ///
+    /// This is for manifests distributed separately from the trees they
+    /// describe, such as with updates, to check with [`VerifiedDirView`].
+    ///
+    /// [`VerifiedDirView`]: crate::VerifiedDirView
+    pub fn from_entries<A: TreeHash>(
+        entries: impl IntoIterator<Item = (PathBuf, ManifestEntry)>,
+        algorithm: &A,
+    ) -> io::Result<Self> {
+        let entries = entries.into_iter().collect();
+        Ok(Self {
+            root_digest: root_digest(&entries, algorithm)?,
+            entries,
+        })
+    }
+
+    /// Returns the entries of the tree, sorted by path. Paths are relative
+    /// to the root of the tree.
+    #[inline]
+    pub fn entries(&self) -> impl Iterator<Item = (&Path, &ManifestEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_path(), entry))
+    }
+
+    /// Returns the entry at `path`, relative to the root of the tree.
+    #[inline]
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&ManifestEntry> {
+        self.entries.get(path.as_ref())
+    }
+
+    /// Returns a digest of the whole tree, covering the path, type, and
+    /// digest of every entry, which can be compared to verify the tree.
+    #[inline]
+    pub fn root_digest(&self) -> &[u8] {
+        &self.root_digest
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Hashes the files and symbolic links in the tree at `path` with
+    /// `algorithm`, returning a manifest of their digests and a digest of
+    /// the whole tree.
+    ///
+    /// The tree is read through the view, so entries it hides aren't
+    /// included, and subdirectories beyond its maximum depth fail to open.
+    /// Symbolic links are hashed by their contents, rather than followed,
+    /// and special files are skipped.
+    #[inline]
+    pub fn hash_tree<P: AsRef<Path>, A: TreeHash>(
+        &self,
+        path: P,
+        algorithm: &A,
+    ) -> io::Result<TreeManifest> {
+        self.hash_tree_with(path, algorithm, &HashTreeOptions::new())
+    }
+
+    /// Hashes the tree at `path` with `algorithm`, with the given options.
+    ///
+    /// See [`Self::hash_tree`] for details.
+    pub fn hash_tree_with<P: AsRef<Path>, A: TreeHash>(
+        &self,
+        path: P,
+        algorithm: &A,
+        options: &HashTreeOptions,
+    ) -> io::Result<TreeManifest> {
+        let root = self.open_dir(path)?;
+        let mut entries = BTreeMap::new();
+        let mut files = Vec::new();
+        for entry in root.walk()? {
+            let entry = entry?;
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                entries.insert(entry.into_path(), ManifestEntry::Dir);
+            } else if file_type.is_symlink() {
+                let contents = root.read_link_contents(entry.path())?;
+                let mut hasher = algorithm.hasher();
+                hasher.write_all(contents.as_os_str().as_encoded_bytes())?;
+                let digest = algorithm.finish(hasher);
+                entries.insert(entry.into_path(), ManifestEntry::Symlink(digest));
+            } else if file_type.is_file() {
+                files.push(entry.into_path());
+            }
+        }
+
+        let threads = match options.threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            threads => threads,
+        };
+        for (path, digest) in hash_files(&root, files, algorithm, threads)? {
+            entries.insert(path, ManifestEntry::File(digest));
+        }
+
+        TreeManifest::from_entries(entries, algorithm)
+    }
+}
+
+/// Returns a digest of `entries` with `algorithm`, covering the path, type,
+/// and digest of every entry.
+fn root_digest<A: TreeHash>(
+    entries: &BTreeMap<PathBuf, ManifestEntry>,
+    algorithm: &A,
+) -> io::Result<Vec<u8>> {
+    let mut hasher = algorithm.hasher();
+    for (path, entry) in entries {
+        let (tag, digest): (&[u8], &[u8]) = match entry {
+            ManifestEntry::File(digest) => (b"f", digest),
+            ManifestEntry::Symlink(digest) => (b"l", digest),
+            ManifestEntry::Dir => (b"d", &[]),
+        };
+        hasher.write_all(tag)?;
+        // Separate components with `/` on all platforms, so that trees
+        // have the same digest everywhere.
+        for (i, component) in path.iter().enumerate() {
+            if i != 0 {
+                hasher.write_all(b"/")?;
+            }
+            hasher.write_all(component.as_encoded_bytes())?;
+        }
+        hasher.write_all(&[0])?;
+        hasher.write_all(digest)?;
+    }
+    Ok(algorithm.finish(hasher))
+}
+
+/// Hashes `files` in `root` on up to `threads` threads.
+fn hash_files<A: TreeHash>(
+    root: &DirView,
+    files: Vec<PathBuf>,
+    algorithm: &A,
+    threads: usize,
+) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let hash = |path: &Path| -> io::Result<Vec<u8>> {
+        let mut hasher = algorithm.hasher();
+        io::copy(&mut root.open(path)?, &mut hasher)?;
+        Ok(algorithm.finish(hasher))
+    };
+    let threads = threads.min(files.len());
+    if threads <= 1 {
+        return files
+            .into_iter()
+            .map(|path| {
+                let digest = hash(&path)?;
+                Ok((path, digest))
+            })
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let digests = thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut digests = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(i) else {
+                            return Ok(digests);
+                        };
+                        digests.push((i, hash(path)?));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+    let mut digests = digests.into_iter().flatten().collect::<Vec<_>>();
+    digests.sort_by_key(|(i, _)| *i);
+    Ok(files
+        .into_iter()
+        .zip(digests)
+        .map(|(path, (_, digest))| (path, digest))
+        .collect())
+}
@@ -0,0 +1,423 @@
+use crate::date::{civil_from_days, days_from_civil};
+use crate::{Budgeted, DirViewUtf8};
+use camino::{Utf8Path, Utf8PathBuf};
+use cap_std::fs_utf8::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The file served for a request for a directory.
+const INDEX: &str = "index.html";
+
+/// A request for [`DirViewUtf8::serve_http`].
+///
+/// This holds just the parts of an HTTP request which affect how a static
+/// file is served, so it can be filled in from any HTTP library's request
+/// type.
+#[derive(Clone, Debug)]
+pub struct HttpRequest<'a> {
+    method: &'a str,
+    target: &'a str,
+    range: Option<&'a str>,
+    if_range: Option<&'a str>,
+    if_none_match: Option<&'a str>,
+    if_modified_since: Option<&'a str>,
+}
+
+impl<'a> HttpRequest<'a> {
+    /// Creates a request with the given method, such as `GET`, and
+    /// request-target, such as `/index.html?query`.
+    pub fn new(method: &'a str, target: &'a str) -> Self {
+        Self {
+            method,
+            target,
+            range: None,
+            if_range: None,
+            if_none_match: None,
+            if_modified_since: None,
+        }
+    }
+
+    /// Adds a header to the request.
+    ///
+    /// Only `Range`, `If-Range`, `If-None-Match`, and `If-Modified-Since`
+    /// affect the response, so other headers are ignored.
+    pub fn header(&mut self, name: &str, value: &'a str) -> &mut Self {
+        let field = if name.eq_ignore_ascii_case("range") {
+            &mut self.range
+        } else if name.eq_ignore_ascii_case("if-range") {
+            &mut self.if_range
+        } else if name.eq_ignore_ascii_case("if-none-match") {
+            &mut self.if_none_match
+        } else if name.eq_ignore_ascii_case("if-modified-since") {
+            &mut self.if_modified_since
+        } else {
+            return self;
+        };
+        *field = Some(value);
+        self
+    }
+}
+
+/// A response from [`DirViewUtf8::serve_http`].
+#[derive(Debug)]
+pub struct HttpResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Option<io::Take<Budgeted<File>>>,
+}
+
+impl HttpResponse {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Returns the status code, such as 200, 206, 304, or 404.
+    #[inline]
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the headers to send, including `Content-Length`.
+    #[inline]
+    pub fn headers(&self) -> &[(&'static str, String)] {
+        &self.headers
+    }
+
+    /// Returns the value of the header `name`, if it's set.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the body to send, positioned at the start of the requested
+    /// range, or `None` if there is no body, as for `HEAD` requests and
+    /// errors.
+    ///
+    /// The file holds a handle from the view's [`HandleBudget`], if it has
+    /// one, until it's dropped.
+    ///
+    /// [`HandleBudget`]: crate::HandleBudget
+    #[inline]
+    pub fn into_body(self) -> Option<io::Take<Budgeted<File>>> {
+        self.body
+    }
+
+    fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn empty(status: u16) -> Self {
+        Self::new(status).with_header("Content-Length", "0")
+    }
+}
+
+impl<D: Borrow<Dir>> DirViewUtf8<D> {
+    /// Serves a static file from `self` in response to `request`.
+    ///
+    /// The request-target is percent-decoded and resolved through the view,
+    /// so it can't reach outside of it: targets with `..` components, or
+    /// with encoded `/`s or NULs, are rejected with 400 Bad Request, and
+    /// entries the view denies access to with 403 Forbidden. A request for a
+    /// directory serves its `index.html`, after redirecting to add a
+    /// trailing `/` if there isn't one. Only `GET` and `HEAD` are allowed.
+    ///
+    /// Single-range `Range` requests are served with 206 Partial Content,
+    /// subject to `If-Range`, and `If-None-Match` and `If-Modified-Since`
+    /// are answered with 304 Not Modified when they match. Requests for
+    /// several ranges are served in full.
+    ///
+    /// Files are only read, so this is best used with a
+    /// [`ViewKind::Readonly`] view. Errors other than those above, such as
+    /// I/O errors, are returned for the caller to report, typically as 500
+    /// Internal Server Error.
+    ///
+    /// [`ViewKind::Readonly`]: crate::ViewKind::Readonly
+    pub fn serve_http(&self, request: &HttpRequest<'_>) -> io::Result<HttpResponse> {
+        let head = match request.method {
+            "GET" => false,
+            "HEAD" => true,
+            _ => return Ok(HttpResponse::empty(405).with_header("Allow", "GET, HEAD")),
+        };
+        let target = request.target;
+        let (target, query) = target.split_at(target.find(['?', '#']).unwrap_or(target.len()));
+        let query = &query[..query.find('#').unwrap_or(query.len())];
+        let Some(mut path) = sanitize(target) else {
+            return Ok(HttpResponse::empty(400));
+        };
+
+        let metadata = match self.metadata(if path.as_str().is_empty() {
+            Utf8Path::new(".")
+        } else {
+            &path
+        }) {
+            Ok(metadata) => metadata,
+            Err(err) => return error_response(err),
+        };
+        if metadata.is_dir() {
+            if !target.ends_with('/') {
+                // Redirect to the sanitized path, so that a target such as
+                // `//host/dir` can't redirect to another host.
+                let mut location = String::from("/");
+                for component in path.components() {
+                    location.push_str(&percent_encode(component.as_str()));
+                    location.push('/');
+                }
+                location.push_str(query);
+                return Ok(HttpResponse::empty(301).with_header("Location", location));
+            }
+            path.push(INDEX);
+        }
+
+        let mut file = match self.open_budgeted(&path, OpenOptions::new().read(true)) {
+            Ok(file) => file,
+            Err(err) => return error_response(err),
+        };
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Ok(HttpResponse::empty(404));
+        }
+        let len = metadata.len();
+        let modified = metadata.modified().ok().map(|time| time.into_std());
+        let etag = etag(len, modified);
+        let last_modified = modified.map(http_date);
+
+        let not_modified = match (request.if_none_match, request.if_modified_since) {
+            (Some(if_none_match), _) => etag_matches(if_none_match, &etag),
+            (None, Some(since)) => match (modified, parse_http_date(since)) {
+                (Some(modified), Some(since)) => modified < since + Duration::from_secs(1),
+                _ => false,
+            },
+            (None, None) => false,
+        };
+        let validators = |mut response: HttpResponse| {
+            response = response.with_header("ETag", etag.clone());
+            if let Some(last_modified) = &last_modified {
+                response = response.with_header("Last-Modified", last_modified.clone());
+            }
+            response
+        };
+        if not_modified {
+            return Ok(validators(HttpResponse::new(304)));
+        }
+
+        let range_applies = match request.if_range {
+            None => true,
+            Some(if_range) if if_range.starts_with('"') => if_range == etag,
+            Some(if_range) => last_modified.as_deref() == Some(if_range),
+        };
+        let range = match request.range.filter(|_| range_applies) {
+            Some(range) => parse_range(range, len),
+            None => Range::Full,
+        };
+        let (status, start, count) = match range {
+            Range::Full => (200, 0, len),
+            Range::Partial(start, end) => (206, start, end - start + 1),
+            Range::Unsatisfiable => {
+                return Ok(validators(HttpResponse::empty(416))
+                    .with_header("Content-Range", format!("bytes */{len}")));
+            }
+        };
+
+        let mut response = validators(HttpResponse::new(status))
+            .with_header("Content-Type", content_type(&path))
+            .with_header("Content-Length", count.to_string())
+            .with_header("Accept-Ranges", "bytes");
+        if status == 206 {
+            response = response.with_header(
+                "Content-Range",
+                format!("bytes {start}-{}/{len}", start + count - 1),
+            );
+        }
+        if !head {
+            file.seek(SeekFrom::Start(start))?;
+            response.body = Some(file.take(count));
+        }
+        Ok(response)
+    }
+}
+
+/// Decodes the path of a request-target into a path relative to the view,
+/// or returns `None` if it's malformed or could refer outside the view.
+fn sanitize(target: &str) -> Option<Utf8PathBuf> {
+    let rest = target.strip_prefix('/')?;
+    let mut path = Utf8PathBuf::new();
+    for segment in rest.split('/') {
+        let segment = percent_decode(segment)?;
+        match segment.as_str() {
+            "" | "." => {}
+            ".." => return None,
+            _ if segment.contains(['/', '\0']) => return None,
+            _ if cfg!(windows) && segment.contains(['\\', ':']) => return None,
+            _ => path.push(segment),
+        }
+    }
+    Some(path)
+}
+
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut iter = segment.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn error_response(err: io::Error) -> io::Result<HttpResponse> {
+    match err.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => Ok(HttpResponse::empty(404)),
+        io::ErrorKind::PermissionDenied => Ok(HttpResponse::empty(403)),
+        _ => Err(err),
+    }
+}
+
+fn etag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!("\"{:x}-{:x}\"", modified.as_nanos(), len)
+}
+
+/// Tests whether an `If-None-Match` header matches `etag`, using the weak
+/// comparison.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == etag)
+}
+
+enum Range {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header for a file of `len` bytes. Multiple ranges, and
+/// malformed headers, are ignored.
+fn parse_range(range: &str, len: u64) -> Range {
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return Range::Full;
+    };
+    if spec.contains(',') {
+        return Range::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Range::Full;
+    };
+    let (start, end) = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.is_empty() => (start, len.saturating_sub(1)),
+        (Err(_), Ok(suffix)) if start.is_empty() => {
+            if suffix == 0 {
+                return Range::Unsatisfiable;
+            }
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        _ => return Range::Full,
+    };
+    if start >= len {
+        return Range::Unsatisfiable;
+    }
+    Range::Partial(start, end)
+}
+
+fn content_type(path: &Utf8Path) -> &'static str {
+    let extension = path.extension().unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats `time` as an IMF-fixdate, such as `Sun, 06 Nov 1994 08:49:37
+/// GMT`.
+fn http_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs()) as i64;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+        DAYS[(days + 4).rem_euclid(7) as usize],
+        MONTHS[month as usize - 1],
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Parses an IMF-fixdate. The obsolete formats aren't accepted.
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let mut fields = date.trim().split([' ', ':']);
+    let _weekday = fields.next()?;
+    let day = fields.next()?.parse::<u32>().ok()?;
+    let month = fields.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+    let year = i64::from(fields.next()?.parse::<u32>().ok()?);
+    let hour = fields.next()?.parse::<u64>().ok()?;
+    let minute = fields.next()?.parse::<u64>().ok()?;
+    let second = fields.next()?.parse::<u64>().ok()?;
+    if fields.next() != Some("GMT")
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    let secs = days
+        .checked_mul(86400)?
+        .checked_add(hour * 3600 + minute * 60 + second)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
@@ -0,0 +1,108 @@
+use crate::glob::{self, PatternComponent};
+use std::io;
+use std::path::Path;
+
+/// The names of the files whose rules are applied by walks which respect
+/// ignore files, in increasing order of precedence.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The rules from the ignore files in a directory, in the format of
+/// `.gitignore` files.
+#[derive(Default)]
+pub(crate) struct Rules(Vec<Rule>);
+
+struct Rule {
+    components: Vec<PatternComponent>,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl Rules {
+    /// Reads the rules from the ignore files in a directory, using
+    /// `read_to_string` to read each file by name.
+    pub(crate) fn read(read_to_string: impl Fn(&str) -> io::Result<String>) -> io::Result<Self> {
+        let mut rules = Self::default();
+        for name in IGNORE_FILES {
+            match read_to_string(name) {
+                Ok(contents) => rules.add(&contents),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Adds the rules in `contents`, which take precedence over those
+    /// already added. Lines which aren't valid patterns are skipped.
+    fn add(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            // A leading backslash escapes a `#` or `!`.
+            let line = line.strip_prefix('\\').unwrap_or(line);
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            // Patterns containing a `/` are relative to the directory
+            // containing the ignore file. Others match at any depth.
+            let anchored = line.contains('/');
+            let line = line.strip_prefix('/').unwrap_or(line);
+            let Ok(mut components) = glob::parse(line) else {
+                continue;
+            };
+            if components.is_empty() {
+                continue;
+            }
+            if !anchored {
+                components.insert(0, PatternComponent::Recursive);
+            }
+            self.0.push(Rule {
+                components,
+                negated,
+                dir_only,
+            });
+        }
+    }
+
+    /// Returns `Some(true)` if the rules ignore `path`, relative to the
+    /// directory containing the ignore files, `Some(false)` if they
+    /// explicitly don't, and `None` if no rule matches it.
+    pub(crate) fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let path = path
+            .iter()
+            .map(|component| component.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        self.0
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && glob::matches_path(&rule.components, &path))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Tests whether `path` is ignored by the rules in `levels`, which pair
+/// the rules of each directory with its path, from the outermost.
+pub(crate) fn is_ignored<'a>(
+    levels: impl DoubleEndedIterator<Item = (&'a Path, &'a Rules)>,
+    path: &Path,
+    is_dir: bool,
+) -> bool {
+    for (base, rules) in levels.rev() {
+        if let Ok(path) = path.strip_prefix(base) {
+            if let Some(ignored) = rules.matched(path, is_dir) {
+                return ignored;
+            }
+        }
+    }
+    false
+}
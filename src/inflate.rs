@@ -0,0 +1,331 @@
+use crate::archive::malformed;
+use crate::zip::{DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+use std::io::{self, BufRead, Read};
+
+/// The order in which a dynamic deflate block lists the lengths of the code
+/// length code.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// How far back deflate's back-references can reach.
+const WINDOW: usize = 1 << 15;
+
+/// A reader of the decompressed contents of deflated data read from `R`.
+///
+/// Input is only consumed as it's needed, so once the deflated data ends,
+/// `R` is just after it, where formats which wrap deflated data put their
+/// trailers.
+pub(crate) struct Inflate<R> {
+    reader: BitReader<R>,
+    state: State,
+
+    /// Whether the current block is the last one.
+    last: bool,
+
+    window: Window,
+
+    /// The distance and remaining length of a back-reference being copied.
+    copy: (usize, usize),
+}
+
+enum State {
+    /// At the start of a block.
+    Header,
+
+    /// In a stored block, with the given number of bytes left.
+    Stored(usize),
+
+    /// In a compressed block, with its literal/length and distance codes.
+    Codes(Box<(Huffman, Huffman)>),
+
+    /// After the last block.
+    Done,
+}
+
+impl<R: BufRead> Inflate<R> {
+    pub(crate) fn new(input: R) -> Self {
+        Self {
+            reader: BitReader {
+                input,
+                bits: 0,
+                len: 0,
+            },
+            state: State::Header,
+            last: false,
+            window: Window {
+                bytes: vec![0; WINDOW].into_boxed_slice(),
+                pos: 0,
+                len: 0,
+            },
+            copy: (0, 0),
+        }
+    }
+
+    /// Returns a mutable reference to the input.
+    #[inline]
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader.input
+    }
+
+    /// Starts decompressing new deflated data from the input, once the
+    /// previous data has ended.
+    pub(crate) fn reset(&mut self) {
+        self.reader.align();
+        self.state = State::Header;
+        self.last = false;
+        // Earlier output can't be referred back to.
+        self.window.len = 0;
+    }
+
+    /// Reads the header of the next block.
+    fn next_block(&mut self) -> io::Result<State> {
+        if self.last {
+            return Ok(State::Done);
+        }
+        self.last = self.reader.bits(1)? == 1;
+        match self.reader.bits(2)? {
+            0 => {
+                self.reader.align();
+                let mut header = [0; 4];
+                for byte in &mut header {
+                    *byte = self.reader.byte()?;
+                }
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if u16::from_le_bytes([header[2], header[3]]) != !len {
+                    return Err(corrupt());
+                }
+                Ok(State::Stored(usize::from(len)))
+            }
+            1 => Ok(State::Codes(Box::new(fixed_codes()?))),
+            2 => Ok(State::Codes(Box::new(dynamic_codes(&mut self.reader)?))),
+            _ => Err(corrupt()),
+        }
+    }
+}
+
+impl<R: BufRead> Read for Inflate<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            let (distance, copy_len) = self.copy;
+            if copy_len != 0 {
+                buf[len] = self.window.push(self.window.get(distance));
+                len += 1;
+                self.copy.1 -= 1;
+                continue;
+            }
+            match &mut self.state {
+                State::Header => self.state = self.next_block()?,
+                State::Stored(0) => self.state = State::Header,
+                State::Stored(left) => {
+                    *left -= 1;
+                    buf[len] = self.window.push(self.reader.byte()?);
+                    len += 1;
+                }
+                State::Codes(codes) => {
+                    let symbol = codes.0.decode(&mut self.reader)?;
+                    match symbol {
+                        0..=255 => {
+                            buf[len] = self.window.push(symbol as u8);
+                            len += 1;
+                        }
+                        256 => self.state = State::Header,
+                        _ => {
+                            let code = usize::from(symbol - 257);
+                            if code >= LENGTH_BASE.len() {
+                                return Err(corrupt());
+                            }
+                            let copy_len = usize::from(LENGTH_BASE[code])
+                                + self.reader.bits(u32::from(LENGTH_EXTRA[code]))? as usize;
+                            let code = usize::from(codes.1.decode(&mut self.reader)?);
+                            if code >= DISTANCE_BASE.len() {
+                                return Err(corrupt());
+                            }
+                            let distance = usize::from(DISTANCE_BASE[code])
+                                + self.reader.bits(u32::from(DISTANCE_EXTRA[code]))? as usize;
+                            if distance > self.window.len {
+                                return Err(corrupt());
+                            }
+                            self.copy = (distance, copy_len);
+                        }
+                    }
+                }
+                State::Done => break,
+            }
+        }
+        Ok(len)
+    }
+}
+
+/// The most recent output, which back-references copy from.
+struct Window {
+    bytes: Box<[u8]>,
+
+    /// The position in `bytes` of the next byte of output.
+    pos: usize,
+
+    /// The number of bytes of output in `bytes`.
+    len: usize,
+}
+
+impl Window {
+    /// Appends `byte`, and returns it.
+    fn push(&mut self, byte: u8) -> u8 {
+        self.bytes[self.pos] = byte;
+        self.pos = (self.pos + 1) % WINDOW;
+        self.len = (self.len + 1).min(WINDOW);
+        byte
+    }
+
+    /// Returns the byte `distance` bytes back.
+    fn get(&self, distance: usize) -> u8 {
+        self.bytes[(self.pos + WINDOW - distance) % WINDOW]
+    }
+}
+
+/// Reads bits least-significant first, as deflate writes them.
+struct BitReader<R> {
+    input: R,
+    bits: u64,
+    len: u32,
+}
+
+impl<R: BufRead> BitReader<R> {
+    /// Reads the next whole byte of the input, ignoring any bits left over.
+    fn byte(&mut self) -> io::Result<u8> {
+        let byte = *self.input.fill_buf()?.first().ok_or_else(corrupt)?;
+        self.input.consume(1);
+        Ok(byte)
+    }
+
+    fn bits(&mut self, len: u32) -> io::Result<u32> {
+        while self.len < len {
+            self.bits |= u64::from(self.byte()?) << self.len;
+            self.len += 8;
+        }
+        let value = (self.bits & ((1 << len) - 1)) as u32;
+        self.bits >>= len;
+        self.len -= len;
+        Ok(value)
+    }
+
+    /// Skips to the next whole byte.
+    fn align(&mut self) {
+        // Bytes are only read as they're needed, so fewer than eight bits
+        // remain, all from the current byte.
+        self.bits = 0;
+        self.len = 0;
+    }
+}
+
+/// A canonical Huffman code, decoded as in zlib's "puff".
+struct Huffman {
+    /// The number of codes of each length.
+    counts: [u16; 16],
+
+    /// The symbols, in order of their codes.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Constructs the code with the given code lengths for each symbol.
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0; 16];
+        for len in lengths {
+            counts[usize::from(*len)] += 1;
+        }
+        // Check that no more codes are assigned than there are bit patterns
+        // for. Incomplete codes are allowed, as for a single distance code.
+        let mut left = 1_i32;
+        for count in &counts[1..] {
+            left = (left << 1) - i32::from(*count);
+            if left < 0 {
+                return Err(corrupt());
+            }
+        }
+        let mut offsets = [0; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                let offset = &mut offsets[usize::from(*len)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode<R: BufRead>(&self, reader: &mut BitReader<R>) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0_i32, 0_i32, 0_i32);
+        for count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(*count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt())
+    }
+}
+
+fn corrupt() -> io::Error {
+    malformed("corrupt deflated data")
+}
+
+/// Returns the fixed literal/length and distance codes.
+fn fixed_codes() -> io::Result<(Huffman, Huffman)> {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+/// Reads the literal/length and distance codes of a dynamic block.
+fn dynamic_codes<R: BufRead>(reader: &mut BitReader<R>) -> io::Result<(Huffman, Huffman)> {
+    let literals = reader.bits(5)? as usize + 257;
+    let distances = reader.bits(5)? as usize + 1;
+    let code_lengths = reader.bits(4)? as usize + 4;
+    if literals > 286 || distances > 30 {
+        return Err(corrupt());
+    }
+    let mut lengths = [0; 19];
+    for index in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[*index] = reader.bits(3)? as u8;
+    }
+    let code = Huffman::new(&lengths)?;
+
+    let mut lengths = vec![0; literals + distances];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..index].last().ok_or_else(corrupt)?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        let run = lengths.get_mut(index..index + repeat).ok_or_else(corrupt)?;
+        run.fill(value);
+        index += repeat;
+    }
+    // A block must be able to end.
+    if lengths[256] == 0 {
+        return Err(corrupt());
+    }
+    Ok((
+        Huffman::new(&lengths[..literals])?,
+        Huffman::new(&lengths[literals..])?,
+    ))
+}
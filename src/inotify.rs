@@ -0,0 +1,304 @@
+use crate::policy::is_dot_file;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation, WatchEvent, WatchOptions};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CString, OsStr};
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// The changes watched for in each directory.
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_MODIFY
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_ONLYDIR
+    | libc::IN_EXCL_UNLINK;
+
+/// The size of the fixed part of an `inotify_event`.
+const EVENT_SIZE: usize = 16;
+
+/// An iterator over the changes made in a watched directory, returned by
+/// [`DirView::watch`].
+///
+/// Iterating blocks until a change is made. Changes are reported by the OS,
+/// using inotify, so they're reported whether they're made through the view
+/// or not. If changes are made faster than they're consumed, the OS drops
+/// some of them, which is reported as an error.
+pub struct EventStream {
+    inotify: OwnedFd,
+    view: DirView,
+    prefix: PathBuf,
+    recursive: bool,
+    hide_hidden: bool,
+
+    /// The path of the directory each watch descriptor watches, relative to
+    /// `view`.
+    watches: HashMap<i32, PathBuf>,
+
+    buf: Vec<u8>,
+    events: VecDeque<io::Result<WatchEvent>>,
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Watches the directory at `path` for changes.
+    ///
+    /// Only changes to entries within the directory, or its subdirectories
+    /// with [`WatchOptions::recursive`], are reported, and entries the view
+    /// hides are left out.
+    pub fn watch<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WatchOptions,
+    ) -> io::Result<EventStream> {
+        self.audited(Operation::Watch, path, |path| {
+            let view = self.open_child_dir(path)?;
+            EventStream::new(view, path.to_path_buf(), options)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Watches the directory at `path` for changes.
+    ///
+    /// See [`DirView::watch`].
+    pub fn watch<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        options: &WatchOptions,
+    ) -> io::Result<EventStream> {
+        self.audited(Operation::Watch, path, |path| {
+            let view = self.open_child_dir(path)?.into_cap_std_view();
+            EventStream::new(view, path.into(), options)
+        })
+    }
+}
+
+impl EventStream {
+    fn new(view: DirView, prefix: PathBuf, options: &WatchOptions) -> io::Result<Self> {
+        // SAFETY: `inotify_init1` takes no pointers.
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut stream = Self {
+            // SAFETY: `inotify_init1` returned a new file descriptor.
+            inotify: unsafe { OwnedFd::from_raw_fd(fd) },
+            hide_hidden: view.policy.hide_hidden,
+            view,
+            prefix,
+            recursive: options.recursive,
+            watches: HashMap::new(),
+            buf: vec![0; 4096 + EVENT_SIZE + libc::FILENAME_MAX as usize],
+            events: VecDeque::new(),
+        };
+        let wd = add_watch(&stream.inotify, &stream.view)?;
+        stream.watches.insert(wd, PathBuf::new());
+        if stream.recursive {
+            stream.watch_subdirs(Path::new(""));
+        }
+        Ok(stream)
+    }
+
+    /// Watches the subdirectories of the directory at `path`. Directories
+    /// which can't be opened through the view are skipped.
+    fn watch_subdirs(&mut self, path: &Path) {
+        let walk = if path.as_os_str().is_empty() {
+            self.view.walk()
+        } else {
+            self.view.open_dir(path).and_then(|dir| dir.walk())
+        };
+        let Ok(walk) = walk else {
+            return;
+        };
+        for entry in walk.flatten() {
+            if entry.file_type().is_dir() {
+                self.watch_dir(&path.join(entry.path()));
+            }
+        }
+    }
+
+    /// Watches the directory at `path`, if it can be opened through the
+    /// view.
+    fn watch_dir(&mut self, path: &Path) {
+        if let Ok(dir) = self.view.open_dir(path) {
+            if let Ok(wd) = add_watch(&self.inotify, &dir) {
+                self.watches.insert(wd, path.to_path_buf());
+            }
+        }
+    }
+
+    /// Stops watching the directory at `path` and its subdirectories.
+    fn unwatch(&mut self, path: &Path) {
+        self.watches.retain(|wd, watched| {
+            if !watched.starts_with(path) {
+                return true;
+            }
+            // SAFETY: `inotify_rm_watch` takes no pointers.
+            unsafe { libc::inotify_rm_watch(self.inotify.as_raw_fd(), *wd) };
+            false
+        });
+    }
+
+    /// Reads a batch of events from the OS, blocking until there's at least
+    /// one.
+    fn read(&mut self) -> io::Result<()> {
+        let len = loop {
+            // SAFETY: `buf` is valid for writes of its length.
+            let len = unsafe {
+                libc::read(
+                    self.inotify.as_raw_fd(),
+                    self.buf.as_mut_ptr().cast(),
+                    self.buf.len(),
+                )
+            };
+            if len >= 0 {
+                break len as usize;
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        };
+
+        let buf = std::mem::take(&mut self.buf);
+        // A move out of a watched directory, which becomes a rename if the
+        // next event is the corresponding move into one.
+        let mut moved_from: Option<(u32, PathBuf, bool)> = None;
+        let mut offset = 0;
+        while offset + EVENT_SIZE <= len {
+            let field = |i: usize| buf[offset + i..offset + i + 4].try_into().unwrap();
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(4));
+            let cookie = u32::from_ne_bytes(field(8));
+            let name_len = u32::from_ne_bytes(field(12)) as usize;
+            let name = &buf[offset + EVENT_SIZE..offset + EVENT_SIZE + name_len];
+            let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+            offset += EVENT_SIZE + name_len;
+
+            if let Some((from_cookie, from, is_dir)) = moved_from.take() {
+                if mask & libc::IN_MOVED_TO != 0 && cookie == from_cookie {
+                    if let Some(to) = self.path(wd, name) {
+                        if is_dir {
+                            self.renamed(&from, &to);
+                        }
+                        self.emit(WatchEvent::Rename { from, to });
+                        continue;
+                    }
+                }
+                self.moved_out(from, is_dir);
+            }
+
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                self.events.push_back(Err(io::Error::other(
+                    "changes were made faster than they were read, and some were lost",
+                )));
+                continue;
+            }
+            if mask & libc::IN_IGNORED != 0 {
+                self.watches.remove(&wd);
+                continue;
+            }
+            if name.is_empty() || (self.hide_hidden && is_dot_file(name)) {
+                continue;
+            }
+            let Some(path) = self.path(wd, name) else {
+                continue;
+            };
+            let is_dir = mask & libc::IN_ISDIR != 0;
+            if mask & libc::IN_MOVED_FROM != 0 {
+                moved_from = Some((cookie, path, is_dir));
+            } else if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                if is_dir && self.recursive {
+                    self.watch_dir(&path);
+                    self.watch_subdirs(&path);
+                }
+                self.emit(WatchEvent::Create(path));
+            } else if mask & libc::IN_DELETE != 0 {
+                self.emit(WatchEvent::Remove(path));
+            } else if mask & (libc::IN_MODIFY | libc::IN_ATTRIB) != 0 {
+                self.emit(WatchEvent::Modify(path));
+            }
+        }
+        if let Some((_, from, is_dir)) = moved_from {
+            self.moved_out(from, is_dir);
+        }
+        self.buf = buf;
+        Ok(())
+    }
+
+    /// Returns the path, relative to `self.view`, of the entry `name` in the
+    /// directory watched by `wd`, or `None` if it's no longer watched.
+    fn path(&self, wd: i32, name: &[u8]) -> Option<PathBuf> {
+        Some(self.watches.get(&wd)?.join(OsStr::from_bytes(name)))
+    }
+
+    /// Updates the watched paths after the directory at `from` is renamed to
+    /// `to`.
+    fn renamed(&mut self, from: &Path, to: &Path) {
+        for watched in self.watches.values_mut() {
+            if let Ok(rest) = watched.strip_prefix(from) {
+                *watched = to.join(rest);
+            }
+        }
+    }
+
+    fn moved_out(&mut self, from: PathBuf, is_dir: bool) {
+        if is_dir {
+            self.unwatch(&from);
+        }
+        self.emit(WatchEvent::Remove(from));
+    }
+
+    fn emit(&mut self, event: WatchEvent) {
+        self.events.push_back(Ok(event.prefixed(&self.prefix)));
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = io::Result<WatchEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(event);
+            }
+            if let Err(err) = self.read() {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("prefix", &self.prefix)
+            .field("recursive", &self.recursive)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Adds a watch for `dir` to `inotify`.
+///
+/// inotify only accepts paths, so this names the directory through its
+/// entry in `/proc/self/fd`, which refers to the open directory itself
+/// rather than to any path.
+fn add_watch(inotify: &OwnedFd, dir: &DirView) -> io::Result<i32> {
+    let path = CString::new(format!("/proc/self/fd/{}", dir.dir.as_fd().as_raw_fd())).unwrap();
+    // SAFETY: `path` is a valid NUL-terminated string.
+    let wd = unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), path.as_ptr(), MASK) };
+    if wd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(wd)
+}
@@ -0,0 +1,244 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, MetadataExt, OpenOptions, OpenOptionsExt};
+use std::borrow::Borrow;
+use std::ffi::OsString;
+use std::io;
+use std::mem::size_of;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::io::AsRawHandle;
+use std::path::{Path, PathBuf};
+use std::ptr::null_mut;
+use windows_sys::Win32::Storage::FileSystem::{
+    FileAttributeTagInfo, GetFileInformationByHandleEx, GetFinalPathNameByHandleW,
+    FILE_ATTRIBUTE_REPARSE_POINT, FILE_ATTRIBUTE_TAG_INFO, FILE_FLAG_BACKUP_SEMANTICS,
+    FILE_FLAG_OPEN_REPARSE_POINT, FILE_NAME_NORMALIZED, VOLUME_NAME_DOS,
+};
+use windows_sys::Win32::System::Ioctl::FSCTL_SET_REPARSE_POINT;
+use windows_sys::Win32::System::SystemServices::IO_REPARSE_TAG_MOUNT_POINT;
+use windows_sys::Win32::System::IO::DeviceIoControl;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the reparse tag of the entry at `path`, such as
+    /// `IO_REPARSE_TAG_MOUNT_POINT` for a junction or
+    /// `IO_REPARSE_TAG_SYMLINK` for a symbolic link, or `None` if it isn't
+    /// a reparse point.
+    ///
+    /// A reparse point in the last component of `path` isn't followed.
+    pub fn reparse_tag<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<u32>> {
+        self.audited(Operation::Metadata, path, |path| {
+            reparse_tag(self.dir.borrow(), path)
+        })
+    }
+
+    /// Returns `true` if the entry at `path` is a directory junction.
+    #[inline]
+    pub fn is_junction<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        Ok(self.reparse_tag(path)? == Some(IO_REPARSE_TAG_MOUNT_POINT))
+    }
+
+    /// Creates a new directory junction at `link` pointing to the directory
+    /// at `target`.
+    ///
+    /// `target` must be a directory within `self`. Junctions always record
+    /// absolute paths, so the junction refers to the directory's current
+    /// location on the host, and views, which treat it like a symbolic link
+    /// to an absolute path, don't follow it. It's for other programs, which
+    /// see it as an ordinary directory. Creating junctions is denied where
+    /// creating symbolic links is.
+    pub fn create_junction<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        target: P,
+        link: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Symlink, link, target, |link, target| {
+            self.policy.check_symlink(link)?;
+            if self.dry_run(Operation::Symlink, link, Some(target.as_ref()))? {
+                return Ok(());
+            }
+            create_junction(self.dir.borrow(), target.as_ref(), link)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns the reparse tag of the entry at `path`, or `None` if it
+    /// isn't a reparse point.
+    ///
+    /// See [`DirView::reparse_tag`].
+    pub fn reparse_tag<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<u32>> {
+        self.audited(Operation::Metadata, path, |path| {
+            reparse_tag(self.dir.borrow().as_cap_std(), path.as_std_path())
+        })
+    }
+
+    /// Returns `true` if the entry at `path` is a directory junction.
+    #[inline]
+    pub fn is_junction<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<bool> {
+        Ok(self.reparse_tag(path)? == Some(IO_REPARSE_TAG_MOUNT_POINT))
+    }
+
+    /// Creates a new directory junction at `link` pointing to the directory
+    /// at `target`.
+    ///
+    /// See [`DirView::create_junction`].
+    pub fn create_junction<P: AsRef<Utf8Path>, Q: AsRef<Utf8Path>>(
+        &self,
+        target: P,
+        link: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Symlink, link, target, |link, target| {
+            self.policy.check_symlink(link.as_std_path())?;
+            if self.dry_run(Operation::Symlink, link, Some(target.as_ref()))? {
+                return Ok(());
+            }
+            create_junction(
+                self.dir.borrow().as_cap_std(),
+                target.as_ref().as_std_path(),
+                link.as_std_path(),
+            )
+        })
+    }
+}
+
+/// Test whether `metadata` is that of a reparse point.
+pub(crate) fn is_reparse_point(metadata: &cap_std::fs::Metadata) -> bool {
+    metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+}
+
+fn reparse_tag(dir: &Dir, path: &Path) -> io::Result<Option<u32>> {
+    if !is_reparse_point(&dir.symlink_metadata(path)?) {
+        return Ok(None);
+    }
+    let file = open_reparse_point(dir, path, false)?;
+    let mut info = FILE_ATTRIBUTE_TAG_INFO {
+        FileAttributes: 0,
+        ReparseTag: 0,
+    };
+    // SAFETY: `info` is a `FILE_ATTRIBUTE_TAG_INFO` of the given size.
+    let ok = unsafe {
+        GetFileInformationByHandleEx(
+            file.as_raw_handle(),
+            FileAttributeTagInfo,
+            (&mut info as *mut FILE_ATTRIBUTE_TAG_INFO).cast(),
+            size_of::<FILE_ATTRIBUTE_TAG_INFO>() as u32,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Some(info.ReparseTag))
+}
+
+fn create_junction(dir: &Dir, target: &Path, link: &Path) -> io::Result<()> {
+    // Opening the target through `dir` checks that it's a directory within
+    // it.
+    let target = final_path(&dir.open_dir(target)?)?;
+    dir.create_dir(link)?;
+    let result =
+        open_reparse_point(dir, link, true).and_then(|file| set_mount_point(&file, &target));
+    if result.is_err() {
+        dir.remove_dir(link).ok();
+    }
+    result
+}
+
+/// Opens the reparse point at `path` itself, rather than what it points
+/// to.
+fn open_reparse_point(dir: &Dir, path: &Path, write: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options
+        .read(true)
+        .write(write)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS);
+    dir.open_with(path, &options)
+}
+
+/// Returns the absolute path of `dir`, in the `\\?\` form.
+fn final_path(dir: &Dir) -> io::Result<PathBuf> {
+    let mut buf = vec![0_u16; 260];
+    loop {
+        // SAFETY: `buf` has room for `buf.len()` UTF-16 units.
+        let len = unsafe {
+            GetFinalPathNameByHandleW(
+                dir.as_raw_handle(),
+                buf.as_mut_ptr(),
+                buf.len() as u32,
+                FILE_NAME_NORMALIZED | VOLUME_NAME_DOS,
+            )
+        } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // If the buffer is too small, the length returned includes the
+        // terminating NUL.
+        if len < buf.len() {
+            buf.truncate(len);
+            return Ok(PathBuf::from(OsString::from_wide(&buf)));
+        }
+        buf.resize(len, 0);
+    }
+}
+
+/// Makes the empty directory opened as `file` a junction to `target`, an
+/// absolute path in the `\\?\` form.
+fn set_mount_point(file: &File, target: &Path) -> io::Result<()> {
+    let target = target.as_os_str().encode_wide().collect::<Vec<u16>>();
+    // The substitute name is an NT path, such as `\??\C:\dir`, and the
+    // print name is the path users see, such as `C:\dir`.
+    let print_name = match target.strip_prefix(&[92, 92, 63, 92][..]) {
+        Some(rest) => rest,
+        None => &target,
+    };
+    let substitute_name = [92, 63, 63, 92]
+        .iter()
+        .chain(print_name)
+        .copied()
+        .collect::<Vec<u16>>();
+
+    let substitute_len = substitute_name.len() * 2;
+    let print_len = print_name.len() * 2;
+    // The names, each NUL-terminated, follow the four offsets and lengths.
+    let data_len = 8 + substitute_len + 2 + print_len + 2;
+    let data_len = u16::try_from(data_len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "junction target too long"))?;
+    let mut buffer = Vec::with_capacity(8 + usize::from(data_len));
+    buffer.extend_from_slice(&IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    buffer.extend_from_slice(&data_len.to_le_bytes());
+    buffer.extend_from_slice(&0_u16.to_le_bytes());
+    for field in [0, substitute_len, substitute_len + 2, print_len] {
+        buffer.extend_from_slice(&(field as u16).to_le_bytes());
+    }
+    for unit in substitute_name
+        .iter()
+        .chain(&[0])
+        .chain(print_name)
+        .chain(&[0])
+    {
+        buffer.extend_from_slice(&unit.to_le_bytes());
+    }
+
+    let mut returned = 0;
+    // SAFETY: `buffer` holds a complete `REPARSE_DATA_BUFFER`, and no output
+    // is requested.
+    let ok = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle(),
+            FSCTL_SET_REPARSE_POINT,
+            buffer.as_ptr().cast(),
+            buffer.len() as u32,
+            null_mut(),
+            0,
+            &mut returned,
+            null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, ViewKind};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+
+const CREATE_RULESET_VERSION: u32 = 1 << 0;
+const RULE_PATH_BENEATH: u32 = 1;
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+const ACCESS_FS_REFER: u64 = 1 << 13;
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+/// The accesses known to Landlock ABI version 1.
+const ACCESS_FS_V1: u64 = (1 << 13) - 1;
+
+/// The accesses a readonly view is granted.
+const ACCESS_FS_READONLY: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// A Landlock ruleset which restricts the calling thread's filesystem
+/// access to the trees of a set of views.
+///
+/// Once [`Self::restrict_self`] is called, the calling thread, and any
+/// threads and processes it creates afterward, can only access files
+/// beneath the added views, with the rights implied by their
+/// [`ViewKind`]s. This has the OS enforce what the views promise, for
+/// defense in depth.
+///
+/// Only the view kind is enforced this way; other restrictions set with
+/// [`DirViewBuilder`] remain enforced by the views alone.
+///
+/// [`DirViewBuilder`]: crate::DirViewBuilder
+#[derive(Debug)]
+pub struct LandlockRuleset {
+    ruleset: OwnedFd,
+    handled: u64,
+}
+
+impl LandlockRuleset {
+    /// Creates a new empty ruleset.
+    ///
+    /// This fails with [`io::ErrorKind::Unsupported`] if the kernel doesn't
+    /// support Landlock, or has it disabled.
+    pub fn new() -> io::Result<Self> {
+        // SAFETY: Querying the ABI version takes no pointers.
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0_usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 0 {
+            let err = io::Error::last_os_error();
+            return Err(match err.raw_os_error() {
+                Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP) => io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Landlock is not supported by this kernel",
+                ),
+                _ => err,
+            });
+        }
+
+        let mut handled = ACCESS_FS_V1;
+        if abi >= 2 {
+            handled |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            handled |= ACCESS_FS_TRUNCATE;
+        }
+
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        // SAFETY: `attr` is a valid `landlock_ruleset_attr` of the given size.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0_u32,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: On success, `landlock_create_ruleset` returns a new fd.
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+        Ok(Self { ruleset, handled })
+    }
+
+    /// Allows access to the tree of `view`, with the rights implied by its
+    /// view kind.
+    pub fn add<D: Borrow<Dir>>(&mut self, view: &DirView<D>) -> io::Result<&mut Self> {
+        self.add_rule(view.dir.borrow().as_fd(), view.view_kind)
+    }
+
+    /// Allows access to the tree of `view`, with the rights implied by its
+    /// view kind.
+    #[cfg(feature = "fs_utf8")]
+    pub fn add_utf8<D: Borrow<cap_std::fs_utf8::Dir>>(
+        &mut self,
+        view: &DirViewUtf8<D>,
+    ) -> io::Result<&mut Self> {
+        self.add_rule(view.dir.borrow().as_fd(), view.view_kind)
+    }
+
+    /// Restricts the calling thread to the added views.
+    ///
+    /// This also sets the thread's `no_new_privs` attribute, which Landlock
+    /// requires. The restriction can't be lifted.
+    pub fn restrict_self(self) -> io::Result<()> {
+        // SAFETY: `PR_SET_NO_NEW_PRIVS` takes no pointers.
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `self.ruleset` is a valid Landlock ruleset fd.
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_restrict_self,
+                self.ruleset.as_raw_fd(),
+                0_u32,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn add_rule(&mut self, dir: BorrowedFd<'_>, view_kind: ViewKind) -> io::Result<&mut Self> {
+        let allowed_access = match view_kind {
+            ViewKind::Full => self.handled,
+            ViewKind::Readonly | ViewKind::DryRun => ACCESS_FS_READONLY,
+        };
+        let attr = PathBeneathAttr {
+            allowed_access,
+            parent_fd: dir.as_raw_fd(),
+        };
+        // SAFETY: `attr` is a valid `landlock_path_beneath_attr`, and
+        // `self.ruleset` is a valid Landlock ruleset fd.
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                self.ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0_u32,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(self)
+    }
+}
@@ -1,31 +1,263 @@
 #![cfg_attr(doc_cfg, feature(doc_cfg, doc_auto_cfg))]
 
+mod advise;
+mod allocate;
+mod archive;
+mod atomic_file;
+mod audit;
+mod backend;
+mod budget;
+mod buffered;
+mod builder;
+mod cache;
+#[cfg(all(feature = "capsicum", target_os = "freebsd"))]
+mod capsicum;
+mod cas;
+#[cfg(any(feature = "json", feature = "toml"))]
+mod config;
+mod contains;
+mod coordinate;
+mod copy_dir;
+mod date;
+mod decompress;
+mod dedupe;
+mod diff;
 mod dir;
+#[cfg(feature = "async-std")]
+mod dir_async;
 mod dir_entry;
 #[cfg(feature = "fs_utf8")]
 mod dir_entry_utf8;
+mod dir_like;
+#[cfg(feature = "tokio")]
+mod dir_tokio;
 #[cfg(feature = "fs_utf8")]
 mod dir_utf8;
+#[cfg(feature = "directories")]
+mod directories;
+mod disk_usage;
+mod dry_run;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod entry_kind;
+mod error;
+mod exchange;
+#[cfg(all(unix, feature = "fd-passing"))]
+mod fd_passing;
+mod file_flags;
+mod find;
+mod fsync;
+mod glob;
+mod hash_tree;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "ignore")]
+mod ignore;
+mod inflate;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+mod inotify;
+#[cfg(windows)]
+mod junction;
+#[cfg(all(feature = "landlock", target_os = "linux"))]
+mod landlock;
+mod limits;
+mod lock;
+mod metadata_batch;
+mod metadata_ext;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod mount;
+mod move_entry;
+mod named_tempfile;
+#[cfg(all(feature = "9p", unix))]
+mod ninep;
+#[cfg(all(unix, not(target_vendor = "apple")))]
+mod node;
+#[cfg(feature = "unicode-normalization")]
+mod normalize;
+mod operation;
+mod overlay;
+mod overrides;
+#[cfg(unix)]
+mod owner;
+mod policy;
+mod portable;
+#[cfg(target_os = "wasi")]
+mod preopens;
+mod progress;
+mod raw_dir;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+mod raw_watch;
 mod read_dir;
 #[cfg(feature = "fs_utf8")]
 mod read_dir_utf8;
+mod read_many;
+mod read_range;
+mod readonly_file;
+mod reflink;
+mod rename_noreplace;
+mod resolve;
+mod same_dir;
+#[cfg(feature = "tempfile")]
+mod sandbox;
+mod sanitize;
+mod sha256;
+mod shared;
+mod snapshot;
+mod sparse;
+mod spawn;
+#[cfg(feature = "serde")]
+mod spec;
+#[cfg(all(unix, not(any(target_os = "haiku", target_os = "redox"))))]
+mod statfs;
+mod stats;
+#[cfg(feature = "stream")]
+mod stream;
+mod sync;
+mod tar;
+#[cfg(feature = "tempfile")]
+mod tempfile;
+#[cfg(feature = "testing")]
+mod testing;
+mod throttle;
+#[cfg(unix)]
+mod tmpfile;
+mod transaction;
+mod trash;
+mod tree_stats;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+mod verified;
+mod walk;
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+mod walk_async;
+mod walk_parallel;
+#[cfg(feature = "fs_utf8")]
+mod walk_utf8;
+#[cfg(feature = "wasi-host")]
+mod wasi_host;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(all(
+    feature = "xattr",
+    any(target_os = "linux", target_os = "android", target_vendor = "apple")
+))]
+mod xattr;
+mod zip;
+mod zip_view;
 
+#[cfg(feature = "async-std")]
+pub use async_std;
 #[cfg(feature = "cap-fs-ext")]
 pub use cap_fs_ext;
+#[cfg(feature = "cap-fs-ext")]
+pub use cap_primitives::fs::AccessModes;
 pub use cap_std::{self, ambient_authority, AmbientAuthority};
+#[cfg(feature = "stream")]
+pub use futures_core;
+#[cfg(feature = "mmap")]
+pub use memmap2;
+#[cfg(feature = "tokio")]
+pub use tokio;
+#[cfg(feature = "wasi-host")]
+pub use wasi_common;
 
-pub use dir::DirView;
+pub use advise::Advice;
+pub use archive::{ArchiveDirEntry, ArchiveDirView, ArchiveFile, ArchiveMetadata, ArchiveReadDir};
+pub use atomic_file::AtomicFile;
+pub use audit::AuditEvent;
+pub use budget::{Budgeted, HandleBudget};
+pub use builder::DirViewBuilder;
+pub use cache::CacheConfig;
+pub use cas::ContentHash;
+pub use coordinate::{Coordinator, IntentGuard};
+pub use copy_dir::{CopyDirOptions, CopyOptions, CopySymlinks};
+pub use decompress::Decompressed;
+pub use dedupe::{DedupeOptions, DedupeReport};
+pub use diff::{diff, DiffOptions, DiffReport};
+pub use dir::{DirView, DirViewRef};
+#[cfg(feature = "async-std")]
+pub use dir_async::{DirViewAsync, ReadDirViewAsync};
 pub use dir_entry::DirEntryView;
 #[cfg(feature = "fs_utf8")]
 pub use dir_entry_utf8::DirEntryViewUtf8;
+pub use dir_like::DirLike;
+#[cfg(feature = "tokio")]
+pub use dir_tokio::{TokioDirView, TokioReadDirView};
+#[cfg(feature = "fs_utf8")]
+pub use dir_utf8::{DirViewRefUtf8, DirViewUtf8};
+pub use disk_usage::{DiskUsage, DiskUsageOptions};
+pub use dry_run::DryRunChange;
+#[cfg(feature = "encryption")]
+pub use encrypted::{Cipher, EncryptedDirEntry, EncryptedDirView, EncryptedReadDir};
+pub use entry_kind::FileTypeSummary;
+pub use error::{PathError, PathErrorKind, ViewError};
+pub use file_flags::FileFlags;
+pub use find::FindOptions;
+pub use glob::Glob;
+pub use hash_tree::{HashTreeOptions, ManifestEntry, TreeHash, TreeManifest};
+#[cfg(feature = "http")]
+pub use http::{HttpRequest, HttpResponse};
+#[cfg(all(feature = "watch", target_os = "linux"))]
+pub use inotify::EventStream;
+#[cfg(all(feature = "landlock", target_os = "linux"))]
+pub use landlock::LandlockRuleset;
+pub use lock::FileLock;
+pub use metadata_ext::{ExtendedMetadata, FieldMask};
+pub use mount::{MountDirEntry, MountDirView, MountReadDir};
+pub use named_tempfile::NamedTempFile;
+#[cfg(feature = "unicode-normalization")]
+pub use normalize::NormalizationForm;
+pub use operation::Operation;
+pub use overlay::{OverlayDirEntry, OverlayDirView, OverlayReadDir};
+pub use progress::{CancellationToken, Cancelled, Progress};
+pub use read_dir::{ReadDirView, ReadDirWithMetadata};
+#[cfg(feature = "fs_utf8")]
+pub use read_dir_utf8::{ReadDirViewUtf8, ReadDirWithMetadataUtf8};
+pub use readonly_file::ReadOnlyFile;
+#[cfg(feature = "tempfile")]
+pub use sandbox::{Change, SandboxView};
+pub use sanitize::sanitize_path;
+pub use sha256::{Sha256, Sha256Hasher};
+pub use shared::SharedDirView;
 #[cfg(feature = "fs_utf8")]
-pub use dir_utf8::DirViewUtf8;
-pub use read_dir::ReadDirView;
+pub use shared::SharedDirViewUtf8;
+pub use snapshot::{SnapshotDiff, SnapshotEntry, SnapshotManifest, SnapshotOptions};
+pub use sparse::FileExtent;
+#[cfg(feature = "serde")]
+pub use spec::{open_views, ViewOptions, ViewSpec};
+#[cfg(all(unix, not(any(target_os = "haiku", target_os = "redox"))))]
+pub use statfs::FsStats;
+pub use stats::ViewStats;
+pub use sync::{SyncCompare, SyncOptions, SyncReport};
+pub use tar::TarOptions;
+#[cfg(feature = "tempfile")]
+pub use tempfile::TempDirView;
+#[cfg(feature = "testing")]
+pub use testing::{MemDirEntry, MemDirView, MemFile, MemMetadata, MemReadDir};
+pub use throttle::{RateLimiter, Throttled};
+pub use transaction::Transaction;
+pub use tree_stats::{TreeStats, TreeStatsOptions};
+pub use verified::VerifiedDirView;
+pub use walk::{Walk, WalkEntry};
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+pub use walk_async::WalkStream;
+pub use walk_parallel::{ParallelWalk, WalkState};
 #[cfg(feature = "fs_utf8")]
-pub use read_dir_utf8::ReadDirViewUtf8;
+pub use walk_utf8::{WalkEntryUtf8, WalkUtf8};
+#[cfg(feature = "watch")]
+pub use watch::{PollChanges, WatchEvent, WatchOptions};
+pub use zip::{ZipCompression, ZipOptions};
+pub use zip_view::ZipDirView;
 
 /// The kind of a view.
+///
+/// More kinds may be added, so code outside this crate matching on a
+/// `ViewKind` needs a wildcard arm.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[non_exhaustive]
 pub enum ViewKind {
     /// Expose everything. The same as `cap_std::fs::Dir` itself.
     Full,
@@ -33,5 +265,80 @@ pub enum ViewKind {
     /// Expose a readonly view. Creating, renaming, or deleting new files or
     /// directories is not permitted, and files can only be opened in readonly
     /// mode.
+    ///
+    /// This is enforced by the view, not by the OS. Directory handles carry
+    /// no access rights of their own on Unix or Windows, so readonly views
+    /// don't expose their handles to code in the process. A readonly view
+    /// sent to another process with `DirView::send_to_unix_socket` sends
+    /// its handle, and is only readonly if the receiver honors it.
     Readonly,
+
+    /// Expose a view which reads like a full view, but records changes
+    /// instead of making them.
+    ///
+    /// Operations which would change the tree, such as creating directories,
+    /// removing files, or writing whole files with `write`, check their
+    /// arguments, record a [`DryRunChange`], and succeed without touching the
+    /// filesystem. The changes can be inspected with
+    /// [`DirView::dry_run_changes`].
+    ///
+    /// Operations which would return a writable handle, such as `create` or
+    /// `tempfile_in`, can't be simulated, and are denied as on a readonly
+    /// view.
+    DryRun,
+}
+
+impl ViewKind {
+    /// Returns `view_kind` if a view of kind `self` may derive a view of kind
+    /// `view_kind`, or `None` if `view_kind` is broader than `self`.
+    pub(crate) fn narrow(self, view_kind: Self) -> Option<Self> {
+        match (self, view_kind) {
+            (Self::Full, _)
+            | (Self::DryRun, Self::DryRun | Self::Readonly)
+            | (Self::Readonly, Self::Readonly) => Some(view_kind),
+            (Self::DryRun, Self::Full) | (Self::Readonly, Self::Full | Self::DryRun) => None,
+        }
+    }
+
+    /// Returns the narrower of `self` and `other`.
+    pub(crate) fn meet(self, other: Self) -> Self {
+        match self.narrow(other) {
+            Some(view_kind) => view_kind,
+            None => self,
+        }
+    }
+
+    /// Returns `options` with anything a view of kind `self` may not use
+    /// cleared. Full views use `options` as is, without copying them.
+    ///
+    /// Platform-specific options can't be inspected, so on readonly and
+    /// dry-run views they're reset: on Unix the custom flags, which could
+    /// otherwise pass `O_CREAT`, `O_TRUNC`, or `O_APPEND` through, and on
+    /// Windows the access mode.
+    #[inline]
+    pub(crate) fn open_options(
+        self,
+        options: &cap_std::fs::OpenOptions,
+    ) -> std::borrow::Cow<'_, cap_std::fs::OpenOptions> {
+        if self == Self::Full {
+            return std::borrow::Cow::Borrowed(options);
+        }
+
+        // Override any flag that allows writing.
+        let mut options = options.clone();
+        options.append(false);
+        options.truncate(false);
+        options.write(false);
+        options.create(false);
+        options.create_new(false);
+
+        #[cfg(unix)]
+        cap_std::fs::OpenOptionsExt::custom_flags(&mut options, 0);
+
+        // `FILE_GENERIC_READ`.
+        #[cfg(windows)]
+        cap_std::fs::OpenOptionsExt::access_mode(&mut options, 0x0012_0089);
+
+        std::borrow::Cow::Owned(options)
+    }
 }
@@ -0,0 +1,138 @@
+use crate::backend::Backend;
+use crate::policy::Policy;
+use crate::{FileTypeSummary, Operation, ViewError};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+impl Policy {
+    /// Check `path` against the restrictions which don't need the
+    /// filesystem, before resolving it.
+    pub(crate) fn check_lexical(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.check_strict(operation, path)?;
+        self.check_path_limits(operation, path)
+    }
+
+    /// Check whether `path` is within the view's limits on the length and
+    /// number of components of paths.
+    pub(crate) fn check_path_limits(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        if self
+            .max_path_len
+            .is_some_and(|max| path.as_os_str().len() > max)
+        {
+            return Err(self.limit_exceeded(
+                io::ErrorKind::InvalidFilename,
+                "attempt to use a path longer than the view permits",
+                operation,
+                path,
+            ));
+        }
+        if self
+            .max_path_components
+            .is_some_and(|max| path.components().count() > max)
+        {
+            return Err(self.limit_exceeded(
+                io::ErrorKind::InvalidFilename,
+                "attempt to use a path with more components than the view permits",
+                operation,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check whether resolving `path` in `dir` traverses no more symbolic
+    /// links than the view permits.
+    pub(crate) fn check_symlink_limit<B: Backend + ?Sized>(
+        &self,
+        dir: &B,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<()> {
+        let Some(max) = self.max_symlink_traversals else {
+            return Ok(());
+        };
+        if count_symlinks(dir, path, max)? > max {
+            return Err(self.limit_exceeded(
+                loop_kind(),
+                "attempt to resolve a path through more symbolic links than the view permits",
+                operation,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    fn limit_exceeded(
+        &self,
+        kind: io::ErrorKind,
+        message: &'static str,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Error {
+        ViewError::limit_exceeded(kind, message, operation, path, self.denial_context).into()
+    }
+}
+
+/// Returns the number of symbolic links traversed in resolving `path`
+/// relative to `dir`, including one in its last component, stopping once
+/// the count exceeds `max`.
+///
+/// Resolution stops early at anything which doesn't exist, or which
+/// resolution would fail at anyway, such as absolute link targets.
+fn count_symlinks<B: Backend + ?Sized>(dir: &B, path: &Path, max: usize) -> io::Result<usize> {
+    let mut count = 0;
+    let mut resolved = PathBuf::new();
+    let mut pending = components(path);
+    while let Some(component) = pending.pop() {
+        match Path::new(&component).components().next() {
+            None | Some(Component::CurDir) => {}
+            Some(Component::ParentDir) => {
+                if !resolved.pop() {
+                    break;
+                }
+            }
+            Some(Component::Normal(name)) => {
+                let next = resolved.join(name);
+                match dir.entry_type(&next) {
+                    Ok(FileTypeSummary::Symlink) => {
+                        count += 1;
+                        if count > max {
+                            break;
+                        }
+                        let target = dir.read_link(&next)?;
+                        if target.has_root() {
+                            break;
+                        }
+                        pending.extend(components(&target));
+                    }
+                    Ok(_) => resolved = next,
+                    Err(_) => break,
+                }
+            }
+            Some(Component::RootDir | Component::Prefix(_)) => break,
+        }
+    }
+    Ok(count)
+}
+
+/// Returns the components of `path`, last first, so that they can be popped
+/// in order.
+fn components(path: &Path) -> Vec<OsString> {
+    path.components()
+        .rev()
+        .map(|component| component.as_os_str().to_owned())
+        .collect()
+}
+
+/// Returns the kind of the errors the platform reports when resolution
+/// traverses too many symbolic links.
+fn loop_kind() -> io::ErrorKind {
+    #[cfg(any(unix, target_os = "wasi"))]
+    return io::Error::from(rustix::io::Errno::LOOP).kind();
+    #[cfg(windows)]
+    return io::Error::from_raw_os_error(
+        windows_sys::Win32::Foundation::ERROR_CANT_RESOLVE_FILENAME as i32,
+    )
+    .kind();
+}
@@ -0,0 +1,177 @@
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, OpenOptions};
+use cap_std::io_lifetimes::AsFilelike;
+use std::borrow::Borrow;
+use std::path::Path;
+use std::{fmt, io};
+
+/// An advisory lock on a file, which is released when dropped.
+///
+/// This is returned by [`DirView::lock_exclusive`], [`DirView::lock_shared`],
+/// and their `try_` variants.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Returns the locked file.
+    #[inline]
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Releases the lock, reporting any errors.
+    ///
+    /// Dropping `self` also releases the lock, but ignores errors.
+    pub fn unlock(self) -> io::Result<()> {
+        self.file.as_filelike_view::<std::fs::File>().unlock()
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.as_filelike_view::<std::fs::File>().unlock();
+    }
+}
+
+impl fmt::Debug for FileLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileLock")
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Opens the file at `path` for reading and writing, creating it if it
+    /// doesn't exist, and takes an exclusive advisory lock on it, blocking
+    /// until the lock is available.
+    ///
+    /// This is denied on readonly views.
+    ///
+    /// This corresponds to [`std::fs::File::lock`], but only accesses paths
+    /// relative to `self`.
+    pub fn lock_exclusive<P: AsRef<Path>>(&self, path: P) -> io::Result<FileLock> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            lock(self.dir.borrow().open_with(path, &exclusive_options())?)
+        })
+    }
+
+    /// Opens the file at `path` for reading and takes a shared advisory lock
+    /// on it, blocking until the lock is available.
+    ///
+    /// This corresponds to [`std::fs::File::lock_shared`], but only accesses
+    /// paths relative to `self`.
+    pub fn lock_shared<P: AsRef<Path>>(&self, path: P) -> io::Result<FileLock> {
+        self.audited(Operation::Lock, path, |path| {
+            lock_shared(self.dir.borrow().open(path)?)
+        })
+    }
+
+    /// Like [`Self::lock_exclusive`], but returns `None` instead of blocking
+    /// if the lock is held elsewhere.
+    pub fn try_lock_exclusive<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileLock>> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            try_lock(self.dir.borrow().open_with(path, &exclusive_options())?)
+        })
+    }
+
+    /// Like [`Self::lock_shared`], but returns `None` instead of blocking if
+    /// an exclusive lock is held elsewhere.
+    pub fn try_lock_shared<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileLock>> {
+        self.audited(Operation::Lock, path, |path| {
+            try_lock_shared(self.dir.borrow().open(path)?)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Opens the file at `path` for reading and writing, creating it if it
+    /// doesn't exist, and takes an exclusive advisory lock on it, blocking
+    /// until the lock is available.
+    ///
+    /// This is denied on readonly views.
+    ///
+    /// This corresponds to [`std::fs::File::lock`], but only accesses paths
+    /// relative to `self`.
+    pub fn lock_exclusive<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<FileLock> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            lock(self.cap_std_dir().open_with(path, &exclusive_options())?)
+        })
+    }
+
+    /// Opens the file at `path` for reading and takes a shared advisory lock
+    /// on it, blocking until the lock is available.
+    ///
+    /// This corresponds to [`std::fs::File::lock_shared`], but only accesses
+    /// paths relative to `self`.
+    pub fn lock_shared<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<FileLock> {
+        self.audited(Operation::Lock, path, |path| {
+            lock_shared(self.cap_std_dir().open(path)?)
+        })
+    }
+
+    /// Like [`Self::lock_exclusive`], but returns `None` instead of blocking
+    /// if the lock is held elsewhere.
+    pub fn try_lock_exclusive<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<FileLock>> {
+        self.audited(Operation::Lock, path, |path| {
+            self.check_mutation(Operation::Lock, path)?;
+            try_lock(self.cap_std_dir().open_with(path, &exclusive_options())?)
+        })
+    }
+
+    /// Like [`Self::lock_shared`], but returns `None` instead of blocking if
+    /// an exclusive lock is held elsewhere.
+    pub fn try_lock_shared<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<FileLock>> {
+        self.audited(Operation::Lock, path, |path| {
+            try_lock_shared(self.cap_std_dir().open(path)?)
+        })
+    }
+
+    fn cap_std_dir(&self) -> &Dir {
+        self.dir.borrow().as_cap_std()
+    }
+}
+
+fn exclusive_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true);
+    options
+}
+
+fn lock(file: File) -> io::Result<FileLock> {
+    file.as_filelike_view::<std::fs::File>().lock()?;
+    Ok(FileLock { file })
+}
+
+fn lock_shared(file: File) -> io::Result<FileLock> {
+    file.as_filelike_view::<std::fs::File>().lock_shared()?;
+    Ok(FileLock { file })
+}
+
+fn try_lock(file: File) -> io::Result<Option<FileLock>> {
+    let result = file.as_filelike_view::<std::fs::File>().try_lock();
+    locked(file, result)
+}
+
+fn try_lock_shared(file: File) -> io::Result<Option<FileLock>> {
+    let result = file.as_filelike_view::<std::fs::File>().try_lock_shared();
+    locked(file, result)
+}
+
+fn locked(file: File, result: Result<(), std::fs::TryLockError>) -> io::Result<Option<FileLock>> {
+    match result {
+        Ok(()) => Ok(Some(FileLock { file })),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(err)) => Err(err),
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Queries the metadata of each of the files at `paths`, following
+    /// symbolic links, returning the metadata of each, or the error querying
+    /// it, in order.
+    ///
+    /// Each file is queried as by [`Self::metadata`], with its own system
+    /// call, and reported to the audit hook separately. Rather than
+    /// resolving each path from the view's directory, this opens the
+    /// directory containing a file once, and looks up the files next to it
+    /// in `paths` by name in that directory, so it's fastest when the files
+    /// in each directory are next to each other in `paths`, as they are
+    /// when `paths` is sorted. Files which are symbolic links are resolved
+    /// from the view's directory.
+    pub fn metadata_batch<I, P>(&self, paths: I) -> Vec<io::Result<Metadata>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut parent = None;
+        paths
+            .into_iter()
+            .map(|path| {
+                self.audited(Operation::Metadata, path, |path| {
+                    self.cached_metadata(path, true, || {
+                        lookup(self.dir.borrow(), &mut parent, path)
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Queries the metadata of each of the files at `paths`, following
+    /// symbolic links, returning the metadata of each, or the error querying
+    /// it, in order.
+    ///
+    /// See [`DirView::metadata_batch`].
+    pub fn metadata_batch<I, P>(&self, paths: I) -> Vec<io::Result<Metadata>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Utf8Path>,
+    {
+        let mut parent = None;
+        paths
+            .into_iter()
+            .map(|path| {
+                self.audited(Operation::Metadata, path, |path| {
+                    self.cached_metadata(path.as_std_path(), true, || {
+                        let dir = self.dir.borrow().as_cap_std();
+                        lookup(dir, &mut parent, path.as_std_path())
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// The directory containing the previous file looked up, and a handle to it,
+/// or `None` if it couldn't be opened.
+type Parent = Option<(PathBuf, Option<Dir>)>;
+
+/// Queries the metadata of the file at `path` in `dir`, through `parent` if
+/// the file is in the same directory as the previous one.
+fn lookup(dir: &Dir, parent: &mut Parent, path: &Path) -> io::Result<Metadata> {
+    let Some((parent_path, name)) = split(path) else {
+        return dir.metadata(path);
+    };
+    let metadata = if parent_path.as_os_str().is_empty() {
+        dir.symlink_metadata(name)
+    } else {
+        if parent
+            .as_ref()
+            .is_none_or(|(previous, _)| previous != parent_path)
+        {
+            *parent = Some((parent_path.to_owned(), dir.open_dir(parent_path).ok()));
+        }
+        match parent {
+            Some((_, Some(parent))) => parent.symlink_metadata(name),
+            _ => return dir.metadata(path),
+        }
+    };
+    // Symbolic links, and errors, which may be reported differently when
+    // resolving the whole path, are left to the usual lookup.
+    match metadata {
+        Ok(metadata) if !metadata.is_symlink() => Ok(metadata),
+        _ => dir.metadata(path),
+    }
+}
+
+/// Splits `path` into the path of its parent directory and its file name,
+/// if its last component is a plain name, without a trailing `/` or `/.`,
+/// which would make the lookup require a directory.
+fn split(path: &Path) -> Option<(&Path, &OsStr)> {
+    let Some(Component::Normal(name)) = path.components().next_back() else {
+        return None;
+    };
+    if !path
+        .as_os_str()
+        .as_encoded_bytes()
+        .ends_with(name.as_encoded_bytes())
+    {
+        return None;
+    }
+    Some((path.parent()?, name))
+}
@@ -0,0 +1,456 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, FileTypeSummary, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::io;
+use std::ops::{BitOr, BitOrAssign};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// A set of fields to request from [`DirView::metadata_ext`].
+///
+/// Masks are combined with `|`, as in `FieldMask::SIZE | FieldMask::MTIME`.
+/// The values match those of the `STATX_*` constants on Linux.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct FieldMask(u32);
+
+impl FieldMask {
+    /// The type of the entry.
+    pub const TYPE: Self = Self(0x1);
+    /// The permission bits.
+    pub const MODE: Self = Self(0x2);
+    /// The number of hard links.
+    pub const NLINK: Self = Self(0x4);
+    /// The owning user.
+    pub const UID: Self = Self(0x8);
+    /// The owning group.
+    pub const GID: Self = Self(0x10);
+    /// The time of last access.
+    pub const ATIME: Self = Self(0x20);
+    /// The time of last modification.
+    pub const MTIME: Self = Self(0x40);
+    /// The time of last status change.
+    pub const CTIME: Self = Self(0x80);
+    /// The inode number.
+    pub const INO: Self = Self(0x100);
+    /// The size.
+    pub const SIZE: Self = Self(0x200);
+    /// The number of blocks allocated.
+    pub const BLOCKS: Self = Self(0x400);
+    /// The fields reported by plain [`DirView::metadata`].
+    pub const BASIC: Self = Self(0x7ff);
+    /// The time of creation.
+    pub const BTIME: Self = Self(0x800);
+    /// The mount ID, on Linux 5.8 and later.
+    pub const MNT_ID: Self = Self(0x1000);
+    /// All of the fields.
+    pub const ALL: Self = Self(0x1fff);
+
+    /// Returns a mask with no fields, for which only the attributes and
+    /// device of the entry are reported.
+    #[inline]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns `true` if all the fields of `other` are in `self`.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the fields which are in `self` or in `other`.
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOr for FieldMask {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for FieldMask {
+    #[inline]
+    fn bitor_assign(&mut self, other: Self) {
+        *self = self.union(other);
+    }
+}
+
+/// Metadata about an entry, returned by [`DirView::metadata_ext`].
+///
+/// Each field is `None` if it wasn't requested, or if the platform or
+/// filesystem doesn't report it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExtendedMetadata {
+    file_type: Option<FileTypeSummary>,
+    mode: Option<u32>,
+    nlink: Option<u64>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    changed: Option<SystemTime>,
+    created: Option<SystemTime>,
+    ino: Option<u64>,
+    len: Option<u64>,
+    blocks: Option<u64>,
+    dev: Option<u64>,
+    mount_id: Option<u64>,
+    attributes: u64,
+    attributes_mask: u64,
+}
+
+impl ExtendedMetadata {
+    /// Returns the type of the entry.
+    #[inline]
+    pub fn file_type(&self) -> Option<FileTypeSummary> {
+        self.file_type
+    }
+
+    /// Returns the permission bits of the entry, on Unix.
+    #[inline]
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// Returns the number of hard links to the entry.
+    #[inline]
+    pub fn nlink(&self) -> Option<u64> {
+        self.nlink
+    }
+
+    /// Returns the ID of the user owning the entry.
+    #[inline]
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// Returns the ID of the group owning the entry.
+    #[inline]
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// Returns the time the entry was last accessed.
+    #[inline]
+    pub fn accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    /// Returns the time the entry was last modified.
+    #[inline]
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Returns the time the entry's metadata was last changed.
+    #[inline]
+    pub fn changed(&self) -> Option<SystemTime> {
+        self.changed
+    }
+
+    /// Returns the time the entry was created, which many filesystems
+    /// don't record.
+    #[inline]
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    /// Returns the inode number of the entry.
+    #[inline]
+    pub fn ino(&self) -> Option<u64> {
+        self.ino
+    }
+
+    /// Returns the size of the entry, in bytes.
+    #[inline]
+    pub fn len(&self) -> Option<u64> {
+        self.len
+    }
+
+    /// Returns `true` if the entry is known to have a size of 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == Some(0)
+    }
+
+    /// Returns the number of 512-byte blocks allocated to the entry.
+    #[inline]
+    pub fn blocks(&self) -> Option<u64> {
+        self.blocks
+    }
+
+    /// Returns the ID of the device containing the entry, on Unix.
+    #[inline]
+    pub fn dev(&self) -> Option<u64> {
+        self.dev
+    }
+
+    /// Returns the ID of the mount containing the entry, on Linux 5.8 and
+    /// later.
+    #[inline]
+    pub fn mount_id(&self) -> Option<u64> {
+        self.mount_id
+    }
+
+    /// Returns the `STATX_ATTR_*` attributes of the entry, on Linux.
+    ///
+    /// Only the attributes in [`Self::attributes_mask`] are meaningful.
+    #[inline]
+    pub fn attributes(&self) -> u64 {
+        self.attributes
+    }
+
+    /// Returns the `STATX_ATTR_*` attributes which the filesystem supports.
+    #[inline]
+    pub fn attributes_mask(&self) -> u64 {
+        self.attributes_mask
+    }
+
+    /// Returns whether the entry is immutable, if the filesystem supports
+    /// that attribute.
+    #[inline]
+    pub fn is_immutable(&self) -> Option<bool> {
+        self.attribute(0x10)
+    }
+
+    /// Returns whether the entry can only be appended to, if the
+    /// filesystem supports that attribute.
+    #[inline]
+    pub fn is_append_only(&self) -> Option<bool> {
+        self.attribute(0x20)
+    }
+
+    /// Returns whether the entry is the root of a mount, if the platform
+    /// reports that.
+    #[inline]
+    pub fn is_mount_root(&self) -> Option<bool> {
+        self.attribute(0x2000)
+    }
+
+    fn attribute(&self, attribute: u64) -> Option<bool> {
+        (self.attributes_mask & attribute != 0).then_some(self.attributes & attribute != 0)
+    }
+
+    /// Converts `metadata`, keeping only the fields in `mask`.
+    fn from_metadata(metadata: &Metadata, mask: FieldMask) -> Self {
+        let time = |field, time: io::Result<cap_std::time::SystemTime>| {
+            mask.contains(field)
+                .then(|| time.ok().map(|time| time.into_std()))
+                .flatten()
+        };
+        #[cfg_attr(not(any(unix, target_os = "wasi")), allow(unused_mut))]
+        let mut ext = Self {
+            file_type: field(
+                mask,
+                FieldMask::TYPE,
+                FileTypeSummary::from_file_type(metadata.file_type()),
+            ),
+            accessed: time(FieldMask::ATIME, metadata.accessed()),
+            modified: time(FieldMask::MTIME, metadata.modified()),
+            created: time(FieldMask::BTIME, metadata.created()),
+            len: field(mask, FieldMask::SIZE, metadata.len()),
+            ..Self::default()
+        };
+        #[cfg(unix)]
+        {
+            use cap_std::fs::MetadataExt;
+
+            ext.mode = field(mask, FieldMask::MODE, metadata.mode() & 0o7777);
+            ext.nlink = field(mask, FieldMask::NLINK, metadata.nlink());
+            ext.uid = field(mask, FieldMask::UID, metadata.uid());
+            ext.gid = field(mask, FieldMask::GID, metadata.gid());
+            ext.changed = mask
+                .contains(FieldMask::CTIME)
+                .then(|| timestamp(metadata.ctime(), metadata.ctime_nsec() as u32))
+                .flatten();
+            ext.ino = field(mask, FieldMask::INO, metadata.ino());
+            ext.blocks = field(mask, FieldMask::BLOCKS, metadata.blocks());
+            ext.dev = Some(metadata.dev());
+        }
+        #[cfg(target_os = "wasi")]
+        {
+            use cap_std::fs::MetadataExt;
+            use std::time::{Duration, UNIX_EPOCH};
+
+            ext.nlink = field(mask, FieldMask::NLINK, metadata.nlink());
+            ext.changed = mask
+                .contains(FieldMask::CTIME)
+                .then(|| UNIX_EPOCH.checked_add(Duration::from_nanos(metadata.ctim())))
+                .flatten();
+            ext.ino = field(mask, FieldMask::INO, metadata.ino());
+            ext.dev = Some(metadata.dev());
+        }
+        ext
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_statx(stat: &rustix::fs::Statx) -> Self {
+        let mask = FieldMask(stat.stx_mask);
+        let time = |field, time: &rustix::fs::StatxTimestamp| {
+            mask.contains(field)
+                .then(|| timestamp(time.tv_sec, time.tv_nsec))
+                .flatten()
+        };
+        let mode = u32::from(stat.stx_mode);
+        Self {
+            file_type: field(
+                mask,
+                FieldMask::TYPE,
+                match mode & 0o170000 {
+                    0o100000 => FileTypeSummary::File,
+                    0o040000 => FileTypeSummary::Dir,
+                    0o120000 => FileTypeSummary::Symlink,
+                    _ => FileTypeSummary::Other,
+                },
+            ),
+            mode: field(mask, FieldMask::MODE, mode & 0o7777),
+            nlink: field(mask, FieldMask::NLINK, u64::from(stat.stx_nlink)),
+            uid: field(mask, FieldMask::UID, stat.stx_uid),
+            gid: field(mask, FieldMask::GID, stat.stx_gid),
+            accessed: time(FieldMask::ATIME, &stat.stx_atime),
+            modified: time(FieldMask::MTIME, &stat.stx_mtime),
+            changed: time(FieldMask::CTIME, &stat.stx_ctime),
+            created: time(FieldMask::BTIME, &stat.stx_btime),
+            ino: field(mask, FieldMask::INO, stat.stx_ino),
+            len: field(mask, FieldMask::SIZE, stat.stx_size),
+            blocks: field(mask, FieldMask::BLOCKS, stat.stx_blocks),
+            dev: Some(rustix::fs::makedev(stat.stx_dev_major, stat.stx_dev_minor)),
+            mount_id: field(mask, FieldMask::MNT_ID, stat.stx_mnt_id),
+            attributes: stat.stx_attributes,
+            attributes_mask: stat.stx_attributes_mask,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Clears fields which the filesystem reported but which weren't
+    /// requested, so that results don't depend on the filesystem.
+    fn retain(&mut self, mask: FieldMask) {
+        fn keep<T>(value: &mut Option<T>, mask: FieldMask, field: FieldMask) {
+            if !mask.contains(field) {
+                *value = None;
+            }
+        }
+        keep(&mut self.file_type, mask, FieldMask::TYPE);
+        keep(&mut self.mode, mask, FieldMask::MODE);
+        keep(&mut self.nlink, mask, FieldMask::NLINK);
+        keep(&mut self.uid, mask, FieldMask::UID);
+        keep(&mut self.gid, mask, FieldMask::GID);
+        keep(&mut self.accessed, mask, FieldMask::ATIME);
+        keep(&mut self.modified, mask, FieldMask::MTIME);
+        keep(&mut self.changed, mask, FieldMask::CTIME);
+        keep(&mut self.created, mask, FieldMask::BTIME);
+        keep(&mut self.ino, mask, FieldMask::INO);
+        keep(&mut self.len, mask, FieldMask::SIZE);
+        keep(&mut self.blocks, mask, FieldMask::BLOCKS);
+        keep(&mut self.mount_id, mask, FieldMask::MNT_ID);
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Queries only the fields in `mask` of the metadata of the entry at
+    /// `path`, including fields plain [`Self::metadata`] doesn't report,
+    /// such as the creation time and mount ID.
+    ///
+    /// On Linux this uses `statx`, which can skip fields which are costly
+    /// to obtain, such as on network filesystems, though filesystems may
+    /// report more fields than requested. Elsewhere, and on kernels without
+    /// `statx`, the fields are taken from plain metadata.
+    ///
+    /// Like [`Self::metadata`], this follows symbolic links.
+    pub fn metadata_ext<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mask: FieldMask,
+    ) -> io::Result<ExtendedMetadata> {
+        self.audited(Operation::Metadata, path, |path| {
+            metadata_ext(self.dir.borrow(), path, mask)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Queries only the fields in `mask` of the metadata of the entry at
+    /// `path`.
+    ///
+    /// See [`DirView::metadata_ext`].
+    pub fn metadata_ext<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        mask: FieldMask,
+    ) -> io::Result<ExtendedMetadata> {
+        self.audited(Operation::Metadata, path, |path| {
+            metadata_ext(self.dir.borrow().as_cap_std(), path.as_std_path(), mask)
+        })
+    }
+}
+
+fn metadata_ext(dir: &Dir, path: &Path, mask: FieldMask) -> io::Result<ExtendedMetadata> {
+    #[cfg(target_os = "linux")]
+    {
+        use rustix::fs::{openat2, statx, AtFlags, Mode, OFlags, ResolveFlags, StatxFlags};
+
+        let path = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        // Resolve `path` beneath `dir` before calling `statx` on it. If this
+        // fails, plain metadata reports the error, or the result, if
+        // `openat2` isn't supported.
+        if let Ok(fd) = openat2(
+            dir,
+            path,
+            OFlags::PATH | OFlags::CLOEXEC,
+            Mode::empty(),
+            ResolveFlags::BENEATH | ResolveFlags::NO_MAGICLINKS,
+        ) {
+            match statx(
+                fd,
+                "",
+                AtFlags::EMPTY_PATH,
+                StatxFlags::from_bits_retain(mask.0),
+            ) {
+                Ok(stat) => {
+                    let mut ext = ExtendedMetadata::from_statx(&stat);
+                    ext.retain(mask);
+                    return Ok(ext);
+                }
+                Err(rustix::io::Errno::NOSYS) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+    Ok(ExtendedMetadata::from_metadata(&dir.metadata(path)?, mask))
+}
+
+/// Returns `value` if `field` is in `mask`.
+#[inline]
+fn field<T>(mask: FieldMask, field: FieldMask, value: T) -> Option<T> {
+    mask.contains(field).then_some(value)
+}
+
+/// Converts a time in seconds and nanoseconds since the Unix epoch.
+#[cfg(unix)]
+fn timestamp(secs: i64, nsecs: u32) -> Option<SystemTime> {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let nsecs = Duration::from_nanos(u64::from(nsecs));
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64) + nsecs)
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+            .checked_add(nsecs)
+    }
+}
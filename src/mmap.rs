@@ -0,0 +1,105 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, OpenOptions};
+use memmap2::{Mmap, MmapMut};
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Maps the file at `path` into memory, read-only.
+    ///
+    /// This is permitted through views of any kind. The map is of the file's
+    /// size when it's mapped.
+    ///
+    /// # Safety
+    ///
+    /// As with [`Mmap::map`], the file must not be modified, or truncated,
+    /// while the map exists, including by other processes, as the changes
+    /// would be visible through the map's immutable slice.
+    #[inline]
+    pub unsafe fn mmap<P: AsRef<Path>>(&self, path: P) -> io::Result<Mmap> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            // The file is closed once it's mapped, but it still needs a
+            // handle from the budget while it's open.
+            let _permit = self.policy.acquire_handle(Operation::Open, path)?;
+            let file = self.dir.borrow().open(path)?;
+            Mmap::map(&file)
+        })
+    }
+
+    /// Maps the file at `path` into memory, writable, so that writes to the
+    /// map change the file.
+    ///
+    /// This fails through views which don't permit changes.
+    ///
+    /// # Safety
+    ///
+    /// As with [`MmapMut::map_mut`], the file must not be modified, or
+    /// truncated, by other means while the map exists.
+    #[inline]
+    pub unsafe fn mmap_mut<P: AsRef<Path>>(&self, path: P) -> io::Result<MmapMut> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_mutation(Operation::Open, path)?;
+            self.check_resolve(Operation::Open, path)?;
+            let _permit = self.policy.acquire_handle(Operation::Open, path)?;
+            let file = self
+                .dir
+                .borrow()
+                .open_with(path, OpenOptions::new().read(true).write(true))?;
+            MmapMut::map_mut(&file)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Maps the file at `path` into memory, read-only.
+    ///
+    /// See [`DirView::mmap`].
+    ///
+    /// # Safety
+    ///
+    /// As with [`Mmap::map`], the file must not be modified, or truncated,
+    /// while the map exists, including by other processes.
+    #[inline]
+    pub unsafe fn mmap<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Mmap> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            let _permit = self
+                .policy
+                .acquire_handle(Operation::Open, path.as_std_path())?;
+            let file = self.dir.borrow().open(path)?;
+            Mmap::map(&file)
+        })
+    }
+
+    /// Maps the file at `path` into memory, writable, so that writes to the
+    /// map change the file.
+    ///
+    /// See [`DirView::mmap_mut`].
+    ///
+    /// # Safety
+    ///
+    /// As with [`MmapMut::map_mut`], the file must not be modified, or
+    /// truncated, by other means while the map exists.
+    #[inline]
+    pub unsafe fn mmap_mut<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<MmapMut> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_mutation(Operation::Open, path)?;
+            self.check_resolve(Operation::Open, path)?;
+            let _permit = self
+                .policy
+                .acquire_handle(Operation::Open, path.as_std_path())?;
+            let file = self
+                .dir
+                .borrow()
+                .open_with(path, OpenOptions::new().read(true).write(true))?;
+            MmapMut::map_mut(&file)
+        })
+    }
+}
@@ -0,0 +1,406 @@
+use crate::overlay::at;
+use crate::{DirView, Operation, ViewError};
+use cap_std::fs::{File, FileType, Metadata, OpenOptions};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use std::{fmt, io, vec};
+
+/// A view composed of a root view with other views grafted onto it at
+/// subpaths, returned by [`DirView::mount`].
+///
+/// Paths under a mount point are served by the view mounted there, with its
+/// own kind and restrictions, and all other paths by the root view. This
+/// assembles a namespace from several capabilities without symbolic links
+/// on disk. Mount points appear in the listings of their parent
+/// directories, hiding anything of the same name in the view beneath them,
+/// and can't be removed or renamed through the composed view.
+///
+/// Paths are resolved lexically, as in an [`OverlayDirView`], so `..` steps
+/// back a component and can't lead outside of the composed tree. Symbolic
+/// links are resolved within the view they're in, so a link in the root
+/// view can't lead into a mounted one.
+///
+/// [`OverlayDirView`]: crate::OverlayDirView
+pub struct MountDirView {
+    root: DirView,
+    mounts: Vec<(PathBuf, DirView)>,
+}
+
+impl DirView {
+    /// Composes `self` with `view` mounted at `path`, so that paths under
+    /// `path` are served by `view`.
+    ///
+    /// The parent of `path` must be a directory. The mount point itself
+    /// needn't exist, and if it does, it's hidden by `view`.
+    pub fn mount<P: AsRef<Path>>(self, path: P, view: DirView) -> io::Result<MountDirView> {
+        MountDirView {
+            root: self,
+            mounts: Vec::new(),
+        }
+        .mount(path, view)
+    }
+}
+
+impl MountDirView {
+    /// Mounts `view` at `path`, so that paths under `path` are served by
+    /// `view`.
+    ///
+    /// `path` may be within another mounted view. Mounting a view where one
+    /// is already mounted replaces it.
+    pub fn mount<P: AsRef<Path>>(mut self, path: P, view: DirView) -> io::Result<Self> {
+        let path = normalize(Operation::OpenDir, path.as_ref())?;
+        let Some(parent) = path.parent() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot mount a view over the root of a composed view",
+            ));
+        };
+        if !self.metadata(parent)?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "not a directory",
+            ));
+        }
+        self.mounts.retain(|(mount_point, _)| *mount_point != path);
+        self.mounts.push((path, view));
+        Ok(self)
+    }
+
+    /// Returns the root view, which serves the paths outside of the mount
+    /// points.
+    #[inline]
+    pub fn root(&self) -> &DirView {
+        &self.root
+    }
+
+    /// Returns the mount points, relative to `self`, and the views mounted
+    /// at them.
+    pub fn mounts(&self) -> impl Iterator<Item = (&Path, &DirView)> {
+        self.mounts
+            .iter()
+            .map(|(mount_point, view)| (mount_point.as_path(), view))
+    }
+
+    /// Attempts to open a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let (view, path) = self.resolve(Operation::Open, path.as_ref())?;
+        view.open(at(&path))
+    }
+
+    /// Opens a file at `path` with the options specified by `options`.
+    pub fn open_with<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> io::Result<File> {
+        let (view, path) = self.resolve(Operation::Open, path.as_ref())?;
+        view.open_with(at(&path), options)
+    }
+
+    /// Opens a file in write-only mode, creating it if it doesn't exist and
+    /// truncating it if it does.
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let (view, path) = self.resolve(Operation::Create, path.as_ref())?;
+        view.create(at(&path))
+    }
+
+    /// Opens a file in append mode, creating it if it doesn't exist.
+    pub fn open_append<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let (view, path) = self.resolve(Operation::Create, path.as_ref())?;
+        view.open_append(at(&path))
+    }
+
+    /// Read the entire contents of a file into a bytes vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let (view, path) = self.resolve(Operation::Read, path.as_ref())?;
+        view.read(at(&path))
+    }
+
+    /// Read the entire contents of a file into a string.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let (view, path) = self.resolve(Operation::Read, path.as_ref())?;
+        view.read_to_string(at(&path))
+    }
+
+    /// Write a slice as the entire contents of a file.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> io::Result<()> {
+        let (view, path) = self.resolve(Operation::Write, path.as_ref())?;
+        view.write(at(&path), contents)
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let (view, path) = self.resolve(Operation::ReadLink, path.as_ref())?;
+        view.read_link_contents(at(&path))
+    }
+
+    /// Given a path, query the composed tree to get information about a
+    /// file, directory, etc.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let (view, path) = self.resolve(Operation::Metadata, path.as_ref())?;
+        view.metadata(at(&path))
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    pub fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let (view, path) = self.resolve(Operation::Metadata, path.as_ref())?;
+        view.symlink_metadata(at(&path))
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Returns `true` if the path points at a regular file.
+    #[inline]
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_file())
+    }
+
+    /// Checks if `path` is a directory.
+    #[inline]
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    /// Returns an iterator over the entries within `self`.
+    pub fn entries(&self) -> io::Result<MountReadDir> {
+        self.read_dir("")
+    }
+
+    /// Returns an iterator over the entries within a directory of the
+    /// composed tree, in order of their names, including the mount points
+    /// in it.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<MountReadDir> {
+        let path = normalize(Operation::ReadDir, path.as_ref())?;
+        let (view, rest) = self.view_for(&path);
+        let mut entries = Vec::new();
+        for entry in view.read_dir(at(rest))? {
+            let entry = entry?;
+            entries.push(MountDirEntry {
+                name: entry.file_name(),
+                metadata: entry.metadata()?,
+                mount_point: false,
+            });
+        }
+        for (mount_point, view) in &self.mounts {
+            if mount_point.parent() != Some(&path) {
+                continue;
+            }
+            let name = mount_point.file_name().unwrap().to_os_string();
+            entries.retain(|entry| entry.name != name);
+            entries.push(MountDirEntry {
+                name,
+                metadata: view.dir_metadata()?,
+                mount_point: true,
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(MountReadDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Attempts to open a directory, as a composed view of it and the views
+    /// mounted within it.
+    pub fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<MountDirView> {
+        let path = normalize(Operation::OpenDir, path.as_ref())?;
+        let (view, rest) = self.view_for(&path);
+        let root = view.open_dir(at(rest))?;
+        let mut mounts = Vec::new();
+        for (mount_point, view) in &self.mounts {
+            match mount_point.strip_prefix(&path) {
+                Ok(rest) if !rest.as_os_str().is_empty() => {
+                    mounts.push((rest.to_owned(), view.try_clone()?))
+                }
+                _ => {}
+            }
+        }
+        Ok(MountDirView { root, mounts })
+    }
+
+    /// Creates a new, empty directory at the provided path.
+    pub fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let (view, path) = self.resolve(Operation::CreateDir, path.as_ref())?;
+        view.create_dir(at(&path))
+    }
+
+    /// Recursively create a directory and all of its parent components if
+    /// they are missing.
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let (view, path) = self.resolve(Operation::CreateDir, path.as_ref())?;
+        view.create_dir_all(at(&path))
+    }
+
+    /// Removes a file from the composed tree.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let (view, path) = self.resolve(Operation::RemoveFile, path.as_ref())?;
+        view.remove_file(at(&path))
+    }
+
+    /// Removes an empty directory from the composed tree.
+    ///
+    /// This fails if the directory is a mount point or contains one.
+    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = normalize(Operation::RemoveDir, path.as_ref())?;
+        self.check_busy(&path)?;
+        let (view, path) = self.view_for(&path);
+        view.remove_dir(at(path))
+    }
+
+    /// Removes a directory from the composed tree, after removing all its
+    /// contents.
+    ///
+    /// This fails if the directory is a mount point or contains one.
+    pub fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = normalize(Operation::RemoveDirAll, path.as_ref())?;
+        self.check_busy(&path)?;
+        let (view, path) = self.view_for(&path);
+        view.remove_dir_all(at(path))
+    }
+
+    /// Rename a file or directory to a new name, replacing the original file
+    /// if `to` already exists.
+    ///
+    /// Entries can't be renamed from one of the composed views to another,
+    /// so this fails with [`io::ErrorKind::CrossesDevices`] if `from` and
+    /// `to` are served by different views. It also fails if either is a
+    /// mount point or contains one.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        let from = normalize(Operation::Rename, from.as_ref())?;
+        let to = normalize(Operation::Rename, to.as_ref())?;
+        self.check_busy(&from)?;
+        self.check_busy(&to)?;
+        let (from_view, from) = self.view_for(&from);
+        let (to_view, to) = self.view_for(&to);
+        if !std::ptr::eq(from_view, to_view) {
+            return Err(io::Error::new(
+                io::ErrorKind::CrossesDevices,
+                "attempted to rename an entry between mounted views",
+            ));
+        }
+        from_view.rename(at(from), to_view, at(to))
+    }
+
+    /// Returns the view serving `path`, and `path` relative to it.
+    fn resolve(&self, operation: Operation, path: &Path) -> io::Result<(&DirView, PathBuf)> {
+        let path = normalize(operation, path)?;
+        let (view, rest) = self.view_for(&path);
+        Ok((view, rest.to_owned()))
+    }
+
+    /// Returns the view serving the normalized `path`, which is the one
+    /// mounted at the longest mount point containing it, and `path`
+    /// relative to it.
+    fn view_for<'a>(&self, path: &'a Path) -> (&DirView, &'a Path) {
+        self.mounts
+            .iter()
+            .filter_map(|(mount_point, view)| Some((view, path.strip_prefix(mount_point).ok()?)))
+            .min_by_key(|(_, rest)| rest.components().count())
+            .unwrap_or((&self.root, path))
+    }
+
+    /// Fails if the normalized `path` is a mount point or contains one, so
+    /// that it can't be removed or renamed.
+    fn check_busy(&self, path: &Path) -> io::Result<()> {
+        if self
+            .mounts
+            .iter()
+            .any(|(mount_point, _)| mount_point.starts_with(path))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                "attempted to remove or rename a mount point or a directory containing one",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MountDirView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MountDirView")
+            .field("root", &self.root)
+            .field("mounts", &self.mounts)
+            .finish()
+    }
+}
+
+/// Normalizes `path` lexically, denying paths which lead outside of the
+/// composed tree.
+fn normalize(operation: Operation, path: &Path) -> io::Result<PathBuf> {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normal.push(name),
+            Component::CurDir => {}
+            Component::ParentDir if normal.pop() => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(ViewError::denied(
+                    io::ErrorKind::PermissionDenied,
+                    "a path led outside of the composed view",
+                    operation,
+                    path,
+                    false,
+                )
+                .into())
+            }
+        }
+    }
+    Ok(normal)
+}
+
+/// An iterator over the entries of a directory in a [`MountDirView`].
+#[derive(Debug)]
+pub struct MountReadDir {
+    entries: vec::IntoIter<MountDirEntry>,
+}
+
+impl Iterator for MountReadDir {
+    type Item = io::Result<MountDirEntry>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Ok)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// An entry of a directory in a [`MountDirView`].
+#[derive(Debug)]
+pub struct MountDirEntry {
+    name: OsString,
+    metadata: Metadata,
+    mount_point: bool,
+}
+
+impl MountDirEntry {
+    /// Returns the bare file name of this entry, without any other leading
+    /// path components.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the file type for the file that this entry points at.
+    #[inline]
+    pub fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.metadata.file_type())
+    }
+
+    /// Returns the metadata for the file that this entry points at, as it
+    /// was when the directory was read.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+
+    /// Returns `true` if this entry is a mount point, whose contents are
+    /// served by a mounted view.
+    #[inline]
+    pub fn is_mount_point(&self) -> bool {
+        self.mount_point
+    }
+}
@@ -0,0 +1,95 @@
+use crate::fsync::sync_dir;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Moves a file, symbolic link, or directory to `to` in `to_dir`,
+    /// replacing any file already there.
+    ///
+    /// This is like [`Self::rename`], but if `to_dir` is on a different
+    /// filesystem, it falls back to copying the entry, syncing the copy to
+    /// storage, and then removing the original. Copied directories are
+    /// copied as by [`Self::copy_dir_all`], so that fallback fails if `to`
+    /// already exists. If the fallback fails part way through, the original
+    /// is left in place.
+    pub fn move_entry<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &DirView<E>,
+        to: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Move, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            let (from, to) = (from, to.as_ref());
+            self.check_change(Operation::Move, from)?;
+            to_dir.check_change(Operation::Move, to)?;
+            if self.dry_run_pair(to_dir, Operation::Move, from, to)? {
+                return Ok(());
+            }
+            match self.dir.borrow().rename(from, to_dir.dir.borrow(), to) {
+                Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+                result => return result,
+            }
+
+            let metadata = self.symlink_metadata(from)?;
+            if metadata.is_dir() {
+                self.copy_dir_all(from, to_dir, to)?;
+                to_dir.sync_all_tree(to)?;
+            } else {
+                if to_dir.symlink_metadata(to).is_ok_and(|to| !to.is_dir()) {
+                    to_dir.remove_file(to)?;
+                }
+                if metadata.is_symlink() {
+                    self.copy_symlink(from, to_dir, to)?;
+                } else {
+                    self.copy(from, to_dir, to)?;
+                    #[cfg(feature = "cap-fs-ext")]
+                    to_dir.copy_times(to, &metadata)?;
+                    to_dir.open(to)?.sync_all()?;
+                }
+            }
+            match to.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                Some(parent) => sync_dir(&to_dir.dir.borrow().open_dir(parent)?)?,
+                None => sync_dir(to_dir.dir.borrow())?,
+            }
+
+            if metadata.is_dir() {
+                self.remove_dir_all(from)
+            } else {
+                self.remove_file(from)
+            }
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Moves a file, symbolic link, or directory to `to` in `to_dir`,
+    /// replacing any file already there.
+    ///
+    /// This is like [`Self::rename`], but if `to_dir` is on a different
+    /// filesystem, it falls back to copying the entry, syncing the copy to
+    /// storage, and then removing the original. Copied directories are
+    /// copied as by [`Self::copy_dir_all`], so that fallback fails if `to`
+    /// already exists. If the fallback fails part way through, the original
+    /// is left in place.
+    pub fn move_entry<E: Borrow<cap_std::fs_utf8::Dir>, P: AsRef<Utf8Path>, Q: AsRef<Utf8Path>>(
+        &self,
+        from: P,
+        to_dir: &DirViewUtf8<E>,
+        to: Q,
+    ) -> io::Result<()> {
+        self.try_clone()?.into_cap_std_view().move_entry(
+            from.as_ref().as_std_path(),
+            &to_dir.try_clone()?.into_cap_std_view(),
+            to.as_ref().as_std_path(),
+        )
+    }
+}
@@ -0,0 +1,228 @@
+use crate::atomic_file::temp_name;
+use crate::error::no_file_name;
+use crate::fsync::sync_dir;
+use crate::rename_noreplace::rename_noreplace;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+/// A temporary file with a name in a view, returned by
+/// [`DirView::named_tempfile`], which is removed when it's dropped unless
+/// it's persisted.
+///
+/// This is the building block under atomic writes: write the contents,
+/// then [`persist`] the file at its final path, replacing what's there, or
+/// [`persist_noclobber`] it, failing if something's already there. Either
+/// way, the contents are synced first, so after a crash, the final path
+/// holds either its old contents or the complete new ones. Unlike with
+/// [`AtomicFile`], the final path needn't be known when the file is
+/// created, though it should be in the same directory, so that it's renamed
+/// rather than copied.
+///
+/// [`persist`]: Self::persist
+/// [`persist_noclobber`]: Self::persist_noclobber
+/// [`AtomicFile`]: crate::AtomicFile
+pub struct NamedTempFile {
+    file: File,
+    temp_path: TempPath,
+}
+
+/// The path of a [`NamedTempFile`], which removes it on drop unless it's
+/// been persisted.
+struct TempPath {
+    view: DirView,
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Creates a temporary file named after `prefix`, which is a path whose
+    /// last component is the start of the name.
+    ///
+    /// The file is created next to `prefix`, with a hidden name starting
+    /// with `prefix`'s last component, and opened for reading and writing.
+    /// It's persisted and removed within the view, and this obtains a
+    /// writable handle, so it fails for read-only and dry-run views.
+    pub fn named_tempfile<P: AsRef<Path>>(&self, prefix: P) -> io::Result<NamedTempFile> {
+        self.audited(Operation::CreateTempFile, prefix, |prefix| {
+            self.check_resolve(Operation::CreateTempFile, prefix)?;
+            self.check_mutation(Operation::CreateTempFile, prefix)?;
+            self.policy
+                .check_unbudgeted(Operation::CreateTempFile, prefix)?;
+            NamedTempFile::create(self.try_clone()?, prefix)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Creates a temporary file named after `prefix`, which is a path whose
+    /// last component is the start of the name.
+    ///
+    /// See [`DirView::named_tempfile`].
+    pub fn named_tempfile<P: AsRef<Utf8Path>>(&self, prefix: P) -> io::Result<NamedTempFile> {
+        self.audited(Operation::CreateTempFile, prefix, |prefix| {
+            self.check_resolve(Operation::CreateTempFile, prefix)?;
+            self.check_mutation(Operation::CreateTempFile, prefix)?;
+            self.policy
+                .check_unbudgeted(Operation::CreateTempFile, prefix.as_std_path())?;
+            NamedTempFile::create(self.try_clone()?.into_cap_std_view(), prefix.as_std_path())
+        })
+    }
+}
+
+impl NamedTempFile {
+    fn create(view: DirView, prefix: &Path) -> io::Result<Self> {
+        let name = prefix.file_name().ok_or_else(no_file_name)?;
+        let parent = prefix.parent().unwrap_or(Path::new(""));
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        loop {
+            let path = parent.join(temp_name(name));
+            match view.dir.open_with(&path, &options) {
+                Ok(file) => {
+                    return Ok(Self {
+                        file,
+                        temp_path: TempPath {
+                            view,
+                            path,
+                            persisted: false,
+                        },
+                    })
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Returns the path of the temporary file, relative to the view it was
+    /// created in.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.temp_path.path
+    }
+
+    /// Returns the temporary file.
+    #[inline]
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns the temporary file.
+    #[inline]
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Syncs the written data and renames the temporary file to `path`,
+    /// replacing any existing file there, and returns the file.
+    ///
+    /// If this fails, the temporary file is removed.
+    pub fn persist<P: AsRef<Path>>(self, path: P) -> io::Result<File> {
+        self.persist_with(path.as_ref(), |dir, from, to| dir.rename(from, dir, to))
+    }
+
+    /// Syncs the written data and renames the temporary file to `path`,
+    /// failing with [`io::ErrorKind::AlreadyExists`] if something's already
+    /// there, and returns the file.
+    ///
+    /// If this fails, the temporary file is removed.
+    pub fn persist_noclobber<P: AsRef<Path>>(self, path: P) -> io::Result<File> {
+        self.persist_with(path.as_ref(), |dir, from, to| {
+            rename_noreplace(dir, from, dir, to)
+        })
+    }
+
+    fn persist_with(
+        self,
+        path: &Path,
+        rename: impl FnOnce(&Dir, &Path, &Path) -> io::Result<()>,
+    ) -> io::Result<File> {
+        let Self {
+            mut file,
+            mut temp_path,
+        } = self;
+        file.flush()?;
+        file.sync_all()?;
+        let view = &temp_path.view;
+        view.audited(Operation::Rename, path, |path| {
+            let path = view.fold_names(path);
+            view.check_resolve(Operation::Rename, path.as_ref())?;
+            view.check_change(Operation::Rename, path.as_ref())?;
+            rename(&view.dir, &temp_path.path, path.as_ref())?;
+            // Sync the directory too, so that the rename itself is durable.
+            match path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                Some(parent) => sync_dir(&view.dir.open_dir(parent)?),
+                None => sync_dir(&view.dir),
+            }
+        })?;
+        temp_path.persisted = true;
+        Ok(file)
+    }
+
+    /// Removes the temporary file, reporting any error in doing so, which
+    /// dropping it ignores.
+    pub fn discard(self) -> io::Result<()> {
+        let mut temp_path = self.temp_path;
+        temp_path.persisted = true;
+        temp_path.view.dir.remove_file(&temp_path.path)
+    }
+}
+
+impl Read for NamedTempFile {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.file.read(buf)?;
+        self.temp_path.view.policy.account_bytes_read(len);
+        Ok(len)
+    }
+}
+
+impl Write for NamedTempFile {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.file.write(buf)?;
+        self.temp_path.view.policy.account_bytes_written(len);
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for NamedTempFile {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if !self.persisted {
+            self.view.dir.remove_file(mem::take(&mut self.path)).ok();
+        }
+    }
+}
+
+impl fmt::Debug for NamedTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedTempFile")
+            .field("path", &self.temp_path.path)
+            .finish_non_exhaustive()
+    }
+}
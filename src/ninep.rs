@@ -0,0 +1,777 @@
+use crate::{DirView, ViewKind};
+use cap_std::fs::{
+    DirBuilder, DirBuilderExt, FileExt, FileType, Metadata, MetadataExt, OpenOptions,
+    OpenOptionsExt, Permissions, PermissionsExt,
+};
+use rustix::fs::DirEntryExt;
+use rustix::io::Errno;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
+
+/// The protocol version served.
+const VERSION: &[u8] = b"9P2000.L";
+
+/// The smallest message size accepted, in bytes.
+const MIN_MSIZE: u32 = 4096;
+
+/// The largest message size negotiated, in bytes.
+const MAX_MSIZE: u32 = 1 << 20;
+
+/// The size of the header of `Rread` and `Twrite` messages, which is
+/// subtracted from the message size to get the largest I/O size.
+const IOHDRSZ: u32 = 24;
+
+/// The most names walked by one `Twalk`.
+const MAXWELEM: usize = 16;
+
+// Message types.
+const RLERROR: u8 = 7;
+const TSTATFS: u8 = 8;
+const TLOPEN: u8 = 12;
+const TLCREATE: u8 = 14;
+const TSYMLINK: u8 = 16;
+const TRENAME: u8 = 20;
+const TREADLINK: u8 = 22;
+const TGETATTR: u8 = 24;
+const TSETATTR: u8 = 26;
+const TREADDIR: u8 = 40;
+const TFSYNC: u8 = 50;
+const TLINK: u8 = 70;
+const TMKDIR: u8 = 72;
+const TRENAMEAT: u8 = 74;
+const TUNLINKAT: u8 = 76;
+const TVERSION: u8 = 100;
+const TATTACH: u8 = 104;
+const TFLUSH: u8 = 108;
+const TWALK: u8 = 110;
+const TREAD: u8 = 116;
+const TWRITE: u8 = 118;
+const TCLUNK: u8 = 120;
+const TREMOVE: u8 = 122;
+
+// Flags of `Tlopen` and `Tlcreate`, with their Linux values.
+const O_ACCMODE: u32 = 0o3;
+const O_WRONLY: u32 = 0o1;
+const O_RDWR: u32 = 0o2;
+const O_EXCL: u32 = 0o200;
+const O_TRUNC: u32 = 0o1000;
+const O_APPEND: u32 = 0o2000;
+
+// Flags of `Tsetattr`.
+const SETATTR_MODE: u32 = 0x1;
+const SETATTR_UID: u32 = 0x2;
+const SETATTR_GID: u32 = 0x4;
+const SETATTR_SIZE: u32 = 0x8;
+const SETATTR_ATIME: u32 = 0x10;
+const SETATTR_MTIME: u32 = 0x20;
+#[cfg(feature = "cap-fs-ext")]
+const SETATTR_ATIME_SET: u32 = 0x80;
+#[cfg(feature = "cap-fs-ext")]
+const SETATTR_MTIME_SET: u32 = 0x100;
+
+/// The fields of `Rgetattr` which are filled in.
+const GETATTR_BASIC: u64 = 0x7ff;
+
+/// The `AT_REMOVEDIR` flag of `Tunlinkat`.
+const AT_REMOVEDIR: u32 = 0x200;
+
+/// The filesystem type reported by `Rstatfs`.
+const V9FS_MAGIC: u32 = 0x0102_1997;
+
+impl DirView {
+    /// Serves `self` over `stream` with the 9P2000.L protocol, until the
+    /// client disconnects.
+    ///
+    /// This lets a virtual machine, or any other 9P client such as Linux's
+    /// `v9fs`, mount the view as a filesystem share. Every request is
+    /// performed through the view, so its restrictions are enforced here
+    /// rather than by the client's mount options: changes through a
+    /// [`ViewKind::Readonly`] view fail with `EROFS`, and other denials with
+    /// `EACCES`. No authentication is performed, and the `aname` of an
+    /// attach is a path within the view to use as the root of the share.
+    ///
+    /// Requests are served one at a time. Locking and extended attributes
+    /// aren't supported.
+    pub fn serve_9p<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        let mut session = Session {
+            view: self,
+            msize: MAX_MSIZE,
+            fids: HashMap::new(),
+        };
+        let mut reply = Vec::new();
+        loop {
+            let mut size = [0; 4];
+            if !read_or_eof(&mut stream, &mut size)? {
+                return Ok(());
+            }
+            let size = u32::from_le_bytes(size);
+            if size < 7 || size > session.msize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "9P message size out of range",
+                ));
+            }
+            let mut message = vec![0; size as usize - 4];
+            stream.read_exact(&mut message)?;
+            let (kind, tag, body) = (message[0], &message[1..3], &message[3..]);
+
+            reply.clear();
+            reply.extend_from_slice(&[0; 4]);
+            reply.push(kind.wrapping_add(1));
+            reply.extend_from_slice(tag);
+            if let Err(errno) = session.handle(kind, &mut Decoder(body), &mut reply) {
+                reply.truncate(4);
+                reply.push(RLERROR);
+                reply.extend_from_slice(tag);
+                put_u32(&mut reply, errno.raw_os_error() as u32);
+            }
+            let len = reply.len() as u32;
+            reply[..4].copy_from_slice(&len.to_le_bytes());
+            stream.write_all(&reply)?;
+            stream.flush()?;
+        }
+    }
+}
+
+/// The state of a connection.
+struct Session<'a> {
+    view: &'a DirView,
+    msize: u32,
+    fids: HashMap<u32, Fid>,
+}
+
+/// A client's reference to an entry.
+struct Fid {
+    /// The entry's path, relative to the view, with no `.` or `..`
+    /// components.
+    path: PathBuf,
+    open: Option<Open>,
+}
+
+enum Open {
+    File(cap_std::fs::File),
+    Dir(Vec<Listed>),
+}
+
+/// An entry of an opened directory.
+struct Listed {
+    name: OsString,
+    qid: Qid,
+    dtype: u8,
+}
+
+/// The server's identifier for an entry.
+#[derive(Copy, Clone)]
+struct Qid {
+    kind: u8,
+    path: u64,
+}
+
+impl Qid {
+    fn new(file_type: FileType, ino: u64) -> Self {
+        let kind = if file_type.is_dir() {
+            0x80
+        } else if file_type.is_symlink() {
+            0x02
+        } else {
+            0
+        };
+        Self { kind, path: ino }
+    }
+
+    fn from_metadata(metadata: &Metadata) -> Self {
+        Self::new(metadata.file_type(), metadata.ino())
+    }
+
+    fn encode(self, out: &mut Vec<u8>) {
+        out.push(self.kind);
+        put_u32(out, 0);
+        put_u64(out, self.path);
+    }
+}
+
+impl Session<'_> {
+    fn handle(&mut self, kind: u8, msg: &mut Decoder<'_>, out: &mut Vec<u8>) -> Result<(), Errno> {
+        match kind {
+            TVERSION => {
+                let msize = msg.u32()?;
+                let version = msg.string()?;
+                // The server may only lower the client's proposal.
+                if msize < MIN_MSIZE {
+                    return Err(Errno::INVAL);
+                }
+                self.fids.clear();
+                self.msize = msize.min(MAX_MSIZE);
+                put_u32(out, self.msize);
+                put_string(
+                    out,
+                    if version.starts_with(VERSION) {
+                        VERSION
+                    } else {
+                        b"unknown"
+                    },
+                );
+            }
+            TATTACH => {
+                let fid = msg.u32()?;
+                let _afid = msg.u32()?;
+                let _uname = msg.string()?;
+                let aname = msg.string()?;
+                let mut path = PathBuf::new();
+                for name in aname.split(|b| *b == b'/').filter(|name| !name.is_empty()) {
+                    path = walk(&path, name)?;
+                }
+                let metadata = self.metadata(&path)?;
+                self.fids.insert(fid, Fid { path, open: None });
+                Qid::from_metadata(&metadata).encode(out);
+            }
+            TFLUSH => {}
+            TWALK => {
+                let fid = msg.u32()?;
+                let newfid = msg.u32()?;
+                let count = msg.u16()? as usize;
+                if count > MAXWELEM {
+                    return Err(Errno::INVAL);
+                }
+                let mut path = self.fid(fid)?.path.clone();
+                let mut qids = Vec::new();
+                for i in 0..count {
+                    let walked = walk(&path, msg.string()?)
+                        .and_then(|walked| Ok((self.metadata(&walked)?, walked)));
+                    match walked {
+                        Ok((metadata, walked)) => {
+                            qids.push(Qid::from_metadata(&metadata));
+                            path = walked;
+                        }
+                        Err(errno) if i == 0 => return Err(errno),
+                        Err(_) => break,
+                    }
+                }
+                if qids.len() == count {
+                    self.fids.insert(newfid, Fid { path, open: None });
+                }
+                put_u16(out, qids.len() as u16);
+                for qid in qids {
+                    qid.encode(out);
+                }
+            }
+            TLOPEN => {
+                let fid = msg.u32()?;
+                let flags = msg.u32()?;
+                let path = self.fid(fid)?.path.clone();
+                let metadata = self.metadata(&path)?;
+                let open = if metadata.is_dir() {
+                    if flags & O_ACCMODE != 0 {
+                        return Err(Errno::ISDIR);
+                    }
+                    Open::Dir(self.list(&path)?)
+                } else {
+                    if flags & (O_ACCMODE | O_TRUNC | O_APPEND) != 0 {
+                        self.check_writable()?;
+                    }
+                    let options = open_options(flags);
+                    Open::File(self.view.open_with(at(&path), &options).map_err(errno)?)
+                };
+                self.fid(fid)?.open = Some(open);
+                Qid::from_metadata(&metadata).encode(out);
+                put_u32(out, self.msize - IOHDRSZ);
+            }
+            TLCREATE => {
+                let fid = msg.u32()?;
+                let name = msg.string()?;
+                let flags = msg.u32()?;
+                let mode = msg.u32()?;
+                let _gid = msg.u32()?;
+                self.check_writable()?;
+                let path = self.fid(fid)?.path.join(name_of(name)?);
+                let mut options = open_options(flags);
+                options.write(true).mode(mode & 0o7777);
+                if flags & O_EXCL != 0 {
+                    options.create_new(true);
+                } else {
+                    options.create(true);
+                }
+                let file = self.view.open_with(&path, &options).map_err(errno)?;
+                let metadata = file.metadata().map_err(errno)?;
+                *self.fid(fid)? = Fid {
+                    path,
+                    open: Some(Open::File(file)),
+                };
+                Qid::from_metadata(&metadata).encode(out);
+                put_u32(out, self.msize - IOHDRSZ);
+            }
+            TSYMLINK => {
+                let fid = msg.u32()?;
+                let name = msg.string()?;
+                let target = msg.string()?;
+                let _gid = msg.u32()?;
+                self.check_writable()?;
+                let path = self.fid(fid)?.path.join(name_of(name)?);
+                self.view
+                    .symlink_contents(OsStr::from_bytes(target), &path)
+                    .map_err(errno)?;
+                Qid::from_metadata(&self.metadata(&path)?).encode(out);
+            }
+            TMKDIR => {
+                let fid = msg.u32()?;
+                let name = msg.string()?;
+                let mode = msg.u32()?;
+                let _gid = msg.u32()?;
+                self.check_writable()?;
+                let path = self.fid(fid)?.path.join(name_of(name)?);
+                let mut builder = DirBuilder::new();
+                builder.mode(mode & 0o7777);
+                self.view.create_dir_with(&path, &builder).map_err(errno)?;
+                Qid::from_metadata(&self.metadata(&path)?).encode(out);
+            }
+            TREADLINK => {
+                let fid = msg.u32()?;
+                let path = self.fid(fid)?.path.clone();
+                let target = self.view.read_link_contents(at(&path)).map_err(errno)?;
+                put_string(out, target.as_os_str().as_bytes());
+            }
+            TGETATTR => {
+                let fid = msg.u32()?;
+                let _mask = msg.u64()?;
+                let path = self.fid(fid)?.path.clone();
+                let metadata = self.metadata(&path)?;
+                put_u64(out, GETATTR_BASIC);
+                Qid::from_metadata(&metadata).encode(out);
+                put_u32(out, metadata.mode());
+                put_u32(out, metadata.uid());
+                put_u32(out, metadata.gid());
+                put_u64(out, metadata.nlink());
+                put_u64(out, metadata.rdev());
+                put_u64(out, metadata.size());
+                put_u64(out, metadata.blksize());
+                put_u64(out, metadata.blocks());
+                for (sec, nsec) in [
+                    (metadata.atime(), metadata.atime_nsec()),
+                    (metadata.mtime(), metadata.mtime_nsec()),
+                    (metadata.ctime(), metadata.ctime_nsec()),
+                    (0, 0),
+                ] {
+                    put_u64(out, sec as u64);
+                    put_u64(out, nsec as u64);
+                }
+                put_u64(out, 0);
+                put_u64(out, 0);
+            }
+            TSETATTR => {
+                let fid = msg.u32()?;
+                let valid = msg.u32()?;
+                let mode = msg.u32()?;
+                let uid = msg.u32()?;
+                let gid = msg.u32()?;
+                let size = msg.u64()?;
+                let atime = (msg.u64()?, msg.u64()?);
+                let mtime = (msg.u64()?, msg.u64()?);
+                let path = self.fid(fid)?.path.clone();
+                self.setattr(&path, valid, mode, (uid, gid), size, atime, mtime)?;
+            }
+            TREADDIR => {
+                let fid = msg.u32()?;
+                let offset = msg.u64()?;
+                let count = msg.u32()?.min(self.msize - IOHDRSZ) as usize;
+                let Some(Open::Dir(entries)) = &self.fid(fid)?.open else {
+                    return Err(Errno::BADF);
+                };
+                let start = out.len();
+                put_u32(out, 0);
+                for (i, entry) in entries.iter().enumerate().skip(offset as usize) {
+                    let name = entry.name.as_bytes();
+                    if out.len() - start - 4 + 24 + name.len() > count {
+                        break;
+                    }
+                    entry.qid.encode(out);
+                    put_u64(out, i as u64 + 1);
+                    out.push(entry.dtype);
+                    put_string(out, name);
+                }
+                let len = (out.len() - start - 4) as u32;
+                out[start..start + 4].copy_from_slice(&len.to_le_bytes());
+            }
+            TREAD => {
+                let fid = msg.u32()?;
+                let offset = msg.u64()?;
+                let count = msg.u32()?.min(self.msize - IOHDRSZ) as usize;
+                let file = self.file(fid)?;
+                let start = out.len();
+                put_u32(out, 0);
+                out.resize(start + 4 + count, 0);
+                let len = file.read_at(&mut out[start + 4..], offset).map_err(errno)?;
+                out.truncate(start + 4 + len);
+                out[start..start + 4].copy_from_slice(&(len as u32).to_le_bytes());
+            }
+            TWRITE => {
+                let fid = msg.u32()?;
+                let offset = msg.u64()?;
+                let count = msg.u32()? as usize;
+                let data = msg.bytes(count)?;
+                let len = self.file(fid)?.write_at(data, offset).map_err(errno)?;
+                put_u32(out, len as u32);
+            }
+            TFSYNC => {
+                let fid = msg.u32()?;
+                let datasync = msg.u32()?;
+                if let Some(Open::File(file)) = &self.fid(fid)?.open {
+                    if datasync != 0 {
+                        file.sync_data().map_err(errno)?;
+                    } else {
+                        file.sync_all().map_err(errno)?;
+                    }
+                }
+            }
+            TSTATFS => {
+                let fid = msg.u32()?;
+                self.fid(fid)?;
+                let stats = self.view.statfs().map_err(errno)?;
+                let bsize = 4096;
+                put_u32(out, V9FS_MAGIC);
+                put_u32(out, bsize);
+                put_u64(out, stats.total_space() / u64::from(bsize));
+                put_u64(out, stats.free_space() / u64::from(bsize));
+                put_u64(out, stats.available_space() / u64::from(bsize));
+                put_u64(out, stats.total_files());
+                put_u64(out, stats.free_files());
+                put_u64(out, 0);
+                put_u32(out, 255);
+            }
+            TRENAME => {
+                let fid = msg.u32()?;
+                let dfid = msg.u32()?;
+                let name = msg.string()?;
+                let from = self.fid(fid)?.path.clone();
+                let to = self.fid(dfid)?.path.join(name_of(name)?);
+                self.rename(&from, &to)?;
+            }
+            TRENAMEAT => {
+                let old_dfid = msg.u32()?;
+                let old_name = msg.string()?;
+                let new_dfid = msg.u32()?;
+                let new_name = msg.string()?;
+                let from = self.fid(old_dfid)?.path.join(name_of(old_name)?);
+                let to = self.fid(new_dfid)?.path.join(name_of(new_name)?);
+                self.rename(&from, &to)?;
+            }
+            TLINK => {
+                let dfid = msg.u32()?;
+                let fid = msg.u32()?;
+                let name = msg.string()?;
+                self.check_writable()?;
+                let from = self.fid(fid)?.path.clone();
+                let to = self.fid(dfid)?.path.join(name_of(name)?);
+                self.view
+                    .hard_link(at(&from), self.view, &to)
+                    .map_err(errno)?;
+            }
+            TUNLINKAT => {
+                let dfid = msg.u32()?;
+                let name = msg.string()?;
+                let flags = msg.u32()?;
+                self.check_writable()?;
+                let path = self.fid(dfid)?.path.join(name_of(name)?);
+                let result = if flags & AT_REMOVEDIR != 0 {
+                    self.view.remove_dir(&path)
+                } else {
+                    self.view.remove_file(&path)
+                };
+                result.map_err(errno)?;
+            }
+            TREMOVE => {
+                let fid = msg.u32()?;
+                let path = self.fid(fid)?.path.clone();
+                self.fids.remove(&fid);
+                self.check_writable()?;
+                if path.as_os_str().is_empty() {
+                    return Err(Errno::BUSY);
+                }
+                let result = if self.metadata(&path)?.is_dir() {
+                    self.view.remove_dir(&path)
+                } else {
+                    self.view.remove_file(&path)
+                };
+                result.map_err(errno)?;
+            }
+            TCLUNK => {
+                let fid = msg.u32()?;
+                self.fids.remove(&fid).ok_or(Errno::BADF)?;
+            }
+            _ => return Err(Errno::OPNOTSUPP),
+        }
+        Ok(())
+    }
+
+    fn fid(&mut self, fid: u32) -> Result<&mut Fid, Errno> {
+        self.fids.get_mut(&fid).ok_or(Errno::BADF)
+    }
+
+    fn file(&mut self, fid: u32) -> Result<&cap_std::fs::File, Errno> {
+        match &self.fid(fid)?.open {
+            Some(Open::File(file)) => Ok(file),
+            Some(Open::Dir(_)) => Err(Errno::ISDIR),
+            None => Err(Errno::BADF),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Errno> {
+        self.view.symlink_metadata(at(path)).map_err(errno)
+    }
+
+    /// Fails with `EROFS` if the view can't make changes anywhere, which
+    /// clients report more clearly than the view's own denial.
+    fn check_writable(&self) -> Result<(), Errno> {
+        match self.view.view_kind {
+            ViewKind::Readonly if self.view.policy.overrides.is_none() => Err(Errno::ROFS),
+            ViewKind::Readonly | ViewKind::Full | ViewKind::DryRun => Ok(()),
+        }
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<Listed>, Errno> {
+        let mut listed = Vec::new();
+        for entry in self.view.read_dir(at(path)).map_err(errno)? {
+            let entry = entry.map_err(errno)?;
+            let file_type = entry.file_type().map_err(errno)?;
+            let dtype = if file_type.is_dir() {
+                4
+            } else if file_type.is_symlink() {
+                10
+            } else if file_type.is_file() {
+                8
+            } else {
+                0
+            };
+            listed.push(Listed {
+                name: entry.file_name(),
+                qid: Qid::new(file_type, entry.ino()),
+                dtype,
+            });
+        }
+        Ok(listed)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), Errno> {
+        self.check_writable()?;
+        self.view.rename(from, self.view, to).map_err(errno)?;
+        for fid in self.fids.values_mut() {
+            if let Ok(rest) = fid.path.strip_prefix(from) {
+                fid.path = to.join(rest);
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn setattr(
+        &self,
+        path: &Path,
+        valid: u32,
+        mode: u32,
+        (uid, gid): (u32, u32),
+        size: u64,
+        atime: (u64, u64),
+        mtime: (u64, u64),
+    ) -> Result<(), Errno> {
+        if valid
+            & (SETATTR_MODE
+                | SETATTR_UID
+                | SETATTR_GID
+                | SETATTR_SIZE
+                | SETATTR_ATIME
+                | SETATTR_MTIME)
+            == 0
+        {
+            return Ok(());
+        }
+        self.check_writable()?;
+        let path = at(path);
+        if valid & SETATTR_MODE != 0 {
+            self.view
+                .set_permissions(path, Permissions::from_mode(mode & 0o7777))
+                .map_err(errno)?;
+        }
+        if valid & (SETATTR_UID | SETATTR_GID) != 0 {
+            self.view
+                .set_owner_nofollow(
+                    path,
+                    (valid & SETATTR_UID != 0).then_some(uid),
+                    (valid & SETATTR_GID != 0).then_some(gid),
+                )
+                .map_err(errno)?;
+        }
+        if valid & SETATTR_SIZE != 0 {
+            self.view
+                .open_with(path, OpenOptions::new().write(true))
+                .and_then(|file| file.set_len(size))
+                .map_err(errno)?;
+        }
+        if valid & (SETATTR_ATIME | SETATTR_MTIME) != 0 {
+            self.set_times(path, valid, atime, mtime)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "cap-fs-ext")]
+    fn set_times(
+        &self,
+        path: &Path,
+        valid: u32,
+        atime: (u64, u64),
+        mtime: (u64, u64),
+    ) -> Result<(), Errno> {
+        use cap_fs_ext::SystemTimeSpec;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let spec = |flag, set, (sec, nsec): (u64, u64)| {
+            if valid & flag == 0 {
+                None
+            } else if valid & set == 0 {
+                Some(SystemTimeSpec::SymbolicNow)
+            } else {
+                let time = UNIX_EPOCH + Duration::new(sec, nsec as u32);
+                Some(SystemTimeSpec::Absolute(
+                    cap_std::time::SystemTime::from_std(time),
+                ))
+            }
+        };
+        cap_fs_ext::DirExt::set_symlink_times(
+            self.view,
+            path,
+            spec(SETATTR_ATIME, SETATTR_ATIME_SET, atime),
+            spec(SETATTR_MTIME, SETATTR_MTIME_SET, mtime),
+        )
+        .map_err(errno)
+    }
+
+    /// Setting timestamps requires the `cap-fs-ext` feature.
+    #[cfg(not(feature = "cap-fs-ext"))]
+    fn set_times(
+        &self,
+        _path: &Path,
+        _valid: u32,
+        _atime: (u64, u64),
+        _mtime: (u64, u64),
+    ) -> Result<(), Errno> {
+        Err(Errno::OPNOTSUPP)
+    }
+}
+
+/// Returns the path of the entry `name` in the directory at `path`, as
+/// `Twalk` does: `..` of the root is the root.
+fn walk(path: &Path, name: &[u8]) -> Result<PathBuf, Errno> {
+    match name {
+        b"." => Ok(path.to_path_buf()),
+        b".." => Ok(path.parent().map(Path::to_path_buf).unwrap_or_default()),
+        _ => Ok(path.join(name_of(name)?)),
+    }
+}
+
+/// Checks that `name` names an entry in a directory.
+fn name_of(name: &[u8]) -> Result<&Path, Errno> {
+    let path = Path::new(OsStr::from_bytes(name));
+    let mut components = path.components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(&b'/') => Ok(path),
+        _ => Err(Errno::INVAL),
+    }
+}
+
+/// Returns `path`, or `.` for the root.
+fn at(path: &Path) -> &Path {
+    if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    }
+}
+
+fn open_options(flags: u32) -> OpenOptions {
+    let mut options = OpenOptions::new();
+    match flags & O_ACCMODE {
+        O_WRONLY => options.write(true),
+        O_RDWR => options.read(true).write(true),
+        _ => options.read(true),
+    };
+    options
+        .append(flags & O_APPEND != 0)
+        .truncate(flags & O_TRUNC != 0);
+    options
+}
+
+fn errno(err: io::Error) -> Errno {
+    Errno::from_io_error(&err).unwrap_or(match err.kind() {
+        io::ErrorKind::NotFound => Errno::NOENT,
+        io::ErrorKind::PermissionDenied => Errno::ACCESS,
+        io::ErrorKind::AlreadyExists => Errno::EXIST,
+        io::ErrorKind::InvalidInput => Errno::INVAL,
+        io::ErrorKind::Unsupported => Errno::OPNOTSUPP,
+        _ => Errno::IO,
+    })
+}
+
+/// Reads exactly enough to fill `buf`, returning `false` if the stream
+/// ends before any of it.
+fn read_or_eof(stream: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match stream.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(len) => filled += len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
+
+/// A reader of the fields of a message.
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Errno> {
+        if self.0.len() < len {
+            return Err(Errno::INVAL);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, Errno> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, Errno> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, Errno> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], Errno> {
+        let len = self.u16()?;
+        self.bytes(len.into())
+    }
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_string(out: &mut Vec<u8>, value: &[u8]) {
+    put_u16(out, value.len() as u16);
+    out.extend_from_slice(value);
+}
@@ -0,0 +1,95 @@
+use crate::error::no_file_name;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use rustix::fs::{mknodat, Dev, FileType, Mode};
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::io;
+use std::os::fd::AsFd;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Creates a new FIFO special file at `path`, with the given permission
+    /// bits.
+    ///
+    /// This corresponds to `mkfifo`, but only accesses paths relative to
+    /// `self`.
+    pub fn mkfifo<P: AsRef<Path>>(&self, path: P, mode: u32) -> io::Result<()> {
+        self.mknod(path, FileType::Fifo.as_raw_mode() | (mode & 0o7777), 0)
+    }
+
+    /// Creates a new filesystem node at `path`.
+    ///
+    /// `mode` includes both the file type and permission bits, and `dev` is
+    /// the device number for block and character device nodes. Creating
+    /// device nodes typically requires privileges.
+    ///
+    /// This corresponds to `mknod`, but only accesses paths relative to
+    /// `self`.
+    pub fn mknod<P: AsRef<Path>>(&self, path: P, mode: u32, dev: u64) -> io::Result<()> {
+        self.audited(Operation::Mknod, path, |path| {
+            if self.dry_run(Operation::Mknod, path, None)? {
+                return Ok(());
+            }
+            let name = path.file_name().ok_or_else(no_file_name)?;
+            match path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                Some(parent) => mknod(&self.dir.borrow().open_dir(parent)?, name, mode, dev),
+                None => mknod(self.dir.borrow(), name, mode, dev),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Creates a new FIFO special file at `path`, with the given permission
+    /// bits.
+    ///
+    /// This corresponds to `mkfifo`, but only accesses paths relative to
+    /// `self`.
+    pub fn mkfifo<P: AsRef<Utf8Path>>(&self, path: P, mode: u32) -> io::Result<()> {
+        self.mknod(path, FileType::Fifo.as_raw_mode() | (mode & 0o7777), 0)
+    }
+
+    /// Creates a new filesystem node at `path`.
+    ///
+    /// `mode` includes both the file type and permission bits, and `dev` is
+    /// the device number for block and character device nodes. Creating
+    /// device nodes typically requires privileges.
+    ///
+    /// This corresponds to `mknod`, but only accesses paths relative to
+    /// `self`.
+    pub fn mknod<P: AsRef<Utf8Path>>(&self, path: P, mode: u32, dev: u64) -> io::Result<()> {
+        self.audited(Operation::Mknod, path, |path| {
+            if self.dry_run(Operation::Mknod, path, None)? {
+                return Ok(());
+            }
+            let name = path.file_name().ok_or_else(no_file_name)?;
+            match path.parent().filter(|parent| !parent.as_str().is_empty()) {
+                Some(parent) => mknod(&self.dir.borrow().open_dir(parent)?, name, mode, dev),
+                None => mknod(self.dir.borrow(), name, mode, dev),
+            }
+        })
+    }
+}
+
+fn mknod<Fd: AsFd, N: AsRef<OsStr>>(dir: Fd, name: N, mode: u32, dev: u64) -> io::Result<()> {
+    // `Dev` is narrower than `u64` on some platforms.
+    #[allow(clippy::unnecessary_cast)]
+    let dev = dev as Dev;
+    Ok(mknodat(
+        dir,
+        name.as_ref(),
+        FileType::from_raw_mode(mode as _),
+        Mode::from_raw_mode(mode as _),
+        dev,
+    )?)
+}
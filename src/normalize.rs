@@ -0,0 +1,33 @@
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc_quick, is_nfd_quick, IsNormalized, UnicodeNormalization};
+
+/// A Unicode normalization form for the names of entries in a view, set
+/// with [`DirViewBuilder::normalize_names`].
+///
+/// [`DirViewBuilder::normalize_names`]: crate::DirViewBuilder::normalize_names
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum NormalizationForm {
+    /// Canonical composition, which most platforms and clients produce.
+    Nfc,
+
+    /// Canonical decomposition, which macOS's HFS+ stores names in.
+    Nfd,
+}
+
+impl NormalizationForm {
+    /// Returns `name` in this form.
+    pub(crate) fn normalize(self, name: &str) -> Cow<'_, str> {
+        match self {
+            Self::Nfc => match is_nfc_quick(name.chars()) {
+                IsNormalized::Yes => Cow::Borrowed(name),
+                _ => Cow::Owned(name.nfc().collect()),
+            },
+            Self::Nfd => match is_nfd_quick(name.chars()) {
+                IsNormalized::Yes => Cow::Borrowed(name),
+                _ => Cow::Owned(name.nfd().collect()),
+            },
+        }
+    }
+}
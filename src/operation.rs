@@ -0,0 +1,149 @@
+use std::fmt;
+
+/// An operation performed through a view.
+///
+/// This identifies the operation in errors reporting that a view denied it,
+/// and in [`AuditEvent`]s.
+///
+/// [`AuditEvent`]: crate::AuditEvent
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Checking whether a file may be accessed.
+    Access,
+    /// Declaring how a file will be accessed.
+    Advise,
+    /// Allocating space for a file.
+    Allocate,
+    /// Binding a Unix-domain socket.
+    BindUnixSocket,
+    /// Canonicalizing a path.
+    Canonicalize,
+    /// Connecting or sending to a Unix-domain socket.
+    ConnectUnixSocket,
+    /// Copying a file.
+    Copy,
+    /// Copying a directory and its contents.
+    CopyDir,
+    /// Creating a file.
+    Create,
+    /// Creating a directory.
+    CreateDir,
+    /// Creating a temporary directory.
+    CreateTempDir,
+    /// Creating a temporary file.
+    CreateTempFile,
+    /// Reading an extended attribute.
+    GetXattr,
+    /// Creating a hard link.
+    HardLink,
+    /// Listing extended attributes.
+    ListXattr,
+    /// Locking a file.
+    Lock,
+    /// Querying metadata.
+    Metadata,
+    /// Creating a special file.
+    Mknod,
+    /// Moving a file or directory, possibly to another filesystem.
+    Move,
+    /// Opening a file.
+    Open,
+    /// Opening a directory.
+    OpenDir,
+    /// Reading a file.
+    Read,
+    /// Reading the entries of a directory.
+    ReadDir,
+    /// Reading a symbolic link.
+    ReadLink,
+    /// Removing an empty directory.
+    RemoveDir,
+    /// Removing a directory and its contents.
+    RemoveDirAll,
+    /// Removing the contents of a directory.
+    RemoveDirContents,
+    /// Removing a file.
+    RemoveFile,
+    /// Removing an extended attribute.
+    RemoveXattr,
+    /// Renaming a file or directory.
+    Rename,
+    /// Setting the platform flags of a file.
+    SetFlags,
+    /// Changing the owner of a file.
+    SetOwner,
+    /// Changing the permissions of a file.
+    SetPermissions,
+    /// Changing the timestamps of a file.
+    SetTimes,
+    /// Setting an extended attribute.
+    SetXattr,
+    /// Spawning a process in a directory.
+    Spawn,
+    /// Creating a symbolic link.
+    Symlink,
+    /// Syncing files or directories to storage.
+    Sync,
+    /// Mirroring a directory tree into another view.
+    SyncTo,
+    /// Watching a directory for changes.
+    Watch,
+    /// Writing a file.
+    Write,
+}
+
+impl Operation {
+    /// Returns the name of the operation, as used in error messages.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Access => "access",
+            Self::Advise => "advise",
+            Self::Allocate => "allocate",
+            Self::BindUnixSocket => "bind_unix_socket",
+            Self::Canonicalize => "canonicalize",
+            Self::ConnectUnixSocket => "connect_unix_socket",
+            Self::Copy => "copy",
+            Self::CopyDir => "copy_dir",
+            Self::Create => "create",
+            Self::CreateDir => "create_dir",
+            Self::CreateTempDir => "create_temp_dir",
+            Self::CreateTempFile => "create_temp_file",
+            Self::GetXattr => "get_xattr",
+            Self::HardLink => "hard_link",
+            Self::ListXattr => "list_xattr",
+            Self::Lock => "lock",
+            Self::Metadata => "metadata",
+            Self::Mknod => "mknod",
+            Self::Move => "move",
+            Self::Open => "open",
+            Self::OpenDir => "open_dir",
+            Self::Read => "read",
+            Self::ReadDir => "read_dir",
+            Self::ReadLink => "read_link",
+            Self::RemoveDir => "remove_dir",
+            Self::RemoveDirAll => "remove_dir_all",
+            Self::RemoveDirContents => "remove_dir_contents",
+            Self::RemoveFile => "remove_file",
+            Self::RemoveXattr => "remove_xattr",
+            Self::Rename => "rename",
+            Self::SetFlags => "set_flags",
+            Self::SetOwner => "set_owner",
+            Self::SetPermissions => "set_permissions",
+            Self::SetTimes => "set_times",
+            Self::SetXattr => "set_xattr",
+            Self::Spawn => "spawn",
+            Self::Symlink => "symlink",
+            Self::Sync => "sync",
+            Self::SyncTo => "sync_to",
+            Self::Watch => "watch",
+            Self::Write => "write",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
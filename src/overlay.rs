@@ -0,0 +1,646 @@
+use crate::{DirView, Operation, ViewError};
+use cap_std::fs::{File, FileType, Metadata, OpenOptions};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::{fmt, vec};
+
+/// The prefix of the names of whiteouts in the upper layer.
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// A view merging a readonly lower layer with a writable upper layer.
+///
+/// Reads see the upper layer's entries where it has them, and the lower
+/// layer's otherwise, so the merged tree starts out looking like the lower
+/// one. Changes land only in the upper layer: modifying an entry from the
+/// lower layer first copies it up, and removing one records a whiteout in
+/// the upper layer which hides it. The lower layer is only ever read, so it
+/// may be a readonly view. This makes it possible to run untrusted code
+/// against a tree while keeping all of its modifications in a scratch
+/// directory.
+///
+/// Whiteouts are files named `.wh.<name>` in the upper layer, as in AUFS,
+/// rather than the device files overlayfs uses, which would need
+/// privileges to create. They're hidden from the merged tree, and names
+/// starting with `.wh.` can't be created through it. A whiteout for a
+/// directory also hides the lower layer's contents of a directory created
+/// in its place.
+///
+/// Paths are resolved lexically, so `..` steps back a component rather
+/// than following the parent of a symbolic link, and can't lead outside of
+/// the merged tree. Symbolic links are resolved within the layer they're
+/// in.
+pub struct OverlayDirView {
+    lower: DirView,
+    upper: DirView,
+}
+
+/// Which layer an entry of the merged tree comes from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Layer {
+    Upper,
+    Lower,
+}
+
+impl OverlayDirView {
+    /// Constructs an overlay of `upper` on `lower`.
+    ///
+    /// `upper` must allow the changes made through the overlay, and is
+    /// typically an empty scratch directory.
+    #[inline]
+    pub fn new(lower: DirView, upper: DirView) -> Self {
+        Self { lower, upper }
+    }
+
+    /// Returns the lower layer.
+    #[inline]
+    pub fn lower(&self) -> &DirView {
+        &self.lower
+    }
+
+    /// Returns the upper layer, which holds every change made through the
+    /// overlay, along with whiteouts.
+    #[inline]
+    pub fn upper(&self) -> &DirView {
+        &self.upper
+    }
+
+    /// Attempts to open a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let path = self.normalize(Operation::Open, path.as_ref())?;
+        self.layer_view(self.locate(&path)?).open(&path)
+    }
+
+    /// Opens a file at `path` with the options specified by `options`.
+    ///
+    /// The file is first copied up to the upper layer if it's in the lower
+    /// one, as `options` may allow writing, so use [`Self::open`] to read a
+    /// file without copying it.
+    pub fn open_with<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> io::Result<File> {
+        let path = self.normalize(Operation::Open, path.as_ref())?;
+        self.prepare_write(Operation::Open, &path)?;
+        self.upper.open_with(&path, options)
+    }
+
+    /// Opens a file in write-only mode, creating it if it doesn't exist and
+    /// truncating it if it does.
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let path = self.normalize(Operation::Create, path.as_ref())?;
+        self.prepare_write(Operation::Create, &path)?;
+        self.upper.create(&path)
+    }
+
+    /// Opens a file in append mode, creating it if it doesn't exist.
+    pub fn open_append<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let path = self.normalize(Operation::Create, path.as_ref())?;
+        self.prepare_write(Operation::Create, &path)?;
+        self.upper.open_append(&path)
+    }
+
+    /// Read the entire contents of a file into a bytes vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let path = self.normalize(Operation::Read, path.as_ref())?;
+        self.layer_view(self.locate(&path)?).read(&path)
+    }
+
+    /// Read the entire contents of a file into a string.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let path = self.normalize(Operation::Read, path.as_ref())?;
+        self.layer_view(self.locate(&path)?).read_to_string(&path)
+    }
+
+    /// Write a slice as the entire contents of a file.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> io::Result<()> {
+        self.create(path)?.write_all(contents.as_ref())
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let path = self.normalize(Operation::ReadLink, path.as_ref())?;
+        self.layer_view(self.locate(&path)?)
+            .read_link_contents(&path)
+    }
+
+    /// Given a path, query the merged tree to get information about a file,
+    /// directory, etc.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let path = self.normalize(Operation::Metadata, path.as_ref())?;
+        self.layer_view(self.locate(&path)?).metadata(at(&path))
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    pub fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let path = self.normalize(Operation::Metadata, path.as_ref())?;
+        self.layer_view(self.locate(&path)?)
+            .symlink_metadata(at(&path))
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Returns `true` if the path points at a regular file.
+    #[inline]
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_file())
+    }
+
+    /// Checks if `path` is a directory.
+    #[inline]
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    /// Returns an iterator over the entries within `self`.
+    pub fn entries(&self) -> io::Result<OverlayReadDir> {
+        self.read_dir("")
+    }
+
+    /// Returns an iterator over the entries within a directory of the merged
+    /// tree, in order of their names.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<OverlayReadDir> {
+        let path = self.normalize(Operation::ReadDir, path.as_ref())?;
+        let entries = self.merged_entries(&path)?;
+        Ok(OverlayReadDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Creates a new, empty directory at the provided path.
+    pub fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = self.normalize(Operation::CreateDir, path.as_ref())?;
+        self.check_name(Operation::CreateDir, &path)?;
+        if self.locate(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "entry already exists",
+            ));
+        }
+        self.copy_up_parent(&path)?;
+        self.upper.create_dir(&path)
+    }
+
+    /// Recursively create a directory and all of its parent components if
+    /// they are missing.
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = self.normalize(Operation::CreateDir, path.as_ref())?;
+        let mut partial = PathBuf::new();
+        for component in path.components() {
+            partial.push(component);
+            if !self.is_dir(&partial) {
+                self.create_dir(&partial)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes a file from the merged tree.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = self.normalize(Operation::RemoveFile, path.as_ref())?;
+        let layer = self.locate(&path)?;
+        if self.layer_view(layer).symlink_metadata(&path)?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "attempted to remove a directory as a file",
+            ));
+        }
+        if layer == Layer::Upper {
+            self.upper.remove_file(&path)?;
+        }
+        self.whiteout(&path)
+    }
+
+    /// Removes an empty directory from the merged tree.
+    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = self.normalize(Operation::RemoveDir, path.as_ref())?;
+        if !self.merged_entries(&path)?.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::DirectoryNotEmpty,
+                "directory not empty",
+            ));
+        }
+        self.remove_upper_dir(&path)?;
+        self.whiteout(&path)
+    }
+
+    /// Removes a directory from the merged tree, after removing all its
+    /// contents.
+    pub fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = self.normalize(Operation::RemoveDirAll, path.as_ref())?;
+        if !self
+            .layer_view(self.locate(&path)?)
+            .symlink_metadata(&path)?
+            .is_dir()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "not a directory",
+            ));
+        }
+        self.remove_upper_dir(&path)?;
+        self.whiteout(&path)
+    }
+
+    /// Rename a file or directory to a new name, replacing the original file
+    /// if `to` already exists.
+    ///
+    /// Entries from the lower layer are copied up first, with all of their
+    /// contents if they're directories.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        let from = self.normalize(Operation::Rename, from.as_ref())?;
+        let to = self.normalize(Operation::Rename, to.as_ref())?;
+        self.check_name(Operation::Rename, &to)?;
+        let from_dir = self
+            .layer_view(self.locate(&from)?)
+            .symlink_metadata(&from)?
+            .is_dir();
+        if from_dir && to.starts_with(&from) && to != from {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "attempted to move a directory into itself",
+            ));
+        }
+        if let Ok(layer) = self.locate(&to) {
+            if to == from {
+                return Ok(());
+            }
+            let to_dir = self.layer_view(layer).symlink_metadata(&to)?.is_dir();
+            match (from_dir, to_dir) {
+                (true, true) if !self.merged_entries(&to)?.is_empty() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::DirectoryNotEmpty,
+                        "directory not empty",
+                    ))
+                }
+                (true, true) => self.remove_upper_dir(&to)?,
+                (true, false) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotADirectory,
+                        "not a directory",
+                    ))
+                }
+                (false, true) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::IsADirectory,
+                        "attempted to replace a directory with a file",
+                    ))
+                }
+                (false, false) => {}
+            }
+            // Hide anything of the lower layer's at `to`, so that a moved
+            // directory doesn't merge with it.
+            self.whiteout(&to)?;
+        }
+        self.copy_up_all(&from)?;
+        self.copy_up_parent(&to)?;
+        self.upper.rename(&from, &self.upper, &to)?;
+        self.whiteout(&from)
+    }
+
+    fn layer_view(&self, layer: Layer) -> &DirView {
+        match layer {
+            Layer::Upper => &self.upper,
+            Layer::Lower => &self.lower,
+        }
+    }
+
+    /// Normalizes `path` lexically, denying paths which lead outside of the
+    /// merged tree.
+    fn normalize(&self, operation: Operation, path: &Path) -> io::Result<PathBuf> {
+        let mut normal = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => normal.push(name),
+                Component::CurDir => {}
+                Component::ParentDir if normal.pop() => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(ViewError::denied(
+                        io::ErrorKind::PermissionDenied,
+                        "a path led outside of the overlay",
+                        operation,
+                        path,
+                        false,
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(normal)
+    }
+
+    /// Denies creating entries whose names would be taken as whiteouts.
+    fn check_name(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        if path.file_name().is_some_and(is_whiteout) {
+            return Err(ViewError::denied(
+                io::ErrorKind::PermissionDenied,
+                "attempt to create an overlay whiteout through an overlay",
+                operation,
+                path,
+                false,
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the layer the entry at the normalized `path` comes from.
+    fn locate(&self, path: &Path) -> io::Result<Layer> {
+        // Whiteouts are hidden from the merged tree.
+        if path
+            .components()
+            .any(|component| is_whiteout(component.as_os_str()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no such file or directory",
+            ));
+        }
+        if self.upper.symlink_metadata(at(path)).is_ok() {
+            return Ok(Layer::Upper);
+        }
+        if !self.lower_hidden(path)? {
+            self.lower.symlink_metadata(at(path))?;
+            return Ok(Layer::Lower);
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no such file or directory",
+        ))
+    }
+
+    /// Tests whether the lower layer's entry at `path` is hidden, by a
+    /// whiteout for it or one of its parents, or by a parent replaced with
+    /// something other than a directory in the upper layer.
+    fn lower_hidden(&self, path: &Path) -> io::Result<bool> {
+        let mut prefix = PathBuf::new();
+        for component in path.components() {
+            if self
+                .upper
+                .try_exists(whiteout_path(&prefix, component.as_os_str()))?
+            {
+                return Ok(true);
+            }
+            prefix.push(component);
+            if prefix != path {
+                if let Ok(metadata) = self.upper.symlink_metadata(&prefix) {
+                    if !metadata.is_dir() {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Hides the lower layer's entry at `path`, if there is one.
+    fn whiteout(&self, path: &Path) -> io::Result<()> {
+        if self.lower.symlink_metadata(path).is_err() || self.lower_hidden(path)? {
+            return Ok(());
+        }
+        self.copy_up_parent(path)?;
+        let name = path.file_name().unwrap();
+        let parent = path.parent().unwrap_or(Path::new(""));
+        self.upper.write(whiteout_path(parent, name), b"")
+    }
+
+    /// Removes the upper layer's directory at `path`, if there is one, with
+    /// its contents and whiteouts.
+    fn remove_upper_dir(&self, path: &Path) -> io::Result<()> {
+        match self.upper.symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => self.upper.remove_dir_all(path),
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "not a directory",
+            )),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Prepares to write to the file at `path`, copying it up if it's in
+    /// the lower layer, or creating its parent in the upper layer if it
+    /// doesn't exist.
+    fn prepare_write(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        match self.locate(path) {
+            Ok(Layer::Upper) => Ok(()),
+            Ok(Layer::Lower) => self.copy_up(path),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.check_name(operation, path)?;
+                self.copy_up_parent(path)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Ensures that the parent of `path` is a directory in the upper layer,
+    /// copying it and its parents up as needed.
+    fn copy_up_parent(&self, path: &Path) -> io::Result<()> {
+        let mut prefix = PathBuf::new();
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+        for component in parent.components() {
+            prefix.push(component);
+            match self.locate(&prefix)? {
+                Layer::Upper => {}
+                Layer::Lower => self.copy_up(&prefix)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the lower layer's entry at `path` up to the upper layer, without
+    /// the contents of a directory.
+    fn copy_up(&self, path: &Path) -> io::Result<()> {
+        self.copy_up_parent(path)?;
+        let metadata = self.lower.symlink_metadata(path)?;
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            self.upper.create_dir(path)?;
+        } else if file_type.is_symlink() {
+            self.copy_up_symlink(path)?;
+            return Ok(());
+        } else {
+            let mut from = self.lower.open(path)?;
+            let mut to = self.upper.create(path)?;
+            io::copy(&mut from, &mut to)?;
+        }
+        #[cfg(not(target_os = "wasi"))]
+        self.upper.set_permissions(path, metadata.permissions())?;
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    fn copy_up_symlink(&self, path: &Path) -> io::Result<()> {
+        let target = self.lower.read_link_contents(path)?;
+        self.upper.symlink_contents(target, path)
+    }
+
+    #[cfg(windows)]
+    fn copy_up_symlink(&self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "copying up symbolic links isn't supported on Windows",
+        ))
+    }
+
+    /// Copies the entry at `path` up to the upper layer, with all of the
+    /// contents of a directory.
+    fn copy_up_all(&self, path: &Path) -> io::Result<()> {
+        let layer = self.locate(path)?;
+        if layer == Layer::Lower {
+            self.copy_up(path)?;
+        }
+        if self.upper.symlink_metadata(path)?.is_dir() {
+            for entry in self.merged_entries(path)? {
+                if entry.layer == Layer::Lower {
+                    self.copy_up_all(&path.join(&entry.name))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the entries of the merged directory at `path`, sorted by name.
+    pub(crate) fn merged_entries(&self, path: &Path) -> io::Result<Vec<OverlayDirEntry>> {
+        let layer = self.locate(path)?;
+        if !self.layer_view(layer).metadata(at(path))?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "not a directory",
+            ));
+        }
+        let mut entries = Vec::new();
+        let mut hidden = Vec::new();
+        if layer == Layer::Upper {
+            for entry in self.upper.read_dir(at(path))? {
+                let entry = entry?;
+                let name = entry.file_name();
+                match name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix(WHITEOUT_PREFIX))
+                {
+                    Some(name) => hidden.push(OsString::from(name)),
+                    None => entries.push(OverlayDirEntry {
+                        metadata: entry.metadata()?,
+                        name,
+                        layer: Layer::Upper,
+                    }),
+                }
+            }
+        }
+        let lower_dir = !self.lower_hidden(path)?
+            && self
+                .lower
+                .metadata(at(path))
+                .is_ok_and(|metadata| metadata.is_dir());
+        if lower_dir {
+            for entry in self.lower.read_dir(at(path))? {
+                let entry = entry?;
+                let name = entry.file_name();
+                if is_whiteout(&name)
+                    || hidden.contains(&name)
+                    || entries.iter().any(|upper| upper.name == name)
+                {
+                    continue;
+                }
+                entries.push(OverlayDirEntry {
+                    metadata: entry.metadata()?,
+                    name,
+                    layer: Layer::Lower,
+                });
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
+
+impl fmt::Debug for OverlayDirView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverlayDirView")
+            .field("lower", &self.lower)
+            .field("upper", &self.upper)
+            .finish()
+    }
+}
+
+/// Returns `path`, or `.` if it's empty, as views expect for their own
+/// directory.
+pub(crate) fn at(path: &Path) -> &Path {
+    if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    }
+}
+
+fn is_whiteout(name: &OsStr) -> bool {
+    name.to_str()
+        .is_some_and(|name| name.starts_with(WHITEOUT_PREFIX))
+}
+
+fn whiteout_path(parent: &Path, name: &OsStr) -> PathBuf {
+    let mut whiteout = OsString::from(WHITEOUT_PREFIX);
+    whiteout.push(name);
+    parent.join(whiteout)
+}
+
+/// An iterator over the entries of a directory in an [`OverlayDirView`].
+#[derive(Debug)]
+pub struct OverlayReadDir {
+    entries: vec::IntoIter<OverlayDirEntry>,
+}
+
+impl Iterator for OverlayReadDir {
+    type Item = io::Result<OverlayDirEntry>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Ok)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// An entry of a directory in an [`OverlayDirView`].
+#[derive(Debug)]
+pub struct OverlayDirEntry {
+    name: OsString,
+    metadata: Metadata,
+    layer: Layer,
+}
+
+impl OverlayDirEntry {
+    /// Returns the bare file name of this entry, without any other leading
+    /// path components.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the file type for the file that this entry points at.
+    #[inline]
+    pub fn file_type(&self) -> io::Result<FileType> {
+        Ok(self.metadata.file_type())
+    }
+
+    /// Returns the metadata for the file that this entry points at, as it
+    /// was when the directory was read.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+
+    /// Returns `true` if this entry is in the upper layer, because it was
+    /// created or modified through the overlay.
+    #[inline]
+    pub fn is_upper(&self) -> bool {
+        self.layer == Layer::Upper
+    }
+}
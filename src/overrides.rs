@@ -0,0 +1,235 @@
+use crate::backend::Backend;
+use crate::policy::Policy;
+use crate::{Operation, ViewKind};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// The kinds of the subtrees of a view which differ from the kind of the
+/// view itself, set with [`DirViewBuilder::override_view_kind`].
+///
+/// [`DirViewBuilder::override_view_kind`]: crate::DirViewBuilder::override_view_kind
+#[derive(Clone, Debug)]
+pub(crate) struct KindOverrides {
+    /// The subtrees, relative to the view's directory, and their kinds.
+    table: Vec<(PathBuf, ViewKind)>,
+
+    /// The narrowest of the kinds, used where the location of a path
+    /// within the view isn't known.
+    floor: ViewKind,
+}
+
+impl KindOverrides {
+    /// Returns the overrides for a view of kind `view_kind` with the given
+    /// subtrees, or `None` if there are none.
+    pub(crate) fn new(view_kind: ViewKind, table: &[(PathBuf, ViewKind)]) -> Option<Arc<Self>> {
+        if table.is_empty() {
+            return None;
+        }
+        let table = table
+            .iter()
+            .map(|(prefix, view_kind)| (normalize(prefix), *view_kind))
+            .collect::<Vec<_>>();
+        let floor = table
+            .iter()
+            .fold(view_kind, |floor, (_, view_kind)| floor.meet(*view_kind));
+        Some(Arc::new(Self { table, floor }))
+    }
+}
+
+impl Policy {
+    /// Returns the kind of the subtree at `location`, relative to the
+    /// directory of a view of kind `view_kind` with this policy, or the
+    /// narrowest kind in the view if `location` is unknown.
+    pub(crate) fn kind_at(&self, view_kind: ViewKind, location: Option<&Path>) -> ViewKind {
+        let Some(overrides) = &self.overrides else {
+            return view_kind;
+        };
+        let Some(location) = location else {
+            return overrides.floor.meet(view_kind);
+        };
+        let location = normalize(location);
+        overrides
+            .table
+            .iter()
+            .filter(|(prefix, _)| location.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components().count())
+            .map_or(view_kind, |(_, view_kind)| *view_kind)
+    }
+
+    /// Returns `view_kind` narrowed to the kinds of any subtrees at or within
+    /// `location`, for operations which change everything there.
+    pub(crate) fn kind_within(&self, view_kind: ViewKind, location: &Path) -> ViewKind {
+        let Some(overrides) = &self.overrides else {
+            return view_kind;
+        };
+        let location = normalize(location);
+        overrides
+            .table
+            .iter()
+            .filter(|(prefix, _)| prefix.starts_with(&location))
+            .fold(view_kind, |view_kind, (_, kind)| view_kind.meet(*kind))
+    }
+
+    /// Returns the policy for views of the directory at `location`, relative
+    /// to the directory of a view with `policy`, keeping the overrides of
+    /// the subtrees within it.
+    pub(crate) fn for_location(policy: &Arc<Policy>, location: Option<&Path>) -> Arc<Self> {
+        let Some(overrides) = &policy.overrides else {
+            return Policy::for_subdir(policy);
+        };
+        let table = match location {
+            Some(location) => {
+                let location = normalize(location);
+                overrides
+                    .table
+                    .iter()
+                    .filter_map(|(prefix, view_kind)| {
+                        let prefix = prefix.strip_prefix(&location).ok()?;
+                        (!prefix.as_os_str().is_empty()).then(|| (prefix.to_owned(), *view_kind))
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Arc::new(Self {
+            cache: None,
+            overrides: (!table.is_empty()).then(|| {
+                Arc::new(KindOverrides {
+                    table,
+                    floor: overrides.floor,
+                })
+            }),
+            ..Policy::clone(policy)
+        })
+    }
+
+    /// Returns `policy` with the kinds of its overrides narrowed to no
+    /// broader than `view_kind`, for a view narrowed to `view_kind`.
+    pub(crate) fn narrowed(policy: Arc<Policy>, view_kind: ViewKind) -> Arc<Self> {
+        let Some(overrides) = &policy.overrides else {
+            return policy;
+        };
+        let overrides = KindOverrides {
+            table: overrides
+                .table
+                .iter()
+                .map(|(prefix, kind)| (prefix.clone(), kind.meet(view_kind)))
+                .collect(),
+            floor: overrides.floor.meet(view_kind),
+        };
+        Arc::new(Self {
+            overrides: Some(Arc::new(overrides)),
+            ..Policy::clone(&policy)
+        })
+    }
+
+    /// Returns the location of `path` within `dir`, the directory of a view
+    /// with this policy, if the view has overrides and it can be determined.
+    pub(crate) fn locate<B: Backend + ?Sized>(&self, dir: &B, path: &Path) -> Option<PathBuf> {
+        self.overrides.as_ref()?;
+        locate(dir, path)
+    }
+
+    /// Returns the kind governing `operation` on `path` in `dir`, a view of
+    /// kind `view_kind` with this policy.
+    ///
+    /// Changes to `path` are changes to the directory containing it, or, for
+    /// operations which create something inside the directory at `path`, to
+    /// that directory. They're also changes to any subtrees within `path`,
+    /// which may be removed or moved along with it. Operations which follow
+    /// a symbolic link at `path` also change wherever it points. The
+    /// narrowest of these kinds governs the operation, and where a location
+    /// can't be determined, the narrowest kind in the view is used.
+    pub(crate) fn kind_for<B: Backend + ?Sized>(
+        &self,
+        view_kind: ViewKind,
+        dir: &B,
+        operation: Operation,
+        path: &Path,
+    ) -> ViewKind {
+        if self.overrides.is_none() {
+            return view_kind;
+        }
+        if matches!(
+            operation,
+            Operation::CreateTempDir | Operation::CreateTempFile
+        ) {
+            return self.kind_at(view_kind, locate(dir, path).as_deref());
+        }
+        let kind = match (path.parent(), path.components().next_back()) {
+            (Some(parent), Some(Component::Normal(name))) => match locate(dir, parent) {
+                Some(parent) => {
+                    self.kind_within(self.kind_at(view_kind, Some(&parent)), &parent.join(name))
+                }
+                None => self.kind_at(view_kind, None),
+            },
+            _ => self.kind_at(view_kind, None),
+        };
+        if !follows(operation) {
+            return kind;
+        }
+        let target = match dir.canonicalize(path) {
+            Ok(resolved) => resolved.parent().map(Path::to_owned),
+            // `path` doesn't exist yet, so only its parent changes.
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !exists(dir, path) => return kind,
+            Err(_) => None,
+        };
+        kind.meet(self.kind_at(view_kind, target.as_deref()))
+    }
+}
+
+/// Returns the location of `path` within `dir`, resolving symbolic links,
+/// where `path` may not exist yet, or `None` if it can't be determined.
+fn locate<B: Backend + ?Sized>(dir: &B, path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if existing.as_os_str().is_empty() {
+            return Some(missing.iter().rev().collect());
+        }
+        match dir.canonicalize(existing) {
+            Ok(mut resolved) => {
+                resolved.extend(missing.iter().rev());
+                return Some(resolved);
+            }
+            // A dangling symbolic link may point anywhere.
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !exists(dir, existing) => {}
+            Err(_) => return None,
+        }
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(Component::Normal(name))) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Tests whether anything, such as a dangling symbolic link, is at `path`.
+fn exists<B: Backend + ?Sized>(dir: &B, path: &Path) -> bool {
+    dir.entry_type(path).is_ok()
+}
+
+/// Tests whether `operation` follows a symbolic link in the last component
+/// of its path.
+fn follows(operation: Operation) -> bool {
+    !matches!(
+        operation,
+        Operation::HardLink
+            | Operation::RemoveDir
+            | Operation::RemoveDirAll
+            | Operation::RemoveFile
+            | Operation::Rename
+            | Operation::Symlink
+    )
+}
+
+/// Returns `path` without `.` components, so that it can be compared with
+/// prefixes.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
@@ -0,0 +1,149 @@
+use crate::error::no_file_name;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, OpenOptions, OpenOptionsExt};
+use rustix::fs::{chownat, AtFlags, Gid, OFlags, Uid};
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::io;
+use std::os::fd::AsFd;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Changes the owner and group of the file at `path`.
+    ///
+    /// Passing `None` for `uid` or `gid` leaves it unchanged. This follows
+    /// symbolic links, within `self`, and requires read access to the file.
+    ///
+    /// This corresponds to [`std::os::unix::fs::chown`], but only accesses
+    /// paths relative to `self`.
+    pub fn set_owner<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> io::Result<()> {
+        self.audited(Operation::SetOwner, path, |path| {
+            if self.dry_run(Operation::SetOwner, path, None)? {
+                return Ok(());
+            }
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            std::os::unix::fs::fchown(&file, uid, gid)
+        })
+    }
+
+    /// Changes the owner and group of the file at `path`, without following
+    /// a symbolic link in the last component of `path`.
+    ///
+    /// Passing `None` for `uid` or `gid` leaves it unchanged.
+    ///
+    /// This corresponds to [`std::os::unix::fs::lchown`], but only accesses
+    /// paths relative to `self`.
+    pub fn set_owner_nofollow<P: AsRef<Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> io::Result<()> {
+        self.audited(Operation::SetOwner, path, |path| {
+            if self.dry_run(Operation::SetOwner, path, None)? {
+                return Ok(());
+            }
+            let name = path.file_name().ok_or_else(no_file_name)?;
+            match path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                Some(parent) => {
+                    chown_nofollow(&self.dir.borrow().open_dir(parent)?, name, uid, gid)
+                }
+                None => chown_nofollow(self.dir.borrow(), name, uid, gid),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Changes the owner and group of the file at `path`.
+    ///
+    /// Passing `None` for `uid` or `gid` leaves it unchanged. This follows
+    /// symbolic links, within `self`, and requires read access to the file.
+    ///
+    /// This corresponds to [`std::os::unix::fs::chown`], but only accesses
+    /// paths relative to `self`.
+    pub fn set_owner<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> io::Result<()> {
+        self.audited(Operation::SetOwner, path, |path| {
+            if self.dry_run(Operation::SetOwner, path, None)? {
+                return Ok(());
+            }
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            std::os::unix::fs::fchown(&file, uid, gid)
+        })
+    }
+
+    /// Changes the owner and group of the file at `path`, without following
+    /// a symbolic link in the last component of `path`.
+    ///
+    /// Passing `None` for `uid` or `gid` leaves it unchanged.
+    ///
+    /// This corresponds to [`std::os::unix::fs::lchown`], but only accesses
+    /// paths relative to `self`.
+    pub fn set_owner_nofollow<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> io::Result<()> {
+        self.audited(Operation::SetOwner, path, |path| {
+            if self.dry_run(Operation::SetOwner, path, None)? {
+                return Ok(());
+            }
+            let name = path.file_name().ok_or_else(no_file_name)?;
+            match path.parent().filter(|parent| !parent.as_str().is_empty()) {
+                Some(parent) => {
+                    chown_nofollow(&self.dir.borrow().open_dir(parent)?, name, uid, gid)
+                }
+                None => chown_nofollow(self.dir.borrow(), name, uid, gid),
+            }
+        })
+    }
+}
+
+/// Options for opening a file to query or change its metadata. `O_NONBLOCK`
+/// avoids blocking on FIFOs.
+pub(crate) fn metadata_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options
+        .read(true)
+        .custom_flags(OFlags::NONBLOCK.bits() as i32);
+    options
+}
+
+fn chown_nofollow<Fd: AsFd, N: AsRef<OsStr>>(
+    dir: Fd,
+    name: N,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> io::Result<()> {
+    // SAFETY: `fchownat` accepts any ID value; an invalid one is reported as
+    // an error.
+    let uid = uid.map(|uid| unsafe { Uid::from_raw(uid) });
+    let gid = gid.map(|gid| unsafe { Gid::from_raw(gid) });
+    Ok(chownat(
+        dir,
+        name.as_ref(),
+        uid,
+        gid,
+        AtFlags::SYMLINK_NOFOLLOW,
+    )?)
+}
@@ -0,0 +1,446 @@
+use crate::audit::{AuditEvent, AuditHook};
+use crate::budget::Permit;
+use crate::cache::MetadataCache;
+use crate::coordinate::Coordinator;
+use crate::dry_run::DryRunLog;
+#[cfg(feature = "unicode-normalization")]
+use crate::normalize::NormalizationForm;
+use crate::overrides::KindOverrides;
+use crate::portable;
+use crate::resolve;
+use crate::sanitize;
+use crate::stats::Counters;
+use crate::throttle::RateLimiter;
+use crate::trash::Trash;
+use crate::{HandleBudget, Operation, ViewError, ViewKind};
+use cap_std::fs::Dir;
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+/// Restrictions which a view imposes in addition to its [`ViewKind`].
+///
+/// This is shared between a view and all of the views, iterators, and entries
+/// derived from it.
+///
+/// [`ViewKind`]: crate::ViewKind
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Policy {
+    /// Deny creating symbolic links.
+    pub(crate) deny_symlinks: bool,
+
+    /// Omit entries whose names start with `.` from directory iteration.
+    pub(crate) hide_hidden: bool,
+
+    /// The maximum number of directory levels views may descend below the
+    /// view they were built from.
+    pub(crate) max_depth: Option<usize>,
+
+    /// The maximum length of paths used through the view, in bytes.
+    pub(crate) max_path_len: Option<usize>,
+
+    /// The maximum number of components of paths used through the view.
+    pub(crate) max_path_components: Option<usize>,
+
+    /// The maximum number of symbolic links resolving a path may traverse.
+    pub(crate) max_symlink_traversals: Option<usize>,
+
+    /// Deny connecting and sending to Unix-domain sockets.
+    pub(crate) deny_unix_connect: bool,
+
+    /// Deny spawning processes in the view's directory.
+    pub(crate) deny_exec: bool,
+
+    /// Deny opening paths whose resolution crosses a mount point.
+    pub(crate) same_filesystem: bool,
+
+    /// Deny opening paths whose resolution passes through a junction or
+    /// other reparse point. This is only set on Windows.
+    pub(crate) deny_reparse_points: bool,
+
+    /// Deny paths with names which aren't valid on Windows.
+    pub(crate) portable_names: bool,
+
+    /// Deny paths which [`sanitize_path`] rejects.
+    ///
+    /// [`sanitize_path`]: crate::sanitize_path
+    pub(crate) strict_paths: bool,
+
+    /// Deny creating symbolic links whose targets are absolute or resolve
+    /// lexically to somewhere outside of the view.
+    pub(crate) confine_symlink_targets: bool,
+
+    /// Match path components case-insensitively where they don't name an
+    /// entry exactly.
+    pub(crate) case_insensitive: bool,
+
+    /// The Unicode normalization form of path components and of the names
+    /// of entries.
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) normalization: Option<NormalizationForm>,
+
+    /// The kinds of subtrees of the view which differ from its own kind.
+    pub(crate) overrides: Option<Arc<KindOverrides>>,
+
+    /// The kind of errors reporting denials, if not `PermissionDenied`.
+    pub(crate) denial_kind: Option<io::ErrorKind>,
+
+    /// Include the operation and path in errors reporting denials.
+    pub(crate) denial_context: bool,
+
+    /// A hook called with every operation performed through the view.
+    pub(crate) audit: Option<AuditHook>,
+
+    /// Counters of operations performed through the view.
+    pub(crate) stats: Option<Arc<Counters>>,
+
+    /// The limit on the rate of reads and writes through the view.
+    pub(crate) rate_limit: Option<RateLimiter>,
+
+    /// The limit on the number of handles open through the view.
+    pub(crate) handle_budget: Option<HandleBudget>,
+
+    /// The handle from the budget which the view's directory holds, shared
+    /// with the views, iterators, and entries which share the directory.
+    pub(crate) _permit: Option<Arc<Permit>>,
+
+    /// The registry of intent locks shared with other views.
+    pub(crate) coordinator: Option<Coordinator>,
+
+    /// The changes recorded by dry-run views.
+    pub(crate) dry_run_log: DryRunLog,
+
+    /// The size of the buffer for reading batches of directory entries, if
+    /// not the platform's default.
+    pub(crate) read_dir_buffer_size: Option<usize>,
+
+    /// Read many files at a time with io_uring.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub(crate) io_uring: bool,
+
+    /// Memoized metadata, for views created with `with_cache`.
+    pub(crate) cache: Option<Arc<MetadataCache>>,
+
+    /// The directory removed entries are moved into, for views created
+    /// with `with_trash`.
+    pub(crate) trash: Option<Arc<Trash>>,
+}
+
+impl Policy {
+    /// Test whether this policy imposes no restrictions and audits nothing,
+    /// so that views may expose their underlying `Dir`.
+    pub(crate) fn is_unrestricted(&self) -> bool {
+        !self.deny_symlinks
+            && !self.hide_hidden
+            && self.max_depth.is_none()
+            && self.max_path_len.is_none()
+            && self.max_path_components.is_none()
+            && self.max_symlink_traversals.is_none()
+            && !self.deny_unix_connect
+            && !self.deny_exec
+            && !self.same_filesystem
+            && self.audit.is_none()
+            && self.stats.is_none()
+            && self.rate_limit.is_none()
+            && self.handle_budget.is_none()
+            && self.trash.is_none()
+            && self.coordinator.is_none()
+            && self.cache.is_none()
+            && !self.deny_reparse_points
+            && !self.portable_names
+            && !self.strict_paths
+            && !self.confine_symlink_targets
+            && self.overrides.is_none()
+            && !self.case_insensitive
+            && !self.normalizes()
+    }
+
+    /// Test whether path components and the names of entries are
+    /// normalized.
+    pub(crate) fn normalizes(&self) -> bool {
+        #[cfg(feature = "unicode-normalization")]
+        return self.normalization.is_some();
+        #[cfg(not(feature = "unicode-normalization"))]
+        return false;
+    }
+
+    /// Returns `name` in the view's normalization form, if it has one and
+    /// `name` is valid UTF-8.
+    pub(crate) fn normalize_name<'a>(&self, name: &'a OsStr) -> Cow<'a, OsStr> {
+        match name.to_str() {
+            Some(str) => match self.normalize_str(str) {
+                Cow::Borrowed(_) => Cow::Borrowed(name),
+                Cow::Owned(normalized) => Cow::Owned(normalized.into()),
+            },
+            None => Cow::Borrowed(name),
+        }
+    }
+
+    /// Returns `name` in the view's normalization form, if it has one.
+    pub(crate) fn normalize_str<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(form) = self.normalization {
+            return form.normalize(name);
+        }
+        Cow::Borrowed(name)
+    }
+
+    /// Test whether operations need to be reported to [`Self::audit`],
+    /// including whether a `tracing` subscriber is interested in them.
+    pub(crate) fn is_observed(&self) -> bool {
+        #[cfg(feature = "tracing")]
+        if crate::audit::is_traced() {
+            return true;
+        }
+        self.audit.is_some() || self.stats.is_some()
+    }
+
+    /// Report an operation to the audit hook and the stats counters, if
+    /// there are any, and to `tracing`, if enabled.
+    pub(crate) fn audit<T>(
+        &self,
+        view_kind: ViewKind,
+        operation: Operation,
+        path: &Path,
+        target: Option<&Path>,
+        result: &io::Result<T>,
+    ) {
+        if let Some(stats) = &self.stats {
+            stats.record(operation, result.as_ref().map(drop));
+        }
+        if cfg!(feature = "tracing") || self.audit.is_some() {
+            let event = AuditEvent {
+                operation,
+                path,
+                target,
+                view_kind,
+                outcome: result.as_ref().map(drop),
+            };
+            #[cfg(feature = "tracing")]
+            crate::audit::trace(&event);
+            if let Some(audit) = &self.audit {
+                audit.call(&event);
+            }
+        }
+    }
+
+    /// Count bytes read through the view, if collecting stats, and wait
+    /// until they're within the view's rate limit, if it has one.
+    pub(crate) fn account_bytes_read(&self, len: usize) {
+        if let Some(stats) = &self.stats {
+            stats.add_bytes_read(len);
+        }
+        if let Some(limiter) = &self.rate_limit {
+            limiter.read(len);
+        }
+    }
+
+    /// Count bytes written through the view, if collecting stats, and wait
+    /// until they're within the view's rate limit, if it has one.
+    pub(crate) fn account_bytes_written(&self, len: usize) {
+        if let Some(stats) = &self.stats {
+            stats.add_bytes_written(len);
+        }
+        if let Some(limiter) = &self.rate_limit {
+            limiter.write(len);
+        }
+    }
+
+    /// Construct an error reporting that `operation` on `path` was denied.
+    pub(crate) fn denied(
+        &self,
+        message: &'static str,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Error {
+        ViewError::denied(
+            self.denial_kind.unwrap_or(io::ErrorKind::PermissionDenied),
+            message,
+            operation,
+            path,
+            self.denial_context,
+        )
+        .into()
+    }
+
+    /// Check whether a view of kind `parent` may derive a view of kind
+    /// `view_kind` for the directory at `path`.
+    pub(crate) fn narrow(
+        &self,
+        parent: ViewKind,
+        view_kind: ViewKind,
+        path: &Path,
+    ) -> io::Result<ViewKind> {
+        parent.narrow(view_kind).ok_or_else(|| {
+            self.denied(
+                "attempt to derive a view broader than its parent view",
+                Operation::OpenDir,
+                path,
+            )
+        })
+    }
+
+    /// Check whether creating a symbolic link at `path` is permitted.
+    pub(crate) fn check_symlink(&self, path: &Path) -> io::Result<()> {
+        if self.deny_symlinks {
+            return Err(self.denied(
+                "attempt to create a symbolic link through a view that denies symlinks",
+                Operation::Symlink,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check whether creating a symbolic link at `path` to `target` is
+    /// permitted, where `path`'s parent is `depth` levels below the root of
+    /// the view.
+    pub(crate) fn check_symlink_target(
+        &self,
+        path: &Path,
+        target: &Path,
+        depth: usize,
+    ) -> io::Result<()> {
+        if self.confine_symlink_targets && resolve::escapes_lexically(depth, target) {
+            return Err(self.denied(
+                "attempt to create a symbolic link to a target outside of a view which confines symlink targets",
+                Operation::Symlink,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check whether `path` may be used by `operation`, if names must be
+    /// portable to Windows.
+    #[inline]
+    pub(crate) fn check_portable(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        if self.portable_names {
+            if let Err(message) = portable::check_path(path) {
+                return Err(self.denied(message, operation, path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether `path` may be used by `operation`, if paths must pass
+    /// [`sanitize_path`] unchanged apart from `.` components.
+    ///
+    /// [`sanitize_path`]: crate::sanitize_path
+    #[inline]
+    pub(crate) fn check_strict(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        if self.strict_paths {
+            if let Err(error) = sanitize::sanitize_path(path) {
+                return Err(self.denied(error.message(), operation, path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether connecting to a Unix-domain socket at `path` is
+    /// permitted.
+    pub(crate) fn check_unix_connect(&self, path: &Path) -> io::Result<()> {
+        if self.deny_unix_connect {
+            return Err(self.denied(
+                "attempt to connect to a socket through a view that denies socket connections",
+                Operation::ConnectUnixSocket,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check whether spawning a process in the directory at `path` is
+    /// permitted.
+    pub(crate) fn check_exec(&self, path: &Path) -> io::Result<()> {
+        if self.deny_exec {
+            return Err(self.denied(
+                "attempt to spawn a process through a view that denies spawning processes",
+                Operation::Spawn,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compute the depth of a directory at `path` in `dir`, a view at
+    /// `depth`, failing if it exceeds the maximum depth.
+    ///
+    /// Symbolic links can lead deeper than `path` looks, so if there's a
+    /// maximum depth, `path` is also checked as it resolves.
+    pub(crate) fn descend(
+        &self,
+        dir: &Dir,
+        depth: usize,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<usize> {
+        let base = depth;
+        let mut depth = depth;
+        for component in path.components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::ParentDir => depth = depth.saturating_sub(1),
+                Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            }
+        }
+        self.check_depth(depth, operation, path)?;
+        if self.max_depth.is_some() {
+            // Paths which don't resolve fail when they're opened.
+            if let Ok(resolved) = dir.canonicalize(path) {
+                depth = base + resolve::depth(&resolved);
+                self.check_depth(depth, operation, path)?;
+            }
+        }
+        Ok(depth)
+    }
+
+    /// Fail if a directory entry at `depth`, which is a symbolic link if
+    /// `symlink`, may not be opened as a directory.
+    ///
+    /// The depth a link leads to isn't known without the view it's in, so
+    /// directories can't be opened through links in entries if there's a
+    /// maximum depth.
+    pub(crate) fn check_entry_depth(
+        &self,
+        depth: usize,
+        symlink: bool,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<()> {
+        self.check_depth(depth, operation, path)?;
+        if symlink && self.max_depth.is_some() {
+            return Err(self.denied(
+                "attempt to open a directory through a symbolic link in a view with a maximum depth",
+                operation,
+                path,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail if `depth`, the depth of a directory at `path`, exceeds the
+    /// maximum depth.
+    pub(crate) fn check_depth(
+        &self,
+        depth: usize,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<()> {
+        match self.max_depth {
+            Some(max_depth) if depth > max_depth => Err(self.denied(
+                "attempt to descend past the maximum depth of a view",
+                operation,
+                path,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Test whether a file name follows the Unix convention for hidden files.
+pub(crate) fn is_dot_file(name: &[u8]) -> bool {
+    name.first() == Some(&b'.')
+}
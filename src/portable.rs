@@ -0,0 +1,75 @@
+use std::path::{Component, Path};
+
+/// The maximum length of a file name on Windows, in UTF-16 code units.
+const MAX_NAME_LEN: usize = 255;
+
+/// The maximum length of a path on Windows, in UTF-16 code units, which is
+/// only reachable with `\\?\` paths.
+const MAX_PATH_LEN: usize = 32_767;
+
+/// Names which Windows reserves for devices, in every directory and with
+/// any extension.
+const RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+/// Checks whether `path` could be used on Windows as well as on other
+/// platforms, returning a description of the problem if it couldn't.
+///
+/// Only normal components are checked, so absolute paths and `..` are left
+/// for the view to deny or resolve.
+pub(crate) fn check_path(path: &Path) -> Result<(), &'static str> {
+    let mut len = 0;
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        let name = name
+            .to_str()
+            .ok_or("attempt to use a file name which isn't valid Unicode")?;
+        let name_len = name.encode_utf16().count();
+        if name_len > MAX_NAME_LEN {
+            return Err("attempt to use a file name longer than Windows permits");
+        }
+        len += name_len + 1;
+        check_name(name)?;
+    }
+    if len > MAX_PATH_LEN {
+        return Err("attempt to use a path longer than Windows permits");
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<(), &'static str> {
+    if name
+        .chars()
+        .any(|c| c < ' ' || matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*'))
+    {
+        return Err("attempt to use a file name with characters which Windows doesn't permit");
+    }
+    if name.ends_with(['.', ' ']) {
+        return Err("attempt to use a file name ending in a dot or space");
+    }
+    if is_reserved(name) {
+        return Err("attempt to use a file name which Windows reserves for a device");
+    }
+    Ok(())
+}
+
+/// Test whether `name` refers to a device on Windows, such as `NUL`,
+/// `nul.txt`, or `COM1`.
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        return true;
+    }
+    let mut chars = stem.chars();
+    let prefix = chars.by_ref().take(3).collect::<String>();
+    let digit = chars.as_str();
+    (prefix.eq_ignore_ascii_case("COM") || prefix.eq_ignore_ascii_case("LPT"))
+        && matches!(
+            digit,
+            "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "¹" | "²" | "³"
+        )
+}
@@ -0,0 +1,76 @@
+use crate::{DirView, ViewKind};
+use cap_std::AmbientAuthority;
+use std::io;
+
+impl DirView {
+    /// Opens each of the directories the host preopened for this guest, as
+    /// views of kind `view_kind`, returning them along with the names the
+    /// host gave them, such as `/data` or `.`.
+    ///
+    /// The preopens are listed with `fd_prestat_get` on WASI Preview 1, and
+    /// with the `wasi:filesystem/preopens` interface on Preview 2. Each is
+    /// then opened by name, as with [`Self::open_ambient_dir`], so that the
+    /// view holds a descriptor the libc can resolve paths relative to.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any preopened
+    /// directory.
+    pub fn preopens(
+        view_kind: ViewKind,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Vec<(String, Self)>> {
+        preopen_names()?
+            .into_iter()
+            .map(|name| {
+                let view = Self::open_ambient_dir(&name, view_kind, ambient_authority)?;
+                Ok((name, view))
+            })
+            .collect()
+    }
+}
+
+/// Returns the names of the preopened directories.
+#[cfg(not(target_env = "p2"))]
+fn preopen_names() -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    // Preopens are numbered consecutively after the standard streams.
+    for fd in 3.. {
+        // SAFETY: `fd_prestat_get` only writes the returned `Prestat`.
+        let prestat = match unsafe { wasi::fd_prestat_get(fd) } {
+            Ok(prestat) => prestat,
+            Err(wasi::ERRNO_BADF) => break,
+            Err(errno) => return Err(io::Error::from_raw_os_error(errno.raw().into())),
+        };
+        if prestat.tag != wasi::PREOPENTYPE_DIR.raw() {
+            continue;
+        }
+        // SAFETY: Directories are the only kind of preopen, so `dir` is the
+        // field the tag says is set.
+        let mut name = vec![0; unsafe { prestat.u.dir.pr_name_len }];
+        // SAFETY: `name` has room for the name's length.
+        unsafe { wasi::fd_prestat_dir_name(fd, name.as_mut_ptr(), name.len()) }
+            .map_err(|errno| io::Error::from_raw_os_error(errno.raw().into()))?;
+        names.push(String::from_utf8(name).map_err(|_| non_utf8_name())?);
+    }
+    Ok(names)
+}
+
+/// Returns the names of the preopened directories.
+#[cfg(target_env = "p2")]
+fn preopen_names() -> io::Result<Vec<String>> {
+    // The descriptors are new handles to the preopens, and are closed as
+    // they're dropped; the views open their own, by name.
+    Ok(wasip2::filesystem::preopens::get_directories()
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect())
+}
+
+#[cfg(not(target_env = "p2"))]
+fn non_utf8_name() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "preopened directory name is not valid UTF-8",
+    )
+}
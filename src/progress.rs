@@ -0,0 +1,199 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{error, fmt, io};
+
+/// Progress through a copy or sync, reported to callbacks set with
+/// [`CopyOptions::progress`] and similar methods.
+///
+/// [`CopyOptions::progress`]: crate::CopyOptions::progress
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Progress {
+    bytes: u64,
+    files: u64,
+}
+
+impl Progress {
+    /// Returns the number of bytes copied so far.
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the number of files copied so far.
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+}
+
+/// A token for cancelling copies and syncs, possibly from another thread.
+///
+/// Cancelled operations stop between files, or between chunks of large
+/// files, and fail with a [`Cancelled`] error.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a new token, which isn't cancelled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the operations using this token.
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Tests whether this token has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The error reported by a cancelled copy or sync.
+///
+/// This is returned inside an [`io::Error`] of kind
+/// [`io::ErrorKind::Interrupted`], and can be recovered with
+/// [`Cancelled::from_io_error`] to find how far the operation got.
+#[derive(Clone, Debug)]
+pub struct Cancelled {
+    progress: Progress,
+}
+
+impl Cancelled {
+    /// Returns the progress made before the operation was cancelled.
+    #[inline]
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Returns the `Cancelled` error within `error`, if it reports a
+    /// cancellation.
+    #[inline]
+    pub fn from_io_error(error: &io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "operation cancelled after copying {} files and {} bytes",
+            self.progress.files, self.progress.bytes
+        )
+    }
+}
+
+impl error::Error for Cancelled {}
+
+/// The progress callback and cancellation token of an options struct.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Monitor {
+    hook: Option<ProgressHook>,
+    cancel: Option<CancellationToken>,
+}
+
+impl Monitor {
+    pub(crate) fn progress(&mut self, hook: impl Fn(&Progress) + Send + Sync + 'static) {
+        self.hook = Some(ProgressHook(Arc::new(hook)));
+    }
+
+    pub(crate) fn cancel(&mut self, token: CancellationToken) {
+        self.cancel = Some(token);
+    }
+}
+
+#[derive(Clone)]
+struct ProgressHook(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressHook").finish_non_exhaustive()
+    }
+}
+
+/// Tracks the progress of one operation, reporting it to a [`Monitor`].
+#[derive(Default)]
+pub(crate) struct Tracker<'a> {
+    monitor: Option<&'a Monitor>,
+    progress: Progress,
+
+    /// The progress of the whole operation, when it's split between
+    /// trackers on several threads.
+    shared: Option<&'a Mutex<Progress>>,
+}
+
+impl<'a> Tracker<'a> {
+    pub(crate) fn new(monitor: &'a Monitor) -> Self {
+        Self {
+            monitor: Some(monitor),
+            progress: Progress::default(),
+            shared: None,
+        }
+    }
+
+    /// Returns a tracker for one of several threads performing an operation
+    /// together, which adds to and reports their combined `progress`.
+    pub(crate) fn shared(monitor: &'a Monitor, progress: &'a Mutex<Progress>) -> Self {
+        Self {
+            monitor: Some(monitor),
+            progress: Progress::default(),
+            shared: Some(progress),
+        }
+    }
+
+    /// Tests whether anything observes the progress, so that operations
+    /// need to report it as they go.
+    pub(crate) fn is_observed(&self) -> bool {
+        self.monitor
+            .is_some_and(|monitor| monitor.hook.is_some() || monitor.cancel.is_some())
+    }
+
+    /// Fails with a [`Cancelled`] error if the operation has been
+    /// cancelled.
+    pub(crate) fn check(&self) -> io::Result<()> {
+        match self.monitor.and_then(|monitor| monitor.cancel.as_ref()) {
+            Some(token) if token.is_cancelled() => Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                Cancelled {
+                    progress: match self.shared {
+                        Some(shared) => *shared.lock().unwrap(),
+                        None => self.progress,
+                    },
+                },
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records copying `bytes` bytes.
+    pub(crate) fn add_bytes(&mut self, bytes: u64) {
+        self.update(|progress| progress.bytes += bytes);
+    }
+
+    /// Records finishing copying a file.
+    pub(crate) fn add_file(&mut self) {
+        self.update(|progress| progress.files += 1);
+    }
+
+    fn update(&mut self, f: impl Fn(&mut Progress)) {
+        let progress = match self.shared {
+            Some(shared) => {
+                let mut shared = shared.lock().unwrap();
+                f(&mut shared);
+                *shared
+            }
+            None => {
+                f(&mut self.progress);
+                self.progress
+            }
+        };
+        if let Some(ProgressHook(hook)) = self.monitor.and_then(|monitor| monitor.hook.as_ref()) {
+            hook(&progress);
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use cap_std::fs::{Dir, DirEntry, File, FileType, Metadata, OpenOptions};
+#[cfg(not(windows))]
+use rustix::fs::DirEntryExt;
+#[cfg(target_os = "linux")]
+use rustix::fs::{openat, Mode, OFlags, RawDir};
+#[cfg(target_os = "linux")]
+use std::collections::VecDeque;
+#[cfg(target_os = "linux")]
+use std::ffi::OsStr;
+use std::ffi::OsString;
+#[cfg(target_os = "linux")]
+use std::os::fd::OwnedFd;
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+use std::{fmt, io};
+
+/// The smallest buffer used to read directory entries in batches, which
+/// leaves room for at least a few entries with long names.
+#[cfg(target_os = "linux")]
+const MIN_BUFFER_SIZE: usize = 1024;
+
+/// A directory entry, read either by cap-std or by [`RawReadDir`].
+///
+/// This has the same methods as [`DirEntry`], which it forwards to.
+pub(crate) enum Entry {
+    Std(DirEntry),
+    #[cfg(target_os = "linux")]
+    Raw(RawDirEntry),
+}
+
+impl Entry {
+    pub(crate) fn open(&self) -> io::Result<File> {
+        self.open_with(OpenOptions::new().read(true))
+    }
+
+    pub(crate) fn open_with(&self, options: &OpenOptions) -> io::Result<File> {
+        match self {
+            Self::Std(entry) => entry.open_with(options),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.open_with(options),
+        }
+    }
+
+    pub(crate) fn open_dir(&self) -> io::Result<Dir> {
+        match self {
+            Self::Std(entry) => entry.open_dir(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.open_dir(),
+        }
+    }
+
+    pub(crate) fn remove_file(&self) -> io::Result<()> {
+        match self {
+            Self::Std(entry) => entry.remove_file(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.remove_file(),
+        }
+    }
+
+    pub(crate) fn remove_dir(&self) -> io::Result<()> {
+        match self {
+            Self::Std(entry) => entry.remove_dir(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.remove_dir(),
+        }
+    }
+
+    pub(crate) fn metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Std(entry) => entry.metadata(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.metadata(),
+        }
+    }
+
+    /// Returns the file type reported by the directory listing, if it
+    /// reported one.
+    pub(crate) fn file_type_hint(&self) -> Option<FileType> {
+        match self {
+            Self::Std(entry) => std_file_type_hint(entry.file_type()),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.file_type,
+        }
+    }
+
+    pub(crate) fn file_name(&self) -> OsString {
+        match self {
+            Self::Std(entry) => entry.file_name(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.name.clone(),
+        }
+    }
+
+    #[cfg(not(windows))]
+    pub(crate) fn ino(&self) -> u64 {
+        match self {
+            Self::Std(entry) => entry.ino(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.ino,
+        }
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn full_metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Std(entry) => cap_primitives::fs::_WindowsDirEntryExt::full_metadata(entry),
+        }
+    }
+
+    #[cfg(feature = "cap-fs-ext")]
+    pub(crate) fn cap_fs_ext_full_metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Std(entry) => cap_fs_ext::DirEntryExt::full_metadata(entry),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.metadata(),
+        }
+    }
+
+    /// Converts `self` into an [`EntryUtf8`], without checking its name.
+    #[cfg(feature = "fs_utf8")]
+    pub(crate) fn into_utf8(self) -> EntryUtf8 {
+        match self {
+            Self::Std(entry) => EntryUtf8::Std(cap_std::fs_utf8::DirEntry::from_cap_std(entry)),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => EntryUtf8::Raw(entry),
+        }
+    }
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Std(entry) => entry.fmt(f),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.fmt(f),
+        }
+    }
+}
+
+/// A directory entry, read either by cap-std or by [`RawReadDir`].
+///
+/// This has the same methods as [`cap_std::fs_utf8::DirEntry`], which it
+/// forwards to.
+#[cfg(feature = "fs_utf8")]
+pub(crate) enum EntryUtf8 {
+    Std(cap_std::fs_utf8::DirEntry),
+    #[cfg(target_os = "linux")]
+    Raw(RawDirEntry),
+}
+
+#[cfg(feature = "fs_utf8")]
+impl EntryUtf8 {
+    pub(crate) fn open(&self) -> io::Result<cap_std::fs_utf8::File> {
+        self.open_with(OpenOptions::new().read(true))
+    }
+
+    pub(crate) fn open_with(&self, options: &OpenOptions) -> io::Result<cap_std::fs_utf8::File> {
+        match self {
+            Self::Std(entry) => entry.open_with(options),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry
+                .open_with(options)
+                .map(cap_std::fs_utf8::File::from_cap_std),
+        }
+    }
+
+    pub(crate) fn open_dir(&self) -> io::Result<cap_std::fs_utf8::Dir> {
+        match self {
+            Self::Std(entry) => entry.open_dir(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.open_dir().map(cap_std::fs_utf8::Dir::from_cap_std),
+        }
+    }
+
+    pub(crate) fn remove_file(&self) -> io::Result<()> {
+        match self {
+            Self::Std(entry) => entry.remove_file(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.remove_file(),
+        }
+    }
+
+    pub(crate) fn remove_dir(&self) -> io::Result<()> {
+        match self {
+            Self::Std(entry) => entry.remove_dir(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.remove_dir(),
+        }
+    }
+
+    pub(crate) fn metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Std(entry) => entry.metadata(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.metadata(),
+        }
+    }
+
+    /// Returns the file type reported by the directory listing, if it
+    /// reported one.
+    pub(crate) fn file_type_hint(&self) -> Option<FileType> {
+        match self {
+            Self::Std(entry) => std_file_type_hint(entry.file_type()),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.file_type,
+        }
+    }
+
+    pub(crate) fn file_name(&self) -> io::Result<String> {
+        match self {
+            Self::Std(entry) => entry.file_name(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.name.clone().into_string().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "filesystem path is not valid UTF-8",
+                )
+            }),
+        }
+    }
+
+    #[cfg(not(windows))]
+    pub(crate) fn ino(&self) -> u64 {
+        match self {
+            Self::Std(entry) => entry.ino(),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.ino,
+        }
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn full_metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Std(entry) => cap_primitives::fs::_WindowsDirEntryExt::full_metadata(entry),
+        }
+    }
+
+    #[cfg(feature = "cap-fs-ext")]
+    pub(crate) fn cap_fs_ext_full_metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Std(entry) => cap_fs_ext::DirEntryExt::full_metadata(entry),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.metadata(),
+        }
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl fmt::Debug for EntryUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Std(entry) => entry.fmt(f),
+            #[cfg(target_os = "linux")]
+            Self::Raw(entry) => entry.fmt(f),
+        }
+    }
+}
+
+/// Converts the file type reported by a cap-std directory entry into a hint,
+/// which is `None` where cap-std reports an unknown type, and where it reads
+/// the type with a separate `fstatat` call.
+fn std_file_type_hint(file_type: io::Result<FileType>) -> Option<FileType> {
+    if cfg!(any(target_os = "illumos", target_os = "solaris")) {
+        return None;
+    }
+    file_type
+        .ok()
+        .filter(|file_type| *file_type != FileType::unknown())
+}
+
+/// An entry read by [`RawReadDir`], which is operated on by name through
+/// the directory it was read from.
+#[cfg(target_os = "linux")]
+pub(crate) struct RawDirEntry {
+    dir: Arc<Dir>,
+    name: OsString,
+    /// The entry's type, if it's one which can be constructed from the
+    /// directory entry alone. Other types are read with `fstatat`.
+    file_type: Option<FileType>,
+    ino: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl RawDirEntry {
+    fn open_with(&self, options: &OpenOptions) -> io::Result<File> {
+        self.dir.open_with(&self.name, options)
+    }
+
+    fn open_dir(&self) -> io::Result<Dir> {
+        self.dir.open_dir(&self.name)
+    }
+
+    fn remove_file(&self) -> io::Result<()> {
+        self.dir.remove_file(&self.name)
+    }
+
+    fn remove_dir(&self) -> io::Result<()> {
+        self.dir.remove_dir(&self.name)
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        self.dir.symlink_metadata(&self.name)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for RawDirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DirEntry").field(&self.name).finish()
+    }
+}
+
+/// An iterator over the entries of a directory which reads them with
+/// `getdents` in batches filling a buffer of a given size.
+#[cfg(target_os = "linux")]
+pub(crate) struct RawReadDir {
+    dir: Arc<Dir>,
+    fd: OwnedFd,
+    buf: Vec<u8>,
+    batch: VecDeque<RawDirEntry>,
+    done: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl RawReadDir {
+    pub(crate) fn new(dir: Arc<Dir>, buffer_size: usize) -> io::Result<Self> {
+        // Open the directory anew, as `dir` may not be opened for reading,
+        // and so that this has its own position.
+        let fd = openat(
+            &*dir,
+            ".",
+            OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+            Mode::empty(),
+        )?;
+        Ok(Self {
+            dir,
+            fd,
+            buf: Vec::with_capacity(buffer_size.max(MIN_BUFFER_SIZE)),
+            batch: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Reads the next batch of entries with a single `getdents` call.
+    fn read_batch(&mut self) -> io::Result<()> {
+        let mut raw = RawDir::new(&self.fd, self.buf.spare_capacity_mut());
+        loop {
+            let entry = match raw.next() {
+                Some(entry) => entry?,
+                None => {
+                    self.done = true;
+                    return Ok(());
+                }
+            };
+            let name = entry.file_name().to_bytes();
+            if name != b"." && name != b".." {
+                let file_type = match entry.file_type() {
+                    rustix::fs::FileType::Directory => Some(FileType::dir()),
+                    rustix::fs::FileType::RegularFile => Some(FileType::file()),
+                    _ => None,
+                };
+                self.batch.push_back(RawDirEntry {
+                    dir: Arc::clone(&self.dir),
+                    name: OsStr::from_bytes(name).to_os_string(),
+                    file_type,
+                    ino: entry.ino(),
+                });
+            }
+            if raw.is_buffer_empty() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Iterator for RawReadDir {
+    type Item = io::Result<RawDirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.batch.is_empty() {
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.read_batch() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.batch.pop_front().map(Ok)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.batch.len();
+        (len, if self.done { Some(len) } else { None })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for RawReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadDir")
+            .field("buffer_size", &self.buf.capacity())
+            .finish_non_exhaustive()
+    }
+}
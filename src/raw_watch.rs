@@ -0,0 +1,212 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use rustix::fs::{openat2, Mode, OFlags, ResolveFlags};
+use rustix::io::Errno;
+use std::borrow::Borrow;
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, OwnedFd};
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Adds a watch for the entry at `path` to the inotify instance
+    /// `inotify`, returning its watch descriptor.
+    ///
+    /// `mask` is passed to `inotify_add_watch` as is, so events are read from
+    /// `inotify` with their full detail. The entry is resolved through the
+    /// view, and the watch is bound to the resolved entry, not to `path`, so
+    /// it stays within the view even if `path` is later replaced. With
+    /// `IN_DONT_FOLLOW`, a symbolic link at `path` is watched itself rather
+    /// than its target.
+    pub fn inotify_watch<P: AsRef<Path>>(
+        &self,
+        inotify: BorrowedFd<'_>,
+        path: P,
+        mask: u32,
+    ) -> io::Result<i32> {
+        self.audited(Operation::Watch, path, |path| {
+            self.check_resolve(Operation::Watch, path)?;
+            let entry = open_path(self.dir.borrow(), path, mask & libc::IN_DONT_FOLLOW == 0)?;
+            inotify_add_watch(inotify, &entry, mask)
+        })
+    }
+
+    /// Marks the entry at `path` in the fanotify group `fanotify`.
+    ///
+    /// `flags` and `mask` are passed to `fanotify_mark`, with `FAN_MARK_ADD`
+    /// unless `flags` includes `FAN_MARK_REMOVE` or `FAN_MARK_FLUSH`. With a
+    /// permission event such as `FAN_OPEN_PERM` in `mask`, opens of the entry
+    /// wait until the group's reader writes a `fanotify_response` allowing or
+    /// denying them, which lets a view veto opens of its entries by other
+    /// processes. Creating a fanotify group usually requires
+    /// `CAP_SYS_ADMIN`.
+    ///
+    /// As with [`Self::inotify_watch`], the mark is bound to the entry
+    /// resolved through the view. Marking a whole mount or filesystem would
+    /// reach beyond the view, so `FAN_MARK_MOUNT` and `FAN_MARK_FILESYSTEM`
+    /// are denied.
+    pub fn fanotify_mark<P: AsRef<Path>>(
+        &self,
+        fanotify: BorrowedFd<'_>,
+        path: P,
+        flags: u32,
+        mask: u64,
+    ) -> io::Result<()> {
+        self.audited(Operation::Watch, path, |path| {
+            if flags & (libc::FAN_MARK_MOUNT | libc::FAN_MARK_FILESYSTEM) != 0 {
+                return Err(self.policy.denied(
+                    "attempt to mark a whole mount or filesystem through a view",
+                    Operation::Watch,
+                    path,
+                ));
+            }
+            self.check_resolve(Operation::Watch, path)?;
+            let entry = open_path(
+                self.dir.borrow(),
+                path,
+                flags & libc::FAN_MARK_DONT_FOLLOW == 0,
+            )?;
+            fanotify_mark(fanotify, &entry, flags, mask)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Adds a watch for the entry at `path` to the inotify instance
+    /// `inotify`, returning its watch descriptor.
+    ///
+    /// See [`DirView::inotify_watch`].
+    pub fn inotify_watch<P: AsRef<Utf8Path>>(
+        &self,
+        inotify: BorrowedFd<'_>,
+        path: P,
+        mask: u32,
+    ) -> io::Result<i32> {
+        self.audited(Operation::Watch, path, |path| {
+            self.check_resolve(Operation::Watch, path)?;
+            let entry = open_path(
+                self.dir.borrow().as_cap_std(),
+                path.as_std_path(),
+                mask & libc::IN_DONT_FOLLOW == 0,
+            )?;
+            inotify_add_watch(inotify, &entry, mask)
+        })
+    }
+
+    /// Marks the entry at `path` in the fanotify group `fanotify`.
+    ///
+    /// See [`DirView::fanotify_mark`].
+    pub fn fanotify_mark<P: AsRef<Utf8Path>>(
+        &self,
+        fanotify: BorrowedFd<'_>,
+        path: P,
+        flags: u32,
+        mask: u64,
+    ) -> io::Result<()> {
+        self.audited(Operation::Watch, path, |path| {
+            if flags & (libc::FAN_MARK_MOUNT | libc::FAN_MARK_FILESYSTEM) != 0 {
+                return Err(self.policy.denied(
+                    "attempt to mark a whole mount or filesystem through a view",
+                    Operation::Watch,
+                    path.as_std_path(),
+                ));
+            }
+            self.check_resolve(Operation::Watch, path)?;
+            let entry = open_path(
+                self.dir.borrow().as_cap_std(),
+                path.as_std_path(),
+                flags & libc::FAN_MARK_DONT_FOLLOW == 0,
+            )?;
+            fanotify_mark(fanotify, &entry, flags, mask)
+        })
+    }
+}
+
+/// Opens the entry at `path` in `dir` with `O_PATH`, resolving it as cap-std
+/// does: beneath `dir`, and without following "magic links".
+fn open_path(dir: &Dir, path: &Path, follow: bool) -> io::Result<OwnedFd> {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    let mut oflags = OFlags::PATH | OFlags::CLOEXEC;
+    if !follow {
+        oflags |= OFlags::NOFOLLOW;
+    }
+    // `openat2` fails with `EAGAIN` if a rename happens anywhere on the host
+    // while it's running, so retry a few times.
+    for _ in 0..4 {
+        match openat2(
+            dir,
+            path,
+            oflags,
+            Mode::empty(),
+            ResolveFlags::BENEATH | ResolveFlags::NO_MAGICLINKS,
+        ) {
+            Err(Errno::AGAIN) => continue,
+            Err(Errno::NOSYS) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "watching an entry of a view requires `openat2`",
+                ))
+            }
+            result => return Ok(result?),
+        }
+    }
+    Err(Errno::AGAIN.into())
+}
+
+/// Returns the path of `entry` in `/proc/self/fd`, which refers to the open
+/// entry itself rather than to any path.
+///
+/// inotify and fanotify can't mark an `O_PATH` descriptor directly, but they
+/// accept this path, following the "magic link" to the entry.
+fn proc_path(entry: &OwnedFd) -> CString {
+    CString::new(format!("/proc/self/fd/{}", entry.as_raw_fd())).unwrap()
+}
+
+fn inotify_add_watch(inotify: BorrowedFd<'_>, entry: &OwnedFd, mask: u32) -> io::Result<i32> {
+    let path = proc_path(entry);
+    // The magic link itself must be followed.
+    let mask = mask & !libc::IN_DONT_FOLLOW;
+    // SAFETY: `path` is a valid NUL-terminated string.
+    let wd = unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), path.as_ptr(), mask) };
+    if wd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(wd)
+}
+
+fn fanotify_mark(
+    fanotify: BorrowedFd<'_>,
+    entry: &OwnedFd,
+    flags: u32,
+    mask: u64,
+) -> io::Result<()> {
+    let path = proc_path(entry);
+    // The magic link itself must be followed.
+    let mut flags = flags & !libc::FAN_MARK_DONT_FOLLOW;
+    if flags & (libc::FAN_MARK_REMOVE | libc::FAN_MARK_FLUSH) == 0 {
+        flags |= libc::FAN_MARK_ADD;
+    }
+    // SAFETY: `path` is a valid NUL-terminated string.
+    let result = unsafe {
+        libc::fanotify_mark(
+            fanotify.as_raw_fd(),
+            flags,
+            mask,
+            libc::AT_FDCWD,
+            path.as_ptr(),
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -1,4 +1,12 @@
-use crate::{DirEntryViewUtf8, ViewKind};
+use crate::policy::{is_dot_file, Policy};
+use crate::raw_dir::EntryUtf8;
+#[cfg(target_os = "linux")]
+use crate::raw_dir::RawReadDir;
+use crate::{DirEntryViewUtf8, Operation, ViewKind};
+use camino::Utf8Path;
+use cap_std::fs_utf8::Metadata;
+use std::iter::{Fuse, FusedIterator};
+use std::sync::{Arc, OnceLock};
 use std::{fmt, io};
 
 /// Iterator over the entries in a directory.
@@ -7,9 +15,106 @@ use std::{fmt, io};
 ///
 /// There is no `from_std` method, as `std::fs::ReadDir` doesn't provide a way
 /// to construct a `ReadDir` without opening directories by ambient paths.
+///
+/// Directories don't report how many entries they have before they're read,
+/// so this doesn't implement `ExactSizeIterator`.
 pub struct ReadDirViewUtf8 {
-    pub(crate) read_dir: cap_std::fs_utf8::ReadDir,
-    pub(crate) view_kind: ViewKind,
+    dir: cap_std::fs_utf8::Dir,
+    read_dir: Entries,
+    position: u64,
+    view_kind: ViewKind,
+    policy: Arc<Policy>,
+    depth: usize,
+    /// The path of the directory, relative to the view it was read through.
+    parent: Arc<Utf8Path>,
+}
+
+impl ReadDirViewUtf8 {
+    pub(crate) fn new(
+        dir: cap_std::fs_utf8::Dir,
+        view_kind: ViewKind,
+        policy: Arc<Policy>,
+        depth: usize,
+        parent: &Utf8Path,
+    ) -> io::Result<Self> {
+        let policy = Policy::with_permit(policy, Operation::ReadDir, parent.as_std_path())?;
+        Ok(Self {
+            read_dir: Entries::new(&dir, &policy)?,
+            dir,
+            position: 0,
+            view_kind,
+            policy,
+            depth,
+            parent: parent.into(),
+        })
+    }
+
+    /// Restarts the enumeration from the first entry.
+    ///
+    /// Entries added or removed since the enumeration started are reflected
+    /// after rewinding.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.read_dir = Entries::new(&self.dir, &self.policy)?;
+        self.position = 0;
+        Ok(())
+    }
+
+    /// Returns a cookie for the current position in the enumeration, which
+    /// can be passed to [`Self::seek`], possibly on another iterator over
+    /// the same directory, to resume from this point.
+    ///
+    /// Cookies count the entries read so far, so they're only meaningful
+    /// while the directory's entries are unchanged.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Moves to the position identified by `cookie`, as returned by
+    /// [`Self::position`].
+    ///
+    /// The platform enumeration APIs available here don't support seeking,
+    /// so this rewinds and skips over the entries before the position,
+    /// without constructing entries for them.
+    pub fn seek(&mut self, cookie: u64) -> io::Result<()> {
+        if cookie < self.position {
+            self.rewind()?;
+        }
+        while self.position < cookie {
+            if self.read_dir.next().is_none() {
+                break;
+            }
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over the remaining entries together with their
+    /// metadata.
+    #[inline]
+    pub fn with_metadata(self) -> ReadDirWithMetadataUtf8 {
+        ReadDirWithMetadataUtf8(self)
+    }
+
+    /// Reads the remaining entries and returns them sorted by file name,
+    /// together with any errors encountered while reading them.
+    ///
+    /// Unlike collecting into an `io::Result<Vec<_>>`, an error reading one
+    /// entry doesn't discard the others, so a listing can still be shown.
+    /// Entries whose names aren't valid UTF-8 are reported as errors.
+    pub fn collect_sorted(self) -> (Vec<DirEntryViewUtf8>, Vec<io::Error>) {
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for entry in self {
+            match entry.and_then(|entry| Ok((entry.file_name()?, entry))) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => errors.push(err),
+            }
+        }
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let entries = entries.into_iter().map(|(_, entry)| entry).collect();
+        (entries, errors)
+    }
 }
 
 impl Iterator for ReadDirViewUtf8 {
@@ -17,17 +122,131 @@ impl Iterator for ReadDirViewUtf8 {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.read_dir.next().map(|entry| {
-            entry.map(|entry| DirEntryViewUtf8 {
+        loop {
+            let entry = self.read_dir.next()?;
+            self.position += 1;
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err)),
+            };
+            if self.policy.hide_hidden
+                && entry
+                    .file_name()
+                    .is_ok_and(|name| is_dot_file(name.as_bytes()))
+            {
+                continue;
+            }
+            return Some(Ok(DirEntryViewUtf8 {
                 entry,
                 view_kind: self.view_kind,
-            })
-        })
+                policy: Arc::clone(&self.policy),
+                depth: self.depth,
+                parent: Arc::clone(&self.parent),
+                metadata: OnceLock::new(),
+            }));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.read_dir.size_hint();
+        if self.policy.hide_hidden {
+            // Any of the entries could be hidden.
+            (0, upper)
+        } else {
+            (lower, upper)
+        }
     }
 }
 
+impl FusedIterator for ReadDirViewUtf8 {}
+
 impl fmt::Debug for ReadDirViewUtf8 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.read_dir.fmt(f)
+        match &self.read_dir {
+            Entries::Std(read_dir) => read_dir.fmt(f),
+            #[cfg(target_os = "linux")]
+            Entries::Batched(read_dir) => read_dir.fmt(f),
+        }
+    }
+}
+
+/// Iterator over the entries in a directory together with their metadata,
+/// returned by [`ReadDirViewUtf8::with_metadata`].
+///
+/// Metadata is read through each entry, as with [`DirEntryViewUtf8::metadata`], which
+/// avoids resolving the entry's path again. On Windows it comes from the
+/// directory enumeration itself; elsewhere it takes a single `fstatat` per
+/// entry. Like `metadata`, it describes symbolic links rather than their
+/// targets.
+pub struct ReadDirWithMetadataUtf8(ReadDirViewUtf8);
+
+impl Iterator for ReadDirWithMetadataUtf8 {
+    type Item = io::Result<(DirEntryViewUtf8, Metadata)>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.0.next()? {
+            Ok(entry) => entry,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(entry.metadata().map(|metadata| (entry, metadata)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl FusedIterator for ReadDirWithMetadataUtf8 {}
+
+impl fmt::Debug for ReadDirWithMetadataUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadDirWithMetadataUtf8")
+            .field(&self.0)
+            .finish()
+    }
+}
+
+/// The source of a directory iterator's entries: cap-std's iterator, or on
+/// Linux, one reading batches of entries into a buffer of a configured size.
+enum Entries {
+    Std(Fuse<cap_std::fs_utf8::ReadDir>),
+    #[cfg(target_os = "linux")]
+    Batched(RawReadDir),
+}
+
+impl Entries {
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn new(dir: &cap_std::fs_utf8::Dir, policy: &Policy) -> io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        if let Some(buffer_size) = policy.read_dir_buffer_size {
+            let dir = dir.as_cap_std().try_clone()?;
+            return Ok(Self::Batched(RawReadDir::new(Arc::new(dir), buffer_size)?));
+        }
+        Ok(Self::Std(dir.entries()?.fuse()))
+    }
+}
+
+impl Iterator for Entries {
+    type Item = io::Result<EntryUtf8>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Std(read_dir) => Some(read_dir.next()?.map(EntryUtf8::Std)),
+            #[cfg(target_os = "linux")]
+            Self::Batched(read_dir) => Some(read_dir.next()?.map(EntryUtf8::Raw)),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Std(read_dir) => read_dir.size_hint(),
+            #[cfg(target_os = "linux")]
+            Self::Batched(read_dir) => read_dir.size_hint(),
+        }
     }
 }
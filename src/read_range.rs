@@ -0,0 +1,177 @@
+use crate::policy::Policy;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+use std::borrow::Borrow;
+use std::io::{self, Read};
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Reads the entire contents of a file into `buf`, replacing its previous
+    /// contents, and returns the number of bytes read.
+    ///
+    /// This is like [`Self::read`], but reuses the allocation of `buf`, so
+    /// that loops reading many files can use a single buffer.
+    #[inline]
+    pub fn read_into<P: AsRef<Path>>(&self, path: P, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            read_into(self.dir.borrow().open(path)?, buf, &self.policy)
+        })
+    }
+
+    /// Reads up to `len` bytes of a file, starting at `offset`.
+    ///
+    /// Fewer bytes are returned if the file ends before `offset + len`. The
+    /// range is read with positional reads, such as `pread` on Unix, and the
+    /// returned buffer is only as large as the part of the range within the
+    /// file.
+    #[inline]
+    pub fn read_range<P: AsRef<Path>>(
+        &self,
+        path: P,
+        offset: u64,
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            read_range(&self.dir.borrow().open(path)?, offset, len, &self.policy)
+        })
+    }
+
+    /// Reads the entire contents of a file into a string, failing with
+    /// [`io::ErrorKind::FileTooLarge`] if it's larger than `max` bytes.
+    ///
+    /// The file's size is checked before reading it, and the string is
+    /// allocated with exactly that capacity. Files which grow while being
+    /// read fail too, once they pass `max`. When reading trees which may
+    /// contain arbitrarily large files, such as through readonly views of
+    /// user-supplied directories, this is preferable to
+    /// [`Self::read_to_string`].
+    #[inline]
+    pub fn read_to_string_limit<P: AsRef<Path>>(&self, path: P, max: u64) -> io::Result<String> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            read_to_string_limit(self.dir.borrow().open(path)?, max, &self.policy)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Reads the entire contents of a file into `buf`, replacing its previous
+    /// contents, and returns the number of bytes read.
+    ///
+    /// This is like [`Self::read`], but reuses the allocation of `buf`, so
+    /// that loops reading many files can use a single buffer.
+    #[inline]
+    pub fn read_into<P: AsRef<Utf8Path>>(&self, path: P, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            let file = self.dir.borrow().as_cap_std().open(path)?;
+            read_into(file, buf, &self.policy)
+        })
+    }
+
+    /// Reads up to `len` bytes of a file, starting at `offset`.
+    ///
+    /// See [`DirView::read_range`].
+    #[inline]
+    pub fn read_range<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        offset: u64,
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            let file = self.dir.borrow().as_cap_std().open(path)?;
+            read_range(&file, offset, len, &self.policy)
+        })
+    }
+
+    /// Reads the entire contents of a file into a string, failing with
+    /// [`io::ErrorKind::FileTooLarge`] if it's larger than `max` bytes.
+    ///
+    /// See [`DirView::read_to_string_limit`].
+    #[inline]
+    pub fn read_to_string_limit<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        max: u64,
+    ) -> io::Result<String> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            let file = self.dir.borrow().as_cap_std().open(path)?;
+            read_to_string_limit(file, max, &self.policy)
+        })
+    }
+}
+
+fn read_into(mut file: File, buf: &mut Vec<u8>, policy: &Policy) -> io::Result<usize> {
+    buf.clear();
+    if let Ok(metadata) = file.metadata() {
+        buf.reserve_exact(usize::try_from(metadata.len()).unwrap_or(0));
+    }
+    let len = file.read_to_end(buf)?;
+    policy.account_bytes_read(len);
+    Ok(len)
+}
+
+fn read_to_string_limit(file: File, max: u64, policy: &Policy) -> io::Result<String> {
+    let too_large = || {
+        io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!("file is larger than the limit of {} bytes", max),
+        )
+    };
+    let size = file.metadata()?.len();
+    if size > max {
+        return Err(too_large());
+    }
+    let mut contents = Vec::with_capacity(usize::try_from(size).unwrap_or(0));
+    // Read one byte more than the limit, to detect files which have grown.
+    file.take(max.saturating_add(1))
+        .read_to_end(&mut contents)?;
+    if contents.len() as u64 > max {
+        return Err(too_large());
+    }
+    policy.account_bytes_read(contents.len());
+    String::from_utf8(contents).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+fn read_range(file: &File, offset: u64, len: usize, policy: &Policy) -> io::Result<Vec<u8>> {
+    let available = file.metadata()?.len().saturating_sub(offset);
+    let len = len.min(usize::try_from(available).unwrap_or(usize::MAX));
+    let mut buf = vec![0; len];
+    let mut filled = 0;
+    while filled < len {
+        match read_at(file, &mut buf[filled..], offset + filled as u64) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    buf.truncate(filled);
+    policy.account_bytes_read(filled);
+    Ok(buf)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    cap_std::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+pub(crate) fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    cap_std::fs::FileExt::seek_read(file, buf, offset)
+}
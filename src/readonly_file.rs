@@ -0,0 +1,140 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+use crate::{Advice, DirView};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, Metadata};
+use std::borrow::Borrow;
+use std::io::{self, IoSliceMut, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// A file opened in read-only mode.
+///
+/// This is returned by [`DirView::open_readonly`]. Unlike [`File`], it only
+/// provides reading, seeking, and metadata queries, and doesn't expose its
+/// handle, so the restriction is carried in its type.
+pub struct ReadOnlyFile {
+    file: File,
+}
+
+impl ReadOnlyFile {
+    /// Queries metadata about the underlying file.
+    ///
+    /// This corresponds to [`std::fs::File::metadata`].
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.file.metadata()
+    }
+
+    /// Declares how the `len` bytes of the file starting at `offset` will be
+    /// read through this handle, or the rest of the file if `len` is 0.
+    ///
+    /// See [`DirView::advise`].
+    #[inline]
+    pub fn advise(&self, offset: u64, len: u64, advice: Advice) -> io::Result<()> {
+        crate::advise::advise(&self.file, offset, len, advice)
+    }
+
+    /// Creates a new `ReadOnlyFile` instance that shares the same underlying
+    /// file handle as the existing `ReadOnlyFile` instance.
+    ///
+    /// This corresponds to [`std::fs::File::try_clone`].
+    #[inline]
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            file: self.file.try_clone()?,
+        })
+    }
+}
+
+impl Read for ReadOnlyFile {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+
+    #[inline]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.file.read_vectored(bufs)
+    }
+
+    #[inline]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.file.read_to_end(buf)
+    }
+
+    #[inline]
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.file.read_to_string(buf)
+    }
+}
+
+impl Read for &ReadOnlyFile {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.file).read(buf)
+    }
+
+    #[inline]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        (&self.file).read_vectored(bufs)
+    }
+}
+
+impl Seek for ReadOnlyFile {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Seek for &ReadOnlyFile {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        (&self.file).seek(pos)
+    }
+}
+
+impl std::fmt::Debug for ReadOnlyFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadOnlyFile")
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Attempts to open a file in read-only mode, returning it as a
+    /// [`ReadOnlyFile`].
+    ///
+    /// This is like [`Self::open`], but the returned type only permits
+    /// reading.
+    #[inline]
+    pub fn open_readonly<P: AsRef<Path>>(&self, path: P) -> io::Result<ReadOnlyFile> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            Ok(ReadOnlyFile {
+                file: self.dir.borrow().open(path)?,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Attempts to open a file in read-only mode, returning it as a
+    /// [`ReadOnlyFile`].
+    ///
+    /// This is like [`Self::open`], but the returned type only permits
+    /// reading.
+    #[inline]
+    pub fn open_readonly<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<ReadOnlyFile> {
+        self.audited(Operation::Open, path, |path| {
+            self.check_resolve(Operation::Open, path)?;
+            Ok(ReadOnlyFile {
+                file: self.dir.borrow().as_cap_std().open(path)?,
+            })
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use crate::progress::Tracker;
+use crate::sparse::{data_extents, FileExtent};
+use crate::throttle::CopyThrottle;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// The size of the chunks in which files are copied, between which
+/// cancellation is checked.
+const CHUNK_SIZE: u64 = 1 << 20;
+
+/// Copies `from` in `from_dir` to `to` in `to_dir`, like [`Dir::copy`].
+///
+/// On Linux, this first tries to share the files' data with `FICLONE`, so
+/// that on filesystems supporting reflinks, no data is copied at all.
+/// Elsewhere, `Dir::copy` already clones files where it can, so it's used
+/// unless progress needs to be reported or the copy is throttled.
+/// Otherwise, only the data extents of sparse files are copied, so that
+/// holes stay holes.
+pub(crate) fn copy(
+    from_dir: &Dir,
+    from: &Path,
+    to_dir: &Dir,
+    to: &Path,
+    tracker: &mut Tracker<'_>,
+    throttle: CopyThrottle<'_>,
+) -> io::Result<u64> {
+    if !cfg!(any(target_os = "linux", target_os = "android"))
+        && !tracker.is_observed()
+        && !throttle.is_limited()
+    {
+        return from_dir.copy(from, to_dir, to);
+    }
+
+    tracker.check()?;
+    let reader = from_dir.open(from)?;
+    let metadata = reader.metadata()?;
+    if !metadata.is_file() {
+        // Let `Dir::copy` report the error.
+        return from_dir.copy(from, to_dir, to);
+    }
+    let permissions = metadata.permissions();
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use cap_std::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(permissions.mode());
+    }
+    let writer = to_dir.open_with(to, &options)?;
+    if writer.metadata()?.is_file() {
+        // Set the permissions in case the file already existed.
+        writer.set_permissions(permissions)?;
+    }
+
+    if clone(&reader, &writer) {
+        tracker.add_bytes(metadata.len());
+        tracker.add_file();
+        return Ok(metadata.len());
+    }
+    let extents = data_extents(&reader)?;
+    let (mut reader, mut writer) = (reader.into_std(), writer.into_std());
+    if extents.iter().map(FileExtent::len).sum::<u64>() < metadata.len() {
+        copy_sparse(
+            &mut reader,
+            &mut writer,
+            &extents,
+            metadata.len(),
+            tracker,
+            throttle,
+        )?;
+        tracker.add_file();
+        return Ok(metadata.len());
+    }
+    reader.rewind()?;
+    let copied = copy_range(&reader, &mut writer, u64::MAX, tracker, throttle)?;
+    tracker.add_file();
+    Ok(copied)
+}
+
+/// Copies the `extents` of `reader` to the same offsets in `writer`, and
+/// extends `writer` to `len` bytes, leaving the rest of it as holes.
+fn copy_sparse(
+    reader: &mut std::fs::File,
+    writer: &mut std::fs::File,
+    extents: &[FileExtent],
+    len: u64,
+    tracker: &mut Tracker<'_>,
+    throttle: CopyThrottle<'_>,
+) -> io::Result<()> {
+    let mut offset = 0;
+    for extent in extents {
+        tracker.add_bytes(extent.offset() - offset);
+        reader.seek(SeekFrom::Start(extent.offset()))?;
+        writer.seek(SeekFrom::Start(extent.offset()))?;
+        copy_range(reader, writer, extent.len(), tracker, throttle)?;
+        offset = extent.end();
+    }
+    tracker.add_bytes(len - offset);
+    writer.set_len(len)
+}
+
+/// Copies up to `len` bytes from the position of `reader` to that of
+/// `writer`, checking for cancellation and waiting for `throttle` between
+/// chunks.
+fn copy_range(
+    reader: &std::fs::File,
+    writer: &mut std::fs::File,
+    len: u64,
+    tracker: &mut Tracker<'_>,
+    throttle: CopyThrottle<'_>,
+) -> io::Result<u64> {
+    // `io::copy` uses `copy_file_range` or `sendfile` between files where
+    // it can, and falls back to copying through a buffer.
+    let mut copied = 0;
+    while copied < len {
+        tracker.check()?;
+        let chunk = io::copy(&mut reader.take(CHUNK_SIZE.min(len - copied)), writer)?;
+        if chunk == 0 {
+            break;
+        }
+        copied += chunk;
+        tracker.add_bytes(chunk);
+        throttle.copy(chunk);
+    }
+    Ok(copied)
+}
+
+/// Tries to share the data of `reader` with `writer`, returning whether it
+/// succeeded.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(any(target_arch = "sparc", target_arch = "sparc64"))
+))]
+fn clone(reader: &File, writer: &File) -> bool {
+    rustix::fs::ioctl_ficlone(writer, reader).is_ok()
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    not(any(target_arch = "sparc", target_arch = "sparc64"))
+)))]
+fn clone(_reader: &File, _writer: &File) -> bool {
+    false
+}
@@ -0,0 +1,134 @@
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::error::no_file_name;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Rename a file or directory to a new name, failing with
+    /// [`io::ErrorKind::AlreadyExists`] if `to` already exists, rather than
+    /// replacing it.
+    ///
+    /// On Linux this uses `renameat2` with `RENAME_NOREPLACE`, so the check
+    /// and the rename are atomic. Elsewhere, and on filesystems which don't
+    /// support that, files are moved by hard-linking them at `to` and then
+    /// removing them from `from`, which never replaces `to` either, while
+    /// directories are renamed after checking that `to` doesn't exist, so a
+    /// directory created at `to` concurrently may be replaced if it's empty.
+    pub fn rename_noreplace<E: Borrow<Dir>, P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to_dir: &DirView<E>,
+        to: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Rename, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            self.check_change(Operation::Rename, from)?;
+            to_dir.check_change(Operation::Rename, to.as_ref())?;
+            if self.dry_run_pair(to_dir, Operation::Rename, from, to.as_ref())? {
+                return Ok(());
+            }
+            rename_noreplace(self.dir.borrow(), from, to_dir.dir.borrow(), to.as_ref())
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Rename a file or directory to a new name, failing with
+    /// [`io::ErrorKind::AlreadyExists`] if `to` already exists, rather than
+    /// replacing it.
+    ///
+    /// See [`DirView::rename_noreplace`].
+    pub fn rename_noreplace<
+        E: Borrow<cap_std::fs_utf8::Dir>,
+        P: AsRef<Utf8Path>,
+        Q: AsRef<Utf8Path>,
+    >(
+        &self,
+        from: P,
+        to_dir: &DirViewUtf8<E>,
+        to: Q,
+    ) -> io::Result<()> {
+        self.audited_pair(Operation::Rename, from, to, |from, to| {
+            let to = to_dir.fold_names(to.as_ref());
+            self.check_change(Operation::Rename, from)?;
+            to_dir.check_change(Operation::Rename, to.as_ref())?;
+            if self.dry_run_pair(to_dir, Operation::Rename, from, to.as_ref())? {
+                return Ok(());
+            }
+            rename_noreplace(
+                self.dir.borrow().as_cap_std(),
+                from.as_std_path(),
+                to_dir.dir.borrow().as_cap_std(),
+                to.as_ref().as_std_path(),
+            )
+        })
+    }
+}
+
+pub(crate) fn rename_noreplace(
+    from_dir: &Dir,
+    from: &Path,
+    to_dir: &Dir,
+    to: &Path,
+) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use rustix::fs::{renameat_with, RenameFlags};
+        use rustix::io::Errno;
+
+        let (from_parent, from_name) = open_parent(from_dir, from)?;
+        let (to_parent, to_name) = open_parent(to_dir, to)?;
+        match renameat_with(
+            from_parent.as_ref().unwrap_or(from_dir),
+            from_name,
+            to_parent.as_ref().unwrap_or(to_dir),
+            to_name,
+            RenameFlags::NOREPLACE,
+        ) {
+            // The filesystem or kernel doesn't support `RENAME_NOREPLACE`.
+            Err(Errno::INVAL | Errno::NOSYS) => {}
+            result => return Ok(result?),
+        }
+    }
+
+    if from_dir.symlink_metadata(from)?.is_dir() {
+        // Directories can't be hard-linked.
+        match to_dir.symlink_metadata(to) {
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "entry already exists",
+                ))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        return from_dir.rename(from, to_dir, to);
+    }
+    from_dir.hard_link(from, to_dir, to)?;
+    from_dir.remove_file(from)
+}
+
+/// Opens the parent directory of `path` in `dir`, or returns `None` if it's
+/// `dir` itself, along with the last component of `path`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn open_parent<'a>(dir: &Dir, path: &'a Path) -> io::Result<(Option<Dir>, &'a OsStr)> {
+    let name = path.file_name().ok_or_else(no_file_name)?;
+    match path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        Some(parent) => Ok((Some(dir.open_dir(parent)?), name)),
+        None => Ok((None, name)),
+    }
+}
@@ -0,0 +1,211 @@
+use crate::backend::Backend;
+use crate::policy::Policy;
+use cap_std::fs::Dir;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Test whether `target`, resolved lexically from a directory `depth`
+/// levels below a root, names something outside of the root.
+///
+/// Absolute targets, and targets with prefixes on Windows, always escape.
+pub(crate) fn escapes_lexically(mut depth: usize, target: &Path) -> bool {
+    for component in target.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return true,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return true,
+            },
+            Component::Normal(_) => depth += 1,
+        }
+    }
+    false
+}
+
+/// Returns the number of directory levels `path`, a canonical relative
+/// path, is below the directory it's relative to.
+pub(crate) fn depth(path: &Path) -> usize {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count()
+}
+
+/// Returns `path` with each of its components put in the normalization
+/// form of `policy`, if it has one, and, if it resolves names
+/// case-insensitively or normalizes them, with each component which doesn't
+/// name an entry in `dir` replaced by the name of one it matches.
+///
+/// Names match if their normalized forms are the same, or, for
+/// case-insensitive policies, their normalized Unicode lowercase forms, or
+/// with ASCII case folding where they aren't valid UTF-8. Where several
+/// entries match, the first in sorted order is used. Once a component
+/// matches nothing, the rest of `path` is normalized without matching.
+/// Symbolic links are followed as usual when resolving later components,
+/// but their contents aren't matched or normalized.
+pub(crate) fn fold_names<'a, B: Backend + ?Sized>(
+    dir: &B,
+    policy: &Policy,
+    path: &'a Path,
+) -> Cow<'a, Path> {
+    let mut folded = PathBuf::new();
+    let mut changed = false;
+    let mut matching = true;
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            folded.push(component);
+            continue;
+        };
+        let name = policy.normalize_name(name);
+        changed |= matches!(name, Cow::Owned(_));
+        if matching && dir.entry_type(&folded.join(&name)).is_err() {
+            match matching_entry(dir, policy, &folded, &name) {
+                Some(entry) => {
+                    folded.push(entry);
+                    changed = true;
+                    continue;
+                }
+                None => matching = false,
+            }
+        }
+        folded.push(name);
+    }
+    if changed {
+        Cow::Owned(folded)
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Returns the name of the first entry of the directory at `parent` whose
+/// name matches `name`, which is already normalized.
+fn matching_entry<B: Backend + ?Sized>(
+    dir: &B,
+    policy: &Policy,
+    parent: &Path,
+    name: &OsStr,
+) -> Option<OsString> {
+    dir.entry_names(parent)
+        .ok()?
+        .into_iter()
+        .filter(|entry| {
+            let entry = policy.normalize_name(entry);
+            if policy.case_insensitive {
+                eq_ignore_case(&entry, name)
+            } else {
+                *entry == *name
+            }
+        })
+        .min()
+}
+
+fn eq_ignore_case(a: &OsStr, b: &OsStr) -> bool {
+    match (a.to_str(), b.to_str()) {
+        (Some(a), Some(b)) => a.to_lowercase() == b.to_lowercase(),
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+/// Test whether resolving `path` relative to `dir` crosses a mount point.
+///
+/// On Linux this resolves `path` with `openat2` and `RESOLVE_NO_XDEV`. If
+/// `path` doesn't exist, its parent is checked instead, so that creating a
+/// file is checked too.
+#[cfg(target_os = "linux")]
+pub(crate) fn crosses_filesystem(dir: &Dir, path: &Path) -> io::Result<bool> {
+    use rustix::fs::{openat2, Mode, OFlags, ResolveFlags};
+    use rustix::io::Errno;
+
+    let resolve = |path: &Path| {
+        let path = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        // `openat2` fails with `EAGAIN` if a rename happens anywhere on the
+        // host while it's running, so retry a few times.
+        for _ in 0..4 {
+            match openat2(
+                dir,
+                path,
+                OFlags::PATH | OFlags::CLOEXEC,
+                Mode::empty(),
+                ResolveFlags::BENEATH | ResolveFlags::NO_MAGICLINKS | ResolveFlags::NO_XDEV,
+            ) {
+                Err(Errno::AGAIN) => continue,
+                result => return result.map(drop),
+            }
+        }
+        Err(Errno::AGAIN)
+    };
+
+    let result = match resolve(path) {
+        Err(Errno::NOENT) => match path.parent() {
+            Some(parent) => resolve(parent),
+            None => Err(Errno::NOENT),
+        },
+        result => result,
+    };
+    match result {
+        Ok(()) => Ok(false),
+        Err(Errno::XDEV) => Ok(true),
+        Err(Errno::NOSYS) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "confining a view to one filesystem requires `openat2`",
+        )),
+        // Other errors are reported by the operation itself.
+        Err(_) => Ok(false),
+    }
+}
+
+/// Test whether resolving `path` relative to `dir` crosses a mount point.
+///
+/// Without `openat2`, this compares the device of each directory along
+/// `path`, and of `path` itself, with that of `dir`. On WASI, the device is
+/// whatever the host reports for each descriptor.
+#[cfg(any(all(unix, not(target_os = "linux")), target_os = "wasi"))]
+pub(crate) fn crosses_filesystem(dir: &Dir, path: &Path) -> io::Result<bool> {
+    use cap_std::fs::MetadataExt;
+
+    let dev = dir.dir_metadata()?.dev();
+    let mut prefix = std::path::PathBuf::new();
+    for component in path.components() {
+        prefix.push(component);
+        match dir.metadata(&prefix) {
+            Ok(metadata) if metadata.dev() != dev => return Ok(true),
+            Ok(_) => {}
+            // Other errors are reported by the operation itself.
+            Err(_) => return Ok(false),
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(windows)]
+pub(crate) fn crosses_filesystem(_dir: &Dir, _path: &Path) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "confining a view to one filesystem is not supported on Windows",
+    ))
+}
+
+/// Test whether resolving `path` relative to `dir` passes through a
+/// junction or other reparse point, including `path` itself.
+///
+/// If `path` doesn't exist, the directories along it are still checked.
+#[cfg(windows)]
+pub(crate) fn crosses_reparse_point(dir: &Dir, path: &Path) -> io::Result<bool> {
+    let mut prefix = std::path::PathBuf::new();
+    for component in path.components() {
+        prefix.push(component);
+        match dir.symlink_metadata(&prefix) {
+            Ok(metadata) if crate::junction::is_reparse_point(&metadata) => return Ok(true),
+            Ok(_) => {}
+            // Other errors are reported by the operation itself.
+            Err(_) => return Ok(false),
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,102 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns `true` if `self` and `other` are views of the same directory.
+    ///
+    /// This compares the device and inode numbers of the directories, or on
+    /// Windows, their volume serial numbers and file IDs, so it detects the
+    /// same directory reached through different paths, such as when a
+    /// caller passes one directory twice under different names. Checking
+    /// this before renaming or copying between views avoids, for example,
+    /// clearing a destination which is also the source.
+    ///
+    /// This only compares the directories, and not the views' kinds or
+    /// restrictions.
+    pub fn is_same_dir<E: Borrow<Dir>>(&self, other: &DirView<E>) -> io::Result<bool> {
+        self.audited(Operation::Metadata, ".", |_| {
+            Ok(dir_id(self.dir.borrow(), Path::new(""))?
+                == dir_id(other.dir.borrow(), Path::new(""))?)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns `true` if `self` and `other` are views of the same directory.
+    ///
+    /// See [`DirView::is_same_dir`].
+    pub fn is_same_dir<E: Borrow<cap_std::fs_utf8::Dir>>(
+        &self,
+        other: &DirViewUtf8<E>,
+    ) -> io::Result<bool> {
+        self.audited(Operation::Metadata, ".", |_| {
+            Ok(dir_id(self.dir.borrow().as_cap_std(), Path::new(""))?
+                == dir_id(other.dir.borrow().as_cap_std(), Path::new(""))?)
+        })
+    }
+}
+
+/// Returns the device and inode of the directory at `path` in `dir`.
+#[cfg(unix)]
+pub(crate) fn dir_id(dir: &Dir, path: &Path) -> io::Result<(u64, u128)> {
+    use cap_std::fs::MetadataExt;
+
+    let metadata = if path.as_os_str().is_empty() {
+        dir.dir_metadata()?
+    } else {
+        dir.metadata(path)?
+    };
+    if !metadata.is_dir() {
+        return Err(io::Error::from(io::ErrorKind::NotADirectory));
+    }
+    Ok((metadata.dev(), metadata.ino().into()))
+}
+
+/// Returns the volume serial number and file ID of the directory at `path`
+/// in `dir`.
+#[cfg(windows)]
+pub(crate) fn dir_id(dir: &Dir, path: &Path) -> io::Result<(u64, u128)> {
+    use std::mem::size_of;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FileIdInfo, GetFileInformationByHandleEx, FILE_ID_INFO,
+    };
+
+    let dir = if path.as_os_str().is_empty() {
+        dir.try_clone()?
+    } else {
+        dir.open_dir(path)?
+    };
+    // SAFETY: `FILE_ID_INFO` is plain data, for which zero is valid.
+    let mut info: FILE_ID_INFO = unsafe { std::mem::zeroed() };
+    // SAFETY: `info` is a `FILE_ID_INFO` of the given size.
+    let ok = unsafe {
+        GetFileInformationByHandleEx(
+            dir.as_raw_handle(),
+            FileIdInfo,
+            (&mut info as *mut FILE_ID_INFO).cast(),
+            size_of::<FILE_ID_INFO>() as u32,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((
+        info.VolumeSerialNumber,
+        u128::from_le_bytes(info.FileId.Identifier),
+    ))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn dir_id(_dir: &Dir, _path: &Path) -> io::Result<(u64, u128)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "directory identities are not supported on this platform",
+    ))
+}
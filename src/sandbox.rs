@@ -0,0 +1,178 @@
+use crate::policy::Policy;
+use crate::sync::same_contents;
+use crate::{DirView, OverlayDirView, TempDirView, ViewKind};
+use cap_std::fs::{Dir, FileType};
+use cap_std::AmbientAuthority;
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::{fmt, io};
+
+/// A change made through a [`SandboxView`], returned by
+/// [`SandboxView::extract_changes`].
+///
+/// Paths are relative to the sandbox.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Change {
+    /// An entry was created. The contents of a created directory aren't
+    /// listed separately.
+    Created(PathBuf),
+
+    /// A file or symbolic link was given different contents, or an entry
+    /// was replaced by one of a different type.
+    Modified(PathBuf),
+
+    /// An entry was removed. The contents of a removed directory aren't
+    /// listed separately.
+    Removed(PathBuf),
+}
+
+impl Change {
+    /// Returns the path of the entry which changed.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Created(path) | Self::Modified(path) | Self::Removed(path) => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (change, path) = match self {
+            Self::Created(path) => ("created", path),
+            Self::Modified(path) => ("modified", path),
+            Self::Removed(path) => ("removed", path),
+        };
+        write!(f, "{} `{}`", change, path.display())
+    }
+}
+
+/// A copy-on-write sandbox of a view, which captures changes in a private
+/// temporary directory instead of making them.
+///
+/// The sandbox dereferences to an [`OverlayDirView`] with a readonly view
+/// of the base as its lower layer, so it reads like the base tree and can
+/// be changed freely. At the end, [`Self::extract_changes`] reports what
+/// was changed, and [`Self::discard`] throws the changes away. The base
+/// tree is never modified.
+pub struct SandboxView {
+    overlay: OverlayDirView,
+    temp_dir: TempDirView,
+}
+
+impl SandboxView {
+    /// Creates a sandbox of `base`, capturing changes in a new temporary
+    /// directory in the host's temporary directory.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn new<D: Borrow<Dir>>(
+        base: &DirView<D>,
+        ambient_authority: AmbientAuthority,
+    ) -> io::Result<Self> {
+        let mut lower = base.try_clone()?;
+        lower.view_kind = ViewKind::Readonly;
+        lower.policy = Policy::narrowed(lower.policy, ViewKind::Readonly);
+        let temp_dir = TempDirView::new(ViewKind::Full, ambient_authority)?;
+        Ok(Self {
+            overlay: OverlayDirView::new(lower, temp_dir.view()?),
+            temp_dir,
+        })
+    }
+
+    /// Returns the changes made through the sandbox, compared to the base
+    /// tree, sorted by path.
+    ///
+    /// Only the directories changed through the sandbox are compared, and
+    /// files are compared by their contents, so changes made to the base
+    /// tree by other means aren't necessarily reported.
+    pub fn extract_changes(&self) -> io::Result<Vec<Change>> {
+        let mut changes = Vec::new();
+        self.compare_dir(Path::new(""), &mut changes)?;
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(changes)
+    }
+
+    /// Throws away the changes made through the sandbox, deleting its
+    /// temporary directory and reporting any errors.
+    ///
+    /// Dropping `self` also throws away the changes, but ignores errors.
+    pub fn discard(self) -> io::Result<()> {
+        let Self { overlay, temp_dir } = self;
+        drop(overlay);
+        temp_dir.close()
+    }
+
+    fn compare_dir(&self, path: &Path, changes: &mut Vec<Change>) -> io::Result<()> {
+        let lower = self.overlay.lower();
+        let mut lower_entries = BTreeMap::<OsString, FileType>::new();
+        for entry in lower.read_dir_or_entries(path)? {
+            let entry = entry?;
+            lower_entries.insert(entry.file_name(), entry.file_type()?);
+        }
+        for entry in self.overlay.merged_entries(path)? {
+            let name = entry.file_name();
+            let path = path.join(&name);
+            let Some(lower_type) = lower_entries.remove(&name) else {
+                changes.push(Change::Created(path));
+                continue;
+            };
+            // Entries which weren't copied up are unchanged.
+            if !entry.is_upper() {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if lower_type.is_dir() != file_type.is_dir()
+                || lower_type.is_file() != file_type.is_file()
+                || lower_type.is_symlink() != file_type.is_symlink()
+            {
+                changes.push(Change::Modified(path));
+            } else if file_type.is_dir() {
+                self.compare_dir(&path, changes)?;
+            } else if file_type.is_symlink() {
+                if lower.read_link_contents(&path)? != self.overlay.read_link(&path)? {
+                    changes.push(Change::Modified(path));
+                }
+            } else if file_type.is_file() && self.file_differs(&path)? {
+                changes.push(Change::Modified(path));
+            }
+        }
+        changes.extend(
+            lower_entries
+                .into_keys()
+                .map(|name| Change::Removed(path.join(name))),
+        );
+        Ok(())
+    }
+
+    fn file_differs(&self, path: &Path) -> io::Result<bool> {
+        let lower = self.overlay.lower();
+        if lower.metadata(path)?.len() != self.overlay.metadata(path)?.len() {
+            return Ok(true);
+        }
+        Ok(!same_contents(lower.open(path)?, self.overlay.open(path)?)?)
+    }
+}
+
+impl Deref for SandboxView {
+    type Target = OverlayDirView;
+
+    #[inline]
+    fn deref(&self) -> &OverlayDirView {
+        &self.overlay
+    }
+}
+
+impl fmt::Debug for SandboxView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SandboxView")
+            .field("overlay", &self.overlay)
+            .field("temp_dir", &self.temp_dir)
+            .finish()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::error::{PathError, PathErrorKind};
+use crate::portable;
+use std::path::{Component, Path, PathBuf};
+
+/// Validates `path` as a path relative to a view, without touching the
+/// filesystem, and returns it without any `.` components.
+///
+/// This rejects absolute paths and paths with prefixes, `..` components,
+/// empty components such as in `a//b`, NUL bytes, and names which aren't
+/// valid on Windows, as [`DirViewBuilder::portable_names`] describes. A
+/// single trailing separator is permitted. A path with no components other
+/// than `.`, including the empty path, names the view's directory and is
+/// returned as `.`.
+///
+/// Servers can use this to validate request paths before using them, and
+/// views built with [`DirViewBuilder::strict_paths`] apply it to every path.
+///
+/// ```
+/// # use dir_view::{sanitize_path, PathErrorKind};
+/// # use std::path::Path;
+/// assert_eq!(sanitize_path(Path::new("a/./b")).unwrap(), Path::new("a/b"));
+/// assert_eq!(
+///     sanitize_path(Path::new("a/../b")).unwrap_err().kind(),
+///     PathErrorKind::ParentDir
+/// );
+/// ```
+///
+/// [`DirViewBuilder::portable_names`]: crate::DirViewBuilder::portable_names
+/// [`DirViewBuilder::strict_paths`]: crate::DirViewBuilder::strict_paths
+pub fn sanitize_path(path: &Path) -> Result<PathBuf, PathError> {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    if bytes.contains(&0) {
+        return Err(PathError::new(
+            PathErrorKind::Nul,
+            "attempt to use a path containing a NUL byte",
+            path,
+        ));
+    }
+    let mut sanitized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => {
+                return Err(PathError::new(
+                    PathErrorKind::Absolute,
+                    "attempt to use an absolute path",
+                    path,
+                ))
+            }
+            Component::ParentDir => {
+                return Err(PathError::new(
+                    PathErrorKind::ParentDir,
+                    "attempt to use a path containing `..`",
+                    path,
+                ))
+            }
+            Component::CurDir => {}
+            Component::Normal(name) => sanitized.push(name),
+        }
+    }
+    // `components` skips repeated separators, so look for them in the path
+    // itself.
+    let trimmed = match bytes.split_last() {
+        Some((last, rest)) if is_separator(*last) => rest,
+        _ => bytes,
+    };
+    if !bytes.is_empty()
+        && trimmed
+            .split(|byte| is_separator(*byte))
+            .any(<[u8]>::is_empty)
+    {
+        return Err(PathError::new(
+            PathErrorKind::EmptyComponent,
+            "attempt to use a path containing an empty component",
+            path,
+        ));
+    }
+    if let Err(message) = portable::check_path(&sanitized) {
+        return Err(PathError::new(PathErrorKind::InvalidName, message, path));
+    }
+    if sanitized.as_os_str().is_empty() {
+        sanitized.push(Component::CurDir);
+    }
+    Ok(sanitized)
+}
+
+fn is_separator(byte: u8) -> bool {
+    byte == b'/' || (cfg!(windows) && byte == b'\\')
+}
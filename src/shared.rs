@@ -0,0 +1,90 @@
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A reference-counted [`DirView`].
+///
+/// Cloning a `SharedDirView` increments a reference count, rather than
+/// duplicating the underlying OS handle as [`DirView::try_clone`] does. All
+/// clones share the same handle, view kind, and restrictions.
+///
+/// `SharedDirView` is `Send` and `Sync`, so clones may be handed to worker
+/// threads freely.
+#[derive(Clone, Debug)]
+pub struct SharedDirView(Arc<DirView>);
+
+impl SharedDirView {
+    /// Constructs a new instance of `Self` from the given [`DirView`].
+    #[inline]
+    pub fn new(view: DirView) -> Self {
+        Self(Arc::new(view))
+    }
+
+    /// Returns the inner [`DirView`], if `self` has no other clones.
+    ///
+    /// Otherwise, `self` is returned unchanged.
+    #[inline]
+    pub fn try_unwrap(self) -> Result<DirView, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl Deref for SharedDirView {
+    type Target = DirView;
+
+    #[inline]
+    fn deref(&self) -> &DirView {
+        &self.0
+    }
+}
+
+impl From<DirView> for SharedDirView {
+    #[inline]
+    fn from(view: DirView) -> Self {
+        Self::new(view)
+    }
+}
+
+/// A reference-counted [`DirViewUtf8`].
+///
+/// This corresponds to [`SharedDirView`].
+#[cfg(feature = "fs_utf8")]
+#[derive(Clone, Debug)]
+pub struct SharedDirViewUtf8(Arc<DirViewUtf8>);
+
+#[cfg(feature = "fs_utf8")]
+impl SharedDirViewUtf8 {
+    /// Constructs a new instance of `Self` from the given [`DirViewUtf8`].
+    #[inline]
+    pub fn new(view: DirViewUtf8) -> Self {
+        Self(Arc::new(view))
+    }
+
+    /// Returns the inner [`DirViewUtf8`], if `self` has no other clones.
+    ///
+    /// Otherwise, `self` is returned unchanged.
+    #[inline]
+    pub fn try_unwrap(self) -> Result<DirViewUtf8, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl Deref for SharedDirViewUtf8 {
+    type Target = DirViewUtf8;
+
+    #[inline]
+    fn deref(&self) -> &DirViewUtf8 {
+        &self.0
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl From<DirViewUtf8> for SharedDirViewUtf8 {
+    #[inline]
+    fn from(view: DirViewUtf8) -> Self {
+        Self::new(view)
+    }
+}
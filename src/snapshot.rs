@@ -0,0 +1,292 @@
+use crate::{DirView, FileTypeSummary, Sha256Hasher};
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Options for [`DirView::snapshot_manifest_with`].
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotOptions {
+    hash: bool,
+}
+
+impl SnapshotOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Contents are initially not hashed.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether files and symbolic links are hashed with SHA-256, so
+    /// that manifests can be compared by contents.
+    #[inline]
+    pub fn hash(&mut self, hash: bool) -> &mut Self {
+        self.hash = hash;
+        self
+    }
+}
+
+/// A listing of the entries in a tree, returned by
+/// [`DirView::snapshot_manifest`].
+///
+/// With the `serde` feature, this can be serialized, such as to JSON, and
+/// saved to be compared with a later snapshot of the tree.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotManifest {
+    entries: Vec<SnapshotEntry>,
+}
+
+/// An entry in a [`SnapshotManifest`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotEntry {
+    path: PathBuf,
+    file_type: FileTypeSummary,
+    len: u64,
+    modified: Option<SystemTime>,
+    readonly: bool,
+    mode: Option<u32>,
+    sha256: Option<String>,
+}
+
+impl SnapshotManifest {
+    /// Returns the entries of the tree, sorted by path.
+    #[inline]
+    pub fn entries(&self) -> &[SnapshotEntry] {
+        &self.entries
+    }
+
+    /// Returns the entry at `path`, relative to the root of the tree.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&SnapshotEntry> {
+        let path = path.as_ref();
+        self.entries
+            .binary_search_by(|entry| entry.path.as_path().cmp(path))
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    /// Compares `self` with a `newer` snapshot of the tree, such as one
+    /// taken after a manifest saved earlier, returning the entries which
+    /// were added, removed, or changed.
+    ///
+    /// Files and symbolic links are modified if their sizes, modification
+    /// times, or, where both snapshots have them, digests differ, and any
+    /// entry is modified if its permissions differ.
+    pub fn compare(&self, newer: &SnapshotManifest) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        let mut newer_entries = newer
+            .entries
+            .iter()
+            .map(|entry| (entry.path.as_path(), entry))
+            .collect::<BTreeMap<_, _>>();
+        for entry in &self.entries {
+            let Some(newer_entry) = newer_entries.remove(entry.path.as_path()) else {
+                diff.removed.push(entry.path.clone());
+                continue;
+            };
+            if entry.file_type != newer_entry.file_type {
+                diff.type_changed.push(entry.path.clone());
+            } else if entry.differs(newer_entry) {
+                diff.modified.push(entry.path.clone());
+            }
+        }
+        diff.added
+            .extend(newer_entries.into_keys().map(Path::to_path_buf));
+        diff
+    }
+}
+
+impl SnapshotEntry {
+    /// Returns the path of the entry, relative to the root of the tree.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the type of the entry.
+    #[inline]
+    pub fn file_type(&self) -> FileTypeSummary {
+        self.file_type
+    }
+
+    /// Returns the size of the entry, in bytes.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the entry has a size of 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the time the entry was last modified, if the platform
+    /// reports it.
+    #[inline]
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Returns `true` if the entry's permissions make it read-only.
+    #[inline]
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Returns the Unix permission bits of the entry, on Unix.
+    #[inline]
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// Returns the SHA-256 digest of the contents of a file or symbolic
+    /// link, in hexadecimal, if the snapshot was taken with
+    /// [`SnapshotOptions::hash`].
+    #[inline]
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+
+    fn differs(&self, newer: &SnapshotEntry) -> bool {
+        if self.readonly != newer.readonly || self.mode != newer.mode {
+            return true;
+        }
+        if self.file_type == FileTypeSummary::Dir {
+            return false;
+        }
+        if let (Some(sha256), Some(newer_sha256)) = (&self.sha256, &newer.sha256) {
+            if sha256 != newer_sha256 {
+                return true;
+            }
+        }
+        self.len != newer.len || self.modified != newer.modified
+    }
+}
+
+/// The differences between two snapshots, returned by
+/// [`SnapshotManifest::compare`].
+///
+/// Paths are relative to the root of the tree, and sorted.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SnapshotDiff {
+    added: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+    modified: Vec<PathBuf>,
+    type_changed: Vec<PathBuf>,
+}
+
+impl SnapshotDiff {
+    /// Returns the entries which are only in the newer snapshot.
+    #[inline]
+    pub fn added(&self) -> &[PathBuf] {
+        &self.added
+    }
+
+    /// Returns the entries which are only in the older snapshot.
+    #[inline]
+    pub fn removed(&self) -> &[PathBuf] {
+        &self.removed
+    }
+
+    /// Returns the entries which changed between the snapshots.
+    #[inline]
+    pub fn modified(&self) -> &[PathBuf] {
+        &self.modified
+    }
+
+    /// Returns the entries which are of different types in the snapshots.
+    #[inline]
+    pub fn type_changed(&self) -> &[PathBuf] {
+        &self.type_changed
+    }
+
+    /// Tests whether the snapshots are the same.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.type_changed.is_empty()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Lists the entries in the tree at `path`, with their types, sizes,
+    /// modification times, and permissions.
+    ///
+    /// The tree is read through the view, so entries it hides aren't
+    /// included, and subdirectories beyond its maximum depth fail to open.
+    /// Symbolic links are listed rather than followed.
+    #[inline]
+    pub fn snapshot_manifest<P: AsRef<Path>>(&self, path: P) -> io::Result<SnapshotManifest> {
+        self.snapshot_manifest_with(path, &SnapshotOptions::new())
+    }
+
+    /// Lists the entries in the tree at `path`, with the given options.
+    ///
+    /// See [`Self::snapshot_manifest`] for details.
+    pub fn snapshot_manifest_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &SnapshotOptions,
+    ) -> io::Result<SnapshotManifest> {
+        let root = self.open_dir(path)?;
+        let mut entries = Vec::new();
+        for entry in root.walk()? {
+            let entry = entry?;
+            let metadata = root.symlink_metadata(entry.path())?;
+            let file_type = FileTypeSummary::from_file_type(metadata.file_type());
+            let sha256 = match file_type {
+                FileTypeSummary::File if options.hash => {
+                    let mut hasher = Sha256Hasher::new();
+                    io::copy(&mut root.open(entry.path())?, &mut hasher)?;
+                    Some(hex(&hasher.finish()))
+                }
+                FileTypeSummary::Symlink if options.hash => {
+                    let contents = root.read_link_contents(entry.path())?;
+                    let mut hasher = Sha256Hasher::new();
+                    hasher.update(contents.as_os_str().as_encoded_bytes());
+                    Some(hex(&hasher.finish()))
+                }
+                _ => None,
+            };
+            entries.push(SnapshotEntry {
+                path: entry.into_path(),
+                file_type,
+                len: metadata.len(),
+                modified: metadata.modified().ok().map(|time| time.into_std()),
+                readonly: metadata.permissions().readonly(),
+                mode: mode(&metadata),
+                sha256,
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(SnapshotManifest { entries })
+    }
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> Option<u32> {
+    use cap_std::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
@@ -0,0 +1,137 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+use std::borrow::Borrow;
+use std::io;
+use std::path::Path;
+
+/// A range of a file which holds data, rather than being a hole, returned
+/// by [`DirView::file_extents`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileExtent {
+    offset: u64,
+    len: u64,
+}
+
+impl FileExtent {
+    /// Returns the offset of the start of the extent, in bytes.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length of the extent, in bytes.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the extent is empty, which extents returned by
+    /// [`DirView::file_extents`] never are.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the offset just past the end of the extent.
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the ranges of the file at `path` which hold data, in order,
+    /// omitting the holes of a sparse file.
+    ///
+    /// This uses `SEEK_DATA` and `SEEK_HOLE`. Where they aren't supported,
+    /// the whole file is reported as one extent, as it is on filesystems
+    /// which don't track holes. Extents may include ranges of zeros which
+    /// the filesystem has allocated.
+    pub fn file_extents<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<FileExtent>> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            data_extents(&self.dir.borrow().open(path)?)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns the ranges of the file at `path` which hold data, in order,
+    /// omitting the holes of a sparse file.
+    ///
+    /// See [`DirView::file_extents`].
+    pub fn file_extents<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Vec<FileExtent>> {
+        self.audited(Operation::Read, path, |path| {
+            self.check_resolve(Operation::Read, path)?;
+            data_extents(&self.dir.borrow().as_cap_std().open(path.as_std_path())?)
+        })
+    }
+}
+
+/// Returns the ranges of `file` which hold data.
+///
+/// This moves the position of `file`.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_vendor = "apple",
+))]
+pub(crate) fn data_extents(file: &File) -> io::Result<Vec<FileExtent>> {
+    use rustix::fs::{seek, SeekFrom};
+    use rustix::io::Errno;
+
+    let len = file.metadata()?.len();
+    let mut extents = Vec::new();
+    let mut offset = 0;
+    while offset < len {
+        let start = match seek(file, SeekFrom::Data(offset as i64)) {
+            Ok(start) => start,
+            // There's no more data before the end of the file.
+            Err(Errno::NXIO) => break,
+            // The filesystem doesn't support finding holes.
+            Err(Errno::INVAL | Errno::NOTSUP) if offset == 0 => return Ok(whole(len)),
+            Err(err) => return Err(err.into()),
+        };
+        if start >= len {
+            break;
+        }
+        let end = seek(file, SeekFrom::Hole(start as i64))?.min(len);
+        extents.push(FileExtent {
+            offset: start,
+            len: end - start,
+        });
+        offset = end;
+    }
+    Ok(extents)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_vendor = "apple",
+)))]
+pub(crate) fn data_extents(file: &File) -> io::Result<Vec<FileExtent>> {
+    Ok(whole(file.metadata()?.len()))
+}
+
+/// Returns a single extent covering a file of `len` bytes, if it isn't
+/// empty.
+fn whole(len: u64) -> Vec<FileExtent> {
+    if len == 0 {
+        return Vec::new();
+    }
+    vec![FileExtent { offset: 0, len }]
+}
@@ -0,0 +1,76 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::process::{Child, Command};
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Spawns `command` as a child process whose working directory is the
+    /// view's directory.
+    ///
+    /// This lets programs be run "inside" a view without the view's path,
+    /// which it may not have. On Unix, the child changes to the directory
+    /// with `fchdir` on a copy of the view's handle, just before it executes
+    /// the program, so relative paths in the program's name and arguments
+    /// are resolved in the directory. On Windows, the directory's path is
+    /// found from its handle. Any working directory set on `command` is
+    /// replaced.
+    ///
+    /// The child isn't confined to the view, and can access anything the
+    /// process could, so this is denied for views built with
+    /// [`DirViewBuilder::deny_exec`], including readonly views.
+    ///
+    /// [`DirViewBuilder::deny_exec`]: crate::DirViewBuilder::deny_exec
+    pub fn spawn_in(&self, command: Command) -> io::Result<Child> {
+        self.audited(Operation::Spawn, ".", |path| {
+            self.policy.check_exec(path)?;
+            spawn_in(self.dir.borrow(), command)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Spawns `command` as a child process whose working directory is the
+    /// view's directory.
+    ///
+    /// See [`DirView::spawn_in`].
+    pub fn spawn_in(&self, command: Command) -> io::Result<Child> {
+        self.audited(Operation::Spawn, ".", |path| {
+            self.policy.check_exec(path.as_std_path())?;
+            spawn_in(self.dir.borrow().as_cap_std(), command)
+        })
+    }
+}
+
+#[cfg(unix)]
+fn spawn_in(dir: &Dir, mut command: Command) -> io::Result<Child> {
+    use std::os::fd::OwnedFd;
+    use std::os::unix::process::CommandExt;
+
+    let dir = OwnedFd::from(dir.try_clone()?.into_std_file());
+    // SAFETY: `fchdir` is async-signal-safe, and the closure doesn't
+    // allocate.
+    unsafe {
+        command.pre_exec(move || Ok(rustix::process::fchdir(&dir)?));
+    }
+    command.spawn()
+}
+
+#[cfg(windows)]
+fn spawn_in(dir: &Dir, mut command: Command) -> io::Result<Child> {
+    use std::os::windows::io::AsRawHandle;
+
+    let path = crate::contains::final_path(dir.as_raw_handle())?;
+    command.current_dir(path).spawn()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn spawn_in(_dir: &Dir, _command: Command) -> io::Result<Child> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "spawning processes is not supported on this platform",
+    ))
+}
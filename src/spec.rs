@@ -0,0 +1,164 @@
+#[cfg(feature = "unicode-normalization")]
+use crate::NormalizationForm;
+use crate::{DirView, DirViewBuilder, ViewKind};
+use cap_std::fs::Dir;
+use cap_std::AmbientAuthority;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+/// A serializable description of a view to open.
+///
+/// This allows the layout of a set of views to be kept in a configuration
+/// file, and opened with [`ViewSpec::open`] or [`open_views`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ViewSpec {
+    /// The path of the directory to open.
+    pub path: PathBuf,
+
+    /// The kind of the view.
+    pub kind: ViewKind,
+
+    /// Restrictions to impose in addition to those implied by `kind`.
+    #[serde(default)]
+    pub options: ViewOptions,
+}
+
+/// Serializable restrictions for a [`ViewSpec`].
+///
+/// These correspond to the options of [`DirViewBuilder`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewOptions {
+    /// See [`DirViewBuilder::deny_symlinks`].
+    pub deny_symlinks: bool,
+
+    /// See [`DirViewBuilder::hide_hidden_files`].
+    pub hide_hidden_files: bool,
+
+    /// See [`DirViewBuilder::max_depth`].
+    pub max_depth: Option<usize>,
+
+    /// See [`DirViewBuilder::max_path_len`].
+    pub max_path_len: Option<usize>,
+
+    /// See [`DirViewBuilder::max_path_components`].
+    pub max_path_components: Option<usize>,
+
+    /// See [`DirViewBuilder::max_symlink_traversals`].
+    pub max_symlink_traversals: Option<usize>,
+
+    /// See [`DirViewBuilder::deny_unix_connect`].
+    pub deny_unix_connect: bool,
+
+    /// See [`DirViewBuilder::deny_exec`].
+    pub deny_exec: bool,
+
+    /// See [`DirViewBuilder::same_filesystem`].
+    pub same_filesystem: bool,
+
+    /// See [`DirViewBuilder::portable_names`].
+    pub portable_names: bool,
+
+    /// See [`DirViewBuilder::strict_paths`].
+    pub strict_paths: bool,
+
+    /// See [`DirViewBuilder::confine_symlink_targets`].
+    pub confine_symlink_targets: bool,
+
+    /// See [`DirViewBuilder::case_insensitive`].
+    pub case_insensitive: bool,
+
+    /// See [`DirViewBuilder::normalize_names`].
+    #[cfg(feature = "unicode-normalization")]
+    pub normalize_names: Option<NormalizationForm>,
+
+    /// The kinds of subtrees, keyed by their paths relative to the view's
+    /// directory. See [`DirViewBuilder::override_view_kind`].
+    pub kind_overrides: BTreeMap<PathBuf, ViewKind>,
+}
+
+impl ViewSpec {
+    /// Returns a [`DirViewBuilder`] configured according to `self`.
+    pub fn builder(&self) -> DirViewBuilder {
+        let mut builder = DirViewBuilder::new();
+        builder.view_kind(self.kind);
+        if self.options.deny_symlinks {
+            builder.deny_symlinks();
+        }
+        if self.options.hide_hidden_files {
+            builder.hide_hidden_files();
+        }
+        if let Some(max_depth) = self.options.max_depth {
+            builder.max_depth(max_depth);
+        }
+        if let Some(max_path_len) = self.options.max_path_len {
+            builder.max_path_len(max_path_len);
+        }
+        if let Some(max_path_components) = self.options.max_path_components {
+            builder.max_path_components(max_path_components);
+        }
+        if let Some(max_symlink_traversals) = self.options.max_symlink_traversals {
+            builder.max_symlink_traversals(max_symlink_traversals);
+        }
+        if self.options.deny_unix_connect {
+            builder.deny_unix_connect();
+        }
+        if self.options.deny_exec {
+            builder.deny_exec();
+        }
+        if self.options.same_filesystem {
+            builder.same_filesystem();
+        }
+        if self.options.portable_names {
+            builder.portable_names();
+        }
+        if self.options.strict_paths {
+            builder.strict_paths();
+        }
+        if self.options.confine_symlink_targets {
+            builder.confine_symlink_targets();
+        }
+        if self.options.case_insensitive {
+            builder.case_insensitive();
+        }
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(form) = self.options.normalize_names {
+            builder.normalize_names(form);
+        }
+        for (path, view_kind) in &self.options.kind_overrides {
+            builder.override_view_kind(path, *view_kind);
+        }
+        builder
+    }
+
+    /// Opens the view described by `self`, using the host process' ambient
+    /// authority.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn open(&self, ambient_authority: AmbientAuthority) -> io::Result<DirView> {
+        let dir = Dir::open_ambient_dir(&self.path, ambient_authority)?;
+        Ok(self.builder().build(dir))
+    }
+}
+
+/// Opens each of the views described by `specs`, using the host process'
+/// ambient authority.
+///
+/// # Ambient Authority
+///
+/// This function is not sandboxed and may access any path that the host
+/// process has access to.
+pub fn open_views<'a, I: IntoIterator<Item = &'a ViewSpec>>(
+    specs: I,
+    ambient_authority: AmbientAuthority,
+) -> io::Result<Vec<DirView>> {
+    specs
+        .into_iter()
+        .map(|spec| spec.open(ambient_authority))
+        .collect()
+}
@@ -0,0 +1,104 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+use cap_std::fs::Dir;
+use rustix::fs::{fstatvfs, StatVfs, StatVfsMountFlags};
+use std::borrow::Borrow;
+use std::io;
+use std::os::fd::AsFd;
+
+/// Statistics for a filesystem, returned by [`DirView::statfs`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FsStats {
+    total: u64,
+    free: u64,
+    available: u64,
+    files: u64,
+    files_free: u64,
+    flags: StatVfsMountFlags,
+}
+
+impl FsStats {
+    /// Returns the total size of the filesystem, in bytes.
+    #[inline]
+    pub fn total_space(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the amount of free space on the filesystem, in bytes,
+    /// including space reserved for privileged users.
+    #[inline]
+    pub fn free_space(&self) -> u64 {
+        self.free
+    }
+
+    /// Returns the amount of free space on the filesystem available to
+    /// unprivileged users, in bytes.
+    #[inline]
+    pub fn available_space(&self) -> u64 {
+        self.available
+    }
+
+    /// Returns the total number of file nodes on the filesystem.
+    #[inline]
+    pub fn total_files(&self) -> u64 {
+        self.files
+    }
+
+    /// Returns the number of free file nodes on the filesystem.
+    #[inline]
+    pub fn free_files(&self) -> u64 {
+        self.files_free
+    }
+
+    /// Returns `true` if the filesystem is mounted read-only.
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.flags.contains(StatVfsMountFlags::RDONLY)
+    }
+
+    /// Returns `true` if the filesystem ignores set-user-ID and set-group-ID
+    /// bits.
+    #[inline]
+    pub fn is_nosuid(&self) -> bool {
+        self.flags.contains(StatVfsMountFlags::NOSUID)
+    }
+}
+
+impl From<StatVfs> for FsStats {
+    fn from(stat: StatVfs) -> Self {
+        Self {
+            total: stat.f_blocks.saturating_mul(stat.f_frsize),
+            free: stat.f_bfree.saturating_mul(stat.f_frsize),
+            available: stat.f_bavail.saturating_mul(stat.f_frsize),
+            files: stat.f_files,
+            files_free: stat.f_ffree,
+            flags: stat.f_flag,
+        }
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns statistics for the filesystem containing `self`.
+    ///
+    /// This corresponds to `fstatvfs` on the view's directory.
+    #[inline]
+    pub fn statfs(&self) -> io::Result<FsStats> {
+        self.audited(Operation::Metadata, ".", |_| statfs(self.dir.borrow()))
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns statistics for the filesystem containing `self`.
+    ///
+    /// This corresponds to `fstatvfs` on the view's directory.
+    #[inline]
+    pub fn statfs(&self) -> io::Result<FsStats> {
+        self.audited(Operation::Metadata, ".", |_| statfs(self.dir.borrow()))
+    }
+}
+
+fn statfs<Fd: AsFd>(dir: Fd) -> io::Result<FsStats> {
+    Ok(fstatvfs(dir)?.into())
+}
@@ -0,0 +1,145 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation, ViewError};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counts of operations performed through a view, returned by
+/// [`DirView::stats`].
+///
+/// Counts are collected when enabled with
+/// [`DirViewBuilder::collect_stats`], and include operations through all
+/// views, iterators, and entries derived from the view, including with
+/// `try_clone`.
+///
+/// [`DirViewBuilder::collect_stats`]: crate::DirViewBuilder::collect_stats
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ViewStats {
+    opens: u64,
+    reads: u64,
+    writes: u64,
+    denials: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+impl ViewStats {
+    /// Returns the number of files and directories successfully opened or
+    /// created.
+    #[inline]
+    pub fn opens(&self) -> u64 {
+        self.opens
+    }
+
+    /// Returns the number of successful calls to `read` and
+    /// `read_to_string`.
+    #[inline]
+    pub fn reads(&self) -> u64 {
+        self.reads
+    }
+
+    /// Returns the number of successful calls to `write`.
+    #[inline]
+    pub fn writes(&self) -> u64 {
+        self.writes
+    }
+
+    /// Returns the number of operations the view denied.
+    #[inline]
+    pub fn denials(&self) -> u64 {
+        self.denials
+    }
+
+    /// Returns the number of bytes read by `read` and `read_to_string`.
+    ///
+    /// Reads through files opened from the view aren't counted.
+    #[inline]
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the number of bytes written by `write`.
+    ///
+    /// Writes through files opened from the view aren't counted.
+    #[inline]
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+}
+
+/// The counters behind [`ViewStats`], shared between views.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    opens: AtomicU64,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    denials: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl Counters {
+    /// Counts an operation with the given outcome.
+    pub(crate) fn record(&self, operation: Operation, outcome: Result<(), &io::Error>) {
+        let counter = match outcome {
+            Ok(()) => match operation {
+                Operation::Open
+                | Operation::OpenDir
+                | Operation::Create
+                | Operation::CreateTempFile => &self.opens,
+                Operation::Read => &self.reads,
+                Operation::Write => &self.writes,
+                _ => return,
+            },
+            Err(error) if ViewError::is_denial(error) => &self.denials,
+            Err(_) => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_bytes_read(&self, len: usize) {
+        self.bytes_read.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_bytes_written(&self, len: usize) {
+        self.bytes_written.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ViewStats {
+        ViewStats {
+            opens: self.opens.load(Ordering::Relaxed),
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            denials: self.denials.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns counts of the operations performed through `self` and the
+    /// views derived from it, if enabled with
+    /// [`DirViewBuilder::collect_stats`].
+    ///
+    /// [`DirViewBuilder::collect_stats`]: crate::DirViewBuilder::collect_stats
+    #[inline]
+    pub fn stats(&self) -> Option<ViewStats> {
+        self.policy.stats.as_ref().map(|stats| stats.snapshot())
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns counts of the operations performed through `self` and the
+    /// views derived from it, if enabled with
+    /// [`DirViewBuilder::collect_stats`].
+    ///
+    /// [`DirViewBuilder::collect_stats`]: crate::DirViewBuilder::collect_stats
+    #[inline]
+    pub fn stats(&self) -> Option<ViewStats> {
+        self.policy.stats.as_ref().map(|stats| stats.snapshot())
+    }
+}
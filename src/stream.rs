@@ -0,0 +1,45 @@
+use crate::{DirEntryView, ReadDirView};
+#[cfg(feature = "fs_utf8")]
+use crate::{DirEntryViewUtf8, ReadDirViewUtf8};
+use futures_core::Stream;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Bridges the synchronous iterator into async code.
+///
+/// Each entry is read synchronously when the stream is polled, so polling
+/// blocks the task for as long as reading the directory takes. For large
+/// directories, or directories on slow filesystems, prefer the async views,
+/// which read entries on a blocking thread pool.
+impl Stream for ReadDirView {
+    type Item = io::Result<DirEntryView>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().next())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        Iterator::size_hint(self)
+    }
+}
+
+/// Bridges the synchronous iterator into async code.
+///
+/// See the implementation for [`ReadDirView`].
+#[cfg(feature = "fs_utf8")]
+impl Stream for ReadDirViewUtf8 {
+    type Item = io::Result<DirEntryViewUtf8>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().next())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        Iterator::size_hint(self)
+    }
+}
@@ -0,0 +1,380 @@
+use crate::copy_dir::{check_copyable, copy_files};
+use crate::progress::{Monitor, Tracker};
+use crate::{CancellationToken, CopySymlinks, DirView, Operation, Progress, ReadDirView};
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Options for [`DirView::sync_to`].
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    compare: SyncCompare,
+    delete: bool,
+    symlinks: CopySymlinks,
+    concurrency: usize,
+    monitor: Monitor,
+}
+
+/// How [`DirView::sync_to`] decides whether a file needs to be copied.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SyncCompare {
+    /// Copy files whose size differs from the destination's, or which were
+    /// modified more recently than it.
+    SizeAndModified,
+
+    /// Copy files whose contents differ from the destination's.
+    Contents,
+}
+
+impl SyncOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Files are initially compared by size and modification time,
+    /// extraneous destination entries are kept, symbolic links are
+    /// preserved, and files are copied one at a time.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            compare: SyncCompare::SizeAndModified,
+            delete: false,
+            symlinks: CopySymlinks::Preserve,
+            concurrency: 1,
+            monitor: Monitor::default(),
+        }
+    }
+
+    /// Sets how files are compared to decide whether to copy them.
+    #[inline]
+    pub fn compare(&mut self, compare: SyncCompare) -> &mut Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Sets whether to delete destination entries which aren't in the
+    /// source.
+    #[inline]
+    pub fn delete(&mut self, delete: bool) -> &mut Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Sets how symbolic links are copied.
+    #[inline]
+    pub fn symlinks(&mut self, symlinks: CopySymlinks) -> &mut Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Sets the number of files to copy at once, on separate threads.
+    ///
+    /// With more than one, the changed files are copied once the rest of
+    /// the destination has been brought up to date.
+    #[inline]
+    pub fn concurrency(&mut self, concurrency: usize) -> &mut Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets a callback to call with the progress of the sync, as each file,
+    /// or chunk of a large file, is copied.
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.monitor.progress(progress);
+        self
+    }
+
+    /// Sets a token which can cancel the sync, which then fails with a
+    /// [`Cancelled`] error, leaving the changes made so far in place.
+    ///
+    /// [`Cancelled`]: crate::Cancelled
+    #[inline]
+    pub fn cancel(&mut self, token: CancellationToken) -> &mut Self {
+        self.monitor.cancel(token);
+        self
+    }
+}
+
+impl Default for SyncOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A summary of the changes made by [`DirView::sync_to`].
+///
+/// Paths are relative to the views.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncReport {
+    copied: Vec<PathBuf>,
+    deleted: Vec<PathBuf>,
+    unchanged: usize,
+}
+
+impl SyncReport {
+    /// Returns the entries copied to the destination: new directories, and
+    /// new or changed files and symbolic links.
+    #[inline]
+    pub fn copied(&self) -> &[PathBuf] {
+        &self.copied
+    }
+
+    /// Returns the entries deleted from the destination, either because
+    /// they weren't in the source, or because they were replaced by an
+    /// entry of a different type.
+    #[inline]
+    pub fn deleted(&self) -> &[PathBuf] {
+        &self.deleted
+    }
+
+    /// Returns the number of files and symbolic links which were already up
+    /// to date.
+    #[inline]
+    pub fn unchanged(&self) -> usize {
+        self.unchanged
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Mirrors the contents of `self` into `dest`, copying new and changed
+    /// entries and, if [`SyncOptions::delete`] is set, deleting entries
+    /// which aren't in `self`.
+    ///
+    /// Files' permissions are copied with their contents, and with the
+    /// `cap-fs-ext` feature, so are their timestamps. This fails before
+    /// changing anything if `dest` doesn't permit changes. If `dest` is a
+    /// dry-run view, the returned report describes what would be changed.
+    ///
+    /// The sync is made through the views, so their restrictions apply to
+    /// every entry, and each step is reported to their audit hooks.
+    pub fn sync_to<E: Borrow<Dir>>(
+        &self,
+        dest: &DirView<E>,
+        options: &SyncOptions,
+    ) -> io::Result<SyncReport> {
+        self.audited(Operation::SyncTo, ".", |_| {
+            dest.check_change(Operation::SyncTo, Path::new("."))?;
+            let mut report = SyncReport::default();
+            let mut tracker = Tracker::new(&options.monitor);
+            let mut files = (options.concurrency > 1).then(Vec::new);
+            self.sync_subdir(
+                Path::new(""),
+                dest,
+                true,
+                options,
+                &mut tracker,
+                &mut report,
+                files.as_mut(),
+            )?;
+            if let Some(files) = files {
+                copy_files(
+                    &self.try_clone()?,
+                    &dest.try_clone()?,
+                    files,
+                    options.concurrency,
+                    &options.monitor,
+                )?;
+            }
+            Ok(report)
+        })
+    }
+
+    /// Syncs the directory at `path`, which is empty for `self` itself.
+    /// `exists` says whether it already exists in `dest`. With `files`, the
+    /// files to copy are recorded there to copy later, instead of being
+    /// copied.
+    #[allow(clippy::too_many_arguments)]
+    fn sync_subdir<E: Borrow<Dir>>(
+        &self,
+        path: &Path,
+        dest: &DirView<E>,
+        exists: bool,
+        options: &SyncOptions,
+        tracker: &mut Tracker<'_>,
+        report: &mut SyncReport,
+        mut files: Option<&mut Vec<(PathBuf, PathBuf)>>,
+    ) -> io::Result<()> {
+        let mut names = HashSet::new();
+        for entry in self.read_dir_or_entries(path)? {
+            tracker.check()?;
+            let entry = entry?;
+            let name = entry.file_name();
+            let path = path.join(&name);
+            names.insert(name);
+
+            let mut file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                match options.symlinks {
+                    CopySymlinks::Preserve => {
+                        self.sync_symlink(&path, dest, exists, report)?;
+                        continue;
+                    }
+                    CopySymlinks::Follow => file_type = self.metadata(&path)?.file_type(),
+                    CopySymlinks::Skip => continue,
+                }
+            }
+            let existing = if exists { existing(dest, &path)? } else { None };
+
+            if file_type.is_dir() {
+                let exists = match existing {
+                    Some(existing) if existing.is_dir() => true,
+                    Some(_) => {
+                        remove(dest, &path, false, report)?;
+                        false
+                    }
+                    None => false,
+                };
+                if !exists {
+                    dest.create_dir(&path)?;
+                    report.copied.push(path.clone());
+                }
+                self.sync_subdir(
+                    &path,
+                    dest,
+                    exists,
+                    options,
+                    tracker,
+                    report,
+                    files.as_deref_mut(),
+                )?;
+                continue;
+            }
+
+            check_copyable(file_type, &path)?;
+            let metadata = self.metadata(&path)?;
+            if let Some(existing) = existing {
+                if !existing.is_file() {
+                    remove(dest, &path, existing.is_dir(), report)?;
+                } else if !self.changed(&path, &metadata, dest, &existing, options.compare)? {
+                    report.unchanged += 1;
+                    continue;
+                }
+            }
+            if let Some(files) = files.as_deref_mut() {
+                files.push((path.clone(), path.clone()));
+            } else {
+                self.copy_tracked(&path, dest, &path, tracker)?;
+                #[cfg(feature = "cap-fs-ext")]
+                dest.copy_times(&path, &metadata)?;
+            }
+            report.copied.push(path);
+        }
+
+        if options.delete && exists {
+            let mut extraneous = Vec::new();
+            for entry in dest.read_dir_or_entries(path)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                if !names.contains(&name) {
+                    extraneous.push((path.join(name), entry.file_type()?.is_dir()));
+                }
+            }
+            for (path, is_dir) in extraneous {
+                remove(dest, &path, is_dir, report)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_symlink<E: Borrow<Dir>>(
+        &self,
+        path: &Path,
+        dest: &DirView<E>,
+        exists: bool,
+        report: &mut SyncReport,
+    ) -> io::Result<()> {
+        let existing = if exists { existing(dest, path)? } else { None };
+        if let Some(existing) = existing {
+            if existing.is_symlink()
+                && dest.read_link_contents(path)? == self.read_link_contents(path)?
+            {
+                report.unchanged += 1;
+                return Ok(());
+            }
+            remove(dest, path, existing.is_dir(), report)?;
+        }
+        self.copy_symlink(path, dest, path)?;
+        report.copied.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Tests whether the file at `path`, with `metadata`, differs from the
+    /// one in `dest`, with `existing` metadata.
+    fn changed<E: Borrow<Dir>>(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        dest: &DirView<E>,
+        existing: &Metadata,
+        compare: SyncCompare,
+    ) -> io::Result<bool> {
+        if metadata.len() != existing.len() {
+            return Ok(true);
+        }
+        match compare {
+            SyncCompare::SizeAndModified => match (metadata.modified(), existing.modified()) {
+                (Ok(modified), Ok(existing)) => Ok(modified > existing),
+                _ => Ok(true),
+            },
+            SyncCompare::Contents => Ok(!same_contents(
+                self.open_budgeted_read(path)?,
+                dest.open_budgeted_read(path)?,
+            )?),
+        }
+    }
+
+    pub(crate) fn read_dir_or_entries(&self, path: &Path) -> io::Result<ReadDirView> {
+        if path.as_os_str().is_empty() {
+            self.entries()
+        } else {
+            self.read_dir(path)
+        }
+    }
+}
+
+/// Returns the metadata of `path` in `dest`, or `None` if it doesn't exist.
+fn existing<E: Borrow<Dir>>(dest: &DirView<E>, path: &Path) -> io::Result<Option<Metadata>> {
+    match dest.symlink_metadata(path) {
+        Ok(metadata) => Ok(Some(metadata)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn remove<E: Borrow<Dir>>(
+    dest: &DirView<E>,
+    path: &Path,
+    is_dir: bool,
+    report: &mut SyncReport,
+) -> io::Result<()> {
+    if is_dir {
+        dest.remove_dir_all(path)?;
+    } else {
+        dest.remove_file(path)?;
+    }
+    report.deleted.push(path.to_path_buf());
+    Ok(())
+}
+
+pub(crate) fn same_contents(mut a: impl Read, mut b: impl Read) -> io::Result<bool> {
+    let mut buf_a = [0; 8192];
+    let mut buf_b = [0; 8192];
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(b.read(&mut buf_b[..1])? == 0);
+        }
+        match b.read_exact(&mut buf_b[..n]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err),
+        }
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
@@ -0,0 +1,316 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{CopySymlinks, DirView};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path};
+use std::time::UNIX_EPOCH;
+
+/// The size of a tar block.
+const BLOCK: usize = 512;
+
+/// Options for [`DirView::write_tar`].
+#[derive(Clone, Debug)]
+pub struct TarOptions {
+    symlinks: CopySymlinks,
+    preserve_owner: bool,
+}
+
+impl TarOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Symbolic links are initially stored as links, and entries are
+    /// initially owned by user and group 0.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            symlinks: CopySymlinks::Preserve,
+            preserve_owner: false,
+        }
+    }
+
+    /// Sets how symbolic links are archived.
+    #[inline]
+    pub fn symlinks(&mut self, symlinks: CopySymlinks) -> &mut Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Sets whether to record the user and group IDs of entries, rather
+    /// than 0, so that the archive depends on who created the files.
+    #[inline]
+    pub fn preserve_owner(&mut self, preserve_owner: bool) -> &mut Self {
+        self.preserve_owner = preserve_owner;
+        self
+    }
+}
+
+impl Default for TarOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Writes a tar archive of the directory at `path` to `writer`.
+    ///
+    /// Entries are named relative to `path`, and written in order of their
+    /// paths, with each directory before its contents, so archiving the same
+    /// tree always produces the same archive. Modes and modification times
+    /// are recorded, but not access times, and entries other than files,
+    /// directories, and symbolic links are left out. Hard links are archived
+    /// as separate files.
+    ///
+    /// The archive is in the POSIX ustar format, with PAX extended headers
+    /// for paths and sizes which don't fit in it. It's read through the
+    /// view, so entries it hides are left out.
+    pub fn write_tar<P: AsRef<Path>, W: Write>(
+        &self,
+        path: P,
+        mut writer: W,
+        options: &TarOptions,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let dir = self.open_dir(if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        })?;
+        let follow = options.symlinks == CopySymlinks::Follow;
+        for entry in dir.walk()?.sort_by_file_name().follow_links(follow) {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = if follow {
+                dir.metadata(path)?
+            } else {
+                dir.symlink_metadata(path)?
+            };
+            let mut name = archive_name(path);
+            let file_type = metadata.file_type();
+            if file_type.is_symlink() {
+                if options.symlinks == CopySymlinks::Skip {
+                    continue;
+                }
+                let target = archive_name(&dir.read_link_contents(path)?);
+                write_header(&mut writer, &name, b'2', &target, 0, &metadata, options)?;
+            } else if file_type.is_dir() {
+                name.push(b'/');
+                write_header(&mut writer, &name, b'5', b"", 0, &metadata, options)?;
+            } else if file_type.is_file() {
+                let file = dir.open(path)?;
+                let metadata = file.metadata()?;
+                let len = metadata.len();
+                write_header(&mut writer, &name, b'0', b"", len, &metadata, options)?;
+                let copied = io::copy(&mut file.take(len), &mut writer)?;
+                if copied != len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "file shrank while it was being archived",
+                    ));
+                }
+                pad(&mut writer, len)?;
+            }
+        }
+        writer.write_all(&[0; 2 * BLOCK])?;
+        writer.flush()
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl DirViewUtf8 {
+    /// Writes a tar archive of the directory at `path` to `writer`.
+    ///
+    /// See [`DirView::write_tar`].
+    pub fn write_tar<P: AsRef<Utf8Path>, W: Write>(
+        &self,
+        path: P,
+        writer: W,
+        options: &TarOptions,
+    ) -> io::Result<()> {
+        self.try_clone()?.into_cap_std_view().write_tar(
+            path.as_ref().as_std_path(),
+            writer,
+            options,
+        )
+    }
+}
+
+/// Returns `path` with `/` separators, as tar and zip archives name
+/// entries.
+pub(crate) fn archive_name(path: &Path) -> Vec<u8> {
+    let mut name = Vec::new();
+    for component in path.components() {
+        let component = match component {
+            Component::RootDir => {
+                name.push(b'/');
+                continue;
+            }
+            Component::Normal(component) => component,
+            Component::CurDir => ".".as_ref(),
+            Component::ParentDir => "..".as_ref(),
+            Component::Prefix(prefix) => prefix.as_os_str(),
+        };
+        if !name.is_empty() && name.last() != Some(&b'/') {
+            name.push(b'/');
+        }
+        name.extend_from_slice(component.as_encoded_bytes());
+    }
+    name
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    name: &[u8],
+    kind: u8,
+    link: &[u8],
+    size: u64,
+    metadata: &Metadata,
+    options: &TarOptions,
+) -> io::Result<()> {
+    let (mode, uid, gid) = owner(metadata, options);
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.into_std().duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs());
+
+    let mut header = [0; BLOCK];
+    let mut pax = Vec::new();
+    match split_name(name) {
+        Some((prefix, name)) => {
+            header[345..345 + prefix.len()].copy_from_slice(prefix);
+            header[..name.len()].copy_from_slice(name);
+        }
+        None => {
+            pax_record(&mut pax, "path", name);
+            let truncated = &name[..name.len().min(100)];
+            header[..truncated.len()].copy_from_slice(truncated);
+        }
+    }
+    if link.len() > 100 {
+        pax_record(&mut pax, "linkpath", link);
+    } else {
+        header[157..157 + link.len()].copy_from_slice(link);
+    }
+    octal(&mut header[100..108], mode);
+    for (field, key, value) in [
+        (108..116, "uid", uid),
+        (116..124, "gid", gid),
+        (124..136, "size", size),
+        (136..148, "mtime", mtime),
+    ] {
+        if !octal(&mut header[field], value) {
+            pax_record(&mut pax, key, value.to_string().as_bytes());
+        }
+    }
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    if !pax.is_empty() {
+        let mut pax_header = [0; BLOCK];
+        let pax_name = [b"PaxHeaders/".as_slice(), &header[..100]].concat();
+        let pax_name = &pax_name[..pax_name
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(100)
+            .min(100)];
+        pax_header[..pax_name.len()].copy_from_slice(pax_name);
+        pax_header[100..156].copy_from_slice(&header[100..156]);
+        octal(&mut pax_header[124..136], pax.len() as u64);
+        pax_header[156] = b'x';
+        pax_header[257..265].copy_from_slice(&header[257..265]);
+        checksum(&mut pax_header);
+        writer.write_all(&pax_header)?;
+        writer.write_all(&pax)?;
+        pad(writer, pax.len() as u64)?;
+    }
+    checksum(&mut header);
+    writer.write_all(&header)
+}
+
+/// Returns the mode, user ID, and group ID to record for an entry.
+#[cfg(unix)]
+fn owner(metadata: &Metadata, options: &TarOptions) -> (u64, u64, u64) {
+    let mode = u64::from(metadata.mode() & 0o7777);
+    if options.preserve_owner {
+        (mode, metadata.uid().into(), metadata.gid().into())
+    } else {
+        (mode, 0, 0)
+    }
+}
+
+/// Returns the mode, user ID, and group ID to record for an entry, with a
+/// mode made up from its type and read-only flag.
+#[cfg(not(unix))]
+fn owner(metadata: &Metadata, _options: &TarOptions) -> (u64, u64, u64) {
+    let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+    let mode = if metadata.permissions().readonly() {
+        mode & !0o222
+    } else {
+        mode
+    };
+    (mode, 0, 0)
+}
+
+/// Splits `name` into the `prefix` and `name` fields of a ustar header, or
+/// returns `None` if it's too long for them.
+fn split_name(name: &[u8]) -> Option<(&[u8], &[u8])> {
+    if name.len() <= 100 {
+        return Some((b"", name));
+    }
+    // Split at the last `/` which leaves at most 100 bytes for the name,
+    // not counting a trailing `/`.
+    let body = name.strip_suffix(b"/").unwrap_or(name);
+    let start = name.len().saturating_sub(101);
+    let split = body[start..].iter().position(|b| *b == b'/')? + start;
+    let (prefix, rest) = (&name[..split], &name[split + 1..]);
+    (prefix.len() <= 155 && !rest.is_empty() && rest.len() <= 100).then_some((prefix, rest))
+}
+
+/// Writes `value` to `field` as NUL-terminated octal, returning `false` if
+/// it doesn't fit.
+fn octal(field: &mut [u8], value: u64) -> bool {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    if digits.len() >= field.len() {
+        return false;
+    }
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+    true
+}
+
+/// Appends a PAX extended header record, `"<len> <key>=<value>\n"`, where
+/// `len` counts its own digits.
+fn pax_record(pax: &mut Vec<u8>, key: &str, value: &[u8]) {
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + 1;
+    while len != rest + len.to_string().len() {
+        len = rest + len.to_string().len();
+    }
+    pax.extend_from_slice(format!("{len} {key}=").as_bytes());
+    pax.extend_from_slice(value);
+    pax.push(b'\n');
+}
+
+fn checksum(header: &mut [u8; BLOCK]) {
+    header[148..156].fill(b' ');
+    let sum = header.iter().map(|b| u64::from(*b)).sum::<u64>();
+    let digits = format!("{sum:06o}\0 ");
+    header[148..156].copy_from_slice(digits.as_bytes());
+}
+
+/// Pads a member of `len` bytes to a whole number of blocks.
+fn pad<W: Write>(writer: &mut W, len: u64) -> io::Result<()> {
+    let rem = (len % BLOCK as u64) as usize;
+    if rem != 0 {
+        writer.write_all(&[0; BLOCK][rem..])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::policy::Policy;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+use crate::{DirView, ViewKind};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::File;
+use cap_std::AmbientAuthority;
+use cap_tempfile::{TempDir, TempFile};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, io};
+
+impl DirView {
+    /// Creates a new anonymous temporary file in the directory at `path`.
+    ///
+    /// The file has no name, and is deleted when it is closed.
+    ///
+    /// This corresponds to `tempfile::tempfile_in`, but only accesses paths
+    /// relative to `self`.
+    pub fn tempfile_in<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        self.audited(Operation::CreateTempFile, path, |path| {
+            self.check_mutation(Operation::CreateTempFile, path)?;
+            self.policy
+                .check_unbudgeted(Operation::CreateTempFile, path)?;
+            let dir = self.dir.open_dir(path)?;
+            TempFile::new_anonymous(&dir)
+        })
+    }
+
+    /// Creates a new temporary directory in the directory at `path`.
+    ///
+    /// The temporary directory and its contents are deleted when the returned
+    /// [`TempDirView`] is dropped. Views of it have the same kind and
+    /// restrictions as `self`.
+    pub fn tempdir_in<P: AsRef<Path>>(&self, path: P) -> io::Result<TempDirView> {
+        self.audited(Operation::CreateTempDir, path, |path| {
+            self.check_mutation(Operation::CreateTempDir, path)?;
+            let depth =
+                self.policy
+                    .descend(&self.dir, self.depth, Operation::CreateTempDir, path)?
+                    + 1;
+            self.policy
+                .check_depth(depth, Operation::CreateTempDir, path)?;
+            let dir = self.dir.open_dir(path)?;
+            Ok(TempDirView {
+                temp_dir: TempDir::new_in(&dir)?,
+                view_kind: self.kind_for(Operation::CreateTempDir, path),
+                policy: Policy::with_permit(
+                    Policy::for_location(&self.policy, None),
+                    Operation::CreateTempDir,
+                    path,
+                )?,
+                depth,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl DirViewUtf8 {
+    /// Creates a new anonymous temporary file in the directory at `path`.
+    ///
+    /// The file has no name, and is deleted when it is closed.
+    ///
+    /// This corresponds to `tempfile::tempfile_in`, but only accesses paths
+    /// relative to `self`.
+    pub fn tempfile_in<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<cap_std::fs_utf8::File> {
+        self.audited(Operation::CreateTempFile, path, |path| {
+            self.check_mutation(Operation::CreateTempFile, path)?;
+            self.policy
+                .check_unbudgeted(Operation::CreateTempFile, path.as_std_path())?;
+            let dir = self.dir.open_dir(path)?;
+            Ok(cap_std::fs_utf8::File::from_cap_std(
+                TempFile::new_anonymous(dir.as_cap_std())?,
+            ))
+        })
+    }
+
+    /// Creates a new temporary directory in the directory at `path`.
+    ///
+    /// The temporary directory and its contents are deleted when the returned
+    /// [`TempDirView`] is dropped. Views of it have the same kind and
+    /// restrictions as `self`.
+    pub fn tempdir_in<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<TempDirView> {
+        self.audited(Operation::CreateTempDir, path, |path| {
+            self.check_mutation(Operation::CreateTempDir, path)?;
+            let depth = self.policy.descend(
+                self.dir.as_cap_std(),
+                self.depth,
+                Operation::CreateTempDir,
+                path.as_std_path(),
+            )? + 1;
+            self.policy
+                .check_depth(depth, Operation::CreateTempDir, path.as_std_path())?;
+            let dir = self.dir.open_dir(path)?;
+            Ok(TempDirView {
+                temp_dir: TempDir::new_in(dir.as_cap_std())?,
+                view_kind: self.kind_for(Operation::CreateTempDir, path),
+                policy: Policy::with_permit(
+                    Policy::for_location(&self.policy, None),
+                    Operation::CreateTempDir,
+                    path.as_std_path(),
+                )?,
+                depth,
+            })
+        })
+    }
+}
+
+/// A temporary directory which is deleted when dropped, and which yields
+/// views of itself.
+///
+/// This wraps [`cap_tempfile::TempDir`].
+pub struct TempDirView {
+    temp_dir: TempDir,
+    view_kind: ViewKind,
+    policy: Arc<Policy>,
+    depth: usize,
+}
+
+impl TempDirView {
+    /// Creates a new temporary directory in the host's temporary directory,
+    /// using the host process' ambient authority, with the given view kind.
+    ///
+    /// # Ambient Authority
+    ///
+    /// This function is not sandboxed and may access any path that the host
+    /// process has access to.
+    pub fn new(view_kind: ViewKind, ambient_authority: AmbientAuthority) -> io::Result<Self> {
+        Ok(Self {
+            temp_dir: TempDir::new(ambient_authority)?,
+            view_kind,
+            policy: Arc::default(),
+            depth: 0,
+        })
+    }
+
+    /// Returns a new view of the temporary directory.
+    ///
+    /// The view has its own handle to the directory, which is deleted when
+    /// `self` is dropped regardless of any outstanding views.
+    pub fn view(&self) -> io::Result<DirView> {
+        Ok(DirView {
+            dir: self.temp_dir.try_clone()?,
+            view_kind: self.view_kind,
+            policy: Arc::clone(&self.policy),
+            depth: self.depth,
+        })
+    }
+
+    /// Returns a new UTF-8 view of the temporary directory.
+    ///
+    /// The view has its own handle to the directory, which is deleted when
+    /// `self` is dropped regardless of any outstanding views.
+    #[cfg(feature = "fs_utf8")]
+    pub fn view_utf8(&self) -> io::Result<DirViewUtf8> {
+        Ok(DirViewUtf8 {
+            dir: cap_std::fs_utf8::Dir::from_cap_std(self.temp_dir.try_clone()?),
+            view_kind: self.view_kind,
+            policy: Arc::clone(&self.policy),
+            depth: self.depth,
+        })
+    }
+
+    /// Deletes the temporary directory and its contents, reporting any
+    /// errors.
+    ///
+    /// Dropping `self` also deletes the directory, but ignores errors.
+    pub fn close(self) -> io::Result<()> {
+        self.temp_dir.close()
+    }
+}
+
+impl fmt::Debug for TempDirView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut b = f.debug_struct("TempDirView");
+        b.field("dir", &*self.temp_dir);
+        b.field("view_kind", &self.view_kind);
+        b.finish()
+    }
+}
@@ -0,0 +1,993 @@
+use crate::backend::{Backend, Restrictions, ViewType};
+use crate::policy::Policy;
+use crate::{FileTypeSummary, Operation, ViewError, ViewKind};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+use std::{fmt, vec};
+
+/// The most symbolic links followed in resolving a path, as on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// A view of an in-memory directory tree, for tests.
+///
+/// This has the same method surface as [`DirView`], but its tree lives in
+/// memory, so unit tests of code which works with views needn't touch the
+/// filesystem. It supports files, directories, symbolic links, and modes,
+/// and views of it may be readonly, denying changes as a readonly
+/// [`DirView`] does.
+///
+/// Clones, and views returned by [`Self::open_dir`] and [`Self::readonly`],
+/// share the same tree. Paths are resolved as in a view, so paths which
+/// would lead outside of a view, through `..` or an absolute symbolic link,
+/// are denied.
+///
+/// Modes are enforced as for an unprivileged user: files without the
+/// owner's write bit can't be opened for writing, and entries can't be
+/// created in or removed from directories without it.
+///
+/// ```
+/// # use dir_view::MemDirView;
+/// let view = MemDirView::new();
+/// view.create_dir_all("a/b")?;
+/// view.write("a/b/file", "hello")?;
+///
+/// let readonly = view.readonly();
+/// assert_eq!(readonly.read_to_string("a/b/file")?, "hello");
+/// assert!(readonly.remove_file("a/b/file").is_err());
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// [`DirView`]: crate::DirView
+#[derive(Clone)]
+pub struct MemDirView {
+    tree: Arc<Mutex<Tree>>,
+
+    /// The inode of this view's root directory.
+    root: u64,
+    view_kind: ViewKind,
+    policy: Arc<Policy>,
+}
+
+struct Tree {
+    nodes: HashMap<u64, Node>,
+    next_ino: u64,
+}
+
+struct Node {
+    kind: NodeKind,
+    mode: u32,
+    modified: SystemTime,
+}
+
+enum NodeKind {
+    /// A file's contents are shared with open handles, which keep working
+    /// after the file is removed, as on Unix.
+    File(Arc<Mutex<FileData>>),
+    Dir(BTreeMap<OsString, u64>),
+    Symlink(PathBuf),
+}
+
+struct FileData {
+    contents: Vec<u8>,
+    modified: SystemTime,
+}
+
+impl Tree {
+    fn node(&self, ino: u64) -> &Node {
+        &self.nodes[&ino]
+    }
+
+    fn children(&self, ino: u64) -> io::Result<&BTreeMap<OsString, u64>> {
+        match &self.node(ino).kind {
+            NodeKind::Dir(children) => Ok(children),
+            _ => Err(not_a_directory()),
+        }
+    }
+
+    fn children_mut(&mut self, ino: u64) -> io::Result<&mut BTreeMap<OsString, u64>> {
+        match &mut self.nodes.get_mut(&ino).unwrap().kind {
+            NodeKind::Dir(children) => Ok(children),
+            _ => Err(not_a_directory()),
+        }
+    }
+
+    fn insert(&mut self, kind: NodeKind, mode: u32) -> u64 {
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.nodes.insert(
+            ino,
+            Node {
+                kind,
+                mode,
+                modified: SystemTime::now(),
+            },
+        );
+        ino
+    }
+
+    /// Removes `ino` and everything beneath it.
+    fn remove_all(&mut self, ino: u64) {
+        if let Some(Node {
+            kind: NodeKind::Dir(children),
+            ..
+        }) = self.nodes.remove(&ino)
+        {
+            for child in children.into_values() {
+                self.remove_all(child);
+            }
+        }
+    }
+
+    fn metadata(&self, ino: u64) -> MemMetadata {
+        let node = self.node(ino);
+        let (kind, len, modified) = match &node.kind {
+            NodeKind::File(data) => {
+                let data = data.lock().unwrap();
+                (
+                    FileTypeSummary::File,
+                    data.contents.len() as u64,
+                    data.modified,
+                )
+            }
+            NodeKind::Dir(children) => (FileTypeSummary::Dir, children.len() as u64, node.modified),
+            NodeKind::Symlink(target) => (
+                FileTypeSummary::Symlink,
+                target.as_os_str().len() as u64,
+                node.modified,
+            ),
+        };
+        MemMetadata {
+            kind,
+            len,
+            mode: node.mode,
+            modified,
+            ino,
+        }
+    }
+}
+
+impl MemDirView {
+    /// Constructs a full view of a new, empty tree.
+    pub fn new() -> Self {
+        let mut tree = Tree {
+            nodes: HashMap::new(),
+            next_ino: 1,
+        };
+        let root = tree.insert(NodeKind::Dir(BTreeMap::new()), 0o755);
+        Self {
+            tree: Arc::new(Mutex::new(tree)),
+            root,
+            view_kind: ViewKind::Full,
+            policy: Arc::default(),
+        }
+    }
+
+    /// Returns the kind of this view.
+    #[inline]
+    pub fn view_kind(&self) -> ViewKind {
+        self.view_kind
+    }
+
+    /// Returns a readonly view of the same tree.
+    ///
+    /// Changes made through `self` remain visible through the new view.
+    pub fn readonly(&self) -> Self {
+        Self {
+            tree: Arc::clone(&self.tree),
+            root: self.root,
+            view_kind: ViewKind::Readonly,
+            policy: Arc::clone(&self.policy),
+        }
+    }
+
+    /// Attempts to open a directory, as a view of the same kind.
+    pub fn open_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Self> {
+        let tree = self.lock();
+        let ino = self.resolve(&tree, Operation::OpenDir, path.as_ref(), true)?;
+        tree.children(ino)?;
+        Ok(Self {
+            tree: Arc::clone(&self.tree),
+            root: ino,
+            view_kind: self.view_kind,
+            policy: Arc::clone(&self.policy),
+        })
+    }
+
+    /// Attempts to open a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<MemFile> {
+        let tree = self.lock();
+        let ino = self.resolve(&tree, Operation::Open, path.as_ref(), true)?;
+        match &tree.node(ino).kind {
+            NodeKind::File(data) => Ok(MemFile::new(Arc::clone(data), false)),
+            NodeKind::Dir(_) => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "attempted to open a directory as a file",
+            )),
+            NodeKind::Symlink(_) => unreachable!(),
+        }
+    }
+
+    /// Opens a file in write-only mode, creating it if it doesn't exist, and
+    /// truncating it if it does.
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<MemFile> {
+        let path = path.as_ref();
+        self.check_writable(Operation::Create, path)?;
+        let mut tree = self.lock();
+        let data = match self.resolve(&tree, Operation::Create, path, true) {
+            Ok(ino) => match &tree.node(ino).kind {
+                NodeKind::File(data) => {
+                    check_mode(tree.node(ino).mode)?;
+                    let data = Arc::clone(data);
+                    let mut locked = data.lock().unwrap();
+                    locked.contents.clear();
+                    locked.modified = SystemTime::now();
+                    drop(locked);
+                    data
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::IsADirectory,
+                        "attempted to open a directory as a file",
+                    ))
+                }
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let data = Arc::new(Mutex::new(FileData {
+                    contents: Vec::new(),
+                    modified: SystemTime::now(),
+                }));
+                self.link(&mut tree, Operation::Create, path, |tree| {
+                    tree.insert(NodeKind::File(Arc::clone(&data)), 0o644)
+                })?;
+                data
+            }
+            Err(error) => return Err(error),
+        };
+        Ok(MemFile::new(data, true))
+    }
+
+    /// Read the entire contents of a file into a bytes vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.open(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Read the entire contents of a file into a string.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let mut contents = String::new();
+        self.open(path)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Write a slice as the entire contents of a file.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> io::Result<()> {
+        self.create(path)?.write_all(contents.as_ref())
+    }
+
+    /// Creates a new, empty directory at the provided path.
+    pub fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.check_writable(Operation::CreateDir, path)?;
+        let mut tree = self.lock();
+        self.link(&mut tree, Operation::CreateDir, path, |tree| {
+            tree.insert(NodeKind::Dir(BTreeMap::new()), 0o755)
+        })
+    }
+
+    /// Recursively create a directory and all of its parent components if
+    /// they are missing.
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.check_writable(Operation::CreateDir, path)?;
+        let mut partial = PathBuf::new();
+        for component in path.components() {
+            partial.push(component);
+            if matches!(component, Component::Normal(_)) && !self.is_dir(&partial) {
+                self.create_dir(&partial)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a new symbolic link at `dst` pointing to `src`.
+    ///
+    /// As with [`DirView::symlink`], `src` is stored as is, and is resolved
+    /// when the link is followed.
+    ///
+    /// [`DirView::symlink`]: crate::DirView::symlink
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
+        let dst = dst.as_ref();
+        self.check_writable(Operation::Symlink, dst)?;
+        let src = src.as_ref().to_path_buf();
+        let mut tree = self.lock();
+        self.link(&mut tree, Operation::Symlink, dst, |tree| {
+            tree.insert(NodeKind::Symlink(src), 0o777)
+        })
+    }
+
+    /// Removes a file from the tree.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.check_writable(Operation::RemoveFile, path)?;
+        let mut tree = self.lock();
+        let (parent, name) = self.resolve_parent(&tree, Operation::RemoveFile, path)?;
+        let ino = *tree.children(parent)?.get(&name).ok_or_else(not_found)?;
+        if let NodeKind::Dir(_) = tree.node(ino).kind {
+            return Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "attempted to remove a directory as a file",
+            ));
+        }
+        check_mode(tree.node(parent).mode)?;
+        tree.children_mut(parent)?.remove(&name);
+        tree.remove_all(ino);
+        Ok(())
+    }
+
+    /// Removes an empty directory.
+    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.remove_dir_impl(Operation::RemoveDir, path.as_ref(), false)
+    }
+
+    /// Removes a directory at this path, after removing all its contents.
+    pub fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.remove_dir_impl(Operation::RemoveDirAll, path.as_ref(), true)
+    }
+
+    fn remove_dir_impl(&self, operation: Operation, path: &Path, all: bool) -> io::Result<()> {
+        self.check_writable(operation, path)?;
+        let mut tree = self.lock();
+        let (parent, name) = self.resolve_parent(&tree, operation, path)?;
+        let ino = *tree.children(parent)?.get(&name).ok_or_else(not_found)?;
+        if !tree.children(ino)?.is_empty() && !all {
+            return Err(io::Error::new(
+                io::ErrorKind::DirectoryNotEmpty,
+                "directory not empty",
+            ));
+        }
+        check_mode(tree.node(parent).mode)?;
+        tree.children_mut(parent)?.remove(&name);
+        tree.remove_all(ino);
+        Ok(())
+    }
+
+    /// Rename a file or directory to a new name, replacing the original file
+    /// if `to` already exists.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.check_writable(Operation::Rename, from)?;
+        let mut tree = self.lock();
+        let (from_parent, from_name) = self.resolve_parent(&tree, Operation::Rename, from)?;
+        let (to_parent, to_name) = self.resolve_parent(&tree, Operation::Rename, to)?;
+        let ino = *tree
+            .children(from_parent)?
+            .get(&from_name)
+            .ok_or_else(not_found)?;
+        check_mode(tree.node(from_parent).mode)?;
+        check_mode(tree.node(to_parent).mode)?;
+        // A directory can't be moved into itself.
+        if self.is_within(&tree, to_parent, ino) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "attempted to move a directory into itself",
+            ));
+        }
+        if let Some(&replaced) = tree.children(to_parent)?.get(&to_name) {
+            if replaced == ino {
+                return Ok(());
+            }
+            match (&tree.node(ino).kind, &tree.node(replaced).kind) {
+                (NodeKind::Dir(_), NodeKind::Dir(children)) if !children.is_empty() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::DirectoryNotEmpty,
+                        "directory not empty",
+                    ))
+                }
+                (NodeKind::Dir(_), NodeKind::Dir(_)) => {}
+                (NodeKind::Dir(_), _) => return Err(not_a_directory()),
+                (_, NodeKind::Dir(_)) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::IsADirectory,
+                        "attempted to replace a directory with a file",
+                    ))
+                }
+                _ => {}
+            }
+            tree.remove_all(replaced);
+        }
+        tree.children_mut(from_parent)?.remove(&from_name);
+        tree.children_mut(to_parent)?.insert(to_name, ino);
+        Ok(())
+    }
+
+    /// Sets the mode of the entry at `path`, following a symbolic link.
+    ///
+    /// Only the permission bits, `0o7777`, are used.
+    pub fn set_mode<P: AsRef<Path>>(&self, path: P, mode: u32) -> io::Result<()> {
+        let path = path.as_ref();
+        self.check_writable(Operation::SetPermissions, path)?;
+        let mut tree = self.lock();
+        let ino = self.resolve(&tree, Operation::SetPermissions, path, true)?;
+        tree.nodes.get_mut(&ino).unwrap().mode = mode & 0o7777;
+        Ok(())
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let tree = self.lock();
+        let ino = self.resolve(&tree, Operation::ReadLink, path.as_ref(), false)?;
+        match &tree.node(ino).kind {
+            NodeKind::Symlink(target) => Ok(target.clone()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a symbolic link",
+            )),
+        }
+    }
+
+    /// Returns the canonical form of a path, relative to this view's
+    /// directory, with all intermediate components normalized and symbolic
+    /// links resolved.
+    pub fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let tree = self.lock();
+        let (_, resolved) =
+            self.resolve_names(&tree, Operation::Canonicalize, path.as_ref(), true)?;
+        Ok(resolved)
+    }
+
+    /// Given a path, query the tree to get information about a file,
+    /// directory, etc.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<MemMetadata> {
+        let tree = self.lock();
+        let ino = self.resolve(&tree, Operation::Metadata, path.as_ref(), true)?;
+        Ok(tree.metadata(ino))
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    pub fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<MemMetadata> {
+        let tree = self.lock();
+        let ino = self.resolve(&tree, Operation::Metadata, path.as_ref(), false)?;
+        Ok(tree.metadata(ino))
+    }
+
+    /// Queries metadata about this directory.
+    pub fn dir_metadata(&self) -> io::Result<MemMetadata> {
+        self.metadata(".")
+    }
+
+    /// Returns an iterator over the entries within `self`.
+    pub fn entries(&self) -> io::Result<MemReadDir> {
+        self.read_dir(".")
+    }
+
+    /// Returns an iterator over the entries within a directory, in order of
+    /// their names.
+    ///
+    /// The entries are read when this is called, so changes made while
+    /// iterating aren't seen.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<MemReadDir> {
+        let tree = self.lock();
+        let ino = self.resolve(&tree, Operation::ReadDir, path.as_ref(), true)?;
+        let entries = tree
+            .children(ino)?
+            .iter()
+            .map(|(name, ino)| MemDirEntry {
+                name: name.clone(),
+                metadata: tree.metadata(*ino),
+            })
+            .collect::<Vec<_>>();
+        Ok(MemReadDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Returns `true` if the path points at an existing entity.
+    #[inline]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Returns `true` if the path points at an existing entity, or an error
+    /// if its existence can't be determined.
+    pub fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        match self.metadata(path) {
+            Ok(_) => Ok(true),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns `true` if the path points at a regular file.
+    #[inline]
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_file())
+    }
+
+    /// Checks if `path` is a directory.
+    #[inline]
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tree> {
+        self.tree.lock().unwrap()
+    }
+
+    /// Returns the restrictions of `self`, over its tree.
+    fn restrictions(&self) -> Restrictions<'_, Self> {
+        Restrictions {
+            backend: self,
+            policy: &self.policy,
+            view_kind: self.view_kind,
+            view_type: ViewType::MemDirView,
+        }
+    }
+
+    /// Checks whether `operation` may change `path`. Changes aren't
+    /// recorded, so dry-run views deny them.
+    fn check_writable(&self, operation: Operation, path: &Path) -> io::Result<()> {
+        self.restrictions().check_mutation(operation, path)
+    }
+
+    fn escape(operation: Operation, path: &Path) -> io::Error {
+        ViewError::denied(
+            io::ErrorKind::PermissionDenied,
+            "a path led outside of the view",
+            operation,
+            path,
+            false,
+        )
+        .into()
+    }
+
+    /// Creates a new entry at `path` with `insert`, failing if something is
+    /// already there.
+    fn link(
+        &self,
+        tree: &mut Tree,
+        operation: Operation,
+        path: &Path,
+        insert: impl FnOnce(&mut Tree) -> u64,
+    ) -> io::Result<()> {
+        let (parent, name) = self.resolve_parent(tree, operation, path)?;
+        if tree.children(parent)?.contains_key(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "entry already exists",
+            ));
+        }
+        check_mode(tree.node(parent).mode)?;
+        let ino = insert(tree);
+        tree.children_mut(parent)?.insert(name, ino);
+        tree.nodes.get_mut(&parent).unwrap().modified = SystemTime::now();
+        Ok(())
+    }
+
+    /// Tests whether the directory `ino` is `ancestor` or beneath it.
+    fn is_within(&self, tree: &Tree, ino: u64, ancestor: u64) -> bool {
+        if ino == ancestor {
+            return true;
+        }
+        let Ok(children) = tree.children(ancestor) else {
+            return false;
+        };
+        children
+            .values()
+            .any(|child| self.is_within(tree, ino, *child))
+    }
+
+    /// Resolves the parent directory of `path`, returning its inode and the
+    /// name of the last component of `path`.
+    fn resolve_parent(
+        &self,
+        tree: &Tree,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<(u64, OsString)> {
+        let Some(Component::Normal(name)) = path.components().next_back() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path doesn't end in a file name",
+            ));
+        };
+        let parent = path.parent().unwrap_or(Path::new(""));
+        let parent = self.resolve(tree, operation, parent, true)?;
+        tree.children(parent)?;
+        Ok((parent, name.to_os_string()))
+    }
+
+    /// Resolves `path` to an inode, following symbolic links in every
+    /// component but the last, and in the last if `follow`.
+    fn resolve(
+        &self,
+        tree: &Tree,
+        operation: Operation,
+        path: &Path,
+        follow: bool,
+    ) -> io::Result<u64> {
+        let (ino, _) = self.resolve_names(tree, operation, path, follow)?;
+        Ok(ino)
+    }
+
+    /// Like [`Self::resolve`], also returning the path of the inode from
+    /// this view's directory.
+    fn resolve_names(
+        &self,
+        tree: &Tree,
+        operation: Operation,
+        path: &Path,
+        follow: bool,
+    ) -> io::Result<(u64, PathBuf)> {
+        // This view's directory may have been removed through another view.
+        if !tree.nodes.contains_key(&self.root) {
+            return Err(not_found());
+        }
+        // The inodes of the directories leading to the current one, so that
+        // `..` can return to them.
+        let mut stack = vec![self.root];
+        // The names of the directories in `stack`, below the root.
+        let mut resolved = PathBuf::new();
+        // Components still to be resolved, in reverse order.
+        let mut pending = Vec::new();
+        push_components(&mut pending, path).map_err(|()| Self::escape(operation, path))?;
+        let mut symlinks = 0;
+        while let Some(component) = pending.pop() {
+            let current = *stack.last().unwrap();
+            if component == ".." {
+                tree.children(current)?;
+                if stack.len() == 1 {
+                    return Err(Self::escape(operation, path));
+                }
+                stack.pop();
+                resolved.pop();
+                continue;
+            }
+            let ino = *tree
+                .children(current)?
+                .get(&component)
+                .ok_or_else(not_found)?;
+            match &tree.node(ino).kind {
+                NodeKind::Symlink(target) if !pending.is_empty() || follow => {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "too many levels of symbolic links",
+                        ));
+                    }
+                    push_components(&mut pending, target)
+                        .map_err(|()| Self::escape(operation, path))?;
+                }
+                _ => {
+                    stack.push(ino);
+                    resolved.push(component);
+                }
+            }
+        }
+        Ok((*stack.last().unwrap(), resolved))
+    }
+}
+
+impl Backend for MemDirView {
+    fn entry_type(&self, path: &Path) -> io::Result<FileTypeSummary> {
+        Ok(self.symlink_metadata(path)?.file_type())
+    }
+
+    #[inline]
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        Self::read_link(self, path)
+    }
+
+    fn entry_names(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        let path = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        self.read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.name))
+            .collect()
+    }
+
+    #[inline]
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Self::canonicalize(self, path)
+    }
+
+    #[inline]
+    fn crosses_filesystem(&self, _path: &Path) -> io::Result<bool> {
+        Ok(false)
+    }
+
+    #[cfg(windows)]
+    #[inline]
+    fn crosses_reparse_point(&self, _path: &Path) -> io::Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Default for MemDirView {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MemDirView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemDirView")
+            .field("root", &self.root)
+            .field("view_kind", &self.view_kind)
+            .finish()
+    }
+}
+
+/// Pushes the components of `path` onto `pending`, in reverse order, or
+/// fails if it's absolute.
+fn push_components(pending: &mut Vec<OsString>, path: &Path) -> Result<(), ()> {
+    for component in path.components().rev() {
+        match component {
+            Component::Normal(name) => pending.push(name.to_os_string()),
+            Component::ParentDir => pending.push("..".into()),
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return Err(()),
+        }
+    }
+    Ok(())
+}
+
+/// Fails if `mode` lacks the owner's write bit.
+fn check_mode(mode: u32) -> io::Result<()> {
+    if mode & 0o200 == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "permission denied",
+        ));
+    }
+    Ok(())
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file or directory")
+}
+
+fn not_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::NotADirectory, "not a directory")
+}
+
+/// Metadata about an entry in a [`MemDirView`].
+#[derive(Clone, Debug)]
+pub struct MemMetadata {
+    kind: FileTypeSummary,
+    len: u64,
+    mode: u32,
+    modified: SystemTime,
+    ino: u64,
+}
+
+impl MemMetadata {
+    /// Returns the type of the entry.
+    #[inline]
+    pub fn file_type(&self) -> FileTypeSummary {
+        self.kind
+    }
+
+    /// Returns `true` if this is a directory.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileTypeSummary::Dir
+    }
+
+    /// Returns `true` if this is a regular file.
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        self.kind == FileTypeSummary::File
+    }
+
+    /// Returns `true` if this is a symbolic link.
+    #[inline]
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileTypeSummary::Symlink
+    }
+
+    /// Returns the size of the file in bytes, or the number of entries in a
+    /// directory.
+    #[allow(clippy::len_without_is_empty)]
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the permission bits of the entry's mode.
+    #[inline]
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Returns `true` if the entry lacks the owner's write bit.
+    #[inline]
+    pub fn readonly(&self) -> bool {
+        self.mode & 0o200 == 0
+    }
+
+    /// Returns the last modification time.
+    #[inline]
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    /// Returns a number identifying the entry within its tree, as an inode
+    /// number does.
+    #[inline]
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+}
+
+/// A file opened from a [`MemDirView`].
+pub struct MemFile {
+    data: Arc<Mutex<FileData>>,
+    pos: u64,
+    writable: bool,
+}
+
+impl MemFile {
+    fn new(data: Arc<Mutex<FileData>>, writable: bool) -> Self {
+        Self {
+            data,
+            pos: 0,
+            writable,
+        }
+    }
+
+    /// Returns the current size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.data.lock().unwrap().contents.len() as u64
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Truncates or extends the file to `size` bytes.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        self.check_writable()?;
+        let size = usize::try_from(size)
+            .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "file size is too large"))?;
+        let mut data = self.data.lock().unwrap();
+        data.contents.resize(size, 0);
+        data.modified = SystemTime::now();
+        Ok(())
+    }
+
+    fn check_writable(&self) -> io::Result<()> {
+        if !self.writable {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file was opened read-only",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.data.lock().unwrap();
+        let start = usize::try_from(self.pos)
+            .unwrap_or(usize::MAX)
+            .min(data.contents.len());
+        let len = buf.len().min(data.contents.len() - start);
+        buf[..len].copy_from_slice(&data.contents[start..start + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_writable()?;
+        let mut data = self.data.lock().unwrap();
+        let start = usize::try_from(self.pos)
+            .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "file size is too large"))?;
+        if data.contents.len() < start + buf.len() {
+            data.contents.resize(start + buf.len(), 0);
+        }
+        data.contents[start..start + buf.len()].copy_from_slice(buf);
+        data.modified = SystemTime::now();
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+impl fmt::Debug for MemFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemFile")
+            .field("pos", &self.pos)
+            .field("writable", &self.writable)
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a directory in a [`MemDirView`].
+#[derive(Debug)]
+pub struct MemReadDir {
+    entries: vec::IntoIter<MemDirEntry>,
+}
+
+impl Iterator for MemReadDir {
+    type Item = io::Result<MemDirEntry>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Ok)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// An entry of a directory in a [`MemDirView`].
+#[derive(Clone, Debug)]
+pub struct MemDirEntry {
+    name: OsString,
+    metadata: MemMetadata,
+}
+
+impl MemDirEntry {
+    /// Returns the bare file name of this entry, without any other leading
+    /// path components.
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the type of this entry, without following a symbolic link.
+    #[inline]
+    pub fn file_type(&self) -> io::Result<FileTypeSummary> {
+        Ok(self.metadata.kind)
+    }
+
+    /// Returns the metadata for this entry, without following a symbolic
+    /// link.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<MemMetadata> {
+        Ok(self.metadata.clone())
+    }
+
+    /// Returns the entry's inode number.
+    #[inline]
+    pub fn ino(&self) -> u64 {
+        self.metadata.ino
+    }
+}
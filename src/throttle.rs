@@ -0,0 +1,272 @@
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::io::{self, BufRead, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fmt, thread};
+
+/// A limit on the rate at which bytes are read and written through views,
+/// set with [`DirViewBuilder::rate_limit`].
+///
+/// The limits are token buckets, which permit bursts of up to one second's
+/// worth of bytes, and then make readers and writers wait for the bytes
+/// they've transferred beyond that. Clones share the same buckets, so one
+/// limiter can throttle several views together.
+///
+/// ```
+/// # use dir_view::{DirViewBuilder, RateLimiter};
+/// # let dir = cap_std::fs::Dir::open_ambient_dir(".", cap_std::ambient_authority())?;
+/// // Let a backup read at most 10 MiB per second.
+/// let limiter = RateLimiter::new(Some(10 << 20), None);
+/// let view = DirViewBuilder::new().rate_limit(&limiter).build(&dir);
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// [`DirViewBuilder::rate_limit`]: crate::DirViewBuilder::rate_limit
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    read: Option<Arc<Bucket>>,
+    write: Option<Arc<Bucket>>,
+}
+
+impl RateLimiter {
+    /// Constructs a limiter permitting `read_bytes_per_second` bytes to be
+    /// read and `write_bytes_per_second` bytes to be written each second,
+    /// where `None` leaves the rate unlimited.
+    ///
+    /// # Panics
+    ///
+    /// This panics if either rate is zero.
+    pub fn new(read_bytes_per_second: Option<u64>, write_bytes_per_second: Option<u64>) -> Self {
+        Self {
+            read: read_bytes_per_second.map(Bucket::new),
+            write: write_bytes_per_second.map(Bucket::new),
+        }
+    }
+
+    /// Waits until reading `len` more bytes is within the limit.
+    pub(crate) fn read(&self, len: usize) {
+        if let Some(bucket) = &self.read {
+            bucket.take(len as u64);
+        }
+    }
+
+    /// Waits until writing `len` more bytes is within the limit.
+    pub(crate) fn write(&self, len: usize) {
+        if let Some(bucket) = &self.write {
+            bucket.take(len as u64);
+        }
+    }
+}
+
+struct Bucket {
+    rate: f64,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// The bytes which may be transferred without waiting. This is negative
+    /// while transfers are waiting for earlier ones.
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: u64) -> Arc<Self> {
+        assert_ne!(rate, 0, "rate limits must be positive");
+        let rate = rate as f64;
+        Arc::new(Self {
+            rate,
+            state: Mutex::new(State {
+                tokens: rate,
+                last: Instant::now(),
+            }),
+        })
+    }
+
+    /// Takes `len` tokens, waiting until the bucket has refilled enough to
+    /// cover any it didn't have.
+    fn take(&self, len: u64) {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.last).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * self.rate).min(self.rate);
+            state.last = now;
+            state.tokens -= len as f64;
+            state.tokens / -self.rate
+        };
+        if wait > 0.0 {
+            thread::sleep(Duration::from_secs_f64(wait));
+        }
+    }
+}
+
+impl fmt::Debug for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bucket")
+            .field("rate", &self.rate)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The limiters of the views a copy reads from and writes to.
+#[derive(Clone, Copy)]
+pub(crate) struct CopyThrottle<'a> {
+    pub(crate) read: Option<&'a RateLimiter>,
+    pub(crate) write: Option<&'a RateLimiter>,
+}
+
+impl CopyThrottle<'_> {
+    /// Tests whether either side of the copy is limited, so that the copy
+    /// needs to proceed in chunks.
+    pub(crate) fn is_limited(&self) -> bool {
+        self.read.is_some() || self.write.is_some()
+    }
+
+    /// Waits until copying `len` more bytes is within the limits.
+    pub(crate) fn copy(&self, len: u64) {
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        if let Some(limiter) = self.read {
+            limiter.read(len);
+        }
+        if let Some(limiter) = self.write {
+            limiter.write(len);
+        }
+    }
+}
+
+/// A file or other stream whose reads and writes are limited by a view's
+/// [`RateLimiter`].
+///
+/// This is returned by [`DirView::throttle`].
+#[derive(Debug)]
+pub struct Throttled<F> {
+    inner: F,
+    limiter: Option<RateLimiter>,
+}
+
+impl<F> Throttled<F> {
+    /// Returns a reference to the underlying stream.
+    #[inline]
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reads and writes through it bypass the limit.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Returns the underlying stream.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn read_done(&self, result: io::Result<usize>) -> io::Result<usize> {
+        if let (Ok(len), Some(limiter)) = (&result, &self.limiter) {
+            limiter.read(*len);
+        }
+        result
+    }
+
+    fn write_done(&self, result: io::Result<usize>) -> io::Result<usize> {
+        if let (Ok(len), Some(limiter)) = (&result, &self.limiter) {
+            limiter.write(*len);
+        }
+        result
+    }
+}
+
+impl<F: Read> Read for Throttled<F> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.inner.read(buf);
+        self.read_done(result)
+    }
+
+    #[inline]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        let result = self.inner.read_vectored(bufs);
+        self.read_done(result)
+    }
+}
+
+impl<F: BufRead> BufRead for Throttled<F> {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        if let Some(limiter) = &self.limiter {
+            limiter.read(amt);
+        }
+        self.inner.consume(amt)
+    }
+}
+
+impl<F: Write> Write for Throttled<F> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.inner.write(buf);
+        self.write_done(result)
+    }
+
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let result = self.inner.write_vectored(bufs);
+        self.write_done(result)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<F: Seek> Seek for Throttled<F> {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Wraps `file`, or any other stream, so that reads and writes through
+    /// it are limited by the view's [`RateLimiter`], if it has one.
+    ///
+    /// Handles returned by [`Self::open`] and similar methods aren't limited
+    /// themselves, so this lets callers which read and write them directly
+    /// share the view's limit.
+    #[inline]
+    pub fn throttle<F>(&self, file: F) -> Throttled<F> {
+        Throttled {
+            inner: file,
+            limiter: self.policy.rate_limit.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Wraps `file`, or any other stream, so that reads and writes through
+    /// it are limited by the view's [`RateLimiter`], if it has one.
+    ///
+    /// See [`DirView::throttle`].
+    #[inline]
+    pub fn throttle<F>(&self, file: F) -> Throttled<F> {
+        Throttled {
+            inner: file,
+            limiter: self.policy.rate_limit.clone(),
+        }
+    }
+}
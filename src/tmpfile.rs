@@ -0,0 +1,185 @@
+use crate::error::no_file_name;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use cap_std::fs::OpenOptionsExt;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::io;
+use std::os::fd::AsFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Creates a new anonymous file in `self`, with no name in the
+    /// filesystem.
+    ///
+    /// On Linux this uses `O_TMPFILE`, so the file never has a name until
+    /// it's given one with [`Self::link_tmpfile_at`]. Elsewhere, or if the
+    /// filesystem doesn't support `O_TMPFILE`, the file is created with a
+    /// unique name and immediately unlinked.
+    ///
+    /// The file is always opened for writing. The create and truncate flags
+    /// of `options` are ignored.
+    pub fn open_anonymous_tmpfile(&self, options: &OpenOptions) -> io::Result<File> {
+        self.audited(Operation::CreateTempFile, ".", |_| {
+            self.check_mutation(Operation::CreateTempFile, Path::new("."))?;
+            self.policy
+                .check_unbudgeted(Operation::CreateTempFile, Path::new("."))?;
+            open_anonymous(self.dir.borrow(), options)
+        })
+    }
+
+    /// Gives a file created by [`Self::open_anonymous_tmpfile`] the name
+    /// `path`.
+    ///
+    /// This fails if `path` already exists, and is only supported on Linux,
+    /// for files created with `O_TMPFILE`.
+    pub fn link_tmpfile_at<F: AsFd, P: AsRef<Path>>(&self, file: &F, path: P) -> io::Result<()> {
+        self.audited(Operation::HardLink, path, |path| {
+            if self.dry_run(Operation::HardLink, path, None)? {
+                return Ok(());
+            }
+            let name = path.file_name().ok_or_else(no_file_name)?;
+            match path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                Some(parent) => link(file, &self.dir.borrow().open_dir(parent)?, name),
+                None => link(file, self.dir.borrow(), name),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Creates a new anonymous file in `self`, with no name in the
+    /// filesystem.
+    ///
+    /// On Linux this uses `O_TMPFILE`, so the file never has a name until
+    /// it's given one with [`Self::link_tmpfile_at`]. Elsewhere, or if the
+    /// filesystem doesn't support `O_TMPFILE`, the file is created with a
+    /// unique name and immediately unlinked.
+    ///
+    /// The file is always opened for writing. The create and truncate flags
+    /// of `options` are ignored.
+    pub fn open_anonymous_tmpfile(
+        &self,
+        options: &OpenOptions,
+    ) -> io::Result<cap_std::fs_utf8::File> {
+        self.audited(Operation::CreateTempFile, ".", |_| {
+            self.check_mutation(Operation::CreateTempFile, Utf8Path::new("."))?;
+            self.policy
+                .check_unbudgeted(Operation::CreateTempFile, Path::new("."))?;
+            Ok(cap_std::fs_utf8::File::from_cap_std(open_anonymous(
+                self.dir.borrow().as_cap_std(),
+                options,
+            )?))
+        })
+    }
+
+    /// Gives a file created by [`Self::open_anonymous_tmpfile`] the name
+    /// `path`.
+    ///
+    /// This fails if `path` already exists, and is only supported on Linux,
+    /// for files created with `O_TMPFILE`.
+    pub fn link_tmpfile_at<F: AsFd, P: AsRef<Utf8Path>>(
+        &self,
+        file: &F,
+        path: P,
+    ) -> io::Result<()> {
+        self.audited(Operation::HardLink, path, |path| {
+            if self.dry_run(Operation::HardLink, path, None)? {
+                return Ok(());
+            }
+            let name = path.file_name().ok_or_else(no_file_name)?;
+            match path.parent().filter(|parent| !parent.as_str().is_empty()) {
+                Some(parent) => link(file, &self.dir.borrow().open_dir(parent)?, name),
+                None => link(file, self.dir.borrow(), name),
+            }
+        })
+    }
+}
+
+fn open_anonymous(dir: &Dir, options: &OpenOptions) -> io::Result<File> {
+    let mut options = options.clone();
+    options
+        .write(true)
+        .append(false)
+        .create(false)
+        .create_new(false)
+        .truncate(false);
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut tmpfile_options = options.clone();
+        tmpfile_options.custom_flags(rustix::fs::OFlags::TMPFILE.bits() as i32);
+        match dir.open_with(".", &tmpfile_options) {
+            Ok(file) => return Ok(file),
+            // The filesystem doesn't support `O_TMPFILE`.
+            Err(err)
+                if err.raw_os_error() == Some(rustix::io::Errno::OPNOTSUPP.raw_os_error())
+                    || err.raw_os_error() == Some(rustix::io::Errno::ISDIR.raw_os_error()) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    options.create_new(true);
+    loop {
+        let name = unique_name();
+        match dir.open_with(&name, &options) {
+            Ok(file) => {
+                dir.remove_file(&name)?;
+                return Ok(file);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Generate a name which is unlikely to collide with existing files.
+fn unique_name() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    format!(
+        ".tmp-{}-{}-{}",
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn link<F: AsFd, Fd: AsFd, N: AsRef<OsStr>>(file: &F, dir: Fd, name: N) -> io::Result<()> {
+    use rustix::fs::{linkat, AtFlags, CWD};
+    use std::os::fd::AsRawFd;
+
+    // Linking an `O_TMPFILE` file by descriptor with `AT_EMPTY_PATH`
+    // requires `CAP_DAC_READ_SEARCH`, so link it through procfs instead.
+    let proc_path = format!("/proc/self/fd/{}", file.as_fd().as_raw_fd());
+    Ok(linkat(
+        CWD,
+        proc_path.as_str(),
+        dir,
+        name.as_ref(),
+        AtFlags::SYMLINK_FOLLOW,
+    )?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn link<F: AsFd, Fd: AsFd, N: AsRef<OsStr>>(_file: &F, _dir: Fd, _name: N) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "linking anonymous temporary files is only supported on Linux",
+    ))
+}
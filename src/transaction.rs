@@ -0,0 +1,323 @@
+use crate::atomic_file::temp_name;
+use crate::error::no_file_name;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File, OpenOptions};
+use std::borrow::Borrow;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "fs_utf8")]
+use std::sync::Arc;
+use std::{fmt, mem};
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Begins a transaction, through which changes to the tree are staged
+    /// and then applied together with [`Transaction::commit`].
+    ///
+    /// The staged changes are kept in a hidden directory in `self` until the
+    /// transaction is committed or rolled back. This obtains a writable
+    /// handle, so it fails for read-only and dry-run views.
+    pub fn begin_transaction(&self) -> io::Result<Transaction> {
+        self.check_mutation(Operation::CreateTempDir, Path::new("."))?;
+        Transaction::begin(self.try_clone()?)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Begins a transaction, through which changes to the tree are staged
+    /// and then applied together with [`Transaction::commit`].
+    ///
+    /// See [`DirView::begin_transaction`].
+    pub fn begin_transaction(&self) -> io::Result<Transaction> {
+        self.check_mutation(Operation::CreateTempDir, Utf8Path::new("."))?;
+        Transaction::begin(DirView {
+            dir: self.dir.borrow().as_cap_std().try_clone()?,
+            view_kind: self.view_kind,
+            policy: Arc::clone(&self.policy),
+            depth: self.depth,
+        })
+    }
+}
+
+/// A set of changes to a tree which are applied together, returned by
+/// [`DirView::begin_transaction`].
+///
+/// Writes, renames, and removals are staged, and nothing in the tree
+/// changes until [`Self::commit`] applies them, in the order they were
+/// staged. Files are written to a hidden staging directory up front, and
+/// each change is applied with a rename, with anything it replaces or
+/// removes moved aside, so that if any change fails, the ones already
+/// applied are undone and the tree is left as it was. If a transaction is
+/// rolled back or dropped without being committed, the staged changes are
+/// thrown away.
+///
+/// This is as atomic as the filesystem allows, but no more: other
+/// processes can see the tree between the changes of a commit, and a crash
+/// during a commit leaves it partially applied, along with the staging
+/// directory. Reads through the view don't see staged changes.
+pub struct Transaction {
+    view: DirView,
+    staging: OsString,
+    journal: Vec<Step>,
+    next: u64,
+    finished: bool,
+}
+
+/// A change staged in a [`Transaction`].
+#[derive(Debug)]
+enum Step {
+    Write { path: PathBuf, staged: PathBuf },
+    CreateDir { path: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+    RemoveFile { path: PathBuf },
+    RemoveDirAll { path: PathBuf },
+}
+
+/// How to undo a change applied by [`Transaction::commit`].
+enum Undo {
+    /// Remove what the change created.
+    Remove(PathBuf),
+    /// Move back what the change moved.
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+impl Transaction {
+    fn begin(view: DirView) -> io::Result<Self> {
+        let staging = temp_name(OsStr::new("transaction"));
+        view.create_dir(&staging)?;
+        Ok(Self {
+            view,
+            staging,
+            journal: Vec::new(),
+            next: 0,
+            finished: false,
+        })
+    }
+
+    /// Stages writing a slice as the entire contents of the file at `path`,
+    /// replacing any existing file.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        path.file_name().ok_or_else(no_file_name)?;
+        let staged = self.staging_path();
+        self.view.check_mutation(Operation::Create, &staged)?;
+        let mut file = self
+            .view
+            .open_budgeted(&staged, OpenOptions::new().write(true).create_new(true))?;
+        self.journal.push(Step::Write {
+            path: path.to_path_buf(),
+            staged,
+        });
+        file.write_all(contents.as_ref())
+    }
+
+    /// Stages creating the file at `path`, replacing any existing file, and
+    /// returns the staged file for writing its contents.
+    ///
+    /// The file isn't visible at `path` until the transaction is committed.
+    /// Like [`DirView::create`], this is denied through views with a
+    /// [`HandleBudget`], where [`Self::write`] can be used instead.
+    ///
+    /// [`HandleBudget`]: crate::HandleBudget
+    pub fn create<P: AsRef<Path>>(&mut self, path: P) -> io::Result<File> {
+        let path = path.as_ref();
+        path.file_name().ok_or_else(no_file_name)?;
+        let staged = self.staging_path();
+        let file = self.view.create_new(&staged)?;
+        self.journal.push(Step::Write {
+            path: path.to_path_buf(),
+            staged,
+        });
+        Ok(file)
+    }
+
+    /// Stages creating a new, empty directory at `path`.
+    pub fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.journal.push(Step::CreateDir {
+            path: path.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Stages renaming a file or directory, replacing any existing file at
+    /// `to`.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        self.journal.push(Step::Rename {
+            from: from.as_ref().to_path_buf(),
+            to: to.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Stages removing the file at `path`.
+    pub fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.journal.push(Step::RemoveFile {
+            path: path.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Stages removing the directory at `path`, along with its contents.
+    pub fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.journal.push(Step::RemoveDirAll {
+            path: path.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Returns the number of changes staged.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Tests whether no changes are staged.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.journal.is_empty()
+    }
+
+    /// Applies the staged changes, in the order they were staged.
+    ///
+    /// If a change fails, the changes already applied are undone, as far as
+    /// possible, and its error is returned. Once all of the changes are
+    /// applied, the commit has succeeded, so failing to remove the staging
+    /// directory afterwards is ignored.
+    pub fn commit(mut self) -> io::Result<()> {
+        let mut undo = Vec::new();
+        for step in mem::take(&mut self.journal) {
+            if let Err(err) = self.apply(step, &mut undo) {
+                for undo in undo.into_iter().rev() {
+                    match undo {
+                        Undo::Remove(path) => self.remove(&path).ok(),
+                        Undo::Rename { from, to } => self.view.rename(&from, &self.view, &to).ok(),
+                    };
+                }
+                return Err(err);
+            }
+        }
+        self.finish().ok();
+        Ok(())
+    }
+
+    /// Throws away the staged changes, reporting any errors.
+    ///
+    /// Dropping `self` also throws away the changes, but ignores errors.
+    pub fn rollback(mut self) -> io::Result<()> {
+        self.finish()
+    }
+
+    fn apply(&mut self, step: Step, undo: &mut Vec<Undo>) -> io::Result<()> {
+        match step {
+            Step::Write { path, staged } => {
+                // Keep the permissions of the file being replaced.
+                #[cfg(not(target_os = "wasi"))]
+                match self.view.symlink_metadata(&path) {
+                    Ok(metadata) if metadata.is_file() => {
+                        self.view.set_permissions(&staged, metadata.permissions())?
+                    }
+                    _ => {}
+                }
+                self.move_aside(&path, false, undo)?;
+                self.view.rename(&staged, &self.view, &path)?;
+                undo.push(Undo::Remove(path));
+            }
+            Step::CreateDir { path } => {
+                self.view.create_dir(&path)?;
+                undo.push(Undo::Remove(path));
+            }
+            Step::Rename { from, to } => {
+                self.move_aside(&to, self.view.symlink_metadata(&from)?.is_dir(), undo)?;
+                self.view.rename(&from, &self.view, &to)?;
+                undo.push(Undo::Rename { from: to, to: from });
+            }
+            Step::RemoveFile { path } => {
+                if self.view.symlink_metadata(&path)?.is_dir() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::IsADirectory,
+                        "attempted to remove a directory as a file",
+                    ));
+                }
+                self.move_aside(&path, false, undo)?;
+            }
+            Step::RemoveDirAll { path } => {
+                if !self.view.symlink_metadata(&path)?.is_dir() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotADirectory,
+                        "not a directory",
+                    ));
+                }
+                self.move_aside(&path, true, undo)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the entry at `path`, if there is one, into the staging
+    /// directory, so that it can be restored if the commit fails.
+    ///
+    /// Directories are only moved if `dir` is true, and otherwise left for
+    /// the change to fail on.
+    fn move_aside(&mut self, path: &Path, dir: bool, undo: &mut Vec<Undo>) -> io::Result<()> {
+        match self.view.symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() && !dir => return Ok(()),
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        }
+        let aside = self.staging_path();
+        self.view.rename(path, &self.view, &aside)?;
+        undo.push(Undo::Rename {
+            from: aside,
+            to: path.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if self.view.symlink_metadata(path)?.is_dir() {
+            self.view.remove_dir_all(path)
+        } else {
+            self.view.remove_file(path)
+        }
+    }
+
+    fn staging_path(&mut self) -> PathBuf {
+        self.next += 1;
+        Path::new(&self.staging).join(self.next.to_string())
+    }
+
+    /// Removes the staging directory, with the staged files and anything
+    /// moved aside.
+    fn finish(&mut self) -> io::Result<()> {
+        self.finished = true;
+        self.view.remove_dir_all(&self.staging)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish().ok();
+        }
+    }
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("view", &self.view)
+            .field("staging", &self.staging)
+            .field("journal", &self.journal)
+            .finish_non_exhaustive()
+    }
+}
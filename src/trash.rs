@@ -0,0 +1,191 @@
+use crate::error::no_file_name;
+use crate::policy::Policy;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The directory which views created with [`DirView::with_trash`] move
+/// removed entries into.
+#[derive(Debug)]
+pub(crate) struct Trash {
+    /// The trash directory.
+    dir: Dir,
+
+    /// The path of the trash directory, relative to the view it was
+    /// configured on.
+    path: PathBuf,
+}
+
+impl Trash {
+    /// Moves the entry at `path` in `dir` into the trash, under its own name
+    /// if that's free, or with a numeric suffix otherwise. The entry must be
+    /// a directory if `is_dir`, and must not be otherwise.
+    fn put(&self, dir: &Dir, path: &Path, is_dir: bool) -> io::Result<()> {
+        let name = path.file_name().ok_or_else(no_file_name)?;
+        match (dir.symlink_metadata(path)?.is_dir(), is_dir) {
+            (true, false) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::IsADirectory,
+                    "attempted to remove a directory as a file",
+                ))
+            }
+            (false, true) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotADirectory,
+                    "not a directory",
+                ))
+            }
+            _ => {}
+        }
+        let mut trash_name = name.to_os_string();
+        let mut suffix = 0_u64;
+        while self.dir.symlink_metadata(&trash_name).is_ok() {
+            suffix += 1;
+            trash_name = OsString::from(name);
+            trash_name.push(format!(".{}", suffix));
+        }
+        dir.rename(path, &self.dir, &trash_name)
+    }
+
+    /// Removes everything in the trash.
+    fn purge(&self) -> io::Result<()> {
+        for entry in self.dir.entries()? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                self.dir.remove_dir_all(entry.file_name())?;
+            } else {
+                self.dir.remove_file(entry.file_name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Policy {
+    /// Returns a copy of `policy` which moves removed entries into `trash`.
+    fn with_trash(policy: &Policy, trash: Trash) -> Arc<Self> {
+        Arc::new(Self {
+            trash: Some(Arc::new(trash)),
+            ..policy.clone()
+        })
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns a view of the same directory which moves entries removed
+    /// with [`Self::remove_file`] or [`Self::remove_dir_all`] into a trash
+    /// directory at `path`, creating it if needed, instead of deleting
+    /// them.
+    ///
+    /// Entries keep their names in the trash, with a numeric suffix such as
+    /// `.1` if an entry of the same name is already there. Views of
+    /// subdirectories opened through the returned view share its trash, so
+    /// it must be on the same filesystem as everything removed through
+    /// them. Entries in the trash itself are deleted when they're removed
+    /// through the returned view, and [`Self::purge_trash`] deletes
+    /// everything in it.
+    ///
+    /// This fails for read-only views, which can't remove anything anyway.
+    pub fn with_trash<P: AsRef<Path>>(self, path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        self.check_mutation(Operation::CreateDir, path)?;
+        self.create_dir_all(path)?;
+        let trash = Trash {
+            dir: self.open_dir(path)?.dir,
+            path: path.to_path_buf(),
+        };
+        Ok(Self {
+            policy: Policy::with_trash(&self.policy, trash),
+            ..self
+        })
+    }
+
+    /// Deletes everything in the trash of a view created with
+    /// [`Self::with_trash`].
+    pub fn purge_trash(&self) -> io::Result<()> {
+        self.audited(Operation::RemoveDirContents, ".", |_| {
+            let trash = self.policy.trash.as_ref().ok_or_else(no_trash)?;
+            if self.dry_run(Operation::RemoveDirContents, &trash.path, None)? {
+                return Ok(());
+            }
+            trash.purge()
+        })
+    }
+
+    /// Moves the entry at `path` into the trash, if `self` has one and
+    /// `path` isn't in it, returning `false` otherwise.
+    pub(crate) fn put_in_trash(&self, path: &Path, is_dir: bool) -> io::Result<bool> {
+        match &self.policy.trash {
+            Some(trash) if self.depth != 0 || !path.starts_with(&trash.path) => {
+                trash.put(self.dir.borrow(), path, is_dir)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns a view of the same directory which moves entries removed
+    /// with [`Self::remove_file`] or [`Self::remove_dir_all`] into a trash
+    /// directory at `path`, creating it if needed, instead of deleting
+    /// them.
+    ///
+    /// See [`DirView::with_trash`].
+    pub fn with_trash<P: AsRef<Utf8Path>>(self, path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        self.check_mutation(Operation::CreateDir, path)?;
+        self.create_dir_all(path)?;
+        let trash = Trash {
+            dir: self.open_dir(path)?.dir.as_cap_std().try_clone()?,
+            path: path.as_std_path().to_path_buf(),
+        };
+        Ok(Self {
+            policy: Policy::with_trash(&self.policy, trash),
+            ..self
+        })
+    }
+
+    /// Deletes everything in the trash of a view created with
+    /// [`Self::with_trash`].
+    ///
+    /// See [`DirView::purge_trash`].
+    pub fn purge_trash(&self) -> io::Result<()> {
+        self.audited(Operation::RemoveDirContents, ".", |_| {
+            let trash = self.policy.trash.as_ref().ok_or_else(no_trash)?;
+            if self.dry_run(
+                Operation::RemoveDirContents,
+                Utf8Path::from_path(&trash.path).unwrap(),
+                None,
+            )? {
+                return Ok(());
+            }
+            trash.purge()
+        })
+    }
+
+    /// Moves the entry at `path` into the trash, if `self` has one and
+    /// `path` isn't in it, returning `false` otherwise.
+    pub(crate) fn put_in_trash(&self, path: &Utf8Path, is_dir: bool) -> io::Result<bool> {
+        match &self.policy.trash {
+            Some(trash) if self.depth != 0 || !path.starts_with(&trash.path) => {
+                trash.put(self.dir.borrow().as_cap_std(), path.as_std_path(), is_dir)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+fn no_trash() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "the view has no trash")
+}
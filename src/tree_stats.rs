@@ -0,0 +1,195 @@
+use crate::DirView;
+use cap_std::fs::Dir;
+use cap_std::time::SystemTime;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Options for [`DirView::tree_stats_with`].
+#[derive(Clone, Debug)]
+pub struct TreeStatsOptions {
+    largest: usize,
+}
+
+impl TreeStatsOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// The 10 largest files are initially reported.
+    #[inline]
+    pub fn new() -> Self {
+        Self { largest: 10 }
+    }
+
+    /// Sets how many of the largest files to report.
+    #[inline]
+    pub fn largest(&mut self, largest: usize) -> &mut Self {
+        self.largest = largest;
+        self
+    }
+}
+
+impl Default for TreeStatsOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A summary of the entries in a subtree, returned by
+/// [`DirView::tree_stats`].
+///
+/// Paths are relative to the root of the subtree.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TreeStats {
+    files: u64,
+    dirs: u64,
+    symlinks: u64,
+    others: u64,
+    total_size: u64,
+    largest: Vec<(PathBuf, u64)>,
+    deepest: Option<(PathBuf, usize)>,
+    oldest: Option<(PathBuf, SystemTime)>,
+    newest: Option<(PathBuf, SystemTime)>,
+}
+
+impl TreeStats {
+    /// Returns the number of regular files.
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Returns the number of directories, not including the root.
+    #[inline]
+    pub fn dirs(&self) -> u64 {
+        self.dirs
+    }
+
+    /// Returns the number of symbolic links.
+    #[inline]
+    pub fn symlinks(&self) -> u64 {
+        self.symlinks
+    }
+
+    /// Returns the number of other entries, such as sockets and FIFOs.
+    #[inline]
+    pub fn others(&self) -> u64 {
+        self.others
+    }
+
+    /// Returns the total size of the regular files, in bytes.
+    #[inline]
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// Returns the largest regular files with their sizes, largest first.
+    #[inline]
+    pub fn largest(&self) -> &[(PathBuf, u64)] {
+        &self.largest
+    }
+
+    /// Returns the most deeply nested entry, with its depth, where entries
+    /// of the root have a depth of 1.
+    #[inline]
+    pub fn deepest(&self) -> Option<(&Path, usize)> {
+        self.deepest
+            .as_ref()
+            .map(|(path, depth)| (path.as_path(), *depth))
+    }
+
+    /// Returns the least recently modified entry, with its modification
+    /// time.
+    #[inline]
+    pub fn oldest(&self) -> Option<(&Path, SystemTime)> {
+        self.oldest
+            .as_ref()
+            .map(|(path, time)| (path.as_path(), *time))
+    }
+
+    /// Returns the most recently modified entry, with its modification
+    /// time.
+    #[inline]
+    pub fn newest(&self) -> Option<(&Path, SystemTime)> {
+        self.newest
+            .as_ref()
+            .map(|(path, time)| (path.as_path(), *time))
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Summarizes the entries in the directory at `path` and its
+    /// subdirectories, in a single traversal.
+    ///
+    /// Symbolic links are counted rather than followed. The subtree is read
+    /// through the view, so entries it hides aren't counted, and
+    /// subdirectories beyond its maximum depth fail to open.
+    #[inline]
+    pub fn tree_stats<P: AsRef<Path>>(&self, path: P) -> io::Result<TreeStats> {
+        self.tree_stats_with(path, &TreeStatsOptions::new())
+    }
+
+    /// Summarizes the entries in the directory at `path` and its
+    /// subdirectories, with the given options.
+    ///
+    /// See [`Self::tree_stats`] for details.
+    pub fn tree_stats_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &TreeStatsOptions,
+    ) -> io::Result<TreeStats> {
+        let mut stats = TreeStats::default();
+        let mut largest = BinaryHeap::new();
+        for entry in self.open_dir(path)?.walk()? {
+            let entry = entry?;
+            let metadata = entry.entry().metadata()?;
+            let file_type = metadata.file_type();
+            if file_type.is_dir() {
+                stats.dirs += 1;
+            } else if file_type.is_file() {
+                stats.files += 1;
+                stats.total_size += metadata.len();
+                largest.push(Reverse((metadata.len(), entry.path().to_path_buf())));
+                if largest.len() > options.largest {
+                    largest.pop();
+                }
+            } else if file_type.is_symlink() {
+                stats.symlinks += 1;
+            } else {
+                stats.others += 1;
+            }
+
+            if stats
+                .deepest
+                .as_ref()
+                .is_none_or(|(_, depth)| entry.depth() > *depth)
+            {
+                stats.deepest = Some((entry.path().to_path_buf(), entry.depth()));
+            }
+            if let Ok(modified) = metadata.modified() {
+                if stats
+                    .oldest
+                    .as_ref()
+                    .is_none_or(|(_, time)| modified < *time)
+                {
+                    stats.oldest = Some((entry.path().to_path_buf(), modified));
+                }
+                if stats
+                    .newest
+                    .as_ref()
+                    .is_none_or(|(_, time)| modified > *time)
+                {
+                    stats.newest = Some((entry.path().to_path_buf(), modified));
+                }
+            }
+        }
+        stats.largest = largest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((len, path))| (path, len))
+            .collect();
+        Ok(stats)
+    }
+}
@@ -0,0 +1,213 @@
+use cap_std::fs::Dir;
+use io_uring::{opcode, squeue, types, IoUring};
+use rustix::fs::{AtFlags, OFlags, ResolveFlags, Statx, StatxFlags};
+use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::mem;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// The number of files read in each batch, which is also the size of the
+/// ring.
+const BATCH: usize = 64;
+
+/// Reads the files at `paths` in `dir` with io_uring, a batch at a time:
+/// opening the files in a batch with `openat2`, then querying their sizes
+/// with `statx`, then reading them, with one submission for each step.
+///
+/// Returns `None` if io_uring isn't available. Otherwise, the contents of
+/// each file are `None` if the file should be read synchronously instead,
+/// including if reading it failed, so that errors are reported as they are
+/// by the synchronous API.
+///
+/// Paths are resolved as cap-std resolves them: beneath `dir`, and without
+/// following "magic links". With `same_filesystem`, paths which cross a
+/// mount point fail to resolve too.
+pub(crate) fn read_many(
+    dir: &Dir,
+    paths: &[&Path],
+    same_filesystem: bool,
+) -> Option<Vec<Option<Vec<u8>>>> {
+    let mut ring = IoUring::new(BATCH as u32).ok()?;
+    let mut contents = Vec::with_capacity(paths.len());
+    for batch in paths.chunks(BATCH) {
+        match read_batch(&mut ring, dir, batch, same_filesystem) {
+            Ok(batch) => contents.extend(batch),
+            // The ring may be unusable, so read the rest synchronously.
+            Err(_) => break,
+        }
+    }
+    contents.resize_with(paths.len(), || None);
+    Some(contents)
+}
+
+fn read_batch(
+    ring: &mut IoUring,
+    dir: &Dir,
+    paths: &[&Path],
+    same_filesystem: bool,
+) -> io::Result<Vec<Option<Vec<u8>>>> {
+    let dir = types::Fd(dir.as_fd().as_raw_fd());
+
+    // Open the files.
+    let names = paths
+        .iter()
+        .map(|path| CString::new(path.as_os_str().as_bytes()).ok())
+        .collect::<Vec<_>>();
+    let mut resolve = ResolveFlags::BENEATH | ResolveFlags::NO_MAGICLINKS;
+    if same_filesystem {
+        resolve |= ResolveFlags::NO_XDEV;
+    }
+    let how = Box::new(
+        types::OpenHow::new()
+            .flags(
+                (OFlags::RDONLY | OFlags::CLOEXEC | OFlags::NOCTTY)
+                    .bits()
+                    .into(),
+            )
+            .resolve(resolve.bits()),
+    );
+    let opens = names.iter().map(|name| {
+        name.as_ref()
+            .map(|name| opcode::OpenAt2::new(dir, name.as_ptr(), &*how).build())
+    });
+    let results = match run(ring, opens) {
+        Ok(results) => results,
+        Err(err) => {
+            mem::forget((names, how));
+            return Err(err);
+        }
+    };
+    let files = results
+        .into_iter()
+        .map(|result| match result {
+            // SAFETY: A successful `openat2` returns a new file descriptor.
+            Some(fd) if fd >= 0 => Some(unsafe { OwnedFd::from_raw_fd(fd) }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // Query their sizes.
+    static EMPTY: &std::ffi::CStr = c"";
+    // SAFETY: `Statx` is plain data, for which all zeros is a valid value.
+    let mut stats = vec![unsafe { mem::zeroed::<Statx>() }; files.len()];
+    let statxs = files.iter().zip(&mut stats).map(|(file, stat)| {
+        file.as_ref().map(|file| {
+            opcode::Statx::new(
+                types::Fd(file.as_raw_fd()),
+                EMPTY.as_ptr(),
+                (stat as *mut Statx).cast(),
+            )
+            .flags(AtFlags::EMPTY_PATH.bits() as i32)
+            .mask(StatxFlags::SIZE.bits())
+            .build()
+        })
+    });
+    let results = match run(ring, statxs) {
+        Ok(results) => results,
+        Err(err) => {
+            mem::forget(stats);
+            return Err(err);
+        }
+    };
+
+    // Read them, into buffers one byte larger than their sizes, to detect
+    // files which have grown.
+    let mut bufs = files
+        .iter()
+        .zip(&stats)
+        .zip(results)
+        .map(|((file, stat), result)| match (file, result) {
+            (Some(_), Some(0)) => usize::try_from(stat.stx_size)
+                .ok()
+                .and_then(|size| size.checked_add(1))
+                .filter(|len| u32::try_from(*len).is_ok())
+                .map(Vec::with_capacity),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let reads = files
+        .iter()
+        .zip(&mut bufs)
+        .map(|(file, buf)| match (file, buf) {
+            (Some(file), Some(buf)) => Some(
+                opcode::Read::new(
+                    types::Fd(file.as_raw_fd()),
+                    buf.as_mut_ptr(),
+                    buf.capacity() as u32,
+                )
+                .build(),
+            ),
+            _ => None,
+        });
+    let results = match run(ring, reads) {
+        Ok(results) => results,
+        Err(err) => {
+            mem::forget(bufs);
+            return Err(err);
+        }
+    };
+
+    let mut contents = Vec::with_capacity(paths.len());
+    for ((file, buf), result) in files.into_iter().zip(bufs).zip(results) {
+        let (Some(file), Some(mut buf), Some(len)) = (file, buf, result) else {
+            contents.push(None);
+            continue;
+        };
+        let Ok(len) = usize::try_from(len) else {
+            contents.push(None);
+            continue;
+        };
+        // SAFETY: The kernel initialized the `len` bytes it read.
+        unsafe { buf.set_len(len) };
+        if len == buf.capacity() {
+            // The file has grown; read the rest of it.
+            let mut file = std::fs::File::from(file);
+            if file.seek(SeekFrom::Start(len as u64)).is_err()
+                || file.read_to_end(&mut buf).is_err()
+            {
+                contents.push(None);
+                continue;
+            }
+        }
+        contents.push(Some(buf));
+    }
+    Ok(contents)
+}
+
+/// Submits `entries`, and waits for them to complete, returning their
+/// results, or `None` for the entries which are `None`.
+///
+/// If this fails, operations may still be in progress, so the buffers the
+/// entries refer to must be leaked.
+fn run(
+    ring: &mut IoUring,
+    entries: impl Iterator<Item = Option<squeue::Entry>>,
+) -> io::Result<Vec<Option<i32>>> {
+    let mut results = Vec::new();
+    let mut pending = 0;
+    for entry in entries {
+        if let Some(entry) = entry {
+            let entry = entry.user_data(results.len() as u64);
+            // SAFETY: The callers keep the buffers the entry refers to alive
+            // until it completes, which this waits for, or leak them.
+            unsafe { ring.submission().push(&entry) }
+                .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+            pending += 1;
+        }
+        results.push(None);
+    }
+    while pending > 0 {
+        match ring.submit_and_wait(pending) {
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+        for cqe in ring.completion() {
+            results[cqe.user_data() as usize] = Some(cqe.result());
+            pending -= 1;
+        }
+    }
+    Ok(results)
+}
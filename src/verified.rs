@@ -0,0 +1,248 @@
+use crate::overlay::at;
+use crate::{DirView, ManifestEntry, Operation, Sha256, TreeHash, TreeManifest, ViewError};
+use cap_std::fs::{File, Metadata};
+use std::fmt;
+use std::io::{self, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// A view of a tree whose contents are checked against a [`TreeManifest`].
+///
+/// Files are hashed as they're opened and read, and fail to open or read if
+/// their contents don't match their digests in the manifest, and symbolic
+/// links are checked the same way. Paths not listed in the manifest are
+/// invisible, as if they didn't exist. This makes a view into a
+/// tamper-evident source of content, for updaters and plugin loaders which
+/// get a manifest from a trusted source, or check a signature over it with
+/// [`Self::signed`].
+///
+/// The view is only read through, so it may be readonly. Paths are resolved
+/// lexically, so `..` steps back a component, and can't lead outside of the
+/// tree.
+///
+/// ```
+/// # use dir_view::{DirView, Sha256, VerifiedDirView};
+/// # let temp_dir = cap_tempfile::TempDir::new(cap_std::ambient_authority())?;
+/// # temp_dir.write("plugin.wasm", b"\0asm")?;
+/// # let dir = temp_dir.open_dir(".")?;
+/// let view = DirView::readonly(dir);
+/// let manifest = view.hash_tree(".", &Sha256)?;
+/// let verified = VerifiedDirView::new(view, manifest, Sha256);
+/// assert_eq!(verified.read("plugin.wasm")?, b"\0asm");
+/// assert!(!verified.exists("unlisted"));
+/// # std::io::Result::Ok(())
+/// ```
+pub struct VerifiedDirView<A: TreeHash = Sha256> {
+    view: DirView,
+    manifest: TreeManifest,
+    algorithm: A,
+}
+
+impl<A: TreeHash> VerifiedDirView<A> {
+    /// Constructs a view of the tree in `view`, checked against `manifest`,
+    /// whose digests were computed with `algorithm`.
+    #[inline]
+    pub fn new(view: DirView, manifest: TreeManifest, algorithm: A) -> Self {
+        Self {
+            view,
+            manifest,
+            algorithm,
+        }
+    }
+
+    /// Constructs a view like [`Self::new`], after checking a signature over
+    /// `manifest` with `verify`.
+    ///
+    /// `verify` is called with the manifest's [root digest], which covers
+    /// every entry, and returns whether the signature over it is valid. If
+    /// it isn't, this fails with [`io::ErrorKind::InvalidData`].
+    ///
+    /// [root digest]: TreeManifest::root_digest
+    pub fn signed(
+        view: DirView,
+        manifest: TreeManifest,
+        algorithm: A,
+        verify: impl FnOnce(&[u8]) -> bool,
+    ) -> io::Result<Self> {
+        if !verify(manifest.root_digest()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the manifest's signature is invalid",
+            ));
+        }
+        Ok(Self::new(view, manifest, algorithm))
+    }
+
+    /// Returns the manifest the view is checked against.
+    #[inline]
+    pub fn manifest(&self) -> &TreeManifest {
+        &self.manifest
+    }
+
+    /// Returns the underlying view.
+    #[inline]
+    pub fn view(&self) -> &DirView {
+        &self.view
+    }
+
+    /// Opens the file at `path` for reading, after checking its contents
+    /// against the manifest.
+    ///
+    /// The contents are hashed through the returned handle, which is then
+    /// rewound, so changes made to the file after it's opened aren't
+    /// detected. Use [`Self::read`] to read and check the contents at once.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let (path, digest) = self.file(Operation::Open, path.as_ref())?;
+        let mut file = self.view.open(&path)?;
+        let mut hasher = self.algorithm.hasher();
+        io::copy(&mut file, &mut hasher)?;
+        self.check(&path, digest, hasher)?;
+        file.rewind()?;
+        Ok(file)
+    }
+
+    /// Reads the entire contents of the file at `path` into a bytes vector,
+    /// after checking them against the manifest.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let (path, digest) = self.file(Operation::Read, path.as_ref())?;
+        let contents = self.view.read(&path)?;
+        let mut hasher = self.algorithm.hasher();
+        hasher.write_all(&contents)?;
+        self.check(&path, digest, hasher)?;
+        Ok(contents)
+    }
+
+    /// Reads the entire contents of the file at `path` into a string, after
+    /// checking them against the manifest.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Reads the contents of the symbolic link at `path`, after checking
+    /// them against the manifest.
+    ///
+    /// The contents are returned verbatim, as with
+    /// [`DirView::read_link_contents`].
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let path = self.normalize(Operation::ReadLink, path.as_ref())?;
+        let Some(ManifestEntry::Symlink(digest)) = self.manifest.get(&path) else {
+            return Err(self.wrong_type(&path, "isn't a symbolic link"));
+        };
+        let contents = self.view.read_link_contents(&path)?;
+        let mut hasher = self.algorithm.hasher();
+        hasher.write_all(contents.as_os_str().as_encoded_bytes())?;
+        self.check(&path, digest, hasher)?;
+        Ok(contents)
+    }
+
+    /// Returns the metadata of the entry at `path`, following symbolic links.
+    ///
+    /// Metadata isn't covered by the manifest, so it isn't checked.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        let path = self.normalize(Operation::Metadata, path.as_ref())?;
+        self.listed(&path)?;
+        self.view.metadata(at(&path))
+    }
+
+    /// Returns `true` if `path` is listed in the manifest and exists.
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Returns `true` if `path` is listed in the manifest as a file and
+    /// exists as one.
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.file(Operation::Metadata, path.as_ref())
+            .is_ok_and(|(path, _)| self.view.is_file(path))
+    }
+
+    /// Returns `true` if `path` is listed in the manifest as a directory and
+    /// exists as one.
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        let Ok(path) = self.normalize(Operation::Metadata, path.as_ref()) else {
+            return false;
+        };
+        (path.as_os_str().is_empty() || self.manifest.get(&path) == Some(&ManifestEntry::Dir))
+            && self.view.is_dir(at(&path))
+    }
+
+    /// Returns the normalized `path`, and the digest of the file there,
+    /// failing if it isn't listed as a file.
+    fn file(&self, operation: Operation, path: &Path) -> io::Result<(PathBuf, &[u8])> {
+        let path = self.normalize(operation, path)?;
+        match self.manifest.get(&path) {
+            Some(ManifestEntry::File(digest)) => Ok((path, digest)),
+            _ => Err(self.wrong_type(&path, "isn't a file")),
+        }
+    }
+
+    /// Fails if `path` isn't listed in the manifest, other than the root.
+    fn listed(&self, path: &Path) -> io::Result<()> {
+        if path.as_os_str().is_empty() || self.manifest.get(path).is_some() {
+            return Ok(());
+        }
+        Err(not_found(path))
+    }
+
+    /// Returns an error for `path` which isn't listed as the expected type,
+    /// which is not found if it isn't listed at all.
+    fn wrong_type(&self, path: &Path, message: &str) -> io::Error {
+        if self.listed(path).is_err() {
+            return not_found(path);
+        }
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` {message} in the manifest", path.display()),
+        )
+    }
+
+    /// Fails if the digest in `hasher` doesn't match `digest`.
+    fn check(&self, path: &Path, digest: &[u8], hasher: A::Hasher) -> io::Result<()> {
+        if self.algorithm.finish(hasher) == digest {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("contents of `{}` don't match the manifest", path.display()),
+        ))
+    }
+
+    /// Resolves the `.` and `..` components of `path` lexically, failing if
+    /// it leads outside of the tree.
+    fn normalize(&self, operation: Operation, path: &Path) -> io::Result<PathBuf> {
+        let mut normal = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => normal.push(name),
+                Component::CurDir => {}
+                Component::ParentDir if normal.pop() => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(ViewError::denied(
+                        io::ErrorKind::PermissionDenied,
+                        "a path led outside of the verified tree",
+                        operation,
+                        path,
+                        false,
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(normal)
+    }
+}
+
+impl<A: TreeHash> fmt::Debug for VerifiedDirView<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerifiedDirView")
+            .field("view", &self.view)
+            .finish_non_exhaustive()
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("`{}` isn't in the manifest", path.display()),
+    )
+}
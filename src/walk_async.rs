@@ -0,0 +1,214 @@
+use crate::{DirView, WalkEntry};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::{fmt, io, thread};
+
+/// Reads the entries of a directory on a blocking thread pool.
+pub(crate) type Spawn = fn(Box<dyn FnOnce() -> Vec<io::Result<WalkEntry>> + Send>) -> Listing;
+
+/// The entries of a directory being read by a [`Spawn`].
+pub(crate) type Listing =
+    Pin<Box<dyn Future<Output = io::Result<Vec<io::Result<WalkEntry>>>> + Send>>;
+
+/// A recursive stream over the entries in an async view and its
+/// subdirectories, returned by [`TokioDirView::walk`] and
+/// [`DirViewAsync::walk`].
+///
+/// Directories are read on the runtime's blocking thread pool, several at
+/// once, through the view, so its restrictions apply at every level, as
+/// with [`Walk`]. Errors are yielded in place of the entries they concern,
+/// and the walk continues after them.
+///
+/// Entries are yielded in no particular order, except that directories are
+/// yielded before their contents. Subdirectories are only read once they've
+/// been yielded, so a slow consumer holds back the walk. Dropping the stream
+/// stops the directory reads in progress.
+///
+/// [`TokioDirView::walk`]: crate::TokioDirView::walk
+/// [`DirViewAsync::walk`]: crate::DirViewAsync::walk
+/// [`Walk`]: crate::Walk
+pub struct WalkStream {
+    root: Arc<DirView>,
+    spawn: Spawn,
+    max_depth: usize,
+    concurrency: usize,
+    started: bool,
+
+    /// Directories waiting to be read, with the depth of their entries.
+    queue: VecDeque<(PathBuf, usize)>,
+
+    /// Directories being read.
+    listings: Vec<Listing>,
+
+    /// Entries read, waiting to be yielded.
+    ready: VecDeque<io::Result<WalkEntry>>,
+
+    cancelled: Arc<AtomicBool>,
+}
+
+impl WalkStream {
+    pub(crate) fn new(root: Arc<DirView>, spawn: Spawn) -> Self {
+        Self {
+            root,
+            spawn,
+            max_depth: usize::MAX,
+            concurrency: 0,
+            started: false,
+            queue: VecDeque::new(),
+            listings: Vec::new(),
+            ready: VecDeque::new(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Sets the maximum depth of entries to yield, where entries of the
+    /// walked view itself have a depth of 1.
+    #[inline]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the number of directories to read at once, each of which holds a
+    /// directory handle open while it's read.
+    ///
+    /// The default, 0, uses [`std::thread::available_parallelism`].
+    #[inline]
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Returns the next entry, or `None` once the walk is complete.
+    ///
+    /// This is cancel-safe: if the returned future is dropped before it
+    /// completes, no entries are lost.
+    pub async fn next_entry(&mut self) -> Option<io::Result<WalkEntry>> {
+        poll_fn(|cx| self.poll_next_entry(cx)).await
+    }
+
+    /// Polls for the next entry.
+    ///
+    /// See [`Self::next_entry`].
+    pub fn poll_next_entry(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<WalkEntry>>> {
+        if !self.started {
+            self.started = true;
+            if self.concurrency == 0 {
+                self.concurrency = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+            }
+            if self.max_depth > 0 {
+                self.queue.push_back((PathBuf::new(), 1));
+            }
+        }
+        loop {
+            while self.listings.len() < self.concurrency {
+                let Some((path, depth)) = self.queue.pop_front() else {
+                    break;
+                };
+                let root = Arc::clone(&self.root);
+                let cancelled = Arc::clone(&self.cancelled);
+                self.listings.push((self.spawn)(Box::new(move || {
+                    read(&root, &path, depth, &cancelled)
+                })));
+            }
+
+            if let Some(entry) = self.ready.pop_front() {
+                if let Ok(entry) = &entry {
+                    if entry.file_type().is_dir() && entry.depth() < self.max_depth {
+                        self.queue
+                            .push_back((entry.path().to_path_buf(), entry.depth() + 1));
+                    }
+                }
+                return Poll::Ready(Some(entry));
+            }
+
+            let mut progressed = false;
+            let mut i = 0;
+            while i < self.listings.len() {
+                match self.listings[i].as_mut().poll(cx) {
+                    Poll::Ready(entries) => {
+                        drop(self.listings.swap_remove(i));
+                        match entries {
+                            Ok(entries) => self.ready.extend(entries),
+                            Err(err) => self.ready.push_back(Err(err)),
+                        }
+                        progressed = true;
+                    }
+                    Poll::Pending => i += 1,
+                }
+            }
+            if progressed {
+                continue;
+            }
+            if self.listings.is_empty() && self.queue.is_empty() {
+                return Poll::Ready(None);
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+impl Stream for WalkStream {
+    type Item = io::Result<WalkEntry>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_entry(cx)
+    }
+}
+
+impl Drop for WalkStream {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for WalkStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkStream")
+            .field("max_depth", &self.max_depth)
+            .field("concurrency", &self.concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the entries of the directory at `path`, stopping early if the walk
+/// is cancelled.
+fn read(
+    root: &DirView,
+    path: &Path,
+    depth: usize,
+    cancelled: &AtomicBool,
+) -> Vec<io::Result<WalkEntry>> {
+    let dir;
+    let dir = if path.as_os_str().is_empty() {
+        root
+    } else {
+        match root.open_dir(path) {
+            Ok(opened) => {
+                dir = opened;
+                &dir
+            }
+            Err(err) => return vec![Err(err)],
+        }
+    };
+    let entries = match dir.entries() {
+        Ok(entries) => entries,
+        Err(err) => return vec![Err(err)],
+    };
+    let mut result = Vec::new();
+    for entry in entries {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        result.push(entry.and_then(|entry| WalkEntry::new(dir, path, entry, depth, false)));
+    }
+    result
+}
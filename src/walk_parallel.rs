@@ -0,0 +1,222 @@
+use crate::{DirView, WalkEntry};
+use cap_std::fs::Dir;
+use std::borrow::Borrow;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::{fmt, io, thread};
+
+/// What a [`ParallelWalk`] does after visiting an entry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum WalkState {
+    /// Continue walking, descending into the entry if it's a directory.
+    Continue,
+
+    /// Continue walking, without descending into the entry.
+    Skip,
+
+    /// Stop walking as soon as possible.
+    Quit,
+}
+
+/// A walk over the entries in a view and its subdirectories which visits
+/// them concurrently, returned by [`DirView::walk_parallel`].
+///
+/// Each thread walks directories opened through the view, so its
+/// restrictions apply at every level, as with [`Walk`].
+///
+/// [`Walk`]: crate::Walk
+pub struct ParallelWalk {
+    root: DirView,
+    threads: usize,
+    max_depth: usize,
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns a walk over the entries within `self` and its subdirectories
+    /// which visits them on several threads.
+    #[inline]
+    pub fn walk_parallel(&self) -> io::Result<ParallelWalk> {
+        Ok(ParallelWalk {
+            root: self.try_clone()?,
+            threads: 0,
+            max_depth: usize::MAX,
+        })
+    }
+}
+
+impl ParallelWalk {
+    /// Sets the number of threads to walk with.
+    ///
+    /// The default, 0, uses [`std::thread::available_parallelism`].
+    #[inline]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the maximum depth of entries to visit, where entries of the
+    /// walked view itself have a depth of 1.
+    #[inline]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Walks the view, calling `visit` with each entry, or with errors
+    /// reading directories, and returns when every entry has been visited or
+    /// `visit` returns [`WalkState::Quit`].
+    ///
+    /// `visit` is called from several threads at once, and entries are
+    /// visited in no particular order, except that directories are visited
+    /// before their contents.
+    pub fn run<F>(self, visit: F)
+    where
+        F: Fn(io::Result<WalkEntry>) -> WalkState + Sync,
+    {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            threads => threads,
+        };
+        if self.max_depth == 0 {
+            return;
+        }
+        let shared = Shared {
+            queue: Mutex::new(Queue {
+                dirs: vec![(self.root, PathBuf::new())],
+                active: 0,
+            }),
+            ready: Condvar::new(),
+            quit: AtomicBool::new(false),
+            max_depth: self.max_depth,
+        };
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| shared.work(&visit));
+            }
+        });
+    }
+}
+
+impl fmt::Debug for ParallelWalk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParallelWalk")
+            .field("threads", &self.threads)
+            .field("max_depth", &self.max_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The state shared by the threads of a walk.
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+    quit: AtomicBool,
+    max_depth: usize,
+}
+
+/// The directories waiting to be walked, and the number being walked.
+struct Queue {
+    dirs: Vec<(DirView, PathBuf)>,
+    active: usize,
+}
+
+/// Marks a directory as walked when dropped, even if `visit` panics, so
+/// that the other threads don't wait for it forever.
+struct Active<'a>(&'a Shared);
+
+impl Drop for Active<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.0.queue.lock().unwrap();
+        queue.active -= 1;
+        if queue.active == 0 && queue.dirs.is_empty() {
+            self.0.ready.notify_all();
+        }
+    }
+}
+
+impl Shared {
+    fn work<F: Fn(io::Result<WalkEntry>) -> WalkState>(&self, visit: &F) {
+        while let Some((dir, path)) = self.next() {
+            let _active = Active(self);
+            self.walk_dir(&dir, &path, visit);
+        }
+    }
+
+    /// Waits for a directory to walk, returning `None` once there are none
+    /// left and none being walked, which could produce more.
+    fn next(&self) -> Option<(DirView, PathBuf)> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if self.quit.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Some(dir) = queue.dirs.pop() {
+                queue.active += 1;
+                return Some(dir);
+            }
+            if queue.active == 0 {
+                return None;
+            }
+            queue = self.ready.wait(queue).unwrap();
+        }
+    }
+
+    fn walk_dir<F: Fn(io::Result<WalkEntry>) -> WalkState>(
+        &self,
+        dir: &DirView,
+        path: &Path,
+        visit: &F,
+    ) {
+        let depth = path.components().count() + 1;
+        let entries = match dir.entries() {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.visited(visit(Err(err)));
+                return;
+            }
+        };
+        for entry in entries {
+            if self.quit.load(Ordering::Relaxed) {
+                return;
+            }
+            let entry = entry.and_then(|entry| WalkEntry::new(dir, path, entry, depth, false));
+            let subdir = match &entry {
+                Ok(entry) if entry.file_type().is_dir() && depth < self.max_depth => {
+                    Some(entry.path().to_path_buf())
+                }
+                _ => None,
+            };
+            if !self.visited(visit(entry)) {
+                continue;
+            }
+            if let Some(subdir) = subdir {
+                match dir.open_dir(subdir.file_name().unwrap_or_default()) {
+                    Ok(dir) => {
+                        self.queue.lock().unwrap().dirs.push((dir, subdir));
+                        self.ready.notify_one();
+                    }
+                    Err(err) => {
+                        self.visited(visit(Err(err)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles the state returned by `visit`, returning whether to descend
+    /// into the entry.
+    fn visited(&self, state: WalkState) -> bool {
+        match state {
+            WalkState::Continue => true,
+            WalkState::Skip => false,
+            WalkState::Quit => {
+                self.quit.store(true, Ordering::Relaxed);
+                let _queue = self.queue.lock().unwrap();
+                self.ready.notify_all();
+                false
+            }
+        }
+    }
+}
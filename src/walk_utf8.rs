@@ -0,0 +1,354 @@
+#[cfg(feature = "ignore")]
+use crate::ignore::{self, Rules};
+use crate::{DirEntryViewUtf8, DirViewUtf8, ReadDirViewUtf8};
+use camino::{Utf8Path, Utf8PathBuf};
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs_utf8::FileType;
+use std::cmp::Ordering;
+use std::{fmt, io, vec};
+
+/// A recursive iterator over the entries in a view and its subdirectories,
+/// returned by [`DirViewUtf8::walk`].
+///
+/// Subdirectories are opened, and their entries read, through the view, so
+/// its restrictions apply at every level: hidden files stay hidden, and
+/// subdirectories beyond the view's maximum depth fail to open. Errors are
+/// yielded in place of the entries they concern, and iteration continues
+/// after them.
+///
+/// Directories are yielded before their contents.
+pub struct WalkUtf8 {
+    root: Option<DirViewUtf8>,
+    stack: Vec<Level>,
+    error: Option<io::Error>,
+    max_depth: usize,
+    follow_links: bool,
+    sorter: Option<Box<Sorter>>,
+    filter: Option<Box<Filter>>,
+    #[cfg(feature = "ignore")]
+    ignore_files: bool,
+}
+
+type Sorter = dyn FnMut(&WalkEntryUtf8, &WalkEntryUtf8) -> Ordering + Send;
+type Filter = dyn FnMut(&WalkEntryUtf8) -> bool + Send;
+
+/// A directory being iterated over.
+struct Level {
+    dir: DirViewUtf8,
+    path: Utf8PathBuf,
+    entries: Entries,
+    #[cfg(unix)]
+    id: (u64, u64),
+    #[cfg(feature = "ignore")]
+    rules: Rules,
+}
+
+enum Entries {
+    Unsorted(ReadDirViewUtf8),
+    Sorted(vec::IntoIter<io::Result<WalkEntryUtf8>>),
+}
+
+impl WalkUtf8 {
+    pub(crate) fn new(root: DirViewUtf8) -> Self {
+        Self {
+            root: Some(root),
+            stack: Vec::new(),
+            error: None,
+            max_depth: usize::MAX,
+            follow_links: false,
+            sorter: None,
+            filter: None,
+            #[cfg(feature = "ignore")]
+            ignore_files: false,
+        }
+    }
+
+    /// Sets the maximum depth of entries to yield.
+    ///
+    /// Entries of the walked view itself have a depth of 1, so a `max_depth`
+    /// of 1 yields the same entries as [`DirViewUtf8::entries`].
+    #[inline]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Follows symbolic links, yielding the entries of directories they
+    /// point to.
+    ///
+    /// Links are resolved within the directory containing them, so links
+    /// to `..` or other parent directories are reported as errors. On Unix,
+    /// a link to the directory containing it is also reported as an error,
+    /// instead of being followed.
+    #[inline]
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Sorts the entries of each directory with `compare`.
+    ///
+    /// This reads all of a directory's entries before yielding any of them.
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: FnMut(&WalkEntryUtf8, &WalkEntryUtf8) -> Ordering + Send + 'static,
+    {
+        self.sorter = Some(Box::new(compare));
+        self
+    }
+
+    /// Sorts the entries of each directory by file name.
+    #[inline]
+    pub fn sort_by_file_name(self) -> Self {
+        self.sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    }
+
+    /// Skips entries for which `predicate` returns `false`. Directories which
+    /// are skipped aren't descended into.
+    pub fn filter_entry<F>(mut self, predicate: F) -> Self
+    where
+        F: FnMut(&WalkEntryUtf8) -> bool + Send + 'static,
+    {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+
+    /// Skips entries ignored by the `.gitignore` and `.ignore` files in the
+    /// walked directories, which are read through the view.
+    ///
+    /// Rules in `.ignore` files take precedence over those in `.gitignore`
+    /// files, and rules in subdirectories over those in their parents.
+    /// Ignored directories aren't descended into.
+    #[cfg(feature = "ignore")]
+    #[inline]
+    pub fn respect_ignore_files(mut self, respect_ignore_files: bool) -> Self {
+        self.ignore_files = respect_ignore_files;
+        self
+    }
+
+    /// Starts iterating over the entries of `dir`, at `path`.
+    fn push(&mut self, dir: DirViewUtf8, path: Utf8PathBuf) -> io::Result<()> {
+        #[cfg(unix)]
+        let id = {
+            let metadata = dir.dir.dir_metadata()?;
+            let id = (metadata.dev(), metadata.ino());
+            if self.follow_links && self.stack.iter().any(|level| level.id == id) {
+                return Err(io::Error::other(format!("filesystem loop at `{}`", path)));
+            }
+            id
+        };
+        #[cfg(feature = "ignore")]
+        let rules = if self.ignore_files {
+            Rules::read(|name| dir.read_to_string(name))?
+        } else {
+            Rules::default()
+        };
+        let read_dir = dir.entries()?;
+        let entries = match &mut self.sorter {
+            None => Entries::Unsorted(read_dir),
+            Some(sorter) => {
+                let depth = self.stack.len() + 1;
+                let mut entries = read_dir
+                    .map(|entry| WalkEntryUtf8::new(&dir, &path, entry?, depth, self.follow_links))
+                    .collect::<Vec<_>>();
+                entries.sort_by(|a, b| match (a, b) {
+                    (Ok(a), Ok(b)) => sorter(a, b),
+                    (Err(_), Err(_)) => Ordering::Equal,
+                    (Err(_), Ok(_)) => Ordering::Less,
+                    (Ok(_), Err(_)) => Ordering::Greater,
+                });
+                Entries::Sorted(entries.into_iter())
+            }
+        };
+        self.stack.push(Level {
+            dir,
+            path,
+            entries,
+            #[cfg(unix)]
+            id,
+            #[cfg(feature = "ignore")]
+            rules,
+        });
+        Ok(())
+    }
+
+    /// Returns the next entry of the innermost directory, or `None` if it
+    /// has no more.
+    fn next_entry(&mut self) -> Option<io::Result<WalkEntryUtf8>> {
+        let depth = self.stack.len();
+        let level = self.stack.last_mut()?;
+        match &mut level.entries {
+            Entries::Unsorted(read_dir) => {
+                let entry = read_dir.next()?;
+                Some(entry.and_then(|entry| {
+                    WalkEntryUtf8::new(&level.dir, &level.path, entry, depth, self.follow_links)
+                }))
+            }
+            Entries::Sorted(entries) => entries.next(),
+        }
+    }
+}
+
+impl Iterator for WalkUtf8 {
+    type Item = io::Result<WalkEntryUtf8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        if let Some(root) = self.root.take() {
+            if self.max_depth > 0 {
+                if let Err(err) = self.push(root, Utf8PathBuf::new()) {
+                    return Some(Err(err));
+                }
+            }
+        }
+        loop {
+            if self.stack.is_empty() {
+                return None;
+            }
+            let entry = match self.next_entry() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            #[cfg(feature = "ignore")]
+            if self.ignore_files
+                && ignore::is_ignored(
+                    self.stack
+                        .iter()
+                        .map(|level| (level.path.as_std_path(), &level.rules)),
+                    entry.path.as_std_path(),
+                    entry.file_type.is_dir(),
+                )
+            {
+                continue;
+            }
+            if let Some(filter) = &mut self.filter {
+                if !filter(&entry) {
+                    continue;
+                }
+            }
+            if entry.file_type.is_dir() && entry.depth < self.max_depth {
+                let parent = &self.stack.last().unwrap().dir;
+                match parent.open_dir(entry.file_name()) {
+                    Ok(dir) => {
+                        if let Err(err) = self.push(dir, entry.path.clone()) {
+                            self.error = Some(err);
+                        }
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+impl fmt::Debug for WalkUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkUtf8")
+            .field("max_depth", &self.max_depth)
+            .field("follow_links", &self.follow_links)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Entries returned by the [`WalkUtf8`] iterator.
+pub struct WalkEntryUtf8 {
+    entry: DirEntryViewUtf8,
+    path: Utf8PathBuf,
+    depth: usize,
+    file_type: FileType,
+    followed: bool,
+}
+
+impl WalkEntryUtf8 {
+    fn new(
+        parent: &DirViewUtf8,
+        parent_path: &Utf8Path,
+        entry: DirEntryViewUtf8,
+        depth: usize,
+        follow_links: bool,
+    ) -> io::Result<Self> {
+        let path = parent_path.join(entry.file_name()?);
+        let mut file_type = entry.file_type()?;
+        let followed = follow_links && file_type.is_symlink();
+        if followed {
+            file_type = parent
+                .metadata(path.file_name().unwrap_or_default())?
+                .file_type();
+        }
+        Ok(Self {
+            entry,
+            path,
+            depth,
+            file_type,
+            followed,
+        })
+    }
+
+    /// Returns the path of the entry, relative to the walked view.
+    #[inline]
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// Returns the path of the entry, relative to the walked view.
+    #[inline]
+    pub fn into_path(self) -> Utf8PathBuf {
+        self.path
+    }
+
+    /// Returns the bare file name of the entry.
+    #[inline]
+    pub fn file_name(&self) -> &str {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    /// Returns how many directories deep the entry is, where entries of the
+    /// walked view itself have a depth of 1.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the file type of the entry. When following symbolic links,
+    /// this is the file type of the link's target.
+    #[inline]
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns `true` if the entry is a symbolic link which was followed.
+    #[inline]
+    pub fn path_is_symlink(&self) -> bool {
+        self.followed
+    }
+
+    /// Returns the underlying directory entry, which can be used to open or
+    /// remove the entry.
+    #[inline]
+    pub fn entry(&self) -> &DirEntryViewUtf8 {
+        &self.entry
+    }
+
+    /// Returns the underlying directory entry.
+    #[inline]
+    pub fn into_entry(self) -> DirEntryViewUtf8 {
+        self.entry
+    }
+}
+
+impl fmt::Debug for WalkEntryUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkEntryUtf8")
+            .field("path", &self.path)
+            .field("depth", &self.depth)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{DirView, ViewKind};
+use cap_fs_ext::{DirExt, FollowSymlinks, MetadataExt, OpenOptionsFollowExt, SystemTimeSpec};
+use cap_std::fs::{Metadata, OpenOptions};
+use std::any::Any;
+use std::io;
+use std::path::{Path, PathBuf};
+use wasi_common::dir::{OpenResult, ReaddirCursor, ReaddirEntity};
+use wasi_common::file::{FdFlags, FileType, Filestat, OFlags};
+use wasi_common::snapshots::preview_1::error::Errno;
+use wasi_common::sync::file::{filetype_from, File};
+use wasi_common::{Error, ErrorExt, WasiDir};
+
+/// Serves the view to a WebAssembly guest as a WASI directory, so that an
+/// embedder can preopen it with `WasiCtx::push_preopened_dir`.
+///
+/// Every request from the guest is performed through the view, so its
+/// restrictions are enforced on the host: changes through a
+/// [`ViewKind::Readonly`] view fail with `EROFS`, and other denials with
+/// `EPERM`. Directories the guest opens are views too, of the same kind and
+/// with the same policy.
+///
+/// Files are handed to the guest as plain handles, as with
+/// [`DirView::open`], so a file opened for reading can't be written to. The
+/// `DSYNC`, `RSYNC`, `SYNC` and `NONBLOCK` flags aren't supported.
+#[wiggle::async_trait]
+impl WasiDir for DirView {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn open_file(
+        &self,
+        symlink_follow: bool,
+        path: &str,
+        oflags: OFlags,
+        read: bool,
+        write: bool,
+        fdflags: FdFlags,
+    ) -> Result<OpenResult, Error> {
+        if !fdflags.is_empty() && fdflags != FdFlags::APPEND {
+            return Err(Error::not_supported().context("file descriptor flags"));
+        }
+        let creates = oflags.contains(OFlags::CREATE);
+        if oflags.contains(OFlags::DIRECTORY)
+            && (creates || oflags.intersects(OFlags::EXCLUSIVE | OFlags::TRUNCATE))
+        {
+            return Err(Error::invalid_argument().context("directory oflags"));
+        }
+        let path = Path::new(path);
+        let metadata = match self.wasi_metadata(path, symlink_follow) {
+            Ok(metadata) => Some(metadata),
+            Err(err) if creates && err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(error(err)),
+        };
+        if let Some(metadata) = &metadata {
+            if metadata.is_dir() {
+                if oflags.contains(OFlags::CREATE | OFlags::EXCLUSIVE) {
+                    return Err(Error::exist());
+                }
+                if write || oflags.contains(OFlags::TRUNCATE) {
+                    return Err(Error::from(Errno::Isdir));
+                }
+                let dir = if symlink_follow {
+                    self.open_dir(path)
+                } else {
+                    self.open_dir_nofollow(path)
+                };
+                return Ok(OpenResult::Dir(Box::new(dir.map_err(error)?)));
+            }
+        }
+        if oflags.contains(OFlags::DIRECTORY) {
+            return Err(Error::not_dir().context("expected directory but got file"));
+        }
+
+        let mut options = OpenOptions::new();
+        options.read(read || !write);
+        if write || creates || oflags.contains(OFlags::TRUNCATE) {
+            self.check_writable()?;
+            options.write(true);
+        }
+        if oflags.contains(OFlags::CREATE | OFlags::EXCLUSIVE) {
+            options.create_new(true);
+        } else if creates {
+            options.create(true);
+        }
+        options.truncate(oflags.contains(OFlags::TRUNCATE));
+        options.append(fdflags.contains(FdFlags::APPEND));
+        options.follow(if symlink_follow {
+            FollowSymlinks::Yes
+        } else {
+            FollowSymlinks::No
+        });
+        let file = self.open_with(path, &options).map_err(error)?;
+        Ok(OpenResult::File(Box::new(File::from_cap_std(file))))
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), Error> {
+        self.check_writable()?;
+        DirView::create_dir(self, path).map_err(error)
+    }
+
+    async fn readdir(
+        &self,
+        cursor: ReaddirCursor,
+    ) -> Result<Box<dyn Iterator<Item = Result<ReaddirEntity, Error>> + Send>, Error> {
+        // The guest expects `.` and `..`, which views don't list.
+        let inode = self.dir_metadata().map_err(error)?.ino();
+        let mut listed = vec![
+            Ok((FileType::Directory, inode, ".".to_owned())),
+            Ok((FileType::Directory, inode, "..".to_owned())),
+        ];
+        for entry in self.entries().map_err(error)? {
+            listed.push(entry.and_then(|entry| {
+                let metadata = entry.metadata()?;
+                let name = entry.file_name_utf8()?;
+                Ok((filetype_from(&metadata.file_type()), metadata.ino(), name))
+            }));
+        }
+        let entities = listed
+            .into_iter()
+            .enumerate()
+            .map(|(index, listed)| {
+                let (filetype, inode, name) = listed.map_err(error)?;
+                Ok(ReaddirEntity {
+                    next: ReaddirCursor::from(index as u64 + 1),
+                    inode,
+                    name,
+                    filetype,
+                })
+            })
+            .skip(u64::from(cursor) as usize);
+        Ok(Box::new(entities.collect::<Vec<_>>().into_iter()))
+    }
+
+    async fn symlink(&self, old_path: &str, new_path: &str) -> Result<(), Error> {
+        self.check_writable()?;
+        DirView::symlink(self, old_path, new_path).map_err(error)
+    }
+
+    async fn remove_dir(&self, path: &str) -> Result<(), Error> {
+        self.check_writable()?;
+        DirView::remove_dir(self, path).map_err(error)
+    }
+
+    async fn unlink_file(&self, path: &str) -> Result<(), Error> {
+        self.check_writable()?;
+        DirExt::remove_file_or_symlink(self, path).map_err(error)
+    }
+
+    async fn read_link(&self, path: &str) -> Result<PathBuf, Error> {
+        self.read_link_contents(path).map_err(error)
+    }
+
+    async fn get_filestat(&self) -> Result<Filestat, Error> {
+        Ok(filestat(&self.dir_metadata().map_err(error)?))
+    }
+
+    async fn get_path_filestat(
+        &self,
+        path: &str,
+        follow_symlinks: bool,
+    ) -> Result<Filestat, Error> {
+        let metadata = self
+            .wasi_metadata(Path::new(path), follow_symlinks)
+            .map_err(error)?;
+        Ok(filestat(&metadata))
+    }
+
+    async fn rename(
+        &self,
+        path: &str,
+        dest_dir: &dyn WasiDir,
+        dest_path: &str,
+    ) -> Result<(), Error> {
+        self.check_writable()?;
+        let dest_dir = view(dest_dir)?;
+        dest_dir.check_writable()?;
+        DirView::rename(self, path, dest_dir, dest_path).map_err(error)
+    }
+
+    async fn hard_link(
+        &self,
+        path: &str,
+        target_dir: &dyn WasiDir,
+        target_path: &str,
+    ) -> Result<(), Error> {
+        let target_dir = view(target_dir)?;
+        target_dir.check_writable()?;
+        DirView::hard_link(self, path, target_dir, target_path).map_err(error)
+    }
+
+    async fn set_times(
+        &self,
+        path: &str,
+        atime: Option<wasi_common::SystemTimeSpec>,
+        mtime: Option<wasi_common::SystemTimeSpec>,
+        follow_symlinks: bool,
+    ) -> Result<(), Error> {
+        self.check_writable()?;
+        let (atime, mtime) = (atime.map(time_spec), mtime.map(time_spec));
+        if follow_symlinks {
+            DirExt::set_times(self, path, atime, mtime)
+        } else {
+            DirExt::set_symlink_times(self, path, atime, mtime)
+        }
+        .map_err(error)
+    }
+}
+
+impl DirView {
+    /// Fails with `EROFS` if the view can't make changes anywhere, which
+    /// guests report more clearly than the view's own denial.
+    fn check_writable(&self) -> Result<(), Error> {
+        match self.view_kind {
+            ViewKind::Readonly if self.policy.overrides.is_none() => Err(Errno::Rofs.into()),
+            ViewKind::Readonly | ViewKind::Full | ViewKind::DryRun => Ok(()),
+        }
+    }
+
+    fn wasi_metadata(&self, path: &Path, follow_symlinks: bool) -> io::Result<Metadata> {
+        if follow_symlinks {
+            self.metadata(path)
+        } else {
+            self.symlink_metadata(path)
+        }
+    }
+}
+
+/// Returns the view `dir` is, for operations between two directories, which
+/// need both to be views.
+fn view(dir: &dyn WasiDir) -> Result<&DirView, Error> {
+    dir.as_any()
+        .downcast_ref()
+        .ok_or_else(|| Error::badf().context("not a `DirView`"))
+}
+
+fn filestat(metadata: &Metadata) -> Filestat {
+    Filestat {
+        device_id: metadata.dev(),
+        inode: metadata.ino(),
+        filetype: filetype_from(&metadata.file_type()),
+        nlink: metadata.nlink(),
+        size: metadata.len(),
+        atim: metadata.accessed().ok().map(|time| time.into_std()),
+        mtim: metadata.modified().ok().map(|time| time.into_std()),
+        ctim: metadata.created().ok().map(|time| time.into_std()),
+    }
+}
+
+fn time_spec(spec: wasi_common::SystemTimeSpec) -> SystemTimeSpec {
+    match spec {
+        wasi_common::SystemTimeSpec::SymbolicNow => SystemTimeSpec::SymbolicNow,
+        wasi_common::SystemTimeSpec::Absolute(time) => SystemTimeSpec::Absolute(time),
+    }
+}
+
+/// Converts a view's error to a WASI error, without trapping the guest on
+/// errors which don't correspond to an errno, such as the view's own
+/// denials.
+fn error(err: io::Error) -> Error {
+    if err.raw_os_error().is_some() {
+        return err.into();
+    }
+    match err.kind() {
+        io::ErrorKind::NotFound
+        | io::ErrorKind::PermissionDenied
+        | io::ErrorKind::AlreadyExists
+        | io::ErrorKind::InvalidInput
+        | io::ErrorKind::WouldBlock => err.into(),
+        io::ErrorKind::Unsupported => Error::not_supported(),
+        io::ErrorKind::InvalidData => Error::illegal_byte_sequence(),
+        _ => Error::io(),
+    }
+}
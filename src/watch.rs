@@ -0,0 +1,361 @@
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{DirView, FileTypeSummary, Operation};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Options for [`DirView::watch`] and [`DirView::poll_changes`].
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    pub(crate) recursive: bool,
+}
+
+impl WatchOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Only the watched directory itself is initially watched, not its
+    /// subdirectories.
+    #[inline]
+    pub fn new() -> Self {
+        Self { recursive: false }
+    }
+
+    /// Sets whether to watch subdirectories too, including ones created
+    /// while watching.
+    ///
+    /// Subdirectories are opened through the view, so those it hides, or
+    /// which are beyond its maximum depth, aren't watched.
+    #[inline]
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+}
+
+impl Default for WatchOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A change reported by [`DirView::watch`] or [`DirView::poll_changes`].
+///
+/// Paths are relative to the view they were called on.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum WatchEvent {
+    /// An entry was created, or moved into a watched directory from
+    /// elsewhere.
+    Create(PathBuf),
+
+    /// The contents or metadata of an entry changed.
+    Modify(PathBuf),
+
+    /// An entry was removed, or moved out of the watched directories.
+    Remove(PathBuf),
+
+    /// An entry was renamed within the watched directories.
+    Rename {
+        /// The entry's previous path.
+        from: PathBuf,
+        /// The entry's new path.
+        to: PathBuf,
+    },
+}
+
+impl WatchEvent {
+    /// Returns this event with `prefix` prepended to its paths.
+    pub(crate) fn prefixed(self, prefix: &Path) -> Self {
+        match self {
+            Self::Create(path) => Self::Create(prefix.join(path)),
+            Self::Modify(path) => Self::Modify(prefix.join(path)),
+            Self::Remove(path) => Self::Remove(prefix.join(path)),
+            Self::Rename { from, to } => Self::Rename {
+                from: prefix.join(from),
+                to: prefix.join(to),
+            },
+        }
+    }
+}
+
+/// An iterator over the changes made in a directory, found by comparing
+/// snapshots of its entries' metadata, returned by
+/// [`DirView::poll_changes`].
+///
+/// Iterating blocks until a snapshot differs from the previous one. Changes
+/// made and undone between snapshots aren't reported, and neither are
+/// changes which leave an entry's size, modification time, and permissions
+/// as they were. On Unix, an entry removed from one path and created at
+/// another with the same inode is reported as a rename.
+pub struct PollChanges {
+    view: DirView,
+    prefix: PathBuf,
+    recursive: bool,
+    interval: Duration,
+    snapshot: BTreeMap<PathBuf, EntryState>,
+    events: VecDeque<WatchEvent>,
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Watches the directory at `path` for changes by taking a snapshot of
+    /// its entries' metadata every `interval`.
+    ///
+    /// This works on any platform and filesystem, including network
+    /// filesystems which don't support change notification, at the cost of
+    /// latency and of reading every watched directory at every interval.
+    /// Changes are reported with the same events, and filtered in the same
+    /// way, as with [`Self::watch`] where it's available.
+    pub fn poll_changes<P: AsRef<Path>>(
+        &self,
+        path: P,
+        interval: Duration,
+        options: &WatchOptions,
+    ) -> io::Result<PollChanges> {
+        self.audited(Operation::Watch, path, |path| {
+            let view = self.open_child_dir(path)?;
+            PollChanges::new(view, path.to_path_buf(), interval, options)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Watches the directory at `path` for changes by taking a snapshot of
+    /// its entries' metadata every `interval`.
+    ///
+    /// See [`DirView::poll_changes`].
+    pub fn poll_changes<P: AsRef<Utf8Path>>(
+        &self,
+        path: P,
+        interval: Duration,
+        options: &WatchOptions,
+    ) -> io::Result<PollChanges> {
+        self.audited(Operation::Watch, path, |path| {
+            let view = self.open_child_dir(path)?.into_cap_std_view();
+            PollChanges::new(view, path.into(), interval, options)
+        })
+    }
+}
+
+impl PollChanges {
+    fn new(
+        view: DirView,
+        prefix: PathBuf,
+        interval: Duration,
+        options: &WatchOptions,
+    ) -> io::Result<Self> {
+        let snapshot = snapshot(&view, options.recursive)?;
+        Ok(Self {
+            view,
+            prefix,
+            recursive: options.recursive,
+            interval,
+            snapshot,
+            events: VecDeque::new(),
+        })
+    }
+}
+
+impl Iterator for PollChanges {
+    type Item = io::Result<WatchEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event.prefixed(&self.prefix)));
+            }
+            thread::sleep(self.interval);
+            let snapshot = match snapshot(&self.view, self.recursive) {
+                Ok(snapshot) => snapshot,
+                Err(err) => return Some(Err(err)),
+            };
+            self.events.extend(diff(&self.snapshot, &snapshot));
+            self.snapshot = snapshot;
+        }
+    }
+}
+
+impl std::fmt::Debug for PollChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PollChanges")
+            .field("prefix", &self.prefix)
+            .field("recursive", &self.recursive)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The metadata of an entry which is compared between snapshots.
+#[derive(Clone, Debug)]
+struct EntryState {
+    file_type: FileTypeSummary,
+    len: u64,
+    modified: Option<SystemTime>,
+    readonly: bool,
+    #[cfg(unix)]
+    mode: u32,
+    #[cfg(unix)]
+    id: (u64, u64),
+}
+
+impl EntryState {
+    fn new(metadata: &Metadata) -> Self {
+        Self {
+            file_type: metadata.file_type().into(),
+            len: metadata.len(),
+            modified: metadata.modified().ok().map(|time| time.into_std()),
+            readonly: metadata.permissions().readonly(),
+            #[cfg(unix)]
+            mode: metadata.mode(),
+            #[cfg(unix)]
+            id: (metadata.dev(), metadata.ino()),
+        }
+    }
+
+    /// Tests whether the entry changed between snapshots. A directory's
+    /// size and modification time change with its entries, which are
+    /// reported themselves, so they're ignored.
+    fn changed(&self, new: &Self) -> bool {
+        #[cfg(unix)]
+        if self.mode != new.mode {
+            return true;
+        }
+        self.readonly != new.readonly
+            || (self.file_type != FileTypeSummary::Dir
+                && (self.len != new.len || self.modified != new.modified))
+    }
+}
+
+/// Takes a snapshot of the entries of `view`, and of its subdirectories if
+/// `recursive`. Entries removed while the snapshot is taken are skipped.
+fn snapshot(view: &DirView, recursive: bool) -> io::Result<BTreeMap<PathBuf, EntryState>> {
+    let mut walk = view.walk()?;
+    if !recursive {
+        walk = walk.max_depth(1);
+    }
+    let mut snapshot = BTreeMap::new();
+    for entry in walk {
+        let metadata = entry.and_then(|entry| {
+            let metadata = view.symlink_metadata(entry.path())?;
+            Ok((entry.into_path(), metadata))
+        });
+        match metadata {
+            Ok((path, metadata)) => {
+                snapshot.insert(path, EntryState::new(&metadata));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Returns the changes between snapshots `old` and `new`: renames, then
+/// removals, then creations, then modifications.
+fn diff(
+    old: &BTreeMap<PathBuf, EntryState>,
+    new: &BTreeMap<PathBuf, EntryState>,
+) -> Vec<WatchEvent> {
+    let mut removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .map(PathBuf::as_path)
+        .collect::<Vec<_>>();
+    let mut created = new
+        .keys()
+        .filter(|path| !old.contains_key(*path))
+        .map(PathBuf::as_path)
+        .collect::<Vec<_>>();
+    let renamed = renames(old, new, &mut removed, &mut created);
+
+    let mut events = Vec::new();
+    let mut modified = Vec::new();
+    for (from, to) in &renamed {
+        // The contents of a renamed directory are renamed with it, so only
+        // report the changes they've had too.
+        let inner = renamed.iter().any(|(outer_from, outer_to)| {
+            from != outer_from
+                && from
+                    .strip_prefix(outer_from)
+                    .is_ok_and(|rest| outer_to.join(rest) == *to)
+        });
+        if !inner {
+            events.push(WatchEvent::Rename {
+                from: from.to_path_buf(),
+                to: to.to_path_buf(),
+            });
+        }
+        if old[*from].changed(&new[*to]) {
+            modified.push(to.to_path_buf());
+        }
+    }
+    events.extend(
+        removed
+            .iter()
+            .map(|path| WatchEvent::Remove(path.to_path_buf())),
+    );
+    events.extend(
+        created
+            .iter()
+            .map(|path| WatchEvent::Create(path.to_path_buf())),
+    );
+    for (path, new_state) in new {
+        let Some(old_state) = old.get(path) else {
+            continue;
+        };
+        if old_state.file_type != new_state.file_type {
+            events.push(WatchEvent::Remove(path.clone()));
+            events.push(WatchEvent::Create(path.clone()));
+        } else if old_state.changed(new_state) {
+            modified.push(path.clone());
+        }
+    }
+    events.extend(modified.into_iter().map(WatchEvent::Modify));
+    events
+}
+
+/// Pairs up entries in `removed` and `created` which are the same entry,
+/// removing them from both, and returns the pairs.
+#[cfg(unix)]
+fn renames<'a>(
+    old: &BTreeMap<PathBuf, EntryState>,
+    new: &BTreeMap<PathBuf, EntryState>,
+    removed: &mut Vec<&'a Path>,
+    created: &mut Vec<&'a Path>,
+) -> Vec<(&'a Path, &'a Path)> {
+    let mut ids = removed
+        .iter()
+        .map(|path| (old[*path].id, *path))
+        .collect::<HashMap<_, _>>();
+    let mut renamed = Vec::new();
+    created.retain(|to| match ids.get(&new[*to].id) {
+        Some(from) if old[*from].file_type == new[*to].file_type => {
+            renamed.push((*from, *to));
+            ids.remove(&new[*to].id);
+            false
+        }
+        _ => true,
+    });
+    removed.retain(|from| !renamed.iter().any(|(renamed_from, _)| renamed_from == from));
+    renamed
+}
+
+/// Without inode numbers, renames aren't detected.
+#[cfg(not(unix))]
+fn renames<'a>(
+    _old: &BTreeMap<PathBuf, EntryState>,
+    _new: &BTreeMap<PathBuf, EntryState>,
+    _removed: &mut Vec<&'a Path>,
+    _created: &mut Vec<&'a Path>,
+) -> Vec<(&'a Path, &'a Path)> {
+    Vec::new()
+}
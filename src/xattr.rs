@@ -0,0 +1,202 @@
+use crate::owner::metadata_options;
+use crate::DirView;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::Operation;
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::Dir;
+use rustix::fs::{fgetxattr, flistxattr, fremovexattr, fsetxattr, XattrFlags};
+use rustix::io::Errno;
+use std::borrow::Borrow;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::fd::AsFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Returns the value of the extended attribute `name` of the file at
+    /// `path`, or `None` if it has no such attribute.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn get_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+        &self,
+        path: P,
+        name: N,
+    ) -> io::Result<Option<Vec<u8>>> {
+        self.audited(Operation::GetXattr, path, |path| {
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            get(&file, name.as_ref())
+        })
+    }
+
+    /// Sets the extended attribute `name` of the file at `path` to `value`.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn set_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+        &self,
+        path: P,
+        name: N,
+        value: &[u8],
+    ) -> io::Result<()> {
+        self.audited(Operation::SetXattr, path, |path| {
+            if self.dry_run(Operation::SetXattr, path, None)? {
+                return Ok(());
+            }
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            Ok(fsetxattr(&file, name.as_ref(), value, XattrFlags::empty())?)
+        })
+    }
+
+    /// Returns the names of the extended attributes of the file at `path`.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn list_xattr<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<OsString>> {
+        self.audited(Operation::ListXattr, path, |path| {
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            list(&file)
+        })
+    }
+
+    /// Removes the extended attribute `name` of the file at `path`.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn remove_xattr<P: AsRef<Path>, N: AsRef<OsStr>>(
+        &self,
+        path: P,
+        name: N,
+    ) -> io::Result<()> {
+        self.audited(Operation::RemoveXattr, path, |path| {
+            if self.dry_run(Operation::RemoveXattr, path, None)? {
+                return Ok(());
+            }
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            Ok(fremovexattr(&file, name.as_ref())?)
+        })
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Returns the value of the extended attribute `name` of the file at
+    /// `path`, or `None` if it has no such attribute.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn get_xattr<P: AsRef<Utf8Path>, N: AsRef<OsStr>>(
+        &self,
+        path: P,
+        name: N,
+    ) -> io::Result<Option<Vec<u8>>> {
+        self.audited(Operation::GetXattr, path, |path| {
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            get(&file, name.as_ref())
+        })
+    }
+
+    /// Sets the extended attribute `name` of the file at `path` to `value`.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn set_xattr<P: AsRef<Utf8Path>, N: AsRef<OsStr>>(
+        &self,
+        path: P,
+        name: N,
+        value: &[u8],
+    ) -> io::Result<()> {
+        self.audited(Operation::SetXattr, path, |path| {
+            if self.dry_run(Operation::SetXattr, path, None)? {
+                return Ok(());
+            }
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            Ok(fsetxattr(&file, name.as_ref(), value, XattrFlags::empty())?)
+        })
+    }
+
+    /// Returns the names of the extended attributes of the file at `path`.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn list_xattr<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Vec<OsString>> {
+        self.audited(Operation::ListXattr, path, |path| {
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            list(&file)
+        })
+    }
+
+    /// Removes the extended attribute `name` of the file at `path`.
+    ///
+    /// This follows symbolic links, within `self`.
+    pub fn remove_xattr<P: AsRef<Utf8Path>, N: AsRef<OsStr>>(
+        &self,
+        path: P,
+        name: N,
+    ) -> io::Result<()> {
+        self.audited(Operation::RemoveXattr, path, |path| {
+            if self.dry_run(Operation::RemoveXattr, path, None)? {
+                return Ok(());
+            }
+            let file = self.dir.borrow().open_with(path, &metadata_options())?;
+            Ok(fremovexattr(&file, name.as_ref())?)
+        })
+    }
+}
+
+fn get<Fd: AsFd>(fd: Fd, name: &OsStr) -> io::Result<Option<Vec<u8>>> {
+    let mut value = Vec::new();
+    loop {
+        // Query the size, then read into a buffer of that size, retrying if
+        // the value grows in between.
+        let len = match fgetxattr(&fd, name, &mut []) {
+            Ok(len) => len,
+            Err(err) if is_no_attr(err) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        value.resize(len, 0);
+        match fgetxattr(&fd, name, &mut value) {
+            Ok(len) => {
+                value.truncate(len);
+                return Ok(Some(value));
+            }
+            Err(Errno::RANGE) => continue,
+            Err(err) if is_no_attr(err) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn list<Fd: AsFd>(fd: Fd) -> io::Result<Vec<OsString>> {
+    let mut list = Vec::new();
+    loop {
+        let len = flistxattr(&fd, &mut [])?;
+        list.resize(len, 0);
+        match flistxattr(&fd, &mut list) {
+            Ok(len) => {
+                list.truncate(len);
+                break;
+            }
+            Err(Errno::RANGE) => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    // The names are NUL-terminated and concatenated.
+    Ok(list
+        .split(|c| *c == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            // `c_char` is signed on some platforms.
+            #[allow(clippy::unnecessary_cast)]
+            let name: Vec<u8> = name.iter().map(|c| *c as u8).collect();
+            OsStr::from_bytes(&name).to_owned()
+        })
+        .collect())
+}
+
+fn is_no_attr(err: Errno) -> bool {
+    #[cfg(target_vendor = "apple")]
+    {
+        err == Errno::NOATTR
+    }
+    #[cfg(not(target_vendor = "apple"))]
+    {
+        err == Errno::NODATA
+    }
+}
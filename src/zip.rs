@@ -0,0 +1,669 @@
+use crate::date::civil_from_days;
+use crate::tar::archive_name;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{CopySymlinks, DirView};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+#[cfg(unix)]
+use cap_std::fs::MetadataExt;
+use cap_std::fs::{Dir, Metadata};
+use std::borrow::Borrow;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// The amount of input compressed into each deflate block, which is the
+/// most a stored block can hold.
+const CHUNK: usize = 0xffff;
+
+/// The size of the deflate window.
+const WINDOW: usize = 32 * 1024;
+
+/// How [`DirView::write_zip`] compresses files.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ZipCompression {
+    /// Store files uncompressed.
+    Stored,
+
+    /// Compress files with deflate, which every zip reader supports.
+    Deflated,
+}
+
+/// Options for [`DirView::write_zip`].
+#[derive(Clone, Debug)]
+pub struct ZipOptions {
+    compression: ZipCompression,
+    symlinks: CopySymlinks,
+}
+
+impl ZipOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// Files are initially deflated, and symbolic links are initially
+    /// stored as links.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            compression: ZipCompression::Deflated,
+            symlinks: CopySymlinks::Preserve,
+        }
+    }
+
+    /// Sets how files are compressed.
+    #[inline]
+    pub fn compression(&mut self, compression: ZipCompression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets how symbolic links are archived.
+    ///
+    /// Preserved links are stored as Info-ZIP does, as entries with the
+    /// link's target as their contents, which Unix zip readers restore as
+    /// links.
+    #[inline]
+    pub fn symlinks(&mut self, symlinks: CopySymlinks) -> &mut Self {
+        self.symlinks = symlinks;
+        self
+    }
+}
+
+impl Default for ZipOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Writes a zip archive of the directory at `path` to `writer`.
+    ///
+    /// As with [`Self::write_tar`], entries are named relative to `path` and
+    /// written in order of their paths, so archiving the same tree always
+    /// produces the same archive, and everything is read through the view.
+    /// Modification times are recorded in UTC, and on Unix, modes are
+    /// recorded too.
+    ///
+    /// The archive is streamed, so `writer` needn't be seekable. Archives
+    /// which would need zip64, with files or archives of 4 GiB or more or
+    /// more than 65535 entries, aren't supported.
+    pub fn write_zip<P: AsRef<Path>, W: Write>(
+        &self,
+        path: P,
+        writer: W,
+        options: &ZipOptions,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let dir = self.open_dir(if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        })?;
+        let mut zip = ZipWriter {
+            writer: Counted { writer, len: 0 },
+            central: Vec::new(),
+            entries: 0,
+        };
+        let follow = options.symlinks == CopySymlinks::Follow;
+        for entry in dir.walk()?.sort_by_file_name().follow_links(follow) {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = if follow {
+                dir.metadata(path)?
+            } else {
+                dir.symlink_metadata(path)?
+            };
+            let mut name = archive_name(path);
+            let file_type = metadata.file_type();
+            if file_type.is_symlink() {
+                if options.symlinks == CopySymlinks::Skip {
+                    continue;
+                }
+                let target = archive_name(&dir.read_link_contents(path)?);
+                zip.add(
+                    &name,
+                    &metadata,
+                    ZipCompression::Stored,
+                    &target[..],
+                    0o120000,
+                )?;
+            } else if file_type.is_dir() {
+                name.push(b'/');
+                zip.add(
+                    &name,
+                    &metadata,
+                    ZipCompression::Stored,
+                    io::empty(),
+                    0o040000,
+                )?;
+            } else if file_type.is_file() {
+                let file = dir.open(path)?;
+                let metadata = file.metadata()?;
+                let len = metadata.len();
+                if len >= u64::from(u32::MAX) {
+                    return Err(too_large());
+                }
+                zip.add(
+                    &name,
+                    &metadata,
+                    options.compression,
+                    file.take(len),
+                    0o100000,
+                )?;
+            }
+        }
+        zip.finish()
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl DirViewUtf8 {
+    /// Writes a zip archive of the directory at `path` to `writer`.
+    ///
+    /// See [`DirView::write_zip`].
+    pub fn write_zip<P: AsRef<Utf8Path>, W: Write>(
+        &self,
+        path: P,
+        writer: W,
+        options: &ZipOptions,
+    ) -> io::Result<()> {
+        self.try_clone()?.into_cap_std_view().write_zip(
+            path.as_ref().as_std_path(),
+            writer,
+            options,
+        )
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "archive too large for a zip archive without zip64",
+    )
+}
+
+/// A writer which counts the bytes written to it.
+struct Counted<W> {
+    writer: W,
+    len: u64,
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.len += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+struct ZipWriter<W> {
+    writer: Counted<W>,
+
+    /// The central directory, written at the end.
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Writes an entry with the contents read from `contents`, and of the
+    /// type given by `kind`, as in the `S_IFMT` bits of a Unix mode.
+    fn add(
+        &mut self,
+        name: &[u8],
+        metadata: &Metadata,
+        compression: ZipCompression,
+        mut contents: impl Read,
+        kind: u32,
+    ) -> io::Result<()> {
+        self.entries = self.entries.checked_add(1).ok_or_else(too_large)?;
+        if name.len() > usize::from(u16::MAX) {
+            return Err(too_large());
+        }
+        let offset = u32::try_from(self.writer.len).map_err(|_| too_large())?;
+        let (time, date) = dos_time(metadata);
+        let method: u16 = match compression {
+            ZipCompression::Stored => 0,
+            ZipCompression::Deflated => 8,
+        };
+        // Sizes follow the data, in a data descriptor, and names are UTF-8
+        // if they're valid UTF-8.
+        let mut flags = 0x08;
+        if std::str::from_utf8(name).is_ok() {
+            flags |= 0x800;
+        }
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put_u32(&mut header, 0x0403_4b50);
+        put_u16(&mut header, 20);
+        put_u16(&mut header, flags);
+        put_u16(&mut header, method);
+        put_u16(&mut header, time);
+        put_u16(&mut header, date);
+        header.extend_from_slice(&[0; 12]);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name);
+        self.writer.write_all(&header)?;
+
+        let start = self.writer.len;
+        let mut crc = Crc32::new();
+        let mut len = 0_u64;
+        let mut deflater = Deflater::new(&mut self.writer);
+        let mut buf = vec![0; CHUNK];
+        loop {
+            let mut filled = 0;
+            while filled < buf.len() {
+                match contents.read(&mut buf[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            let chunk = &buf[..filled];
+            crc.update(chunk);
+            len += filled as u64;
+            let last = filled < buf.len();
+            match compression {
+                ZipCompression::Stored => deflater.out.writer.write_all(chunk)?,
+                ZipCompression::Deflated => deflater.block(chunk, last)?,
+            }
+            if last {
+                break;
+            }
+        }
+        deflater.out.finish()?;
+        let compressed = self.writer.len - start;
+        let crc = crc.finish();
+        let (Ok(len), Ok(compressed)) = (u32::try_from(len), u32::try_from(compressed)) else {
+            return Err(too_large());
+        };
+
+        let mut descriptor = Vec::with_capacity(16);
+        put_u32(&mut descriptor, 0x0807_4b50);
+        put_u32(&mut descriptor, crc);
+        put_u32(&mut descriptor, compressed);
+        put_u32(&mut descriptor, len);
+        self.writer.write_all(&descriptor)?;
+
+        let central = &mut self.central;
+        put_u32(central, 0x0201_4b50);
+        // Made by Unix, so that readers use the mode in the external
+        // attributes.
+        put_u16(central, if cfg!(unix) { 3 << 8 | 20 } else { 20 });
+        put_u16(central, 20);
+        put_u16(central, flags);
+        put_u16(central, method);
+        put_u16(central, time);
+        put_u16(central, date);
+        put_u32(central, crc);
+        put_u32(central, compressed);
+        put_u32(central, len);
+        put_u16(central, name.len() as u16);
+        central.extend_from_slice(&[0; 8]);
+        put_u32(central, external_attributes(metadata, kind));
+        put_u32(central, offset);
+        central.extend_from_slice(name);
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let offset = u32::try_from(self.writer.len).map_err(|_| too_large())?;
+        let size = u32::try_from(self.central.len()).map_err(|_| too_large())?;
+        let mut end = Vec::with_capacity(22);
+        put_u32(&mut end, 0x0605_4b50);
+        put_u32(&mut end, 0);
+        put_u16(&mut end, self.entries);
+        put_u16(&mut end, self.entries);
+        put_u32(&mut end, size);
+        put_u32(&mut end, offset);
+        put_u16(&mut end, 0);
+        self.writer.write_all(&self.central)?;
+        self.writer.write_all(&end)?;
+        self.writer.flush()
+    }
+}
+
+/// Returns the external attributes of an entry: its Unix mode, and the
+/// MS-DOS directory and read-only flags.
+fn external_attributes(metadata: &Metadata, kind: u32) -> u32 {
+    #[cfg(unix)]
+    let mode = metadata.mode() & 0o7777;
+    #[cfg(not(unix))]
+    let mode = if metadata.permissions().readonly() {
+        0o555
+    } else {
+        0o755
+    } & if kind == 0o040000 { 0o777 } else { 0o666 };
+    let mut dos = 0;
+    if kind == 0o040000 {
+        dos |= 0x10;
+    }
+    if metadata.permissions().readonly() {
+        dos |= 0x01;
+    }
+    (kind | mode) << 16 | dos
+}
+
+/// Returns the MS-DOS time and date of the modification time of an entry,
+/// in UTC, clamped to the range MS-DOS dates can represent.
+fn dos_time(metadata: &Metadata) -> (u16, u16) {
+    let secs = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.into_std().duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs()) as i64;
+    let (year, month, day) = civil_from_days(secs / 86400);
+    if year < 1980 {
+        return (0, 1 << 5 | 1);
+    }
+    if year > 2107 {
+        return (23 << 11 | 59 << 5 | 29, 127 << 9 | 12 << 5 | 31);
+    }
+    let secs = secs % 86400;
+    let time = (secs / 3600) << 11 | (secs / 60 % 60) << 5 | (secs % 60 / 2);
+    let date = (year - 1980) << 9 | i64::from(month) << 5 | i64::from(day);
+    (time as u16, date as u16)
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// The CRC-32 checksum zip uses.
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 {
+                    crc >> 1 ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    pub(crate) fn new() -> Self {
+        Self(!0)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = Self::TABLE[((self.0 ^ u32::from(*byte)) & 0xff) as usize] ^ self.0 >> 8;
+        }
+    }
+
+    pub(crate) fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// Writes bits least-significant first, as deflate does.
+struct BitWriter<W> {
+    writer: W,
+    bits: u64,
+    len: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    fn write(&mut self, value: u32, len: u32) -> io::Result<()> {
+        self.bits |= u64::from(value) << self.len;
+        self.len += len;
+        if self.len >= 32 {
+            self.writer.write_all(&(self.bits as u32).to_le_bytes())?;
+            self.bits >>= 32;
+            self.len -= 32;
+        }
+        Ok(())
+    }
+
+    /// Writes a Huffman code, which is packed most-significant bit first.
+    fn write_code(&mut self, code: u32, len: u32) -> io::Result<()> {
+        self.write(code.reverse_bits() >> (32 - len), len)
+    }
+
+    /// Writes the remaining bits, padded to a whole byte.
+    fn finish(&mut self) -> io::Result<()> {
+        let bytes = self.len.div_ceil(8) as usize;
+        self.writer.write_all(&self.bits.to_le_bytes()[..bytes])?;
+        self.bits = 0;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+pub(crate) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+pub(crate) const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+pub(crate) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+pub(crate) const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The longest hash chain followed when looking for a match.
+const MAX_CHAIN: usize = 64;
+
+/// Hashes the first three bytes of `bytes` into 15 bits.
+fn hash(bytes: &[u8]) -> usize {
+    let key = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (key.wrapping_mul(0x9e37_79b1) >> 17) as usize
+}
+
+/// A literal byte, or a match of a length and a distance back.
+enum Token {
+    Literal(u8),
+    Match(usize, usize),
+}
+
+impl Token {
+    /// Returns the number of bits the token takes with the fixed Huffman
+    /// codes.
+    fn bits(&self) -> usize {
+        match *self {
+            Self::Literal(byte) => literal_bits(u32::from(byte)),
+            Self::Match(len, dist) => {
+                let len = LENGTH_BASE
+                    .iter()
+                    .rposition(|base| usize::from(*base) <= len)
+                    .unwrap();
+                let dist = DISTANCE_BASE
+                    .iter()
+                    .rposition(|base| usize::from(*base) <= dist)
+                    .unwrap();
+                literal_bits(257 + len as u32)
+                    + usize::from(LENGTH_EXTRA[len])
+                    + 5
+                    + usize::from(DISTANCE_EXTRA[dist])
+            }
+        }
+    }
+}
+
+/// Returns the length of the fixed Huffman code for a literal/length
+/// symbol.
+fn literal_bits(symbol: u32) -> usize {
+    match symbol {
+        0..=143 => 8,
+        144..=255 => 9,
+        256..=279 => 7,
+        _ => 8,
+    }
+}
+
+/// A deflate compressor, which finds matches with hash chains and codes
+/// them with the fixed Huffman codes, falling back to storing blocks which
+/// don't compress.
+struct Deflater<W> {
+    out: BitWriter<W>,
+
+    /// The end of the previous input, which matches may refer back to.
+    history: Vec<u8>,
+}
+
+impl<W: Write> Deflater<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            out: BitWriter {
+                writer,
+                bits: 0,
+                len: 0,
+            },
+            history: Vec::new(),
+        }
+    }
+
+    /// Compresses `input` into a block, which is the final one if `last`.
+    fn block(&mut self, input: &[u8], last: bool) -> io::Result<()> {
+        let mut data = std::mem::take(&mut self.history);
+        let start = data.len();
+        data.extend_from_slice(input);
+
+        // `head` holds the latest position of each hash of three bytes, and
+        // `prev` the previous position with the same hash.
+        let mut head = vec![usize::MAX; 1 << 15];
+        let mut prev = vec![usize::MAX; data.len()];
+        let insert = |i: usize, head: &mut [usize], prev: &mut [usize]| {
+            if i + 3 <= data.len() {
+                let h = hash(&data[i..]);
+                prev[i] = head[h];
+                head[h] = i;
+            }
+        };
+        for i in 0..start {
+            insert(i, &mut head, &mut prev);
+        }
+
+        let mut tokens = Vec::new();
+        let mut i = start;
+        while i < data.len() {
+            let (mut best_len, mut best_dist) = (0, 0);
+            if i + 3 <= data.len() {
+                let max_len = (data.len() - i).min(258);
+                let mut candidate = head[hash(&data[i..])];
+                let mut chain = 0;
+                while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                    let len = data[candidate..candidate + max_len]
+                        .iter()
+                        .zip(&data[i..i + max_len])
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    if len > best_len {
+                        (best_len, best_dist) = (len, i - candidate);
+                        if len == max_len {
+                            break;
+                        }
+                    }
+                    candidate = prev[candidate];
+                    chain += 1;
+                }
+            }
+            if best_len >= 3 {
+                tokens.push(Token::Match(best_len, best_dist));
+                for j in i..i + best_len {
+                    insert(j, &mut head, &mut prev);
+                }
+                i += best_len;
+            } else {
+                tokens.push(Token::Literal(data[i]));
+                insert(i, &mut head, &mut prev);
+                i += 1;
+            }
+        }
+
+        // Store the input as is if compressing it wouldn't make it smaller,
+        // as with data which is already compressed.
+        let compressed = tokens.iter().map(Token::bits).sum::<usize>() + literal_bits(256);
+        if compressed > input.len() * 8 + 32 {
+            self.out.write(u32::from(last), 1)?;
+            self.out.write(0, 2)?;
+            self.out.finish()?;
+            let len = input.len() as u16;
+            self.out.writer.write_all(&len.to_le_bytes())?;
+            self.out.writer.write_all(&(!len).to_le_bytes())?;
+            self.out.writer.write_all(input)?;
+        } else {
+            self.out.write(u32::from(last), 1)?;
+            self.out.write(1, 2)?;
+            for token in tokens {
+                match token {
+                    Token::Literal(byte) => self.literal(u32::from(byte))?,
+                    Token::Match(len, dist) => {
+                        self.length(len)?;
+                        self.distance(dist)?;
+                    }
+                }
+            }
+            self.literal(256)?;
+        }
+
+        data.drain(..data.len().saturating_sub(WINDOW));
+        self.history = data;
+        Ok(())
+    }
+
+    /// Writes a literal/length symbol with the fixed Huffman code.
+    fn literal(&mut self, symbol: u32) -> io::Result<()> {
+        match symbol {
+            0..=143 => self.out.write_code(0x30 + symbol, 8),
+            144..=255 => self.out.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.out.write_code(symbol - 256, 7),
+            _ => self.out.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn length(&mut self, len: usize) -> io::Result<()> {
+        let code = LENGTH_BASE
+            .iter()
+            .rposition(|base| usize::from(*base) <= len)
+            .unwrap();
+        self.literal(257 + code as u32)?;
+        let extra = u32::from(LENGTH_EXTRA[code]);
+        if extra > 0 {
+            self.out
+                .write((len - usize::from(LENGTH_BASE[code])) as u32, extra)?;
+        }
+        Ok(())
+    }
+
+    fn distance(&mut self, dist: usize) -> io::Result<()> {
+        let code = DISTANCE_BASE
+            .iter()
+            .rposition(|base| usize::from(*base) <= dist)
+            .unwrap();
+        self.out.write_code(code as u32, 5)?;
+        let extra = u32::from(DISTANCE_EXTRA[code]);
+        if extra > 0 {
+            self.out
+                .write((dist - usize::from(DISTANCE_BASE[code])) as u32, extra)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use crate::archive::{
+    insert_entry, malformed, normalize, os_path, read_exact_at, truncated, Compression, Entry,
+};
+use crate::date::days_from_civil;
+use crate::inflate::Inflate;
+use crate::zip::Crc32;
+#[cfg(feature = "fs_utf8")]
+use crate::DirViewUtf8;
+use crate::{ArchiveDirView, DirView, FileTypeSummary};
+#[cfg(feature = "fs_utf8")]
+use camino::Utf8Path;
+use cap_std::fs::{Dir, File};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::io::{self, BufReader, Read};
+use std::mem;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// The largest symbolic link target accepted.
+const MAX_LINK: u64 = 1 << 16;
+
+/// A readonly view of the contents of a zip archive, presented as a
+/// directory.
+///
+/// This dereferences to an [`ArchiveDirView`], which provides the read side
+/// of the [`DirView`] method surface, so bundles shipped as zip archives can
+/// be consumed without extracting them. Entries are located through the
+/// archive's central directory, so any of them can be opened directly.
+/// Stored entries are read from the archive in place, and deflated ones are
+/// decompressed as they're read, and checked against their recorded sizes
+/// and checksums once they've been read to the end. Other compression methods and encrypted
+/// entries aren't supported, and fail to open.
+///
+/// Modes and symbolic links are presented for entries archived on Unix, and
+/// modification times are taken from the extended timestamp if there is
+/// one, and otherwise from the MS-DOS time, assuming UTC.
+#[derive(Clone, Debug)]
+pub struct ZipDirView(ArchiveDirView);
+
+impl ZipDirView {
+    /// Constructs a view of the zip archive in `file`.
+    ///
+    /// This reads the archive's central directory, and the local header of
+    /// each entry, failing if any is malformed. zip64 archives are
+    /// supported.
+    pub fn from_file(file: File) -> io::Result<Self> {
+        let entries = read_index(&file)?;
+        Ok(Self(ArchiveDirView::from_entries(file, entries)))
+    }
+
+    /// Returns the inner [`ArchiveDirView`].
+    #[inline]
+    pub fn into_inner(self) -> ArchiveDirView {
+        self.0
+    }
+}
+
+impl Deref for ZipDirView {
+    type Target = ArchiveDirView;
+
+    #[inline]
+    fn deref(&self) -> &ArchiveDirView {
+        &self.0
+    }
+}
+
+impl From<ZipDirView> for ArchiveDirView {
+    #[inline]
+    fn from(view: ZipDirView) -> Self {
+        view.0
+    }
+}
+
+impl<D: Borrow<Dir>> DirView<D> {
+    /// Opens the zip archive at `path` as a [`ZipDirView`], a readonly view
+    /// of its contents.
+    ///
+    /// As with [`Self::open_tar`], the archive is read through this view,
+    /// but entries within it aren't subject to this view's policy.
+    pub fn open_zip<P: AsRef<Path>>(&self, path: P) -> io::Result<ZipDirView> {
+        ZipDirView::from_file(self.open(path)?)
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+impl<D: Borrow<cap_std::fs_utf8::Dir>> DirViewUtf8<D> {
+    /// Opens the zip archive at `path` as a [`ZipDirView`], a readonly view
+    /// of its contents.
+    ///
+    /// See [`DirView::open_zip`].
+    pub fn open_zip<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<ZipDirView> {
+        ZipDirView::from_file(File::from_std(self.open(path)?.into_std()))
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+/// Reads the central directory of the zip archive in `file`, returning its
+/// entries by path.
+fn read_index(file: &File) -> io::Result<BTreeMap<PathBuf, Entry>> {
+    let file_len = file.metadata()?.len();
+
+    // The end of central directory record is at the end of the archive,
+    // followed only by a comment of up to 65535 bytes.
+    let tail_len = file_len.min(22 + 0xffff);
+    let tail_start = file_len - tail_len;
+    let mut tail = vec![0; tail_len as usize];
+    read_exact_at(file, &mut tail, tail_start)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|at| u32_at(&tail, *at) == 0x0605_4b50)
+        .ok_or_else(|| malformed("not a zip archive"))?;
+    let mut count = u64::from(u16_at(&tail, end + 10));
+    let mut cd_len = u64::from(u32_at(&tail, end + 12));
+    let mut cd_offset = u64::from(u32_at(&tail, end + 16));
+
+    // zip64 archives record the real values in a zip64 end of central
+    // directory record, found through a locator just before the end of
+    // central directory record.
+    let end = tail_start + end as u64;
+    if (count == 0xffff || cd_len == 0xffff_ffff || cd_offset == 0xffff_ffff) && end >= 20 {
+        let mut locator = [0; 20];
+        read_exact_at(file, &mut locator, end - 20)?;
+        if u32_at(&locator, 0) == 0x0706_4b50 {
+            let mut record = [0; 56];
+            read_exact_at(file, &mut record, u64_at(&locator, 8))?;
+            if u32_at(&record, 0) != 0x0606_4b50 {
+                return Err(malformed("invalid zip64 end of central directory"));
+            }
+            count = u64_at(&record, 32);
+            cd_len = u64_at(&record, 40);
+            cd_offset = u64_at(&record, 48);
+        }
+    }
+    if cd_offset
+        .checked_add(cd_len)
+        .is_none_or(|cd_end| cd_end > file_len)
+    {
+        return Err(truncated());
+    }
+    let mut cd = vec![0; cd_len as usize];
+    read_exact_at(file, &mut cd, cd_offset)?;
+
+    let mut entries = BTreeMap::new();
+    let mut rest = &cd[..];
+    for _ in 0..count {
+        if rest.len() < 46 || u32_at(rest, 0) != 0x0201_4b50 {
+            return Err(malformed("invalid zip central directory"));
+        }
+        let made_by_unix = rest[5] == 3;
+        let flags = u16_at(rest, 8);
+        let method = u16_at(rest, 10);
+        let crc = u32_at(rest, 16);
+        let mut compressed = u64::from(u32_at(rest, 20));
+        let mut len = u64::from(u32_at(rest, 24));
+        let name_len = usize::from(u16_at(rest, 28));
+        let extra_len = usize::from(u16_at(rest, 30));
+        let comment_len = usize::from(u16_at(rest, 32));
+        let attributes = u32_at(rest, 38);
+        let mut offset = u64::from(u32_at(rest, 42));
+        let mut mtime = dos_time(u16_at(rest, 12), u16_at(rest, 14));
+        let header_len = 46 + name_len + extra_len + comment_len;
+        if rest.len() < header_len {
+            return Err(malformed("invalid zip central directory"));
+        }
+        let name = &rest[46..46 + name_len];
+        let mut extra = &rest[46 + name_len..46 + name_len + extra_len];
+        rest = &rest[header_len..];
+
+        while extra.len() >= 4 {
+            let id = u16_at(extra, 0);
+            let size = usize::from(u16_at(extra, 2)).min(extra.len() - 4);
+            let mut field = &extra[4..4 + size];
+            extra = &extra[4 + size..];
+            match id {
+                // zip64 extended information, with the values which didn't
+                // fit in the header, in order.
+                0x0001 => {
+                    for value in [&mut len, &mut compressed, &mut offset] {
+                        if *value == 0xffff_ffff && field.len() >= 8 {
+                            *value = u64_at(field, 0);
+                            field = &field[8..];
+                        }
+                    }
+                }
+                // The extended timestamp, with the modification time first.
+                0x5455 if field.len() >= 5 && field[0] & 1 != 0 => {
+                    if let Ok(time) = u64::try_from(u32_at(field, 1) as i32) {
+                        mtime = time;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(path) = normalize(name) else {
+            continue;
+        };
+        let mut local = [0; 30];
+        read_exact_at(file, &mut local, offset)?;
+        if u32_at(&local, 0) != 0x0403_4b50 {
+            return Err(malformed("invalid zip local header"));
+        }
+        let data = offset + 30 + u64::from(u16_at(&local, 26)) + u64::from(u16_at(&local, 28));
+        if data
+            .checked_add(compressed)
+            .is_none_or(|data_end| data_end > file_len)
+        {
+            return Err(truncated());
+        }
+
+        let mode = if made_by_unix { attributes >> 16 } else { 0 };
+        let kind = if name.ends_with(b"/") || mode & 0o170000 == 0o040000 {
+            FileTypeSummary::Dir
+        } else if mode & 0o170000 == 0o120000 {
+            FileTypeSummary::Symlink
+        } else {
+            FileTypeSummary::File
+        };
+        let compression = match method {
+            _ if flags & 1 != 0 => Compression::Unsupported,
+            0 if compressed != len => return Err(malformed("invalid zip stored entry size")),
+            0 => Compression::Stored,
+            8 => Compression::Deflated { compressed, crc },
+            _ => Compression::Unsupported,
+        };
+        let mut entry = Entry {
+            kind,
+            offset: data,
+            len,
+            mode: match mode & 0o7777 {
+                0 if kind == FileTypeSummary::Dir => 0o755,
+                0 => 0o644,
+                mode => mode,
+            },
+            mtime,
+            link: PathBuf::new(),
+            compression,
+        };
+        if kind == FileTypeSummary::Symlink {
+            if len > MAX_LINK {
+                return Err(malformed("zip symbolic link target is too long"));
+            }
+            let target = match compression {
+                Compression::Stored => {
+                    let mut target = vec![0; len as usize];
+                    read_exact_at(file, &mut target, data)?;
+                    target
+                }
+                Compression::Deflated { compressed, crc } => {
+                    // Deflate can't expand data by more than a few bytes
+                    // per block.
+                    if compressed > 2 * MAX_LINK {
+                        return Err(malformed("zip symbolic link target is too long"));
+                    }
+                    let mut input = vec![0; compressed as usize];
+                    read_exact_at(file, &mut input, data)?;
+                    let mut target = Vec::new();
+                    InflateEntry::new(&input[..], len, crc).read_to_end(&mut target)?;
+                    target
+                }
+                Compression::Unsupported => continue,
+            };
+            entry.link = os_path(&target);
+        }
+        insert_entry(&mut entries, path, entry);
+    }
+    Ok(entries)
+}
+
+/// Converts an MS-DOS time and date, taken to be in UTC, to seconds since
+/// the Unix epoch.
+fn dos_time(time: u16, date: u16) -> u64 {
+    let days = days_from_civil(
+        i64::from(date >> 9) + 1980,
+        u32::from(date >> 5 & 0xf).max(1),
+        u32::from(date & 0x1f).max(1),
+    );
+    let secs = u64::from(time >> 11) * 3600
+        + u64::from(time >> 5 & 0x3f) * 60
+        + u64::from(time & 0x1f) * 2;
+    days as u64 * 86400 + secs
+}
+
+/// A reader of the contents of a deflated entry, decompressed from `R` as
+/// they're read, which checks once they end that they have the recorded
+/// length and CRC-32.
+pub(crate) struct InflateEntry<R> {
+    inflate: Inflate<BufReader<R>>,
+    len: u64,
+    crc: u32,
+    checksum: Crc32,
+
+    /// The number of bytes read so far.
+    pos: u64,
+
+    /// Whether the end of the contents has been checked.
+    checked: bool,
+}
+
+impl<R: Read> InflateEntry<R> {
+    pub(crate) fn new(input: R, len: u64, crc: u32) -> Self {
+        Self {
+            inflate: Inflate::new(BufReader::new(input)),
+            len,
+            crc,
+            checksum: Crc32::new(),
+            pos: 0,
+            checked: false,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    #[inline]
+    pub(crate) fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Checks that the deflated data ends with the recorded length, and
+    /// that the contents have the recorded checksum.
+    fn check_end(&mut self) -> io::Result<()> {
+        if mem::replace(&mut self.checked, true) {
+            return Ok(());
+        }
+        if self.inflate.read(&mut [0])? != 0 {
+            return Err(malformed("archive entry is larger than its recorded size"));
+        }
+        if mem::replace(&mut self.checksum, Crc32::new()).finish() != self.crc {
+            return Err(malformed("archive entry checksum mismatch"));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for InflateEntry<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len - self.pos;
+        if remaining == 0 {
+            self.check_end()?;
+            return Ok(0);
+        }
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let read = self.inflate.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(malformed("archive entry is smaller than its recorded size"));
+        }
+        self.checksum.update(&buf[..read]);
+        self.pos += read as u64;
+        if self.pos == self.len {
+            self.check_end()?;
+        }
+        Ok(read)
+    }
+}
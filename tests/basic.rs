@@ -187,4 +187,5881 @@ fn cap_fs_ext() {
         readonly.symlink_dir(".", "foo").unwrap_err().kind(),
         std::io::ErrorKind::PermissionDenied
     );
+    assert_eq!(
+        readonly.symlink_file(".", "foo").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/file", b"").unwrap();
+    assert_eq!(
+        readonly
+            .remove_file_or_symlink("sub/file")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    let perm = temp_dir.metadata("sub/file").unwrap().permissions();
+    assert_eq!(
+        readonly
+            .set_symlink_permissions("sub/file", perm)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(temp_dir.exists("sub/file"));
+
+    // Directories opened without following symlinks inherit the view kind.
+    let sub = readonly.open_dir_nofollow("sub").unwrap();
+    assert_eq!(
+        sub.remove_file_or_symlink("file").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    let full = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let sub = full.open_dir_nofollow("sub").unwrap();
+    sub.remove_file_or_symlink("file").unwrap();
+    assert!(!temp_dir.exists("sub/file"));
+
+    #[cfg(not(windows))]
+    {
+        full.symlink("sub", "link").unwrap();
+        assert!(full.open_dir_nofollow("link").is_err());
+        full.remove_file_or_symlink("link").unwrap();
+    }
+}
+
+#[cfg(feature = "fs_utf8")]
+#[test]
+fn utf8_conversions() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    // Converting between the view types preserves the view kind.
+    let dir = temp_dir.open_dir(".").unwrap();
+    let readonly = DirView::readonly(dir).into_utf8();
+    assert_eq!(
+        readonly.create("hello").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    let readonly = readonly.into_cap_std_view();
+    assert_eq!(
+        readonly.create("hello").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let dir = temp_dir.open_dir(".").unwrap();
+    let full = DirView::full(dir).into_utf8();
+    full.create("hello").unwrap();
+    assert!(full.into_cap_std_view().exists("hello"));
+}
+
+#[test]
+fn builder() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b/c").unwrap();
+    temp_dir.write(".hidden", b"").unwrap();
+    temp_dir.write("visible", b"").unwrap();
+
+    let dir = temp_dir.open_dir(".").unwrap();
+    let view = DirViewBuilder::new()
+        .deny_symlinks()
+        .hide_hidden_files()
+        .max_depth(2)
+        .build(dir);
+
+    // The view kind defaults to `Full`.
+    view.write("file", b"").unwrap();
+
+    #[cfg(not(windows))]
+    assert_eq!(
+        view.symlink("file", "symlink").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let names = view
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert!(names.iter().any(|name| name == "visible"));
+    assert!(!names.iter().any(|name| name == ".hidden"));
+
+    // Depth is tracked across views opened from the built view.
+    let a = view.open_dir("a").unwrap();
+    a.open_dir("b").unwrap();
+    assert_eq!(
+        a.open_dir("b/c").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        view.read_dir("a/b/c").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    // Symbolic links can't lead past the maximum depth either.
+    #[cfg(unix)]
+    {
+        temp_dir.symlink("a/b/c", "deep").unwrap();
+        temp_dir.symlink("a", "shallow").unwrap();
+        assert_eq!(
+            view.open_dir("deep").unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        let shallow = view.open_dir("shallow").unwrap();
+        shallow.open_dir("b").unwrap();
+        assert!(shallow.open_dir("b/c").is_err());
+        let entry = view
+            .entries()
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|entry| entry.file_name() == "deep")
+            .unwrap();
+        assert!(entry.open_dir().is_err());
+    }
+
+    let dir = temp_dir.open_dir(".").unwrap();
+    let readonly = DirViewBuilder::new().readonly().build(dir);
+    assert_eq!(
+        readonly.create("hello").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn open_dir_view() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("vendor/crate").unwrap();
+
+    // A full view can hand out a readonly view of a subdirectory.
+    let dir = temp_dir.open_dir(".").unwrap();
+    let full = DirView::from_dir(dir, ViewKind::Full);
+    let vendor = full.open_dir_view("vendor", ViewKind::Readonly).unwrap();
+    assert_eq!(
+        vendor.create("hello").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    // A readonly view can't be widened.
+    assert_eq!(
+        vendor
+            .open_dir_view("crate", ViewKind::Full)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    vendor.open_dir_view("crate", ViewKind::Readonly).unwrap();
+
+    for entry in vendor.entries().unwrap() {
+        let entry = entry.unwrap();
+        assert_eq!(
+            entry.open_dir_view(ViewKind::Full).unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+    }
+}
+
+#[test]
+fn try_clone_with() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(temp_dir.try_clone().unwrap());
+
+    let readonly = view.try_clone_with(ViewKind::Readonly).unwrap();
+    assert!(readonly.create_dir("denied").is_err());
+    view.create_dir("allowed").unwrap();
+    assert!(readonly.is_dir("allowed"));
+
+    let dry_run = view.try_clone_with(ViewKind::DryRun).unwrap();
+    dry_run.create_dir("simulated").unwrap();
+    assert!(!view.exists("simulated"));
+
+    let error = readonly.try_clone_with(ViewKind::Full).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(readonly.try_clone_with(ViewKind::DryRun).is_err());
+    assert!(dry_run.try_clone_with(ViewKind::Full).is_err());
+    assert!(readonly.try_clone_with(ViewKind::Readonly).is_ok());
+}
+
+#[test]
+fn as_dir() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    let full = DirView::full(temp_dir.open_dir(".").unwrap());
+    full.as_dir().unwrap().create("hello").unwrap();
+
+    let readonly = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    assert!(readonly.as_dir().is_none());
+
+    let restricted = DirViewBuilder::new()
+        .deny_symlinks()
+        .build(temp_dir.open_dir(".").unwrap());
+    assert!(restricted.as_dir().is_none());
+
+    // Handles are only exposed where `as_dir` would expose the `Dir`.
+    #[cfg(not(windows))]
+    {
+        assert!(full.as_fd().is_some());
+        assert!(readonly.as_fd().is_none());
+        assert!(restricted.as_fd().is_none());
+        let readonly = readonly.into_fd().unwrap_err();
+        assert!(readonly.exists("hello"));
+        let fd = full.into_fd().unwrap();
+        let dir = cap_std::fs::Dir::from(fd);
+        assert!(dir.exists("hello"));
+    }
+}
+
+#[test]
+fn shared() {
+    use dir_view::SharedDirView;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedDirView>();
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("hello", b"world").unwrap();
+
+    let shared = SharedDirView::new(DirView::readonly(temp_dir.open_dir(".").unwrap()));
+    let threads = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                assert_eq!(shared.read("hello").unwrap(), b"world");
+                assert_eq!(
+                    shared.create("hello").unwrap_err().kind(),
+                    std::io::ErrorKind::PermissionDenied
+                );
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // Once all the clones are gone, the view can be recovered.
+    shared.try_unwrap().unwrap();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn view_spec() {
+    use dir_view::ViewSpec;
+
+    #[derive(serde::Deserialize)]
+    struct Config {
+        views: Vec<ViewSpec>,
+    }
+
+    let config: Config = toml::from_str(
+        r#"
+        [[views]]
+        path = "."
+        kind = "readonly"
+
+        [[views]]
+        path = "."
+        kind = "full"
+        options = { deny_symlinks = true, max_depth = 1 }
+        "#,
+    )
+    .unwrap();
+
+    let views = dir_view::open_views(&config.views, ambient_authority()).unwrap();
+    assert_eq!(
+        views[0].create("hello").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(views[1].as_dir().is_none());
+}
+
+#[cfg(all(feature = "json", feature = "toml"))]
+#[test]
+fn read_json_toml() {
+    use std::io;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Config {
+        name: String,
+        retries: u32,
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("conf").unwrap();
+    temp_dir
+        .write("conf/app.json", r#"{ "name": "app", "retries": 3 }"#)
+        .unwrap();
+    temp_dir
+        .write("conf/app.toml", "name = \"app\"\nretries = 3\n")
+        .unwrap();
+    temp_dir.write("conf/bad.json", r#"{ "name": 3 }"#).unwrap();
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+
+    let expected = Config {
+        name: "app".to_owned(),
+        retries: 3,
+    };
+    assert_eq!(
+        view.read_json::<Config, _>("conf/app.json").unwrap(),
+        expected
+    );
+    assert_eq!(
+        view.read_toml::<Config, _>("conf/app.toml").unwrap(),
+        expected
+    );
+
+    let error = view.read_json::<Config, _>("conf/bad.json").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("conf/bad.json"));
+    let error = view.read_toml::<Config, _>("conf/app.json").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    let error = view
+        .read_json_limit::<Config, _>("conf/app.json", 8)
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::FileTooLarge);
+}
+
+#[cfg(all(feature = "json", feature = "toml"))]
+#[test]
+fn write_json_toml() {
+    use std::collections::BTreeMap;
+    use std::io;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+
+    let mut config = BTreeMap::new();
+    config.insert("name".to_owned(), "app".to_owned());
+    view.write_json("app.json", &config).unwrap();
+    view.write_toml("app.toml", &config).unwrap();
+    assert_eq!(
+        view.read_to_string("app.json").unwrap(),
+        "{\n  \"name\": \"app\"\n}\n"
+    );
+    assert_eq!(view.read_to_string("app.toml").unwrap(), "name = \"app\"\n");
+    let loaded: BTreeMap<String, String> = view.read_toml("app.toml").unwrap();
+    assert_eq!(loaded, config);
+
+    // Values TOML can't represent fail before anything is written.
+    let error = view.write_toml("list.toml", &[1, 2, 3]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(!view.exists("list.toml"));
+
+    let readonly = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    let error = readonly.write_json("app.json", &config).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(
+        view.read_json::<BTreeMap<String, String>, _>("app.json")
+            .unwrap(),
+        config
+    );
+    assert_eq!(view.read_dir(".").unwrap().count(), 2);
+}
+
+#[cfg(all(unix, feature = "fd-passing"))]
+#[test]
+fn fd_passing() {
+    use dir_view::DirViewBuilder;
+    use std::os::unix::net::UnixStream;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("hello", b"world").unwrap();
+
+    let view = DirViewBuilder::new()
+        .readonly()
+        .max_depth(1)
+        .deny_unix_connect()
+        .build(temp_dir.open_dir(".").unwrap());
+
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    view.send_to_unix_socket(&sender).unwrap();
+    let received = DirView::recv_from_unix_socket(&receiver).unwrap();
+
+    // The view kind and restrictions are preserved.
+    assert_eq!(received.read("hello").unwrap(), b"world");
+    assert_eq!(
+        received.create("hello").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    received.open_dir("a").unwrap();
+    assert_eq!(
+        received.open_dir("a/b").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        received.connect_unix_stream("sock").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let dry_run = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::DryRun);
+    dry_run.send_to_unix_socket(&sender).unwrap();
+    let received = DirView::recv_from_unix_socket(&receiver).unwrap();
+    received.remove_file("hello").unwrap();
+    assert!(temp_dir.exists("hello"));
+}
+
+#[cfg(feature = "tempfile")]
+#[test]
+fn tempfile() {
+    use dir_view::TempDirView;
+    use std::io::{Read, Seek, Write};
+
+    let temp_dir = TempDirView::new(ViewKind::Full, ambient_authority()).unwrap();
+    let full = temp_dir.view().unwrap();
+    full.create_dir("sub").unwrap();
+
+    let mut file = full.tempfile_in("sub").unwrap();
+    file.write_all(b"hello").unwrap();
+    file.rewind().unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello");
+    assert_eq!(full.read_dir("sub").unwrap().count(), 0);
+
+    let nested = full.tempdir_in("sub").unwrap();
+    nested.view().unwrap().write("file", b"").unwrap();
+    assert_eq!(full.read_dir("sub").unwrap().count(), 1);
+    nested.close().unwrap();
+    assert_eq!(full.read_dir("sub").unwrap().count(), 0);
+
+    // Readonly views can't create temporary files or directories.
+    let readonly = DirView::readonly(full.as_dir().unwrap().try_clone().unwrap());
+    assert_eq!(
+        readonly.tempfile_in("sub").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly.tempdir_in("sub").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[cfg(all(feature = "tempfile", feature = "fs_utf8"))]
+#[test]
+fn tempfile_utf8() {
+    use camino::Utf8Path;
+    use dir_view::TempDirView;
+
+    let temp_dir = TempDirView::new(ViewKind::Full, ambient_authority()).unwrap();
+    let full = temp_dir.view_utf8().unwrap();
+    full.create_dir("sub").unwrap();
+
+    full.tempfile_in(Utf8Path::new("sub")).unwrap();
+    let nested = full.tempdir_in(Utf8Path::new("sub")).unwrap();
+    nested.view_utf8().unwrap().write("file", b"").unwrap();
+    assert_eq!(full.read_dir("sub").unwrap().count(), 1);
+    nested.close().unwrap();
+
+    temp_dir.close().unwrap();
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn reopen_o_path() {
+    use rustix::fs::{openat, Mode, OFlags};
+    use std::os::fd::AsFd;
+
+    let tmpdir = TempDir::new(ambient_authority()).unwrap();
+    tmpdir.create_dir("dir").unwrap();
+    tmpdir.write("dir/file", b"hello").unwrap();
+
+    let path_fd = openat(
+        &*tmpdir,
+        "dir",
+        OFlags::PATH | OFlags::DIRECTORY | OFlags::CLOEXEC,
+        Mode::empty(),
+    )
+    .unwrap();
+
+    let view = DirView::reopen_dir_fd(path_fd.as_fd(), ViewKind::Readonly).unwrap();
+    assert_eq!(view.read_to_string("file").unwrap(), "hello");
+    assert_eq!(view.read_dir(".").unwrap().count(), 1);
+    assert!(view.write("new", b"").is_err());
+
+    let view = DirView::reopen_dir(&path_fd, ViewKind::Full).unwrap();
+    view.write("new", b"").unwrap();
+    assert!(tmpdir.exists("dir/new"));
+}
+
+#[test]
+fn readonly_ambient() {
+    let view = DirView::open_ambient_dir_readonly(".", ambient_authority()).unwrap();
+    assert!(view.exists("Cargo.toml"));
+    assert!(view.write("should-not-exist", b"").is_err());
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("a").unwrap();
+    let full = DirView::full(temp_dir.open_dir("a").unwrap());
+    let parent = full.open_parent_dir_readonly(ambient_authority()).unwrap();
+    assert!(parent.exists("a"));
+    assert_eq!(
+        parent.create_dir("b").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn borrowed_view() {
+    use dir_view::DirViewRef;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("a").unwrap();
+    temp_dir.write("a/file", b"hello").unwrap();
+
+    // Lend a readonly view of a `Dir` owned elsewhere.
+    let readonly: DirViewRef<'_> = DirView::readonly(&*temp_dir);
+    assert_eq!(readonly.read_to_string("a/file").unwrap(), "hello");
+    assert_eq!(
+        readonly.write("a/new", b"").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    // Opening a subdirectory yields an owned view with the same restrictions.
+    let a: DirView = readonly.open_dir("a").unwrap();
+    assert!(a.create_dir("b").is_err());
+
+    // Owned and borrowed views can be mixed in two-directory operations.
+    let full = DirView::full(&*temp_dir);
+    full.rename(
+        "a/file",
+        &DirView::full(temp_dir.open_dir("a").unwrap()),
+        "moved",
+    )
+    .unwrap();
+    assert!(temp_dir.exists("a/moved"));
+    assert!(readonly.copy("a/moved", &full, "copied").is_ok());
+    assert!(full.copy("a/moved", &readonly, "copied").is_err());
+}
+
+#[test]
+fn custom_backend() {
+    use cap_std::fs::Dir;
+    use std::borrow::Borrow;
+    use std::sync::Arc;
+
+    // A backend which wraps a `Dir` in a user-defined type.
+    struct Wrapper {
+        dir: Dir,
+    }
+
+    impl Borrow<Dir> for Wrapper {
+        fn borrow(&self) -> &Dir {
+            &self.dir
+        }
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"hello").unwrap();
+
+    let wrapped = DirView::readonly(Wrapper {
+        dir: temp_dir.open_dir(".").unwrap(),
+    });
+    assert_eq!(wrapped.read_to_string("file").unwrap(), "hello");
+    assert!(wrapped.write("file", b"").is_err());
+    assert!(wrapped.as_dir().is_none());
+
+    // Views over cloneable backends are cheap to copy.
+    let shared = DirView::<Arc<Dir>>::readonly(Arc::new(temp_dir.open_dir(".").unwrap()));
+    let copy = shared.clone();
+    assert_eq!(copy.read("file").unwrap(), b"hello");
+    assert!(copy.remove_file("file").is_err());
+}
+
+#[test]
+fn dir_like() {
+    use dir_view::{DirLike, TarOptions, ZipOptions};
+
+    fn count_files<D: DirLike>(dir: &D) -> usize
+    where
+        str: AsRef<D::Path>,
+    {
+        assert!(dir.is_dir("sub"));
+        assert!(!dir.exists("missing"));
+        assert_eq!(dir.read_to_string("sub/file").unwrap(), "hello");
+        let sub = dir.open_dir("sub").unwrap();
+        sub.entries().unwrap().count()
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/file", b"hello").unwrap();
+    temp_dir.write("sub/other", b"").unwrap();
+
+    assert_eq!(count_files(&*temp_dir), 2);
+    assert_eq!(count_files(&DirView::readonly(&*temp_dir)), 2);
+    #[cfg(feature = "fs_utf8")]
+    {
+        let utf8 = DirViewUtf8::readonly(cap_std::fs_utf8::Dir::from_cap_std(
+            temp_dir.open_dir(".").unwrap(),
+        ));
+        assert_eq!(count_files(&utf8), 2);
+    }
+
+    let view = DirView::full(&*temp_dir);
+    let (mut tar, mut zip) = (Vec::new(), Vec::new());
+    view.write_tar(".", &mut tar, &TarOptions::new()).unwrap();
+    view.write_zip(".", &mut zip, &ZipOptions::new()).unwrap();
+    view.write("tree.tar", tar).unwrap();
+    view.write("tree.zip", zip).unwrap();
+    assert_eq!(count_files(&view.open_tar("tree.tar").unwrap()), 2);
+    assert_eq!(count_files(&view.open_zip("tree.zip").unwrap()), 2);
+    #[cfg(feature = "testing")]
+    {
+        let mem = dir_view::MemDirView::new();
+        mem.create_dir("sub").unwrap();
+        mem.write("sub/file", "hello").unwrap();
+        mem.write("sub/other", "").unwrap();
+        assert_eq!(count_files(&mem), 2);
+    }
+}
+
+#[cfg(feature = "cap-fs-ext")]
+#[test]
+fn access() {
+    use dir_view::cap_fs_ext::{AccessType, DirExt};
+    use dir_view::AccessModes;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"").unwrap();
+
+    let read = AccessType::Access(AccessModes {
+        readable: true,
+        writable: false,
+        executable: false,
+    });
+    let write = AccessType::Access(AccessModes {
+        readable: false,
+        writable: true,
+        executable: false,
+    });
+
+    let full = DirView::full(&*temp_dir);
+    full.access("file", read).unwrap();
+    full.access("file", write).unwrap();
+
+    // A readonly view reports write access as denied, even though the file
+    // itself is writable.
+    let readonly = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    readonly.access("file", read).unwrap();
+    readonly.access("file", AccessType::Exists).unwrap();
+    assert_eq!(
+        readonly.access("file", write).unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        DirExt::access_symlink(&readonly, "file", write)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly.access("missing", read).unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn symlink_contents() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("dst").unwrap();
+
+    let full = DirView::full(&*temp_dir);
+    full.symlink_contents("/etc/passwd", "absolute").unwrap();
+    assert!(full.read_link("absolute").is_err());
+    assert_eq!(
+        full.read_link_contents("absolute").unwrap(),
+        std::path::Path::new("/etc/passwd")
+    );
+
+    // Copy the symlink verbatim into another view.
+    let dst = full.open_dir("dst").unwrap();
+    let contents = full.read_link_contents("absolute").unwrap();
+    dst.symlink_contents(&contents, "absolute").unwrap();
+    assert_eq!(dst.read_link_contents("absolute").unwrap(), contents);
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(readonly.read_link_contents("absolute").unwrap(), contents);
+    assert_eq!(
+        readonly
+            .symlink_contents("/etc/passwd", "other")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn is_symlink_and_is_dir_empty() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("empty").unwrap();
+    temp_dir.create_dir("hidden").unwrap();
+    temp_dir.write("hidden/.dot", b"").unwrap();
+    temp_dir.write("file", b"").unwrap();
+
+    let view = dir_view::DirViewBuilder::new()
+        .hide_hidden_files()
+        .build(&*temp_dir);
+    assert!(!view.is_symlink("file"));
+    assert!(!view.is_symlink("missing"));
+    assert!(view.is_dir_empty("empty").unwrap());
+    // Hidden entries still make a directory non-empty.
+    assert!(!view.is_dir_empty("hidden").unwrap());
+    assert!(view.is_dir_empty("file").is_err());
+    assert_eq!(
+        view.is_dir_empty("missing").unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+
+    #[cfg(not(windows))]
+    {
+        view.symlink("missing", "broken").unwrap();
+        view.symlink("empty", "link").unwrap();
+        assert!(view.is_symlink("broken"));
+        assert!(!view.is_file("broken"));
+        assert!(view.is_symlink("link"));
+        assert!(view.is_dir_empty("link").unwrap());
+    }
+}
+
+#[test]
+fn open_dir_nofollow() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+
+    let view = dir_view::DirViewBuilder::new()
+        .readonly()
+        .max_depth(1)
+        .build(&*temp_dir);
+    let a = view.open_dir_nofollow("a").unwrap();
+    assert_eq!(
+        a.create_dir("c").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        a.open_dir_nofollow("b").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    #[cfg(not(windows))]
+    {
+        temp_dir.symlink("a", "link").unwrap();
+        assert!(view.open_dir("link").is_ok());
+        assert!(view.open_dir_nofollow("link").is_err());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn metadata_accessors() {
+    use cap_std::fs::MetadataExt;
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"").unwrap();
+    temp_dir
+        .set_permissions(
+            "file",
+            cap_std::fs::Permissions::from_std(std::fs::Permissions::from_mode(0o640)),
+        )
+        .unwrap();
+    temp_dir.hard_link("file", &temp_dir, "link").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    assert_eq!(view.mode("file").unwrap() & 0o777, 0o640);
+    assert_eq!(view.nlink("file").unwrap(), 2);
+    let metadata = temp_dir.metadata("file").unwrap();
+    assert_eq!(
+        view.owner("file").unwrap(),
+        (metadata.uid(), metadata.gid())
+    );
+    assert_eq!(
+        view.mode("missing").unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn set_owner() {
+    use cap_std::fs::MetadataExt;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/file", b"").unwrap();
+    temp_dir.symlink("missing", "sub/broken").unwrap();
+    let metadata = temp_dir.metadata("sub/file").unwrap();
+    let (uid, gid) = (metadata.uid(), metadata.gid());
+
+    // Setting the owner to the current owner is always permitted.
+    let full = DirView::full(&*temp_dir);
+    full.set_owner("sub/file", Some(uid), Some(gid)).unwrap();
+    full.set_owner("sub", None, Some(gid)).unwrap();
+    full.set_owner_nofollow("sub/broken", Some(uid), None)
+        .unwrap();
+    assert!(full.set_owner("sub/broken", Some(uid), None).is_err());
+    assert!(full
+        .set_owner_nofollow("../escape", Some(uid), None)
+        .is_err());
+    assert_eq!(
+        full.set_owner_nofollow("sub/..", Some(uid), None)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly
+            .set_owner("sub/file", Some(uid), Some(gid))
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly
+            .set_owner_nofollow("sub/file", Some(uid), Some(gid))
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[cfg(all(
+    feature = "xattr",
+    any(target_os = "linux", target_os = "android", target_vendor = "apple")
+))]
+#[test]
+fn xattr() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"").unwrap();
+
+    let full = DirView::full(&*temp_dir);
+    match full.set_xattr("file", "user.dir-view", b"value") {
+        Ok(()) => {}
+        // Not all filesystems support user extended attributes.
+        Err(err) if err.raw_os_error() == Some(rustix::io::Errno::OPNOTSUPP.raw_os_error()) => {
+            return
+        }
+        Err(err) => panic!("{err}"),
+    }
+    assert_eq!(
+        full.get_xattr("file", "user.dir-view").unwrap().as_deref(),
+        Some(&b"value"[..])
+    );
+    assert_eq!(full.get_xattr("file", "user.missing").unwrap(), None);
+    assert!(full
+        .list_xattr("file")
+        .unwrap()
+        .iter()
+        .any(|name| name == "user.dir-view"));
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert!(readonly
+        .get_xattr("file", "user.dir-view")
+        .unwrap()
+        .is_some());
+    assert_eq!(
+        readonly
+            .set_xattr("file", "user.dir-view", b"")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly
+            .remove_xattr("file", "user.dir-view")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    full.remove_xattr("file", "user.dir-view").unwrap();
+    assert_eq!(full.get_xattr("file", "user.dir-view").unwrap(), None);
+}
+
+#[cfg(all(unix, not(target_vendor = "apple")))]
+#[test]
+fn mkfifo() {
+    use cap_std::fs::FileTypeExt;
+    use dir_view::{AuditEvent, Operation};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+
+    let full = DirView::full(&*temp_dir);
+    full.mkfifo("sub/fifo", 0o600).unwrap();
+    assert!(temp_dir
+        .symlink_metadata("sub/fifo")
+        .unwrap()
+        .file_type()
+        .is_fifo());
+    assert_eq!(
+        full.mkfifo("sub/fifo", 0o600).unwrap_err().kind(),
+        std::io::ErrorKind::AlreadyExists
+    );
+    assert!(full.mkfifo("../fifo", 0o600).is_err());
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.mkfifo("fifo", 0o600).unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(!temp_dir.exists("fifo"));
+
+    // The audit hook sees one event.
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let hook_events = std::sync::Arc::clone(&events);
+    let audited = DirView::with_audit(&*temp_dir, ViewKind::Full, move |event: &AuditEvent| {
+        hook_events.lock().unwrap().push(event.operation());
+    });
+    audited.mkfifo("fifo", 0o600).unwrap();
+    assert_eq!(*events.lock().unwrap(), [Operation::Mknod]);
+}
+
+#[cfg(unix)]
+#[test]
+fn anonymous_tmpfile() {
+    use std::io::{Read, Seek, Write};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("existing", "old").unwrap();
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly
+            .open_anonymous_tmpfile(cap_std::fs::OpenOptions::new().read(true))
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let full = DirView::full(&*temp_dir);
+    let mut file = full
+        .open_anonymous_tmpfile(cap_std::fs::OpenOptions::new().read(true))
+        .unwrap();
+    file.write_all(b"hello").unwrap();
+    file.rewind().unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello");
+
+    // Anonymous files don't show up in the directory.
+    assert_eq!(temp_dir.entries().unwrap().count(), 2);
+
+    if cfg!(target_os = "linux") {
+        full.link_tmpfile_at(&file, "sub/linked").unwrap();
+        assert_eq!(temp_dir.read_to_string("sub/linked").unwrap(), "hello");
+        assert!(full.link_tmpfile_at(&file, "existing").is_err());
+        assert_eq!(temp_dir.read_to_string("existing").unwrap(), "old");
+        assert!(full.link_tmpfile_at(&file, "../escaped").is_err());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_sockets() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    // Binding creates a filesystem node, so readonly views can't do it.
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.bind_unix_listener("sock").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly.bind_unix_datagram("sock").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let view = dir_view::DirViewBuilder::new()
+        .deny_unix_connect()
+        .build(&*temp_dir);
+    assert!(view.as_dir().is_none());
+    assert_eq!(
+        view.connect_unix_stream("sock").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    let datagram = cap_std::os::unix::net::UnixDatagram::unbound().unwrap();
+    assert_eq!(
+        view.connect_unix_datagram(&datagram, "sock")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        view.send_to_unix_datagram_addr(&datagram, b"", "sock")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn open_append() {
+    use std::io::Write;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    let full = DirView::full(&*temp_dir);
+    full.open_append("log")
+        .unwrap()
+        .write_all(b"one\n")
+        .unwrap();
+    full.open_append("log")
+        .unwrap()
+        .write_all(b"two\n")
+        .unwrap();
+    assert_eq!(temp_dir.read_to_string("log").unwrap(), "one\ntwo\n");
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.open_append("log").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly.open_append("other").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(!temp_dir.exists("other"));
+}
+
+#[test]
+fn create_new() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    let full = DirView::full(&*temp_dir);
+    full.create_new("lock").unwrap();
+    assert_eq!(
+        full.create_new("lock").unwrap_err().kind(),
+        std::io::ErrorKind::AlreadyExists
+    );
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.create_new("other").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(!temp_dir.exists("other"));
+}
+
+#[cfg(unix)]
+#[test]
+fn readonly_custom_flags() {
+    use cap_std::fs::{OpenOptions, OpenOptionsExt};
+    use rustix::fs::OFlags;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", "contents").unwrap();
+
+    let flags = (OFlags::CREATE | OFlags::TRUNC | OFlags::APPEND | OFlags::RDWR).bits() as i32;
+    let mut options = OpenOptions::new();
+    options.read(true).custom_flags(flags);
+
+    let readonly = DirView::readonly(&*temp_dir);
+    readonly.open_with("file", &options).unwrap();
+    assert_eq!(temp_dir.read_to_string("file").unwrap(), "contents");
+    assert!(readonly.open_with("other", &options).is_err());
+    assert!(!temp_dir.exists("other"));
+
+    let full = DirView::full(&*temp_dir);
+    full.open_with("other", &options).unwrap();
+    assert!(temp_dir.exists("other"));
+}
+
+#[cfg(feature = "fs_utf8")]
+#[test]
+fn open_with_utf8() {
+    use cap_std::fs::OpenOptions;
+    use std::io::Write;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+
+    let full = DirViewUtf8::full(&dir);
+    full.open_with("file", OpenOptions::new().write(true).create(true))
+        .unwrap()
+        .write_all(b"hello")
+        .unwrap();
+    assert_eq!(temp_dir.read_to_string("file").unwrap(), "hello");
+
+    let readonly = DirViewUtf8::readonly(&dir);
+    assert!(readonly
+        .open_with("other", OpenOptions::new().write(true).create(true))
+        .is_err());
+    assert!(!temp_dir.exists("other"));
+}
+
+#[cfg(unix)]
+#[test]
+fn statfs() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    let stats = DirView::readonly(&*temp_dir).statfs().unwrap();
+    assert!(stats.total_space() > 0);
+    assert!(stats.free_space() <= stats.total_space());
+    assert!(stats.available_space() <= stats.free_space());
+    assert!(!stats.is_read_only());
+}
+
+#[test]
+fn file_locks() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    let full = DirView::full(&*temp_dir);
+    let exclusive = full.lock_exclusive("lock").unwrap();
+    assert!(temp_dir.exists("lock"));
+    assert!(full.try_lock_exclusive("lock").unwrap().is_none());
+    assert!(full.try_lock_shared("lock").unwrap().is_none());
+    exclusive.unlock().unwrap();
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.lock_exclusive("lock").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly.try_lock_exclusive("lock").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    let shared = readonly.lock_shared("lock").unwrap();
+    let other = readonly.try_lock_shared("lock").unwrap().unwrap();
+    assert!(full.try_lock_exclusive("lock").unwrap().is_none());
+    drop(shared);
+    drop(other);
+    assert!(full.try_lock_exclusive("lock").unwrap().is_some());
+}
+
+#[cfg(all(feature = "landlock", target_os = "linux"))]
+#[test]
+fn landlock() {
+    use dir_view::LandlockRuleset;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("ro").unwrap();
+    temp_dir.create_dir("rw").unwrap();
+    temp_dir.write("ro/file", "contents").unwrap();
+
+    let readonly = DirView::full(&*temp_dir)
+        .open_dir_view("ro", ViewKind::Readonly)
+        .unwrap();
+    let full = DirView::full(&*temp_dir).open_dir("rw").unwrap();
+    let ro = temp_dir.open_dir("ro").unwrap();
+
+    // Landlock restricts the calling thread, so do it on a separate one.
+    std::thread::spawn(move || {
+        let mut ruleset = match LandlockRuleset::new() {
+            Ok(ruleset) => ruleset,
+            Err(err) if err.kind() == std::io::ErrorKind::Unsupported => return,
+            Err(err) => panic!("{}", err),
+        };
+        ruleset.add(&readonly).unwrap().add(&full).unwrap();
+        ruleset.restrict_self().unwrap();
+
+        // Other handles to the views' directories are now restricted by
+        // the kernel too.
+        assert_eq!(ro.read_to_string("file").unwrap(), "contents");
+        assert!(ro.write("other", "data").is_err());
+        full.write("file", "new").unwrap();
+        assert_eq!(full.read_to_string("file").unwrap(), "new");
+
+        // Nothing outside the views is accessible.
+        assert!(std::fs::read_dir("/").is_err());
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn open_readonly() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", "hello").unwrap();
+
+    let mut file = DirView::full(&*temp_dir).open_readonly("file").unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 5);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello");
+    file.seek(SeekFrom::Start(1)).unwrap();
+    contents.clear();
+    file.try_clone()
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "ello");
+
+    assert!(DirView::readonly(&*temp_dir)
+        .open_readonly("missing")
+        .is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn same_filesystem() {
+    use dir_view::DirViewBuilder;
+
+    let root = cap_std::fs::Dir::open_ambient_dir("/", ambient_authority()).unwrap();
+    let unrestricted = DirView::readonly(&root);
+    let confined = DirViewBuilder::new()
+        .readonly()
+        .same_filesystem()
+        .build(&root);
+
+    // `/proc` is normally its own mount.
+    if unrestricted.read_to_string("proc/self/status").is_ok() {
+        assert_eq!(
+            confined
+                .read_to_string("proc/self/status")
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        assert!(confined.open_dir("proc").is_err());
+    }
+    confined.entries().unwrap();
+}
+
+#[test]
+fn denial_diagnostics() {
+    use dir_view::DirViewBuilder;
+    use std::io::ErrorKind;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+
+    let readonly = DirView::readonly(&*temp_dir);
+    let err = readonly.create_dir("some/dir").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert!(!err.to_string().contains("some/dir"));
+
+    let view = DirViewBuilder::new()
+        .readonly()
+        .denial_error_kind(ErrorKind::ReadOnlyFilesystem)
+        .denial_context()
+        .build(&*temp_dir);
+    let err = view.create_dir("some/dir").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ReadOnlyFilesystem);
+    let message = err.to_string();
+    assert!(message.contains("create_dir"), "{message}");
+    assert!(message.contains("some/dir"), "{message}");
+
+    // Errors from the OS are unaffected.
+    assert_eq!(
+        view.open("missing").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+}
+
+#[test]
+fn view_error() {
+    use dir_view::{DirViewBuilder, Operation, ViewError};
+    use std::error::Error;
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirViewBuilder::new()
+        .readonly()
+        .denial_error_kind(ErrorKind::ReadOnlyFilesystem)
+        .build(&*temp_dir);
+
+    let err = ViewError::from(view.create_dir("some/dir").unwrap_err());
+    assert!(err.is_denied());
+    assert_eq!(err.kind(), ErrorKind::ReadOnlyFilesystem);
+    assert_eq!(err.operation(), Some(Operation::CreateDir));
+    assert_eq!(err.path(), Some(Path::new("some/dir")));
+    assert!(err.source().is_none());
+
+    // Converting back into an `io::Error` preserves the denial.
+    let err = std::io::Error::from(err);
+    assert_eq!(err.kind(), ErrorKind::ReadOnlyFilesystem);
+    assert!(ViewError::from(err).is_denied());
+
+    let err = ViewError::from(view.open("missing").unwrap_err());
+    assert!(!err.is_denied());
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.operation(), None);
+    assert_eq!(err.path(), None);
+}
+
+#[test]
+fn audit() {
+    use dir_view::{AuditEvent, Operation};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use ViewKind::{Full, Readonly};
+
+    type Log = Vec<(Operation, PathBuf, Option<PathBuf>, bool, ViewKind)>;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let log = Arc::new(Mutex::new(Log::new()));
+    let hook_log = Arc::clone(&log);
+    let view = DirView::with_audit(&*temp_dir, ViewKind::Full, move |event: &AuditEvent| {
+        hook_log.lock().unwrap().push((
+            event.operation(),
+            event.path().to_path_buf(),
+            event.target().map(PathBuf::from),
+            event.outcome().is_ok(),
+            event.view_kind(),
+        ));
+    });
+    assert!(view.as_dir().is_none());
+
+    view.create_dir("dir").unwrap();
+    view.write("dir/file", "hello").unwrap();
+    view.rename("dir/file", &view, "dir/renamed").unwrap();
+    view.open("missing").unwrap_err();
+    assert!(!view.exists("missing"));
+
+    // Derived views and entries are audited too.
+    let sub = view.open_dir_view("dir", ViewKind::Readonly).unwrap();
+    sub.remove_file("renamed").unwrap_err();
+    for entry in sub.entries().unwrap() {
+        entry.unwrap().metadata().unwrap();
+    }
+
+    let path = |path: &str| PathBuf::from(path);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            (Operation::CreateDir, path("dir"), None, true, Full),
+            (Operation::Write, path("dir/file"), None, true, Full),
+            (
+                Operation::Rename,
+                path("dir/file"),
+                Some(path("dir/renamed")),
+                true,
+                Full
+            ),
+            (Operation::Open, path("missing"), None, false, Full),
+            (Operation::Metadata, path("missing"), None, false, Full),
+            (Operation::OpenDir, path("dir"), None, true, Full),
+            (
+                Operation::RemoveFile,
+                path("renamed"),
+                None,
+                false,
+                Readonly
+            ),
+            (Operation::ReadDir, path("."), None, true, Readonly),
+            (Operation::Metadata, path("renamed"), None, true, Readonly),
+        ]
+    );
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_events() {
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    struct Levels(Arc<Mutex<Vec<Level>>>);
+
+    impl Subscriber for Levels {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            if event.metadata().target() == "dir_view::audit" {
+                self.0.lock().unwrap().push(*event.metadata().level());
+            }
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::readonly(&*temp_dir);
+    let levels = Arc::new(Mutex::new(Vec::new()));
+    tracing::subscriber::with_default(Levels(Arc::clone(&levels)), || {
+        view.entries().unwrap();
+        view.open("missing").unwrap_err();
+        view.create("file").unwrap_err();
+    });
+    assert_eq!(
+        *levels.lock().unwrap(),
+        [Level::TRACE, Level::DEBUG, Level::WARN]
+    );
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_passthrough() {
+    use dir_view::DirViewBuilder;
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::subscriber::Interest;
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Counts the events dir-view would emit, without enabling any.
+    struct Queries(Arc<Mutex<usize>>);
+
+    impl Subscriber for Queries {
+        fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+            Interest::sometimes()
+        }
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            if metadata.is_event() && metadata.target() == "dir_view::audit" {
+                *self.0.lock().unwrap() += 1;
+            }
+            false
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", "hello").unwrap();
+    let queries = Arc::new(Mutex::new(0));
+    let count = |view: &DirView<&cap_std::fs::Dir>| {
+        *queries.lock().unwrap() = 0;
+        tracing::subscriber::with_default(Queries(Arc::clone(&queries)), || {
+            view.read("file").unwrap();
+            view.metadata("file").unwrap();
+        });
+        *queries.lock().unwrap()
+    };
+
+    // Operations through full, unrestricted views go straight to the
+    // directory when no subscriber is interested in them.
+    assert_eq!(count(&DirView::full(&*temp_dir)), 0);
+    assert_ne!(count(&DirView::readonly(&*temp_dir)), 0);
+    let audited = DirViewBuilder::new().audit(|_| {}).build(&*temp_dir);
+    assert_ne!(count(&audited), 0);
+}
+
+#[test]
+fn view_stats() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    assert_eq!(DirView::full(&*temp_dir).stats(), None);
+
+    let view = DirViewBuilder::new().collect_stats().build(&*temp_dir);
+    view.write("file", "hello").unwrap();
+    view.read("file").unwrap();
+    view.open("file").unwrap();
+
+    // Clones share counts.
+    let clone = view.try_clone().unwrap();
+    clone.read_to_string("file").unwrap();
+    let readonly = clone.open_dir_view(".", ViewKind::Readonly).unwrap();
+    readonly.remove_file("file").unwrap_err();
+
+    let stats = view.stats().unwrap();
+    assert_eq!(stats, clone.stats().unwrap());
+    assert_eq!(stats.opens(), 2);
+    assert_eq!(stats.reads(), 2);
+    assert_eq!(stats.writes(), 1);
+    assert_eq!(stats.denials(), 1);
+    assert_eq!(stats.bytes_read(), 10);
+    assert_eq!(stats.bytes_written(), 5);
+}
+
+#[test]
+fn dry_run() {
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", "hello").unwrap();
+
+    let view = DirView::from_dir(&*temp_dir, ViewKind::DryRun);
+    assert_eq!(view.read_to_string("file").unwrap(), "hello");
+
+    // Changes are recorded, not made.
+    view.create_dir("dir").unwrap();
+    view.write("file", "goodbye").unwrap();
+    view.rename("file", &view, "renamed").unwrap();
+    assert!(!temp_dir.exists("dir"));
+    assert!(!temp_dir.exists("renamed"));
+    assert_eq!(temp_dir.read_to_string("file").unwrap(), "hello");
+
+    // Changes are checked.
+    assert_eq!(
+        view.remove_file("missing").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+
+    // Writable handles can't be simulated.
+    assert_eq!(
+        view.create("new").unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+
+    // Derived views record into the same log, and can't be broader.
+    let sub = view.open_dir(".").unwrap();
+    for entry in sub.entries().unwrap() {
+        entry.unwrap().remove_file().unwrap();
+    }
+    assert!(view.open_dir_view(".", ViewKind::Full).is_err());
+    view.open_dir_view(".", ViewKind::Readonly).unwrap();
+
+    let changes = view.dry_run_changes();
+    let described: Vec<String> = changes.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        described,
+        [
+            "create_dir `dir`",
+            "write `file`",
+            "rename `file` `renamed`",
+            "remove_file `file`",
+        ]
+    );
+    assert_eq!(changes[2].target(), Some(Path::new("renamed")));
+}
+
+#[test]
+fn walk() {
+    use dir_view::DirViewBuilder;
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b/c").unwrap();
+    temp_dir.create_dir_all("skip/me").unwrap();
+    temp_dir.write("a/b/file", b"").unwrap();
+    temp_dir.write(".hidden", b"").unwrap();
+    temp_dir.write("z", b"").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    let paths = view
+        .walk()
+        .unwrap()
+        .sort_by_file_name()
+        .filter_entry(|entry| entry.file_name() != "skip")
+        .map(|entry| entry.unwrap().into_path())
+        .collect::<Vec<_>>();
+    let expected = [".hidden", "a", "a/b", "a/b/c", "a/b/file", "z"];
+    assert_eq!(paths, expected.map(PathBuf::from));
+
+    let depths = view
+        .walk()
+        .unwrap()
+        .max_depth(2)
+        .map(|entry| entry.unwrap().depth())
+        .collect::<Vec<_>>();
+    assert_eq!(depths.len(), 6);
+    assert_eq!(depths.iter().max(), Some(&2));
+
+    // The view's restrictions apply at every level.
+    let view = DirViewBuilder::new()
+        .hide_hidden_files()
+        .max_depth(1)
+        .build(temp_dir.open_dir(".").unwrap());
+    let mut walk = view.walk().unwrap().sort_by_file_name();
+    assert_eq!(walk.next().unwrap().unwrap().path(), "a");
+    assert_eq!(walk.next().unwrap().unwrap().path(), "a/b");
+    assert_eq!(
+        walk.next().unwrap().unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(walk.next().unwrap().unwrap().path(), "skip");
+
+    #[cfg(unix)]
+    {
+        temp_dir.symlink("c", "a/b/down").unwrap();
+        temp_dir.symlink(".", "a/b/loop").unwrap();
+        let view = DirView::readonly(&*temp_dir);
+        let entries = view
+            .walk()
+            .unwrap()
+            .follow_links(true)
+            .filter_entry(|entry| entry.path().starts_with("a"))
+            .collect::<Vec<_>>();
+        let down = entries
+            .iter()
+            .flatten()
+            .find(|entry| entry.file_name() == "down")
+            .unwrap();
+        assert!(down.path_is_symlink());
+        assert!(down.file_type().is_dir());
+        assert_eq!(entries.iter().filter(|entry| entry.is_err()).count(), 1);
+    }
+}
+
+#[test]
+fn copy_dir_all() {
+    use dir_view::{CopyDirOptions, CopySymlinks};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("src/sub").unwrap();
+    temp_dir.write("src/file", b"hello").unwrap();
+    temp_dir.write("src/sub/nested", b"world").unwrap();
+    #[cfg(not(windows))]
+    temp_dir.symlink("file", "src/link").unwrap();
+
+    let view = DirView::full(&*temp_dir);
+    view.copy_dir_all("src", &view, "dst").unwrap();
+    assert_eq!(temp_dir.read("dst/file").unwrap(), b"hello");
+    assert_eq!(temp_dir.read("dst/sub/nested").unwrap(), b"world");
+    #[cfg(not(windows))]
+    assert!(temp_dir.symlink_metadata("dst/link").unwrap().is_symlink());
+
+    // The destination must not exist.
+    assert_eq!(
+        view.copy_dir_all("src", &view, "dst").unwrap_err().kind(),
+        std::io::ErrorKind::AlreadyExists
+    );
+
+    #[cfg(not(windows))]
+    {
+        let mut options = CopyDirOptions::new();
+        options.symlinks(CopySymlinks::Follow);
+        view.copy_dir_all_with("src", &view, "followed", &options)
+            .unwrap();
+        assert!(temp_dir
+            .symlink_metadata("followed/link")
+            .unwrap()
+            .is_file());
+
+        options.symlinks(CopySymlinks::Skip);
+        view.copy_dir_all_with("src", &view, "skipped", &options)
+            .unwrap();
+        assert!(!temp_dir.exists("skipped/link"));
+    }
+
+    // Copying into a readonly view fails upfront.
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        view.copy_dir_all("src", &readonly, "denied")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(!temp_dir.exists("denied"));
+}
+
+#[test]
+fn snapshot_to() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("data/sub").unwrap();
+    temp_dir.create_dir("snapshots").unwrap();
+    temp_dir.write("data/file", b"hello").unwrap();
+    temp_dir.write("data/sub/nested", b"world").unwrap();
+
+    // Snapshots of readonly views can be taken into another view.
+    let data = temp_dir.open_dir("data").unwrap();
+    let snapshots = temp_dir.open_dir("snapshots").unwrap();
+    DirView::readonly(&data)
+        .snapshot_to(&DirView::full(&snapshots), "first")
+        .unwrap();
+    assert_eq!(temp_dir.read("snapshots/first/file").unwrap(), b"hello");
+    assert_eq!(
+        temp_dir.read("snapshots/first/sub/nested").unwrap(),
+        b"world"
+    );
+
+    // The snapshot is independent of the original.
+    temp_dir.write("data/file", b"changed").unwrap();
+    assert_eq!(temp_dir.read("snapshots/first/file").unwrap(), b"hello");
+
+    // Snapshots within the view leave themselves out.
+    let view = DirView::full(&*temp_dir);
+    view.snapshot_to(&view, "snapshots/second").unwrap();
+    assert_eq!(
+        temp_dir.read("snapshots/second/data/file").unwrap(),
+        b"changed"
+    );
+    assert!(temp_dir.exists("snapshots/second/snapshots/first/file"));
+    assert!(!temp_dir.exists("snapshots/second/snapshots/second"));
+}
+
+#[test]
+fn remove_dir_contents() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("cache/sub/dir").unwrap();
+    temp_dir.write("cache/file", b"").unwrap();
+    temp_dir.write("cache/.hidden", b"").unwrap();
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.remove_dir_contents("cache").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let view = DirView::full(&*temp_dir);
+    let cache = view.open_dir("cache").unwrap();
+    view.remove_dir_contents("cache").unwrap();
+    assert!(temp_dir.is_dir("cache"));
+    assert_eq!(temp_dir.read_dir("cache").unwrap().count(), 0);
+
+    // Handles to the directory remain valid.
+    cache.write("new", b"").unwrap();
+    assert!(temp_dir.exists("cache/new"));
+}
+
+#[test]
+fn sync_to() {
+    use dir_view::{SyncCompare, SyncOptions};
+    use std::path::Path;
+
+    let src_dir = TempDir::new(ambient_authority()).unwrap();
+    let dst_dir = TempDir::new(ambient_authority()).unwrap();
+    src_dir.create_dir_all("a/b").unwrap();
+    src_dir.write("a/b/file", b"hello").unwrap();
+    src_dir.write("top", b"top").unwrap();
+    dst_dir.write("extra", b"").unwrap();
+
+    let src = DirView::readonly(&*src_dir);
+    let dst = DirView::full(&*dst_dir);
+    let mut options = SyncOptions::new();
+    options.compare(SyncCompare::Contents);
+
+    let report = src.sync_to(&dst, &options).unwrap();
+    let mut copied = report.copied().to_vec();
+    copied.sort();
+    assert_eq!(copied, ["a", "a/b", "a/b/file", "top"].map(Path::new));
+    assert!(report.deleted().is_empty());
+    assert_eq!(dst_dir.read("a/b/file").unwrap(), b"hello");
+    assert!(dst_dir.exists("extra"));
+
+    // Only changes are copied, and extraneous entries are deleted on request.
+    src_dir.write("top", b"changed").unwrap();
+    options.delete(true);
+    let report = src.sync_to(&dst, &options).unwrap();
+    assert_eq!(report.copied(), [Path::new("top")]);
+    assert_eq!(report.deleted(), [Path::new("extra")]);
+    assert_eq!(report.unchanged(), 1);
+    assert_eq!(dst_dir.read("top").unwrap(), b"changed");
+    assert!(!dst_dir.exists("extra"));
+
+    // A dry run reports what would change without changing it.
+    src_dir.write("new", b"").unwrap();
+    let dry_run = DirView::from_dir(&*dst_dir, ViewKind::DryRun);
+    let report = src.sync_to(&dry_run, &options).unwrap();
+    assert_eq!(report.copied(), [Path::new("new")]);
+    assert!(!dst_dir.exists("new"));
+
+    // Syncing into a readonly view fails upfront.
+    assert_eq!(
+        src.sync_to(&src, &options).unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn diff() {
+    use dir_view::DiffOptions;
+    use std::path::Path;
+
+    let a_dir = TempDir::new(ambient_authority()).unwrap();
+    let b_dir = TempDir::new(ambient_authority()).unwrap();
+    for dir in [&a_dir, &b_dir] {
+        dir.create_dir_all("sub").unwrap();
+        dir.write("same", b"same").unwrap();
+        dir.write("sub/changed", b"aaaa").unwrap();
+    }
+    b_dir.write("sub/changed", b"bbbb").unwrap();
+    a_dir.write("removed", b"").unwrap();
+    b_dir.create_dir("added").unwrap();
+    a_dir.write("kind", b"").unwrap();
+    b_dir.create_dir("kind").unwrap();
+
+    let a = DirView::readonly(&*a_dir);
+    let b = DirView::readonly(&*b_dir);
+    let mut options = DiffOptions::new();
+    options.compare_contents(true);
+    let report = dir_view::diff(&a, &b, &options).unwrap();
+    assert_eq!(report.added(), [Path::new("added")]);
+    assert_eq!(report.removed(), [Path::new("removed")]);
+    assert_eq!(report.modified(), [Path::new("sub/changed")]);
+    assert_eq!(report.type_changed(), [Path::new("kind")]);
+
+    assert!(dir_view::diff(&a, &a, &options).unwrap().is_empty());
+}
+
+#[test]
+fn disk_usage() {
+    use dir_view::DiskUsageOptions;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/sub").unwrap();
+    temp_dir.write("tree/a", [0; 100]).unwrap();
+    temp_dir.write("tree/sub/b", [0; 50]).unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    let usage = view.disk_usage("tree").unwrap();
+    assert_eq!(usage.files(), 2);
+    assert_eq!(usage.dirs(), 2);
+    assert!(usage.apparent_size() >= 150);
+
+    #[cfg(unix)]
+    {
+        temp_dir
+            .hard_link("tree/a", &temp_dir, "tree/sub/a")
+            .unwrap();
+        temp_dir.symlink("sub", "tree/link").unwrap();
+
+        let usage = view.disk_usage("tree").unwrap();
+        assert_eq!(usage.files(), 3);
+        assert_eq!(usage.symlinks(), 1);
+
+        let mut options = DiskUsageOptions::new();
+        options.count_hard_links_once(true).follow_symlinks(true);
+        let usage = view.disk_usage_with("tree", &options).unwrap();
+        // `sub/b` is counted again through the link, but `sub/a` isn't.
+        assert_eq!(usage.files(), 3);
+        assert_eq!(usage.dirs(), 3);
+        assert_eq!(usage.symlinks(), 0);
+    }
+}
+
+#[test]
+fn glob() {
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("src/bin").unwrap();
+    temp_dir.create_dir_all("target/debug").unwrap();
+    for file in [
+        "src/lib.rs",
+        "src/bin/main.rs",
+        "src/bin/notes.txt",
+        "target/debug/out.rs",
+        "a1",
+        "b2",
+        "c3",
+    ] {
+        temp_dir.write(file, b"").unwrap();
+    }
+
+    let view = DirView::readonly(&*temp_dir);
+    let glob = |pattern| {
+        view.glob(pattern)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        glob("src/**/*.rs"),
+        ["src/lib.rs", "src/bin/main.rs"].map(PathBuf::from)
+    );
+    assert_eq!(glob("*/bin"), ["src/bin"].map(PathBuf::from));
+    assert_eq!(glob("[!b]?"), ["a1", "c3"].map(PathBuf::from));
+    assert_eq!(glob("[a-b][0-9]"), ["a1", "b2"].map(PathBuf::from));
+    assert_eq!(
+        glob("**/out.rs"),
+        ["target/debug/out.rs"].map(PathBuf::from)
+    );
+    assert!(glob("missing/*").is_empty());
+
+    for pattern in ["../*", "/etc/*", "src/[abc"] {
+        assert_eq!(
+            view.glob(pattern).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+}
+
+#[test]
+fn find() {
+    use dir_view::FindOptions;
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("a/log.txt", b"").unwrap();
+    temp_dir.write("a/b/log.txt", b"").unwrap();
+    temp_dir.write("a/b/other", b"").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    let is_log = |entry: &dir_view::DirEntryView| entry.file_name() == "log.txt";
+    let mut found = view
+        .find(is_log)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+    found.sort();
+    assert_eq!(found, ["a/b/log.txt", "a/log.txt"].map(Path::new));
+
+    let mut options = FindOptions::new();
+    options.max_depth(2);
+    let found = view.find_with(&options, is_log).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path(), "a/log.txt");
+
+    let mut visited = 0;
+    let first = view
+        .find_first(|_| {
+            visited += 1;
+            true
+        })
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.path(), "a");
+    assert_eq!(visited, 1);
+    assert!(view.find_first(|_| false).unwrap().is_none());
+}
+
+#[test]
+fn sorted_entries() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    for name in ["b", "c", "a", "sub/y", "sub/x"] {
+        temp_dir.write(name, name).unwrap();
+    }
+
+    let view = DirView::readonly(&*temp_dir);
+    let names = |entries: Vec<dir_view::DirEntryView>| {
+        entries
+            .into_iter()
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(view.entries_sorted().unwrap()),
+        ["a", "b", "c", "sub"]
+    );
+    assert_eq!(names(view.read_dir_sorted("sub").unwrap()), ["x", "y"]);
+    assert_eq!(
+        names(
+            view.entries_sorted_by(|a, b| b.file_name().cmp(&a.file_name()))
+                .unwrap()
+        ),
+        ["sub", "c", "b", "a"]
+    );
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        let view = DirViewUtf8::readonly(cap_std::fs_utf8::Dir::from_cap_std(
+            temp_dir.open_dir(".").unwrap(),
+        ));
+        let names = view
+            .read_dir_sorted("sub")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.file_name().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["x", "y"]);
+    }
+}
+
+#[test]
+fn entries_with_metadata() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("dir").unwrap();
+    temp_dir.write("file", b"hello").unwrap();
+    temp_dir.write(".hidden", b"").unwrap();
+
+    let view = DirViewBuilder::new()
+        .hide_hidden_files()
+        .build(temp_dir.open_dir(".").unwrap());
+    let mut entries = view
+        .entries_with_metadata()
+        .unwrap()
+        .map(|entry| {
+            let (entry, metadata) = entry.unwrap();
+            (entry.file_name(), metadata.is_dir(), metadata.len())
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].0, "dir");
+    assert!(entries[0].1);
+    assert_eq!(entries[1], ("file".into(), false, 5));
+}
+
+#[test]
+fn write_atomic() {
+    use std::io::Write;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/config", b"old").unwrap();
+
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    view.write_atomic("sub/config", b"new").unwrap();
+    assert_eq!(view.read_to_string("sub/config").unwrap(), "new");
+
+    // Nothing changes until the file is committed, and if it's dropped
+    // instead, the temporary file is removed.
+    let mut file = view.open_atomic("sub/config").unwrap();
+    file.write_all(b"newer").unwrap();
+    assert_eq!(view.read_to_string("sub/config").unwrap(), "new");
+    drop(file);
+    assert_eq!(view.read_dir("sub").unwrap().count(), 1);
+    assert_eq!(view.read_to_string("sub/config").unwrap(), "new");
+
+    let mut file = view.open_atomic("sub/config").unwrap();
+    file.write_all(b"newest").unwrap();
+    file.commit().unwrap();
+    assert_eq!(view.read_to_string("sub/config").unwrap(), "newest");
+    assert_eq!(view.read_dir("sub").unwrap().count(), 1);
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.write_atomic("sub/config", b"").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        readonly.open_atomic("sub/config").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn named_tempfile() {
+    use std::io::{self, Read, Seek, Write};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("conf").unwrap();
+    temp_dir.write("conf/app", b"old").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+
+    let mut file = view.named_tempfile("conf/app").unwrap();
+    assert_eq!(file.path().parent().unwrap(), std::path::Path::new("conf"));
+    assert!(view.is_file(file.path()));
+    file.write_all(b"new").unwrap();
+    file.rewind().unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "new");
+
+    // Persisting without clobbering fails when the target exists, and the
+    // temporary file is removed.
+    let temp_path = file.path().to_owned();
+    let error = file.persist_noclobber("conf/app").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    assert!(!view.exists(&temp_path));
+    assert_eq!(view.read("conf/app").unwrap(), b"old");
+
+    let mut file = view.named_tempfile("conf/app").unwrap();
+    file.write_all(b"new").unwrap();
+    file.persist("conf/app").unwrap();
+    assert_eq!(view.read("conf/app").unwrap(), b"new");
+
+    let mut file = view.named_tempfile("conf/other").unwrap();
+    file.write_all(b"other").unwrap();
+    file.persist_noclobber("conf/other").unwrap();
+    assert_eq!(view.read("conf/other").unwrap(), b"other");
+
+    let file = view.named_tempfile("conf/app").unwrap();
+    let temp_path = file.path().to_owned();
+    file.discard().unwrap();
+    assert!(!view.exists(&temp_path));
+    drop(view.named_tempfile("conf/app").unwrap());
+    assert_eq!(view.read_dir("conf").unwrap().count(), 2);
+
+    let readonly = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    let error = readonly.named_tempfile("conf/app").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn sync_all_tree() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("a/file", b"hello").unwrap();
+    temp_dir.write("a/b/file", b"hello").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    view.sync_dir().unwrap();
+    view.sync_all_tree(".").unwrap();
+    view.sync_all_tree("a/b").unwrap();
+
+    // The tree is synced through the view, so its restrictions apply.
+    let view = DirViewBuilder::new()
+        .max_depth(1)
+        .build(temp_dir.open_dir(".").unwrap());
+    view.sync_all_tree("a/b").unwrap_err();
+}
+
+#[test]
+fn move_entry() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("from/sub").unwrap();
+    temp_dir.create_dir("to").unwrap();
+    temp_dir.write("from/file", b"file").unwrap();
+    temp_dir.write("from/sub/nested", b"nested").unwrap();
+    temp_dir.write("to/file", b"old").unwrap();
+
+    let from = DirView::from_dir(temp_dir.open_dir("from").unwrap(), ViewKind::Full);
+    let to = DirView::from_dir(temp_dir.open_dir("to").unwrap(), ViewKind::Full);
+    from.move_entry("file", &to, "file").unwrap();
+    from.move_entry("sub", &to, "moved").unwrap();
+    assert!(!from.exists("file"));
+    assert!(!from.exists("sub"));
+    assert_eq!(to.read_to_string("file").unwrap(), "file");
+    assert_eq!(to.read_to_string("moved/nested").unwrap(), "nested");
+
+    let readonly = DirView::readonly(temp_dir.open_dir("to").unwrap());
+    assert_eq!(
+        to.move_entry("file", &readonly, "other")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(to.exists("file"));
+}
+
+#[test]
+fn copy_file() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let contents = vec![7; 100_000];
+    temp_dir.write("from", &contents).unwrap();
+    temp_dir.write("to", b"longer old contents").unwrap();
+    temp_dir.create_dir("dir").unwrap();
+
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    assert_eq!(view.copy("from", &view, "new").unwrap(), 100_000);
+    assert_eq!(view.copy("from", &view, "to").unwrap(), 100_000);
+    assert_eq!(view.read("new").unwrap(), contents);
+    assert_eq!(view.read("to").unwrap(), contents);
+    assert!(view.copy("dir", &view, "copied").is_err());
+
+    #[cfg(unix)]
+    {
+        use cap_std::fs::PermissionsExt;
+
+        view.set_permissions("from", cap_std::fs::Permissions::from_mode(0o640))
+            .unwrap();
+        view.copy("from", &view, "to").unwrap();
+        assert_eq!(
+            view.metadata("to").unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+}
+
+#[test]
+fn copy_progress() {
+    use dir_view::{CancellationToken, Cancelled, CopyDirOptions, CopyOptions, SyncOptions};
+    use std::sync::{Arc, Mutex};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("src").unwrap();
+    for name in ["a", "b", "c"] {
+        temp_dir
+            .write(format!("src/{}", name), vec![0; 3 << 20])
+            .unwrap();
+    }
+
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let mut options = CopyOptions::new();
+    let log = Arc::clone(&reports);
+    options.progress(move |progress| log.lock().unwrap().push(*progress));
+    assert_eq!(
+        view.copy_with("src/a", &view, "a", &options).unwrap(),
+        3 << 20
+    );
+    let last = *reports.lock().unwrap().last().unwrap();
+    assert_eq!((last.bytes(), last.files()), (3 << 20, 1));
+
+    // Cancelling stops the copy between files, reporting how far it got.
+    let token = CancellationToken::new();
+    let mut options = CopyDirOptions::new();
+    let cancel = token.clone();
+    options
+        .progress(move |progress| {
+            if progress.files() == 1 {
+                cancel.cancel();
+            }
+        })
+        .cancel(token.clone());
+    let err = view
+        .copy_dir_all_with("src", &view, "dst", &options)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+    let cancelled = Cancelled::from_io_error(&err).unwrap();
+    assert_eq!(cancelled.progress().files(), 1);
+    assert_eq!(view.read_dir("dst").unwrap().count(), 1);
+
+    // A cancelled token cancels a sync before it copies anything.
+    let mut options = SyncOptions::new();
+    options.cancel(token);
+    let err = view
+        .sync_to(&view.open_dir("dst").unwrap(), &options)
+        .unwrap_err();
+    assert_eq!(
+        Cancelled::from_io_error(&err).unwrap().progress().bytes(),
+        0
+    );
+}
+
+#[test]
+fn hash_tree() {
+    use dir_view::{HashTreeOptions, ManifestEntry, Sha256, Sha256Hasher};
+
+    let hex = |digest: &[u8]| {
+        digest
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    };
+    let mut hasher = Sha256Hasher::new();
+    hasher.update(b"abc");
+    assert_eq!(
+        hex(&hasher.finish()),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    let mut hasher = Sha256Hasher::new();
+    hasher.update(&[b'a'; 1000]);
+    assert_eq!(
+        hex(&hasher.finish()),
+        "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+    );
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/sub").unwrap();
+    temp_dir.write("tree/a", b"abc").unwrap();
+    temp_dir.write("tree/sub/b", b"").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    let manifest = view.hash_tree("tree", &Sha256).unwrap();
+    let paths = manifest
+        .entries()
+        .map(|(path, _)| path.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, ["a", "sub", "sub/b"]);
+    match manifest.get("a").unwrap() {
+        ManifestEntry::File(digest) => assert!(hex(digest).starts_with("ba7816bf")),
+        entry => panic!("unexpected entry {:?}", entry),
+    }
+    assert_eq!(manifest.get("sub"), Some(&ManifestEntry::Dir));
+
+    let mut options = HashTreeOptions::new();
+    options.threads(4);
+    let parallel = view.hash_tree_with("tree", &Sha256, &options).unwrap();
+    assert_eq!(parallel, manifest);
+
+    temp_dir.write("tree/sub/b", b"changed").unwrap();
+    let changed = view.hash_tree("tree", &Sha256).unwrap();
+    assert_ne!(changed.root_digest(), manifest.root_digest());
+    assert_eq!(changed.get("a"), manifest.get("a"));
+}
+
+#[test]
+fn dedupe() {
+    use dir_view::DedupeOptions;
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("cache/sub").unwrap();
+    temp_dir.write("cache/a", b"same").unwrap();
+    temp_dir.write("cache/sub/b", b"same").unwrap();
+    temp_dir.write("cache/c", b"diff").unwrap();
+    temp_dir.write("cache/empty", b"").unwrap();
+    temp_dir.write("cache/empty2", b"").unwrap();
+
+    let options = DedupeOptions::new();
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(
+        readonly.dedupe("cache", &options).unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    let dry_run = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::DryRun);
+    let report = dry_run.dedupe("cache", &options).unwrap();
+    assert_eq!(report.bytes_saved(), 4);
+    assert_eq!(dry_run.dry_run_changes().len(), 1);
+
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let report = view.dedupe("cache", &options).unwrap();
+    assert_eq!(
+        report.linked(),
+        [(PathBuf::from("sub/b"), PathBuf::from("a"))]
+    );
+    assert_eq!(report.bytes_saved(), 4);
+    assert_eq!(view.read_to_string("cache/sub/b").unwrap(), "same");
+    assert_eq!(view.read_dir("cache/sub").unwrap().count(), 1);
+    #[cfg(unix)]
+    assert_eq!(view.nlink("cache/a").unwrap(), 2);
+
+    // Files which are already linked aren't linked again.
+    assert_eq!(view.dedupe("cache", &options).unwrap().linked().len(), 0);
+}
+
+#[test]
+fn tree_stats() {
+    use dir_view::TreeStatsOptions;
+    use std::path::{Path, PathBuf};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/a/b").unwrap();
+    temp_dir.write("tree/small", b"1").unwrap();
+    temp_dir.write("tree/a/medium", b"12").unwrap();
+    temp_dir.write("tree/a/b/large", b"123").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    let mut options = TreeStatsOptions::new();
+    options.largest(2);
+    let stats = view.tree_stats_with("tree", &options).unwrap();
+    assert_eq!((stats.files(), stats.dirs(), stats.symlinks()), (3, 2, 0));
+    assert_eq!(stats.total_size(), 6);
+    assert_eq!(
+        stats.largest(),
+        [
+            (PathBuf::from("a/b/large"), 3),
+            (PathBuf::from("a/medium"), 2)
+        ]
+    );
+    assert_eq!(stats.deepest(), Some((Path::new("a/b/large"), 3)));
+    assert!(stats.oldest().unwrap().1 <= stats.newest().unwrap().1);
+}
+
+#[test]
+fn read_dir_fused() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("a", b"").unwrap();
+
+    let view = DirViewBuilder::new()
+        .hide_hidden_files()
+        .build(temp_dir.open_dir(".").unwrap());
+    let mut entries = view.entries().unwrap();
+    assert_eq!(entries.size_hint().0, 0);
+    assert!(entries.next().is_some());
+    assert!(entries.next().is_none());
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn read_dir_seek() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    for name in ["a", "b", "c", "d"] {
+        temp_dir.write(name, b"").unwrap();
+    }
+
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    let mut entries = view.entries().unwrap();
+    assert_eq!(entries.position(), 0);
+    let first = entries
+        .by_ref()
+        .take(2)
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    let cookie = entries.position();
+    assert_eq!(cookie, 2);
+    let rest = entries
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(first.len() + rest.len(), 4);
+
+    let mut resumed = view.entries().unwrap();
+    resumed.seek(cookie).unwrap();
+    let resumed_rest = resumed
+        .by_ref()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(resumed_rest, rest);
+
+    resumed.rewind().unwrap();
+    assert_eq!(resumed.position(), 0);
+    assert_eq!(resumed.count(), 4);
+}
+
+#[test]
+fn read_dir_buffer_size() {
+    use dir_view::DirViewBuilder;
+    use std::io::Read;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let mut expected = Vec::new();
+    for i in 0..200 {
+        let name = format!("file-with-a-fairly-long-name-{:03}", i);
+        temp_dir.write(&name, b"contents").unwrap();
+        expected.push(name);
+    }
+    temp_dir.create_dir("subdir").unwrap();
+    temp_dir.write(".hidden", b"").unwrap();
+    expected.push("subdir".to_owned());
+    expected.sort();
+
+    let view = DirViewBuilder::new()
+        .read_dir_buffer_size(1)
+        .hide_hidden_files()
+        .build(temp_dir.open_dir(".").unwrap());
+    let mut names = Vec::new();
+    for entry in view.entries().unwrap() {
+        let entry = entry.unwrap();
+        let name = entry.file_name().into_string().unwrap();
+        let file_type = entry.file_type().unwrap();
+        assert_eq!(file_type.is_dir(), name == "subdir");
+        if file_type.is_file() {
+            assert_eq!(entry.metadata().unwrap().len(), 8);
+            let mut contents = String::new();
+            entry.open().unwrap().read_to_string(&mut contents).unwrap();
+            assert_eq!(contents, "contents");
+        } else {
+            assert_eq!(entry.open_dir().unwrap().entries().unwrap().count(), 0);
+        }
+        names.push(name);
+    }
+    names.sort();
+    assert_eq!(names, expected);
+
+    let view = DirViewBuilder::new()
+        .read_dir_buffer_size(1)
+        .build(temp_dir.open_dir(".").unwrap());
+    let mut entries = view.entries().unwrap();
+    entries.seek(150).unwrap();
+    assert_eq!(entries.count(), 52);
+}
+
+#[test]
+fn file_type_hint() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"").unwrap();
+    temp_dir.create_dir("dir").unwrap();
+
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    for entry in view.entries().unwrap() {
+        let entry = entry.unwrap();
+        let file_type = entry.file_type().unwrap();
+        assert_eq!(file_type.is_dir(), entry.file_name() == "dir");
+        if let Some(hint) = entry.file_type_hint() {
+            assert_eq!(hint, file_type);
+        }
+    }
+}
+
+#[test]
+fn metadata_cache() {
+    use dir_view::CacheConfig;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("dir").unwrap();
+    let view = DirView::full(temp_dir.open_dir(".").unwrap())
+        .with_cache(CacheConfig::new().ttl(Duration::from_secs(3600)));
+
+    assert!(!view.exists("a"));
+    assert!(view.is_dir("dir"));
+    temp_dir.write("a", b"").unwrap();
+    temp_dir.remove_dir("dir").unwrap();
+    assert!(!view.exists("a"));
+    assert!(view.is_dir("dir"));
+    let clone = view.try_clone().unwrap();
+    assert!(!clone.exists("a"));
+
+    // Changes through the view discard the cache, so its directory, which
+    // could be changed without discarding it, isn't exposed.
+    assert!(view.as_dir().is_none());
+    view.write("b", b"").unwrap();
+    assert!(view.exists("a"));
+    assert!(!view.is_dir("dir"));
+
+    let view = DirView::full(temp_dir.open_dir(".").unwrap())
+        .with_cache(CacheConfig::new().ttl(Duration::ZERO));
+    assert!(view.exists("a"));
+    temp_dir.remove_file("a").unwrap();
+    assert!(!view.exists("a"));
+
+    // Missing paths can be remembered for longer, or not at all.
+    let view = DirView::full(temp_dir.open_dir(".").unwrap()).with_cache(
+        CacheConfig::new()
+            .ttl(Duration::ZERO)
+            .negative_ttl(Duration::from_secs(3600)),
+    );
+    assert!(!view.exists("a"));
+    assert!(view.exists("b"));
+    temp_dir.write("a", b"").unwrap();
+    temp_dir.remove_file("b").unwrap();
+    assert!(!view.exists("a"));
+    assert!(!view.exists("b"));
+    view.create_dir("c").unwrap();
+    assert!(view.exists("a"));
+
+    // Lookups by a clone while a path is being created don't leave it
+    // remembered as missing.
+    let clone = view.try_clone().unwrap();
+    let current = AtomicUsize::new(0);
+    let done = AtomicBool::new(false);
+    let missing = thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                clone.exists(format!("f{}", current.load(Ordering::Relaxed)));
+            }
+        });
+        let missing = (0..1000)
+            .filter(|i| {
+                current.store(*i, Ordering::Relaxed);
+                view.write(format!("f{i}"), b"").unwrap();
+                !view.exists(format!("f{i}"))
+            })
+            .count();
+        done.store(true, Ordering::Relaxed);
+        missing
+    });
+    assert_eq!(missing, 0);
+
+    let view = DirView::full(temp_dir.open_dir(".").unwrap()).with_cache(
+        CacheConfig::new()
+            .ttl(Duration::from_secs(3600))
+            .negative_ttl(Duration::ZERO),
+    );
+    assert!(!view.exists("d"));
+    assert!(view.exists("a"));
+    temp_dir.write("d", b"").unwrap();
+    temp_dir.remove_file("a").unwrap();
+    assert!(view.exists("d"));
+    assert!(view.exists("a"));
+}
+
+#[test]
+fn read_into_and_range() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("a", b"hello world").unwrap();
+    temp_dir.write("b", b"hi").unwrap();
+
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    let mut buf = Vec::new();
+    assert_eq!(view.read_into("a", &mut buf).unwrap(), 11);
+    assert_eq!(buf, b"hello world");
+    assert_eq!(view.read_into("b", &mut buf).unwrap(), 2);
+    assert_eq!(buf, b"hi");
+
+    assert_eq!(view.read_range("a", 6, 5).unwrap(), b"world");
+    assert_eq!(view.read_range("a", 6, 100).unwrap(), b"world");
+    assert_eq!(view.read_range("a", 100, 5).unwrap(), b"");
+    assert!(view.read_range("missing", 0, 5).is_err());
+}
+
+#[test]
+fn read_to_string_limit() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("a", b"hello").unwrap();
+    temp_dir.write("b", b"\xff").unwrap();
+
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    assert_eq!(view.read_to_string_limit("a", 5).unwrap(), "hello");
+    assert_eq!(
+        view.read_to_string_limit("a", 4).unwrap_err().kind(),
+        std::io::ErrorKind::FileTooLarge
+    );
+    assert_eq!(
+        view.read_to_string_limit("b", 4).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn buffered() {
+    use std::io::Write;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(temp_dir.open_dir(".").unwrap());
+    let mut writer = view.open_buffered_write("lines").unwrap();
+    writer.write_all(b"one\ntwo\n").unwrap();
+    writer.flush().unwrap();
+    drop(writer);
+
+    let lines = view
+        .read_lines("lines")
+        .unwrap()
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(lines, ["one", "two"]);
+
+    let readonly = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    assert!(readonly.open_buffered("lines").is_ok());
+    assert!(readonly.open_buffered_write("other").is_err());
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mmap() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"hello").unwrap();
+
+    let readonly = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    let map = unsafe { readonly.mmap("file") }.unwrap();
+    assert_eq!(&map[..], b"hello");
+    assert!(unsafe { readonly.mmap_mut("file") }.is_err());
+
+    let view = DirView::full(temp_dir.open_dir(".").unwrap());
+    let mut map = unsafe { view.mmap_mut("file") }.unwrap();
+    map[0] = b'j';
+    map.flush().unwrap();
+    drop(map);
+    assert_eq!(view.read("file").unwrap(), b"jello");
+}
+
+#[test]
+fn read_many() {
+    use dir_view::DirViewBuilder;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/a", b"aaa").unwrap();
+    temp_dir.write("sub/b", b"").unwrap();
+    temp_dir.write("outside", b"outside").unwrap();
+    let paths = ["a", "b", "missing", "../outside", "/etc/passwd"];
+
+    #[allow(unused_mut)]
+    let mut builders = vec![DirViewBuilder::new()];
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    {
+        let mut builder = DirViewBuilder::new();
+        builder.io_uring();
+        builders.push(builder);
+    }
+    for builder in builders {
+        let view = builder.build(temp_dir.open_dir("sub").unwrap());
+        let results = view.read_many(paths);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].as_ref().unwrap(), b"aaa");
+        assert_eq!(results[1].as_ref().unwrap(), b"");
+        assert_eq!(
+            results[2].as_ref().unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+        assert!(results[3].is_err());
+        assert!(results[4].is_err());
+    }
+}
+
+#[test]
+fn metadata_batch() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("sub/dir").unwrap();
+    temp_dir.write("sub/a", b"aaa").unwrap();
+    temp_dir.write("sub/dir/b", b"bb").unwrap();
+    temp_dir.write("sub/dir/c", b"c").unwrap();
+    temp_dir.write("outside", b"outside").unwrap();
+    let view = DirView::readonly(temp_dir.open_dir("sub").unwrap());
+
+    let paths = [
+        "a",
+        "dir/b",
+        "dir/c",
+        "dir/missing",
+        "a/",
+        "dir",
+        "../outside",
+        "/etc/passwd",
+    ];
+    let results = view.metadata_batch(paths);
+    assert_eq!(results.len(), 8);
+    assert_eq!(results[0].as_ref().unwrap().len(), 3);
+    assert_eq!(results[1].as_ref().unwrap().len(), 2);
+    assert_eq!(results[2].as_ref().unwrap().len(), 1);
+    assert_eq!(
+        results[3].as_ref().unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    assert!(results[4].is_err());
+    assert!(results[5].as_ref().unwrap().is_dir());
+    assert!(results[6].is_err());
+    assert!(results[7].is_err());
+
+    #[cfg(unix)]
+    {
+        temp_dir.symlink("dir/b", "sub/link").unwrap();
+        temp_dir.symlink("../outside", "sub/escape").unwrap();
+        let results = view.metadata_batch(["link", "escape"]);
+        assert_eq!(results[0].as_ref().unwrap().len(), 2);
+        assert!(results[1].is_err());
+    }
+}
+
+#[test]
+fn entry_kind() {
+    use dir_view::FileTypeSummary;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", b"").unwrap();
+    temp_dir.create_dir("dir").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    assert_eq!(
+        view.entry_kind("file").unwrap(),
+        Some(FileTypeSummary::File)
+    );
+    assert_eq!(view.entry_kind("dir").unwrap(), Some(FileTypeSummary::Dir));
+    assert_eq!(view.entry_kind("missing").unwrap(), None);
+    assert!(view.entry_kind("../escape").is_err());
+
+    #[cfg(unix)]
+    {
+        temp_dir.symlink("dir", "link").unwrap();
+        temp_dir.symlink("missing", "broken").unwrap();
+        assert_eq!(
+            view.entry_kind("link").unwrap(),
+            Some(FileTypeSummary::Symlink)
+        );
+        assert_eq!(
+            view.entry_kind_follow("link").unwrap(),
+            Some(FileTypeSummary::Dir)
+        );
+        assert_eq!(
+            view.entry_kind("broken").unwrap(),
+            Some(FileTypeSummary::Symlink)
+        );
+        assert_eq!(view.entry_kind_follow("broken").unwrap(), None);
+    }
+}
+
+#[cfg(feature = "async-std")]
+#[test]
+fn dir_view_async() {
+    use dir_view::async_std::prelude::*;
+    use dir_view::async_std::task;
+    use dir_view::DirViewAsync;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/a", b"aaa").unwrap();
+    let full = DirViewAsync::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let readonly = DirViewAsync::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    task::block_on(async {
+        assert_eq!(readonly.read("sub/a").await.unwrap(), b"aaa");
+        assert!(readonly.write("b", "b").await.is_err());
+        assert!(readonly.create("b").await.is_err());
+        assert!(readonly.remove_file("sub/a").await.is_err());
+        assert!(readonly.read("../escape").await.is_err());
+        assert!(!readonly.exists("b").await);
+
+        full.write("b", "bb").await.unwrap();
+        assert_eq!(readonly.read_to_string("b").await.unwrap(), "bb");
+        let mut contents = String::new();
+        full.open("b")
+            .await
+            .unwrap()
+            .read_to_string(&mut contents)
+            .await
+            .unwrap();
+        assert_eq!(contents, "bb");
+
+        let sub = readonly.open_dir("sub").await.unwrap();
+        assert!(sub.write("c", "c").await.is_err());
+        let mut names = Vec::new();
+        let mut entries = readonly.entries().await.unwrap();
+        while let Some(entry) = entries.next().await {
+            names.push(entry.unwrap().file_name());
+        }
+        names.sort();
+        assert_eq!(names, ["b", "sub"]);
+        let mut entries = readonly.read_dir("sub").await.unwrap();
+        assert_eq!(entries.next().await.unwrap().unwrap().file_name(), "a");
+        assert!(entries.next().await.is_none());
+        assert!(entries.next().await.is_none());
+    });
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn tokio_dir_view() {
+    use dir_view::tokio::runtime::Builder;
+    use dir_view::TokioDirView;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/a", b"aaa").unwrap();
+    let full = TokioDirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let readonly = TokioDirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    let runtime = Builder::new_current_thread().build().unwrap();
+    runtime.block_on(async {
+        assert_eq!(readonly.read("sub/a").await.unwrap(), b"aaa");
+        assert!(readonly.write("b", "b").await.is_err());
+        assert!(readonly.create("b").await.is_err());
+        assert!(readonly.remove_file("sub/a").await.is_err());
+        assert!(readonly.read("../escape").await.is_err());
+        assert!(!readonly.exists("b").await);
+
+        full.write("b", "bb").await.unwrap();
+        assert_eq!(readonly.read_to_string("b").await.unwrap(), "bb");
+        assert_eq!(
+            full.open("b")
+                .await
+                .unwrap()
+                .metadata()
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let sub = readonly.open_dir("sub").await.unwrap();
+        assert!(sub.write("c", "c").await.is_err());
+        let mut names = Vec::new();
+        let mut entries = readonly.entries().await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            names.push(entry.file_name());
+        }
+        names.sort();
+        assert_eq!(names, ["b", "sub"]);
+        let mut entries = readonly.read_dir("sub").await.unwrap();
+        assert_eq!(
+            entries.next_entry().await.unwrap().unwrap().file_name(),
+            "a"
+        );
+        assert!(entries.next_entry().await.unwrap().is_none());
+        assert!(entries.next_entry().await.unwrap().is_none());
+    });
+}
+
+#[cfg(feature = "stream")]
+#[test]
+fn read_dir_stream() {
+    use dir_view::futures_core::Stream;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("a", b"").unwrap();
+    temp_dir.write("b", b"").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut entries = view.entries().unwrap();
+    let mut names = Vec::new();
+    while let Poll::Ready(Some(entry)) = Pin::new(&mut entries).poll_next(&mut cx) {
+        names.push(entry.unwrap().file_name());
+    }
+    names.sort();
+    assert_eq!(names, ["a", "b"]);
+
+    #[cfg(feature = "tokio")]
+    {
+        let view = dir_view::TokioDirView::from(view);
+        let runtime = dir_view::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let names = runtime.block_on(async {
+            let mut entries = view.entries().await.unwrap();
+            let mut names = Vec::new();
+            while let Some(entry) =
+                std::future::poll_fn(|cx| Pin::new(&mut entries).poll_next(cx)).await
+            {
+                names.push(entry.unwrap().file_name());
+            }
+            names
+        });
+        assert_eq!(names.len(), 2);
+    }
+
+    #[cfg(feature = "async-std")]
+    {
+        fn assert_stream<S: Stream<Item = std::io::Result<dir_view::DirEntryView>>>() {}
+        assert_stream::<dir_view::ReadDirViewAsync>();
+    }
+}
+
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+#[test]
+fn walk_stream() {
+    use dir_view::WalkStream;
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b/c").unwrap();
+    temp_dir.create_dir_all("d").unwrap();
+    temp_dir.write("a/file", b"").unwrap();
+    temp_dir.write("a/b/c/deep", b"").unwrap();
+    temp_dir.write(".hidden", b"").unwrap();
+    let mut builder = dir_view::DirViewBuilder::new();
+    builder.hide_hidden_files();
+    let view = builder.build(temp_dir.open_dir(".").unwrap());
+
+    async fn collect(mut walk: WalkStream) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        while let Some(entry) = walk.next_entry().await {
+            let entry = entry.unwrap();
+            if let Some(parent) = entry.path().parent() {
+                if !parent.as_os_str().is_empty() {
+                    // Directories are yielded before their contents.
+                    assert!(paths.iter().any(|path| path == parent));
+                }
+            }
+            paths.push(entry.into_path());
+        }
+        paths.sort();
+        paths
+    }
+
+    let all: Vec<PathBuf> = ["a", "a/b", "a/b/c", "a/b/c/deep", "a/file", "d"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    let shallow: Vec<PathBuf> = ["a", "a/b", "a/file", "d"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+    #[cfg(feature = "tokio")]
+    {
+        let view = dir_view::TokioDirView::from(view.try_clone().unwrap());
+        let runtime = dir_view::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert_eq!(collect(view.walk().concurrency(2)).await, all);
+            assert_eq!(collect(view.walk().max_depth(2)).await, shallow);
+            assert!(collect(view.walk().max_depth(0)).await.is_empty());
+
+            // Dropping a walk part way through stops it.
+            let mut walk = view.walk().concurrency(1);
+            assert!(walk.next_entry().await.unwrap().is_ok());
+            drop(walk);
+        });
+    }
+
+    #[cfg(feature = "async-std")]
+    {
+        let view = dir_view::DirViewAsync::from(view);
+        dir_view::async_std::task::block_on(async {
+            assert_eq!(collect(view.walk()).await, all);
+            assert_eq!(collect(view.walk().max_depth(2)).await, shallow);
+        });
+    }
+}
+
+#[test]
+fn copy_concurrently() {
+    use dir_view::{CopyDirOptions, SyncOptions};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("src/sub").unwrap();
+    for i in 0..20 {
+        temp_dir.write(format!("src/{}", i), i.to_string()).unwrap();
+        temp_dir
+            .write(format!("src/sub/{}", i), i.to_string())
+            .unwrap();
+    }
+    #[cfg(unix)]
+    {
+        use cap_std::fs::PermissionsExt;
+        temp_dir
+            .set_permissions("src/sub", cap_std::fs::Permissions::from_mode(0o555))
+            .unwrap();
+    }
+
+    let view = DirView::full(&*temp_dir);
+    let files = Arc::new(AtomicU64::new(0));
+    let mut options = CopyDirOptions::new();
+    options.concurrency(4).progress({
+        let files = Arc::clone(&files);
+        move |progress| {
+            files.fetch_max(progress.files(), Ordering::Relaxed);
+        }
+    });
+    view.copy_dir_all_with("src", &view, "dst", &options)
+        .unwrap();
+    assert_eq!(files.load(Ordering::Relaxed), 40);
+    for i in 0..20 {
+        assert_eq!(
+            temp_dir.read_to_string(format!("dst/sub/{}", i)).unwrap(),
+            i.to_string()
+        );
+    }
+    // Directory permissions are set after the files are copied into them.
+    #[cfg(unix)]
+    assert!(temp_dir
+        .metadata("dst/sub")
+        .unwrap()
+        .permissions()
+        .readonly());
+
+    let dst_dir = TempDir::new(ambient_authority()).unwrap();
+    let dst = DirView::full(&*dst_dir);
+    let mut options = SyncOptions::new();
+    options.concurrency(3);
+    let report = view
+        .open_dir("dst")
+        .unwrap()
+        .sync_to(&dst, &options)
+        .unwrap();
+    assert_eq!(report.copied().len(), 41);
+    assert_eq!(dst_dir.read_to_string("sub/7").unwrap(), "7");
+
+    #[cfg(unix)]
+    {
+        use cap_std::fs::PermissionsExt;
+        for dir in ["src/sub", "dst/sub"] {
+            temp_dir
+                .set_permissions(dir, cap_std::fs::Permissions::from_mode(0o755))
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn tokio_copy_dir_all() {
+    use dir_view::{SyncOptions, TokioDirView};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("src/sub").unwrap();
+    temp_dir.write("src/sub/file", b"hello").unwrap();
+    let view = TokioDirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let readonly = TokioDirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    let runtime = dir_view::tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        view.copy_dir_all("src", &view, "dst").await.unwrap();
+        assert_eq!(view.read("dst/sub/file").await.unwrap(), b"hello");
+        assert!(view.copy_dir_all("src", &readonly, "denied").await.is_err());
+
+        let dst = view.open_dir("dst").await.unwrap();
+        view.create_dir("mirror").await.unwrap();
+        let mirror = view.open_dir("mirror").await.unwrap();
+        let report = dst.sync_to(&mirror, &SyncOptions::new()).await.unwrap();
+        assert_eq!(report.copied().len(), 2);
+        assert_eq!(view.read("mirror/sub/file").await.unwrap(), b"hello");
+    });
+}
+
+#[cfg(all(feature = "watch", target_os = "linux"))]
+#[test]
+fn watch() {
+    use dir_view::{DirViewBuilder, WatchEvent, WatchOptions};
+    use std::path::PathBuf;
+
+    fn expect(events: &mut dir_view::EventStream, expected: WatchEvent) {
+        loop {
+            let event = events.next().unwrap().unwrap();
+            if event == expected {
+                return;
+            }
+            assert!(matches!(event, WatchEvent::Modify(_)), "{event:?}");
+        }
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.create_dir("sub/old").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+    let mut events = view
+        .watch("sub", WatchOptions::new().recursive(true))
+        .unwrap();
+    let path = |path: &str| PathBuf::from(path);
+
+    temp_dir.create("sub/a").unwrap();
+    expect(&mut events, WatchEvent::Create(path("sub/a")));
+    temp_dir.write("sub/a", b"a").unwrap();
+    expect(&mut events, WatchEvent::Modify(path("sub/a")));
+    temp_dir.rename("sub/a", &temp_dir, "sub/b").unwrap();
+    expect(
+        &mut events,
+        WatchEvent::Rename {
+            from: path("sub/a"),
+            to: path("sub/b"),
+        },
+    );
+    temp_dir.remove_file("sub/b").unwrap();
+    expect(&mut events, WatchEvent::Remove(path("sub/b")));
+
+    // Subdirectories are watched, including new and renamed ones.
+    temp_dir.create("sub/old/c").unwrap();
+    expect(&mut events, WatchEvent::Create(path("sub/old/c")));
+    temp_dir.create_dir("sub/new").unwrap();
+    expect(&mut events, WatchEvent::Create(path("sub/new")));
+    temp_dir.create("sub/new/d").unwrap();
+    expect(&mut events, WatchEvent::Create(path("sub/new/d")));
+    temp_dir.rename("sub/old", &temp_dir, "sub/moved").unwrap();
+    expect(
+        &mut events,
+        WatchEvent::Rename {
+            from: path("sub/old"),
+            to: path("sub/moved"),
+        },
+    );
+    temp_dir.remove_file("sub/moved/c").unwrap();
+    expect(&mut events, WatchEvent::Remove(path("sub/moved/c")));
+
+    // Moving an entry out of the view is a removal.
+    temp_dir.rename("sub/new", &temp_dir, "gone").unwrap();
+    expect(&mut events, WatchEvent::Remove(path("sub/new")));
+
+    // Hidden entries are left out.
+    let view = DirViewBuilder::new()
+        .readonly()
+        .hide_hidden_files()
+        .build(temp_dir.open_dir(".").unwrap());
+    let mut events = view.watch("sub", &WatchOptions::new()).unwrap();
+    temp_dir.create("sub/.hidden").unwrap();
+    temp_dir.create("sub/visible").unwrap();
+    expect(&mut events, WatchEvent::Create(path("sub/visible")));
+}
+
+#[cfg(all(feature = "watch", target_os = "linux"))]
+#[test]
+fn inotify_watch() {
+    use std::io::Read;
+    use std::os::fd::{AsFd, FromRawFd, OwnedFd};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/file", b"").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir("sub").unwrap(), ViewKind::Readonly);
+
+    let inotify = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    assert!(inotify >= 0);
+    let inotify = unsafe { OwnedFd::from_raw_fd(inotify) };
+    let wd = view
+        .inotify_watch(inotify.as_fd(), "file", libc::IN_MODIFY)
+        .unwrap();
+    temp_dir.write("sub/file", b"changed").unwrap();
+    let mut buf = [0; 4096];
+    let len = std::fs::File::from(inotify.try_clone().unwrap())
+        .read(&mut buf)
+        .unwrap();
+    assert!(len >= 16);
+    assert_eq!(i32::from_ne_bytes(buf[..4].try_into().unwrap()), wd);
+    assert_ne!(
+        u32::from_ne_bytes(buf[4..8].try_into().unwrap()) & libc::IN_MODIFY,
+        0
+    );
+
+    // Entries outside the view can't be watched.
+    assert!(view
+        .inotify_watch(inotify.as_fd(), "..", libc::IN_MODIFY)
+        .is_err());
+
+    // Nor can whole mounts be marked.
+    assert_eq!(
+        view.fanotify_mark(
+            inotify.as_fd(),
+            "file",
+            libc::FAN_MARK_MOUNT,
+            libc::FAN_OPEN
+        )
+        .unwrap_err()
+        .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn poll_changes() {
+    use dir_view::{WatchEvent, WatchOptions};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("sub/old").unwrap();
+    temp_dir.write("sub/old/a", b"a").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+    let mut changes = view
+        .poll_changes(
+            "sub",
+            Duration::from_millis(10),
+            WatchOptions::new().recursive(true),
+        )
+        .unwrap();
+    let path = |path: &str| PathBuf::from(path);
+
+    temp_dir.write("sub/b", b"b").unwrap();
+    assert_eq!(
+        changes.next().unwrap().unwrap(),
+        WatchEvent::Create(path("sub/b"))
+    );
+    temp_dir.write("sub/b", b"bigger").unwrap();
+    assert_eq!(
+        changes.next().unwrap().unwrap(),
+        WatchEvent::Modify(path("sub/b"))
+    );
+    temp_dir.remove_file("sub/b").unwrap();
+    assert_eq!(
+        changes.next().unwrap().unwrap(),
+        WatchEvent::Remove(path("sub/b"))
+    );
+
+    // A renamed directory is reported once, not for each of its entries.
+    temp_dir.rename("sub/old", &temp_dir, "sub/new").unwrap();
+    #[cfg(unix)]
+    assert_eq!(
+        changes.next().unwrap().unwrap(),
+        WatchEvent::Rename {
+            from: path("sub/old"),
+            to: path("sub/new"),
+        }
+    );
+    temp_dir.write("sub/new/a", b"changed").unwrap();
+    #[cfg(unix)]
+    assert_eq!(
+        changes.next().unwrap().unwrap(),
+        WatchEvent::Modify(path("sub/new/a"))
+    );
+}
+
+#[cfg(all(feature = "9p", unix))]
+#[test]
+fn serve_9p() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    fn string(body: &mut Vec<u8>, s: &str) {
+        body.extend_from_slice(&(s.len() as u16).to_le_bytes());
+        body.extend_from_slice(s.as_bytes());
+    }
+
+    fn rpc(stream: &mut UnixStream, kind: u8, body: &[u8]) -> (u8, Vec<u8>) {
+        let mut message = ((body.len() + 7) as u32).to_le_bytes().to_vec();
+        message.push(kind);
+        message.extend_from_slice(&1u16.to_le_bytes());
+        message.extend_from_slice(body);
+        stream.write_all(&message).unwrap();
+        let mut size = [0; 4];
+        stream.read_exact(&mut size).unwrap();
+        let mut reply = vec![0; u32::from_le_bytes(size) as usize - 4];
+        stream.read_exact(&mut reply).unwrap();
+        (reply[0], reply[3..].to_vec())
+    }
+
+    fn errno(reply: (u8, Vec<u8>)) -> u32 {
+        assert_eq!(reply.0, 7);
+        u32::from_le_bytes(reply.1[..4].try_into().unwrap())
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("share").unwrap();
+    temp_dir.write("share/file", b"hello").unwrap();
+    temp_dir.write("secret", b"").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir("share").unwrap(), ViewKind::Readonly);
+
+    let (mut client, server) = UnixStream::pair().unwrap();
+    let server = std::thread::spawn(move || view.serve_9p(server));
+
+    // Tversion and Tattach. Message sizes below the minimum are refused.
+    let mut body = 512u32.to_le_bytes().to_vec();
+    string(&mut body, "9P2000.L");
+    assert_eq!(errno(rpc(&mut client, 100, &body)), 22);
+    let mut body = 8192u32.to_le_bytes().to_vec();
+    string(&mut body, "9P2000.L");
+    let (kind, reply) = rpc(&mut client, 100, &body);
+    assert_eq!(kind, 101);
+    assert_eq!(reply[..4], 8192u32.to_le_bytes());
+    assert_eq!(&reply[6..], b"9P2000.L");
+    let mut body = [0u32.to_le_bytes(), u32::MAX.to_le_bytes()].concat();
+    string(&mut body, "user");
+    string(&mut body, "");
+    body.extend_from_slice(&0u32.to_le_bytes());
+    let (kind, reply) = rpc(&mut client, 104, &body);
+    assert_eq!(kind, 105);
+    assert_eq!(reply[0], 0x80);
+
+    // Twalk to the file, Tlopen it, and Tread it.
+    let mut body = [0u32.to_le_bytes(), 1u32.to_le_bytes()].concat();
+    body.extend_from_slice(&1u16.to_le_bytes());
+    string(&mut body, "file");
+    let (kind, _) = rpc(&mut client, 110, &body);
+    assert_eq!(kind, 111);
+    let (kind, _) = rpc(
+        &mut client,
+        12,
+        &[1u32.to_le_bytes(), 0u32.to_le_bytes()].concat(),
+    );
+    assert_eq!(kind, 13);
+    let mut body = 1u32.to_le_bytes().to_vec();
+    body.extend_from_slice(&0u64.to_le_bytes());
+    body.extend_from_slice(&100u32.to_le_bytes());
+    let (kind, reply) = rpc(&mut client, 116, &body);
+    assert_eq!(kind, 117);
+    assert_eq!(&reply[4..], b"hello");
+
+    // Walking out of the share stays at its root, so only `..` is walked.
+    let mut body = [0u32.to_le_bytes(), 2u32.to_le_bytes()].concat();
+    body.extend_from_slice(&2u16.to_le_bytes());
+    string(&mut body, "..");
+    string(&mut body, "secret");
+    let (kind, reply) = rpc(&mut client, 110, &body);
+    assert_eq!(kind, 111);
+    assert_eq!(reply[..2], 1u16.to_le_bytes());
+
+    // Changes fail with EROFS.
+    let (kind, _) = rpc(
+        &mut client,
+        12,
+        &[0u32.to_le_bytes(), 0u32.to_le_bytes()].concat(),
+    );
+    assert_eq!(kind, 13);
+    let mut body = 0u32.to_le_bytes().to_vec();
+    string(&mut body, "new");
+    body.extend_from_slice(
+        &[
+            0o1u32.to_le_bytes(),
+            0o644u32.to_le_bytes(),
+            0u32.to_le_bytes(),
+        ]
+        .concat(),
+    );
+    assert_eq!(errno(rpc(&mut client, 14, &body)), 30);
+    let mut body = 0u32.to_le_bytes().to_vec();
+    string(&mut body, "file");
+    body.extend_from_slice(&0u32.to_le_bytes());
+    assert_eq!(errno(rpc(&mut client, 76, &body)), 30);
+    assert!(temp_dir.exists("share/file"));
+
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[cfg(feature = "wasi-host")]
+#[test]
+fn wasi_preopen() {
+    use dir_view::wasi_common::dir::{OpenResult, ReaddirCursor};
+    use dir_view::wasi_common::file::{FdFlags, OFlags};
+    use dir_view::wasi_common::snapshots::preview_1::error::Errno;
+    use dir_view::wasi_common::{Error, WasiDir};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    // Views are synchronous, so their futures are ready when first polled.
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("a view's future is pending"),
+        }
+    }
+
+    fn errno(err: Error) -> Errno {
+        *err.downcast_ref().unwrap()
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("share/sub").unwrap();
+    temp_dir.write("share/file", b"hello").unwrap();
+    temp_dir.write("secret", b"").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir("share").unwrap(), ViewKind::Readonly);
+    let dir: &dyn WasiDir = &view;
+
+    let open = |path, oflags, write| {
+        ready(dir.open_file(true, path, oflags, true, write, FdFlags::empty()))
+    };
+    assert!(matches!(
+        open("file", OFlags::empty(), false),
+        Ok(OpenResult::File(_))
+    ));
+    assert!(matches!(
+        open("sub", OFlags::DIRECTORY, false),
+        Ok(OpenResult::Dir(_))
+    ));
+    assert_eq!(
+        errno(open("file", OFlags::DIRECTORY, false).err().unwrap()),
+        Errno::Notdir
+    );
+    assert!(open("../secret", OFlags::empty(), false).is_err());
+    assert_eq!(ready(dir.get_path_filestat("file", true)).unwrap().size, 5);
+
+    let mut names = ready(dir.readdir(ReaddirCursor::from(0)))
+        .unwrap()
+        .map(|entity| entity.unwrap().name)
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, [".", "..", "file", "sub"]);
+    assert_eq!(
+        ready(dir.readdir(ReaddirCursor::from(4))).unwrap().count(),
+        0
+    );
+
+    // Changes fail with EROFS.
+    assert_eq!(
+        errno(open("file", OFlags::empty(), true).err().unwrap()),
+        Errno::Rofs
+    );
+    assert_eq!(
+        errno(open("new", OFlags::CREATE, false).err().unwrap()),
+        Errno::Rofs
+    );
+    assert_eq!(
+        errno(ready(dir.create_dir("new")).unwrap_err()),
+        Errno::Rofs
+    );
+    assert_eq!(
+        errno(ready(dir.unlink_file("file")).unwrap_err()),
+        Errno::Rofs
+    );
+    assert!(temp_dir.exists("share/file"));
+
+    let view = DirView::from_dir(temp_dir.open_dir("share").unwrap(), ViewKind::Full);
+    let dir: &dyn WasiDir = &view;
+    ready(dir.create_dir("new")).unwrap();
+    assert!(matches!(
+        ready(dir.open_file(
+            true,
+            "new/made",
+            OFlags::CREATE | OFlags::EXCLUSIVE,
+            false,
+            true,
+            FdFlags::empty()
+        )),
+        Ok(OpenResult::File(_))
+    ));
+    ready(dir.rename("new/made", dir, "moved")).unwrap();
+    assert!(temp_dir.exists("share/moved"));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn serve_http() {
+    use dir_view::HttpRequest;
+    use std::io::Read;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("site/docs").unwrap();
+    temp_dir
+        .write("site/hello world.txt", b"0123456789")
+        .unwrap();
+    temp_dir
+        .write("site/docs/index.html", b"<p>docs</p>")
+        .unwrap();
+    temp_dir.write("secret", b"").unwrap();
+    let view = DirViewUtf8::from_dir(
+        cap_std::fs_utf8::Dir::from_cap_std(temp_dir.open_dir("site").unwrap()),
+        ViewKind::Readonly,
+    );
+
+    let response = view
+        .serve_http(&HttpRequest::new("GET", "/hello%20world.txt?x=1"))
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(response.header("Content-Length"), Some("10"));
+    let etag = response.header("ETag").unwrap().to_owned();
+    let mut body = String::new();
+    response
+        .into_body()
+        .unwrap()
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, "0123456789");
+
+    // Ranges.
+    let mut request = HttpRequest::new("GET", "/hello%20world.txt");
+    request.header("Range", "bytes=2-4");
+    let response = view.serve_http(&request).unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.header("Content-Range"), Some("bytes 2-4/10"));
+    let mut body = String::new();
+    response
+        .into_body()
+        .unwrap()
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, "234");
+    request.header("Range", "bytes=-3");
+    let response = view.serve_http(&request).unwrap();
+    assert_eq!(response.header("Content-Range"), Some("bytes 7-9/10"));
+    request.header("Range", "bytes=10-");
+    assert_eq!(view.serve_http(&request).unwrap().status(), 416);
+
+    // Conditional requests.
+    let mut request = HttpRequest::new("GET", "/hello%20world.txt");
+    request.header("If-None-Match", &etag);
+    let response = view.serve_http(&request).unwrap();
+    assert_eq!(response.status(), 304);
+    assert!(response.into_body().is_none());
+    let mut request = HttpRequest::new("GET", "/hello%20world.txt");
+    request.header("If-Modified-Since", "Fri, 01 Nov 2999 00:00:00 GMT");
+    assert_eq!(view.serve_http(&request).unwrap().status(), 304);
+    request.header(
+        "If-Modified-Since",
+        "Mon, 01 Jan 99999999999999 00:00:00 GMT",
+    );
+    assert_eq!(view.serve_http(&request).unwrap().status(), 200);
+
+    // Directories serve their index, after a redirect.
+    let response = view.serve_http(&HttpRequest::new("GET", "/docs")).unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.header("Location"), Some("/docs/"));
+    let response = view
+        .serve_http(&HttpRequest::new("GET", "//docs?x=1"))
+        .unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.header("Location"), Some("/docs/?x=1"));
+    let response = view
+        .serve_http(&HttpRequest::new("HEAD", "/docs/"))
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("Content-Length"), Some("11"));
+    assert!(response.into_body().is_none());
+
+    // Escapes are rejected.
+    for target in [
+        "/../secret",
+        "/docs/%2e%2e/%2e%2e/secret",
+        "/docs%2f..%2f..%2fsecret",
+        "secret",
+    ] {
+        let response = view.serve_http(&HttpRequest::new("GET", target)).unwrap();
+        assert_eq!(response.status(), 400, "{target}");
+    }
+    let response = view
+        .serve_http(&HttpRequest::new("GET", "/missing"))
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let response = view.serve_http(&HttpRequest::new("POST", "/")).unwrap();
+    assert_eq!(response.status(), 405);
+}
+
+#[test]
+fn write_tar() {
+    use dir_view::{CopySymlinks, TarOptions};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let long = "d".repeat(60);
+    temp_dir
+        .create_dir_all(format!("tree/sub/{long}/{long}"))
+        .unwrap();
+    temp_dir.write("tree/b", b"bee").unwrap();
+    temp_dir.write("tree/sub/a", b"a").unwrap();
+    temp_dir
+        .write(format!("tree/sub/{}", "f".repeat(120)), b"pax")
+        .unwrap();
+    temp_dir
+        .write(format!("tree/sub/{long}/{long}/{long}"), b"deep")
+        .unwrap();
+    #[cfg(unix)]
+    temp_dir.symlink("b", "tree/link").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    let mut archive = Vec::new();
+    view.write_tar("tree", &mut archive, &TarOptions::new())
+        .unwrap();
+    assert_eq!(archive.len() % 512, 0);
+    assert!(archive.ends_with(&[0; 1024]));
+
+    // Archiving the same tree produces the same archive.
+    let mut again = Vec::new();
+    view.write_tar("tree", &mut again, &TarOptions::new())
+        .unwrap();
+    assert_eq!(archive, again);
+
+    // Entries are sorted, and named relative to the archived directory.
+    let name = |header: &[u8]| {
+        let name = &header[..100];
+        String::from_utf8(name[..name.iter().position(|b| *b == 0).unwrap_or(100)].to_vec())
+            .unwrap()
+    };
+    assert_eq!(name(&archive[..512]), "b");
+    assert_eq!(&archive[257..263], b"ustar\0");
+    assert_eq!(&archive[512..515], b"bee");
+    #[cfg(unix)]
+    {
+        assert_eq!(name(&archive[1024..]), "link");
+        assert_eq!(archive[1024 + 156], b'2');
+        assert_eq!(name(&archive[1024 + 157..]), "b");
+    }
+
+    // Symbolic links can be left out.
+    #[cfg(unix)]
+    {
+        let mut skipped = Vec::new();
+        view.write_tar(
+            "tree",
+            &mut skipped,
+            TarOptions::new().symlinks(CopySymlinks::Skip),
+        )
+        .unwrap();
+        assert_eq!(skipped.len(), archive.len() - 512);
+    }
+}
+
+#[test]
+fn write_zip() {
+    use dir_view::{ZipCompression, ZipOptions};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/sub").unwrap();
+    temp_dir.write("tree/b", b"bee").unwrap();
+    temp_dir
+        .write("tree/sub/repeat", "all work and no play ".repeat(1000))
+        .unwrap();
+    temp_dir.write("tree/sub/empty", b"").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+
+    let mut archive = Vec::new();
+    view.write_zip("tree", &mut archive, &ZipOptions::new())
+        .unwrap();
+
+    // Archiving the same tree produces the same archive.
+    let mut again = Vec::new();
+    view.write_zip("tree", &mut again, &ZipOptions::new())
+        .unwrap();
+    assert_eq!(archive, again);
+
+    // Entries are sorted, and named relative to the archived directory.
+    assert_eq!(&archive[..4], b"PK\x03\x04");
+    assert_eq!(&archive[30..31], b"b");
+    let end = &archive[archive.len() - 22..];
+    assert_eq!(&end[..4], b"PK\x05\x06");
+    assert_eq!(u16::from_le_bytes([end[10], end[11]]), 4);
+
+    // Stored files are kept as they are, and deflated ones are smaller.
+    let mut stored = Vec::new();
+    view.write_zip(
+        "tree",
+        &mut stored,
+        ZipOptions::new().compression(ZipCompression::Stored),
+    )
+    .unwrap();
+    assert_eq!(&stored[31..34], b"bee");
+    assert!(archive.len() < stored.len() / 10);
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn mem_dir_view() {
+    use dir_view::{FileTypeSummary, MemDirView, ViewError};
+    use std::io::{ErrorKind, Seek, SeekFrom, Write};
+
+    let view = MemDirView::new();
+    view.create_dir_all("a/b").unwrap();
+    view.write("a/b/file", b"hello").unwrap();
+    view.symlink("b/file", "a/link").unwrap();
+    view.symlink("../../..", "a/escape").unwrap();
+    assert_eq!(view.read_to_string("a/link").unwrap(), "hello");
+    assert_eq!(
+        view.read_link("a/link").unwrap(),
+        std::path::Path::new("b/file")
+    );
+    assert_eq!(
+        view.symlink_metadata("a/link").unwrap().file_type(),
+        FileTypeSummary::Symlink
+    );
+    assert_eq!(view.metadata("a/link").unwrap().len(), 5);
+    assert_eq!(
+        view.canonicalize("a/b/../link").unwrap(),
+        std::path::Path::new("a/b/file")
+    );
+    assert_eq!(
+        view.create_dir("a").unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+
+    // Files can be written at any position.
+    let mut file = view.create("a/b/other").unwrap();
+    file.write_all(b"world").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.write_all(b"W").unwrap();
+    assert_eq!(view.read("a/b/other").unwrap(), b"World");
+
+    let names = view
+        .read_dir("a")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["b", "escape", "link"]);
+
+    // Paths can't lead outside of a view.
+    let sub = view.open_dir("a").unwrap();
+    assert_eq!(sub.read("b/file").unwrap(), b"hello");
+    assert!(ViewError::from(sub.read("../a/b/file").unwrap_err()).is_denied());
+    assert!(ViewError::from(view.read_dir("a/escape").unwrap_err()).is_denied());
+
+    // Modes are enforced.
+    view.set_mode("a/b/file", 0o444).unwrap();
+    assert!(view.metadata("a/b/file").unwrap().readonly());
+    assert_eq!(
+        view.write("a/b/file", b"").unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    view.set_mode("a/b", 0o555).unwrap();
+    assert_eq!(
+        view.remove_file("a/b/other").unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    view.set_mode("a/b", 0o755).unwrap();
+
+    // Readonly views see changes, but can't make them.
+    let readonly = view.readonly();
+    assert!(ViewError::from(readonly.create_dir("new").unwrap_err()).is_denied());
+    assert!(ViewError::from(readonly.remove_file("a/b/other").unwrap_err()).is_denied());
+    view.rename("a/b/other", "moved").unwrap();
+    assert_eq!(readonly.read("moved").unwrap(), b"World");
+    assert!(!readonly.exists("a/b/other"));
+
+    assert_eq!(
+        view.remove_dir("a").unwrap_err().kind(),
+        ErrorKind::DirectoryNotEmpty
+    );
+    view.remove_dir_all("a").unwrap();
+    assert_eq!(sub.metadata(".").unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(view.entries().unwrap().count(), 1);
+}
+
+#[test]
+fn open_tar() {
+    use dir_view::{FileTypeSummary, TarOptions, ViewError};
+    use std::io::{Read, Seek, SeekFrom};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/sub").unwrap();
+    temp_dir.write("tree/b", b"bee").unwrap();
+    temp_dir.write("tree/sub/a", b"hello world").unwrap();
+    let long = "f".repeat(120);
+    temp_dir.write(format!("tree/sub/{long}"), b"pax").unwrap();
+    #[cfg(unix)]
+    {
+        temp_dir.symlink("../b", "tree/sub/link").unwrap();
+        temp_dir.symlink("../..", "tree/sub/escape").unwrap();
+    }
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let mut archive = view.create("tree.tar").unwrap();
+    view.write_tar("tree", &mut archive, &TarOptions::new())
+        .unwrap();
+    drop(archive);
+
+    let archive = view.open_tar("tree.tar").unwrap();
+    assert_eq!(archive.read("b").unwrap(), b"bee");
+    assert_eq!(archive.read_to_string("sub/a").unwrap(), "hello world");
+    assert_eq!(archive.read(format!("sub/{long}")).unwrap(), b"pax");
+    assert_eq!(archive.metadata("sub/a").unwrap().len(), 11);
+    assert!(archive.is_dir("sub"));
+    assert!(archive.is_file("./sub/../b"));
+    assert!(!archive.exists("missing"));
+    assert_eq!(
+        archive.open("sub").unwrap_err().kind(),
+        std::io::ErrorKind::IsADirectory
+    );
+
+    let names = archive
+        .read_dir("sub")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    #[cfg(unix)]
+    assert_eq!(names, ["a", "escape", long.as_str(), "link"]);
+    #[cfg(not(unix))]
+    assert_eq!(names, ["a", long.as_str()]);
+
+    let mut file = archive.open_dir("sub").unwrap().open("a").unwrap();
+    file.seek(SeekFrom::Start(6)).unwrap();
+    let mut rest = String::new();
+    file.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "world");
+
+    // Paths can't lead outside of the archive.
+    let error = ViewError::from(
+        archive
+            .open_dir("sub")
+            .unwrap()
+            .read("../../b")
+            .unwrap_err(),
+    );
+    assert!(error.is_denied());
+    #[cfg(unix)]
+    {
+        assert_eq!(archive.read("sub/link").unwrap(), b"bee");
+        assert_eq!(
+            archive.symlink_metadata("sub/link").unwrap().file_type(),
+            FileTypeSummary::Symlink
+        );
+        assert_eq!(
+            archive.read_link("sub/link").unwrap(),
+            std::path::Path::new("../b")
+        );
+        let error = ViewError::from(archive.read_dir("sub/escape").unwrap_err());
+        assert!(error.is_denied());
+    }
+}
+
+#[test]
+fn open_zip() {
+    use dir_view::{FileTypeSummary, ViewError, ZipCompression, ZipOptions};
+    use std::io::{Read, Seek, SeekFrom};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/sub").unwrap();
+    temp_dir.write("tree/b", b"bee").unwrap();
+    let repeat = "all work and no play ".repeat(1000);
+    temp_dir.write("tree/sub/repeat", &repeat).unwrap();
+    temp_dir.write("tree/sub/empty", b"").unwrap();
+    #[cfg(unix)]
+    temp_dir.symlink("../b", "tree/sub/link").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+
+    for compression in [ZipCompression::Deflated, ZipCompression::Stored] {
+        let mut archive = view.create("tree.zip").unwrap();
+        view.write_zip(
+            "tree",
+            &mut archive,
+            ZipOptions::new().compression(compression),
+        )
+        .unwrap();
+        drop(archive);
+
+        let archive = view.open_zip("tree.zip").unwrap();
+        assert_eq!(archive.read("b").unwrap(), b"bee");
+        assert_eq!(archive.read_to_string("sub/repeat").unwrap(), repeat);
+        assert_eq!(archive.read("sub/empty").unwrap(), b"");
+        assert_eq!(archive.metadata("sub/repeat").unwrap().len(), 21000);
+        assert!(archive.is_dir("sub"));
+
+        let names = archive
+            .read_dir("sub")
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        #[cfg(unix)]
+        assert_eq!(names, ["empty", "link", "repeat"]);
+        #[cfg(not(unix))]
+        assert_eq!(names, ["empty", "repeat"]);
+
+        // Entries can be read from any position.
+        let mut file = archive.open("sub/repeat").unwrap();
+        file.seek(SeekFrom::End(-5)).unwrap();
+        let mut end = String::new();
+        file.read_to_string(&mut end).unwrap();
+        assert_eq!(end, "play ");
+        file.seek(SeekFrom::Start(4)).unwrap();
+        let mut start = [0; 4];
+        file.read_exact(&mut start).unwrap();
+        assert_eq!(&start, b"work");
+
+        let error = ViewError::from(archive.read("../tree.zip").unwrap_err());
+        assert!(error.is_denied());
+        #[cfg(unix)]
+        {
+            assert_eq!(archive.read("sub/link").unwrap(), b"bee");
+            assert_eq!(
+                archive.symlink_metadata("sub/link").unwrap().file_type(),
+                FileTypeSummary::Symlink
+            );
+        }
+    }
+
+    // Deflated entries are checked against their recorded checksums once
+    // they've been read to the end.
+    let mut archive = Vec::new();
+    view.write_zip("tree", &mut archive, &ZipOptions::new())
+        .unwrap();
+    let record = (0..archive.len())
+        .filter(|i| archive[*i..].starts_with(b"PK\x01\x02"))
+        .find(|i| {
+            let name_len = usize::from(u16::from_le_bytes([archive[i + 28], archive[i + 29]]));
+            archive[i + 46..i + 46 + name_len].ends_with(b"sub/repeat")
+        })
+        .unwrap();
+    archive[record + 16] ^= 1;
+    temp_dir.write("corrupt.zip", &archive).unwrap();
+    let archive = view.open_zip("corrupt.zip").unwrap();
+    let mut file = archive.open("sub/repeat").unwrap();
+    let mut start = [0; 8];
+    file.read_exact(&mut start).unwrap();
+    assert_eq!(&start, b"all work");
+    let error = file.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn open_decompressed() {
+    use dir_view::ZipOptions;
+    use std::io::{self, Read};
+
+    // Two gzip members, as produced by concatenating gzip files.
+    const GZIP: &[u8] = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x02\x03\x4b\xcb\x2c\x2a\x2e\x51\
+        \xc8\xc9\xcc\x4b\xe5\x02\x00\x10\xfc\xef\xbd\x0b\x00\x00\x00\x1f\x8b\x08\x00\x00\x00\
+        \x00\x00\x02\x03\x2b\x4e\x4d\xce\xcf\x4b\x51\xc8\xc9\xcc\x4b\xe5\x2a\xc6\xc1\x06\x00\
+        \xe7\x7b\xd8\xe8\x24\x00\x00\x00";
+    // The same contents as two Zstandard frames, and as two xz streams.
+    const ZSTD: &[u8] = b"\x28\xb5\x2f\xfd\x24\x0b\x59\x00\x00\x66\x69\x72\x73\x74\x20\x6c\
+        \x69\x6e\x65\x0a\x9d\x96\x59\x36\x28\xb5\x2f\xfd\x24\x24\x95\x00\x00\x60\x73\x65\
+        \x63\x6f\x6e\x64\x20\x6c\x69\x6e\x65\x0a\x01\x00\x07\x4b\x12\xf5\x8e\x04\x5a";
+    const XZ: &[u8] = b"\xfd\x37\x7a\x58\x5a\x00\x00\x01\x69\x22\xde\x36\x04\xc0\x0f\x0b\
+        \x21\x01\x16\x00\x00\x00\x00\x00\x00\x00\x00\x00\xb9\x3e\x01\x65\x01\x00\x0a\x66\
+        \x69\x72\x73\x74\x20\x6c\x69\x6e\x65\x0a\x00\x00\x10\xfc\xef\xbd\x00\x01\x27\x0b\
+        \xc6\xde\x91\x6d\x90\x42\x99\x0d\x01\x00\x00\x00\x00\x01\x59\x5a\xfd\x37\x7a\x58\
+        \x5a\x00\x00\x01\x69\x22\xde\x36\x04\xc0\x1b\x24\x21\x01\x16\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x00\x0e\x13\x11\x8e\xe0\x00\x23\x00\x13\x5d\x00\x39\x99\x48\x91\xb1\
+        \x69\x96\x07\xd6\xdf\x9e\xff\x69\x4f\x64\xc0\xe2\x80\x00\x00\x00\xe7\x7b\xd8\xe8\
+        \x00\x01\x33\x24\xca\x34\xee\xe8\x90\x42\x99\x0d\x01\x00\x00\x00\x00\x01\x59\x5a";
+    let expected = format!("first line\n{}", "second line\n".repeat(3));
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("app.log.1.gz", GZIP).unwrap();
+    temp_dir.write("app.log.2", GZIP).unwrap();
+    temp_dir.write("app.log", b"plain\n").unwrap();
+    temp_dir.write("app.log.3.zst", ZSTD).unwrap();
+    temp_dir.write("app.log.4.xz", XZ).unwrap();
+    temp_dir.write("fake.gz", b"plain\n").unwrap();
+    let mut corrupt = GZIP.to_vec();
+    corrupt[24] ^= 1;
+    temp_dir.write("corrupt.gz", corrupt).unwrap();
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+
+    for path in ["app.log.1.gz", "app.log.2"] {
+        let mut reader = view.open_decompressed(path).unwrap();
+        assert!(reader.is_decompressed());
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, expected);
+    }
+
+    let mut reader = view.open_decompressed("app.log").unwrap();
+    assert!(!reader.is_decompressed());
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "plain\n");
+
+    // Zstandard and xz are only supported with their features.
+    for (path, contents, offset, supported) in [
+        ("app.log.3.zst", ZSTD, 10, cfg!(feature = "zstd")),
+        ("app.log.4.xz", XZ, 40, cfg!(feature = "xz")),
+    ] {
+        if !supported {
+            let error = view.open_decompressed(path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+            continue;
+        }
+        let mut reader = view.open_decompressed(path).unwrap();
+        assert!(reader.is_decompressed());
+        let mut decompressed = String::new();
+        reader.read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, expected);
+
+        // Corrupting the contents is found by their checksums.
+        let mut corrupt = contents.to_vec();
+        corrupt[offset] ^= 1;
+        temp_dir.write(path, corrupt).unwrap();
+        let mut reader = view.open_decompressed(path).unwrap();
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // Bad headers are found when files are opened, and corrupt data when
+    // it's read.
+    let error = view.open_decompressed("fake.gz").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    let mut reader = view.open_decompressed("corrupt.gz").unwrap();
+    let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    // Large files are decompressed as they're read. The deflated data is
+    // taken from a zip archive, which records the same checksum and length
+    // as gzip does, in the data descriptor after it.
+    let big = (0..10_000)
+        .map(|i| format!("line {i}\n"))
+        .collect::<String>();
+    temp_dir.create_dir("big").unwrap();
+    temp_dir.write("big/data", &big).unwrap();
+    let mut zip = Vec::new();
+    view.write_zip("big", &mut zip, &ZipOptions::new()).unwrap();
+    let start = 30
+        + usize::from(u16::from_le_bytes([zip[26], zip[27]]))
+        + usize::from(u16::from_le_bytes([zip[28], zip[29]]));
+    let end = start
+        + zip[start..]
+            .windows(4)
+            .position(|window| window == b"PK\x07\x08")
+            .unwrap();
+    let mut gzip = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03".to_vec();
+    gzip.extend_from_slice(&zip[start..end]);
+    gzip.extend_from_slice(&zip[end + 4..end + 8]);
+    gzip.extend_from_slice(&zip[end + 12..end + 16]);
+    temp_dir.write("big.gz", &gzip).unwrap();
+    let mut contents = String::new();
+    view.open_decompressed("big.gz")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, big);
+    let error = view.open_decompressed("../outside.gz").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn overlay_dir_view() {
+    use dir_view::{OverlayDirView, ViewError};
+    use std::io::{ErrorKind, Write};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("lower/a/b").unwrap();
+    temp_dir.create_dir("upper").unwrap();
+    temp_dir.write("lower/a/file", b"lower").unwrap();
+    temp_dir.write("lower/a/b/deep", b"deep").unwrap();
+    temp_dir.write("lower/gone", b"gone").unwrap();
+
+    let lower = DirView::from_dir(temp_dir.open_dir("lower").unwrap(), ViewKind::Readonly);
+    let upper = DirView::from_dir(temp_dir.open_dir("upper").unwrap(), ViewKind::Full);
+    let overlay = OverlayDirView::new(lower, upper);
+
+    // The merged tree starts out as the lower one.
+    assert_eq!(overlay.read_to_string("a/file").unwrap(), "lower");
+    assert!(overlay.is_dir("a/b"));
+    let names = |path| {
+        overlay
+            .read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("a"), ["b", "file"]);
+
+    // Writing copies up, leaving the lower layer unchanged.
+    overlay.write("a/file", b"upper").unwrap();
+    let mut file = overlay.open_append("a/file").unwrap();
+    file.write_all(b"!").unwrap();
+    assert_eq!(overlay.read_to_string("a/file").unwrap(), "upper!");
+    assert_eq!(temp_dir.read_to_string("lower/a/file").unwrap(), "lower");
+    assert_eq!(temp_dir.read_to_string("upper/a/file").unwrap(), "upper!");
+    assert!(overlay
+        .read_dir("a")
+        .unwrap()
+        .any(|entry| entry.unwrap().is_upper()));
+
+    // Removing entries leaves whiteouts, which are hidden.
+    overlay.remove_file("gone").unwrap();
+    assert!(!overlay.exists("gone"));
+    assert!(temp_dir.exists("lower/gone"));
+    assert!(temp_dir.exists("upper/.wh.gone"));
+    assert!(!names("").contains(&".wh.gone".to_owned()));
+    assert_eq!(
+        overlay.read(".wh.gone").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    assert!(ViewError::from(overlay.write("a/.wh.file", b"").unwrap_err()).is_denied());
+
+    // A directory recreated over a removed one doesn't show the old contents.
+    assert_eq!(
+        overlay.remove_dir("a/b").unwrap_err().kind(),
+        ErrorKind::DirectoryNotEmpty
+    );
+    overlay.remove_dir_all("a/b").unwrap();
+    assert!(!overlay.exists("a/b/deep"));
+    overlay.create_dir("a/b").unwrap();
+    assert!(names("a/b").is_empty());
+    overlay.write("gone", b"back").unwrap();
+    assert_eq!(overlay.read_to_string("gone").unwrap(), "back");
+
+    // Renaming moves whole directories up.
+    overlay.write("a/b/new", b"new").unwrap();
+    overlay.rename("a", "c").unwrap();
+    assert!(!overlay.exists("a"));
+    assert_eq!(names("c"), ["b", "file"]);
+    assert_eq!(overlay.read_to_string("c/b/new").unwrap(), "new");
+    assert_eq!(temp_dir.read_to_string("lower/a/b/deep").unwrap(), "deep");
+
+    // Paths can't lead outside of the overlay.
+    assert!(ViewError::from(overlay.read("../lower/gone").unwrap_err()).is_denied());
+}
+
+#[cfg(feature = "tempfile")]
+#[test]
+fn sandbox_view() {
+    use dir_view::{Change, SandboxView};
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("a/file", b"base").unwrap();
+    temp_dir.write("a/same", b"same").unwrap();
+    temp_dir.write("a/b/deep", b"deep").unwrap();
+    temp_dir.write("gone", b"gone").unwrap();
+    let base = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+
+    let sandbox = SandboxView::new(&base, ambient_authority()).unwrap();
+    assert!(sandbox.extract_changes().unwrap().is_empty());
+    sandbox.write("a/file", b"changed").unwrap();
+    sandbox.write("a/same", b"same").unwrap();
+    sandbox.create_dir_all("new/dir").unwrap();
+    sandbox.remove_file("gone").unwrap();
+    sandbox.remove_dir_all("a/b").unwrap();
+    sandbox.write("a/b", b"now a file").unwrap();
+    assert_eq!(
+        sandbox.extract_changes().unwrap(),
+        [
+            Change::Modified(PathBuf::from("a/b")),
+            Change::Modified(PathBuf::from("a/file")),
+            Change::Removed(PathBuf::from("gone")),
+            Change::Created(PathBuf::from("new")),
+        ]
+    );
+
+    // The base tree is untouched.
+    assert_eq!(temp_dir.read_to_string("a/file").unwrap(), "base");
+    assert!(temp_dir.exists("a/b/deep"));
+    assert!(!temp_dir.exists("new"));
+    sandbox.discard().unwrap();
+    assert_eq!(temp_dir.read_to_string("gone").unwrap(), "gone");
+}
+
+#[test]
+fn transaction() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("config", b"old").unwrap();
+    temp_dir.write("stale", b"stale").unwrap();
+    temp_dir.create_dir_all("old/dir").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let names = || {
+        let mut names = temp_dir
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    // Nothing changes until the transaction is committed.
+    let mut transaction = view.begin_transaction().unwrap();
+    transaction.write("config", b"new").unwrap();
+    transaction.create_dir("pkg").unwrap();
+    transaction.write("pkg/file", b"file").unwrap();
+    transaction.remove_file("stale").unwrap();
+    transaction.rename("old", "renamed").unwrap();
+    assert_eq!(transaction.len(), 5);
+    assert_eq!(temp_dir.read_to_string("config").unwrap(), "old");
+    transaction.commit().unwrap();
+    assert_eq!(temp_dir.read_to_string("config").unwrap(), "new");
+    assert_eq!(temp_dir.read_to_string("pkg/file").unwrap(), "file");
+    assert!(temp_dir.is_dir("renamed/dir"));
+    assert_eq!(names(), ["config", "pkg", "renamed"]);
+
+    // A failed change undoes the ones before it.
+    let mut transaction = view.begin_transaction().unwrap();
+    transaction.write("config", b"newer").unwrap();
+    transaction.remove_dir_all("pkg").unwrap();
+    transaction.remove_file("missing").unwrap();
+    assert_eq!(
+        transaction.commit().unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    assert_eq!(temp_dir.read_to_string("config").unwrap(), "new");
+    assert_eq!(temp_dir.read_to_string("pkg/file").unwrap(), "file");
+    assert_eq!(names(), ["config", "pkg", "renamed"]);
+
+    // Rolling back, or dropping, throws the changes away.
+    let mut transaction = view.begin_transaction().unwrap();
+    transaction.write("config", b"newer").unwrap();
+    transaction.rollback().unwrap();
+    let mut transaction = view.begin_transaction().unwrap();
+    transaction.remove_file("config").unwrap();
+    drop(transaction);
+    assert_eq!(temp_dir.read_to_string("config").unwrap(), "new");
+    assert_eq!(names(), ["config", "pkg", "renamed"]);
+
+    let readonly = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+    assert!(readonly.begin_transaction().is_err());
+}
+
+#[test]
+fn with_trash() {
+    use dir_view::ViewError;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("a/file", b"one").unwrap();
+    temp_dir.write("file", b"two").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full)
+        .with_trash(".trash")
+        .unwrap();
+
+    // Removed entries are moved into the trash, without colliding.
+    view.remove_file("file").unwrap();
+    view.open_dir("a").unwrap().remove_file("file").unwrap();
+    view.remove_dir_all("a/b").unwrap();
+    assert!(!view.exists("file"));
+    assert_eq!(temp_dir.read_to_string(".trash/file").unwrap(), "two");
+    assert_eq!(temp_dir.read_to_string(".trash/file.1").unwrap(), "one");
+    assert!(temp_dir.is_dir(".trash/b"));
+    assert_eq!(
+        view.remove_file("a").unwrap_err().kind(),
+        std::io::ErrorKind::IsADirectory
+    );
+
+    // Entries in the trash are deleted, as is everything on a purge.
+    view.remove_file(".trash/file.1").unwrap();
+    assert!(!temp_dir.exists(".trash/file.1"));
+    view.purge_trash().unwrap();
+    assert_eq!(temp_dir.read_dir(".trash").unwrap().count(), 0);
+
+    // Readonly views can't have a trash.
+    let readonly = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+    assert!(ViewError::from(readonly.with_trash(".trash").unwrap_err()).is_denied());
+}
+
+#[test]
+fn write_versioned() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+
+    for version in 1..=4 {
+        view.write_versioned("config", version.to_string(), 2)
+            .unwrap();
+    }
+    assert_eq!(view.read_to_string("config").unwrap(), "4");
+    assert_eq!(view.read_to_string("config.~1~").unwrap(), "3");
+    assert_eq!(view.read_to_string("config.~2~").unwrap(), "2");
+    assert!(!view.exists("config.~3~"));
+
+    // Lowering the number kept prunes the older backups.
+    view.write_versioned("config", b"5", 1).unwrap();
+    assert_eq!(view.read_to_string("config.~1~").unwrap(), "4");
+    assert!(!view.exists("config.~2~"));
+    view.write_versioned("config", b"6", 0).unwrap();
+    assert_eq!(view.read_to_string("config").unwrap(), "6");
+    assert_eq!(temp_dir.entries().unwrap().count(), 1);
+
+    // Readers always find the file, with either its old or new contents,
+    // while backups are rotated.
+    let done = AtomicBool::new(false);
+    let unexpected = thread::scope(|scope| {
+        let reader = scope.spawn(|| {
+            let mut unexpected = Vec::new();
+            while !done.load(Ordering::Relaxed) {
+                match temp_dir.read_to_string("config") {
+                    Ok(contents) if contents.parse::<u32>().is_ok() => {}
+                    other => unexpected.push(format!("{other:?}")),
+                }
+            }
+            unexpected
+        });
+        for version in 7..200 {
+            view.write_versioned("config", version.to_string(), 2)
+                .unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap()
+    });
+    assert!(unexpected.is_empty(), "{:?}", unexpected.first());
+    assert_eq!(view.read_to_string("config.~2~").unwrap(), "197");
+
+    let readonly = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Readonly);
+    assert!(readonly.write_versioned("config", b"7", 1).is_err());
+}
+
+#[test]
+fn snapshot_manifest() {
+    use dir_view::{FileTypeSummary, SnapshotOptions};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tree/sub").unwrap();
+    temp_dir.write("tree/file", b"hello").unwrap();
+    temp_dir.write("tree/sub/gone", b"gone").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+
+    let before = view
+        .snapshot_manifest_with("tree", SnapshotOptions::new().hash(true))
+        .unwrap();
+    let paths = before
+        .entries()
+        .iter()
+        .map(|entry| entry.path().to_str().unwrap().replace('\\', "/"))
+        .collect::<Vec<_>>();
+    assert_eq!(paths, ["file", "sub", "sub/gone"]);
+    let file = before.get("file").unwrap();
+    assert_eq!(file.file_type(), FileTypeSummary::File);
+    assert_eq!(file.len(), 5);
+    assert_eq!(
+        file.sha256(),
+        Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+    );
+    assert_eq!(before.get("sub").unwrap().sha256(), None);
+    assert!(before.compare(&before).is_empty());
+
+    // Saved manifests round-trip.
+    #[cfg(feature = "serde")]
+    {
+        let saved = toml::to_string(&before).unwrap();
+        let loaded: dir_view::SnapshotManifest = toml::from_str(&saved).unwrap();
+        assert_eq!(loaded, before);
+    }
+
+    temp_dir.write("tree/file", b"HELLO").unwrap();
+    temp_dir.remove_file("tree/sub/gone").unwrap();
+    temp_dir.write("tree/new", b"new").unwrap();
+    let after = view
+        .snapshot_manifest_with("tree", SnapshotOptions::new().hash(true))
+        .unwrap();
+    let diff = before.compare(&after);
+    assert_eq!(diff.added(), [std::path::PathBuf::from("new")]);
+    assert_eq!(diff.removed(), [std::path::Path::new("sub").join("gone")]);
+    assert_eq!(diff.modified(), [std::path::PathBuf::from("file")]);
+    assert!(diff.type_changed().is_empty());
+}
+
+#[test]
+fn rename_noreplace() {
+    use std::io::ErrorKind;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("queue/done").unwrap();
+    temp_dir.write("queue/job", b"job").unwrap();
+    temp_dir.write("queue/done/job", b"old").unwrap();
+    temp_dir.create_dir("queue/dir").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir("queue").unwrap(), ViewKind::Full);
+
+    assert_eq!(
+        view.rename_noreplace("job", &view, "done/job")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::AlreadyExists
+    );
+    assert_eq!(temp_dir.read_to_string("queue/done/job").unwrap(), "old");
+    assert!(view.exists("job"));
+    view.rename_noreplace("job", &view, "done/job2").unwrap();
+    assert!(!view.exists("job"));
+    assert_eq!(view.read_to_string("done/job2").unwrap(), "job");
+
+    // Directories aren't replaced either, even when empty.
+    view.create_dir("empty").unwrap();
+    assert_eq!(
+        view.rename_noreplace("dir", &view, "empty")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::AlreadyExists
+    );
+    view.rename_noreplace("dir", &view, "done/dir").unwrap();
+    assert!(view.is_dir("done/dir"));
+
+    let readonly = DirView::from_dir(temp_dir.open_dir("queue").unwrap(), ViewKind::Readonly);
+    assert!(readonly
+        .rename_noreplace("done/job2", &view, "job")
+        .is_err());
+    assert!(view
+        .rename_noreplace("done/job2", &readonly, "job")
+        .is_err());
+}
+
+#[test]
+fn exchange() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("site/live").unwrap();
+    temp_dir.create_dir_all("staging/next").unwrap();
+    temp_dir.write("site/live/index", b"blue").unwrap();
+    temp_dir.write("staging/next/index", b"green").unwrap();
+    temp_dir.write("staging/file", b"file").unwrap();
+    let site = DirView::from_dir(temp_dir.open_dir("site").unwrap(), ViewKind::Full);
+    let staging = DirView::from_dir(temp_dir.open_dir("staging").unwrap(), ViewKind::Full);
+
+    site.exchange("live", &staging, "next").unwrap();
+    assert_eq!(site.read_to_string("live/index").unwrap(), "green");
+    assert_eq!(staging.read_to_string("next/index").unwrap(), "blue");
+
+    // Entries of different types can be exchanged, but both must exist.
+    site.exchange("live", &staging, "file").unwrap();
+    assert_eq!(site.read_to_string("live").unwrap(), "file");
+    assert!(staging.is_dir("file"));
+    assert_eq!(
+        site.exchange("live", &staging, "missing")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::NotFound
+    );
+
+    let readonly = DirView::from_dir(temp_dir.open_dir("staging").unwrap(), ViewKind::Readonly);
+    assert!(site.exchange("live", &readonly, "next").is_err());
+    assert_eq!(site.read_to_string("live").unwrap(), "file");
+}
+
+#[cfg(windows)]
+#[test]
+fn junctions() {
+    use dir_view::{DirViewBuilder, ViewError};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.create_dir("target").unwrap();
+    view.write("target/file", "hello").unwrap();
+    view.write("plain", "").unwrap();
+
+    view.create_junction("target", "link").unwrap();
+    assert!(view.is_junction("link").unwrap());
+    assert!(view.reparse_tag("link").unwrap().is_some());
+    assert!(!view.is_junction("target").unwrap());
+    assert_eq!(view.reparse_tag("plain").unwrap(), None);
+    // Junctions record absolute paths, which views don't follow.
+    assert!(view.read("link/file").is_err());
+    assert!(view.create_junction("plain", "link2").is_err());
+
+    let denying = DirViewBuilder::new()
+        .readonly()
+        .deny_reparse_points()
+        .build(&*temp_dir);
+    let err = denying.open("link/file").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+    assert_eq!(denying.read_to_string("target/file").unwrap(), "hello");
+
+    let readonly = DirView::readonly(&*temp_dir);
+    let err = readonly.create_junction("target", "link3").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+}
+
+#[test]
+fn portable_names() {
+    use dir_view::{DirViewBuilder, ViewError};
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirViewBuilder::new().portable_names().build(&*temp_dir);
+
+    view.create_dir("dir").unwrap();
+    view.write("dir/file.txt", "hello").unwrap();
+    view.write("dir/CONSOLE", "").unwrap();
+    view.write("dir/com10", "").unwrap();
+    assert_eq!(
+        view.read_to_string("./dir/../dir/file.txt").unwrap(),
+        "hello"
+    );
+
+    for name in [
+        "CON",
+        "nul.txt",
+        "Aux",
+        "com1",
+        "LPT9.log",
+        "prn .txt",
+        "trailing.",
+        "trailing ",
+        "a:b",
+        "a*b",
+        "a\\b",
+    ] {
+        let err = view.write(Path::new("dir").join(name), "").unwrap_err();
+        assert!(ViewError::from(err).is_denied(), "{}", name);
+    }
+    let err = view.read("NUL").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+    let err = view.rename("dir/file.txt", &view, "dir/con").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+    let err = view.write("x".repeat(256), "").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+    assert!(view.exists("dir/file.txt"));
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        let temp_dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+        let view = DirViewBuilder::new().portable_names().build_utf8(&temp_dir);
+        assert_eq!(view.read_to_string("dir/file.txt").unwrap(), "hello");
+        let err = view.create_dir("dir/aux").unwrap_err();
+        assert!(ViewError::from(err).is_denied());
+    }
+}
+
+#[test]
+fn metadata_ext() {
+    use dir_view::{FieldMask, FileTypeSummary};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write("file", "hello").unwrap();
+    view.create_dir("dir").unwrap();
+
+    let ext = view
+        .metadata_ext("file", FieldMask::SIZE | FieldMask::TYPE)
+        .unwrap();
+    assert_eq!(ext.len(), Some(5));
+    assert_eq!(ext.file_type(), Some(FileTypeSummary::File));
+    assert_eq!(ext.modified(), None);
+    assert_eq!(ext.nlink(), None);
+
+    let metadata = view.metadata("file").unwrap();
+    let ext = view.metadata_ext("file", FieldMask::ALL).unwrap();
+    assert_eq!(
+        ext.modified(),
+        Some(metadata.modified().unwrap().into_std())
+    );
+    #[cfg(unix)]
+    {
+        use cap_std::fs::MetadataExt;
+
+        assert_eq!(ext.ino(), Some(metadata.ino()));
+        assert_eq!(ext.nlink(), Some(1));
+        assert_eq!(ext.dev(), Some(metadata.dev()));
+    }
+    if let Some(created) = ext.created() {
+        assert!(created <= ext.modified().unwrap());
+    }
+
+    let ext = view.metadata_ext("dir", FieldMask::TYPE).unwrap();
+    assert_eq!(ext.file_type(), Some(FileTypeSummary::Dir));
+    assert!(view.metadata_ext("missing", FieldMask::ALL).is_err());
+    assert!(view.metadata_ext("..", FieldMask::ALL).is_err());
+    assert!(view.metadata_ext("/", FieldMask::ALL).is_err());
+}
+
+#[test]
+fn file_extents() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+
+    let len = 16 << 20;
+    let mut file = view.create("sparse").unwrap();
+    file.set_len(len).unwrap();
+    file.seek(SeekFrom::Start(8 << 20)).unwrap();
+    file.write_all(b"data").unwrap();
+    drop(file);
+    view.write("empty", "").unwrap();
+
+    let extents = view.file_extents("sparse").unwrap();
+    assert!(!extents.is_empty());
+    assert!(extents
+        .iter()
+        .any(|extent| extent.offset() <= 8 << 20 && extent.end() >= (8 << 20) + 4));
+    assert!(extents.windows(2).all(|w| w[0].end() <= w[1].offset()));
+    assert!(extents.iter().all(|extent| extent.end() <= len));
+    assert!(view.file_extents("empty").unwrap().is_empty());
+
+    assert_eq!(view.copy("sparse", &view, "copy").unwrap(), len);
+    assert_eq!(view.read("copy").unwrap(), view.read("sparse").unwrap());
+    // If the filesystem supports holes, the copy keeps them.
+    let data = |path| {
+        view.file_extents(path)
+            .unwrap()
+            .iter()
+            .map(|extent| extent.len())
+            .sum::<u64>()
+    };
+    if data("sparse") < len {
+        assert!(data("copy") < len);
+    }
+}
+
+#[test]
+fn allocate() {
+    use dir_view::ViewError;
+    use std::io::ErrorKind;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write("file", "hello").unwrap();
+
+    match view.allocate("file", 0, 1 << 20) {
+        Ok(()) => {
+            assert_eq!(view.metadata("file").unwrap().len(), 1 << 20);
+            assert_eq!(&view.read("file").unwrap()[..5], b"hello");
+            // Allocating within the file doesn't change its size.
+            view.allocate("file", 4096, 4096).unwrap();
+            assert_eq!(view.metadata("file").unwrap().len(), 1 << 20);
+        }
+        Err(err) => assert_eq!(err.kind(), ErrorKind::Unsupported),
+    }
+    assert_eq!(
+        view.allocate("missing", 0, 1).unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+
+    let readonly = DirView::readonly(&*temp_dir);
+    let err = readonly.allocate("file", 0, 1 << 21).unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+}
+
+#[test]
+fn advise() {
+    use dir_view::Advice;
+    use std::io::Read;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    DirView::full(&*temp_dir)
+        .write("file", vec![7; 1 << 16])
+        .unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    view.advise("file", 0, 0, Advice::WillNeed).unwrap();
+    let mut file = view.open_readonly("file").unwrap();
+    file.advise(0, 0, Advice::Sequential).unwrap();
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents.len(), 1 << 16);
+    file.advise(4096, 4096, Advice::Random).unwrap();
+    view.advise("file", 0, 0, Advice::DontNeed).unwrap();
+    assert!(view.advise("missing", 0, 0, Advice::Normal).is_err());
+}
+
+#[test]
+fn file_flags() {
+    use dir_view::ViewError;
+    use std::io::ErrorKind;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write("file", "hello").unwrap();
+
+    let mut flags = match view.file_flags("file") {
+        Ok(flags) => flags,
+        Err(err) => {
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+            return;
+        }
+    };
+    assert!(!flags.immutable());
+    assert!(!flags.append_only());
+
+    // Setting `immutable` may need privileges.
+    flags.set_immutable(true);
+    if view.set_file_flags("file", flags).is_ok() {
+        assert!(view.file_flags("file").unwrap().immutable());
+        assert!(view.write("file", "changed").is_err());
+        assert!(view.remove_file("file").is_err());
+        flags.set_immutable(false);
+        view.set_file_flags("file", flags).unwrap();
+        assert!(!view.file_flags("file").unwrap().immutable());
+        view.write("file", "changed").unwrap();
+    }
+
+    let readonly = DirView::readonly(&*temp_dir);
+    assert_eq!(readonly.file_flags("file").unwrap(), flags);
+    let err = readonly.set_file_flags("file", flags).unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+}
+
+#[test]
+fn dir_entry_names() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write("hello.txt", "hello").unwrap();
+
+    let entry = view.read_dir(".").unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.file_name_utf8().unwrap(), "hello.txt");
+    assert_eq!(entry.file_name_lossy(), "hello.txt");
+    #[cfg(feature = "fs_utf8")]
+    {
+        let entry = entry.into_utf8().unwrap();
+        assert_eq!(entry.file_name().unwrap(), "hello.txt");
+        assert_eq!(entry.open().unwrap().metadata().unwrap().len(), 5);
+    }
+    view.remove_file("hello.txt").unwrap();
+
+    // Linux filesystems accept names which aren't UTF-8.
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        view.write(OsStr::from_bytes(b"bad\xff"), "bad").unwrap();
+        let entry = view.read_dir(".").unwrap().next().unwrap().unwrap();
+        assert_eq!(
+            entry.file_name_utf8().unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        assert_eq!(entry.file_name_lossy(), "bad\u{fffd}");
+        #[cfg(feature = "fs_utf8")]
+        {
+            let entry = entry.into_utf8().unwrap_err();
+            assert_eq!(entry.file_name(), OsStr::from_bytes(b"bad\xff"));
+        }
+    }
+}
+
+#[test]
+fn dir_entry_is_hidden() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write(".hidden", "hidden").unwrap();
+    view.write("visible", "visible").unwrap();
+
+    let mut hidden = view
+        .read_dir(".")
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (entry.file_name(), entry.is_hidden().unwrap())
+        })
+        .collect::<Vec<_>>();
+    hidden.sort();
+    // On Windows, only the hidden attribute counts.
+    assert_eq!(
+        hidden,
+        [
+            (".hidden".into(), cfg!(not(windows))),
+            ("visible".into(), false)
+        ]
+    );
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+        let view = DirViewUtf8::full(&dir);
+        for entry in view.read_dir(".").unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().unwrap();
+            assert_eq!(
+                entry.is_hidden().unwrap(),
+                cfg!(not(windows)) && name == ".hidden"
+            );
+        }
+    }
+}
+
+#[test]
+fn dir_entry_relative_path() {
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.create_dir_all("a/b").unwrap();
+    view.write("a/b/file", "hello").unwrap();
+
+    let entry = view.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.relative_path(), Path::new("a"));
+    let entry = view.read_dir("a").unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.relative_path(), Path::new("a/b"));
+    let entry = view.read_dir("a/b").unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.relative_path(), Path::new("a/b/file"));
+
+    // Entries of an opened subdirectory are relative to it.
+    let sub = view.open_dir("a").unwrap();
+    let entry = sub.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.relative_path(), Path::new("b"));
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        let entry = view.read_dir("a/b").unwrap().next().unwrap().unwrap();
+        let entry = entry.into_utf8().unwrap();
+        assert_eq!(entry.relative_path().unwrap(), "a/b/file");
+
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+        let view = DirViewUtf8::full(&dir);
+        let entry = view.read_dir("a").unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.relative_path().unwrap(), "a/b");
+    }
+}
+
+#[test]
+fn dir_entry_remove() {
+    use dir_view::ViewError;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write("file", "hello").unwrap();
+    view.create_dir("empty").unwrap();
+    view.create_dir_all("full/sub").unwrap();
+    view.write("full/sub/file", "hello").unwrap();
+
+    // A read-only view can't remove anything.
+    let readonly = DirView::readonly(&*temp_dir);
+    for entry in readonly.entries().unwrap() {
+        let entry = entry.unwrap();
+        assert!(ViewError::from(entry.remove().unwrap_err()).is_denied());
+        assert!(ViewError::from(entry.remove_all().unwrap_err()).is_denied());
+    }
+
+    for entry in view.entries().unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_name() == "full" {
+            assert!(entry.remove().is_err());
+            entry.remove_all().unwrap();
+        } else {
+            entry.remove().unwrap();
+        }
+    }
+    assert_eq!(view.entries().unwrap().count(), 0);
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        view.write("file", "hello").unwrap();
+        view.create_dir_all("full/sub").unwrap();
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+        let view = DirViewUtf8::full(&dir);
+        for entry in view.entries().unwrap() {
+            entry.unwrap().remove_all().unwrap();
+        }
+        assert_eq!(view.entries().unwrap().count(), 0);
+    }
+}
+
+#[test]
+fn dir_entry_cached_metadata() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    view.write("file", "hello").unwrap();
+
+    let entry = view.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.metadata().unwrap().len(), 5);
+    view.write("file", "hello, world").unwrap();
+
+    // The first metadata read is cached on the entry.
+    assert_eq!(entry.metadata().unwrap().len(), 5);
+    assert!(entry.file_type().unwrap().is_file());
+    assert_eq!(entry.fresh_metadata().unwrap().len(), 12);
+
+    // Metadata read while iterating is cached too.
+    let (entry, metadata) = view
+        .entries_with_metadata()
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(metadata.len(), 12);
+    view.write("file", "hi").unwrap();
+    assert_eq!(entry.metadata().unwrap().len(), 12);
+    assert_eq!(entry.fresh_metadata().unwrap().len(), 2);
+}
+
+#[test]
+fn read_dir_collect_sorted() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    for name in ["c", "a", "b"] {
+        view.write(name, name).unwrap();
+    }
+
+    let (entries, errors) = view.entries().unwrap().collect_sorted();
+    assert!(errors.is_empty());
+    let names = entries
+        .iter()
+        .map(|entry| entry.file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "b", "c"]);
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        view.write(OsStr::from_bytes(b"\xff"), "bad").unwrap();
+        let (entries, errors) = view.entries().unwrap().collect_sorted();
+        assert!(errors.is_empty());
+        assert_eq!(entries.len(), 4);
+
+        // Names which aren't UTF-8 are reported separately.
+        #[cfg(feature = "fs_utf8")]
+        {
+            let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+            let view = DirViewUtf8::full(&dir);
+            let (entries, errors) = view.entries().unwrap().collect_sorted();
+            let names = entries
+                .iter()
+                .map(|entry| entry.file_name().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["a", "b", "c"]);
+            assert_eq!(errors.len(), 1);
+        }
+    }
+}
+
+#[test]
+fn try_remove_open_dir() {
+    use dir_view::ViewError;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let full = DirView::full(&*temp_dir);
+    full.create_dir_all("dir/sub").unwrap();
+
+    // A denied removal hands the view back.
+    let readonly = DirView::from_dir(temp_dir.open_dir("dir").unwrap(), ViewKind::Readonly);
+    let (readonly, err) = readonly.try_remove_open_dir_all().unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+    assert!(readonly.exists("sub"));
+
+    // So does a failed one.
+    let view = DirView::from_dir(temp_dir.open_dir("dir").unwrap(), ViewKind::Full);
+    let (view, _err) = view.try_remove_open_dir().unwrap_err();
+    assert!(view.exists("sub"));
+
+    view.try_remove_open_dir_all().unwrap();
+    assert!(!full.exists("dir"));
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        full.create_dir("dir").unwrap();
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.open_dir("dir").unwrap());
+        let readonly = DirViewUtf8::from_dir(dir, ViewKind::Readonly);
+        let (readonly, err) = readonly.try_remove_open_dir().unwrap_err();
+        assert!(ViewError::from(err).is_denied());
+        assert!(readonly.entries().is_ok());
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.open_dir("dir").unwrap());
+        DirViewUtf8::from_dir(dir, ViewKind::Full)
+            .try_remove_open_dir()
+            .unwrap();
+        assert!(!full.exists("dir"));
+    }
+}
+
+#[cfg(not(windows))]
+#[test]
+fn confine_symlink_targets() {
+    use dir_view::{DirViewBuilder, ViewError};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirViewBuilder::new()
+        .confine_symlink_targets()
+        .build(&*temp_dir);
+    view.create_dir_all("a/b").unwrap();
+    view.write("file", "hello").unwrap();
+
+    view.symlink("file", "link").unwrap();
+    view.symlink("../../file", "a/b/link").unwrap();
+    view.symlink("b/../../a/./b", "a/link").unwrap();
+    assert_eq!(view.read_to_string("a/b/link").unwrap(), "hello");
+
+    for (original, link) in [
+        ("../file", "escape"),
+        ("../../../file", "a/b/escape"),
+        ("b/../../../file", "a/escape"),
+        ("/etc/passwd", "absolute"),
+    ] {
+        let err = view.symlink_contents(original, link).unwrap_err();
+        assert!(ViewError::from(err).is_denied(), "{}", original);
+    }
+
+    // The directory containing the link is located through symlinks, so a
+    // link behind one isn't deeper than it appears.
+    view.symlink("..", "a/up").unwrap();
+    let err = view.symlink("../file", "a/up/escape").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+
+    // Without the option, the targets aren't checked.
+    let view = DirView::full(&*temp_dir);
+    view.symlink_contents("../file", "escape").unwrap();
+}
+
+#[test]
+fn override_view_kind() {
+    use dir_view::{DirViewBuilder, ViewError};
+    use std::io::Write;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("tmp/keep").unwrap();
+    temp_dir.create_dir("src").unwrap();
+    temp_dir.write("src/main.rs", "fn main() {}").unwrap();
+    let view = DirViewBuilder::new()
+        .readonly()
+        .override_view_kind("tmp", ViewKind::Full)
+        .override_view_kind("tmp/keep", ViewKind::Readonly)
+        .build(&*temp_dir);
+
+    view.write("tmp/a", "hello").unwrap();
+    view.create_dir_all("tmp/b/c").unwrap();
+    view.remove_dir("tmp/b/c").unwrap();
+    assert!(ViewError::from(view.write("src/main.rs", "").unwrap_err()).is_denied());
+    assert!(ViewError::from(view.write("tmp/keep/a", "").unwrap_err()).is_denied());
+    assert!(ViewError::from(view.remove_dir("tmp").unwrap_err()).is_denied());
+    // Removing `tmp/b` is permitted, but removing `tmp` would remove
+    // `tmp/keep` too.
+    view.remove_dir_all("tmp/b").unwrap();
+    assert!(ViewError::from(view.remove_dir_all("tmp").unwrap_err()).is_denied());
+
+    // Writes are stripped from files opened outside of `tmp`.
+    let mut options = cap_std::fs::OpenOptions::new();
+    options.read(true).write(true);
+    view.open_with("tmp/a", &options)
+        .unwrap()
+        .write_all(b"!")
+        .unwrap();
+    assert!(view
+        .open_with("src/main.rs", &options)
+        .unwrap()
+        .write_all(b"!")
+        .is_err());
+
+    // Views and entries of `tmp` have its kind, and the views of `src`
+    // aren't widened.
+    let tmp = view.open_dir("tmp").unwrap();
+    tmp.write("d", "").unwrap();
+    assert!(ViewError::from(tmp.write("keep/d", "").unwrap_err()).is_denied());
+    view.open_dir_view("tmp", ViewKind::Full).unwrap();
+    assert!(ViewError::from(view.open_dir_view("src", ViewKind::Full).unwrap_err()).is_denied());
+    let narrowed = view.open_dir_view("tmp", ViewKind::Readonly).unwrap();
+    assert!(ViewError::from(narrowed.write("e", "").unwrap_err()).is_denied());
+    for entry in view.read_dir("tmp").unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_name() == "d" {
+            entry.remove().unwrap();
+        }
+    }
+    let entry = view
+        .entries()
+        .unwrap()
+        .map(Result::unwrap)
+        .find(|entry| entry.file_name() == "tmp")
+        .unwrap();
+    entry.open_dir().unwrap().write("f", "").unwrap();
+    assert!(ViewError::from(entry.remove_all().unwrap_err()).is_denied());
+
+    // Writing through a symbolic link in `tmp` is governed by its target too.
+    #[cfg(not(windows))]
+    {
+        view.symlink("../src/main.rs", "tmp/link").unwrap();
+        assert!(ViewError::from(view.write("tmp/link", "").unwrap_err()).is_denied());
+        view.remove_file("tmp/link").unwrap();
+    }
+    assert_eq!(
+        temp_dir.read_to_string("src/main.rs").unwrap(),
+        "fn main() {}"
+    );
+}
+
+#[test]
+fn mount_views() {
+    use dir_view::ViewError;
+
+    let root_dir = TempDir::new(ambient_authority()).unwrap();
+    let assets_dir = TempDir::new(ambient_authority()).unwrap();
+    root_dir.create_dir("src").unwrap();
+    root_dir.create_dir("assets").unwrap();
+    root_dir.write("assets/hidden", "root").unwrap();
+    root_dir.write("src/main.rs", "fn main() {}").unwrap();
+    assets_dir.create_dir("images").unwrap();
+    assets_dir.write("logo.svg", "<svg/>").unwrap();
+
+    let root = DirView::full(root_dir.try_clone().unwrap());
+    let assets = DirView::readonly(assets_dir.try_clone().unwrap());
+    let view = root.mount("assets", assets).unwrap();
+
+    assert_eq!(view.read_to_string("assets/logo.svg").unwrap(), "<svg/>");
+    assert_eq!(
+        view.read_to_string("src/../assets/./logo.svg").unwrap(),
+        "<svg/>"
+    );
+    assert_eq!(view.read_to_string("src/main.rs").unwrap(), "fn main() {}");
+    assert!(!view.exists("assets/hidden"));
+    assert!(view.is_dir("assets/images"));
+
+    // The mounted view keeps its own kind.
+    view.write("src/lib.rs", "").unwrap();
+    let err = view.write("assets/new", "").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+    let err = view.read("../escape").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+
+    let names = |entries: dir_view::MountReadDir| {
+        entries
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.file_name(), entry.is_mount_point())
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(view.entries().unwrap()),
+        [("assets".into(), true), ("src".into(), false)]
+    );
+    assert_eq!(
+        names(view.read_dir("assets").unwrap()),
+        [("images".into(), false), ("logo.svg".into(), false)]
+    );
+
+    // Mount points can't be removed or renamed, and entries can't be moved
+    // between views.
+    let err = view.remove_dir_all("assets").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ResourceBusy);
+    let err = view.rename("assets", "elsewhere").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ResourceBusy);
+    let err = view.rename("src/lib.rs", "assets/lib.rs").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::CrossesDevices);
+    view.rename("src/lib.rs", "lib.rs").unwrap();
+
+    // Mounts can be nested, and opening a directory keeps the mounts within
+    // it.
+    let scratch_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = view
+        .mount(
+            "assets/images/scratch",
+            DirView::full(scratch_dir.try_clone().unwrap()),
+        )
+        .unwrap();
+    view.write("assets/images/scratch/a", "a").unwrap();
+    assert_eq!(scratch_dir.read_to_string("a").unwrap(), "a");
+    let images = view.open_dir("assets/images").unwrap();
+    assert_eq!(images.read_to_string("scratch/a").unwrap(), "a");
+    assert_eq!(names(images.entries().unwrap()), [("scratch".into(), true)]);
+    assert!(view
+        .mount(
+            "missing/dir",
+            DirView::full(scratch_dir.try_clone().unwrap())
+        )
+        .is_err());
+}
+
+#[test]
+fn case_insensitive() {
+    use dir_view::{AuditEvent, DirViewBuilder, ViewError};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("Docs/Guide").unwrap();
+    temp_dir.write("Docs/ReadMe.TXT", "hello").unwrap();
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let hook_paths = Arc::clone(&paths);
+    let view = DirViewBuilder::new()
+        .case_insensitive()
+        .override_view_kind("Docs/Guide", ViewKind::Readonly)
+        .audit(move |event: &AuditEvent| {
+            hook_paths.lock().unwrap().push(event.path().to_path_buf())
+        })
+        .build(&*temp_dir);
+
+    assert_eq!(view.read_to_string("docs/readme.txt").unwrap(), "hello");
+    assert_eq!(
+        paths.lock().unwrap().last().unwrap(),
+        &PathBuf::from("Docs/ReadMe.TXT")
+    );
+
+    // Components which don't match anything keep their case.
+    view.write("DOCS/New.txt", "new").unwrap();
+    assert_eq!(temp_dir.read_to_string("Docs/New.txt").unwrap(), "new");
+    view.rename("docs/new.txt", &view, "DOCS/guide/../Renamed.txt")
+        .unwrap();
+    assert!(temp_dir.exists("Docs/Renamed.txt"));
+
+    // Restrictions apply to the matched path.
+    let err = view.write("docs/GUIDE/file", "").unwrap_err();
+    assert!(ViewError::from(err).is_denied());
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+        let view = DirViewBuilder::new().case_insensitive().build_utf8(&dir);
+        assert_eq!(view.read_to_string("DOCS/README.txt").unwrap(), "hello");
+    }
+
+    #[cfg(not(any(windows, target_vendor = "apple")))]
+    assert_eq!(
+        DirView::full(&*temp_dir)
+            .read("docs/readme.txt")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::NotFound
+    );
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalize_names() {
+    use dir_view::{DirViewBuilder, NormalizationForm};
+
+    // "café", decomposed as it's stored on macOS, and composed.
+    let nfd = "cafe\u{301}";
+    let nfc = "caf\u{e9}";
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir(nfd).unwrap();
+    temp_dir.write(format!("{nfd}/{nfd}.txt"), "hello").unwrap();
+    let view = DirViewBuilder::new()
+        .normalize_names(NormalizationForm::Nfc)
+        .build(&*temp_dir);
+
+    assert_eq!(
+        view.read_to_string(format!("{nfc}/{nfc}.txt")).unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        view.read_to_string(format!("{nfd}/{nfd}.txt")).unwrap(),
+        "hello"
+    );
+
+    // Names from enumeration are normalized, and can be used to open the
+    // entries they name.
+    let names = view
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, [nfc]);
+    let sub = view.open_dir(&names[0]).unwrap();
+    assert_eq!(
+        sub.read_dir(".")
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>(),
+        [format!("{nfc}.txt").as_str()]
+    );
+
+    // New entries are created with normalized names.
+    view.write(format!("{nfd}/new-{nfd}"), "new").unwrap();
+    assert!(temp_dir.exists(format!("{nfd}/new-{nfc}")));
+    assert!(!temp_dir.exists(format!("{nfd}/new-{nfd}")));
+
+    #[cfg(feature = "fs_utf8")]
+    {
+        let dir = cap_std::fs_utf8::Dir::from_cap_std(temp_dir.try_clone().unwrap());
+        let view = DirViewBuilder::new()
+            .normalize_names(NormalizationForm::Nfc)
+            .build_utf8(&dir);
+        assert_eq!(
+            view.read_to_string(format!("{nfc}/{nfc}.txt")).unwrap(),
+            "hello"
+        );
+        let names = view
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [nfc]);
+    }
+}
+
+#[test]
+fn strict_paths() {
+    use dir_view::{sanitize_path, DirViewBuilder, PathErrorKind, ViewError};
+    use std::path::Path;
+
+    let kind = |path: &str| sanitize_path(Path::new(path)).unwrap_err().kind();
+    assert_eq!(
+        sanitize_path(Path::new("./a/./b/")).unwrap(),
+        Path::new("a/b")
+    );
+    assert_eq!(sanitize_path(Path::new("")).unwrap(), Path::new("."));
+    assert_eq!(kind("/etc/passwd"), PathErrorKind::Absolute);
+    assert_eq!(kind("a/../b"), PathErrorKind::ParentDir);
+    assert_eq!(kind("a//b"), PathErrorKind::EmptyComponent);
+    assert_eq!(kind("a\0b"), PathErrorKind::Nul);
+    assert_eq!(kind("a/con.txt"), PathErrorKind::InvalidName);
+    assert_eq!(kind("a/b:c"), PathErrorKind::InvalidName);
+    let err = std::io::Error::from(sanitize_path(Path::new("../x")).unwrap_err());
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("a/b/file", "hello").unwrap();
+    let view = DirViewBuilder::new().strict_paths().build(&*temp_dir);
+    assert_eq!(view.read_to_string("./a/b/file").unwrap(), "hello");
+    for path in ["a/b/../b/file", "a//b/file", "a/b/aux"] {
+        let err = ViewError::from(view.read(path).unwrap_err());
+        assert!(err.is_denied(), "{path}");
+    }
+    // `..` within the view is fine without strict paths.
+    assert_eq!(
+        DirView::full(&*temp_dir)
+            .read_to_string("a/b/../b/file")
+            .unwrap(),
+        "hello"
+    );
+    // Symbolic link targets aren't paths in the view.
+    #[cfg(unix)]
+    view.symlink("../b/file", "a/b/link").unwrap();
+}
+
+#[test]
+fn path_limits() {
+    use dir_view::{DirViewBuilder, ViewError};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b/c").unwrap();
+    temp_dir.write("a/b/c/file", "hello").unwrap();
+    let view = DirViewBuilder::new()
+        .max_path_len(12)
+        .max_path_components(4)
+        .build(&*temp_dir);
+    assert_eq!(view.read_to_string("a/b/c/file").unwrap(), "hello");
+
+    let err = view.read("a/../a/b/c/file").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidFilename);
+    let err = ViewError::from(err);
+    assert!(err.is_denied());
+    assert!(err.is_limit_exceeded());
+    let err = ViewError::from(view.read("a/b/c/d/e").unwrap_err());
+    assert!(err.is_limit_exceeded());
+    let err = ViewError::from(view.read("a/b/c/nope").unwrap_err());
+    assert!(!err.is_limit_exceeded());
+
+    #[cfg(unix)]
+    {
+        temp_dir.symlink("a/b", "one").unwrap();
+        temp_dir.symlink("one/c", "two").unwrap();
+        let view = DirViewBuilder::new()
+            .max_symlink_traversals(1)
+            .build(&*temp_dir);
+        assert_eq!(view.read_to_string("one/c/file").unwrap(), "hello");
+        let err = ViewError::from(view.read("two/file").unwrap_err());
+        assert!(err.is_limit_exceeded());
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            err.kind(),
+            std::io::Error::from_raw_os_error(libc::ELOOP).kind()
+        );
+    }
+}
+
+#[test]
+fn rate_limit() {
+    use dir_view::{DirViewBuilder, RateLimiter};
+    use std::io::Read;
+    use std::time::{Duration, Instant};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.write("file", vec![0; 15_000]).unwrap();
+    let limiter = RateLimiter::new(Some(10_000), Some(10_000));
+    let view = DirViewBuilder::new().rate_limit(&limiter).build(&*temp_dir);
+
+    // The first second's worth of bytes is a burst, and the rest waits.
+    let start = Instant::now();
+    assert_eq!(view.read("file").unwrap().len(), 15_000);
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    // Clones of the limiter share it, and copies wait for writing too.
+    let other = DirViewBuilder::new().rate_limit(&limiter).build(&*temp_dir);
+    let start = Instant::now();
+    view.copy("file", &other, "copy").unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(1400));
+    assert_eq!(temp_dir.read("copy").unwrap().len(), 15_000);
+
+    // Handles can be throttled too.
+    let mut file = view.throttle(view.open("file").unwrap());
+    let start = Instant::now();
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(800));
+
+    // Views without a limit aren't throttled.
+    let start = Instant::now();
+    let mut file = DirView::full(&*temp_dir).throttle(temp_dir.open("file").unwrap());
+    file.read_to_end(&mut contents).unwrap();
+    assert!(start.elapsed() < Duration::from_millis(400));
+}
+
+#[test]
+fn handle_budget() {
+    use cap_std::fs::OpenOptions;
+    use dir_view::{DirViewBuilder, HandleBudget, ViewError};
+    use std::io::ErrorKind;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    temp_dir.write("file", b"contents").unwrap();
+    let budget = HandleBudget::new(2);
+    let view = DirViewBuilder::new()
+        .handle_budget(&budget)
+        .build(&*temp_dir);
+    assert_eq!(budget.open_handles(), 0);
+
+    // Subdirectory views and files opened with `open_budgeted` each take a
+    // handle, and the budget denies more than it permits.
+    let a = view.open_dir("a").unwrap();
+    let file = view
+        .open_budgeted("file", OpenOptions::new().read(true))
+        .unwrap();
+    assert_eq!(budget.open_handles(), 2);
+    let err = a.open_dir("b").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::QuotaExceeded);
+    assert!(ViewError::from(err).is_limit_exceeded());
+    assert!(view.entries().is_err());
+    assert!(view.try_clone().is_err());
+    assert_eq!(budget.open_handles(), 2);
+
+    // Dropping handles gives them back.
+    drop(file);
+    let entries = a.entries().unwrap();
+    assert_eq!(budget.open_handles(), 2);
+    drop(entries);
+    drop(a);
+    assert_eq!(budget.open_handles(), 0);
+
+    // Entries keep the enumeration's handle until they're dropped.
+    let entry = view.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(budget.open_handles(), 1);
+    drop(entry);
+    assert_eq!(budget.open_handles(), 0);
+
+    // Handles which couldn't count against the budget are denied, but
+    // operations which only use a file while they run aren't.
+    for err in [
+        view.open("file").unwrap_err(),
+        view.create("new").unwrap_err(),
+        view.open_with("file", OpenOptions::new().read(true))
+            .unwrap_err(),
+        view.open_buffered("file").unwrap_err(),
+        view.open_atomic("file").unwrap_err(),
+    ] {
+        assert!(ViewError::from(err).is_denied());
+    }
+    assert!(!temp_dir.exists("new"));
+    assert_eq!(view.read("file").unwrap(), b"contents");
+    view.write("file", b"changed").unwrap();
+    assert_eq!(budget.open_handles(), 0);
+}
+
+#[test]
+fn coordinate() {
+    use dir_view::{Coordinator, DirViewBuilder};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    let coordinator = Coordinator::new();
+    let a = DirViewBuilder::new()
+        .coordinate(&coordinator)
+        .build(&*temp_dir);
+    let sub = temp_dir.open_dir("sub").unwrap();
+    let b = DirViewBuilder::new().coordinate(&coordinator).build(&sub);
+
+    // Guards taken through different views of the same file exclude each
+    // other, and the file needn't exist.
+    let guard = a.write_guard("sub/file").unwrap();
+    assert!(guard.is_exclusive());
+    assert!(b.try_write_guard("file").unwrap().is_none());
+    assert!(b.try_read_guard("./file").unwrap().is_none());
+    assert!(b.try_write_guard("other").unwrap().is_some());
+    assert_eq!(coordinator.guarded_files(), 1);
+    drop(guard);
+    assert_eq!(coordinator.guarded_files(), 0);
+
+    // Shared guards only exclude exclusive ones.
+    let read = a.read_guard("sub/file").unwrap();
+    let read_too = b.try_read_guard("file").unwrap().unwrap();
+    assert!(!read_too.is_exclusive());
+    assert!(a.try_write_guard("sub/file").unwrap().is_none());
+    drop(read_too);
+
+    // Blocking guards wait for conflicting ones to be dropped.
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let _guard = b.write_guard("file").unwrap();
+            sender.send(()).unwrap();
+        });
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(read);
+        receiver.recv().unwrap();
+    });
+
+    // Views without a coordinator don't coordinate, and readonly views
+    // can't take exclusive guards.
+    let _guard = a.write_guard("sub/file").unwrap();
+    assert!(DirView::full(&sub)
+        .try_write_guard("file")
+        .unwrap()
+        .is_some());
+    assert!(DirView::readonly(&sub).write_guard("file").is_err());
+    assert!(a.write_guard("missing/file").is_err());
+
+    // Coordinated views don't expose their directories, which could change
+    // files without taking guards.
+    assert!(a.as_dir().is_none());
+    assert!(DirView::full(&sub).as_dir().is_some());
+}
+
+#[test]
+fn is_same_dir() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b").unwrap();
+    let view = DirView::from_dir(temp_dir.open_dir(".").unwrap(), ViewKind::Full);
+    let a = view.open_dir("a").unwrap();
+    let alias = DirView::readonly(temp_dir.open_dir("a/b/..").unwrap());
+    let b = view.open_dir("a/b").unwrap();
+
+    assert!(a.is_same_dir(&alias).unwrap());
+    assert!(alias.is_same_dir(&a).unwrap());
+    assert!(a.is_same_dir(&a).unwrap());
+    assert!(!a.is_same_dir(&b).unwrap());
+    assert!(!view.is_same_dir(&a).unwrap());
+}
+
+#[cfg(any(target_os = "linux", target_vendor = "apple", windows))]
+#[test]
+fn contains() {
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("served/sub").unwrap();
+    temp_dir.create_dir("other").unwrap();
+    temp_dir.write("served/sub/file", b"").unwrap();
+    temp_dir.write("other/file", b"").unwrap();
+    let view = DirView::readonly(temp_dir.open_dir("served").unwrap());
+
+    let inside = temp_dir.open("served/sub/file").unwrap();
+    let outside = temp_dir.open("other/file").unwrap();
+    assert!(view.contains(&inside).unwrap());
+    assert!(!view.contains(&outside).unwrap());
+    assert!(view
+        .contains(&temp_dir.open_dir("served").unwrap())
+        .unwrap());
+    assert!(view
+        .contains(&temp_dir.open_dir("served/sub").unwrap())
+        .unwrap());
+    assert!(!view.contains(&temp_dir.open_dir("other").unwrap()).unwrap());
+    assert!(!view.contains(&temp_dir.open_dir(".").unwrap()).unwrap());
+
+    // Moving a file out of the tree moves it out of the view.
+    temp_dir
+        .rename("served/sub/file", &temp_dir, "other/moved")
+        .unwrap();
+    assert!(!view.contains(&inside).unwrap());
+}
+
+#[cfg(any(target_os = "linux", target_vendor = "apple", windows))]
+#[test]
+fn path_of() {
+    use std::io;
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("served/sub").unwrap();
+    temp_dir.write("served/sub/file", b"").unwrap();
+    temp_dir.write("outside", b"").unwrap();
+    let view = DirView::readonly(temp_dir.open_dir("served").unwrap());
+
+    let file = temp_dir.open("served/sub/file").unwrap();
+    assert_eq!(view.path_of(&file).unwrap(), Path::new("sub/file"));
+    let sub = temp_dir.open_dir("served/sub").unwrap();
+    assert_eq!(view.path_of(&sub).unwrap(), Path::new("sub"));
+    let root = temp_dir.open_dir("served").unwrap();
+    assert_eq!(view.path_of(&root).unwrap(), Path::new("."));
+
+    let outside = temp_dir.open("outside").unwrap();
+    assert_eq!(
+        view.path_of(&outside).unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn spawn_in() {
+    use dir_view::DirViewBuilder;
+    use std::process::{Command, Stdio};
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("project").unwrap();
+    temp_dir.write("project/marker", b"inside").unwrap();
+    let view = DirView::readonly(temp_dir.open_dir("project").unwrap());
+
+    let mut command = Command::new("cat");
+    command.arg("marker").stdout(Stdio::piped());
+    let output = view.spawn_in(command).unwrap().wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"inside");
+
+    let view = DirViewBuilder::new()
+        .readonly()
+        .deny_exec()
+        .build(temp_dir.open_dir("project").unwrap());
+    let error = view.spawn_in(Command::new("cat")).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn verified_dir_view() {
+    use dir_view::{Sha256, TreeManifest, VerifiedDirView};
+    use std::io::{ErrorKind, Read};
+    use std::path::Path;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("sub").unwrap();
+    temp_dir.write("sub/file", b"trusted").unwrap();
+    temp_dir.write("other", b"").unwrap();
+    #[cfg(not(windows))]
+    temp_dir.symlink("sub/file", "link").unwrap();
+
+    let view = DirView::readonly(temp_dir.open_dir(".").unwrap());
+    let manifest = view.hash_tree(".", &Sha256).unwrap();
+    let entries = manifest
+        .entries()
+        .filter(|(path, _)| *path != Path::new("other"))
+        .map(|(path, entry)| (path.to_path_buf(), entry.clone()));
+    let manifest = TreeManifest::from_entries(entries, &Sha256).unwrap();
+
+    // A signature over the manifest is checked before anything is read.
+    let root_digest = manifest.root_digest().to_vec();
+    let err = VerifiedDirView::signed(
+        DirView::readonly(temp_dir.open_dir(".").unwrap()),
+        manifest.clone(),
+        Sha256,
+        |_| false,
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let verified =
+        VerifiedDirView::signed(view, manifest, Sha256, |digest| digest == root_digest).unwrap();
+
+    // Listed entries are readable while they match.
+    assert_eq!(verified.read("sub/file").unwrap(), b"trusted");
+    assert_eq!(
+        verified.read_to_string("sub/../sub/./file").unwrap(),
+        "trusted"
+    );
+    let mut contents = String::new();
+    verified
+        .open("sub/file")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "trusted");
+    assert!(verified.is_dir("sub"));
+    assert!(verified.is_file("sub/file"));
+    assert!(!verified.is_file("sub"));
+    #[cfg(not(windows))]
+    assert_eq!(verified.read_link("link").unwrap(), Path::new("sub/file"));
+
+    // Unlisted entries are invisible.
+    assert!(!verified.exists("other"));
+    assert_eq!(
+        verified.read("other").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    assert!(verified.read("..").is_err());
+
+    // Tampered files fail to open or read.
+    temp_dir.write("sub/file", b"tampered").unwrap();
+    assert_eq!(
+        verified.read("sub/file").unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+    assert_eq!(
+        verified.open("sub/file").unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn cas() {
+    use dir_view::ContentHash;
+    use std::io::Read;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    let view = DirView::full(&*temp_dir);
+    let hash = view.cas_write(b"hello").unwrap();
+    assert_eq!(
+        hash.to_string(),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(ContentHash::from_hex(&hash.to_string()), Some(hash));
+    assert_eq!(ContentHash::of(b"hello"), hash);
+    assert!(temp_dir.is_file("2c/f24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"));
+
+    // Writing the same contents again, even concurrently, is harmless.
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| assert_eq!(view.cas_write(b"hello").unwrap(), hash));
+        }
+    });
+    assert_eq!(temp_dir.read_dir("2c").unwrap().count(), 1);
+
+    let mut contents = Vec::new();
+    view.cas_open(&hash)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(contents, b"hello");
+    assert!(view.cas_contains(&hash));
+    let missing = ContentHash::of(b"missing");
+    assert!(!view.cas_contains(&missing));
+    assert!(view.cas_open(&missing).is_err());
+
+    // Readonly views can read the store, but not write to it.
+    let readonly = DirView::readonly(&*temp_dir);
+    assert!(readonly.cas_contains(&hash));
+    assert_eq!(
+        readonly.cas_write(b"other").unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(!view.cas_contains(&ContentHash::of(b"other")));
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_dir_view() {
+    use dir_view::{Cipher, EncryptedDirView};
+    use std::io;
+
+    /// A toy cipher, which XORs with a key and appends a checksum of the
+    /// plaintext and associated data.
+    struct Xor(u8);
+
+    impl Xor {
+        fn apply(&self, bytes: &[u8]) -> Vec<u8> {
+            bytes.iter().map(|byte| byte ^ self.0).collect()
+        }
+
+        fn checksum(&self, plaintext: &[u8], associated_data: &[u8]) -> u8 {
+            plaintext
+                .iter()
+                .chain(associated_data)
+                .fold(self.0, |sum, byte| sum.rotate_left(1) ^ *byte)
+        }
+    }
+
+    impl Cipher for Xor {
+        fn encrypt(&self, plaintext: &[u8], associated_data: &[u8]) -> io::Result<Vec<u8>> {
+            let mut ciphertext = self.apply(plaintext);
+            ciphertext.push(self.checksum(plaintext, associated_data));
+            Ok(ciphertext)
+        }
+
+        fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> io::Result<Vec<u8>> {
+            let (sum, ciphertext) = ciphertext.split_last().unwrap();
+            let plaintext = self.apply(ciphertext);
+            if self.checksum(&plaintext, associated_data) != *sum {
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            Ok(plaintext)
+        }
+
+        fn encrypt_name(&self, name: &[u8]) -> io::Result<Vec<u8>> {
+            Ok(self.apply(name))
+        }
+
+        fn decrypt_name(&self, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+            Ok(self.apply(ciphertext))
+        }
+    }
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir("contents").unwrap();
+    temp_dir.create_dir("names").unwrap();
+
+    // Contents are encrypted on disk and decrypted when read.
+    let view = EncryptedDirView::new(
+        DirView::full(temp_dir.open_dir("contents").unwrap()),
+        Xor(0x5a),
+    );
+    view.write("secret", b"hello").unwrap();
+    assert_ne!(temp_dir.read("contents/secret").unwrap(), b"hello");
+    assert_eq!(view.read_to_string("secret").unwrap(), "hello");
+    assert_eq!(view.read_to_string("./secret").unwrap(), "hello");
+
+    // Files are bound to their paths.
+    temp_dir
+        .copy("contents/secret", &temp_dir, "contents/moved")
+        .unwrap();
+    assert_eq!(
+        view.read("moved").unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    view.rename("secret", "renamed").unwrap();
+    assert_eq!(view.read("renamed").unwrap(), b"hello");
+    assert!(!view.exists("secret"));
+    view.rename("renamed", "secret").unwrap();
+
+    temp_dir.write("contents/secret", b"tampered").unwrap();
+    assert_eq!(
+        view.read("secret").unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    // Names can be encrypted too.
+    let view = EncryptedDirView::with_encrypted_names(
+        DirView::full(temp_dir.open_dir("names").unwrap()),
+        Xor(0x5a),
+    );
+    view.create_dir_all("dir/sub").unwrap();
+    view.write_atomic("dir/sub/file", b"hello").unwrap();
+    assert!(!temp_dir.exists("names/dir"));
+    assert!(view.is_dir("dir"));
+    assert!(view.is_file("dir/sub/file"));
+    assert_eq!(view.read("dir/sub/file").unwrap(), b"hello");
+    view.rename("dir/sub/file", "dir/renamed").unwrap();
+    assert_eq!(view.read("dir/renamed").unwrap(), b"hello");
+    assert_eq!(
+        view.rename("dir/sub", "dir/other").unwrap_err().kind(),
+        io::ErrorKind::Unsupported
+    );
+    let mut names = view
+        .read_dir("dir")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["renamed", "sub"]);
+    view.remove_dir_all("dir").unwrap();
+    assert_eq!(view.entries().unwrap().count(), 0);
+
+    // The underlying view's restrictions apply.
+    let readonly = EncryptedDirView::new(
+        DirView::readonly(temp_dir.open_dir("contents").unwrap()),
+        Xor(0x5a),
+    );
+    assert_eq!(
+        readonly.write("secret", b"").unwrap_err().kind(),
+        io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn walk_parallel() {
+    use dir_view::WalkState;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("a/b/c").unwrap();
+    temp_dir.create_dir_all("skip/me").unwrap();
+    temp_dir.create_dir_all("x/y").unwrap();
+    temp_dir.write("a/b/file", b"").unwrap();
+    temp_dir.write("x/y/file", b"").unwrap();
+
+    let view = DirView::readonly(&*temp_dir);
+    let paths = Mutex::new(Vec::new());
+    view.walk_parallel().unwrap().threads(4).run(|entry| {
+        let entry = entry.unwrap();
+        let state = if entry.file_name() == "skip" {
+            WalkState::Skip
+        } else {
+            WalkState::Continue
+        };
+        paths.lock().unwrap().push(entry.into_path());
+        state
+    });
+    let mut paths = paths.into_inner().unwrap();
+    paths.sort();
+    let expected = [
+        "a", "a/b", "a/b/c", "a/b/file", "skip", "x", "x/y", "x/y/file",
+    ];
+    assert_eq!(paths, expected.map(PathBuf::from));
+
+    let depths = Mutex::new(Vec::new());
+    view.walk_parallel().unwrap().max_depth(2).run(|entry| {
+        depths.lock().unwrap().push(entry.unwrap().depth());
+        WalkState::Continue
+    });
+    assert_eq!(depths.into_inner().unwrap().len(), 6);
+
+    let visited = Mutex::new(0);
+    view.walk_parallel().unwrap().threads(1).run(|_| {
+        *visited.lock().unwrap() += 1;
+        WalkState::Quit
+    });
+    assert_eq!(visited.into_inner().unwrap(), 1);
+}
+
+#[cfg(feature = "ignore")]
+#[test]
+fn walk_ignore_files() {
+    use std::path::PathBuf;
+
+    let temp_dir = TempDir::new(ambient_authority()).unwrap();
+    temp_dir.create_dir_all("target/debug").unwrap();
+    temp_dir.create_dir_all("src/gen").unwrap();
+    temp_dir
+        .write(".gitignore", "/target/\n*.log\n!keep.log\n")
+        .unwrap();
+    temp_dir.write("src/.ignore", "gen\n").unwrap();
+    for file in [
+        "a.log",
+        "keep.log",
+        "src/lib.rs",
+        "src/b.log",
+        "src/gen/x.rs",
+    ] {
+        temp_dir.write(file, b"").unwrap();
+    }
+
+    let view = DirView::readonly(&*temp_dir);
+    let paths = view
+        .walk()
+        .unwrap()
+        .respect_ignore_files(true)
+        .sort_by_file_name()
+        .map(|entry| entry.unwrap().into_path())
+        .collect::<Vec<_>>();
+    let expected = [".gitignore", "keep.log", "src", "src/.ignore", "src/lib.rs"];
+    assert_eq!(paths, expected.map(PathBuf::from));
 }